@@ -101,6 +101,10 @@ impl<'a> Writer<'a> {
     pub fn mut_used_slice(&mut self) -> &mut [u8] {
         &mut self.buf[..self.offs]
     }
+
+    pub fn clear(&mut self) {
+        self.offs = 0;
+    }
 }
 
 /// Things we can encode and read from a Reader.
@@ -84,11 +84,11 @@ pub fn req_create_info() -> (common::SpdmConfigInfo, common::SpdmProvisionInfo)
         .copy_from_slice(leaf_cert.as_ref());
 
     let provision_info = common::SpdmProvisionInfo {
-        my_cert_chain_data: None,
-        my_cert_chain: None,
+        my_cert_chain_data: [None, None, None, None, None, None, None, None],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
         peer_cert_chain_data: Some(peer_cert_chain_data),
         peer_cert_chain_root_hash: None,
-        default_version: SpdmVersion::SpdmVersion12,
+        ..Default::default()
     };
 
     (config_info, provision_info)
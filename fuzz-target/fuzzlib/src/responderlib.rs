@@ -88,11 +88,20 @@ pub fn rsp_create_info() -> (common::SpdmConfigInfo, common::SpdmProvisionInfo)
         .copy_from_slice(leaf_cert.as_ref());
 
     let provision_info = common::SpdmProvisionInfo {
-        my_cert_chain_data: Some(my_cert_chain_data),
-        my_cert_chain: None,
+        my_cert_chain_data: [
+            Some(my_cert_chain_data),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
         peer_cert_chain_data: None,
         peer_cert_chain_root_hash: None,
-        default_version: SpdmVersion::SpdmVersion12,
+        ..Default::default()
     };
 
     (config_info, provision_info)
@@ -123,6 +123,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest1.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -135,6 +136,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest2.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -147,6 +149,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest3.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -159,6 +162,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest4.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -171,6 +175,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest5.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -183,6 +188,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest6.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -195,6 +201,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest7.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -207,6 +214,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest8.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -219,6 +227,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest9.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -231,6 +240,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest10.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -261,6 +271,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
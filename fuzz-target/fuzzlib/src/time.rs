@@ -8,4 +8,10 @@ pub static SPDM_TIME_IMPL: SpdmTime = SpdmTime {
         use std::{thread, time::Duration};
         thread::sleep(Duration::from_millis(time as u64));
     },
+    now_cb: || {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_micros() as u64)
+    },
 };
@@ -58,11 +58,14 @@ pub fn fuzz_total_requesters() {
     if let Ok(session_id) = result {
         log::info!(
             "\nSession established ... session_id is {:0x?}\n",
-            session_id
+            session_id.as_u32()
         );
         log::info!("Key Information ...\n");
 
-        let session = requester.common.get_session_via_id(session_id).unwrap();
+        let session = requester
+            .common
+            .get_session_via_id(session_id.as_u32())
+            .unwrap();
         let (request_direction, response_direction) = session.export_keys();
         log::info!(
             "equest_direction.encryption_key {:0x?}\n",
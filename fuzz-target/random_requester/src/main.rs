@@ -82,17 +82,12 @@ fn run_spdm(spdm: Vec<i32>) {
                 }
             }
             7 => {
-                let mut total_number = 0;
-                let mut spdm_measurement_record_structure =
-                    SpdmMeasurementRecordStructure::default();
                 if requester
                     .send_receive_spdm_measurement(
                         None,
                         0,
                         SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
                         SpdmMeasurementOperation::SpdmMeasurementRequestAll,
-                        &mut total_number,
-                        &mut spdm_measurement_record_structure,
                     )
                     .is_err()
                 {
@@ -36,7 +36,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
         let mut device_io_requester =
@@ -53,6 +53,8 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         requester.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
 
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
         let _ = requester.send_receive_spdm_certificate(None, 0).is_err();
     }
     {
@@ -74,7 +76,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
         let mut device_io_requester =
@@ -91,6 +93,8 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         requester.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
 
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
         let _ = requester.send_receive_spdm_certificate(None, 0).is_err();
     }
     {
@@ -111,7 +115,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
         let mut device_io_requester =
@@ -135,6 +139,8 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         tmp.data_size += 1;
         requester.common.provision_info.peer_cert_chain_data = Some(tmp);
 
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
         let _ = requester.send_receive_spdm_certificate(None, 0).is_err();
     }
     {
@@ -156,7 +162,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         // digest_rsp
 
@@ -180,6 +186,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         requester.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        requester.common.peer_info.peer_slot_mask = 0x1;
         let _ = requester.send_receive_spdm_certificate(None, 0).is_err();
     }
     {
@@ -199,7 +206,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
         let mut device_io_requester =
@@ -214,6 +221,7 @@ fn fuzz_send_receive_spdm_certificate(fuzzdata: &[u8]) {
         requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         requester.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        requester.common.peer_info.peer_slot_mask = 0x1;
         let _ = requester.send_receive_spdm_certificate(None, 0).is_err();
     }
 }
@@ -25,7 +25,7 @@ fn fuzz_send_receive_spdm_challenge(fuzzdata: &[u8]) {
     );
 
     responder.common.reset_runtime_info();
-    responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+    responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
         data_size: 512u16,
         data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
     });
@@ -47,6 +47,8 @@ fn fuzz_send_receive_spdm_challenge(fuzzdata: &[u8]) {
 
     requester.common.reset_runtime_info();
 
+    requester.common.peer_info.peer_slot_mask = 0x1;
+
     requester
         .common
         .negotiate_info
@@ -24,7 +24,7 @@ fn fuzz_send_receive_spdm_digest(fuzzdata: &[u8]) {
         rsp_config_info,
         rsp_provision_info,
     );
-    responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+    responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
         data_size: 512u16,
         data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
     });
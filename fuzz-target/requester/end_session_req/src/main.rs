@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use fuzzlib::{
-    spdmlib::common::session::{SpdmSession, SpdmSessionState},
+    spdmlib::common::session::{SpdmSession, SpdmSessionId, SpdmSessionState},
     *,
 };
 use spdmlib::protocol::*;
@@ -64,7 +64,7 @@ fn fuzz_send_receive_spdm_end_session(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester.send_receive_spdm_end_session(4294901758);
+        let _ = requester.send_receive_spdm_end_session(SpdmSessionId::from(4294901758u32));
     }
 
     {
@@ -116,7 +116,7 @@ fn fuzz_send_receive_spdm_end_session(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester.send_receive_spdm_end_session(4294901758);
+        let _ = requester.send_receive_spdm_end_session(SpdmSessionId::from(4294901758u32));
     }
 }
 
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use fuzzlib::{
-    spdmlib::common::session::{SpdmSession, SpdmSessionState},
+    spdmlib::common::session::{SpdmSession, SpdmSessionId, SpdmSessionState},
     *,
 };
 use spdmlib::protocol::*;
@@ -47,7 +47,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         responder.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048;
         responder.common.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
 
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         responder.common.reset_runtime_info();
 
@@ -103,7 +103,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester.send_receive_spdm_finish(0, 4294901758);
+        let _ = requester.send_receive_spdm_finish(0, SpdmSessionId::from(4294901758u32));
     }
 
     {
@@ -143,7 +143,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         responder.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048;
         responder.common.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
 
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         responder.common.reset_runtime_info();
 
@@ -197,7 +197,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester.send_receive_spdm_finish(0, 4294901758);
+        let _ = requester.send_receive_spdm_finish(0, SpdmSessionId::from(4294901758u32));
     }
     {
         let shared_buffer = SharedBuffer::new();
@@ -229,7 +229,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         responder.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048;
         responder.common.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
 
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         responder.common.reset_runtime_info();
 
@@ -283,7 +283,7 @@ fn fuzz_send_receive_spdm_finish(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester.send_receive_spdm_finish(0, 4294901758);
+        let _ = requester.send_receive_spdm_finish(0, SpdmSessionId::from(4294901758u32));
     }
 }
 
@@ -6,7 +6,7 @@ use spdmlib::protocol::*;
 // use spdmlib::protocol::*;
 
 use fuzzlib::{
-    spdmlib::common::session::{SpdmSession, SpdmSessionState},
+    spdmlib::common::session::{SpdmSession, SpdmSessionId, SpdmSessionState},
     *,
 };
 
@@ -67,7 +67,7 @@ fn fuzz_send_receive_spdm_heartbeat(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionEstablished);
 
-        let _ = requester.send_receive_spdm_heartbeat(4294901758);
+        let _ = requester.send_receive_spdm_heartbeat(SpdmSessionId::from(4294901758u32));
     }
 
     {
@@ -120,7 +120,7 @@ fn fuzz_send_receive_spdm_heartbeat(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionEstablished);
 
-        let _ = requester.send_receive_spdm_heartbeat(4294901758);
+        let _ = requester.send_receive_spdm_heartbeat(SpdmSessionId::from(4294901758u32));
     }
 }
 
@@ -28,7 +28,7 @@ fn fuzz_send_receive_spdm_key_exchange(fuzzdata: &[u8]) {
             rsp_provision_info,
         );
 
-        responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -67,6 +67,8 @@ fn fuzz_send_receive_spdm_key_exchange(fuzzdata: &[u8]) {
 
         requester.common.reset_runtime_info();
 
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
         requester
             .common
             .runtime_info
@@ -97,7 +99,7 @@ fn fuzz_send_receive_spdm_key_exchange(fuzzdata: &[u8]) {
             rsp_provision_info1,
         );
 
-        responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -136,6 +138,8 @@ fn fuzz_send_receive_spdm_key_exchange(fuzzdata: &[u8]) {
 
         requester.common.reset_runtime_info();
 
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
         requester
             .common
             .runtime_info
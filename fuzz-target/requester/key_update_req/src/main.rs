@@ -4,7 +4,7 @@
 
 use crate::spdmlib::message::*;
 use fuzzlib::{
-    spdmlib::common::session::{SpdmSession, SpdmSessionState},
+    spdmlib::common::session::{SpdmSession, SpdmSessionId, SpdmSessionState},
     *,
 };
 use spdmlib::protocol::*;
@@ -62,8 +62,10 @@ fn fuzz_send_receive_spdm_key_update(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester
-            .send_receive_spdm_key_update(4294901758, SpdmKeyUpdateOperation::SpdmUpdateAllKeys);
+        let _ = requester.send_receive_spdm_key_update(
+            SpdmSessionId::from(4294901758u32),
+            SpdmKeyUpdateOperation::SpdmUpdateAllKeys,
+        );
     }
 
     {
@@ -111,8 +113,10 @@ fn fuzz_send_receive_spdm_key_update(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester
-            .send_receive_spdm_key_update(4294901758, SpdmKeyUpdateOperation::SpdmUpdateAllKeys);
+        let _ = requester.send_receive_spdm_key_update(
+            SpdmSessionId::from(4294901758u32),
+            SpdmKeyUpdateOperation::SpdmUpdateAllKeys,
+        );
     }
     {
         let shared_buffer = SharedBuffer::new();
@@ -159,8 +163,10 @@ fn fuzz_send_receive_spdm_key_update(fuzzdata: &[u8]) {
         );
         requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
-        let _ = requester
-            .send_receive_spdm_key_update(4294901758, SpdmKeyUpdateOperation::SpdmVerifyNewKey);
+        let _ = requester.send_receive_spdm_key_update(
+            SpdmSessionId::from(4294901758u32),
+            SpdmKeyUpdateOperation::SpdmVerifyNewKey,
+        );
     }
 }
 
@@ -54,6 +54,7 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
             .runtime_info
             .message_m
             .append_message(message_m);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.reset_runtime_info();
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
@@ -84,15 +85,11 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
             SpdmMeasurementHashAlgo::TPM_ALG_SHA_384;
         // requester.common.peer_info.peer_cert_chain.cert_chain = [Some(REQ_CERT_CHAIN_DATA);8].clone();
         requester.common.reset_runtime_info();
-        let mut total_number = 0;
-        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
         let _ = requester.send_receive_spdm_measurement(
             None,
             0,
             SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
             SpdmMeasurementOperation::SpdmMeasurementRequestAll,
-            &mut total_number,
-            &mut spdm_measurement_record_structure,
         );
     }
     {
@@ -133,6 +130,7 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
             .runtime_info
             .message_m
             .append_message(message_m);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.reset_runtime_info();
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
@@ -164,15 +162,11 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
         // requester.common.peer_info.peer_cert_chain.cert_chain = REQ_CERT_CHAIN_DATA;
         requester.common.reset_runtime_info();
 
-        let mut total_number = 0;
-        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
         let _ = requester.send_receive_spdm_measurement(
             None,
             0,
             SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
             SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
-            &mut total_number,
-            &mut spdm_measurement_record_structure,
         );
     }
 
@@ -212,6 +206,7 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
             .runtime_info
             .message_m
             .append_message(message_m);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.reset_runtime_info();
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
@@ -243,15 +238,11 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
         requester.common.peer_info.peer_cert_chain[0] = Some(SpdmCertChain::default());
         requester.common.reset_runtime_info();
 
-        let mut total_number = 0;
-        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
         let _ = requester.send_receive_spdm_measurement(
             None,
             0,
             SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
             SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
-            &mut total_number,
-            &mut spdm_measurement_record_structure,
         );
     }
     {
@@ -290,6 +281,7 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
             .runtime_info
             .message_m
             .append_message(message_m);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.reset_runtime_info();
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
@@ -321,15 +313,11 @@ fn fuzz_send_receive_spdm_measurement(fuzzdata: &[u8]) {
         // requester.common.peer_info.peer_cert_chain.cert_chain = REQ_CERT_CHAIN_DATA;
         requester.common.reset_runtime_info();
 
-        let mut total_number = 0;
-        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
         let _ = requester.send_receive_spdm_measurement(
             None,
             0,
             SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
             SpdmMeasurementOperation::Unknown(4),
-            &mut total_number,
-            &mut spdm_measurement_record_structure,
         );
     }
 }
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use fuzzlib::{
-    spdmlib::common::session::{SpdmSession, SpdmSessionState},
+    spdmlib::common::session::{SpdmSession, SpdmSessionId, SpdmSessionState},
     *,
 };
 use spdmlib::protocol::*;
@@ -67,7 +67,7 @@ fn fuzz_send_receive_spdm_psk_finish(fuzzdata: &[u8]) {
         SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
     );
     requester.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
-    let _ = requester.send_receive_spdm_psk_finish(4294901758);
+    let _ = requester.send_receive_spdm_psk_finish(SpdmSessionId::from(4294901758u32));
 }
 
 fn main() {
@@ -26,7 +26,7 @@ fn fuzz_handle_spdm_certificate(data: &[u8]) {
         provision_info,
     );
 
-    context.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+    context.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
     context.handle_spdm_certificate(data, None);
 }
 fn main() {
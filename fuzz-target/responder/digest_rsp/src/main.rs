@@ -26,7 +26,7 @@ fn fuzz_handle_spdm_digest(data: &[u8]) {
         provision_info,
     );
 
-    context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+    context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
         data_size: 512u16,
         data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
     });
@@ -83,7 +83,7 @@ fn fuzz_handle_spdm_finish(data: &[u8]) {
             SpdmAeadAlgo::AES_256_GCM,
             SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
         );
-        context.common.provision_info.my_cert_chain_data = None;
+        context.common.provision_info.my_cert_chain_data[0] = None;
         context.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
 
         context.handle_spdm_finish(4294901758, data);
@@ -63,7 +63,7 @@ fn fuzz_handle_spdm_key_exchange(data: &[u8]) {
         context.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048;
         context.common.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
 
-        context.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        context.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         context.common.reset_runtime_info();
 
@@ -97,7 +97,7 @@ fn fuzz_handle_spdm_key_exchange(data: &[u8]) {
         context.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048;
         context.common.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
 
-        context.common.provision_info.my_cert_chain_data = None;
+        context.common.provision_info.my_cert_chain_data[0] = None;
         context.common.reset_runtime_info();
 
         let _ = context.handle_spdm_key_exchange(data);
@@ -0,0 +1,22 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! PCIe IDE Key Management (IDE_KM) over SPDM secured sessions.
+//!
+//! IDE_KM objects travel in VENDOR_DEFINED_REQUEST / VENDOR_DEFINED_RESPONSE
+//! with the PCI-SIG standard ID, inside the session the stream keys are
+//! programmed under.
+
+#![forbid(unsafe_code)]
+#![no_std]
+
+#[macro_use]
+extern crate log;
+
+mod message;
+pub use message::*;
+mod requester;
+pub use requester::*;
+mod responder;
+pub use responder::*;
@@ -0,0 +1,365 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::enum_builder;
+use codec::{Codec, Reader, Writer};
+use spdmlib::config;
+use spdmlib::message::VendorIDStruct;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub const IDEKM_PROTOCOL_ID: u8 = 0x00;
+pub const IDEKM_PCI_SIG_VENDOR_ID: u16 = 0x0001;
+
+pub const IDEKM_KEY_SIZE: usize = 32;
+pub const IDEKM_IFV_SIZE: usize = 8;
+pub const MAX_IDEKM_IDE_REG_COUNT: usize = 64;
+
+pub const IDEKM_SUB_STREAM_PR: u8 = 0x0;
+pub const IDEKM_SUB_STREAM_NPR: u8 = 0x1;
+pub const IDEKM_SUB_STREAM_CPL: u8 = 0x2;
+
+/// Vendor ID of the VENDOR_DEFINED messages carrying IDE_KM objects.
+pub fn idekm_vendor_id() -> VendorIDStruct {
+    let mut vendor_id = VendorIDStruct {
+        len: 2,
+        vendor_id: [0u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN],
+    };
+    vendor_id.vendor_id[..2].copy_from_slice(&IDEKM_PCI_SIG_VENDOR_ID.to_le_bytes());
+    vendor_id
+}
+
+enum_builder! {
+    @U8
+    EnumName: IdekmObjectId;
+    EnumVal{
+        IdekmQuery => 0x00,
+        IdekmQueryResp => 0x01,
+        IdekmKeyProg => 0x02,
+        IdekmKpAck => 0x03,
+        IdekmKSetGo => 0x04,
+        IdekmKSetStop => 0x05,
+        IdekmKGostopAck => 0x06
+    }
+}
+
+enum_builder! {
+    @U8
+    EnumName: IdekmKpAckStatus;
+    EnumVal{
+        IdekmKpAckStatusSuccess => 0x00,
+        IdekmKpAckStatusIncorrectLength => 0x01,
+        IdekmKpAckStatusUnsupportedPortIndex => 0x02,
+        IdekmKpAckStatusUnsupportedValue => 0x03,
+        IdekmKpAckStatusUnspecifiedFailure => 0x04
+    }
+}
+
+/// Protocol ID and Object ID leading every IDE_KM object.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IdekmMessageHeader {
+    pub object_id: IdekmObjectId,
+}
+
+impl Codec for IdekmMessageHeader {
+    fn encode(&self, bytes: &mut Writer) {
+        IDEKM_PROTOCOL_ID.encode(bytes);
+        self.object_id.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmMessageHeader> {
+        if u8::read(r)? != IDEKM_PROTOCOL_ID {
+            return None;
+        }
+        let object_id = IdekmObjectId::read(r)?;
+        Some(IdekmMessageHeader { object_id })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IdekmQueryPayload {
+    pub port_index: u8,
+}
+
+impl Codec for IdekmQueryPayload {
+    fn encode(&self, bytes: &mut Writer) {
+        0u8.encode(bytes); // reserved
+        self.port_index.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmQueryPayload> {
+        u8::read(r)?; // reserved
+        let port_index = u8::read(r)?;
+        Some(IdekmQueryPayload { port_index })
+    }
+}
+
+/// The IDE register block holds the IDE Capability and Control registers,
+/// then the Link and Selective IDE Stream registers, as in the IDE
+/// Extended Capability of the port.
+#[derive(Debug, Copy, Clone)]
+pub struct IdekmQueryRespPayload {
+    pub port_index: u8,
+    pub dev_func_num: u8,
+    pub bus_num: u8,
+    pub segment: u8,
+    pub max_port_index: u8,
+    pub ide_reg_count: usize,
+    pub ide_reg: [u32; MAX_IDEKM_IDE_REG_COUNT],
+}
+
+impl Default for IdekmQueryRespPayload {
+    fn default() -> Self {
+        IdekmQueryRespPayload {
+            port_index: 0,
+            dev_func_num: 0,
+            bus_num: 0,
+            segment: 0,
+            max_port_index: 0,
+            ide_reg_count: 0,
+            ide_reg: [0u32; MAX_IDEKM_IDE_REG_COUNT],
+        }
+    }
+}
+
+impl Codec for IdekmQueryRespPayload {
+    fn encode(&self, bytes: &mut Writer) {
+        0u8.encode(bytes); // reserved
+        self.port_index.encode(bytes);
+        self.dev_func_num.encode(bytes);
+        self.bus_num.encode(bytes);
+        self.segment.encode(bytes);
+        self.max_port_index.encode(bytes);
+        for ide_reg in self.ide_reg.iter().take(self.ide_reg_count) {
+            ide_reg.encode(bytes);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmQueryRespPayload> {
+        u8::read(r)?; // reserved
+        let port_index = u8::read(r)?;
+        let dev_func_num = u8::read(r)?;
+        let bus_num = u8::read(r)?;
+        let segment = u8::read(r)?;
+        let max_port_index = u8::read(r)?;
+        // the register block runs to the end of the object
+        let ide_reg_count = r.left() / 4;
+        if ide_reg_count > MAX_IDEKM_IDE_REG_COUNT {
+            return None;
+        }
+        let mut ide_reg = [0u32; MAX_IDEKM_IDE_REG_COUNT];
+        for ide_reg in ide_reg.iter_mut().take(ide_reg_count) {
+            *ide_reg = u32::read(r)?;
+        }
+        Some(IdekmQueryRespPayload {
+            port_index,
+            dev_func_num,
+            bus_num,
+            segment,
+            max_port_index,
+            ide_reg_count,
+            ide_reg,
+        })
+    }
+}
+
+/// Stream, key set, direction and sub-stream a key object applies to.
+/// This is the whole payload of K_SET_GO, K_SET_STOP and K_GOSTOP_ACK.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Zeroize)]
+pub struct IdekmKeyId {
+    pub stream_id: u8,
+    pub key_set: u8,    // 0 or 1
+    pub tx: bool,       // false: receive key, true: transmit key
+    pub sub_stream: u8, // IDEKM_SUB_STREAM_*
+    pub port_index: u8,
+}
+
+impl IdekmKeyId {
+    // The byte after Stream ID is reserved, except in KP_ACK where it holds
+    // the status.
+    fn encode_with(&self, after_stream_id: u8, bytes: &mut Writer) {
+        0u16.encode(bytes); // reserved
+        self.stream_id.encode(bytes);
+        after_stream_id.encode(bytes);
+        let key_sub_stream =
+            (self.key_set & 0x1) | ((self.tx as u8) << 1) | ((self.sub_stream & 0xF) << 4);
+        key_sub_stream.encode(bytes);
+        self.port_index.encode(bytes);
+    }
+
+    fn read_with(r: &mut Reader) -> Option<(IdekmKeyId, u8)> {
+        u16::read(r)?; // reserved
+        let stream_id = u8::read(r)?;
+        let after_stream_id = u8::read(r)?;
+        let key_sub_stream = u8::read(r)?;
+        let port_index = u8::read(r)?;
+        let key_id = IdekmKeyId {
+            stream_id,
+            key_set: key_sub_stream & 0x1,
+            tx: key_sub_stream & 0x2 != 0,
+            sub_stream: key_sub_stream >> 4,
+            port_index,
+        };
+        Some((key_id, after_stream_id))
+    }
+}
+
+impl Codec for IdekmKeyId {
+    fn encode(&self, bytes: &mut Writer) {
+        self.encode_with(0, bytes); // reserved
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmKeyId> {
+        let (key_id, _) = IdekmKeyId::read_with(r)?; // reserved
+        Some(key_id)
+    }
+}
+
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct IdekmKeyProgPayload {
+    pub key_id: IdekmKeyId,
+    pub key: [u8; IDEKM_KEY_SIZE],
+    pub ifv: [u8; IDEKM_IFV_SIZE], // initial IV
+}
+
+// the key never goes to the logs
+impl core::fmt::Debug for IdekmKeyProgPayload {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IdekmKeyProgPayload")
+            .field("key_id", &self.key_id)
+            .field("key", &"<redacted>")
+            .field("ifv", &self.ifv)
+            .finish()
+    }
+}
+
+impl Codec for IdekmKeyProgPayload {
+    fn encode(&self, bytes: &mut Writer) {
+        self.key_id.encode(bytes);
+        bytes.extend_from_slice(&self.key);
+        bytes.extend_from_slice(&self.ifv);
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmKeyProgPayload> {
+        let key_id = IdekmKeyId::read(r)?;
+        let mut key = [0u8; IDEKM_KEY_SIZE];
+        key.copy_from_slice(r.take(IDEKM_KEY_SIZE)?);
+        let mut ifv = [0u8; IDEKM_IFV_SIZE];
+        ifv.copy_from_slice(r.take(IDEKM_IFV_SIZE)?);
+        Some(IdekmKeyProgPayload { key_id, key, ifv })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IdekmKpAckPayload {
+    pub key_id: IdekmKeyId,
+    pub status: IdekmKpAckStatus,
+}
+
+impl Codec for IdekmKpAckPayload {
+    fn encode(&self, bytes: &mut Writer) {
+        self.key_id.encode_with(self.status.get_u8(), bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<IdekmKpAckPayload> {
+        let (key_id, status) = IdekmKeyId::read_with(r)?;
+        let status = IdekmKpAckStatus::read_bytes(&[status])?;
+        Some(IdekmKpAckPayload { key_id, status })
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    extern crate alloc;
+
+    #[test]
+    fn test_case0_idekm_key_prog_payload() {
+        let value = IdekmKeyProgPayload {
+            key_id: IdekmKeyId {
+                stream_id: 3,
+                key_set: 1,
+                tx: true,
+                sub_stream: IDEKM_SUB_STREAM_CPL,
+                port_index: 2,
+            },
+            key: [0x5au8; IDEKM_KEY_SIZE],
+            ifv: [0xa5u8; IDEKM_IFV_SIZE],
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.encode(&mut writer);
+        assert_eq!(writer.used(), 6 + IDEKM_KEY_SIZE + IDEKM_IFV_SIZE);
+        assert_eq!(&u8_slice[..6], &[0, 0, 3, 0, 0x23, 2]);
+
+        let mut reader = Reader::init(u8_slice);
+        let key_prog = IdekmKeyProgPayload::read(&mut reader).unwrap();
+        assert_eq!(key_prog.key_id, value.key_id);
+        assert_eq!(key_prog.key, value.key);
+        assert_eq!(key_prog.ifv, value.ifv);
+    }
+
+    #[test]
+    fn test_case1_idekm_query_resp_payload() {
+        let mut value = IdekmQueryRespPayload {
+            port_index: 1,
+            bus_num: 0x3a,
+            max_port_index: 4,
+            ide_reg_count: 3,
+            ..Default::default()
+        };
+        value.ide_reg[..3].copy_from_slice(&[0x1111, 0x2222, 0x3333]);
+        let u8_slice = &mut [0u8; 32];
+        let mut writer = Writer::init(u8_slice);
+        value.encode(&mut writer);
+        let used = writer.used();
+        assert_eq!(used, 18);
+
+        let mut reader = Reader::init(&u8_slice[..used]);
+        let query_resp = IdekmQueryRespPayload::read(&mut reader).unwrap();
+        assert_eq!(query_resp.port_index, 1);
+        assert_eq!(query_resp.bus_num, 0x3a);
+        assert_eq!(query_resp.max_port_index, 4);
+        assert_eq!(query_resp.ide_reg_count, 3);
+        assert_eq!(&query_resp.ide_reg[..3], &[0x1111, 0x2222, 0x3333]);
+
+        let mut reader = Reader::init(&[0x01u8, 0x01]);
+        assert!(IdekmMessageHeader::read(&mut reader).is_none());
+    }
+
+    #[test]
+    fn test_case2_idekm_kp_ack_payload() {
+        let value = IdekmKpAckPayload {
+            key_id: IdekmKeyId {
+                stream_id: 3,
+                key_set: 1,
+                tx: false,
+                sub_stream: IDEKM_SUB_STREAM_NPR,
+                port_index: 2,
+            },
+            status: IdekmKpAckStatus::IdekmKpAckStatusUnsupportedValue,
+        };
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        value.encode(&mut writer);
+        assert_eq!(writer.used(), 6);
+        // the status sits between Stream ID and the key set byte
+        assert_eq!(&u8_slice[..6], &[0, 0, 3, 0x03, 0x11, 2]);
+
+        let mut reader = Reader::init(&u8_slice[..6]);
+        let kp_ack = IdekmKpAckPayload::read(&mut reader).unwrap();
+        assert_eq!(kp_ack.key_id, value.key_id);
+        assert_eq!(kp_ack.status, value.status);
+    }
+
+    #[test]
+    fn test_case3_idekm_key_prog_payload_debug() {
+        let value = IdekmKeyProgPayload {
+            key: [0x5au8; IDEKM_KEY_SIZE],
+            ..Default::default()
+        };
+        let debug = alloc::format!("{:?}", value);
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("90"));
+    }
+}
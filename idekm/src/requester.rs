@@ -0,0 +1,153 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::{Codec, Reader, Writer};
+use core::convert::TryInto;
+use spdmlib::common::session::SpdmSessionId;
+use spdmlib::config;
+use spdmlib::error::SpdmResult;
+use spdmlib::message::{
+    RegistryOrStandardsBodyID, VendorDefinedReqPayloadStruct, VendorDefinedRspPayloadStruct,
+};
+use spdmlib::requester::RequesterContext;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::message::*;
+
+pub fn encode_idekm_request<T: Codec>(
+    object_id: IdekmObjectId,
+    payload: &T,
+) -> VendorDefinedReqPayloadStruct {
+    let mut vendor_defined_req_payload = [0u8; config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE];
+    let mut writer = Writer::init(&mut vendor_defined_req_payload);
+    IdekmMessageHeader { object_id }.encode(&mut writer);
+    payload.encode(&mut writer);
+    let req_length: u16 = writer.used().try_into().unwrap();
+    VendorDefinedReqPayloadStruct {
+        req_length,
+        vendor_defined_req_payload,
+    }
+}
+
+/// Read the IDE_KM object of a VENDOR_DEFINED_RESPONSE, which must be the
+/// object_id answering the request.
+pub fn handle_idekm_response<T: Codec>(
+    object_id: IdekmObjectId,
+    vendor_defined_rsp_payload_struct: &VendorDefinedRspPayloadStruct,
+) -> SpdmResult<T> {
+    let rsp_length = vendor_defined_rsp_payload_struct.rsp_length as usize;
+    if rsp_length > config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE {
+        return spdm_result_err!(EFAULT);
+    }
+    let mut reader =
+        Reader::init(&vendor_defined_rsp_payload_struct.vendor_defined_rsp_payload[..rsp_length]);
+    let header = IdekmMessageHeader::read(&mut reader).ok_or_else(|| spdm_err!(EFAULT))?;
+    if header.object_id != object_id {
+        error!("!!! idekm rsp : object id mismatch !!!\n");
+        return spdm_result_err!(EFAULT);
+    }
+    T::read(&mut reader).ok_or_else(|| spdm_err!(EFAULT))
+}
+
+fn send_receive_idekm<T: Codec, R: Codec>(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    object_id: IdekmObjectId,
+    payload: &T,
+    response_object_id: IdekmObjectId,
+) -> SpdmResult<R> {
+    let vendor_defined_rsp_payload_struct = spdm_requester.send_spdm_vendor_defined_request(
+        session_id,
+        RegistryOrStandardsBodyID::PCISIG,
+        idekm_vendor_id(),
+        encode_idekm_request(object_id, payload),
+    )?;
+    handle_idekm_response(response_object_id, &vendor_defined_rsp_payload_struct)
+}
+
+/// QUERY the IDE capability and registers of port_index.
+pub fn idekm_query(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    port_index: u8,
+) -> SpdmResult<IdekmQueryRespPayload> {
+    let query_resp: IdekmQueryRespPayload = send_receive_idekm(
+        spdm_requester,
+        session_id,
+        IdekmObjectId::IdekmQuery,
+        &IdekmQueryPayload { port_index },
+        IdekmObjectId::IdekmQueryResp,
+    )?;
+    if query_resp.port_index != port_index {
+        return spdm_result_err!(EFAULT);
+    }
+    Ok(query_resp)
+}
+
+/// KEY_PROG one key of a stream, the returned KP_ACK status tells if the
+/// device took it.
+pub fn idekm_key_prog(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    key_prog: &IdekmKeyProgPayload,
+) -> SpdmResult<IdekmKpAckStatus> {
+    let kp_ack: IdekmKpAckPayload = send_receive_idekm(
+        spdm_requester,
+        session_id,
+        IdekmObjectId::IdekmKeyProg,
+        key_prog,
+        IdekmObjectId::IdekmKpAck,
+    )?;
+    if kp_ack.key_id != key_prog.key_id {
+        return spdm_result_err!(EFAULT);
+    }
+    Ok(kp_ack.status)
+}
+
+/// K_SET_GO: start using a programmed key set.
+pub fn idekm_key_set_go(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    key_id: &IdekmKeyId,
+) -> SpdmResult {
+    idekm_key_set_go_stop(
+        spdm_requester,
+        session_id,
+        IdekmObjectId::IdekmKSetGo,
+        key_id,
+    )
+}
+
+/// K_SET_STOP: stop using a key set and discard its key.
+pub fn idekm_key_set_stop(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    key_id: &IdekmKeyId,
+) -> SpdmResult {
+    idekm_key_set_go_stop(
+        spdm_requester,
+        session_id,
+        IdekmObjectId::IdekmKSetStop,
+        key_id,
+    )
+}
+
+fn idekm_key_set_go_stop(
+    spdm_requester: &mut RequesterContext,
+    session_id: SpdmSessionId,
+    object_id: IdekmObjectId,
+    key_id: &IdekmKeyId,
+) -> SpdmResult {
+    let gostop_ack: IdekmKeyId = send_receive_idekm(
+        spdm_requester,
+        session_id,
+        object_id,
+        key_id,
+        IdekmObjectId::IdekmKGostopAck,
+    )?;
+    if gostop_ack != *key_id {
+        return spdm_result_err!(EFAULT);
+    }
+    Ok(())
+}
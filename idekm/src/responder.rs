@@ -0,0 +1,249 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::{Codec, Reader, Writer};
+use core::convert::TryInto;
+use spdmlib::config;
+use spdmlib::error::SpdmResult;
+use spdmlib::message::{VendorDefinedReqPayloadStruct, VendorDefinedRspPayloadStruct};
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::message::*;
+
+/// The IDE ports of a device. The methods take &self because the
+/// VENDOR_DEFINED handler is a Fn, key state is kept in the device
+/// registers or behind a Cell/RefCell.
+pub trait IdekmDevice {
+    fn query(&self, port_index: u8) -> SpdmResult<IdekmQueryRespPayload>;
+
+    fn key_prog(&self, key_prog: &IdekmKeyProgPayload) -> IdekmKpAckStatus;
+
+    fn key_set_go(&self, key_id: &IdekmKeyId) -> SpdmResult;
+
+    fn key_set_stop(&self, key_id: &IdekmKeyId) -> SpdmResult;
+}
+
+/// Answer one IDE_KM object. Register it for the PCI-SIG standard ID and
+/// idekm_vendor_id():
+///
+/// ```ignore
+/// let handler = |_session_id: u32,
+///                _app_data: &mut SpdmSessionAppData,
+///                req: &VendorDefinedReqPayloadStruct| idekm_handle_request(&device, req);
+/// responder.register_vendor_defined_handler(
+///     RegistryOrStandardsBodyID::PCISIG,
+///     idekm_vendor_id(),
+///     &handler,
+/// )?;
+/// ```
+pub fn idekm_handle_request(
+    device: &dyn IdekmDevice,
+    vendor_defined_req_payload_struct: &VendorDefinedReqPayloadStruct,
+) -> SpdmResult<VendorDefinedRspPayloadStruct> {
+    let req_length = vendor_defined_req_payload_struct.req_length as usize;
+    if req_length > config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE {
+        return spdm_result_err!(EINVAL);
+    }
+    let mut reader =
+        Reader::init(&vendor_defined_req_payload_struct.vendor_defined_req_payload[..req_length]);
+    let header = IdekmMessageHeader::read(&mut reader).ok_or_else(|| spdm_err!(EINVAL))?;
+
+    let mut vendor_defined_rsp_payload_struct = VendorDefinedRspPayloadStruct {
+        rsp_length: 0,
+        vendor_defined_rsp_payload: [0u8; config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE],
+    };
+    let mut writer =
+        Writer::init(&mut vendor_defined_rsp_payload_struct.vendor_defined_rsp_payload);
+
+    match header.object_id {
+        IdekmObjectId::IdekmQuery => {
+            let query = IdekmQueryPayload::read(&mut reader).ok_or_else(|| spdm_err!(EINVAL))?;
+            let mut query_resp = device.query(query.port_index)?;
+            query_resp.port_index = query.port_index;
+            IdekmMessageHeader {
+                object_id: IdekmObjectId::IdekmQueryResp,
+            }
+            .encode(&mut writer);
+            query_resp.encode(&mut writer);
+        }
+        IdekmObjectId::IdekmKeyProg => {
+            let key_id = IdekmKeyId::read(&mut reader).ok_or_else(|| spdm_err!(EINVAL))?;
+            let status = if reader.left() != IDEKM_KEY_SIZE + IDEKM_IFV_SIZE {
+                IdekmKpAckStatus::IdekmKpAckStatusIncorrectLength
+            } else {
+                let mut key_prog = IdekmKeyProgPayload {
+                    key_id,
+                    key: [0u8; IDEKM_KEY_SIZE],
+                    ifv: [0u8; IDEKM_IFV_SIZE],
+                };
+                key_prog
+                    .key
+                    .copy_from_slice(reader.take(IDEKM_KEY_SIZE).unwrap());
+                key_prog
+                    .ifv
+                    .copy_from_slice(reader.take(IDEKM_IFV_SIZE).unwrap());
+                device.key_prog(&key_prog)
+            };
+            IdekmMessageHeader {
+                object_id: IdekmObjectId::IdekmKpAck,
+            }
+            .encode(&mut writer);
+            IdekmKpAckPayload { key_id, status }.encode(&mut writer);
+        }
+        IdekmObjectId::IdekmKSetGo | IdekmObjectId::IdekmKSetStop => {
+            let key_id = IdekmKeyId::read(&mut reader).ok_or_else(|| spdm_err!(EINVAL))?;
+            if header.object_id == IdekmObjectId::IdekmKSetGo {
+                device.key_set_go(&key_id)?;
+            } else {
+                device.key_set_stop(&key_id)?;
+            }
+            IdekmMessageHeader {
+                object_id: IdekmObjectId::IdekmKGostopAck,
+            }
+            .encode(&mut writer);
+            key_id.encode(&mut writer);
+        }
+        _ => return spdm_result_err!(EINVAL),
+    }
+
+    vendor_defined_rsp_payload_struct.rsp_length = writer.used().try_into().unwrap();
+    Ok(vendor_defined_rsp_payload_struct)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::requester::*;
+    use core::cell::RefCell;
+
+    #[derive(Default)]
+    struct FakeIdekmDevice {
+        key: RefCell<Option<IdekmKeyProgPayload>>,
+        key_id_go: RefCell<Option<IdekmKeyId>>,
+    }
+
+    impl IdekmDevice for FakeIdekmDevice {
+        fn query(&self, port_index: u8) -> SpdmResult<IdekmQueryRespPayload> {
+            if port_index > 1 {
+                return spdm_result_err!(EINVAL);
+            }
+            let mut query_resp = IdekmQueryRespPayload {
+                max_port_index: 1,
+                ide_reg_count: 2,
+                ..Default::default()
+            };
+            query_resp.ide_reg[0] = 0x0000_0003;
+            Ok(query_resp)
+        }
+
+        fn key_prog(&self, key_prog: &IdekmKeyProgPayload) -> IdekmKpAckStatus {
+            *self.key.borrow_mut() = Some(key_prog.clone());
+            IdekmKpAckStatus::IdekmKpAckStatusSuccess
+        }
+
+        fn key_set_go(&self, key_id: &IdekmKeyId) -> SpdmResult {
+            match &*self.key.borrow() {
+                Some(key_prog) if key_prog.key_id == *key_id => {
+                    *self.key_id_go.borrow_mut() = Some(*key_id);
+                    Ok(())
+                }
+                _ => spdm_result_err!(EINVAL),
+            }
+        }
+
+        fn key_set_stop(&self, _key_id: &IdekmKeyId) -> SpdmResult {
+            *self.key.borrow_mut() = None;
+            *self.key_id_go.borrow_mut() = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case0_idekm_handle_request() {
+        let device = FakeIdekmDevice::default();
+
+        let rsp = idekm_handle_request(
+            &device,
+            &encode_idekm_request(
+                IdekmObjectId::IdekmQuery,
+                &IdekmQueryPayload { port_index: 1 },
+            ),
+        )
+        .unwrap();
+        let query_resp: IdekmQueryRespPayload =
+            handle_idekm_response(IdekmObjectId::IdekmQueryResp, &rsp).unwrap();
+        assert_eq!(query_resp.port_index, 1);
+        assert_eq!(query_resp.ide_reg_count, 2);
+        assert_eq!(query_resp.ide_reg[0], 0x0000_0003);
+        assert!(idekm_handle_request(
+            &device,
+            &encode_idekm_request(
+                IdekmObjectId::IdekmQuery,
+                &IdekmQueryPayload { port_index: 2 }
+            ),
+        )
+        .is_err());
+
+        let key_id = IdekmKeyId {
+            stream_id: 1,
+            key_set: 0,
+            tx: false,
+            sub_stream: IDEKM_SUB_STREAM_NPR,
+            port_index: 1,
+        };
+        // K_SET_GO before KEY_PROG
+        assert!(idekm_handle_request(
+            &device,
+            &encode_idekm_request(IdekmObjectId::IdekmKSetGo, &key_id),
+        )
+        .is_err());
+
+        let key_prog = IdekmKeyProgPayload {
+            key_id,
+            key: [0x11u8; IDEKM_KEY_SIZE],
+            ifv: [0x22u8; IDEKM_IFV_SIZE],
+        };
+        let rsp = idekm_handle_request(
+            &device,
+            &encode_idekm_request(IdekmObjectId::IdekmKeyProg, &key_prog),
+        )
+        .unwrap();
+        let kp_ack: IdekmKpAckPayload =
+            handle_idekm_response(IdekmObjectId::IdekmKpAck, &rsp).unwrap();
+        assert_eq!(kp_ack.key_id, key_id);
+        assert_eq!(kp_ack.status, IdekmKpAckStatus::IdekmKpAckStatusSuccess);
+        assert_eq!(device.key.borrow().as_ref().unwrap().key, key_prog.key);
+
+        let rsp = idekm_handle_request(
+            &device,
+            &encode_idekm_request(IdekmObjectId::IdekmKSetGo, &key_id),
+        )
+        .unwrap();
+        let gostop_ack: IdekmKeyId =
+            handle_idekm_response(IdekmObjectId::IdekmKGostopAck, &rsp).unwrap();
+        assert_eq!(gostop_ack, key_id);
+        assert_eq!(*device.key_id_go.borrow(), Some(key_id));
+
+        idekm_handle_request(
+            &device,
+            &encode_idekm_request(IdekmObjectId::IdekmKSetStop, &key_id),
+        )
+        .unwrap();
+        assert!(device.key.borrow().is_none());
+
+        // KEY_PROG without the IFV
+        let mut truncated = encode_idekm_request(IdekmObjectId::IdekmKeyProg, &key_prog);
+        truncated.req_length -= IDEKM_IFV_SIZE as u16;
+        let rsp_truncated = idekm_handle_request(&device, &truncated).unwrap();
+        let kp_ack: IdekmKpAckPayload =
+            handle_idekm_response(IdekmObjectId::IdekmKpAck, &rsp_truncated).unwrap();
+        assert_eq!(
+            kp_ack.status,
+            IdekmKpAckStatus::IdekmKpAckStatusIncorrectLength
+        );
+        assert!(device.key.borrow().is_none());
+
+        assert!(handle_idekm_response::<IdekmKeyId>(IdekmObjectId::IdekmKpAck, &rsp).is_err());
+    }
+}
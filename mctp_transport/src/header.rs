@@ -5,7 +5,9 @@
 use codec::enum_builder;
 use codec::{Codec, Reader, Writer};
 use spdmlib::common::SpdmTransportEncap;
+use spdmlib::config;
 use spdmlib::error::SpdmResult;
+use spdmlib::protocol::SPDM_MAX_AEAD_TAG_SIZE;
 use spdmlib::{spdm_err, spdm_result_err};
 
 enum_builder! {
@@ -24,6 +26,8 @@ enum_builder! {
     }
 }
 
+pub const MCTP_MESSAGE_HEADER_SIZE: usize = 1;
+
 #[derive(Debug, Copy, Clone, Default)]
 pub struct MctpMessageHeader {
     pub r#type: MctpMessageType,
@@ -51,7 +55,41 @@ impl SpdmTransportEncap for MctpTransportEncap {
         secured_message: bool,
     ) -> SpdmResult<usize> {
         let payload_len = spdm_buffer.len();
-        let mut writer = Writer::init(&mut *transport_buffer);
+        if transport_buffer.len() < MCTP_MESSAGE_HEADER_SIZE + payload_len {
+            return spdm_result_err!(EINVAL);
+        }
+        transport_buffer[MCTP_MESSAGE_HEADER_SIZE..(MCTP_MESSAGE_HEADER_SIZE + payload_len)]
+            .copy_from_slice(spdm_buffer);
+        self.encap_in_place(transport_buffer, payload_len, secured_message)
+    }
+
+    fn decap(
+        &mut self,
+        transport_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        let (offset, payload_size, secured_message) = self.decap_in_place(transport_buffer)?;
+        if spdm_buffer.len() < payload_size {
+            return spdm_result_err!(EINVAL);
+        }
+        spdm_buffer[..payload_size].copy_from_slice(&transport_buffer[offset..]);
+        Ok((payload_size, secured_message))
+    }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        Some(MCTP_MESSAGE_HEADER_SIZE)
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        if transport_buffer.len() < MCTP_MESSAGE_HEADER_SIZE + spdm_size {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut writer = Writer::init(&mut transport_buffer[..MCTP_MESSAGE_HEADER_SIZE]);
         let mctp_header = MctpMessageHeader {
             r#type: if secured_message {
                 MctpMessageType::MctpMessageTypeSecuredMctp
@@ -60,19 +98,10 @@ impl SpdmTransportEncap for MctpTransportEncap {
             },
         };
         mctp_header.encode(&mut writer);
-        let header_size = writer.used();
-        if transport_buffer.len() < header_size + payload_len {
-            return spdm_result_err!(EINVAL);
-        }
-        transport_buffer[header_size..(header_size + payload_len)].copy_from_slice(spdm_buffer);
-        Ok(header_size + payload_len)
+        Ok(MCTP_MESSAGE_HEADER_SIZE + spdm_size)
     }
 
-    fn decap(
-        &mut self,
-        transport_buffer: &[u8],
-        spdm_buffer: &mut [u8],
-    ) -> SpdmResult<(usize, bool)> {
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
         let mut reader = Reader::init(transport_buffer);
         let secured_message;
         match MctpMessageHeader::read(&mut reader) {
@@ -88,38 +117,48 @@ impl SpdmTransportEncap for MctpTransportEncap {
             None => return spdm_result_err!(EIO),
         }
         let header_size = reader.used();
-        let payload_size = transport_buffer.len() - header_size;
-        if spdm_buffer.len() < payload_size {
-            return spdm_result_err!(EINVAL);
-        }
-        let payload = &transport_buffer[header_size..];
-        spdm_buffer[..payload_size].copy_from_slice(payload);
-        Ok((payload_size, secured_message))
+        Ok((
+            header_size,
+            transport_buffer.len() - header_size,
+            secured_message,
+        ))
     }
 
+    // Application messages are PLDM, unless they start with a vendor
+    // defined MCTP message type: those keep it in front, the first byte of
+    // a PLDM message cannot be 0x7E or 0x7F as its bit 5 is reserved.
     fn encap_app(
         &mut self,
         spdm_buffer: &[u8],
         app_buffer: &mut [u8],
         is_app_message: bool,
     ) -> SpdmResult<usize> {
-        let payload_len = spdm_buffer.len();
-        let mut writer = Writer::init(&mut *app_buffer);
-        let mctp_header = if is_app_message {
-            MctpMessageHeader {
-                r#type: MctpMessageType::MctpMessageTypePldm,
-            }
+        let (r#type, payload) = if !is_app_message {
+            (MctpMessageType::MctpMessageTypeSpdm, spdm_buffer)
         } else {
-            MctpMessageHeader {
-                r#type: MctpMessageType::MctpMessageTypeSpdm,
+            match spdm_buffer.split_first() {
+                Some((&r#type, payload))
+                    if r#type == MctpMessageType::MctpMessageTypeVendorDefinedPci.get_u8() =>
+                {
+                    (MctpMessageType::MctpMessageTypeVendorDefinedPci, payload)
+                }
+                Some((&r#type, payload))
+                    if r#type == MctpMessageType::MctpMessageTypeVendorDefinedIana.get_u8() =>
+                {
+                    (MctpMessageType::MctpMessageTypeVendorDefinedIana, payload)
+                }
+                _ => (MctpMessageType::MctpMessageTypePldm, spdm_buffer),
             }
         };
+        let payload_len = payload.len();
+        let mut writer = Writer::init(&mut *app_buffer);
+        let mctp_header = MctpMessageHeader { r#type };
         mctp_header.encode(&mut writer);
         let header_size = writer.used();
         if app_buffer.len() < header_size + payload_len {
             return spdm_result_err!(EINVAL);
         }
-        app_buffer[header_size..(header_size + payload_len)].copy_from_slice(spdm_buffer);
+        app_buffer[header_size..(header_size + payload_len)].copy_from_slice(payload);
         Ok(header_size + payload_len)
     }
 
@@ -130,17 +169,22 @@ impl SpdmTransportEncap for MctpTransportEncap {
     ) -> SpdmResult<(usize, bool)> {
         let mut reader = Reader::init(app_buffer);
         let mut is_app_mesaage = false;
+        let mut header_size = MCTP_MESSAGE_HEADER_SIZE;
         match MctpMessageHeader::read(&mut reader) {
             Some(mctp_header) => match mctp_header.r#type {
                 MctpMessageType::MctpMessageTypeSpdm => {}
                 MctpMessageType::MctpMessageTypePldm => {
                     is_app_mesaage = true;
                 }
+                MctpMessageType::MctpMessageTypeVendorDefinedPci
+                | MctpMessageType::MctpMessageTypeVendorDefinedIana => {
+                    is_app_mesaage = true;
+                    header_size = 0;
+                }
                 _ => return spdm_result_err!(EINVAL),
             },
             None => return spdm_result_err!(EIO),
         }
-        let header_size = reader.used();
         let payload_size = app_buffer.len() - header_size;
         if spdm_buffer.len() < payload_size {
             return spdm_result_err!(EINVAL);
@@ -156,12 +200,24 @@ impl SpdmTransportEncap for MctpTransportEncap {
     fn get_max_random_count(&mut self) -> u16 {
         32
     }
+
+    fn get_max_transport_size(&mut self) -> usize {
+        config::DATA_TRANSFER_SIZE - MCTP_MESSAGE_HEADER_SIZE
+    }
+
+    // the protected data starts with the MCTP message type encap_app writes
+    fn get_max_secured_overhead(&mut self) -> usize {
+        4 + self.get_sequence_number_count() as usize
+            + 2
+            + 2
+            + self.get_max_random_count() as usize
+            + SPDM_MAX_AEAD_TAG_SIZE
+            + MCTP_MESSAGE_HEADER_SIZE
+    }
 }
 
 #[cfg(all(test,))]
 mod tests {
-    use spdmlib::config;
-
     use super::*;
 
     #[test]
@@ -241,6 +297,28 @@ mod tests {
         assert!(status);
     }
     #[test]
+    fn test_case0_encap_in_place() {
+        let mut mctp_transport_encap = MctpTransportEncap {};
+        let header_size = mctp_transport_encap.get_encap_header_size().unwrap();
+
+        let mut transport_buffer = [0u8; 8];
+        transport_buffer[header_size..(header_size + 4)].copy_from_slice(&[0x12, 0x84, 0, 0]);
+        let used = mctp_transport_encap
+            .encap_in_place(&mut transport_buffer, 4, false)
+            .unwrap();
+        assert_eq!(used, 5);
+        assert_eq!(&transport_buffer[..used], &[0x05, 0x12, 0x84, 0, 0]);
+        assert_eq!(
+            mctp_transport_encap
+                .decap_in_place(&transport_buffer[..used])
+                .unwrap(),
+            (1, 4, false)
+        );
+        assert!(mctp_transport_encap
+            .encap_in_place(&mut transport_buffer, 8, true)
+            .is_err());
+    }
+    #[test]
     fn test_case0_encap_app() {
         let mut mctp_transport_encap = MctpTransportEncap {};
         let mut app_buffer = [0u8; 100];
@@ -282,6 +360,52 @@ mod tests {
             .is_ok();
         assert!(status);
     }
+    #[test]
+    fn test_case1_app_message() {
+        let mut mctp_transport_encap = MctpTransportEncap {};
+        let mut app_buffer = [0u8; 16];
+        let mut spdm_buffer = [0u8; 16];
+
+        // PLDM gets its message type added and removed
+        let used = mctp_transport_encap
+            .encap_app(&[0x80, 0x00, 0x02], &mut app_buffer, true)
+            .unwrap();
+        assert_eq!(&app_buffer[..used], &[0x01, 0x80, 0x00, 0x02]);
+        let (used, is_app_message) = mctp_transport_encap
+            .decap_app(&app_buffer[..used], &mut spdm_buffer)
+            .unwrap();
+        assert!(is_app_message);
+        assert_eq!(&spdm_buffer[..used], &[0x80, 0x00, 0x02]);
+
+        // vendor defined messages keep theirs
+        let vendor_message = [0x7f, 0x00, 0x00, 0x01, 0x57, 0xaa];
+        let used = mctp_transport_encap
+            .encap_app(&vendor_message, &mut app_buffer, true)
+            .unwrap();
+        assert_eq!(&app_buffer[..used], &vendor_message);
+        let (used, is_app_message) = mctp_transport_encap
+            .decap_app(&app_buffer[..used], &mut spdm_buffer)
+            .unwrap();
+        assert!(is_app_message);
+        assert_eq!(&spdm_buffer[..used], &vendor_message);
+
+        let (used, is_app_message) = mctp_transport_encap
+            .decap_app(&[0x7e, 0x86, 0x80, 0xbb], &mut spdm_buffer)
+            .unwrap();
+        assert!(is_app_message);
+        assert_eq!(&spdm_buffer[..used], &[0x7e, 0x86, 0x80, 0xbb]);
+
+        // an SPDM message starting like a vendor defined one stays SPDM
+        let used = mctp_transport_encap
+            .encap_app(&[0x7f, 0x01], &mut app_buffer, false)
+            .unwrap();
+        assert_eq!(&app_buffer[..used], &[0x05, 0x7f, 0x01]);
+
+        assert!(mctp_transport_encap
+            .decap_app(&[0x04, 0x00], &mut spdm_buffer)
+            .is_err());
+    }
+
     #[test]
     fn test_case0_get_sequence_number_count() {
         let mut mctp_transport_encap = MctpTransportEncap {};
@@ -292,4 +416,30 @@ mod tests {
         let mut mctp_transport_encap = MctpTransportEncap {};
         assert_eq!(mctp_transport_encap.get_max_random_count(), 32);
     }
+    #[test]
+    fn test_case0_max_transport_size() {
+        let mut mctp_transport_encap = MctpTransportEncap {};
+        let max_size = mctp_transport_encap.get_max_transport_size();
+
+        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let spdm_buffer = [0x5au8; config::DATA_TRANSFER_SIZE];
+        let used = mctp_transport_encap
+            .encap(&spdm_buffer[..max_size], &mut transport_buffer, true)
+            .unwrap();
+        assert_eq!(used, config::DATA_TRANSFER_SIZE);
+        assert!(mctp_transport_encap
+            .encap(&spdm_buffer[..(max_size + 1)], &mut transport_buffer, true)
+            .is_err());
+
+        // a secured message also carries the MCTP message type of encap_app
+        let mut app_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let used = mctp_transport_encap
+            .encap_app(&spdm_buffer[..16], &mut app_buffer, false)
+            .unwrap();
+        assert_eq!(used, 16 + MCTP_MESSAGE_HEADER_SIZE);
+        assert_eq!(
+            mctp_transport_encap.get_max_secured_overhead(),
+            4 + 2 + 2 + 2 + 32 + SPDM_MAX_AEAD_TAG_SIZE + MCTP_MESSAGE_HEADER_SIZE
+        );
+    }
 }
@@ -2,8 +2,21 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-#![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "std"), forbid(unsafe_code))]
+#![cfg_attr(feature = "std", deny(unsafe_code))]
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 mod header;
 pub use header::*;
+
+mod packet;
+pub use packet::*;
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+#[allow(unsafe_code)]
+mod socket;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use socket::*;
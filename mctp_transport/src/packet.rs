@@ -0,0 +1,473 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::{Codec, Reader, Writer};
+use spdmlib::common::SpdmTransportEncap;
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::header::MctpTransportEncap;
+
+pub const MCTP_TRANSPORT_HEADER_SIZE: usize = 4;
+/// Smallest MTU every MCTP medium carries, the default packet payload size.
+pub const MCTP_BASELINE_TRANSMISSION_UNIT: usize = 64;
+pub const MCTP_NULL_EID: u8 = 0;
+pub const MCTP_BROADCAST_EID: u8 = 0xFF;
+
+const MCTP_HEADER_VERSION: u8 = 0x01;
+const MCTP_HEADER_VERSION_MASK: u8 = 0x0F;
+const MCTP_SOM: u8 = 0x80;
+const MCTP_EOM: u8 = 0x40;
+const MCTP_PACKET_SEQUENCE_SHIFT: u8 = 4;
+const MCTP_PACKET_SEQUENCE_MASK: u8 = 0x03;
+const MCTP_TAG_OWNER: u8 = 0x08;
+const MCTP_MESSAGE_TAG_MASK: u8 = 0x07;
+
+/// MCTP transport header in front of every packet.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MctpTransportHeader {
+    pub dest_eid: u8,
+    pub source_eid: u8,
+    pub som: bool,
+    pub eom: bool,
+    pub packet_sequence: u8,
+    pub tag_owner: bool,
+    pub message_tag: u8,
+}
+
+impl Codec for MctpTransportHeader {
+    fn encode(&self, bytes: &mut Writer) {
+        MCTP_HEADER_VERSION.encode(bytes);
+        self.dest_eid.encode(bytes);
+        self.source_eid.encode(bytes);
+        let mut flags = ((self.packet_sequence & MCTP_PACKET_SEQUENCE_MASK)
+            << MCTP_PACKET_SEQUENCE_SHIFT)
+            | (self.message_tag & MCTP_MESSAGE_TAG_MASK);
+        if self.som {
+            flags |= MCTP_SOM;
+        }
+        if self.eom {
+            flags |= MCTP_EOM;
+        }
+        if self.tag_owner {
+            flags |= MCTP_TAG_OWNER;
+        }
+        flags.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<MctpTransportHeader> {
+        let version = u8::read(r)?;
+        if version & MCTP_HEADER_VERSION_MASK != MCTP_HEADER_VERSION {
+            return None;
+        }
+        let dest_eid = u8::read(r)?;
+        let source_eid = u8::read(r)?;
+        let flags = u8::read(r)?;
+        Some(MctpTransportHeader {
+            dest_eid,
+            source_eid,
+            som: flags & MCTP_SOM != 0,
+            eom: flags & MCTP_EOM != 0,
+            packet_sequence: (flags >> MCTP_PACKET_SEQUENCE_SHIFT) & MCTP_PACKET_SEQUENCE_MASK,
+            tag_owner: flags & MCTP_TAG_OWNER != 0,
+            message_tag: flags & MCTP_MESSAGE_TAG_MASK,
+        })
+    }
+}
+
+/// MCTP transport for a physical binding (SMBus, PCIe VDM, ...) that moves
+/// MCTP packets rather than whole messages: each transport message is split
+/// into packets of up to mtu payload bytes behind the MCTP transport header,
+/// and reassembled on receive.
+///
+/// The requester side owns the message tags, it takes a new tag for every
+/// request and only takes a response from dest_eid carrying that tag. Behind
+/// a bridge, set_peer_address moves it to another endpoint. The responder
+/// side answers the EID and tag of the last request, so dest_eid follows the
+/// requester.
+#[derive(Debug, Copy, Clone)]
+pub struct MctpPacketTransportEncap {
+    pub source_eid: u8,
+    pub dest_eid: u8,
+    pub mtu: usize,
+    pub tag_owner: bool,
+    message_tag: u8,
+    send_sequence: u8,
+    receive_sequence: u8,
+    receive_tag: u8,
+    receive_source_eid: u8,
+}
+
+impl MctpPacketTransportEncap {
+    pub fn new(source_eid: u8, dest_eid: u8, mtu: usize, tag_owner: bool) -> Self {
+        MctpPacketTransportEncap {
+            source_eid,
+            dest_eid,
+            mtu,
+            tag_owner,
+            message_tag: 0,
+            send_sequence: 0,
+            receive_sequence: 0,
+            receive_tag: 0,
+            receive_source_eid: MCTP_NULL_EID,
+        }
+    }
+}
+
+impl SpdmTransportEncap for MctpPacketTransportEncap {
+    fn encap(
+        &mut self,
+        spdm_buffer: &[u8],
+        transport_buffer: &mut [u8],
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        MctpTransportEncap {}.encap(spdm_buffer, transport_buffer, secured_message)
+    }
+
+    fn decap(
+        &mut self,
+        transport_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        MctpTransportEncap {}.decap(transport_buffer, spdm_buffer)
+    }
+
+    fn encap_app(
+        &mut self,
+        spdm_buffer: &[u8],
+        app_buffer: &mut [u8],
+        is_app_message: bool,
+    ) -> SpdmResult<usize> {
+        MctpTransportEncap {}.encap_app(spdm_buffer, app_buffer, is_app_message)
+    }
+
+    fn decap_app(
+        &mut self,
+        app_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        MctpTransportEncap {}.decap_app(app_buffer, spdm_buffer)
+    }
+
+    fn get_sequence_number_count(&mut self) -> u8 {
+        MctpTransportEncap {}.get_sequence_number_count()
+    }
+
+    fn get_max_random_count(&mut self) -> u16 {
+        MctpTransportEncap {}.get_max_random_count()
+    }
+
+    fn get_max_transport_size(&mut self) -> usize {
+        MctpTransportEncap {}.get_max_transport_size()
+    }
+
+    fn get_max_secured_overhead(&mut self) -> usize {
+        MctpTransportEncap {}.get_max_secured_overhead()
+    }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        MctpTransportEncap {}.get_encap_header_size()
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        MctpTransportEncap {}.encap_in_place(transport_buffer, spdm_size, secured_message)
+    }
+
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
+        MctpTransportEncap {}.decap_in_place(transport_buffer)
+    }
+
+    fn get_peer_address(&mut self) -> Option<u32> {
+        Some(self.dest_eid as u32)
+    }
+
+    fn set_peer_address(&mut self, peer_address: u32) -> SpdmResult {
+        if peer_address >= MCTP_BROADCAST_EID as u32 {
+            return spdm_result_err!(EINVAL);
+        }
+        self.dest_eid = peer_address as u8;
+        Ok(())
+    }
+
+    fn get_max_transport_unit(&mut self) -> Option<usize> {
+        Some(MCTP_TRANSPORT_HEADER_SIZE + self.mtu)
+    }
+
+    fn encap_fragment(
+        &mut self,
+        transport_message: &[u8],
+        first: bool,
+        fragment_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, usize)> {
+        if self.mtu == 0 || fragment_buffer.len() < MCTP_TRANSPORT_HEADER_SIZE + self.mtu {
+            return spdm_result_err!(EINVAL);
+        }
+        // all packets but the last one carry exactly mtu bytes
+        let payload_size = transport_message.len().min(self.mtu);
+
+        if first {
+            self.send_sequence = 0;
+            if self.tag_owner {
+                self.message_tag = (self.message_tag + 1) & MCTP_MESSAGE_TAG_MASK;
+            } else {
+                self.message_tag = self.receive_tag;
+            }
+        }
+        let mctp_header = MctpTransportHeader {
+            dest_eid: self.dest_eid,
+            source_eid: self.source_eid,
+            som: first,
+            eom: payload_size == transport_message.len(),
+            packet_sequence: self.send_sequence,
+            tag_owner: self.tag_owner,
+            message_tag: self.message_tag,
+        };
+        self.send_sequence = self.send_sequence.wrapping_add(1);
+
+        let mut writer = Writer::init(&mut fragment_buffer[..MCTP_TRANSPORT_HEADER_SIZE]);
+        mctp_header.encode(&mut writer);
+        fragment_buffer[MCTP_TRANSPORT_HEADER_SIZE..(MCTP_TRANSPORT_HEADER_SIZE + payload_size)]
+            .copy_from_slice(&transport_message[..payload_size]);
+        Ok((payload_size, MCTP_TRANSPORT_HEADER_SIZE + payload_size))
+    }
+
+    fn decap_fragment(
+        &mut self,
+        fragment: &[u8],
+        first: bool,
+        transport_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        let mctp_header =
+            MctpTransportHeader::read(&mut Reader::init(fragment)).ok_or_else(|| spdm_err!(EIO))?;
+        if mctp_header.dest_eid != self.source_eid && mctp_header.dest_eid != MCTP_NULL_EID {
+            return spdm_result_err!(EIO);
+        }
+        let payload = &fragment[MCTP_TRANSPORT_HEADER_SIZE..];
+        let last = mctp_header.eom;
+
+        if first != mctp_header.som {
+            return spdm_result_err!(EIO);
+        }
+        if first {
+            // requests for a responder, responses to our latest request from
+            // the peer for a requester
+            if mctp_header.tag_owner == self.tag_owner {
+                return spdm_result_err!(EIO);
+            }
+            if self.tag_owner
+                && (mctp_header.message_tag != self.message_tag
+                    || (self.dest_eid != MCTP_NULL_EID && mctp_header.source_eid != self.dest_eid))
+            {
+                return spdm_result_err!(EIO);
+            }
+            self.receive_sequence = 0;
+            self.receive_tag = mctp_header.message_tag;
+            self.receive_source_eid = mctp_header.source_eid;
+        } else if mctp_header.message_tag != self.receive_tag
+            || mctp_header.tag_owner == self.tag_owner
+            || mctp_header.source_eid != self.receive_source_eid
+        {
+            return spdm_result_err!(EIO);
+        }
+        if mctp_header.packet_sequence != self.receive_sequence & MCTP_PACKET_SEQUENCE_MASK {
+            return spdm_result_err!(EIO);
+        }
+        self.receive_sequence = self.receive_sequence.wrapping_add(1);
+        if last && !self.tag_owner {
+            // answer the requester of this request
+            self.dest_eid = self.receive_source_eid;
+        }
+        // only the last packet of a message may be short
+        if payload.is_empty() || payload.len() > self.mtu || (!last && payload.len() != self.mtu) {
+            return spdm_result_err!(EIO);
+        }
+
+        if transport_buffer.len() < payload.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        transport_buffer[..payload.len()].copy_from_slice(payload);
+        Ok((payload.len(), last))
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    fn packetize(
+        transport_encap: &mut MctpPacketTransportEncap,
+        transport_message: &[u8],
+        packets: &mut [[u8; MCTP_TRANSPORT_HEADER_SIZE + MCTP_BASELINE_TRANSMISSION_UNIT]],
+    ) -> [usize; 3] {
+        let mut packet_sizes = [0usize; 3];
+        let mut offset = 0;
+        for (i, packet) in packets.iter_mut().enumerate() {
+            let (consumed, used) = transport_encap
+                .encap_fragment(&transport_message[offset..], i == 0, packet)
+                .unwrap();
+            packet_sizes[i] = used;
+            offset += consumed;
+        }
+        assert_eq!(offset, transport_message.len());
+        packet_sizes
+    }
+
+    #[test]
+    fn test_case0_mctp_packet_round_trip() {
+        let mut requester =
+            MctpPacketTransportEncap::new(8, 9, MCTP_BASELINE_TRANSMISSION_UNIT, true);
+        let mut responder =
+            MctpPacketTransportEncap::new(9, 8, MCTP_BASELINE_TRANSMISSION_UNIT, false);
+        assert_eq!(requester.get_max_transport_unit(), Some(68));
+
+        let mut transport_message = [0u8; 150];
+        for (i, data) in transport_message.iter_mut().enumerate() {
+            *data = i as u8;
+        }
+
+        let mut packets = [[0u8; 68]; 3];
+        let packet_sizes = packetize(&mut requester, &transport_message, &mut packets);
+        assert_eq!(packet_sizes, [68, 68, 26]);
+        assert_eq!(&packets[0][..4], &[0x01, 9, 8, 0x80 | 0x08 | 0x01]);
+        assert_eq!(packets[1][3], 0x10 | 0x08 | 0x01);
+        assert_eq!(packets[2][3], 0x40 | 0x20 | 0x08 | 0x01);
+
+        let mut transport_buffer = [0u8; 256];
+        let mut used = 0;
+        for (i, packet) in packets.iter().enumerate() {
+            let (payload_size, last) = responder
+                .decap_fragment(
+                    &packet[..packet_sizes[i]],
+                    i == 0,
+                    &mut transport_buffer[used..],
+                )
+                .unwrap();
+            used += payload_size;
+            assert_eq!(last, i == 2);
+        }
+        assert_eq!(&transport_buffer[..used], &transport_message[..]);
+
+        // the response carries the request tag with TO clear
+        let (_, used) = responder
+            .encap_fragment(&transport_message[..10], true, &mut packets[0])
+            .unwrap();
+        assert_eq!(used, 14);
+        assert_eq!(&packets[0][..4], &[0x01, 8, 9, 0x80 | 0x40 | 0x01]);
+        assert!(
+            requester
+                .decap_fragment(&packets[0][..used], true, &mut transport_buffer)
+                .unwrap()
+                .1
+        );
+    }
+
+    #[test]
+    fn test_case1_mctp_packet_errors() {
+        let mut requester =
+            MctpPacketTransportEncap::new(8, 9, MCTP_BASELINE_TRANSMISSION_UNIT, true);
+        let mut responder =
+            MctpPacketTransportEncap::new(9, 8, MCTP_BASELINE_TRANSMISSION_UNIT, false);
+        let transport_message = [0x5au8; 150];
+        let mut packets = [[0u8; 68]; 3];
+        let packet_sizes = packetize(&mut requester, &transport_message, &mut packets);
+        let mut transport_buffer = [0u8; 256];
+
+        // a lost packet breaks the sequence
+        assert!(responder
+            .decap_fragment(&packets[0][..packet_sizes[0]], true, &mut transport_buffer)
+            .is_ok());
+        assert!(responder
+            .decap_fragment(&packets[2][..packet_sizes[2]], false, &mut transport_buffer)
+            .is_err());
+
+        // a packet of another message
+        assert!(responder
+            .decap_fragment(&packets[0][..packet_sizes[0]], true, &mut transport_buffer)
+            .is_ok());
+        let mut packet = packets[1];
+        packet[3] ^= 0x02;
+        assert!(responder
+            .decap_fragment(&packet[..packet_sizes[1]], false, &mut transport_buffer)
+            .is_err());
+
+        // a packet for another endpoint
+        let mut packet = packets[0];
+        packet[1] = 10;
+        assert!(responder
+            .decap_fragment(&packet[..packet_sizes[0]], true, &mut transport_buffer)
+            .is_err());
+
+        // a short middle packet
+        assert!(responder
+            .decap_fragment(
+                &packets[0][..packet_sizes[0] - 1],
+                true,
+                &mut transport_buffer
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_case2_mctp_packet_peer_eid() {
+        let mut requester =
+            MctpPacketTransportEncap::new(8, 9, MCTP_BASELINE_TRANSMISSION_UNIT, true);
+        let mut responder_9 =
+            MctpPacketTransportEncap::new(9, MCTP_NULL_EID, MCTP_BASELINE_TRANSMISSION_UNIT, false);
+        let mut responder_10 = MctpPacketTransportEncap::new(
+            10,
+            MCTP_NULL_EID,
+            MCTP_BASELINE_TRANSMISSION_UNIT,
+            false,
+        );
+        let transport_message = [0x5au8; 10];
+        let mut packet = [0u8; 68];
+        let mut transport_buffer = [0u8; 64];
+
+        let (_, used) = requester
+            .encap_fragment(&transport_message, true, &mut packet)
+            .unwrap();
+        assert!(responder_9
+            .decap_fragment(&packet[..used], true, &mut transport_buffer)
+            .is_ok());
+        assert_eq!(responder_9.get_peer_address(), Some(8));
+
+        // behind the same bridge, EID 10 is now the peer
+        assert!(requester.set_peer_address(0xff).is_err());
+        assert!(requester.set_peer_address(10).is_ok());
+        let (_, used) = requester
+            .encap_fragment(&transport_message, true, &mut packet)
+            .unwrap();
+        let mctp_header = MctpTransportHeader::read(&mut Reader::init(&packet[..used])).unwrap();
+        assert_eq!(mctp_header.dest_eid, 10);
+        assert_eq!(mctp_header.source_eid, 8);
+        assert!(mctp_header.som && mctp_header.eom && mctp_header.tag_owner);
+        assert!(responder_10
+            .decap_fragment(&packet[..used], true, &mut transport_buffer)
+            .is_ok());
+
+        // a late response of EID 9 is not the response from EID 10
+        let (_, used) = responder_9
+            .encap_fragment(&transport_message, true, &mut packet)
+            .unwrap();
+        assert!(requester
+            .decap_fragment(&packet[..used], true, &mut transport_buffer)
+            .is_err());
+        let (_, used) = responder_10
+            .encap_fragment(&transport_message, true, &mut packet)
+            .unwrap();
+        assert_eq!(&packet[..4], &[0x01, 8, 10, 0x80 | 0x40 | 0x02]);
+        assert!(requester
+            .decap_fragment(&packet[..used], true, &mut transport_buffer)
+            .is_ok());
+        // a responder only takes requests addressed to it
+        assert!(responder_9
+            .decap_fragment(&packet[..used], true, &mut transport_buffer)
+            .is_err());
+    }
+}
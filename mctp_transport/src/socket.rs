@@ -0,0 +1,264 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! SpdmDeviceIo over Linux kernel AF_MCTP sockets.
+//!
+//! The kernel MCTP stack does packetization, routing and tag allocation, so
+//! pair it with MctpTransportEncap, not MctpPacketTransportEncap. The MCTP
+//! message type MctpTransportEncap puts in front of a message travels in
+//! sockaddr_mctp instead of the datagram.
+
+use core::mem;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::vec::Vec;
+
+use spdmlib::common::SpdmDeviceIo;
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::header::MctpMessageType;
+
+pub const AF_MCTP: libc::c_int = 45;
+pub const MCTP_NET_ANY: u32 = 0x0;
+pub const MCTP_ADDR_ANY: u8 = 0xff;
+pub const MCTP_TAG_MASK: u8 = 0x07;
+pub const MCTP_TAG_OWNER: u8 = 0x08;
+
+/// struct sockaddr_mctp of linux/mctp.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+struct SockaddrMctp {
+    smctp_family: libc::sa_family_t,
+    smctp_pad0: u16,
+    smctp_network: libc::c_uint,
+    smctp_addr: u8,
+    smctp_type: u8,
+    smctp_tag: u8,
+    smctp_pad1: u8,
+}
+
+impl SockaddrMctp {
+    fn new(network: u32, eid: u8, r#type: u8, tag: u8) -> Self {
+        SockaddrMctp {
+            smctp_family: AF_MCTP as libc::sa_family_t,
+            smctp_network: network,
+            smctp_addr: eid,
+            smctp_type: r#type,
+            smctp_tag: tag,
+            ..Default::default()
+        }
+    }
+}
+
+const SOCKADDR_MCTP_LEN: libc::socklen_t = mem::size_of::<SockaddrMctp>() as libc::socklen_t;
+
+fn mctp_socket() -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::socket(AF_MCTP, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn mctp_bind(socket: &OwnedFd, addr: &SockaddrMctp) -> io::Result<()> {
+    let ret = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            addr as *const SockaddrMctp as *const libc::sockaddr,
+            SOCKADDR_MCTP_LEN,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn mctp_sendto(socket: &OwnedFd, buffer: &[u8], addr: &SockaddrMctp) -> io::Result<()> {
+    let ret = unsafe {
+        libc::sendto(
+            socket.as_raw_fd(),
+            buffer.as_ptr() as *const libc::c_void,
+            buffer.len(),
+            0,
+            addr as *const SockaddrMctp as *const libc::sockaddr,
+            SOCKADDR_MCTP_LEN,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receive one message, None if it did not fit in buffer.
+fn mctp_recvfrom(socket: &OwnedFd, buffer: &mut [u8]) -> io::Result<Option<(usize, SockaddrMctp)>> {
+    let mut addr = SockaddrMctp::default();
+    let mut addr_len = SOCKADDR_MCTP_LEN;
+    let ret = unsafe {
+        libc::recvfrom(
+            socket.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            buffer.len(),
+            libc::MSG_TRUNC,
+            &mut addr as *mut SockaddrMctp as *mut libc::sockaddr,
+            &mut addr_len,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let used = ret as usize;
+    if used > buffer.len() {
+        return Ok(None);
+    }
+    Ok(Some((used, addr)))
+}
+
+/// Index of a socket ready to read, None once timeout (in us) expired.
+fn mctp_poll(sockets: &[OwnedFd], timeout: usize) -> io::Result<Option<usize>> {
+    let mut poll_fds = [libc::pollfd {
+        fd: -1,
+        events: libc::POLLIN,
+        revents: 0,
+    }; 2];
+    for (poll_fd, socket) in poll_fds.iter_mut().zip(sockets.iter()) {
+        poll_fd.fd = socket.as_raw_fd();
+    }
+    let timeout_ms = ((timeout + 999) / 1000).min(libc::c_int::MAX as usize) as libc::c_int;
+    let ret = unsafe {
+        libc::poll(
+            poll_fds.as_mut_ptr(),
+            sockets.len() as libc::nfds_t,
+            timeout_ms,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(poll_fds
+        .iter()
+        .take(sockets.len())
+        .position(|poll_fd| poll_fd.revents & libc::POLLIN != 0))
+}
+
+/// SpdmDeviceIo on the kernel MCTP stack.
+///
+/// A requester talks to one remote EID: the kernel allocates an owned tag
+/// for every request and routes the response back to this socket. A
+/// responder listens for SPDM and secured messages on every local EID and
+/// answers the EID and tag of the last request it received.
+pub struct MctpSocketDeviceIo {
+    network: u32,
+    remote_eid: u8,
+    tag: u8,
+    is_responder: bool,
+    sockets: Vec<OwnedFd>,
+}
+
+impl MctpSocketDeviceIo {
+    /// Requester end talking to remote_eid on network, MCTP_NET_ANY for the
+    /// default network.
+    pub fn new_requester(network: u32, remote_eid: u8) -> SpdmResult<Self> {
+        let socket = mctp_socket().map_err(|_| spdm_err!(EIO))?;
+        Ok(MctpSocketDeviceIo {
+            network,
+            remote_eid,
+            tag: MCTP_TAG_OWNER,
+            is_responder: false,
+            sockets: std::vec![socket],
+        })
+    }
+
+    /// Responder end, bound to the SPDM and secured messages MCTP message
+    /// types on every local EID of network.
+    pub fn new_responder(network: u32) -> SpdmResult<Self> {
+        let mut sockets = Vec::new();
+        for r#type in [
+            MctpMessageType::MctpMessageTypeSpdm,
+            MctpMessageType::MctpMessageTypeSecuredMctp,
+        ] {
+            let socket = mctp_socket().map_err(|_| spdm_err!(EIO))?;
+            mctp_bind(
+                &socket,
+                &SockaddrMctp::new(network, MCTP_ADDR_ANY, r#type.get_u8(), 0),
+            )
+            .map_err(|_| spdm_err!(EIO))?;
+            sockets.push(socket);
+        }
+        Ok(MctpSocketDeviceIo {
+            network,
+            remote_eid: MCTP_ADDR_ANY,
+            tag: 0,
+            is_responder: true,
+            sockets,
+        })
+    }
+
+    /// EID of the peer, for a responder the sender of the last request.
+    pub fn remote_eid(&self) -> u8 {
+        self.remote_eid
+    }
+}
+
+impl SpdmDeviceIo for MctpSocketDeviceIo {
+    fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+        // the MCTP message type goes in the address
+        if buffer.is_empty() || (self.is_responder && self.remote_eid == MCTP_ADDR_ANY) {
+            return spdm_result_err!(EINVAL);
+        }
+        let addr = SockaddrMctp::new(self.network, self.remote_eid, buffer[0], self.tag);
+        mctp_sendto(&self.sockets[0], &buffer[1..], &addr).map_err(|_| spdm_err!(EIO))
+    }
+
+    fn receive(&mut self, buffer: &mut [u8], timeout: usize) -> Result<usize, usize> {
+        if buffer.is_empty() {
+            return Err(0);
+        }
+        loop {
+            let index = match mctp_poll(&self.sockets, timeout) {
+                Ok(Some(index)) => index,
+                _ => return Err(0),
+            };
+            let (used, addr) = match mctp_recvfrom(&self.sockets[index], &mut buffer[1..]) {
+                Ok(Some(received)) => received,
+                Ok(None) => continue,
+                Err(_) => return Err(0),
+            };
+            if self.is_responder {
+                // only requests, answered with the same tag and TO cleared
+                if addr.smctp_tag & MCTP_TAG_OWNER == 0 {
+                    continue;
+                }
+                self.remote_eid = addr.smctp_addr;
+                self.tag = addr.smctp_tag & MCTP_TAG_MASK;
+            } else if addr.smctp_addr != self.remote_eid || addr.smctp_tag & MCTP_TAG_OWNER != 0 {
+                continue;
+            }
+            buffer[0] = addr.smctp_type;
+            return Ok(used + 1);
+        }
+    }
+
+    fn flush_all(&mut self) -> SpdmResult {
+        Ok(())
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_sockaddr_mctp() {
+        assert_eq!(SOCKADDR_MCTP_LEN, 12);
+        let addr = SockaddrMctp::new(1, 8, 5, MCTP_TAG_OWNER);
+        assert_eq!(addr.smctp_family, 45);
+        assert_eq!(addr.smctp_network, 1);
+        assert_eq!(addr.smctp_addr, 8);
+        assert_eq!(addr.smctp_type, 5);
+        assert_eq!(addr.smctp_tag, MCTP_TAG_OWNER);
+    }
+}
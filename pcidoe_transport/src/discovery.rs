@@ -0,0 +1,149 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::{Codec, Reader, Writer};
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::header::{PciDoeMessageHeader, PciDoeProtocol, PCI_DOE_PROTOCOL_DISCOVERY};
+
+/// DOE Discovery request, the index of the protocol to report.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PciDoeDiscoveryRequest {
+    pub index: u8,
+}
+
+impl Codec for PciDoeDiscoveryRequest {
+    fn encode(&self, bytes: &mut Writer) {
+        self.index.encode(bytes);
+        0u8.encode(bytes); // version
+        0u16.encode(bytes); // reserved
+    }
+
+    fn read(r: &mut Reader) -> Option<PciDoeDiscoveryRequest> {
+        let index = u8::read(r)?;
+        u8::read(r)?;
+        u16::read(r)?;
+        Some(PciDoeDiscoveryRequest { index })
+    }
+}
+
+/// DOE Discovery response, next_index is 0 after the last protocol.
+#[derive(Debug, Copy, Clone)]
+pub struct PciDoeDiscoveryResponse {
+    pub protocol: PciDoeProtocol,
+    pub next_index: u8,
+}
+
+impl Codec for PciDoeDiscoveryResponse {
+    fn encode(&self, bytes: &mut Writer) {
+        self.protocol.encode(bytes);
+        self.next_index.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<PciDoeDiscoveryResponse> {
+        let protocol = PciDoeProtocol::read(r)?;
+        let next_index = u8::read(r)?;
+        Some(PciDoeDiscoveryResponse {
+            protocol,
+            next_index,
+        })
+    }
+}
+
+fn encode_data_object<T: Codec>(payload: &T, buffer: &mut [u8]) -> SpdmResult<usize> {
+    if buffer.len() < 12 {
+        return spdm_result_err!(EINVAL);
+    }
+    let mut writer = Writer::init(buffer);
+    let header = PciDoeMessageHeader {
+        vendor_id: PCI_DOE_PROTOCOL_DISCOVERY.vendor_id,
+        data_object_type: PCI_DOE_PROTOCOL_DISCOVERY.data_object_type,
+        payload_length: 4,
+    };
+    header.encode(&mut writer);
+    payload.encode(&mut writer);
+    Ok(writer.used())
+}
+
+fn decode_data_object<T: Codec>(data_object: &[u8]) -> SpdmResult<T> {
+    let mut reader = Reader::init(data_object);
+    let header = PciDoeMessageHeader::read(&mut reader).ok_or_else(|| spdm_err!(EIO))?;
+    if header.protocol() != PCI_DOE_PROTOCOL_DISCOVERY {
+        return spdm_result_err!(EINVAL);
+    }
+    T::read(&mut reader).ok_or_else(|| spdm_err!(EIO))
+}
+
+/// Encode the DOE Discovery request data object for index into buffer.
+pub fn pci_doe_encode_discovery_request(index: u8, buffer: &mut [u8]) -> SpdmResult<usize> {
+    encode_data_object(&PciDoeDiscoveryRequest { index }, buffer)
+}
+
+pub fn pci_doe_decode_discovery_response(
+    data_object: &[u8],
+) -> SpdmResult<PciDoeDiscoveryResponse> {
+    decode_data_object(data_object)
+}
+
+/// Answer the DOE Discovery request data object in request with entry
+/// `index` of protocols, which the spec wants to start with DOE Discovery.
+pub fn pci_doe_discovery_respond(
+    protocols: &[PciDoeProtocol],
+    request: &[u8],
+    response: &mut [u8],
+) -> SpdmResult<usize> {
+    let discovery_request: PciDoeDiscoveryRequest = decode_data_object(request)?;
+    let index = discovery_request.index as usize;
+    let protocol = *protocols.get(index).ok_or_else(|| spdm_err!(EINVAL))?;
+    let next_index = if index + 1 < protocols.len() {
+        index as u8 + 1
+    } else {
+        0
+    };
+    encode_data_object(
+        &PciDoeDiscoveryResponse {
+            protocol,
+            next_index,
+        },
+        response,
+    )
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::header::*;
+
+    #[test]
+    fn test_case0_pci_doe_discovery() {
+        let protocols = [
+            PCI_DOE_PROTOCOL_DISCOVERY,
+            PCI_DOE_PROTOCOL_SPDM,
+            PCI_DOE_PROTOCOL_SECURED_SPDM,
+        ];
+        let request = &mut [0u8; 12];
+        let response = &mut [0u8; 12];
+
+        for (index, (expected, expected_next_index)) in [
+            (PCI_DOE_PROTOCOL_SPDM, 2),
+            (PCI_DOE_PROTOCOL_SECURED_SPDM, 0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let used = pci_doe_encode_discovery_request(index as u8 + 1, request).unwrap();
+            assert_eq!(used, 12);
+            let used = pci_doe_discovery_respond(&protocols, request, response).unwrap();
+            assert_eq!(used, 12);
+            let discovery_response = pci_doe_decode_discovery_response(response).unwrap();
+            assert_eq!(discovery_response.protocol, *expected);
+            assert_eq!(discovery_response.next_index, *expected_next_index);
+        }
+
+        pci_doe_encode_discovery_request(3, request).unwrap();
+        assert!(pci_doe_discovery_respond(&protocols, request, response).is_err());
+        assert!(pci_doe_encode_discovery_request(0, &mut [0u8; 8]).is_err());
+    }
+}
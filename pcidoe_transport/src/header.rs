@@ -5,6 +5,7 @@
 use codec::enum_builder;
 use codec::{Codec, Reader, Writer};
 use spdmlib::common::SpdmTransportEncap;
+use spdmlib::config;
 use spdmlib::error::SpdmResult;
 use spdmlib::{spdm_err, spdm_result_err};
 
@@ -12,20 +13,132 @@ enum_builder! {
     @U16
     EnumName: PciDoeVendorId;
     EnumVal{
-        PciDoeVendorIdPciSig => 0x0001
+        PciDoeVendorIdPciSig => 0x0001,
+        PciDoeVendorIdCxl => 0x1E98
     }
 }
 
-enum_builder! {
-    @U8
-    EnumName: PciDoeDataObjectType;
-    EnumVal{
-        PciDoeDataObjectTypeDoeDiscovery => 0x00,
-        PciDoeDataObjectTypeSpdm => 0x01,
-        PciDoeDataObjectTypeSecuredSpdm => 0x02
+/// Data Object Types are assigned per Vendor ID, so the same value names a
+/// PCI-SIG or a CXL data object depending on the Vendor ID next to it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PciDoeDataObjectType {
+    PciDoeDataObjectTypeDoeDiscovery,
+    PciDoeDataObjectTypeSpdm,
+    PciDoeDataObjectTypeSecuredSpdm,
+    PciDoeDataObjectTypeCxlCompliance,
+    PciDoeDataObjectTypeCxlTableAccess,
+    Unknown(u8),
+}
+
+impl PciDoeDataObjectType {
+    pub fn get_u8(&self) -> u8 {
+        match *self {
+            PciDoeDataObjectType::PciDoeDataObjectTypeDoeDiscovery => 0x00,
+            PciDoeDataObjectType::PciDoeDataObjectTypeSpdm => 0x01,
+            PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm => 0x02,
+            PciDoeDataObjectType::PciDoeDataObjectTypeCxlCompliance => 0x00,
+            PciDoeDataObjectType::PciDoeDataObjectTypeCxlTableAccess => 0x02,
+            PciDoeDataObjectType::Unknown(x) => x,
+        }
+    }
+
+    /// Data Object Type value of vendor_id.
+    pub fn from_u8(vendor_id: PciDoeVendorId, value: u8) -> Self {
+        match (vendor_id, value) {
+            (PciDoeVendorId::PciDoeVendorIdPciSig, 0x00) => {
+                PciDoeDataObjectType::PciDoeDataObjectTypeDoeDiscovery
+            }
+            (PciDoeVendorId::PciDoeVendorIdPciSig, 0x01) => {
+                PciDoeDataObjectType::PciDoeDataObjectTypeSpdm
+            }
+            (PciDoeVendorId::PciDoeVendorIdPciSig, 0x02) => {
+                PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm
+            }
+            (PciDoeVendorId::PciDoeVendorIdCxl, 0x00) => {
+                PciDoeDataObjectType::PciDoeDataObjectTypeCxlCompliance
+            }
+            (PciDoeVendorId::PciDoeVendorIdCxl, 0x02) => {
+                PciDoeDataObjectType::PciDoeDataObjectTypeCxlTableAccess
+            }
+            (_, x) => PciDoeDataObjectType::Unknown(x),
+        }
+    }
+}
+
+impl Default for PciDoeDataObjectType {
+    fn default() -> PciDoeDataObjectType {
+        PciDoeDataObjectType::Unknown(0u8)
+    }
+}
+
+/// A DOE protocol, the Vendor ID and Data Object Type pair data objects
+/// are routed by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PciDoeProtocol {
+    pub vendor_id: PciDoeVendorId,
+    pub data_object_type: PciDoeDataObjectType,
+}
+
+pub const PCI_DOE_PROTOCOL_DISCOVERY: PciDoeProtocol = PciDoeProtocol {
+    vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
+    data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeDoeDiscovery,
+};
+pub const PCI_DOE_PROTOCOL_SPDM: PciDoeProtocol = PciDoeProtocol {
+    vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
+    data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeSpdm,
+};
+pub const PCI_DOE_PROTOCOL_SECURED_SPDM: PciDoeProtocol = PciDoeProtocol {
+    vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
+    data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm,
+};
+
+// CXL CMA (Component Measurement and Authentication) runs SPDM over the
+// PCI-SIG SPDM and secured SPDM data objects above, next to the CXL
+// protocols of the same mailbox.
+pub const PCI_DOE_PROTOCOL_CXL_COMPLIANCE: PciDoeProtocol = PciDoeProtocol {
+    vendor_id: PciDoeVendorId::PciDoeVendorIdCxl,
+    data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeCxlCompliance,
+};
+pub const PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS: PciDoeProtocol = PciDoeProtocol {
+    vendor_id: PciDoeVendorId::PciDoeVendorIdCxl,
+    data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeCxlTableAccess,
+};
+
+/// DOE protocols of the CMA mailbox of a CXL device, in DOE Discovery order.
+pub const PCI_DOE_CXL_CMA_PROTOCOLS: [PciDoeProtocol; 4] = [
+    PCI_DOE_PROTOCOL_DISCOVERY,
+    PCI_DOE_PROTOCOL_SPDM,
+    PCI_DOE_PROTOCOL_SECURED_SPDM,
+    PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS,
+];
+
+impl Codec for PciDoeProtocol {
+    fn encode(&self, bytes: &mut Writer) {
+        self.vendor_id.encode(bytes);
+        self.data_object_type.get_u8().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<PciDoeProtocol> {
+        let vendor_id = PciDoeVendorId::read(r)?;
+        let data_object_type = PciDoeDataObjectType::from_u8(vendor_id, u8::read(r)?);
+        Some(PciDoeProtocol {
+            vendor_id,
+            data_object_type,
+        })
     }
 }
 
+/// Protocol of the data object in data_object, None if the header is
+/// truncated.
+pub fn pci_doe_get_protocol(data_object: &[u8]) -> Option<PciDoeProtocol> {
+    let header = PciDoeMessageHeader::read(&mut Reader::init(data_object))?;
+    Some(header.protocol())
+}
+
+pub const PCI_DOE_MESSAGE_HEADER_SIZE: usize = 8;
+// the Length field counts up to 2^18 DW, header included
+pub const PCI_DOE_MAX_DATA_OBJECT_SIZE: usize = 0x40000 * 4;
+
 #[derive(Debug, Copy, Clone, Default)]
 pub struct PciDoeMessageHeader {
     pub vendor_id: PciDoeVendorId,
@@ -33,10 +146,19 @@ pub struct PciDoeMessageHeader {
     pub payload_length: u32, // in bytes
 }
 
+impl PciDoeMessageHeader {
+    pub fn protocol(&self) -> PciDoeProtocol {
+        PciDoeProtocol {
+            vendor_id: self.vendor_id,
+            data_object_type: self.data_object_type,
+        }
+    }
+}
+
 impl Codec for PciDoeMessageHeader {
     fn encode(&self, bytes: &mut Writer) {
         self.vendor_id.encode(bytes);
-        self.data_object_type.encode(bytes);
+        self.data_object_type.get_u8().encode(bytes);
         0u8.encode(bytes);
         let mut length = (self.payload_length + 8) >> 2;
         if length > 0x100000 {
@@ -55,7 +177,7 @@ impl Codec for PciDoeMessageHeader {
 
     fn read(r: &mut Reader) -> Option<PciDoeMessageHeader> {
         let vendor_id = PciDoeVendorId::read(r)?;
-        let data_object_type = PciDoeDataObjectType::read(r)?;
+        let data_object_type = PciDoeDataObjectType::from_u8(vendor_id, u8::read(r)?);
         u8::read(r)?;
         let mut length = u32::read(r)?;
         if length == 0 {
@@ -84,8 +206,43 @@ impl SpdmTransportEncap for PciDoeTransportEncap {
         secured_message: bool,
     ) -> SpdmResult<usize> {
         let payload_len = spdm_buffer.len();
-        let aligned_payload_len = (payload_len + 3) / 4 * 4;
-        let mut writer = Writer::init(&mut *transport_buffer);
+        if transport_buffer.len() < PCI_DOE_MESSAGE_HEADER_SIZE + payload_len {
+            return spdm_result_err!(EINVAL);
+        }
+        transport_buffer[PCI_DOE_MESSAGE_HEADER_SIZE..(PCI_DOE_MESSAGE_HEADER_SIZE + payload_len)]
+            .copy_from_slice(spdm_buffer);
+        self.encap_in_place(transport_buffer, payload_len, secured_message)
+    }
+
+    fn decap(
+        &mut self,
+        transport_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        let (offset, payload_size, secured_message) = self.decap_in_place(transport_buffer)?;
+        if spdm_buffer.len() < payload_size {
+            return spdm_result_err!(EINVAL);
+        }
+        spdm_buffer[..payload_size]
+            .copy_from_slice(&transport_buffer[offset..(offset + payload_size)]);
+        Ok((payload_size, secured_message))
+    }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        Some(PCI_DOE_MESSAGE_HEADER_SIZE)
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        let aligned_payload_len = (spdm_size + 3) / 4 * 4;
+        if transport_buffer.len() < PCI_DOE_MESSAGE_HEADER_SIZE + aligned_payload_len {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut writer = Writer::init(&mut transport_buffer[..PCI_DOE_MESSAGE_HEADER_SIZE]);
         let pcidoe_header = PciDoeMessageHeader {
             vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
             data_object_type: if secured_message {
@@ -96,44 +253,32 @@ impl SpdmTransportEncap for PciDoeTransportEncap {
             payload_length: aligned_payload_len as u32,
         };
         pcidoe_header.encode(&mut writer);
-        let header_size = writer.used();
-        if transport_buffer.len() < header_size + aligned_payload_len {
-            return spdm_result_err!(EINVAL);
-        }
-        transport_buffer[header_size..(header_size + payload_len)].copy_from_slice(spdm_buffer);
-        Ok(header_size + aligned_payload_len)
+        // DOE objects are DW aligned
+        transport_buffer[(PCI_DOE_MESSAGE_HEADER_SIZE + spdm_size)
+            ..(PCI_DOE_MESSAGE_HEADER_SIZE + aligned_payload_len)]
+            .fill(0);
+        Ok(PCI_DOE_MESSAGE_HEADER_SIZE + aligned_payload_len)
     }
 
-    fn decap(
-        &mut self,
-        transport_buffer: &[u8],
-        spdm_buffer: &mut [u8],
-    ) -> SpdmResult<(usize, bool)> {
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
         let mut reader = Reader::init(transport_buffer);
-        let secured_message;
-        match PciDoeMessageHeader::read(&mut reader) {
-            Some(pcidoe_header) => {
-                match pcidoe_header.vendor_id {
-                    PciDoeVendorId::PciDoeVendorIdPciSig => {}
-                    _ => return spdm_result_err!(EINVAL),
-                }
-                match pcidoe_header.data_object_type {
-                    PciDoeDataObjectType::PciDoeDataObjectTypeSpdm => secured_message = false,
-                    PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm => secured_message = true,
-                    _ => return spdm_result_err!(EINVAL),
-                }
-            }
+        let pcidoe_header = match PciDoeMessageHeader::read(&mut reader) {
+            Some(pcidoe_header) => pcidoe_header,
             None => return spdm_result_err!(EIO),
-        }
+        };
+        // discovery and vendor defined data objects are not SPDM, they are
+        // routed with pci_doe_get_protocol before they get here
+        let secured_message = match pcidoe_header.protocol() {
+            PCI_DOE_PROTOCOL_SPDM => false,
+            PCI_DOE_PROTOCOL_SECURED_SPDM => true,
+            _ => return spdm_result_err!(EINVAL),
+        };
         let header_size = reader.used();
-        let payload_size = transport_buffer.len() - header_size;
-        // TBD : check payload_size with Length field;
-        if spdm_buffer.len() < payload_size {
-            return spdm_result_err!(EINVAL);
+        let payload_size = pcidoe_header.payload_length as usize;
+        if transport_buffer.len() < header_size + payload_size {
+            return spdm_result_err!(EIO);
         }
-        let payload = &transport_buffer[header_size..];
-        spdm_buffer[..payload_size].copy_from_slice(payload);
-        Ok((payload_size, secured_message))
+        Ok((header_size, payload_size, secured_message))
     }
 
     fn encap_app(
@@ -161,6 +306,14 @@ impl SpdmTransportEncap for PciDoeTransportEncap {
     fn get_max_random_count(&mut self) -> u16 {
         0
     }
+
+    // the DW aligned payload of the largest data object a transport buffer
+    // holds
+    fn get_max_transport_size(&mut self) -> usize {
+        (config::DATA_TRANSFER_SIZE.min(PCI_DOE_MAX_DATA_OBJECT_SIZE) - PCI_DOE_MESSAGE_HEADER_SIZE)
+            / 4
+            * 4
+    }
 }
 
 #[cfg(all(test,))]
@@ -263,4 +416,81 @@ mod tests_header {
         };
         value.encode(&mut writer);
     }
+    #[test]
+    fn test_case0_encap_in_place() {
+        let mut pcidoe_transport_encap = PciDoeTransportEncap {};
+        assert_eq!(
+            pcidoe_transport_encap.get_encap_header_size(),
+            Some(PCI_DOE_MESSAGE_HEADER_SIZE)
+        );
+
+        let mut transport_buffer = [0xffu8; 32];
+        transport_buffer[8..13].copy_from_slice(&[0x12, 0x84, 0x00, 0x00, 0x55]);
+        let used = pcidoe_transport_encap
+            .encap_in_place(&mut transport_buffer, 5, true)
+            .unwrap();
+        assert_eq!(used, 16);
+        assert_eq!(
+            &transport_buffer[..16],
+            &[1, 0, 2, 0, 4, 0, 0, 0, 0x12, 0x84, 0x00, 0x00, 0x55, 0, 0, 0]
+        );
+
+        let mut copied_buffer = [0u8; 32];
+        assert_eq!(
+            pcidoe_transport_encap
+                .encap(&[0x12, 0x84, 0x00, 0x00, 0x55], &mut copied_buffer, true)
+                .unwrap(),
+            used
+        );
+        assert_eq!(&copied_buffer[..used], &transport_buffer[..used]);
+
+        assert_eq!(
+            pcidoe_transport_encap
+                .decap_in_place(&transport_buffer[..used])
+                .unwrap(),
+            (8, 8, true)
+        );
+        assert!(pcidoe_transport_encap
+            .decap_in_place(&transport_buffer[..12])
+            .is_err());
+        assert!(pcidoe_transport_encap
+            .encap_in_place(&mut transport_buffer[..15], 5, false)
+            .is_err());
+    }
+    #[test]
+    fn test_case0_max_transport_size() {
+        let mut pcidoe_transport_encap = PciDoeTransportEncap {};
+        let max_size = pcidoe_transport_encap.get_max_transport_size();
+        assert_eq!(max_size % 4, 0);
+
+        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let spdm_buffer = [0x5au8; config::DATA_TRANSFER_SIZE];
+        let used = pcidoe_transport_encap
+            .encap(&spdm_buffer[..max_size], &mut transport_buffer, false)
+            .unwrap();
+        assert_eq!(used, PCI_DOE_MESSAGE_HEADER_SIZE + max_size);
+        assert!(pcidoe_transport_encap
+            .encap(&spdm_buffer[..(max_size + 1)], &mut transport_buffer, false)
+            .is_err());
+    }
+    #[test]
+    fn test_case0_cxl_data_object_type() {
+        for (protocol, bytes) in [
+            (PCI_DOE_PROTOCOL_DISCOVERY, [0x01, 0x00, 0x00]),
+            (PCI_DOE_PROTOCOL_SECURED_SPDM, [0x01, 0x00, 0x02]),
+            (PCI_DOE_PROTOCOL_CXL_COMPLIANCE, [0x98, 0x1e, 0x00]),
+            (PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS, [0x98, 0x1e, 0x02]),
+        ] {
+            let u8_slice = &mut [0u8; 3];
+            let mut writer = Writer::init(u8_slice);
+            protocol.encode(&mut writer);
+            assert_eq!(*u8_slice, bytes);
+            let mut reader = Reader::init(&bytes);
+            assert_eq!(PciDoeProtocol::read(&mut reader), Some(protocol));
+        }
+        assert_eq!(
+            PciDoeDataObjectType::from_u8(PciDoeVendorId::PciDoeVendorIdCxl, 0x01),
+            PciDoeDataObjectType::Unknown(0x01)
+        );
+    }
 }
@@ -0,0 +1,251 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::discovery::{pci_doe_decode_discovery_response, pci_doe_encode_discovery_request};
+use crate::header::{pci_doe_get_protocol, PciDoeProtocol};
+
+pub const MAX_PCI_DOE_MAILBOX_COUNT: usize = 4;
+pub const MAX_PCI_DOE_PROTOCOL_COUNT: usize = 8;
+
+/// Access to the DOE mailboxes of a device, each one identified by the
+/// config space offset of its DOE extended capability.
+pub trait PciDoeMailboxIo {
+    fn send(&mut self, mailbox: u16, data_object: &[u8]) -> SpdmResult;
+
+    fn receive(&mut self, mailbox: u16, data_object: &mut [u8]) -> SpdmResult<usize>;
+}
+
+/// One DOE mailbox and the protocols it reported through DOE Discovery.
+#[derive(Debug, Copy, Clone)]
+pub struct PciDoeMailbox {
+    pub capability_offset: u16,
+    pub protocols: [Option<PciDoeProtocol>; MAX_PCI_DOE_PROTOCOL_COUNT],
+}
+
+impl PciDoeMailbox {
+    pub fn new(capability_offset: u16) -> Self {
+        PciDoeMailbox {
+            capability_offset,
+            protocols: [None; MAX_PCI_DOE_PROTOCOL_COUNT],
+        }
+    }
+
+    pub fn add_protocol(&mut self, protocol: PciDoeProtocol) -> SpdmResult {
+        if self.supports(protocol) {
+            return Ok(());
+        }
+        let slot = self
+            .protocols
+            .iter_mut()
+            .find(|p| p.is_none())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        *slot = Some(protocol);
+        Ok(())
+    }
+
+    pub fn supports(&self, protocol: PciDoeProtocol) -> bool {
+        self.protocols.iter().any(|p| *p == Some(protocol))
+    }
+}
+
+/// The DOE mailboxes of a device, used to pick the mailbox a data object
+/// goes to from its Vendor ID and Data Object Type.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PciDoeMailboxTable {
+    pub mailboxes: [Option<PciDoeMailbox>; MAX_PCI_DOE_MAILBOX_COUNT],
+}
+
+impl PciDoeMailboxTable {
+    pub fn add_mailbox(&mut self, mailbox: PciDoeMailbox) -> SpdmResult {
+        let slot = self
+            .mailboxes
+            .iter_mut()
+            .find(|m| m.map_or(true, |m| m.capability_offset == mailbox.capability_offset))
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        *slot = Some(mailbox);
+        Ok(())
+    }
+
+    /// Enumerate the protocols of the mailbox at capability_offset with DOE
+    /// Discovery and add it to the table.
+    pub fn discover(&mut self, io: &mut dyn PciDoeMailboxIo, capability_offset: u16) -> SpdmResult {
+        let mut mailbox = PciDoeMailbox::new(capability_offset);
+        let mut index = 0u8;
+        loop {
+            let mut buffer = [0u8; 12];
+            let used = pci_doe_encode_discovery_request(index, &mut buffer)?;
+            io.send(capability_offset, &buffer[..used])?;
+            let used = io.receive(capability_offset, &mut buffer)?;
+            let response = pci_doe_decode_discovery_response(&buffer[..used])?;
+            mailbox.add_protocol(response.protocol)?;
+
+            if response.next_index == 0 {
+                break;
+            }
+            // indexes only move forward, a device going back would loop
+            if response.next_index <= index {
+                return spdm_result_err!(EFAULT);
+            }
+            index = response.next_index;
+        }
+        self.add_mailbox(mailbox)
+    }
+
+    /// Capability offset of the first mailbox serving protocol.
+    pub fn find_mailbox(&self, protocol: PciDoeProtocol) -> Option<u16> {
+        self.mailboxes
+            .iter()
+            .flatten()
+            .find(|m| m.supports(protocol))
+            .map(|m| m.capability_offset)
+    }
+
+    /// Mailbox the data object in data_object has to be written to, SPDM
+    /// and secured SPDM objects may be served by different mailboxes.
+    pub fn route(&self, data_object: &[u8]) -> SpdmResult<u16> {
+        let protocol = pci_doe_get_protocol(data_object).ok_or_else(|| spdm_err!(EIO))?;
+        self.find_mailbox(protocol).ok_or_else(|| spdm_err!(ENOENT))
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::discovery::pci_doe_discovery_respond;
+    use crate::header::*;
+    use codec::{Codec, Writer};
+    use spdmlib::common::SpdmTransportEncap;
+
+    struct FakeDevice {
+        mailboxes: [(u16, &'static [PciDoeProtocol]); 2],
+        response: [u8; 12],
+        response_size: usize,
+    }
+
+    impl PciDoeMailboxIo for FakeDevice {
+        fn send(&mut self, mailbox: u16, data_object: &[u8]) -> SpdmResult {
+            let (_, protocols) = self
+                .mailboxes
+                .iter()
+                .find(|(offset, _)| *offset == mailbox)
+                .ok_or_else(|| spdm_err!(EIO))?;
+            self.response_size =
+                pci_doe_discovery_respond(protocols, data_object, &mut self.response)?;
+            Ok(())
+        }
+
+        fn receive(&mut self, _mailbox: u16, data_object: &mut [u8]) -> SpdmResult<usize> {
+            data_object[..self.response_size].copy_from_slice(&self.response[..self.response_size]);
+            Ok(self.response_size)
+        }
+    }
+
+    #[test]
+    fn test_case0_pci_doe_mailbox_table() {
+        let vendor_protocol = PciDoeProtocol {
+            vendor_id: PciDoeVendorId::Unknown(0x8086),
+            data_object_type: PciDoeDataObjectType::Unknown(0x10),
+        };
+        let mut device = FakeDevice {
+            mailboxes: [
+                (
+                    0x150,
+                    &[
+                        PCI_DOE_PROTOCOL_DISCOVERY,
+                        PCI_DOE_PROTOCOL_SPDM,
+                        PCI_DOE_PROTOCOL_SECURED_SPDM,
+                    ],
+                ),
+                (0x180, &[PCI_DOE_PROTOCOL_DISCOVERY]),
+            ],
+            response: [0u8; 12],
+            response_size: 0,
+        };
+
+        let mut table = PciDoeMailboxTable::default();
+        table.discover(&mut device, 0x150).unwrap();
+        table.discover(&mut device, 0x180).unwrap();
+        assert!(table.discover(&mut device, 0x1c0).is_err());
+        let mut mailbox = PciDoeMailbox::new(0x1c0);
+        mailbox.add_protocol(vendor_protocol).unwrap();
+        table.add_mailbox(mailbox).unwrap();
+
+        assert_eq!(table.find_mailbox(PCI_DOE_PROTOCOL_SPDM), Some(0x150));
+        assert_eq!(
+            table.find_mailbox(PCI_DOE_PROTOCOL_SECURED_SPDM),
+            Some(0x150)
+        );
+        assert_eq!(table.find_mailbox(vendor_protocol), Some(0x1c0));
+
+        let data_object = &mut [0u8; 12];
+        let mut writer = Writer::init(data_object);
+        PciDoeMessageHeader {
+            vendor_id: vendor_protocol.vendor_id,
+            data_object_type: vendor_protocol.data_object_type,
+            payload_length: 4,
+        }
+        .encode(&mut writer);
+        assert_eq!(table.route(data_object).unwrap(), 0x1c0);
+
+        let transport_encap = &mut PciDoeTransportEncap {};
+        let used = transport_encap
+            .encap(&[0x12, 0x84, 0, 0], data_object, true)
+            .unwrap();
+        assert_eq!(table.route(&data_object[..used]).unwrap(), 0x150);
+        assert!(table.route(&data_object[..4]).is_err());
+    }
+
+    #[test]
+    fn test_case1_pci_doe_mailbox_table() {
+        let mut device = FakeDevice {
+            mailboxes: [
+                (0x200, &PCI_DOE_CXL_CMA_PROTOCOLS),
+                (
+                    0x240,
+                    &[PCI_DOE_PROTOCOL_DISCOVERY, PCI_DOE_PROTOCOL_CXL_COMPLIANCE],
+                ),
+            ],
+            response: [0u8; 12],
+            response_size: 0,
+        };
+
+        let mut table = PciDoeMailboxTable::default();
+        table.discover(&mut device, 0x200).unwrap();
+        table.discover(&mut device, 0x240).unwrap();
+        assert_eq!(table.find_mailbox(PCI_DOE_PROTOCOL_SPDM), Some(0x200));
+        assert_eq!(
+            table.find_mailbox(PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS),
+            Some(0x200)
+        );
+        assert_eq!(
+            table.find_mailbox(PCI_DOE_PROTOCOL_CXL_COMPLIANCE),
+            Some(0x240)
+        );
+        assert_ne!(
+            PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS,
+            PCI_DOE_PROTOCOL_SECURED_SPDM
+        );
+
+        let data_object = &mut [0u8; 12];
+        let mut writer = Writer::init(data_object);
+        PciDoeMessageHeader {
+            vendor_id: PciDoeVendorId::PciDoeVendorIdCxl,
+            data_object_type: PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS.data_object_type,
+            payload_length: 4,
+        }
+        .encode(&mut writer);
+        assert_eq!(
+            pci_doe_get_protocol(data_object),
+            Some(PCI_DOE_PROTOCOL_CXL_TABLE_ACCESS)
+        );
+        assert_eq!(table.route(data_object).unwrap(), 0x200);
+        // not SPDM, even though the Data Object Type is the secured SPDM one
+        assert!(PciDoeTransportEncap {}
+            .decap(data_object, &mut [0u8; 4])
+            .is_err());
+    }
+}
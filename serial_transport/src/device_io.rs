@@ -0,0 +1,173 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use spdmlib::common::SpdmDeviceIo;
+use spdmlib::config;
+use spdmlib::error::SpdmResult;
+
+use crate::framing::*;
+
+pub const SERIAL_MAX_FRAME_SIZE: usize = serial_max_frame_size(config::DATA_TRANSFER_SIZE);
+
+/// A UART, or anything else moving bytes without message boundaries.
+pub trait SerialPort {
+    /// Write all of data.
+    fn write(&mut self, data: &[u8]) -> SpdmResult;
+
+    /// Next received byte, None once timeout (in us) expired.
+    fn read_byte(&mut self, timeout: usize) -> Option<u8>;
+
+    /// Drop the bytes received but not read yet.
+    fn discard_input(&mut self) -> SpdmResult {
+        Ok(())
+    }
+}
+
+/// SpdmDeviceIo sending every transport message as one frame on a
+/// SerialPort. Pair it with the transport encap the other end uses, both
+/// ends frame the same way.
+///
+/// Frames are also preceded by a delimiter, so a receiver coming up in the
+/// middle of a frame or after line noise only drops the garbage. A frame
+/// failing its CRC is dropped too, and the receive goes on with the next
+/// one.
+pub struct SerialDeviceIo<P: SerialPort> {
+    port: P,
+    frame: [u8; SERIAL_MAX_FRAME_SIZE],
+    // bytes of the frame being received, beyond the frame size once it
+    // overflowed
+    receive_used: usize,
+}
+
+impl<P: SerialPort> SerialDeviceIo<P> {
+    pub fn new(port: P) -> Self {
+        SerialDeviceIo {
+            port,
+            frame: [0u8; SERIAL_MAX_FRAME_SIZE],
+            receive_used: 0,
+        }
+    }
+
+    pub fn port(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    pub fn into_port(self) -> P {
+        self.port
+    }
+}
+
+impl<P: SerialPort> SpdmDeviceIo for SerialDeviceIo<P> {
+    fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+        let used = serial_frame_encode(buffer, &mut self.frame)?;
+        self.port.write(&[SERIAL_FRAME_DELIMITER])?;
+        self.port.write(&self.frame[..used])
+    }
+
+    fn receive(&mut self, buffer: &mut [u8], timeout: usize) -> Result<usize, usize> {
+        loop {
+            let byte = self.port.read_byte(timeout).ok_or(0usize)?;
+            if byte != SERIAL_FRAME_DELIMITER {
+                if self.receive_used < self.frame.len() {
+                    self.frame[self.receive_used] = byte;
+                }
+                self.receive_used = self.receive_used.saturating_add(1);
+                continue;
+            }
+
+            let used = self.receive_used;
+            self.receive_used = 0;
+            if used == 0 || used > self.frame.len() {
+                continue;
+            }
+            let size = match serial_frame_decode(&mut self.frame[..used]) {
+                Ok(size) => size,
+                Err(_) => continue,
+            };
+            if size > buffer.len() {
+                return Err(0);
+            }
+            buffer[..size].copy_from_slice(&self.frame[..size]);
+            return Ok(size);
+        }
+    }
+
+    fn flush_all(&mut self) -> SpdmResult {
+        self.receive_used = 0;
+        self.port.discard_input()
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    /// The bytes written are read back.
+    struct LoopbackPort {
+        bytes: [u8; 2 * SERIAL_MAX_FRAME_SIZE],
+        written: usize,
+        read: usize,
+    }
+
+    impl LoopbackPort {
+        fn new() -> Self {
+            LoopbackPort {
+                bytes: [0u8; 2 * SERIAL_MAX_FRAME_SIZE],
+                written: 0,
+                read: 0,
+            }
+        }
+    }
+
+    impl SerialPort for LoopbackPort {
+        fn write(&mut self, data: &[u8]) -> SpdmResult {
+            self.bytes[self.written..self.written + data.len()].copy_from_slice(data);
+            self.written += data.len();
+            Ok(())
+        }
+
+        fn read_byte(&mut self, _timeout: usize) -> Option<u8> {
+            if self.read == self.written {
+                return None;
+            }
+            self.read += 1;
+            Some(self.bytes[self.read - 1])
+        }
+
+        fn discard_input(&mut self) -> SpdmResult {
+            self.read = self.written;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case0_serial_device_io() {
+        let mut device_io = SerialDeviceIo::new(LoopbackPort::new());
+        let mut buffer = [0u8; 16];
+
+        // line noise before the first frame
+        assert!(device_io.port().write(&[0x55, 0xAA]).is_ok());
+        assert!(device_io.send(&[1, 0, 2]).is_ok());
+        assert!(device_io.send(&[]).is_ok());
+        assert_eq!(device_io.receive(&mut buffer, 0), Ok(3));
+        assert_eq!(&buffer[..3], &[1, 0, 2]);
+        assert_eq!(device_io.receive(&mut buffer, 0), Ok(0));
+        assert_eq!(device_io.receive(&mut buffer, 0), Err(0));
+
+        // a corrupted frame is dropped
+        assert!(device_io.send(&[3, 4]).is_ok());
+        let port = device_io.port();
+        port.bytes[port.written - 3] ^= 0x01;
+        assert!(device_io.send(&[5, 6]).is_ok());
+        assert_eq!(device_io.receive(&mut buffer, 0), Ok(2));
+        assert_eq!(&buffer[..2], &[5, 6]);
+
+        assert!(device_io.send(&[7u8; 17]).is_ok());
+        assert_eq!(device_io.receive(&mut buffer, 0), Err(0));
+
+        assert!(device_io.send(&[8]).is_ok());
+        assert!(device_io.flush_all().is_ok());
+        assert_eq!(device_io.receive(&mut buffer, 0), Err(0));
+    }
+}
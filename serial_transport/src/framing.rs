@@ -0,0 +1,197 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Framing of transport messages on a byte stream.
+//!
+//! A frame is the COBS encoding of the message followed by its CRC-32
+//! (IEEE 802.3, little endian), terminated by a zero byte. COBS removes
+//! every zero byte from the encoded data, so a receiver finds the frame
+//! boundaries by looking for the delimiter only, and resynchronizes on the
+//! next delimiter after noise or a lost byte.
+
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+pub const SERIAL_FRAME_DELIMITER: u8 = 0x00;
+pub const SERIAL_CRC_SIZE: usize = 4;
+
+/// Data bytes after which COBS starts a new block.
+const COBS_MAX_BLOCK_SIZE: usize = 254;
+
+/// Frame size for a message of message_size bytes, delimiter included.
+pub const fn serial_max_frame_size(message_size: usize) -> usize {
+    let data_size = message_size + SERIAL_CRC_SIZE;
+    // one code byte per block, the last block may be empty
+    data_size + data_size / COBS_MAX_BLOCK_SIZE + 1 + 1
+}
+
+/// CRC-32 of IEEE 802.3, bitwise to keep the device side small.
+pub fn serial_crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+struct CobsWriter<'a> {
+    frame: &'a mut [u8],
+    code_index: usize,
+    used: usize,
+}
+
+impl<'a> CobsWriter<'a> {
+    fn new(frame: &'a mut [u8]) -> Self {
+        CobsWriter {
+            frame,
+            code_index: 0,
+            used: 1,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Option<()> {
+        if byte != 0 {
+            *self.frame.get_mut(self.used)? = byte;
+            self.used += 1;
+            if self.used - self.code_index <= COBS_MAX_BLOCK_SIZE {
+                return Some(());
+            }
+        }
+        self.end_block()?;
+        self.code_index = self.used;
+        self.used += 1;
+        Some(())
+    }
+
+    fn end_block(&mut self) -> Option<()> {
+        *self.frame.get_mut(self.code_index)? = (self.used - self.code_index) as u8;
+        Some(())
+    }
+
+    /// Size of the encoded data.
+    fn finish(mut self) -> Option<usize> {
+        self.end_block()?;
+        Some(self.used)
+    }
+}
+
+/// Decode COBS data in place, returns the decoded size.
+fn cobs_decode(data: &mut [u8]) -> Option<usize> {
+    let mut read = 0;
+    let mut write = 0;
+    while read < data.len() {
+        let code = data[read] as usize;
+        if code == 0 {
+            return None;
+        }
+        let start = read + 1;
+        let end = read + code;
+        if end > data.len() || data[start..end].contains(&0) {
+            return None;
+        }
+        data.copy_within(start..end, write);
+        write += code - 1;
+        read = end;
+        // a block shorter than the maximum stood for a zero byte
+        if code <= COBS_MAX_BLOCK_SIZE && read < data.len() {
+            data[write] = 0;
+            write += 1;
+        }
+    }
+    Some(write)
+}
+
+/// Encode message as one frame, delimiter included, returns the frame size.
+pub fn serial_frame_encode(message: &[u8], frame: &mut [u8]) -> SpdmResult<usize> {
+    let crc = serial_crc32(message).to_le_bytes();
+    let mut writer = CobsWriter::new(frame);
+    for byte in message.iter().chain(crc.iter()) {
+        if writer.push(*byte).is_none() {
+            return spdm_result_err!(ENOMEM);
+        }
+    }
+    let used = match writer.finish() {
+        Some(used) if used < frame.len() => used,
+        _ => return spdm_result_err!(ENOMEM),
+    };
+    frame[used] = SERIAL_FRAME_DELIMITER;
+    Ok(used + 1)
+}
+
+/// Decode a frame received without its delimiter in place. The message is
+/// left at the start of frame, returns its size.
+pub fn serial_frame_decode(frame: &mut [u8]) -> SpdmResult<usize> {
+    let size = match cobs_decode(frame) {
+        Some(size) if size >= SERIAL_CRC_SIZE => size - SERIAL_CRC_SIZE,
+        _ => return spdm_result_err!(EINVAL),
+    };
+    let mut crc = [0u8; SERIAL_CRC_SIZE];
+    crc.copy_from_slice(&frame[size..size + SERIAL_CRC_SIZE]);
+    if u32::from_le_bytes(crc) != serial_crc32(&frame[..size]) {
+        return spdm_result_err!(EIO);
+    }
+    Ok(size)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_serial_crc32() {
+        assert_eq!(serial_crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(serial_crc32(&[]), 0);
+    }
+
+    #[test]
+    fn test_case0_serial_frame_encode() {
+        let mut message = [0u8; 600];
+        for (i, byte) in message.iter_mut().enumerate() {
+            *byte = (i % 300) as u8;
+        }
+        let mut frame = [0u8; serial_max_frame_size(600)];
+
+        // zero runs, exactly one full block and a block split
+        for size in [0, 1, 2, 250, 254, 255, 256, 300, 600] {
+            let message = &message[..size];
+            let used = serial_frame_encode(message, &mut frame).unwrap();
+            assert!(used <= serial_max_frame_size(size));
+            assert_eq!(frame[used - 1], SERIAL_FRAME_DELIMITER);
+            assert!(!frame[..used - 1].contains(&SERIAL_FRAME_DELIMITER));
+
+            let decoded = serial_frame_decode(&mut frame[..used - 1]).unwrap();
+            assert_eq!(&frame[..decoded], message);
+        }
+
+        let message = [0x11u8; 254];
+        let mut frame = [0u8; serial_max_frame_size(254)];
+        assert!(serial_frame_encode(&message, &mut frame).is_ok());
+        assert!(serial_frame_encode(&message, &mut frame[..20]).is_err());
+    }
+
+    #[test]
+    fn test_case1_serial_frame_decode() {
+        let mut frame = [0u8; serial_max_frame_size(8)];
+        let used = serial_frame_encode(&[1, 0, 2, 0, 3], &mut frame).unwrap();
+
+        let mut corrupted = frame;
+        corrupted[2] ^= 0x40;
+        assert!(serial_frame_decode(&mut corrupted[..used - 1]).is_err());
+        // truncated
+        let mut truncated = frame;
+        assert!(serial_frame_decode(&mut truncated[..used - 3]).is_err());
+        // a delimiter inside the frame
+        let mut split = frame;
+        split[3] = SERIAL_FRAME_DELIMITER;
+        assert!(serial_frame_decode(&mut split[..used - 1]).is_err());
+        assert!(serial_frame_decode(&mut []).is_err());
+
+        assert_eq!(serial_frame_decode(&mut frame[..used - 1]).unwrap(), 5);
+        assert_eq!(&frame[..5], &[1, 0, 2, 0, 3]);
+    }
+}
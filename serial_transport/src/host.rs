@@ -0,0 +1,95 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Host end of a serial link, over anything doing std::io::Read + Write:
+//! a tty opened in raw mode, a serialport crate port, a TCP stream to a
+//! UART bridge.
+//!
+//! std::io::Read has no timeout, the wait for a byte is bounded by the read
+//! timeout of the port itself (VTIME of a tty, serialport set_timeout), a
+//! read failing with TimedOut or WouldBlock being a receive timeout.
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+
+use spdmlib::error::SpdmResult;
+use spdmlib::spdm_err;
+
+use crate::device_io::{SerialDeviceIo, SerialPort};
+
+pub struct StdSerialPort<T: Read + Write> {
+    reader: BufReader<T>,
+}
+
+impl<T: Read + Write> StdSerialPort<T> {
+    pub fn new(port: T) -> Self {
+        StdSerialPort {
+            reader: BufReader::new(port),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.reader.get_mut()
+    }
+}
+
+impl<T: Read + Write> SerialPort for StdSerialPort<T> {
+    fn write(&mut self, data: &[u8]) -> SpdmResult {
+        let port = self.reader.get_mut();
+        port.write_all(data).map_err(|_| spdm_err!(EIO))?;
+        port.flush().map_err(|_| spdm_err!(EIO))
+    }
+
+    fn read_byte(&mut self, _timeout: usize) -> Option<u8> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(bytes) => {
+                    let byte = bytes[0];
+                    self.reader.consume(1);
+                    return Some(byte);
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn discard_input(&mut self) -> SpdmResult {
+        // what the OS received stays there, std has no portable flush of it
+        let buffered = self.reader.buffer().len();
+        self.reader.consume(buffered);
+        Ok(())
+    }
+}
+
+/// SpdmDeviceIo of the host end.
+pub type StdSerialDeviceIo<T> = SerialDeviceIo<StdSerialPort<T>>;
+
+impl<T: Read + Write> SerialDeviceIo<StdSerialPort<T>> {
+    pub fn new_std(port: T) -> Self {
+        SerialDeviceIo::new(StdSerialPort::new(port))
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use spdmlib::common::SpdmDeviceIo;
+    use std::io::Cursor;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_case0_std_serial_device_io() {
+        let mut device_io = StdSerialDeviceIo::new_std(Cursor::new(Vec::new()));
+        assert!(device_io.send(&[1, 2, 0, 3]).is_ok());
+        assert!(device_io.send(&[4]).is_ok());
+        device_io.port().get_mut().set_position(0);
+
+        let mut buffer = [0u8; 8];
+        assert_eq!(device_io.receive(&mut buffer, 0), Ok(4));
+        assert_eq!(&buffer[..4], &[1, 2, 0, 3]);
+        assert_eq!(device_io.receive(&mut buffer, 0), Ok(1));
+        assert_eq!(device_io.receive(&mut buffer, 0), Err(0));
+    }
+}
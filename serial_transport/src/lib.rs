@@ -0,0 +1,20 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+#![forbid(unsafe_code)]
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+mod framing;
+pub use framing::*;
+
+mod device_io;
+pub use device_io::*;
+
+#[cfg(feature = "std")]
+mod host;
+#[cfg(feature = "std")]
+pub use host::*;
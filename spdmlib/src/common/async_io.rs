@@ -0,0 +1,250 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Executor agnostic device IO for AsyncRequesterContext and
+//! AsyncResponderContext.
+//!
+//! The SPDM state machines stay synchronous: an async context parks the
+//! transport message the sync code sends in SpdmDeferredIo, and serves the
+//! sync receive from the transport message it awaited, so only the device
+//! IO is polled.
+
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use crate::common::{SpdmDeviceIo, SpdmTransportEncap};
+use crate::config;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+#[cfg(feature = "downcast")]
+use core::any::Any;
+
+/// Poll based SpdmDeviceIo. An implementation returns Poll::Pending when
+/// the device is not ready and wakes cx once it is, e.g. from a tokio
+/// AsyncRead/AsyncWrite or an interrupt handler.
+pub trait SpdmAsyncDeviceIo {
+    fn poll_send(&mut self, cx: &mut Context<'_>, buffer: &[u8]) -> Poll<SpdmResult>;
+
+    fn poll_receive(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffer: &mut [u8],
+        timeout: usize,
+    ) -> Poll<Result<usize, usize>>;
+
+    fn poll_flush_all(&mut self, cx: &mut Context<'_>) -> Poll<SpdmResult>;
+
+    /// Wait us microseconds, before RESPOND_IF_READY. Polled until ready,
+    /// the default does not wait.
+    fn poll_sleep(&mut self, _cx: &mut Context<'_>, _us: usize) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+impl<'b> dyn SpdmAsyncDeviceIo + 'b {
+    pub async fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+        poll_fn(|cx| self.poll_send(cx, buffer)).await
+    }
+
+    pub async fn receive(&mut self, buffer: &mut [u8], timeout: usize) -> Result<usize, usize> {
+        poll_fn(|cx| self.poll_receive(cx, buffer, timeout)).await
+    }
+
+    pub async fn flush_all(&mut self) -> SpdmResult {
+        poll_fn(|cx| self.poll_flush_all(cx)).await
+    }
+
+    pub async fn sleep(&mut self, us: usize) {
+        poll_fn(|cx| self.poll_sleep(cx, us)).await
+    }
+}
+
+/// Transport messages exchanged between the sync SpdmContext code and an
+/// async context, one in each direction.
+pub(crate) struct SpdmDeferredIo {
+    send_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    send_used: Option<usize>,
+    receive_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    receive_used: Option<usize>,
+}
+
+impl SpdmDeferredIo {
+    pub(crate) fn new() -> Box<Self> {
+        Box::new(SpdmDeferredIo {
+            send_buffer: [0u8; config::DATA_TRANSFER_SIZE],
+            send_used: None,
+            receive_buffer: [0u8; config::DATA_TRANSFER_SIZE],
+            receive_used: None,
+        })
+    }
+
+    pub(crate) fn put_send(&mut self, transport_message: &[u8]) -> SpdmResult {
+        if self.send_used.is_some() {
+            return spdm_result_err!(EBUSY);
+        }
+        if transport_message.len() > self.send_buffer.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        self.send_buffer[..transport_message.len()].copy_from_slice(transport_message);
+        self.send_used = Some(transport_message.len());
+        Ok(())
+    }
+
+    pub(crate) fn take_send(&mut self) -> Option<&[u8]> {
+        let used = self.send_used.take()?;
+        Some(&self.send_buffer[..used])
+    }
+
+    pub(crate) fn put_received(&mut self, transport_message: &[u8]) -> SpdmResult {
+        if transport_message.len() > self.receive_buffer.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        self.receive_buffer[..transport_message.len()].copy_from_slice(transport_message);
+        self.receive_used = Some(transport_message.len());
+        Ok(())
+    }
+
+    pub(crate) fn take_received(&mut self, transport_buffer: &mut [u8]) -> Result<usize, usize> {
+        let used = self.receive_used.take().ok_or(0usize)?;
+        if transport_buffer.len() < used {
+            return Err(0);
+        }
+        transport_buffer[..used].copy_from_slice(&self.receive_buffer[..used]);
+        Ok(used)
+    }
+}
+
+/// SpdmDeviceIo of the SpdmContext inside an async context. SpdmContext
+/// goes through SpdmDeferredIo instead, so it is never called.
+pub(crate) struct SpdmDeferredDeviceIo;
+
+impl SpdmDeferredDeviceIo {
+    pub(crate) fn leaked<'a>() -> &'a mut dyn SpdmDeviceIo {
+        // zero sized, leaking it does not allocate
+        Box::leak(Box::new(SpdmDeferredDeviceIo))
+    }
+}
+
+impl SpdmDeviceIo for SpdmDeferredDeviceIo {
+    fn send(&mut self, _buffer: &[u8]) -> SpdmResult {
+        spdm_result_err!(EIO)
+    }
+
+    fn receive(&mut self, _buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+        Err(0)
+    }
+
+    fn flush_all(&mut self) -> SpdmResult {
+        Ok(())
+    }
+
+    #[cfg(feature = "downcast")]
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Async counterpart of SpdmContext::send_transport_message.
+pub async fn spdm_async_send_transport_message(
+    transport_encap: &mut dyn SpdmTransportEncap,
+    device_io: &mut dyn SpdmAsyncDeviceIo,
+    transport_message: &[u8],
+) -> SpdmResult {
+    if transport_encap.get_max_transport_unit().is_none() {
+        return device_io.send(transport_message).await;
+    }
+
+    let mut offset = 0;
+    loop {
+        let mut fragment_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let (consumed, used) = transport_encap.encap_fragment(
+            &transport_message[offset..],
+            offset == 0,
+            &mut fragment_buffer,
+        )?;
+        device_io.send(&fragment_buffer[..used]).await?;
+        offset += consumed;
+        if offset >= transport_message.len() {
+            return Ok(());
+        }
+        if consumed == 0 {
+            return spdm_result_err!(EIO);
+        }
+    }
+}
+
+/// Async counterpart of SpdmContext::receive_transport_message.
+pub async fn spdm_async_receive_transport_message(
+    transport_encap: &mut dyn SpdmTransportEncap,
+    device_io: &mut dyn SpdmAsyncDeviceIo,
+    transport_buffer: &mut [u8],
+    timeout: usize,
+) -> Result<usize, usize> {
+    if transport_encap.get_max_transport_unit().is_none() {
+        return device_io.receive(transport_buffer, timeout).await;
+    }
+
+    let mut used = 0;
+    loop {
+        let mut fragment_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let size = device_io.receive(&mut fragment_buffer, timeout).await?;
+        let (payload_size, last) = transport_encap
+            .decap_fragment(
+                &fragment_buffer[..size],
+                used == 0,
+                &mut transport_buffer[used..],
+            )
+            .map_err(|_| used)?;
+        used += payload_size;
+        if last {
+            return Ok(used);
+        }
+    }
+}
+
+impl<'a> crate::common::SpdmContext<'a> {
+    /// Send the transport message the sync code parked, if any.
+    pub(crate) async fn flush_deferred_send(
+        &mut self,
+        device_io: &mut dyn SpdmAsyncDeviceIo,
+    ) -> SpdmResult {
+        let deferred_io = self.deferred_io.as_mut().ok_or_else(|| spdm_err!(EINVAL))?;
+        match deferred_io.take_send() {
+            Some(transport_message) => {
+                spdm_async_send_transport_message(
+                    &mut *self.transport_encap,
+                    device_io,
+                    transport_message,
+                )
+                .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Await one transport message into transport_buffer and hand it to
+    /// the next sync receive.
+    pub(crate) async fn receive_deferred(
+        &mut self,
+        device_io: &mut dyn SpdmAsyncDeviceIo,
+        transport_buffer: &mut [u8],
+        timeout: usize,
+    ) -> Result<usize, usize> {
+        let used = spdm_async_receive_transport_message(
+            &mut *self.transport_encap,
+            device_io,
+            transport_buffer,
+            timeout,
+        )
+        .await?;
+        let deferred_io = self.deferred_io.as_mut().ok_or(used)?;
+        deferred_io
+            .put_received(&transport_buffer[..used])
+            .map_err(|_| used)?;
+        Ok(used)
+    }
+}
@@ -0,0 +1,459 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::config;
+use crate::crypto;
+use crate::crypto::SpdmCertValidityTime;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::protocol::{SpdmBaseHashAlgo, SpdmCertChainData, SpdmDigestStruct};
+
+// Length (2 bytes) and Reserved (2 bytes) ahead of RootHash
+pub const SPDM_CERT_CHAIN_HEADER_SIZE: usize = 4;
+
+pub const SPDM_MAX_TRUST_ANCHOR_NUMBER: usize = 4;
+
+/// A root of trust the requester anchors peer certificate chains to.
+#[derive(Debug, Clone)]
+pub enum SpdmTrustAnchor {
+    /// DER encoded trusted root certificate. The peer chain may carry it as
+    /// its first certificate or start at a certificate it issued.
+    RootCert(SpdmCertChainData),
+    /// Pinned hash of the root certificate, compared with the chain RootHash.
+    RootHash(SpdmDigestStruct),
+}
+
+// KeyPurposeId OIDs, DER content without tag and length
+
+/// id-kp-serverAuth, 1.3.6.1.5.5.7.3.1
+pub const EKU_SERVER_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
+/// id-kp-clientAuth, 1.3.6.1.5.5.7.3.2
+pub const EKU_CLIENT_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02];
+/// id-DMTF-eku-responder-auth, 1.3.6.1.4.1.412.274.3
+pub const EKU_SPDM_RESPONDER_AUTH: &[u8] =
+    &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0x1c, 0x82, 0x12, 0x03];
+/// id-DMTF-eku-requester-auth, 1.3.6.1.4.1.412.274.4
+pub const EKU_SPDM_REQUESTER_AUTH: &[u8] =
+    &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0x1c, 0x82, 0x12, 0x04];
+
+static RESPONDER_CERT_DEFAULT_EKUS: &[&[u8]] = &[EKU_SPDM_RESPONDER_AUTH, EKU_SERVER_AUTH];
+static REQUESTER_CERT_DEFAULT_EKUS: &[&[u8]] = &[EKU_SPDM_REQUESTER_AUTH, EKU_CLIENT_AUTH];
+
+/// Extended key usage a certificate chain must allow to pass verification.
+/// A leaf without the EKU extension allows any usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmCertEkuPolicy {
+    /// The SPDM auth OID of the certificate role, or the TLS server/client
+    /// auth OID of certificates issued before the SPDM OIDs.
+    RoleDefault,
+    /// Any one of these KeyPurposeId OIDs.
+    Require(&'static [&'static [u8]]),
+    /// Legacy device certificates, EKU is not enforced.
+    NotEnforced,
+}
+
+impl Default for SpdmCertEkuPolicy {
+    fn default() -> Self {
+        SpdmCertEkuPolicy::RoleDefault
+    }
+}
+
+impl SpdmCertEkuPolicy {
+    /// EKU set handed to the certificate verifier, None when not enforced.
+    pub fn required_ekus(&self, is_requester_cert: bool) -> Option<&'static [&'static [u8]]> {
+        match self {
+            SpdmCertEkuPolicy::RoleDefault if is_requester_cert => {
+                Some(REQUESTER_CERT_DEFAULT_EKUS)
+            }
+            SpdmCertEkuPolicy::RoleDefault => Some(RESPONDER_CERT_DEFAULT_EKUS),
+            SpdmCertEkuPolicy::Require(ekus) => Some(ekus),
+            SpdmCertEkuPolicy::NotEnforced => None,
+        }
+    }
+}
+
+/// Build an SPDM certificate chain (Length, Reserved, RootHash, Certificates)
+/// from DER certificates ordered root first.
+pub fn spdm_build_cert_chain(
+    base_hash_algo: SpdmBaseHashAlgo,
+    certs: &[&[u8]],
+) -> SpdmResult<SpdmCertChainData> {
+    let root_cert = certs.first().ok_or_else(|| spdm_err!(EINVAL))?;
+    spdm_build_cert_chain_without_root(base_hash_algo, root_cert, certs)
+}
+
+/// Build an SPDM certificate chain that leaves out its root: RootHash is the
+/// hash of root_cert and certs start at a certificate root_cert issued.
+pub fn spdm_build_cert_chain_without_root(
+    base_hash_algo: SpdmBaseHashAlgo,
+    root_cert: &[u8],
+    certs: &[&[u8]],
+) -> SpdmResult<SpdmCertChainData> {
+    if certs.is_empty() {
+        return spdm_result_err!(EINVAL);
+    }
+    let root_hash =
+        crypto::hash::hash_all(base_hash_algo, root_cert).ok_or_else(|| spdm_err!(Crypto))?;
+
+    let mut cert_chain = SpdmCertChainData {
+        data_size: 0,
+        data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+    };
+    let mut offset = SPDM_CERT_CHAIN_HEADER_SIZE;
+    for data in core::iter::once(root_hash.as_ref()).chain(certs.iter().copied()) {
+        if offset + data.len() > config::MAX_SPDM_CERT_CHAIN_DATA_SIZE {
+            return spdm_result_err!(ENOMEM);
+        }
+        cert_chain.data[offset..(offset + data.len())].copy_from_slice(data);
+        offset += data.len();
+    }
+
+    cert_chain.data_size = offset as u16;
+    cert_chain.data[0..2].copy_from_slice(&cert_chain.data_size.to_le_bytes());
+    Ok(cert_chain)
+}
+
+/// Build an SPDM certificate chain from concatenated DER certificates,
+/// root first, such as SpdmProvisionInfo::my_cert_chain_data.
+pub fn spdm_build_cert_chain_from_der(
+    base_hash_algo: SpdmBaseHashAlgo,
+    cert_chain_der: &[u8],
+) -> SpdmResult<SpdmCertChainData> {
+    let (root_cert_begin, root_cert_end) =
+        crypto::cert_operation::get_cert_from_cert_chain(cert_chain_der, 0)?;
+    if root_cert_begin != 0 {
+        return spdm_result_err!(EINVAL);
+    }
+    spdm_build_cert_chain(
+        base_hash_algo,
+        &[
+            &cert_chain_der[..root_cert_end],
+            &cert_chain_der[root_cert_end..],
+        ],
+    )
+}
+
+/// Split a received SPDM certificate chain into its RootHash and DER
+/// certificates. Fails if the Length field disagrees with the chain. The
+/// first certificate is the root, or one the root issued if the peer left
+/// the root out.
+pub fn spdm_split_cert_chain(
+    base_hash_algo: SpdmBaseHashAlgo,
+    cert_chain: &[u8],
+) -> SpdmResult<(&[u8], &[u8])> {
+    let hash_size = base_hash_algo.get_size() as usize;
+    if cert_chain.len() <= SPDM_CERT_CHAIN_HEADER_SIZE + hash_size {
+        return spdm_result_err!(Codec);
+    }
+    if u16::from_le_bytes([cert_chain[0], cert_chain[1]]) as usize != cert_chain.len() {
+        error!("cert_chain length - fail!\n");
+        return spdm_result_err!(Codec);
+    }
+
+    let root_hash =
+        &cert_chain[SPDM_CERT_CHAIN_HEADER_SIZE..(SPDM_CERT_CHAIN_HEADER_SIZE + hash_size)];
+    let cert_chain_der = &cert_chain[(SPDM_CERT_CHAIN_HEADER_SIZE + hash_size)..];
+    Ok((root_hash, cert_chain_der))
+}
+
+/// Same as spdm_split_cert_chain for a chain that carries its root: also
+/// fails if the RootHash is not the hash of the first certificate.
+pub fn spdm_parse_cert_chain(
+    base_hash_algo: SpdmBaseHashAlgo,
+    cert_chain: &[u8],
+) -> SpdmResult<(&[u8], &[u8])> {
+    let (root_hash, cert_chain_der) = spdm_split_cert_chain(base_hash_algo, cert_chain)?;
+
+    let (root_cert_begin, root_cert_end) =
+        crypto::cert_operation::get_cert_from_cert_chain(cert_chain_der, 0)?;
+    let root_cert_hash = crypto::hash::hash_all(
+        base_hash_algo,
+        &cert_chain_der[root_cert_begin..root_cert_end],
+    )
+    .ok_or_else(|| spdm_err!(Crypto))?;
+    if root_cert_hash.as_ref() != root_hash {
+        error!("root_hash - fail!\n");
+        return spdm_result_err!(VerificationFailed);
+    }
+
+    Ok((root_hash, cert_chain_der))
+}
+
+/// Check the RootHash of a received SPDM certificate chain against the
+/// digest of a provisioned trust anchor.
+pub fn spdm_verify_cert_chain_root_hash(
+    base_hash_algo: SpdmBaseHashAlgo,
+    cert_chain: &[u8],
+    trust_anchor_digest: &SpdmDigestStruct,
+) -> SpdmResult {
+    let (root_hash, _) = spdm_parse_cert_chain(base_hash_algo, cert_chain)?;
+    if root_hash != trust_anchor_digest.as_ref() {
+        error!("root_hash does not match the trust anchor!\n");
+        return spdm_result_err!(VerificationFailed);
+    }
+    Ok(())
+}
+
+/// Verify a received SPDM certificate chain against the trust store. The
+/// chain passes if any trust anchor matches and the certificates verify up
+/// to it; the root supplied by the peer is never trusted on its own. A chain
+/// that leaves its root out can only be anchored to a RootCert.
+pub fn spdm_verify_cert_chain_with_trust_anchors(
+    base_hash_algo: SpdmBaseHashAlgo,
+    cert_chain: &[u8],
+    trust_anchors: &[Option<SpdmTrustAnchor>],
+    required_ekus: Option<&[&'static [u8]]>,
+    validity_time: SpdmCertValidityTime,
+) -> SpdmResult {
+    let (root_hash, cert_chain_der) = spdm_split_cert_chain(base_hash_algo, cert_chain)?;
+    let (_, first_cert_end) = crypto::cert_operation::get_cert_from_cert_chain(cert_chain_der, 0)?;
+    let first_cert = &cert_chain_der[..first_cert_end];
+    let first_cert_hash =
+        crypto::hash::hash_all(base_hash_algo, first_cert).ok_or_else(|| spdm_err!(Crypto))?;
+    let has_root = first_cert_hash.as_ref() == root_hash;
+
+    for trust_anchor in trust_anchors.iter().flatten() {
+        let verified = match trust_anchor {
+            SpdmTrustAnchor::RootHash(digest) => {
+                has_root
+                    && root_hash == digest.as_ref()
+                    && crypto::cert_operation::verify_cert_chain(
+                        cert_chain_der,
+                        required_ekus,
+                        validity_time,
+                    )
+                    .is_ok()
+            }
+            SpdmTrustAnchor::RootCert(anchor_cert)
+                if crypto::hash::hash_all(base_hash_algo, anchor_cert.as_ref())
+                    .map_or(true, |anchor_hash| anchor_hash.as_ref() != root_hash) =>
+            {
+                false
+            }
+            SpdmTrustAnchor::RootCert(_) if has_root => crypto::cert_operation::verify_cert_chain(
+                cert_chain_der,
+                required_ekus,
+                validity_time,
+            )
+            .is_ok(),
+            SpdmTrustAnchor::RootCert(anchor_cert) => {
+                // the peer chain starts below the trusted root
+                let mut anchored_chain =
+                    Vec::with_capacity(anchor_cert.as_ref().len() + cert_chain_der.len());
+                anchored_chain.extend_from_slice(anchor_cert.as_ref());
+                anchored_chain.extend_from_slice(cert_chain_der);
+                crypto::cert_operation::verify_cert_chain(
+                    &anchored_chain,
+                    required_ekus,
+                    validity_time,
+                )
+                .is_ok()
+            }
+        };
+        if verified {
+            return Ok(());
+        }
+    }
+
+    error!("cert_chain is not anchored to any trust anchor!\n");
+    spdm_result_err!(VerificationFailed)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_build_parse_cert_chain() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let ca_cert = &include_bytes!("../../../test_key/EcP384/ca.cert.der")[..];
+        let inter_cert = &include_bytes!("../../../test_key/EcP384/inter.cert.der")[..];
+        let leaf_cert = &include_bytes!("../../../test_key/EcP384/end_responder.cert.der")[..];
+        let bundle = &include_bytes!("../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+
+        let cert_chain =
+            spdm_build_cert_chain(base_hash_algo, &[ca_cert, inter_cert, leaf_cert]).unwrap();
+        let cert_chain_from_der = spdm_build_cert_chain_from_der(base_hash_algo, bundle).unwrap();
+        assert_eq!(cert_chain.as_ref(), cert_chain_from_der.as_ref());
+        assert_eq!(
+            cert_chain.data_size as usize,
+            SPDM_CERT_CHAIN_HEADER_SIZE + 48 + bundle.len()
+        );
+
+        let (root_hash, cert_chain_der) =
+            spdm_parse_cert_chain(base_hash_algo, cert_chain.as_ref()).unwrap();
+        let ca_hash = crypto::hash::hash_all(base_hash_algo, ca_cert).unwrap();
+        assert_eq!(root_hash, ca_hash.as_ref());
+        assert_eq!(cert_chain_der, bundle);
+
+        assert!(
+            spdm_verify_cert_chain_root_hash(base_hash_algo, cert_chain.as_ref(), &ca_hash).is_ok()
+        );
+        let inter_hash = crypto::hash::hash_all(base_hash_algo, inter_cert).unwrap();
+        assert!(
+            spdm_verify_cert_chain_root_hash(base_hash_algo, cert_chain.as_ref(), &inter_hash)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_case1_parse_cert_chain() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let bundle = &include_bytes!("../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        let cert_chain = spdm_build_cert_chain_from_der(base_hash_algo, bundle).unwrap();
+        let size = cert_chain.data_size as usize;
+
+        // truncated chain
+        assert!(spdm_parse_cert_chain(base_hash_algo, &cert_chain.data[..(size - 1)]).is_err());
+        assert!(spdm_parse_cert_chain(base_hash_algo, &cert_chain.data[..52]).is_err());
+
+        // tampered root hash
+        let mut tampered = cert_chain.clone();
+        tampered.data[SPDM_CERT_CHAIN_HEADER_SIZE] ^= 0xff;
+        assert!(spdm_parse_cert_chain(base_hash_algo, &tampered.data[..size]).is_err());
+
+        assert!(spdm_build_cert_chain(base_hash_algo, &[]).is_err());
+        assert!(spdm_build_cert_chain(base_hash_algo, &[&[0u8; 4096][..]]).is_err());
+    }
+
+    #[test]
+    fn test_case2_verify_cert_chain_with_trust_anchors() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let required_ekus = SpdmCertEkuPolicy::default().required_ekus(false);
+        let ca_cert = &include_bytes!("../../../test_key/EcP384/ca.cert.der")[..];
+        let inter_cert = &include_bytes!("../../../test_key/EcP384/inter.cert.der")[..];
+        let leaf_cert = &include_bytes!("../../../test_key/EcP384/end_responder.cert.der")[..];
+
+        let mut ca_anchor = SpdmCertChainData {
+            data_size: ca_cert.len() as u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        };
+        ca_anchor.data[..ca_cert.len()].copy_from_slice(ca_cert);
+        let ca_hash = crypto::hash::hash_all(base_hash_algo, ca_cert).unwrap();
+        let inter_hash = crypto::hash::hash_all(base_hash_algo, inter_cert).unwrap();
+
+        let full_chain =
+            spdm_build_cert_chain(base_hash_algo, &[ca_cert, inter_cert, leaf_cert]).unwrap();
+        let partial_chain =
+            spdm_build_cert_chain_without_root(base_hash_algo, ca_cert, &[inter_cert, leaf_cert])
+                .unwrap();
+        // a chain whose RootHash names its own first certificate
+        let self_rooted_chain =
+            spdm_build_cert_chain(base_hash_algo, &[inter_cert, leaf_cert]).unwrap();
+
+        let by_cert = [Some(SpdmTrustAnchor::RootCert(ca_anchor)), None];
+        let by_hash = [None, Some(SpdmTrustAnchor::RootHash(ca_hash))];
+        let by_inter_hash = [Some(SpdmTrustAnchor::RootHash(inter_hash))];
+
+        for trust_anchors in [&by_cert[..], &by_hash[..]] {
+            assert!(spdm_verify_cert_chain_with_trust_anchors(
+                base_hash_algo,
+                full_chain.as_ref(),
+                trust_anchors,
+                required_ekus,
+                SpdmCertValidityTime::PlatformClock
+            )
+            .is_ok());
+        }
+        // a chain without the root is anchored through the trusted root cert
+        assert!(spdm_verify_cert_chain_with_trust_anchors(
+            base_hash_algo,
+            partial_chain.as_ref(),
+            &by_cert,
+            required_ekus,
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
+        assert!(spdm_verify_cert_chain_with_trust_anchors(
+            base_hash_algo,
+            partial_chain.as_ref(),
+            &by_hash,
+            required_ekus,
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+        assert!(spdm_verify_cert_chain_with_trust_anchors(
+            base_hash_algo,
+            self_rooted_chain.as_ref(),
+            &by_cert,
+            required_ekus,
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+        assert!(spdm_parse_cert_chain(base_hash_algo, partial_chain.as_ref()).is_err());
+        let (root_hash, cert_chain_der) =
+            spdm_split_cert_chain(base_hash_algo, partial_chain.as_ref()).unwrap();
+        assert_eq!(
+            root_hash,
+            crypto::hash::hash_all(base_hash_algo, ca_cert)
+                .unwrap()
+                .as_ref()
+        );
+        assert_eq!(cert_chain_der.len(), inter_cert.len() + leaf_cert.len());
+        assert!(spdm_verify_cert_chain_with_trust_anchors(
+            base_hash_algo,
+            full_chain.as_ref(),
+            &by_inter_hash,
+            required_ekus,
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+        assert!(spdm_verify_cert_chain_with_trust_anchors(
+            base_hash_algo,
+            full_chain.as_ref(),
+            &[],
+            required_ekus,
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_case3_cert_eku_policy() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let ca_cert = &include_bytes!("../../../test_key/EcP384/ca.cert.der")[..];
+        let ca_hash = crypto::hash::hash_all(base_hash_algo, ca_cert).unwrap();
+        let trust_anchors = [Some(SpdmTrustAnchor::RootHash(ca_hash))];
+        let cert_chain =
+            &include_bytes!("../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        let cert_chain = spdm_build_cert_chain_from_der(base_hash_algo, cert_chain).unwrap();
+
+        // the test leaf certificates carry the TLS server and client auth OIDs
+        for (policy, is_requester_cert, verified) in [
+            (SpdmCertEkuPolicy::RoleDefault, false, true),
+            (SpdmCertEkuPolicy::RoleDefault, true, true),
+            (
+                SpdmCertEkuPolicy::Require(&[EKU_SPDM_RESPONDER_AUTH]),
+                false,
+                false,
+            ),
+            (
+                SpdmCertEkuPolicy::Require(&[EKU_SPDM_RESPONDER_AUTH, EKU_SERVER_AUTH]),
+                false,
+                true,
+            ),
+            (SpdmCertEkuPolicy::NotEnforced, false, true),
+        ] {
+            assert_eq!(
+                spdm_verify_cert_chain_with_trust_anchors(
+                    base_hash_algo,
+                    cert_chain.as_ref(),
+                    &trust_anchors,
+                    policy.required_ekus(is_requester_cert),
+                    SpdmCertValidityTime::PlatformClock,
+                )
+                .is_ok(),
+                verified
+            );
+        }
+
+        assert_eq!(
+            SpdmCertEkuPolicy::RoleDefault.required_ekus(true),
+            Some(&[EKU_SPDM_REQUESTER_AUTH, EKU_CLIENT_AUTH][..])
+        );
+        assert_eq!(SpdmCertEkuPolicy::NotEnforced.required_ekus(true), None);
+    }
+}
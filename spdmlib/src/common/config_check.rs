@@ -0,0 +1,266 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Consistency of the capabilities, algorithms and secrets of an endpoint,
+//! checked by RequesterBuilder and ResponderBuilder before any message is
+//! sent, rather than failing the first request that needs them.
+
+use super::*;
+use crate::error::SpdmError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmConfigError {
+    NoVersion,
+    NoBaseHashAlgo,
+    NoBaseAsymAlgo,
+    // KEY_EX_CAP or PSK_CAP without ENCRYPT_CAP or MAC_CAP
+    SessionWithoutEncryptOrMac,
+    // KEY_EX_CAP or PSK_CAP without AEAD or key schedule, or KEY_EX_CAP
+    // without DHE
+    NoSessionAlgo,
+    NoMeasurementAlgo,
+    NoReqAsymAlgo,
+    PskWithoutPskStore,
+    NoCertChain,
+    NoSigningKey,
+}
+
+impl SpdmConfigError {
+    pub fn as_str(&self) -> &'static str {
+        use SpdmConfigError::*;
+        match *self {
+            NoVersion => "no SPDM version offered",
+            NoBaseHashAlgo => "no base hash algorithm offered",
+            NoBaseAsymAlgo => "no base asymmetric algorithm offered",
+            SessionWithoutEncryptOrMac => "sessions without ENCRYPT_CAP or MAC_CAP",
+            NoSessionAlgo => "sessions without DHE, AEAD or key schedule algorithm",
+            NoMeasurementAlgo => "measurements without measurement hash or specification",
+            NoReqAsymAlgo => "mutual authentication without requester asymmetric algorithm",
+            PskWithoutPskStore => "PSK_CAP without PSK store",
+            NoCertChain => "no certificate chain provisioned",
+            NoSigningKey => "signatures without signing key",
+        }
+    }
+}
+
+impl From<SpdmConfigError> for SpdmError {
+    fn from(error: SpdmConfigError) -> Self {
+        spdm_err!(EINVAL, error.as_str())
+    }
+}
+
+impl SpdmConfigInfo {
+    fn check_algorithms(&self) -> Result<(), SpdmConfigError> {
+        if !self
+            .spdm_version
+            .iter()
+            .any(|version| !matches!(version, SpdmVersion::Unknown(_)))
+        {
+            return Err(SpdmConfigError::NoVersion);
+        }
+        if self.base_hash_algo.is_empty() {
+            return Err(SpdmConfigError::NoBaseHashAlgo);
+        }
+        if self.base_asym_algo.is_empty() {
+            return Err(SpdmConfigError::NoBaseAsymAlgo);
+        }
+        Ok(())
+    }
+
+    fn check_sessions(
+        &self,
+        key_ex: bool,
+        psk: bool,
+        encrypt_or_mac: bool,
+    ) -> Result<(), SpdmConfigError> {
+        if !key_ex && !psk {
+            return Ok(());
+        }
+        if !encrypt_or_mac {
+            return Err(SpdmConfigError::SessionWithoutEncryptOrMac);
+        }
+        if self.aead_algo.is_empty()
+            || self.key_schedule_algo.is_empty()
+            || (key_ex && self.dhe_algo.is_empty())
+        {
+            return Err(SpdmConfigError::NoSessionAlgo);
+        }
+        Ok(())
+    }
+
+    /// Capabilities and algorithms of a requester against its provisioning.
+    pub fn check_requester(
+        &self,
+        provision_info: &SpdmProvisionInfo,
+        has_psk_store: bool,
+    ) -> Result<(), SpdmConfigError> {
+        self.check_algorithms()?;
+        let capabilities = self.req_capabilities;
+        let psk = capabilities.intersects(SpdmRequestCapabilityFlags::PSK_CAP_MASK);
+        self.check_sessions(
+            capabilities.contains(SpdmRequestCapabilityFlags::KEY_EX_CAP),
+            psk,
+            capabilities.intersects(
+                SpdmRequestCapabilityFlags::ENCRYPT_CAP | SpdmRequestCapabilityFlags::MAC_CAP,
+            ),
+        )?;
+        if psk && !has_psk_store {
+            return Err(SpdmConfigError::PskWithoutPskStore);
+        }
+        if capabilities.contains(SpdmRequestCapabilityFlags::MUT_AUTH_CAP) {
+            if self.req_asym_algo.is_empty() {
+                return Err(SpdmConfigError::NoReqAsymAlgo);
+            }
+            if !capabilities.contains(SpdmRequestCapabilityFlags::PUB_KEY_ID_CAP)
+                && provision_info
+                    .my_cert_chain_data
+                    .iter()
+                    .all(Option::is_none)
+            {
+                return Err(SpdmConfigError::NoCertChain);
+            }
+            if !crypto::asym_sign::is_available() {
+                return Err(SpdmConfigError::NoSigningKey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Capabilities and algorithms of a responder against its provisioning.
+    pub fn check_responder(
+        &self,
+        provision_info: &SpdmProvisionInfo,
+        has_psk_store: bool,
+    ) -> Result<(), SpdmConfigError> {
+        self.check_algorithms()?;
+        let capabilities = self.rsp_capabilities;
+        let key_ex = capabilities.contains(SpdmResponseCapabilityFlags::KEY_EX_CAP);
+        let psk = capabilities.intersects(SpdmResponseCapabilityFlags::PSK_CAP_MASK);
+        self.check_sessions(
+            key_ex,
+            psk,
+            capabilities.intersects(
+                SpdmResponseCapabilityFlags::ENCRYPT_CAP | SpdmResponseCapabilityFlags::MAC_CAP,
+            ),
+        )?;
+        if psk && !has_psk_store {
+            return Err(SpdmConfigError::PskWithoutPskStore);
+        }
+        if capabilities.intersects(SpdmResponseCapabilityFlags::MEAS_CAP_MASK)
+            && (self.measurement_hash_algo.is_empty() || self.measurement_specification.is_empty())
+        {
+            return Err(SpdmConfigError::NoMeasurementAlgo);
+        }
+        if (!self.mut_auth_requested.is_empty() || self.basic_mut_auth_requested)
+            && self.req_asym_algo.is_empty()
+        {
+            return Err(SpdmConfigError::NoReqAsymAlgo);
+        }
+
+        let signing = key_ex
+            || capabilities.intersects(
+                SpdmResponseCapabilityFlags::CHAL_CAP | SpdmResponseCapabilityFlags::MEAS_CAP_SIG,
+            );
+        if (signing || capabilities.contains(SpdmResponseCapabilityFlags::CERT_CAP))
+            && !capabilities.contains(SpdmResponseCapabilityFlags::PUB_KEY_ID_CAP)
+            && provision_info
+                .my_cert_chain_data
+                .iter()
+                .all(Option::is_none)
+        {
+            return Err(SpdmConfigError::NoCertChain);
+        }
+        if signing && !crypto::asym_sign::is_available() {
+            return Err(SpdmConfigError::NoSigningKey);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::testlib::*;
+
+    #[test]
+    fn test_case0_check_responder() {
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let (config_info, provision_info) = create_info();
+        assert_eq!(config_info.check_responder(&provision_info, true), Ok(()));
+        assert_eq!(
+            config_info.check_responder(&provision_info, false),
+            Err(SpdmConfigError::PskWithoutPskStore)
+        );
+
+        let (mut config_info, mut provision_info) = create_info();
+        config_info.rsp_capabilities -= SpdmResponseCapabilityFlags::PSK_CAP_MASK;
+        assert_eq!(config_info.check_responder(&provision_info, false), Ok(()));
+        provision_info.my_cert_chain_data = Default::default();
+        assert_eq!(
+            config_info.check_responder(&provision_info, false),
+            Err(SpdmConfigError::NoCertChain)
+        );
+
+        let (mut config_info, provision_info) = create_info();
+        config_info.spdm_version = Default::default();
+        assert_eq!(
+            config_info.check_responder(&provision_info, true),
+            Err(SpdmConfigError::NoVersion)
+        );
+
+        let (mut config_info, provision_info) = create_info();
+        config_info.rsp_capabilities -=
+            SpdmResponseCapabilityFlags::ENCRYPT_CAP | SpdmResponseCapabilityFlags::MAC_CAP;
+        assert_eq!(
+            config_info.check_responder(&provision_info, true),
+            Err(SpdmConfigError::SessionWithoutEncryptOrMac)
+        );
+
+        let (mut config_info, provision_info) = create_info();
+        config_info.dhe_algo = SpdmDheAlgo::empty();
+        assert_eq!(
+            config_info.check_responder(&provision_info, true),
+            Err(SpdmConfigError::NoSessionAlgo)
+        );
+
+        let (mut config_info, provision_info) = create_info();
+        config_info.measurement_hash_algo = SpdmMeasurementHashAlgo::empty();
+        assert_eq!(
+            config_info.check_responder(&provision_info, true),
+            Err(SpdmConfigError::NoMeasurementAlgo)
+        );
+    }
+
+    #[test]
+    fn test_case0_check_requester() {
+        let (mut config_info, provision_info) = create_info();
+        config_info.req_capabilities = SpdmRequestCapabilityFlags::CERT_CAP
+            | SpdmRequestCapabilityFlags::CHAL_CAP
+            | SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::PSK_CAP;
+        assert_eq!(config_info.check_requester(&provision_info, true), Ok(()));
+        assert_eq!(
+            config_info.check_requester(&provision_info, false),
+            Err(SpdmConfigError::PskWithoutPskStore)
+        );
+
+        config_info.req_capabilities |= SpdmRequestCapabilityFlags::MUT_AUTH_CAP;
+        config_info.req_asym_algo = SpdmReqAsymAlgo::empty();
+        assert_eq!(
+            config_info.check_requester(&provision_info, true),
+            Err(SpdmConfigError::NoReqAsymAlgo)
+        );
+
+        config_info.base_hash_algo = SpdmBaseHashAlgo::empty();
+        assert_eq!(
+            config_info.check_requester(&provision_info, true),
+            Err(SpdmConfigError::NoBaseHashAlgo)
+        );
+        let error: SpdmError = SpdmConfigError::NoBaseHashAlgo.into();
+        assert_eq!(error.num(), crate::error::SpdmErrorNum::EINVAL);
+    }
+}
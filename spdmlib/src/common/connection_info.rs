@@ -0,0 +1,89 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! What the connection negotiated and the sessions running over it, for a
+//! management layer to report the attestation parameters of a peer without
+//! reaching into SpdmContext.
+
+use super::*;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// A session handshaking or established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpdmSessionInfo {
+    pub session_id: u32,
+    pub session_state: SpdmSessionState,
+    pub use_psk: bool,
+    pub slot_id: u8, // responder cert chain slot, INVALID_SLOT for a PSK session
+}
+
+#[derive(Debug, Clone)]
+pub struct SpdmConnectionInfo {
+    pub spdm_version: SpdmVersion, // Unknown(0) until VERSION
+    pub req_capabilities: SpdmRequestCapabilityFlags,
+    pub rsp_capabilities: SpdmResponseCapabilityFlags,
+    pub req_ct_exponent: u8,
+    pub rsp_ct_exponent: u8,
+    pub measurement_specification: SpdmMeasurementSpecification,
+    pub measurement_hash_algo: SpdmMeasurementHashAlgo,
+    pub base_hash_algo: SpdmBaseHashAlgo,
+    pub base_asym_algo: SpdmBaseAsymAlgo,
+    pub dhe_algo: SpdmDheAlgo,
+    pub aead_algo: SpdmAeadAlgo,
+    pub req_asym_algo: SpdmReqAsymAlgo,
+    pub key_schedule_algo: SpdmKeyScheduleAlgo,
+    pub opaque_data_support: SpdmOpaqueSupport,
+    pub peer_slot_mask: u8, // slots the peer reported in DIGESTS
+    // digest of each peer cert chain retrieved, indexed by slot
+    pub peer_cert_chain_hash: [Option<SpdmDigestStruct>; SPDM_MAX_SLOT_NUMBER],
+    pub sessions: Vec<SpdmSessionInfo>,
+}
+
+impl<'a> SpdmContext<'a> {
+    pub(crate) fn get_connection_info(&self) -> SpdmConnectionInfo {
+        let negotiate_info = &self.negotiate_info;
+        let mut peer_cert_chain_hash: [Option<SpdmDigestStruct>; SPDM_MAX_SLOT_NUMBER] =
+            Default::default();
+        for (slot_id, hash) in peer_cert_chain_hash.iter_mut().enumerate() {
+            if self.peer_info.peer_cert_chain_hash[slot_id].is_some()
+                || self.peer_info.peer_cert_chain[slot_id].is_some()
+            {
+                *hash = self.get_certchain_hash_req(slot_id as u8, false);
+            }
+        }
+        let sessions = self
+            .session
+            .iter()
+            .filter(|session| session.get_session_id() != 0)
+            .map(|session| SpdmSessionInfo {
+                session_id: session.get_session_id(),
+                session_state: session.get_session_state(),
+                use_psk: session.get_use_psk(),
+                slot_id: session.slot_id,
+            })
+            .collect();
+
+        SpdmConnectionInfo {
+            spdm_version: negotiate_info.spdm_version_sel,
+            req_capabilities: negotiate_info.req_capabilities_sel,
+            rsp_capabilities: negotiate_info.rsp_capabilities_sel,
+            req_ct_exponent: negotiate_info.req_ct_exponent_sel,
+            rsp_ct_exponent: negotiate_info.rsp_ct_exponent_sel,
+            measurement_specification: negotiate_info.measurement_specification_sel,
+            measurement_hash_algo: negotiate_info.measurement_hash_sel,
+            base_hash_algo: negotiate_info.base_hash_sel,
+            base_asym_algo: negotiate_info.base_asym_sel,
+            dhe_algo: negotiate_info.dhe_sel,
+            aead_algo: negotiate_info.aead_sel,
+            req_asym_algo: negotiate_info.req_asym_sel,
+            key_schedule_algo: negotiate_info.key_schedule_sel,
+            opaque_data_support: negotiate_info.opaque_data_support,
+            peer_slot_mask: self.peer_info.peer_slot_mask,
+            peer_cert_chain_hash,
+            sessions,
+        }
+    }
+}
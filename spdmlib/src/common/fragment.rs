@@ -0,0 +1,275 @@
+// Copyright (c) 2020 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSequenceNumberEndian;
+use crate::common::SpdmTransportEncap;
+use crate::error::{spdm_result_err, SpdmResult};
+
+// Each unit starts with one flag byte: start of message, end of message and
+// a 2-bit sequence number to catch lost or reordered units.
+pub const SPDM_FRAGMENT_HEADER_SIZE: usize = 1;
+const SPDM_FRAGMENT_SOM: u8 = 0x80;
+const SPDM_FRAGMENT_EOM: u8 = 0x40;
+const SPDM_FRAGMENT_SEQ_MASK: u8 = 0x03;
+
+/// Fallback fragmentation for a medium whose unit is smaller than a transport
+/// message, used when CHUNK_CAP is not negotiated. Wraps an existing transport
+/// encap (MCTP, PCI DOE, ...) and leaves its message format untouched; both
+/// endpoints need the same wrapper. Once the CAPABILITIES exchange negotiated
+/// CHUNK_CAP, large SPDM messages go in CHUNK_SEND and CHUNK_GET chunks and
+/// transport messages go to the wrapped encap unframed.
+pub struct SpdmFragmentTransportEncap<'a> {
+    transport_encap: &'a mut dyn SpdmTransportEncap,
+    max_transport_unit: usize,
+    send_sequence: u8,
+    receive_sequence: u8,
+    chunk_cap_negotiated: bool,
+}
+
+impl<'a> SpdmFragmentTransportEncap<'a> {
+    pub fn new(transport_encap: &'a mut dyn SpdmTransportEncap, max_transport_unit: usize) -> Self {
+        SpdmFragmentTransportEncap {
+            transport_encap,
+            max_transport_unit,
+            send_sequence: 0,
+            receive_sequence: 0,
+            chunk_cap_negotiated: false,
+        }
+    }
+}
+
+impl<'a> SpdmTransportEncap for SpdmFragmentTransportEncap<'a> {
+    fn encap(
+        &mut self,
+        spdm_buffer: &[u8],
+        transport_buffer: &mut [u8],
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        self.transport_encap
+            .encap(spdm_buffer, transport_buffer, secured_message)
+    }
+
+    fn decap(
+        &mut self,
+        transport_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        self.transport_encap.decap(transport_buffer, spdm_buffer)
+    }
+
+    fn encap_app(
+        &mut self,
+        spdm_buffer: &[u8],
+        app_buffer: &mut [u8],
+        is_app_message: bool,
+    ) -> SpdmResult<usize> {
+        self.transport_encap
+            .encap_app(spdm_buffer, app_buffer, is_app_message)
+    }
+
+    fn decap_app(
+        &mut self,
+        app_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        self.transport_encap.decap_app(app_buffer, spdm_buffer)
+    }
+
+    fn get_sequence_number_count(&mut self) -> u8 {
+        self.transport_encap.get_sequence_number_count()
+    }
+
+    fn get_max_random_count(&mut self) -> u16 {
+        self.transport_encap.get_max_random_count()
+    }
+
+    fn get_sequence_number_endian(&mut self) -> SpdmSequenceNumberEndian {
+        self.transport_encap.get_sequence_number_endian()
+    }
+
+    fn get_replay_window_size(&mut self) -> u8 {
+        self.transport_encap.get_replay_window_size()
+    }
+
+    fn get_max_sequence_number(&mut self) -> u64 {
+        self.transport_encap.get_max_sequence_number()
+    }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        self.transport_encap.get_encap_header_size()
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        self.transport_encap
+            .encap_in_place(transport_buffer, spdm_size, secured_message)
+    }
+
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
+        self.transport_encap.decap_in_place(transport_buffer)
+    }
+
+    fn get_max_transport_unit(&mut self) -> Option<usize> {
+        if self.chunk_cap_negotiated {
+            return self.transport_encap.get_max_transport_unit();
+        }
+        Some(self.max_transport_unit)
+    }
+
+    fn encap_fragment(
+        &mut self,
+        transport_message: &[u8],
+        first: bool,
+        fragment_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, usize)> {
+        if self.chunk_cap_negotiated {
+            return self
+                .transport_encap
+                .encap_fragment(transport_message, first, fragment_buffer);
+        }
+        let unit_size = self.max_transport_unit.min(fragment_buffer.len());
+        if unit_size <= SPDM_FRAGMENT_HEADER_SIZE {
+            return spdm_result_err!(EINVAL);
+        }
+        let payload_size = transport_message
+            .len()
+            .min(unit_size - SPDM_FRAGMENT_HEADER_SIZE);
+
+        if first {
+            self.send_sequence = 0;
+        }
+        let mut flags = self.send_sequence & SPDM_FRAGMENT_SEQ_MASK;
+        if first {
+            flags |= SPDM_FRAGMENT_SOM;
+        }
+        if payload_size == transport_message.len() {
+            flags |= SPDM_FRAGMENT_EOM;
+        }
+        self.send_sequence = self.send_sequence.wrapping_add(1);
+
+        fragment_buffer[0] = flags;
+        fragment_buffer[SPDM_FRAGMENT_HEADER_SIZE..(SPDM_FRAGMENT_HEADER_SIZE + payload_size)]
+            .copy_from_slice(&transport_message[..payload_size]);
+        Ok((payload_size, SPDM_FRAGMENT_HEADER_SIZE + payload_size))
+    }
+
+    fn decap_fragment(
+        &mut self,
+        fragment: &[u8],
+        first: bool,
+        transport_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        if self.chunk_cap_negotiated {
+            return self
+                .transport_encap
+                .decap_fragment(fragment, first, transport_buffer);
+        }
+        if fragment.len() < SPDM_FRAGMENT_HEADER_SIZE {
+            return spdm_result_err!(EIO);
+        }
+        let flags = fragment[0];
+        if first != (flags & SPDM_FRAGMENT_SOM != 0) {
+            return spdm_result_err!(EIO);
+        }
+        if first {
+            self.receive_sequence = 0;
+        }
+        if flags & SPDM_FRAGMENT_SEQ_MASK != self.receive_sequence & SPDM_FRAGMENT_SEQ_MASK {
+            return spdm_result_err!(EIO);
+        }
+        self.receive_sequence = self.receive_sequence.wrapping_add(1);
+
+        let payload = &fragment[SPDM_FRAGMENT_HEADER_SIZE..];
+        if transport_buffer.len() < payload.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        transport_buffer[..payload.len()].copy_from_slice(payload);
+        Ok((payload.len(), flags & SPDM_FRAGMENT_EOM != 0))
+    }
+
+    fn set_chunk_cap_negotiated(&mut self, chunk_cap_negotiated: bool) {
+        self.chunk_cap_negotiated = chunk_cap_negotiated;
+        self.transport_encap
+            .set_chunk_cap_negotiated(chunk_cap_negotiated);
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::testlib::PciDoeTransportEncap;
+
+    #[test]
+    fn test_case0_fragment_round_trip() {
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut fragment_transport_encap =
+            SpdmFragmentTransportEncap::new(pcidoe_transport_encap, 16);
+        assert_eq!(fragment_transport_encap.get_max_transport_unit(), Some(16));
+
+        let mut transport_message = [0u8; 40];
+        for (i, data) in transport_message.iter_mut().enumerate() {
+            *data = i as u8;
+        }
+
+        let mut units = [[0u8; 16]; 3];
+        let mut unit_sizes = [0usize; 3];
+        let mut offset = 0;
+        for (i, unit) in units.iter_mut().enumerate() {
+            let (consumed, used) = fragment_transport_encap
+                .encap_fragment(&transport_message[offset..], i == 0, unit)
+                .unwrap();
+            assert!(used <= 16);
+            unit_sizes[i] = used;
+            offset += consumed;
+        }
+        assert_eq!(offset, transport_message.len());
+
+        let mut transport_buffer = [0u8; 64];
+        let mut used = 0;
+        for (i, unit) in units.iter().enumerate() {
+            let (payload_size, last) = fragment_transport_encap
+                .decap_fragment(
+                    &unit[..unit_sizes[i]],
+                    i == 0,
+                    &mut transport_buffer[used..],
+                )
+                .unwrap();
+            used += payload_size;
+            assert_eq!(last, i == 2);
+        }
+        assert_eq!(&transport_buffer[..used], &transport_message[..]);
+
+        // a lost unit breaks the sequence
+        assert!(fragment_transport_encap
+            .decap_fragment(&units[0][..unit_sizes[0]], true, &mut transport_buffer)
+            .is_ok());
+        assert!(fragment_transport_encap
+            .decap_fragment(&units[2][..unit_sizes[2]], false, &mut transport_buffer)
+            .is_err());
+    }
+    #[test]
+    fn test_case1_fragment_chunk_cap_negotiated() {
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut fragment_transport_encap =
+            SpdmFragmentTransportEncap::new(pcidoe_transport_encap, 16);
+
+        // with CHUNK_CAP, transport messages go to PCI DOE whole
+        fragment_transport_encap.set_chunk_cap_negotiated(true);
+        assert_eq!(fragment_transport_encap.get_max_transport_unit(), None);
+        let mut unit = [0u8; 16];
+        assert!(fragment_transport_encap
+            .encap_fragment(&[0u8; 8], true, &mut unit)
+            .is_err());
+
+        fragment_transport_encap.set_chunk_cap_negotiated(false);
+        assert_eq!(fragment_transport_encap.get_max_transport_unit(), Some(16));
+        assert!(fragment_transport_encap
+            .encap_fragment(&[0u8; 8], true, &mut unit)
+            .is_ok());
+    }
+}
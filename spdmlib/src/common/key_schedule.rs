@@ -45,7 +45,7 @@ impl SpdmKeySchedule {
         hash_algo: SpdmBaseHashAlgo,
         key: &[u8],
     ) -> Option<SpdmDigestStruct> {
-        crypto::hmac::hmac(hash_algo, &SALT_0[0..hash_algo.get_size() as usize], key)
+        crypto::hkdf::hkdf_extract(hash_algo, &SALT_0[0..hash_algo.get_size() as usize], key)
     }
 
     pub fn derive_master_secret(
@@ -65,7 +65,7 @@ impl SpdmKeySchedule {
         let salt_1 = crypto::hkdf::hkdf_expand(hash_algo, key, bin_str0, hash_algo.get_size())?;
         debug!("salt_1 - {:02x?}", salt_1.as_ref());
 
-        crypto::hmac::hmac(
+        crypto::hkdf::hkdf_extract(
             hash_algo,
             salt_1.as_ref(),
             &ZERO_FILLED[0..hash_algo.get_size() as usize],
@@ -241,6 +241,29 @@ impl SpdmKeySchedule {
         crypto::hkdf::hkdf_expand(hash_algo, key, bin_str9, hash_algo.get_size())
     }
 
+    /// HKDF-Expand of the export master secret with BinConcat(length,
+    /// version, label, Hash(context)), in the style of the TLS 1.3 exporter.
+    pub fn derive_exported_keying_material(
+        &self,
+        spdm_version: SpdmVersion,
+        hash_algo: SpdmBaseHashAlgo,
+        export_master_secret: &[u8],
+        label: &[u8],
+        context: &[u8],
+        length: u16,
+    ) -> Option<SpdmDigestStruct> {
+        let context_hash = crypto::hash::hash_all(hash_algo, context)?;
+        let buffer = &mut [0; MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let bin_str = self.binconcat(
+            length,
+            spdm_version,
+            label,
+            Some(context_hash.as_ref()),
+            buffer,
+        )?;
+        crypto::hkdf::hkdf_expand(hash_algo, export_master_secret, bin_str, length)
+    }
+
     fn binconcat<'a>(
         &self,
         length: u16,
@@ -274,3 +297,246 @@ impl SpdmKeySchedule {
         Some(&buffer[0..len])
     }
 }
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    // SPDM 1.1, SHA-256, AES-256-GCM, shared secret 00..1f,
+    // TH1 = SHA-256("th1") and TH2 = SHA-256("th2").
+    const HANDSHAKE_SECRET: [u8; 32] = [
+        0x46, 0xbd, 0x32, 0x06, 0x05, 0xc5, 0xa6, 0xb6, 0x16, 0x3a, 0xb7, 0x0b, 0xc6, 0x34, 0x5b,
+        0x92, 0xa5, 0xf9, 0x08, 0xe7, 0x9f, 0xe5, 0x89, 0x79, 0xc2, 0x3e, 0xbb, 0x47, 0xd1, 0xa5,
+        0xe3, 0x07,
+    ];
+    const MASTER_SECRET: [u8; 32] = [
+        0xa2, 0x24, 0x35, 0xad, 0x90, 0x12, 0x03, 0x98, 0x01, 0xce, 0xd3, 0xbd, 0x1f, 0x79, 0xa7,
+        0x8e, 0x2d, 0x04, 0x35, 0xb6, 0x3c, 0xdf, 0xa0, 0x20, 0x35, 0xae, 0x79, 0xe2, 0x43, 0xc5,
+        0xa1, 0x60,
+    ];
+    const REQUEST_HANDSHAKE_SECRET: [u8; 32] = [
+        0x95, 0xc4, 0x86, 0xc7, 0x73, 0x8a, 0x51, 0xac, 0x21, 0x96, 0x1d, 0xc8, 0x22, 0xf4, 0x8e,
+        0x91, 0xe6, 0x3c, 0x24, 0x67, 0xc4, 0x17, 0xa9, 0xa1, 0xf8, 0x41, 0x46, 0xd4, 0xe8, 0x58,
+        0x9f, 0xf6,
+    ];
+    const REQUEST_FINISHED_KEY: [u8; 32] = [
+        0xdd, 0xa9, 0xd7, 0x25, 0x85, 0x42, 0x21, 0x80, 0xf3, 0xbf, 0x83, 0x52, 0x24, 0x09, 0x08,
+        0xe5, 0x05, 0x12, 0x37, 0xd5, 0x1d, 0x65, 0x6b, 0x59, 0x49, 0xcc, 0x95, 0x21, 0xd9, 0xed,
+        0x1d, 0x12,
+    ];
+    const REQUEST_HANDSHAKE_KEY: [u8; 32] = [
+        0x5f, 0x26, 0x3c, 0xe0, 0x1b, 0xc6, 0x5f, 0xca, 0x3d, 0x45, 0x0f, 0xe2, 0xd7, 0xd2, 0x08,
+        0x7a, 0xc5, 0x7c, 0xf9, 0xd7, 0xf7, 0xcf, 0x29, 0x9c, 0x86, 0xd5, 0x78, 0x34, 0xf2, 0x1b,
+        0x98, 0xab,
+    ];
+    const REQUEST_HANDSHAKE_IV: [u8; 12] = [
+        0x5a, 0x7e, 0x8d, 0x07, 0xa0, 0x59, 0xd8, 0x44, 0xb1, 0xb0, 0xaf, 0x33,
+    ];
+    const REQUEST_DATA_SECRET: [u8; 32] = [
+        0xa9, 0x33, 0x96, 0x87, 0x0d, 0x46, 0xcd, 0x64, 0x42, 0x0b, 0xa3, 0x45, 0x50, 0x04, 0x6b,
+        0x6e, 0x8d, 0xfa, 0x78, 0xb4, 0x3a, 0xee, 0x21, 0xf5, 0x45, 0x35, 0x29, 0x38, 0x90, 0x8d,
+        0x15, 0x81,
+    ];
+
+    // SPDM 1.2, SHA-384, the libspdm test PSK "TestPskData\0", TH1 = SHA-384 of
+    // PSK_EXCHANGE with PSKHint "TestPskHint\0" and a requester context of
+    // 0x11, and PSK_EXCHANGE_RSP with a responder context of 0x22.
+    const PSK_HANDSHAKE_SECRET: [u8; 48] = [
+        0x91, 0xfb, 0xda, 0x7e, 0x67, 0x88, 0x02, 0x25, 0x87, 0x66, 0x0d, 0xdb, 0x80, 0x7f, 0x5d,
+        0xd8, 0x2f, 0x02, 0x33, 0x77, 0xf8, 0x88, 0xaf, 0xe7, 0xbd, 0x28, 0x08, 0x34, 0x8d, 0xc0,
+        0x02, 0x92, 0xe0, 0x09, 0x23, 0x23, 0x34, 0xf6, 0x01, 0x7a, 0x59, 0xa6, 0x56, 0x7b, 0x0c,
+        0x86, 0x0e, 0x07,
+    ];
+    const PSK_REQUEST_HANDSHAKE_SECRET: [u8; 48] = [
+        0xb9, 0xa0, 0x25, 0x2c, 0x21, 0x30, 0x14, 0xd2, 0xb4, 0x27, 0xf3, 0x17, 0xdc, 0x8b, 0xf8,
+        0x39, 0xc3, 0xcc, 0xff, 0x95, 0x2e, 0x88, 0xa8, 0x1f, 0x40, 0xee, 0x8f, 0x11, 0x14, 0xbd,
+        0xd2, 0x6d, 0xe3, 0x59, 0xbe, 0x6d, 0xd2, 0x82, 0xde, 0x38, 0x34, 0x21, 0x98, 0xad, 0x6b,
+        0x5d, 0xf0, 0x5e,
+    ];
+    const PSK_RESPONSE_HANDSHAKE_SECRET: [u8; 48] = [
+        0x49, 0x2e, 0x84, 0x0b, 0xbb, 0xe5, 0xfb, 0x46, 0xde, 0x69, 0xaf, 0xa1, 0xe9, 0x97, 0x8d,
+        0xb0, 0x59, 0x4d, 0x89, 0x07, 0x4e, 0x5f, 0xcb, 0xfd, 0x66, 0x86, 0x61, 0xf2, 0x97, 0x68,
+        0x4b, 0x9c, 0x49, 0x5a, 0xa6, 0xb8, 0xca, 0x71, 0x17, 0x98, 0x6b, 0xd5, 0xfc, 0xd8, 0x75,
+        0x26, 0xdc, 0x50,
+    ];
+    const PSK_REQUEST_FINISHED_KEY: [u8; 48] = [
+        0xa8, 0x7c, 0x68, 0xb0, 0x6c, 0xb6, 0xfe, 0xc7, 0x3a, 0xd6, 0xbc, 0x91, 0x2e, 0xe6, 0xd3,
+        0xbd, 0x83, 0xc4, 0x24, 0x23, 0x70, 0x1e, 0x4d, 0x83, 0xd5, 0x09, 0x4a, 0xcd, 0x46, 0xdb,
+        0xe8, 0x6b, 0xdf, 0xfa, 0x86, 0x71, 0xe3, 0xc1, 0xb8, 0xe9, 0xa8, 0x11, 0xac, 0x35, 0xb6,
+        0x26, 0xaa, 0x57,
+    ];
+
+    #[test]
+    fn test_case0_key_schedule_vectors() {
+        let key_schedule = SpdmKeySchedule::new();
+        let spdm_version = SpdmVersion::SpdmVersion11;
+        let hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        let shared_secret: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let th1 = crypto::hash::hash_all(hash_algo, b"th1").unwrap();
+        let th2 = crypto::hash::hash_all(hash_algo, b"th2").unwrap();
+
+        let handshake_secret = key_schedule
+            .derive_handshake_secret(spdm_version, hash_algo, &shared_secret)
+            .unwrap();
+        assert_eq!(handshake_secret.as_ref(), &HANDSHAKE_SECRET[..]);
+
+        let master_secret = key_schedule
+            .derive_master_secret(spdm_version, hash_algo, handshake_secret.as_ref())
+            .unwrap();
+        assert_eq!(master_secret.as_ref(), &MASTER_SECRET[..]);
+
+        let request_handshake_secret = key_schedule
+            .derive_request_handshake_secret(
+                spdm_version,
+                hash_algo,
+                handshake_secret.as_ref(),
+                th1.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(
+            request_handshake_secret.as_ref(),
+            &REQUEST_HANDSHAKE_SECRET[..]
+        );
+
+        let finished_key = key_schedule
+            .derive_finished_key(spdm_version, hash_algo, request_handshake_secret.as_ref())
+            .unwrap();
+        assert_eq!(finished_key.as_ref(), &REQUEST_FINISHED_KEY[..]);
+
+        let (key, iv) = key_schedule
+            .derive_aead_key_iv(
+                spdm_version,
+                hash_algo,
+                SpdmAeadAlgo::AES_256_GCM,
+                request_handshake_secret.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(&key.data[..], &REQUEST_HANDSHAKE_KEY[..]);
+        assert_eq!(&iv.data[..], &REQUEST_HANDSHAKE_IV[..]);
+
+        let request_data_secret = key_schedule
+            .derive_request_data_secret(
+                spdm_version,
+                hash_algo,
+                master_secret.as_ref(),
+                th2.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(request_data_secret.as_ref(), &REQUEST_DATA_SECRET[..]);
+    }
+
+    #[test]
+    fn test_case1_key_schedule_psk_vectors() {
+        let key_schedule = SpdmKeySchedule::new();
+        let spdm_version = SpdmVersion::SpdmVersion12;
+        let hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+
+        let mut transcript = [0u8; 132];
+        transcript[..12].copy_from_slice(&[
+            0x12, 0xe6, 0x00, 0x00, 0xfd, 0xff, 0x0c, 0x00, 0x30, 0x00, 0x00, 0x00,
+        ]);
+        transcript[12..24].copy_from_slice(b"TestPskHint\0");
+        transcript[24..72].copy_from_slice(&[0x11; 48]);
+        transcript[72..84].copy_from_slice(&[
+            0x12, 0x66, 0x00, 0x00, 0xfe, 0xff, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00,
+        ]);
+        transcript[84..].copy_from_slice(&[0x22; 48]);
+        let th1 = crypto::hash::hash_all(hash_algo, &transcript).unwrap();
+
+        let handshake_secret = key_schedule
+            .derive_handshake_secret(spdm_version, hash_algo, b"TestPskData\0")
+            .unwrap();
+        assert_eq!(handshake_secret.as_ref(), &PSK_HANDSHAKE_SECRET[..]);
+
+        let request_handshake_secret = key_schedule
+            .derive_request_handshake_secret(
+                spdm_version,
+                hash_algo,
+                handshake_secret.as_ref(),
+                th1.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(
+            request_handshake_secret.as_ref(),
+            &PSK_REQUEST_HANDSHAKE_SECRET[..]
+        );
+        let response_handshake_secret = key_schedule
+            .derive_response_handshake_secret(
+                spdm_version,
+                hash_algo,
+                handshake_secret.as_ref(),
+                th1.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(
+            response_handshake_secret.as_ref(),
+            &PSK_RESPONSE_HANDSHAKE_SECRET[..]
+        );
+
+        let finished_key = key_schedule
+            .derive_finished_key(spdm_version, hash_algo, request_handshake_secret.as_ref())
+            .unwrap();
+        assert_eq!(finished_key.as_ref(), &PSK_REQUEST_FINISHED_KEY[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "key-schedule-trace")]
+    fn test_case2_key_schedule_trace() {
+        use crate::common::session::SpdmSession;
+
+        let spdm_version = SpdmVersion::SpdmVersion11;
+        let hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        let th1 = crypto::hash::hash_all(hash_algo, b"th1").unwrap();
+        let th2 = crypto::hash::hash_all(hash_algo, b"th2").unwrap();
+
+        let mut session = SpdmSession::new();
+        session.setup(0xFFFE_FFFE).unwrap();
+        session.set_crypto_param(
+            hash_algo,
+            SpdmDheAlgo::SECP_256_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        let mut shared_secret = SpdmDheFinalKeyStruct {
+            data_size: 32,
+            ..Default::default()
+        };
+        for (i, d) in shared_secret.data[..32].iter_mut().enumerate() {
+            *d = i as u8;
+        }
+        session.set_dhe_secret(spdm_version, shared_secret).unwrap();
+        session
+            .generate_handshake_secret(spdm_version, &th1)
+            .unwrap();
+        session.generate_data_secret(spdm_version, &th2).unwrap();
+
+        let trace = session.get_key_schedule_trace();
+        assert_eq!(trace.handshake_secret.as_ref(), &HANDSHAKE_SECRET[..]);
+        assert_eq!(trace.master_secret.as_ref(), &MASTER_SECRET[..]);
+        assert_eq!(trace.th1.as_ref(), th1.as_ref());
+        assert_eq!(
+            trace.request_handshake_secret.as_ref(),
+            &REQUEST_HANDSHAKE_SECRET[..]
+        );
+        assert_eq!(
+            trace.request_finished_key.as_ref(),
+            &REQUEST_FINISHED_KEY[..]
+        );
+        assert_eq!(
+            &trace.request_handshake_key.data[..],
+            &REQUEST_HANDSHAKE_KEY[..]
+        );
+        assert_eq!(
+            &trace.request_handshake_iv.data[..],
+            &REQUEST_HANDSHAKE_IV[..]
+        );
+        assert_eq!(trace.th2.as_ref(), th2.as_ref());
+        assert_eq!(trace.request_data_secret.as_ref(), &REQUEST_DATA_SECRET[..]);
+
+        session.teardown(0xFFFE_FFFE).unwrap();
+        assert_eq!(
+            session.get_key_schedule_trace().handshake_secret.data_size,
+            0
+        );
+    }
+}
@@ -2,21 +2,41 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+pub mod async_io;
+pub mod cert_chain;
+pub mod config_check;
+pub mod connection_info;
+pub mod fragment;
 pub mod key_schedule;
 pub mod opaque;
+pub mod saved_state;
 pub mod session;
+pub mod session_audit;
 pub mod spdm_codec;
+pub mod transcript_hash;
+pub mod x509;
 
 use crate::{crypto, protocol::*};
 
+pub use config_check::SpdmConfigError;
 pub use opaque::*;
 pub use spdm_codec::SpdmCodec;
 
 use crate::config;
+use crate::crypto::{
+    SpdmAsymSign, SpdmAsymSignOperation, SpdmCertValidityTime, SpdmDheKeyExchange, SpdmRng,
+};
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
-use codec::Writer;
+use crate::message::{
+    SpdmCertificateModel, SpdmDigestsResponsePayload, SpdmEndSessionRequestAttributes,
+    SpdmKeyExchangeMutAuthAttributes, SpdmKeyUsageBitMask,
+};
+use codec::{Codec, Reader, Writer};
 use session::*;
 
+extern crate alloc;
+use alloc::boxed::Box;
+
 #[cfg(feature = "hash-update")]
 pub use crate::crypto::HashCtx;
 
@@ -78,6 +98,120 @@ pub trait SpdmTransportEncap {
     // for session
     fn get_sequence_number_count(&mut self) -> u8;
     fn get_max_random_count(&mut self) -> u16;
+
+    /// Byte order of the sequence number bytes in the secured message header.
+    fn get_sequence_number_endian(&mut self) -> SpdmSequenceNumberEndian {
+        SpdmSequenceNumberEndian::Little
+    }
+
+    /// How far apart the sequence numbers of messages the transport reorders
+    /// may be, at most SPDM_MAX_REPLAY_WINDOW_SIZE. 0 (the default) when it
+    /// keeps them in order.
+    fn get_replay_window_size(&mut self) -> u8 {
+        0
+    }
+
+    /// Last sequence number a session key may be used with, before a key
+    /// update. SPDM_MAX_SEQUENCE_NUMBER unless the transport bounds it.
+    fn get_max_sequence_number(&mut self) -> u64 {
+        SPDM_MAX_SEQUENCE_NUMBER
+    }
+
+    /// Largest SPDM message one transport message carries.
+    fn get_max_transport_size(&mut self) -> usize {
+        config::MAX_SPDM_MESSAGE_BUFFER_SIZE
+    }
+
+    /// Bytes a secured message adds to the SPDM message it protects: session
+    /// ID, sequence number, length, application data length, random data and
+    /// MAC. A binding whose encap_app adds a header includes it here.
+    fn get_max_secured_overhead(&mut self) -> usize {
+        4 + self.get_sequence_number_count() as usize
+            + 2
+            + 2
+            + self.get_max_random_count() as usize
+            + SPDM_MAX_AEAD_TAG_SIZE
+    }
+
+    /// Size of the header encap_in_place puts in front of the SPDM message.
+    /// None (the default) when the binding only has the copying encap and
+    /// decap.
+    ///
+    /// With a header size, secured messages are encrypted straight behind
+    /// the transport header and decrypted straight out of the transport
+    /// message, and the responder handles requests where the transport
+    /// message put them. A message in the clear is still copied once
+    /// between the SPDM message buffer of the caller and the transport
+    /// buffer, as requests and responses are built and parsed in their own
+    /// buffers.
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        None
+    }
+
+    /// Turn the `spdm_size` bytes at `transport_buffer[header size..]` into a
+    /// transport message without moving them: the header is written in front
+    /// and any padding after. Returns the transport message size.
+    fn encap_in_place(
+        &mut self,
+        _transport_buffer: &mut [u8],
+        _spdm_size: usize,
+        _secured_message: bool,
+    ) -> SpdmResult<usize> {
+        spdm_result_err!(ENOSYS)
+    }
+
+    /// Locate the SPDM message inside `transport_buffer` without copying it.
+    /// Returns its offset, its size and whether it is a secured message.
+    fn decap_in_place(&mut self, _transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
+        spdm_result_err!(ENOSYS)
+    }
+
+    /// Address of the peer on a medium reaching several endpoints, e.g. the
+    /// MCTP EID behind a bridge. None (the default) for point to point
+    /// bindings.
+    fn get_peer_address(&mut self) -> Option<u32> {
+        None
+    }
+
+    /// Send the next transport messages to another endpoint of the medium.
+    fn set_peer_address(&mut self, _peer_address: u32) -> SpdmResult {
+        spdm_result_err!(ENOSYS)
+    }
+
+    /// Largest unit the medium carries at once. When set, every transport
+    /// message is sent through encap_fragment and received through
+    /// decap_fragment, so messages above it are split and reassembled.
+    /// None (the default) sends each transport message whole.
+    fn get_max_transport_unit(&mut self) -> Option<usize> {
+        None
+    }
+
+    /// Wrap the leading part of `transport_message` into `fragment_buffer`.
+    /// Returns the number of message bytes consumed and the unit size.
+    fn encap_fragment(
+        &mut self,
+        _transport_message: &[u8],
+        _first: bool,
+        _fragment_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, usize)> {
+        spdm_result_err!(ENOSYS)
+    }
+
+    /// Unwrap one received unit into `transport_buffer`. Returns the payload
+    /// size and whether the unit ends the transport message.
+    fn decap_fragment(
+        &mut self,
+        _fragment: &[u8],
+        _first: bool,
+        _transport_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        spdm_result_err!(ENOSYS)
+    }
+
+    /// Whether CHUNK_CAP is negotiated with the peer, told once the
+    /// CAPABILITIES exchange is over and again when the connection is
+    /// reset. A fragmentation fallback steps aside while it is.
+    fn set_chunk_cap_negotiated(&mut self, _chunk_cap_negotiated: bool) {}
 }
 
 impl Debug for dyn SpdmTransportEncap {
@@ -86,6 +220,223 @@ impl Debug for dyn SpdmTransportEncap {
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct SpdmPskInfo {
+    pub psk: SpdmDheFinalKeyStruct,
+    /// Responder context for PSK_EXCHANGE_RSP, a random one is generated when data_size is 0.
+    pub psk_context: SpdmPskContextStruct,
+}
+
+pub trait SpdmPskStore {
+    /// Look up the PSK identified by psk_hint, None if the hint is unknown.
+    fn lookup_psk(&self, psk_hint: &[u8]) -> Option<SpdmPskInfo>;
+}
+
+impl Debug for dyn SpdmPskStore {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Dyn SpdmPskStore")
+    }
+}
+
+/// A PSK provisioned in an SpdmPskTable.
+#[derive(Debug, Clone, Copy)]
+pub struct SpdmPskEntry<'a> {
+    /// PSKHint of PSK_EXCHANGE, empty for the PSK used when no hint is sent.
+    pub psk_hint: &'a [u8],
+    pub psk: &'a [u8],
+    /// Responder context, empty for a random one.
+    pub psk_context: &'a [u8],
+}
+
+/// SpdmPskStore of several PSKs provisioned on an endpoint, looked up by
+/// PSKHint.
+#[derive(Debug, Clone, Copy)]
+pub struct SpdmPskTable<'a> {
+    entries: &'a [SpdmPskEntry<'a>],
+}
+
+impl<'a> SpdmPskTable<'a> {
+    pub const fn new(entries: &'a [SpdmPskEntry<'a>]) -> Self {
+        SpdmPskTable { entries }
+    }
+}
+
+impl<'a> SpdmPskStore for SpdmPskTable<'a> {
+    fn lookup_psk(&self, psk_hint: &[u8]) -> Option<SpdmPskInfo> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.psk_hint == psk_hint)?;
+        if entry.psk.is_empty()
+            || entry.psk.len() > SPDM_MAX_DHE_KEY_SIZE
+            || entry.psk_context.len() > config::MAX_SPDM_PSK_CONTEXT_SIZE
+        {
+            return None;
+        }
+
+        let mut psk_info = SpdmPskInfo::default();
+        psk_info.psk.data_size = entry.psk.len() as u16;
+        psk_info.psk.data[..entry.psk.len()].copy_from_slice(entry.psk);
+        psk_info.psk_context.data_size = entry.psk_context.len() as u16;
+        psk_info.psk_context.data[..entry.psk_context.len()].copy_from_slice(entry.psk_context);
+        Some(psk_info)
+    }
+}
+
+pub trait SpdmTimeProvider {
+    /// Trusted time to check certificate validity periods against, or
+    /// SpdmCertValidityTime::Skip on platforms without one.
+    fn get_cert_validity_time(&self) -> SpdmResult<SpdmCertValidityTime>;
+
+    /// Timestamp of the transport messages traced, in a unit of the
+    /// platform's choosing.
+    fn now(&self) -> u64;
+}
+
+impl Debug for dyn SpdmTimeProvider {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Dyn SpdmTimeProvider")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmTraceDirection {
+    Send,
+    Receive,
+}
+
+pub trait SpdmTracer {
+    /// One transport message as it goes to or comes from the device IO, whole
+    /// even on a fragmenting medium. session_id is set for secured messages,
+    /// timestamp is SpdmTimeProvider::now(), 0 without a time provider.
+    fn trace(
+        &self,
+        direction: SpdmTraceDirection,
+        session_id: Option<u32>,
+        timestamp: u64,
+        transport_message: &[u8],
+    );
+}
+
+impl Debug for dyn SpdmTracer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Dyn SpdmTracer")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmSessionEvent {
+    /// KEY_EXCHANGE or PSK_EXCHANGE went through, the session is handshaking.
+    HandshakeStarted,
+    /// FINISH or PSK_FINISH went through, the session is established.
+    Established,
+    /// The session switched to the updated data keys.
+    KeyUpdated,
+    /// A session past HandshakeStarted was torn down, whatever the reason.
+    Terminated,
+    /// The peer's HMAC (verify data) of a handshake message did not verify.
+    HmacFailure,
+    /// The requester sent nothing for twice the HeartbeatPeriod, the
+    /// responder terminates the session right after.
+    HeartbeatTimeout,
+}
+
+pub trait SpdmSessionObserver {
+    /// Called once the session state already reflects the event, so the
+    /// session may be looked up by session_id, except after Terminated.
+    fn notify(&self, session_id: u32, event: SpdmSessionEvent);
+}
+
+impl Debug for dyn SpdmSessionObserver {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Dyn SpdmSessionObserver")
+    }
+}
+
+/// Handler of the application messages (PLDM, ...) received in a secured
+/// session: gets the session ID, the app data of the session and the
+/// message as decap_app returned it, writes the application message to
+/// answer with into the buffer and returns its size, 0 to send nothing back.
+pub type SpdmAppMessageHandler<'a> =
+    &'a dyn Fn(u32, &mut SpdmSessionAppData, &[u8], &mut [u8]) -> SpdmResult<usize>;
+
+/// PSK used for every hint when no SpdmPskStore is registered.
+const SPDM_DEFAULT_PSK: &[u8] = b"TestPskData\0";
+
+/// Digest form of a DMTF measurement. A raw bit stream is hashed with hash_algo,
+/// a digest is returned unchanged. None if the measurement type has no digest form.
+pub fn spdm_measurement_to_digest(
+    measurement: &SpdmDmtfMeasurementStructure,
+    hash_algo: SpdmBaseHashAlgo,
+) -> Option<SpdmDmtfMeasurementStructure> {
+    if !measurement.is_raw_bit_stream() {
+        return Some(measurement.clone());
+    }
+    match measurement.r#type {
+        SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom
+        | SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware
+        | SpdmDmtfMeasurementType::SpdmDmtfMeasurementHardwareConfig
+        | SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmwareConfig
+        | SpdmDmtfMeasurementType::SpdmDmtfMeasurementManifest => {}
+        _ => return None,
+    }
+
+    let digest = crypto::hash::hash_all(
+        hash_algo,
+        &measurement.value[..measurement.value_size as usize],
+    )?;
+    let mut value = [0u8; config::MAX_SPDM_MEASUREMENT_VALUE_LEN];
+    value[..digest.data_size as usize].copy_from_slice(digest.as_ref());
+    Some(SpdmDmtfMeasurementStructure {
+        r#type: measurement.r#type,
+        representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementDigest,
+        value_size: digest.data_size,
+        value,
+    })
+}
+
+/// Measurement summary hash over the measurement blocks of measurement_record,
+/// as carried in CHALLENGE_AUTH and KEY_EXCHANGE_RSP.
+/// TCB type only covers the blocks with the tcb bit set.
+pub fn spdm_calc_measurement_summary_hash(
+    measurement_record: &SpdmMeasurementRecordStructure,
+    base_hash_algo: SpdmBaseHashAlgo,
+    measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+) -> Option<SpdmDigestStruct> {
+    let tcb_only = match measurement_summary_hash_type {
+        SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb => true,
+        SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll => false,
+        _ => return None,
+    };
+    if measurement_record.number_of_blocks as usize > config::MAX_SPDM_MEASUREMENT_BLOCK_COUNT {
+        return None;
+    }
+
+    let mut message = ManagedBuffer::default();
+    for block in measurement_record
+        .record
+        .iter()
+        .take(measurement_record.number_of_blocks as usize)
+    {
+        if tcb_only && !block.tcb {
+            continue;
+        }
+        let measurement = &block.measurement;
+        let value_size = measurement.value_size as usize;
+        if value_size > config::MAX_SPDM_MEASUREMENT_VALUE_LEN {
+            return None;
+        }
+        message.append_message(&[block.index, block.measurement_specification.bits()])?;
+        message.append_message(&block.measurement_size.to_le_bytes())?;
+        message
+            .append_message(&[measurement.r#type.get_u8() + measurement.representation.get_u8()])?;
+        message.append_message(&measurement.value_size.to_le_bytes())?;
+        message.append_message(&measurement.value[..value_size])?;
+    }
+
+    crypto::hash::hash_all(base_hash_algo, message.as_ref())
+}
+
 pub struct SpdmContext<'a> {
     pub device_io: &'a mut dyn SpdmDeviceIo,
     pub transport_encap: &'a mut dyn SpdmTransportEncap,
@@ -98,6 +449,27 @@ pub struct SpdmContext<'a> {
     pub peer_info: SpdmPeerInfo,
 
     pub session: [SpdmSession; config::MAX_SPDM_SESSION_COUNT],
+
+    pub psk_store: Option<&'a dyn SpdmPskStore>,
+
+    pub rng: Option<&'a mut dyn SpdmRng>,
+
+    pub time_provider: Option<&'a dyn SpdmTimeProvider>,
+
+    pub app_message_handler: Option<SpdmAppMessageHandler<'a>>,
+
+    pub tracer: Option<&'a dyn SpdmTracer>,
+
+    pub session_observer: Option<&'a dyn SpdmSessionObserver>,
+
+    // set inside an async context, transport messages go through it
+    // instead of device_io
+    pub(crate) deferred_io: Option<Box<async_io::SpdmDeferredIo>>,
+
+    // CHUNK_CAP negotiated, set once the CAPABILITIES exchange is over on
+    // both sides: large messages then go in chunks and no longer through
+    // the fragment fallback of the transport
+    chunk_cap_negotiated: bool,
 }
 
 impl<'a> SpdmContext<'a> {
@@ -117,6 +489,68 @@ impl<'a> SpdmContext<'a> {
             provision_info,
             peer_info: SpdmPeerInfo::default(),
             session: gen_array(config::MAX_SPDM_SESSION_COUNT),
+            psk_store: None,
+            rng: None,
+            time_provider: None,
+            app_message_handler: None,
+            tracer: None,
+            session_observer: None,
+            deferred_io: None,
+            chunk_cap_negotiated: false,
+        }
+    }
+
+    /// Time certificate validity periods are checked against, the crypto
+    /// provider clock unless an SpdmTimeProvider is registered.
+    pub fn get_cert_validity_time(&self) -> SpdmResult<SpdmCertValidityTime> {
+        match self.time_provider {
+            Some(time_provider) => time_provider.get_cert_validity_time(),
+            None => Ok(SpdmCertValidityTime::PlatformClock),
+        }
+    }
+
+    /// Random bytes from the registered SpdmRng, or from the crypto provider.
+    pub fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+        match self.rng.as_mut() {
+            Some(rng) => rng.get_random(data),
+            None => crypto::rand::get_random(data),
+        }
+    }
+
+    pub fn generate_dhe_key_pair(
+        &mut self,
+        dhe_algo: SpdmDheAlgo,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        match self.rng.as_mut() {
+            Some(rng) => crypto::dhe::generate_key_pair(dhe_algo, &mut **rng),
+            None => crypto::dhe::generate_key_pair(dhe_algo, &mut crypto::rand::SpdmCryptoRng),
+        }
+    }
+
+    pub fn generate_dhe_response(
+        &mut self,
+        dhe_algo: SpdmDheAlgo,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<(SpdmDheExchangeStruct, SpdmDheFinalKeyStruct)> {
+        match self.rng.as_mut() {
+            Some(rng) => crypto::dhe::generate_response(dhe_algo, peer_pub_key, &mut **rng),
+            None => crypto::dhe::generate_response(
+                dhe_algo,
+                peer_pub_key,
+                &mut crypto::rand::SpdmCryptoRng,
+            ),
+        }
+    }
+
+    pub fn get_psk(&self, psk_hint: &[u8]) -> Option<SpdmPskInfo> {
+        match self.psk_store {
+            Some(psk_store) => psk_store.lookup_psk(psk_hint),
+            None => {
+                let mut psk_info = SpdmPskInfo::default();
+                psk_info.psk.data_size = SPDM_DEFAULT_PSK.len() as u16;
+                psk_info.psk.data[..SPDM_DEFAULT_PSK.len()].copy_from_slice(SPDM_DEFAULT_PSK);
+                Some(psk_info)
+            }
         }
     }
 
@@ -126,12 +560,313 @@ impl<'a> SpdmContext<'a> {
     pub fn get_asym_key_size(&self) -> u16 {
         self.negotiate_info.base_asym_sel.get_size()
     }
+    pub fn get_req_asym_key_size(&self) -> u16 {
+        self.negotiate_info.req_asym_sel.get_size()
+    }
     pub fn get_dhe_key_size(&self) -> u16 {
         self.negotiate_info.dhe_sel.get_size()
     }
+    pub fn is_heartbeat_negotiated(&self) -> bool {
+        self.negotiate_info
+            .req_capabilities_sel
+            .contains(SpdmRequestCapabilityFlags::HBEAT_CAP)
+            && self
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::HBEAT_CAP)
+    }
+    pub fn is_key_update_negotiated(&self) -> bool {
+        self.negotiate_info
+            .req_capabilities_sel
+            .contains(SpdmRequestCapabilityFlags::KEY_UPD_CAP)
+            && self
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::KEY_UPD_CAP)
+    }
+    /// KEY_EXCHANGE_RSP carries no ResponderVerifyData, FINISH and
+    /// FINISH_RSP are sent in the clear and no handshake keys are used.
+    pub fn is_handshake_in_the_clear(&self) -> bool {
+        self.negotiate_info
+            .req_capabilities_sel
+            .contains(SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
+            && self
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
+    }
+
+    /// Large messages go in CHUNK_SEND and CHUNK_GET chunks, spdm 1.2.
+    pub fn is_chunk_cap_negotiated(&self) -> bool {
+        self.chunk_cap_negotiated
+    }
+
+    /// Take CHUNK_CAP from the negotiated capabilities, once the
+    /// CAPABILITIES response is sent or received.
+    pub(crate) fn update_chunk_cap_negotiated(&mut self) {
+        self.chunk_cap_negotiated = self.negotiate_info.spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion12.get_u8()
+            && self
+                .negotiate_info
+                .req_capabilities_sel
+                .contains(SpdmRequestCapabilityFlags::CHUNK_CAP)
+            && self
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::CHUNK_CAP);
+        self.transport_encap
+            .set_chunk_cap_negotiated(self.chunk_cap_negotiated);
+    }
+
+    pub fn is_multi_key_conn_negotiated(&self) -> bool {
+        self.negotiate_info
+            .opaque_data_support
+            .contains(SpdmOpaqueSupport::MULTI_KEY_CONN)
+    }
+
+    /// Check whether the key in `slot_id` may be used for `key_usage`.
+    /// Outside of a multi-key connection any provisioned key may be used.
+    pub fn is_key_usage_allowed(
+        &self,
+        key_usage_masks: &[SpdmKeyUsageBitMask; SPDM_MAX_SLOT_NUMBER],
+        slot_id: u8,
+        key_usage: SpdmKeyUsageBitMask,
+    ) -> bool {
+        if !self.is_multi_key_conn_negotiated() || slot_id == INVALID_SLOT {
+            return true;
+        }
+        match key_usage_masks.get(slot_id as usize) {
+            Some(key_usage_mask) => key_usage_mask.contains(key_usage),
+            None => false,
+        }
+    }
+
+    /// Largest SPDM message the transport carries in one transport message,
+    /// secured or not.
+    pub fn get_max_transport_message_size(&mut self, secured: bool) -> usize {
+        let max_size = self.transport_encap.get_max_transport_size();
+        if secured {
+            max_size.saturating_sub(self.transport_encap.get_max_secured_overhead())
+        } else {
+            max_size
+        }
+    }
+
+    /// Largest PortionLength a CERTIFICATE response may carry: what fits the
+    /// transport after the 8-byte CERTIFICATE header (SPDM header,
+    /// PortionLength, RemainderLength). With SPDM 1.2 it is further bounded
+    /// by the requester's DataTransferSize minus that header.
+    pub fn get_max_cert_portion_len(&mut self, secured: bool) -> u16 {
+        let mut max_portion_len = config::MAX_SPDM_CERT_PORTION_LEN as u32;
+        let transport_portion_len = self
+            .get_max_transport_message_size(secured)
+            .saturating_sub(8)
+            .min(u32::MAX as usize) as u32;
+        max_portion_len = max_portion_len.min(transport_portion_len);
+        let data_transfer_size = self.negotiate_info.req_data_transfer_size_sel;
+        if self.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8()
+            && data_transfer_size != 0
+        {
+            max_portion_len = max_portion_len.min(data_transfer_size.saturating_sub(8));
+        }
+        max_portion_len as u16
+    }
+
+    /// Largest SPDM message the requester accepts in a single transfer, None
+    /// when no DataTransferSize was negotiated (before SPDM 1.2).
+    pub fn get_req_data_transfer_size(&self) -> Option<usize> {
+        self.data_transfer_size(self.negotiate_info.req_data_transfer_size_sel)
+    }
+
+    /// Largest SPDM message the responder accepts in a single transfer, None
+    /// when no DataTransferSize was negotiated (before SPDM 1.2).
+    pub fn get_rsp_data_transfer_size(&self) -> Option<usize> {
+        self.data_transfer_size(self.negotiate_info.rsp_data_transfer_size_sel)
+    }
+
+    /// Largest SPDM message the requester accepts as a response, in chunks
+    /// when CHUNK_CAP is negotiated, None when no DataTransferSize was
+    /// negotiated (before SPDM 1.2).
+    pub fn get_req_max_message_size(&self) -> Option<usize> {
+        self.max_message_size(
+            self.negotiate_info.req_data_transfer_size_sel,
+            self.negotiate_info.req_max_spdm_msg_size_sel,
+        )
+    }
+
+    /// Largest SPDM message the responder accepts as a request, in chunks
+    /// when CHUNK_CAP is negotiated, None when no DataTransferSize was
+    /// negotiated (before SPDM 1.2).
+    pub fn get_rsp_max_message_size(&self) -> Option<usize> {
+        self.max_message_size(
+            self.negotiate_info.rsp_data_transfer_size_sel,
+            self.negotiate_info.rsp_max_spdm_msg_size_sel,
+        )
+    }
+
+    fn data_transfer_size(&self, data_transfer_size: u32) -> Option<usize> {
+        if self.negotiate_info.spdm_version_sel.get_u8() < SpdmVersion::SpdmVersion12.get_u8()
+            || data_transfer_size == 0
+        {
+            return None;
+        }
+        Some(data_transfer_size as usize)
+    }
+
+    // Without CHUNK_CAP, a message has to fit in one transfer.
+    fn max_message_size(&self, data_transfer_size: u32, max_spdm_msg_size: u32) -> Option<usize> {
+        let data_transfer_size = self.data_transfer_size(data_transfer_size)?;
+        let max_spdm_msg_size = max_spdm_msg_size as usize;
+        if max_spdm_msg_size == 0 {
+            Some(data_transfer_size)
+        } else if self.is_chunk_cap_negotiated() {
+            Some(max_spdm_msg_size)
+        } else {
+            Some(data_transfer_size.min(max_spdm_msg_size))
+        }
+    }
+
+    /// The provisioned cert chain of `slot_id`, None for an empty or invalid slot.
+    pub fn get_my_cert_chain(&self, slot_id: u8) -> Option<&SpdmCertChainData> {
+        self.provision_info
+            .my_cert_chain
+            .get(slot_id as usize)
+            .and_then(|cert_chain| cert_chain.as_ref())
+    }
+
+    /// Bit N set when slot N holds a provisioned cert chain.
+    pub fn get_my_slot_mask(&self) -> u8 {
+        let mut slot_mask = 0u8;
+        for (slot_id, cert_chain) in self.provision_info.my_cert_chain.iter().enumerate() {
+            if cert_chain.is_some() {
+                slot_mask |= 1 << slot_id;
+            }
+        }
+        slot_mask
+    }
+
+    /// DIGESTS payload over every provisioned slot. Digests and key information
+    /// are packed in slot order. None if no slot is provisioned.
+    pub fn get_my_digests(&self) -> Option<SpdmDigestsResponsePayload> {
+        let slot_mask = self.get_my_slot_mask();
+        if slot_mask == 0 {
+            return None;
+        }
+
+        let mut digests = SpdmDigestsResponsePayload {
+            slot_mask,
+            slot_count: 0,
+            digests: gen_array_clone(SpdmDigestStruct::default(), SPDM_MAX_SLOT_NUMBER),
+            ..Default::default()
+        };
+        for slot_id in 0..SPDM_MAX_SLOT_NUMBER {
+            let my_cert_chain = match self.get_my_cert_chain(slot_id as u8) {
+                Some(my_cert_chain) => my_cert_chain,
+                None => continue,
+            };
+            let index = digests.slot_count as usize;
+            digests.digests[index] =
+                crypto::hash::hash_all(self.negotiate_info.base_hash_sel, my_cert_chain.as_ref())?;
+            digests.key_pair_id[index] = self.provision_info.my_key_pair_id[slot_id];
+            digests.certificate_info[index] = self.provision_info.my_cert_model[slot_id];
+            digests.key_usage_mask[index] = self.provision_info.my_key_usage_mask[slot_id];
+            digests.slot_count += 1;
+        }
+        Some(digests)
+    }
+
+    /// Record the per-slot key information the peer reported in DIGESTS.
+    pub fn update_peer_key_info(&mut self, digests: &SpdmDigestsResponsePayload) {
+        if !self.is_multi_key_conn_negotiated() {
+            return;
+        }
+        let mut index = 0;
+        for slot_id in 0..SPDM_MAX_SLOT_NUMBER {
+            if digests.slot_mask & (1 << slot_id) != 0 {
+                self.peer_info.peer_key_pair_id[slot_id] = digests.key_pair_id[index];
+                self.peer_info.peer_cert_model[slot_id] = digests.certificate_info[index];
+                self.peer_info.peer_key_usage_mask[slot_id] = digests.key_usage_mask[index];
+                index += 1;
+            }
+        }
+    }
+
+    /// Whether the peer reported a cert chain in slot_id in DIGESTS. Before
+    /// DIGESTS the slot mask is unknown and any slot is taken as provisioned.
+    pub fn is_peer_slot_provisioned(&self, slot_id: u8) -> bool {
+        (slot_id as usize) < SPDM_MAX_SLOT_NUMBER
+            && (self.peer_info.peer_slot_mask == 0
+                || self.peer_info.peer_slot_mask & (1 << slot_id) != 0)
+    }
+
+    /// Whether sign_with_slot_key has a private key for slot_id.
+    pub fn has_slot_key(&self, slot_id: u8) -> bool {
+        match self.provision_info.my_asym_sign.get(slot_id as usize) {
+            Some(Some(_)) => true,
+            Some(None) => slot_id == 0,
+            None => false,
+        }
+    }
+
+    /// Sign `data` with the private key of `slot_id`. A slot without a
+    /// signer of its own falls back to the registered signer, which holds the
+    /// key of slot 0 only.
+    pub fn sign_with_slot_key(
+        &self,
+        slot_id: u8,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> SpdmResult<SpdmSignatureStruct> {
+        let base_hash_algo = self.negotiate_info.base_hash_sel;
+        match self.provision_info.my_asym_sign.get(slot_id as usize) {
+            Some(Some(asym_sign)) => (asym_sign.sign_cb)(base_hash_algo, base_asym_algo, data),
+            Some(None) if slot_id == 0 => {
+                crypto::asym_sign::sign(base_hash_algo, base_asym_algo, data)
+            }
+            _ => {
+                error!("no signing key for slot {}\n", slot_id);
+                return spdm_result_err!(EINVAL);
+            }
+        }
+        .ok_or_else(|| spdm_err!(Crypto))
+    }
+
+    /// Same as sign_with_slot_key, for signatures that may complete later.
+    pub fn sign_start_with_slot_key(
+        &self,
+        slot_id: u8,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
+        let base_hash_algo = self.negotiate_info.base_hash_sel;
+        match self.provision_info.my_asym_sign.get(slot_id as usize) {
+            Some(Some(asym_sign)) => {
+                crypto::asym_sign::sign_start_with(asym_sign, base_hash_algo, base_asym_algo, data)
+            }
+            Some(None) if slot_id == 0 => {
+                crypto::asym_sign::sign_start(base_hash_algo, base_asym_algo, data)
+            }
+            _ => {
+                error!("no signing key for slot {}\n", slot_id);
+                return spdm_result_err!(EINVAL);
+            }
+        }
+        .ok_or_else(|| spdm_err!(Crypto))
+    }
 
     pub fn reset_runtime_info(&mut self) {
         self.runtime_info = SpdmRuntimeInfo::default();
+        // the hash algorithm may change with the new negotiation
+        self.peer_info.peer_cert_chain_hash = Default::default();
+    }
+
+    // Forget the VERSION, CAPABILITIES and ALGORITHMS exchange, a new
+    // connection has to be set up before the next request.
+    pub fn reset_negotiate_info(&mut self) {
+        self.negotiate_info = SpdmNegotiateInfo::default();
+        self.chunk_cap_negotiated = false;
+        self.transport_encap.set_chunk_cap_negotiated(false);
+        self.reset_runtime_info();
     }
 
     pub fn get_immutable_session_via_id(&self, session_id: u32) -> Option<&SpdmSession> {
@@ -146,10 +881,88 @@ impl<'a> SpdmContext<'a> {
             .find(|session| session.get_session_id() == session_id)
     }
 
+    /// The open session of handle, EFAULT if the context has none.
+    pub fn get_session<H: Into<SpdmSessionHandle>>(&self, handle: H) -> SpdmResult<&SpdmSession> {
+        let handle = handle.into();
+        if handle.is_null() {
+            return spdm_result_err!(EFAULT);
+        }
+        self.get_immutable_session_via_id(handle.as_u32())
+            .ok_or_else(|| spdm_err!(EFAULT))
+    }
+
+    /// The open session of handle for update, EFAULT if the context has none.
+    pub fn get_session_mut<H: Into<SpdmSessionHandle>>(
+        &mut self,
+        handle: H,
+    ) -> SpdmResult<&mut SpdmSession> {
+        let handle = handle.into();
+        if handle.is_null() {
+            return spdm_result_err!(EFAULT);
+        }
+        self.get_session_via_id(handle.as_u32())
+            .ok_or_else(|| spdm_err!(EFAULT))
+    }
+
+    /// A random half of the ID of a new session, non-zero and unlike the
+    /// same half of the open sessions. The requester picks ReqSessionID and
+    /// the responder RspSessionID, so the whole ID is unique on both ends.
+    /// A colliding draw moves on to the next free value.
+    pub(crate) fn generate_session_id_half(&mut self, is_requester: bool) -> SpdmResult<u16> {
+        let mut random = [0u8; 2];
+        self.get_random(&mut random)?;
+        let mut half = u16::from_le_bytes(random);
+        loop {
+            let in_use = self.session.iter().any(|session| {
+                let session_id = session.get_spdm_session_id();
+                session.get_session_id() != 0
+                    && if is_requester {
+                        session_id.get_req_session_id() == half
+                    } else {
+                        session_id.get_rsp_session_id() == half
+                    }
+            });
+            if half != 0 && !in_use {
+                return Ok(half);
+            }
+            half = half.wrapping_add(1);
+        }
+    }
+
+    /// A free session, None once max_session_count sessions are open.
     pub fn get_next_avaiable_session(&mut self) -> Option<&mut SpdmSession> {
+        if self.get_session_count() >= self.get_max_session_count() {
+            return None;
+        }
         self.get_session_via_id(0)
     }
 
+    /// Number of sessions handshaking or established.
+    pub fn get_session_count(&self) -> usize {
+        self.session
+            .iter()
+            .filter(|session| session.get_session_id() != 0)
+            .count()
+    }
+
+    pub fn get_max_session_count(&self) -> usize {
+        match self.config_info.max_session_count {
+            0 => config::MAX_SPDM_SESSION_COUNT,
+            max_session_count => max_session_count.min(config::MAX_SPDM_SESSION_COUNT),
+        }
+    }
+
+    /// Secured message parameters of the transport, for a new session.
+    pub fn get_session_transport_param(&mut self) -> SpdmSessionTransportParam {
+        SpdmSessionTransportParam {
+            sequence_number_count: self.transport_encap.get_sequence_number_count(),
+            max_random_count: self.transport_encap.get_max_random_count(),
+            sequence_number_endian: self.transport_encap.get_sequence_number_endian(),
+            replay_window_size: self.transport_encap.get_replay_window_size(),
+            max_sequence_number: self.transport_encap.get_max_sequence_number(),
+        }
+    }
+
     #[cfg(not(feature = "hash-update"))]
     pub fn calc_req_transcript_data(
         &self,
@@ -165,27 +978,13 @@ impl<'a> SpdmContext<'a> {
         debug!("message_a - {:02x?}", self.runtime_info.message_a.as_ref());
 
         if !use_psk {
-            if self.peer_info.peer_cert_chain[slot_id as usize].is_none() {
-                error!("peer_cert_chain is not populated!\n");
-                return spdm_result_err!(EINVAL);
-            }
-
-            let cert_chain_data = &self.peer_info.peer_cert_chain[slot_id as usize]
-                .as_ref()
-                .unwrap()
-                .cert_chain
-                .data[..(self.peer_info.peer_cert_chain[slot_id as usize]
-                .as_ref()
-                .unwrap()
-                .cert_chain
-                .data_size as usize)];
-            let cert_chain_hash =
-                crypto::hash::hash_all(self.negotiate_info.base_hash_sel, cert_chain_data)
-                    .ok_or_else(|| spdm_err!(EFAULT))?;
+            let cert_chain_hash = self
+                .get_certchain_hash_req(slot_id, false)
+                .ok_or_else(|| spdm_err!(EINVAL))?;
             message
                 .append_message(cert_chain_hash.as_ref())
                 .ok_or_else(|| spdm_err!(ENOMEM))?;
-            debug!("cert_chain_data - {:02x?}", cert_chain_data);
+            debug!("cert_chain_hash - {:02x?}", cert_chain_hash.as_ref());
         }
         message
             .append_message(message_k.as_ref())
@@ -203,7 +1002,8 @@ impl<'a> SpdmContext<'a> {
 
     #[cfg(not(feature = "hash-update"))]
     pub fn calc_rsp_transcript_data(
-        &mut self,
+        &self,
+        slot_id: u8,
         use_psk: bool,
         message_k: &ManagedBuffer,
         message_f: Option<&ManagedBuffer>,
@@ -214,13 +1014,13 @@ impl<'a> SpdmContext<'a> {
             .ok_or(spdm_err!(ENOMEM))?;
         debug!("message_a - {:02x?}", self.runtime_info.message_a.as_ref());
         if !use_psk {
-            if self.provision_info.my_cert_chain.is_none() {
+            let my_cert_chain_data = self.get_my_cert_chain(slot_id);
+            if my_cert_chain_data.is_none() {
                 error!("my_cert_chain is not populated!\n");
                 return spdm_result_err!(EINVAL);
             }
 
-            let my_cert_chain_data = self.provision_info.my_cert_chain.as_ref().unwrap();
-            let cert_chain_data = my_cert_chain_data.as_ref();
+            let cert_chain_data = my_cert_chain_data.unwrap().as_ref();
             let cert_chain_hash =
                 crypto::hash::hash_all(self.negotiate_info.base_hash_sel, cert_chain_data)
                     .ok_or_else(|| spdm_err!(EFAULT))?;
@@ -244,6 +1044,41 @@ impl<'a> SpdmContext<'a> {
         Ok(message)
     }
 
+    // Data the requester signs in the CHALLENGE_AUTH of basic mutual
+    // authentication: VCA followed by the encapsulated exchange.
+    pub fn calc_basic_mut_auth_sign_data(&self) -> SpdmResult<ManagedBuffer> {
+        let mut message = ManagedBuffer::default();
+        message
+            .append_message(self.runtime_info.message_a.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(self.runtime_info.message_mut_c.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        if let Some(signing_prefix_context) = self
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            let message_hash =
+                crypto::hash::hash_all(self.negotiate_info.base_hash_sel, message.as_ref())
+                    .ok_or_else(|| spdm_err!(EFAULT))?;
+            message.reset_message();
+            message
+                .append_message(signing_prefix_context)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_4)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_CHALLENGE_AUTH_SIGN_CONTEXT)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(message_hash.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+        Ok(message)
+    }
+
     #[cfg(not(feature = "hash-update"))]
     pub fn calc_req_transcript_hash(
         &self,
@@ -262,12 +1097,13 @@ impl<'a> SpdmContext<'a> {
 
     #[cfg(not(feature = "hash-update"))]
     pub fn calc_rsp_transcript_hash(
-        &mut self,
+        &self,
+        slot_id: u8,
         use_psk: bool,
         message_k: &ManagedBuffer,
         message_f: Option<&ManagedBuffer>,
     ) -> SpdmResult<SpdmDigestStruct> {
-        let message = self.calc_rsp_transcript_data(use_psk, message_k, message_f)?;
+        let message = self.calc_rsp_transcript_data(slot_id, use_psk, message_k, message_f)?;
 
         let transcript_hash =
             crypto::hash::hash_all(self.negotiate_info.base_hash_sel, message.as_ref())
@@ -275,15 +1111,15 @@ impl<'a> SpdmContext<'a> {
         Ok(transcript_hash)
     }
 
-    pub fn get_certchain_hash_rsp(&self, use_psk: bool) -> Option<SpdmDigestStruct> {
+    pub fn get_certchain_hash_rsp(&self, slot_id: u8, use_psk: bool) -> Option<SpdmDigestStruct> {
         if !use_psk {
-            if self.provision_info.my_cert_chain.is_none() {
+            let my_cert_chain_data = self.get_my_cert_chain(slot_id);
+            if my_cert_chain_data.is_none() {
                 error!("my_cert_chain is not populated!\n");
                 return None;
             }
 
-            let my_cert_chain_data = self.provision_info.my_cert_chain.as_ref().unwrap();
-            let cert_chain_data = my_cert_chain_data.as_ref();
+            let cert_chain_data = my_cert_chain_data.unwrap().as_ref();
             let cert_chain_hash =
                 crypto::hash::hash_all(self.negotiate_info.base_hash_sel, cert_chain_data)
                     .ok_or(None::<SpdmDigestStruct>);
@@ -297,8 +1133,13 @@ impl<'a> SpdmContext<'a> {
         }
     }
 
+    /// Hash of the peer cert chain in slot_id, the digest cached once the
+    /// chain was retrieved is reused.
     pub fn get_certchain_hash_req(&self, slot_id: u8, use_psk: bool) -> Option<SpdmDigestStruct> {
         if !use_psk {
+            if let Some(Some(hash)) = self.peer_info.peer_cert_chain_hash.get(slot_id as usize) {
+                return Some(hash.clone());
+            }
             if self.peer_info.peer_cert_chain[slot_id as usize].is_none() {
                 error!("peer_cert_chain is not populated!\n");
                 return None;
@@ -327,6 +1168,175 @@ impl<'a> SpdmContext<'a> {
         }
     }
 
+    /// What a MEASUREMENTS signature covers, given the L1/L2 transcript
+    /// and message_hash, its hash: the transcript before SPDM 1.2, else the
+    /// signing context of the negotiated version followed by the hash.
+    pub fn get_measurement_signing_message(
+        &self,
+        transcript: &[u8],
+        message_hash: &[u8],
+    ) -> SpdmResult<ManagedBuffer> {
+        let mut message = ManagedBuffer::default();
+        match self
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            Some(signing_prefix_context) => {
+                message
+                    .append_message(signing_prefix_context)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+                message
+                    .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_6)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+                message
+                    .append_message(&SPDM_MEASUREMENTS_SIGN_CONTEXT)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+                message
+                    .append_message(message_hash)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+            }
+            None => {
+                message
+                    .append_message(transcript)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+            }
+        }
+        Ok(message)
+    }
+
+    /// Hash the peer cert chain in slot_id once, for the transcripts of the
+    /// following CHALLENGE, KEY_EXCHANGE and FINISH.
+    pub fn cache_peer_cert_chain_hash(&mut self, slot_id: u8) -> SpdmResult {
+        if slot_id as usize >= SPDM_MAX_SLOT_NUMBER {
+            return spdm_result_err!(EINVAL);
+        }
+        self.peer_info.peer_cert_chain_hash[slot_id as usize] = None;
+        let hash = self
+            .get_certchain_hash_req(slot_id, false)
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        self.peer_info.peer_cert_chain_hash[slot_id as usize] = Some(hash);
+        Ok(())
+    }
+
+    /// Send one transport message, split into units when the transport
+    /// encap has a maximum transport unit.
+    pub fn send_transport_message(&mut self, transport_message: &[u8]) -> SpdmResult {
+        #[cfg(feature = "crypto-self-test")]
+        crypto::self_test::run()?;
+
+        self.trace(SpdmTraceDirection::Send, transport_message);
+        if let Some(deferred_io) = self.deferred_io.as_mut() {
+            return deferred_io.put_send(transport_message);
+        }
+        if self.transport_encap.get_max_transport_unit().is_none() {
+            return self.device_io.send(transport_message);
+        }
+
+        let mut offset = 0;
+        loop {
+            let mut fragment_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            let (consumed, used) = self.transport_encap.encap_fragment(
+                &transport_message[offset..],
+                offset == 0,
+                &mut fragment_buffer,
+            )?;
+            self.device_io.send(&fragment_buffer[..used])?;
+            offset += consumed;
+            if offset >= transport_message.len() {
+                return Ok(());
+            }
+            if consumed == 0 {
+                return spdm_result_err!(EIO);
+            }
+        }
+    }
+
+    /// Receive one transport message, reassembling it from units when the
+    /// transport encap has a maximum transport unit.
+    pub fn receive_transport_message(
+        &mut self,
+        transport_buffer: &mut [u8],
+        timeout: usize,
+    ) -> Result<usize, usize> {
+        let used = self.receive_whole_transport_message(transport_buffer, timeout)?;
+        self.trace(SpdmTraceDirection::Receive, &transport_buffer[..used]);
+        Ok(used)
+    }
+
+    fn receive_whole_transport_message(
+        &mut self,
+        transport_buffer: &mut [u8],
+        timeout: usize,
+    ) -> Result<usize, usize> {
+        if let Some(deferred_io) = self.deferred_io.as_mut() {
+            return deferred_io.take_received(transport_buffer);
+        }
+        if self.transport_encap.get_max_transport_unit().is_none() {
+            return self.device_io.receive(transport_buffer, timeout);
+        }
+
+        let mut used = 0;
+        loop {
+            let mut fragment_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            let size = self.device_io.receive(&mut fragment_buffer, timeout)?;
+            let (payload_size, last) = self
+                .transport_encap
+                .decap_fragment(
+                    &fragment_buffer[..size],
+                    used == 0,
+                    &mut transport_buffer[used..],
+                )
+                .map_err(|_| used)?;
+            used += payload_size;
+            if last {
+                return Ok(used);
+            }
+        }
+    }
+
+    fn trace(&mut self, direction: SpdmTraceDirection, transport_message: &[u8]) {
+        if let Some(tracer) = self.tracer {
+            let session_id = self.get_secured_message_session_id(transport_message);
+            let timestamp = self
+                .time_provider
+                .map_or(0, |time_provider| time_provider.now());
+            tracer.trace(direction, session_id, timestamp, transport_message);
+        }
+    }
+
+    pub(crate) fn notify_session_event(&self, session_id: u32, event: SpdmSessionEvent) {
+        if let Some(session_observer) = self.session_observer {
+            session_observer.notify(session_id, event);
+        }
+    }
+
+    // the session ID leading a secured message, None for other messages
+    fn get_secured_message_session_id(&mut self, transport_message: &[u8]) -> Option<u32> {
+        if self.transport_encap.get_encap_header_size().is_some() {
+            let (offset, used, secured_message) = self
+                .transport_encap
+                .decap_in_place(transport_message)
+                .ok()?;
+            if !secured_message {
+                return None;
+            }
+            u32::read(&mut Reader::init(
+                transport_message.get(offset..(offset + used))?,
+            ))
+        } else {
+            let mut secured_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            let (used, secured_message) = self
+                .transport_encap
+                .decap(transport_message, &mut secured_buffer)
+                .ok()?;
+            if !secured_message {
+                return None;
+            }
+            u32::read(&mut Reader::init(&secured_buffer[..used]))
+        }
+    }
+
     pub fn encap(&mut self, send_buffer: &[u8], transport_buffer: &mut [u8]) -> SpdmResult<usize> {
         self.transport_encap
             .encap(send_buffer, transport_buffer, false)
@@ -345,10 +1355,26 @@ impl<'a> SpdmContext<'a> {
             .transport_encap
             .encap_app(send_buffer, &mut app_buffer, is_app_message)?;
 
+        let header_size = self.transport_encap.get_encap_header_size();
         let spdm_session = self
             .get_session_via_id(session_id)
             .ok_or(spdm_err!(EINVAL))?;
 
+        if let Some(header_size) = header_size {
+            // encrypt straight behind the transport header
+            let secured_buffer = transport_buffer
+                .get_mut(header_size..)
+                .ok_or(spdm_err!(EINVAL))?;
+            let encode_size = spdm_session.encode_spdm_secured_message(
+                &app_buffer[0..used],
+                secured_buffer,
+                is_requester,
+            )?;
+            return self
+                .transport_encap
+                .encap_in_place(transport_buffer, encode_size, true);
+        }
+
         let mut encoded_send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let encode_size = spdm_session.encode_spdm_secured_message(
             &app_buffer[0..used],
@@ -376,16 +1402,29 @@ impl<'a> SpdmContext<'a> {
         Ok(used)
     }
 
+    /// Decrypt a secured message into receive_buffer. Returns its size and
+    /// whether it is an application message rather than an SPDM one.
     pub fn decode_secured_message(
         &mut self,
         session_id: u32,
         transport_buffer: &[u8],
         receive_buffer: &mut [u8],
-    ) -> SpdmResult<usize> {
+    ) -> SpdmResult<(usize, bool)> {
         let mut encoded_receive_buffer = [0u8; config::DATA_TRANSFER_SIZE];
-        let (used, secured_message) = self
-            .transport_encap
-            .decap(transport_buffer, &mut encoded_receive_buffer)?;
+        let (encoded_receive, secured_message) =
+            if self.transport_encap.get_encap_header_size().is_some() {
+                let (offset, used, secured_message) =
+                    self.transport_encap.decap_in_place(transport_buffer)?;
+                let encoded_receive = transport_buffer
+                    .get(offset..(offset + used))
+                    .ok_or(spdm_err!(EIO))?;
+                (encoded_receive, secured_message)
+            } else {
+                let (used, secured_message) = self
+                    .transport_encap
+                    .decap(transport_buffer, &mut encoded_receive_buffer)?;
+                (&encoded_receive_buffer[..used], secured_message)
+            };
 
         if !secured_message {
             return spdm_result_err!(EFAULT);
@@ -396,23 +1435,17 @@ impl<'a> SpdmContext<'a> {
             .ok_or(spdm_err!(EINVAL))?;
 
         let mut app_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let decode_size = spdm_session.decode_spdm_secured_message(
-            &encoded_receive_buffer[..used],
-            &mut app_buffer,
-            false,
-        )?;
+        let decode_size =
+            spdm_session.decode_spdm_secured_message(encoded_receive, &mut app_buffer, false)?;
 
-        let used = self
-            .transport_encap
-            .decap_app(&app_buffer[0..decode_size], receive_buffer)?;
-
-        Ok(used.0)
+        self.transport_encap
+            .decap_app(&app_buffer[0..decode_size], receive_buffer)
     }
 }
 
 #[derive(Debug, Default)]
 pub struct SpdmConfigInfo {
-    pub spdm_version: [SpdmVersion; config::MAX_SPDM_VERSION_COUNT],
+    pub spdm_version: [SpdmVersion; config::MAX_SPDM_VERSION_COUNT], // offered, Unknown(0) unused
     pub req_capabilities: SpdmRequestCapabilityFlags,
     pub rsp_capabilities: SpdmResponseCapabilityFlags,
     pub req_ct_exponent: u8,
@@ -432,6 +1465,94 @@ pub struct SpdmConfigInfo {
     pub max_spdm_msg_size: u32,
     pub heartbeat_period: u8,    // used by responder only
     pub secure_spdm_version: u8, // used by responder only
+    pub mut_auth_requested: SpdmKeyExchangeMutAuthAttributes, // used by responder only
+    pub end_session_attributes: SpdmEndSessionRequestAttributes, // used by requester only
+    pub runtime_update_preserves_sessions: bool, // used by responder only
+    pub basic_mut_auth_requested: bool, // used by responder only
+    // EKU a responder or requester certificate chain must allow
+    pub responder_cert_eku_policy: cert_chain::SpdmCertEkuPolicy,
+    pub requester_cert_eku_policy: cert_chain::SpdmCertEkuPolicy,
+    pub retry_policy: SpdmRetryPolicy, // used by requester only
+    // used by requester only, round-trip time of the transport in us, added
+    // to the response timeout of every request (RTT)
+    pub round_trip_time: usize,
+    pub decrypt_error_policy: SpdmDecryptErrorPolicy, // used by requester only
+    // sessions open at once, at most config::MAX_SPDM_SESSION_COUNT, 0 for
+    // that maximum
+    pub max_session_count: usize,
+    pub key_update_policy: SpdmKeyUpdatePolicy, // used by requester only
+    // used by responder only, a session without heartbeat is torn down once
+    // the requester sent nothing for that many microseconds, 0 never
+    pub session_idle_timeout: u64,
+    // used by responder only, a request of an established session that does
+    // not decrypt is answered ERROR(DecryptError) in the session, which is
+    // kept, instead of being dropped
+    pub keep_session_on_decrypt_error: bool,
+}
+
+/// Retransmission of a request the responder did not answer in time, for
+/// lossy transports such as SMBus MCTP. The first attempt waits the response
+/// timeout of the request (RTT plus ST1, or plus CT from the responder's CT
+/// exponent);
+/// every retry multiplies that timeout and the backoff by backoff_factor.
+///
+/// A retry resends the very same transport message. Within a session that
+/// only helps if the request itself was lost, as the responder rejects a
+/// sequence number it already saw.
+#[derive(Debug, Copy, Clone)]
+pub struct SpdmRetryPolicy {
+    pub retry_count: u8,    // retransmissions after the first attempt, 0 for none
+    pub backoff: usize,     // us slept before the first retransmission, 0 for none
+    pub backoff_factor: u8, // 1 keeps timeout and backoff constant
+}
+
+impl Default for SpdmRetryPolicy {
+    fn default() -> Self {
+        SpdmRetryPolicy {
+            retry_count: 0,
+            backoff: 0,
+            backoff_factor: 2,
+        }
+    }
+}
+
+impl SpdmRetryPolicy {
+    /// Timeout of attempt `retry`, 0 being the first transmission.
+    pub fn get_timeout(&self, timeout: usize, retry: u8) -> usize {
+        Self::scale(timeout, self.backoff_factor, retry)
+    }
+
+    /// Sleep before retransmission `retry`, counting from 1.
+    pub fn get_backoff(&self, retry: u8) -> usize {
+        Self::scale(self.backoff, self.backoff_factor, retry.saturating_sub(1))
+    }
+
+    fn scale(value: usize, factor: u8, count: u8) -> usize {
+        (0..count).fold(value, |value, _| value.saturating_mul(factor as usize))
+    }
+}
+
+/// Recovery of a session the responder answered a DecryptError in, for
+/// transports corrupting a message now and then. The request is encrypted
+/// and sent again up to retry_count times, then with rekey the keys are
+/// updated (KEY_UPDATE and VERIFY_NEW_KEY) and the request sent once more.
+/// The session is torn down when all of it fails, at once by default.
+///
+/// A responder following the specification frees the session along with
+/// the DecryptError, so nothing is sent again unless peer_keeps_session
+/// tells the responder is known to keep it, as a ResponderContext with
+/// keep_session_on_decrypt_error does.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SpdmDecryptErrorPolicy {
+    pub retry_count: u8,
+    pub rekey: bool,
+    pub peer_keeps_session: bool,
+}
+
+impl SpdmDecryptErrorPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.peer_keeps_session && (self.retry_count != 0 || self.rekey)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -450,11 +1571,10 @@ pub struct SpdmNegotiateInfo {
     pub req_asym_sel: SpdmReqAsymAlgo,
     pub key_schedule_sel: SpdmKeyScheduleAlgo,
     pub opaque_data_support: SpdmOpaqueSupport,
-    pub termination_policy_set: bool, // used by responder to take action when code or configuration changed.
     pub req_data_transfer_size_sel: u32, // spdm 1.2
-    pub req_max_spdm_msg_size_sel: u32, // spdm 1.2
+    pub req_max_spdm_msg_size_sel: u32,  // spdm 1.2
     pub rsp_data_transfer_size_sel: u32, // spdm 1.2
-    pub rsp_max_spdm_msg_size_sel: u32, // spdm 1.2
+    pub rsp_max_spdm_msg_size_sel: u32,  // spdm 1.2
 }
 
 // TBD ManagedSmallBuffer
@@ -491,10 +1611,15 @@ impl Default for ManagedBuffer {
 pub struct SpdmRuntimeInfo {
     pub need_measurement_summary_hash: bool,
     pub need_measurement_signature: bool,
+    pub need_endpoint_info_signature: bool,
     pub message_a: ManagedBuffer,
     pub message_b: ManagedBuffer,
     pub message_c: ManagedBuffer,
     pub message_m: ManagedBuffer,
+    pub message_mut_c: ManagedBuffer, // encapsulated exchange of basic mutual authentication
+    pub req_challenge_auth: bool,     // CHALLENGE_AUTH carries a requester signature
+    pub basic_mut_auth_requested: bool, // used by requester, set when CHALLENGE_AUTH asks for it
+    pub basic_mut_auth_verified: bool, // used by responder, set once the requester is authenticated
     pub content_changed: u8, // used by responder, set when content changed and spdm version is 1.2.
                              // used by requester, consume when measurement response report content changed.
 }
@@ -504,25 +1629,46 @@ pub struct SpdmRuntimeInfo {
 pub struct SpdmRuntimeInfo {
     pub need_measurement_summary_hash: bool,
     pub need_measurement_signature: bool,
+    pub need_endpoint_info_signature: bool,
     pub message_a: ManagedBuffer,
     pub message_m: Option<HashCtx>,              // for M1/M2
     pub message_mes_no_session: Option<HashCtx>, // for out of session get measurement/measurement
+    pub message_mut_c: ManagedBuffer, // encapsulated exchange of basic mutual authentication
+    pub req_challenge_auth: bool,     // CHALLENGE_AUTH carries a requester signature
+    pub basic_mut_auth_requested: bool, // used by requester, set when CHALLENGE_AUTH asks for it
+    pub basic_mut_auth_verified: bool, // used by responder, set once the requester is authenticated
     pub content_changed: u8, // used by responder, set when content changed and spdm version is 1.2.
                              // used by requester, consume when measurement response report content changed.
 }
 
 #[derive(Default, Clone)]
 pub struct SpdmProvisionInfo {
-    pub my_cert_chain_data: Option<SpdmCertChainData>,
-    pub my_cert_chain: Option<SpdmCertChainData>, // use SpdmCertChainData instead of SpdmCertChain for easy command sending.
+    // indexed by slot
+    pub my_cert_chain_data: [Option<SpdmCertChainData>; SPDM_MAX_SLOT_NUMBER],
+    pub my_cert_chain: [Option<SpdmCertChainData>; SPDM_MAX_SLOT_NUMBER], // use SpdmCertChainData instead of SpdmCertChain for easy command sending.
     // TBD: union peer. But it is still option.
     pub peer_cert_chain_data: Option<SpdmCertChainData>,
-    pub peer_cert_chain_root_hash: Option<SpdmDigestStruct>,
-    pub default_version: SpdmVersion,
+    // trust store the requester anchors peer cert chains to
+    pub peer_trust_anchors:
+        [Option<cert_chain::SpdmTrustAnchor>; cert_chain::SPDM_MAX_TRUST_ANCHOR_NUMBER],
+    // SPDM 1.3 multi-key connection, indexed by slot
+    pub my_key_pair_id: [u8; SPDM_MAX_SLOT_NUMBER],
+    pub my_cert_model: [SpdmCertificateModel; SPDM_MAX_SLOT_NUMBER],
+    pub my_key_usage_mask: [SpdmKeyUsageBitMask; SPDM_MAX_SLOT_NUMBER],
+    // signer of the slot key, indexed by slot. Slot 0 defaults to the
+    // registered signer, any other slot needs its own.
+    pub my_asym_sign: [Option<SpdmAsymSign>; SPDM_MAX_SLOT_NUMBER],
 }
 
 #[derive(Default)]
 pub struct SpdmPeerInfo {
     pub peer_cert_chain: [Option<SpdmCertChain>; 8],
+    // digest of peer_cert_chain, cached after GET_CERTIFICATE, indexed by slot
+    pub peer_cert_chain_hash: [Option<SpdmDigestStruct>; SPDM_MAX_SLOT_NUMBER],
+    pub peer_slot_mask: u8, // slots the peer reported in DIGESTS, 0 before
+    // learned from DIGESTS in a multi-key connection, indexed by slot
+    pub peer_key_pair_id: [u8; SPDM_MAX_SLOT_NUMBER],
+    pub peer_cert_model: [SpdmCertificateModel; SPDM_MAX_SLOT_NUMBER],
+    pub peer_key_usage_mask: [SpdmKeyUsageBitMask; SPDM_MAX_SLOT_NUMBER],
     // pub peer_cert_chain_data: SpdmCertChain,
 }
@@ -11,7 +11,7 @@ pub const MAX_SECURE_SPDM_VERSION_COUNT: usize = 0x02;
 pub const MAX_VENDOR_ID_LENGTH: usize = 0xFF;
 pub const MAX_OPAQUE_LIST_ELEMENTS_COUNT: usize = 3;
 
-pub const DMTF_SPEC_ID: u32 = 0x444D546;
+pub const DMTF_SPEC_ID: u32 = 0x444D5446; // "FTMD"
 pub const DMTF_OPAQUE_VERSION: u8 = 0x01;
 pub const SM_DATA_VERSION: u8 = 0x01;
 pub const PADDING: u8 = 0x00;
@@ -279,6 +279,194 @@ impl SpdmCodec for SecuredMessageGeneralOpaqueDataHeader {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct SpdmOpaqueElement {
+    pub header: OpaqueElementHeader, // registry ID and vendor ID
+    pub data_len: u16,
+    pub data: [u8; config::MAX_SPDM_OPAQUE_SIZE],
+}
+
+impl Default for SpdmOpaqueElement {
+    fn default() -> Self {
+        Self {
+            header: OpaqueElementHeader::default(),
+            data_len: 0,
+            data: [0u8; config::MAX_SPDM_OPAQUE_SIZE],
+        }
+    }
+}
+
+impl SpdmCodec for SpdmOpaqueElement {
+    fn spdm_encode(&self, context: &mut SpdmContext, bytes: &mut Writer) {
+        self.header.spdm_encode(context, bytes);
+        self.data_len.encode(bytes);
+        for d in self.data.iter().take(self.data_len as usize) {
+            d.encode(bytes);
+        }
+        for _i in 0..self.get_align_padding() {
+            PADDING.encode(bytes);
+        }
+    }
+    fn spdm_read(context: &mut SpdmContext, r: &mut Reader) -> Option<SpdmOpaqueElement> {
+        let header = OpaqueElementHeader::spdm_read(context, r)?;
+        let data_len = u16::read(r)?;
+        if data_len as usize > config::MAX_SPDM_OPAQUE_SIZE {
+            return None;
+        }
+        let mut data = [0u8; config::MAX_SPDM_OPAQUE_SIZE];
+        for d in data.iter_mut().take(data_len as usize) {
+            *d = u8::read(r)?;
+        }
+        let element = SpdmOpaqueElement {
+            header,
+            data_len,
+            data,
+        };
+        for _i in 0..element.get_align_padding() {
+            u8::read(r)?;
+        }
+
+        Some(element)
+    }
+}
+
+impl SpdmOpaqueElement {
+    /// Build a DMTF secured message element, data is the content after SMDataID.
+    pub fn new_dmtf_sm_element(sm_data_id: u8, data: &[u8]) -> Option<Self> {
+        if data.len() + 2 > config::MAX_SPDM_OPAQUE_SIZE {
+            return None;
+        }
+        let mut element = SpdmOpaqueElement {
+            data_len: data.len() as u16 + 2,
+            ..Default::default()
+        };
+        element.header.id = DMTF_ID;
+        element.header.vendor_len = DMTF_VENDOR_LEN;
+        element.data[0] = SM_DATA_VERSION;
+        element.data[1] = sm_data_id;
+        element.data[2..(data.len() + 2)].copy_from_slice(data);
+        Some(element)
+    }
+
+    pub fn is_dmtf(&self) -> bool {
+        self.header.id == DMTF_ID && self.header.vendor_len == DMTF_VENDOR_LEN
+    }
+
+    /// Content after SMDataID when this is a DMTF secured message element with sm_data_id.
+    pub fn get_dmtf_sm_data(&self, sm_data_id: u8) -> Option<&[u8]> {
+        if !self.is_dmtf() || self.data_len < 2 {
+            return None;
+        }
+        if self.data[0] != SM_DATA_VERSION || self.data[1] != sm_data_id {
+            return None;
+        }
+        Some(&self.data[2..self.data_len as usize])
+    }
+
+    fn get_align_padding(&self) -> usize {
+        let len = 4 + self.header.vendor_len as usize + self.data_len as usize;
+        ((len + 3) & (!3)) - len
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SpdmGeneralOpaqueData {
+    pub header: SecuredMessageGeneralOpaqueDataHeader,
+    pub elements: [SpdmOpaqueElement; MAX_OPAQUE_LIST_ELEMENTS_COUNT],
+}
+
+impl SpdmCodec for SpdmGeneralOpaqueData {
+    fn spdm_encode(&self, context: &mut SpdmContext, bytes: &mut Writer) {
+        self.header.spdm_encode(context, bytes);
+        for element in self
+            .elements
+            .iter()
+            .take(self.header.total_elements as usize)
+        {
+            element.spdm_encode(context, bytes);
+        }
+    }
+    fn spdm_read(context: &mut SpdmContext, r: &mut Reader) -> Option<SpdmGeneralOpaqueData> {
+        let header = SecuredMessageGeneralOpaqueDataHeader::spdm_read(context, r)?;
+        if !context
+            .negotiate_info
+            .opaque_data_support
+            .contains(SpdmOpaqueSupport::OPAQUE_DATA_FMT1)
+            && (header.spec_id != DMTF_SPEC_ID || header.opaque_version != DMTF_OPAQUE_VERSION)
+        {
+            return None;
+        }
+        if header.total_elements as usize > MAX_OPAQUE_LIST_ELEMENTS_COUNT {
+            return None;
+        }
+        let mut elements: [SpdmOpaqueElement; MAX_OPAQUE_LIST_ELEMENTS_COUNT] = Default::default();
+        for element in elements.iter_mut().take(header.total_elements as usize) {
+            *element = SpdmOpaqueElement::spdm_read(context, r)?;
+        }
+
+        Some(SpdmGeneralOpaqueData { header, elements })
+    }
+}
+
+impl SpdmGeneralOpaqueData {
+    pub fn new() -> Self {
+        SpdmGeneralOpaqueData {
+            header: SecuredMessageGeneralOpaqueDataHeader {
+                spec_id: DMTF_SPEC_ID,
+                opaque_version: DMTF_OPAQUE_VERSION,
+                total_elements: 0,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn add_element(&mut self, element: SpdmOpaqueElement) -> SpdmResult {
+        let index = self.header.total_elements as usize;
+        if index >= MAX_OPAQUE_LIST_ELEMENTS_COUNT {
+            return spdm_result_err!(ENOMEM);
+        }
+        self.elements[index] = element;
+        self.header.total_elements += 1;
+        Ok(())
+    }
+
+    pub fn get_elements(&self) -> &[SpdmOpaqueElement] {
+        &self.elements[..self.header.total_elements as usize]
+    }
+
+    /// Content of the first DMTF secured message element with sm_data_id.
+    pub fn get_dmtf_sm_data(&self, sm_data_id: u8) -> Option<&[u8]> {
+        self.get_elements()
+            .iter()
+            .find_map(|element| element.get_dmtf_sm_data(sm_data_id))
+    }
+}
+
+pub fn is_supported_secure_spdm_version(secure_spdm_version: u8) -> bool {
+    DMTF_SUPPORTED_SECURE_SPDM_VERSION_LIST
+        .iter()
+        .any(|supported| supported.get_secure_spdm_version() == secure_spdm_version)
+}
+
+/// Select the highest secured message version offered by the peer that is
+/// supported locally and not newer than max_secure_spdm_version.
+pub fn select_secure_spdm_version(
+    secured_message_version_list: &SecuredMessageVersionList,
+    max_secure_spdm_version: u8,
+) -> Option<SecuredMessageVersion> {
+    secured_message_version_list
+        .versions_list
+        .iter()
+        .take(secured_message_version_list.version_count as usize)
+        .filter(|version| {
+            let secure_spdm_version = version.get_secure_spdm_version();
+            secure_spdm_version <= max_secure_spdm_version
+                && is_supported_secure_spdm_version(secure_spdm_version)
+        })
+        .max_by_key(|version| version.get_secure_spdm_version())
+        .copied()
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct OpaqueElementDMTFVersionSelection {
     pub selected_version: SecuredMessageVersion,
@@ -461,6 +649,9 @@ impl SpdmCodec for SpdmOpaqueStruct {
     }
     fn spdm_read(_context: &mut SpdmContext, r: &mut Reader) -> Option<SpdmOpaqueStruct> {
         let data_size = u16::read(r)?;
+        if data_size as usize > config::MAX_SPDM_OPAQUE_SIZE {
+            return None;
+        }
         let mut data = [0u8; config::MAX_SPDM_OPAQUE_SIZE];
         for d in data.iter_mut().take(data_size as usize) {
             *d = u8::read(r)?;
@@ -471,32 +662,99 @@ impl SpdmCodec for SpdmOpaqueStruct {
 }
 
 impl SpdmOpaqueStruct {
-    pub fn rsp_get_dmtf_supported_secure_spdm_version_list(
+    pub fn get_general_opaque_data(
         &self,
         context: &mut SpdmContext,
-    ) -> Option<SecuredMessageVersionList> {
+    ) -> Option<SpdmGeneralOpaqueData> {
         let mut r = Reader::init(&self.data[0..self.data_size as usize]);
-        let secured_message_dmtf_supported_version =
-            SecuredMessageDMTFSupportedVersion::spdm_read(context, &mut r)?;
+        SpdmGeneralOpaqueData::spdm_read(context, &mut r)
+    }
 
-        Some(SecuredMessageVersionList {
-            ..secured_message_dmtf_supported_version.opaque_element_dmtf_supported_version_list[0]
-                .secured_msg_vers
-        })
+    pub fn from_general_opaque_data(
+        context: &mut SpdmContext,
+        general_opaque_data: &SpdmGeneralOpaqueData,
+    ) -> SpdmResult<SpdmOpaqueStruct> {
+        let mut buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut buffer);
+        general_opaque_data.spdm_encode(context, &mut writer);
+        let used = writer.used();
+        if used > config::MAX_SPDM_OPAQUE_SIZE {
+            return spdm_result_err!(ENOMEM);
+        }
+
+        let mut opaque = SpdmOpaqueStruct {
+            data_size: used as u16,
+            ..Default::default()
+        };
+        opaque.data[..used].copy_from_slice(&buffer[..used]);
+        Ok(opaque)
+    }
+
+    /// Opaque data for KEY_EXCHANGE/PSK_EXCHANGE listing the supported secured message versions.
+    pub fn req_dmtf_supported_secure_spdm_version_list(
+        context: &mut SpdmContext,
+    ) -> SpdmResult<SpdmOpaqueStruct> {
+        let secured_message_version_list = SecuredMessageVersionList {
+            version_count: MAX_SECURE_SPDM_VERSION_COUNT as u8,
+            versions_list: DMTF_SUPPORTED_SECURE_SPDM_VERSION_LIST,
+        };
+        let mut sm_data = [0u8; 1 + 2 * MAX_SECURE_SPDM_VERSION_COUNT];
+        let mut writer = Writer::init(&mut sm_data);
+        secured_message_version_list.spdm_encode(context, &mut writer);
+        let used = writer.used();
+
+        let element = SpdmOpaqueElement::new_dmtf_sm_element(
+            SUPPORTED_VERSION_LIST_SM_DATA_ID,
+            &sm_data[..used],
+        )
+        .ok_or(spdm_err!(ENOMEM))?;
+        let mut general_opaque_data = SpdmGeneralOpaqueData::new();
+        general_opaque_data.add_element(element)?;
+        Self::from_general_opaque_data(context, &general_opaque_data)
+    }
+
+    /// Opaque data for KEY_EXCHANGE_RSP/PSK_EXCHANGE_RSP with the selected secured message version.
+    pub fn rsp_dmtf_secure_spdm_version_selection(
+        context: &mut SpdmContext,
+        selected_version: SecuredMessageVersion,
+    ) -> SpdmResult<SpdmOpaqueStruct> {
+        let mut sm_data = [0u8; 2];
+        let mut writer = Writer::init(&mut sm_data);
+        selected_version.spdm_encode(context, &mut writer);
+
+        let element =
+            SpdmOpaqueElement::new_dmtf_sm_element(VERSION_SELECTION_SM_DATA_ID, &sm_data)
+                .ok_or(spdm_err!(ENOMEM))?;
+        let mut general_opaque_data = SpdmGeneralOpaqueData::new();
+        general_opaque_data.add_element(element)?;
+        Self::from_general_opaque_data(context, &general_opaque_data)
+    }
+
+    pub fn rsp_get_dmtf_supported_secure_spdm_version_list(
+        &self,
+        context: &mut SpdmContext,
+    ) -> Option<SecuredMessageVersionList> {
+        let general_opaque_data = self.get_general_opaque_data(context)?;
+        let sm_data = general_opaque_data.get_dmtf_sm_data(SUPPORTED_VERSION_LIST_SM_DATA_ID)?;
+        let mut r = Reader::init(sm_data);
+        let mut secured_message_version_list =
+            SecuredMessageVersionList::spdm_read(context, &mut r)?;
+        // versions beyond MAX_SECURE_SPDM_VERSION_COUNT are not kept
+        secured_message_version_list.version_count = secured_message_version_list
+            .version_count
+            .min(MAX_SECURE_SPDM_VERSION_COUNT as u8);
+
+        Some(secured_message_version_list)
     }
 
     pub fn req_get_dmtf_secure_spdm_version_selection(
         &self,
         context: &mut SpdmContext,
     ) -> Option<SecuredMessageVersion> {
-        let mut r = Reader::init(&self.data[0..self.data_size as usize]);
-        let secured_message_dmtf_version_selection =
-            SecuredMessageDMTFVersionSelection::spdm_read(context, &mut r)?;
-
-        Some(SecuredMessageVersion {
-            ..secured_message_dmtf_version_selection.opaque_element_dmtf_version_selection_list[0]
-                .selected_version
-        })
+        let general_opaque_data = self.get_general_opaque_data(context)?;
+        let sm_data = general_opaque_data.get_dmtf_sm_data(VERSION_SELECTION_SM_DATA_ID)?;
+        let mut r = Reader::init(sm_data);
+        SecuredMessageVersion::spdm_read(context, &mut r)
     }
 }
 
@@ -505,6 +763,7 @@ bitflags! {
     pub struct SpdmOpaqueSupport: u8 {
         const OPAQUE_DATA_FMT0 = 0b0000_0001;
         const OPAQUE_DATA_FMT1 = 0b0000_0010;
+        const MULTI_KEY_CONN = 0b0010_0000;
     }
 }
 
@@ -0,0 +1,462 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Negotiated state and established sessions kept across a device reset.
+//!
+//! A responder going through a warm reset, or suspending to RAM or flash,
+//! saves its SpdmContext with save_state and restores it with
+//! restore_state once up again. With CACHE_CAP the requester may then go
+//! on without VCA, and the established sessions carry on with their
+//! sequence numbers.
+//!
+//! The state is encrypted with AES-256-GCM under a wrapping key of the
+//! caller, a device unique key typically:
+//!
+//! | header (AAD) | encrypted state | tag |
+//!
+//! the header being the magic, the format version, the generation, the size
+//! of the encrypted state and the IV.
+//!
+//! The session keys are not derived again, so a sequence number must never
+//! be used twice under them. Saving hands the sessions over to the saved
+//! state: they are torn down in the context saved. And a saved state is only
+//! restored once: it carries the generation of an anti-rollback counter of
+//! the caller, a monotonic counter in fuses or RPMB typically, and only the
+//! one of the current generation is taken. The caller advances the counter
+//! right after a restore, before the sessions send anything.
+//!
+//! The peer certificate chains are not saved, nor the transcripts but
+//! message_a. Sessions still handshaking, or in the middle of a key update,
+//! cannot be saved.
+
+use super::*;
+
+use zeroize::Zeroize;
+
+pub const SPDM_SAVED_STATE_MAGIC: u32 = 0x5344_5053; // "SPDS"
+pub const SPDM_SAVED_STATE_VERSION: u16 = 2;
+
+pub const SPDM_SAVED_STATE_WRAPPING_KEY_SIZE: usize = AEAD_AES_256_GCM_KEY_SIZE;
+
+const SAVED_STATE_IV_SIZE: usize = 12;
+const SAVED_STATE_TAG_SIZE: usize = 16;
+// magic, version, reserved, generation, size of the encrypted state, IV
+const SAVED_STATE_HEADER_SIZE: usize = 4 + 2 + 2 + 8 + 4 + SAVED_STATE_IV_SIZE;
+
+// 18 fields of at most 4 bytes
+const SAVED_NEGOTIATE_INFO_MAX_SIZE: usize = 18 * 4;
+// 15 fields of at most 4 bytes, 3 secrets, the last sequence number, 2
+// sequence numbers and bitmaps
+const SAVED_SESSION_MAX_SIZE: usize = 15 * 4 + 3 * (2 + SPDM_MAX_HASH_SIZE) + 5 * 8;
+const SAVED_STATE_PLAIN_MAX_SIZE: usize = SAVED_NEGOTIATE_INFO_MAX_SIZE
+    + 4
+    + config::MAX_SPDM_MESSAGE_BUFFER_SIZE
+    + 1
+    + config::MAX_SPDM_SESSION_COUNT * SAVED_SESSION_MAX_SIZE;
+
+/// Buffer size save_state needs at most.
+pub const SPDM_SAVED_STATE_MAX_SIZE: usize =
+    SAVED_STATE_HEADER_SIZE + SAVED_STATE_PLAIN_MAX_SIZE + SAVED_STATE_TAG_SIZE;
+
+impl<'a> SpdmContext<'a> {
+    /// Encrypt the negotiated state and the established sessions under
+    /// wrapping_key into buffer, returns the size used. generation is the
+    /// current value of the anti-rollback counter. The established sessions
+    /// go with the saved state, they are torn down here.
+    pub fn save_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        buffer: &mut [u8],
+    ) -> SpdmResult<usize> {
+        if wrapping_key.len() != SPDM_SAVED_STATE_WRAPPING_KEY_SIZE {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut plain_text = Box::new([0u8; SAVED_STATE_PLAIN_MAX_SIZE]);
+        let r = self.seal_state(wrapping_key, generation, &mut plain_text[..], buffer);
+        plain_text.zeroize();
+        if r.is_ok() {
+            for session in self.session.iter_mut() {
+                let session_id = session.get_session_id();
+                if session.get_session_state() == SpdmSessionState::SpdmSessionEstablished {
+                    let _ = session.teardown(session_id);
+                }
+            }
+        }
+        r
+    }
+
+    /// Decrypt a state written by save_state with the same wrapping_key
+    /// and take it over, if it is of generation, the current value of the
+    /// anti-rollback counter. No session may be in use.
+    pub fn restore_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        buffer: &[u8],
+    ) -> SpdmResult {
+        if wrapping_key.len() != SPDM_SAVED_STATE_WRAPPING_KEY_SIZE {
+            return spdm_result_err!(EINVAL);
+        }
+        if self.get_session_count() != 0 {
+            return spdm_result_err!(EBUSY);
+        }
+        let mut plain_text = Box::new([0u8; SAVED_STATE_PLAIN_MAX_SIZE]);
+        let r = self.unseal_state(wrapping_key, generation, buffer, &mut plain_text[..]);
+        plain_text.zeroize();
+        if r.is_err() {
+            self.reset_negotiate_info();
+        } else {
+            self.update_chunk_cap_negotiated();
+        }
+        r
+    }
+
+    fn seal_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        plain_text: &mut [u8],
+        buffer: &mut [u8],
+    ) -> SpdmResult<usize> {
+        let plain_size = self.save_plain_state(plain_text)?;
+        let used = SAVED_STATE_HEADER_SIZE + plain_size + SAVED_STATE_TAG_SIZE;
+        if buffer.len() < used {
+            return spdm_result_err!(ENOMEM);
+        }
+
+        let mut iv = [0u8; SAVED_STATE_IV_SIZE];
+        self.get_random(&mut iv)?;
+        let (header, rest) = buffer.split_at_mut(SAVED_STATE_HEADER_SIZE);
+        let mut writer = Writer::init(header);
+        SPDM_SAVED_STATE_MAGIC.encode(&mut writer);
+        SPDM_SAVED_STATE_VERSION.encode(&mut writer);
+        0u16.encode(&mut writer);
+        generation.encode(&mut writer);
+        (plain_size as u32).encode(&mut writer);
+        writer.extend_from_slice(&iv).ok_or(spdm_err!(ENOMEM))?;
+
+        let (cipher_text, rest) = rest.split_at_mut(plain_size);
+        crypto::aead::encrypt(
+            SpdmAeadAlgo::AES_256_GCM,
+            wrapping_key,
+            &iv,
+            header,
+            &plain_text[..plain_size],
+            &mut rest[..SAVED_STATE_TAG_SIZE],
+            cipher_text,
+        )?;
+        Ok(used)
+    }
+
+    fn unseal_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        buffer: &[u8],
+        plain_text: &mut [u8],
+    ) -> SpdmResult {
+        let mut reader = Reader::init(buffer);
+        let magic = u32::read(&mut reader).ok_or(spdm_err!(EINVAL))?;
+        let version = u16::read(&mut reader).ok_or(spdm_err!(EINVAL))?;
+        let _reserved = u16::read(&mut reader).ok_or(spdm_err!(EINVAL))?;
+        let saved_generation = u64::read(&mut reader).ok_or(spdm_err!(EINVAL))?;
+        let plain_size = u32::read(&mut reader).ok_or(spdm_err!(EINVAL))? as usize;
+        if magic != SPDM_SAVED_STATE_MAGIC
+            || version != SPDM_SAVED_STATE_VERSION
+            || plain_size > plain_text.len()
+            || buffer.len() < SAVED_STATE_HEADER_SIZE + plain_size + SAVED_STATE_TAG_SIZE
+        {
+            return spdm_result_err!(EINVAL);
+        }
+
+        let (header, rest) = buffer.split_at(SAVED_STATE_HEADER_SIZE);
+        let (cipher_text, rest) = rest.split_at(plain_size);
+        crypto::aead::decrypt(
+            SpdmAeadAlgo::AES_256_GCM,
+            wrapping_key,
+            &header[SAVED_STATE_HEADER_SIZE - SAVED_STATE_IV_SIZE..],
+            header,
+            cipher_text,
+            &rest[..SAVED_STATE_TAG_SIZE],
+            &mut plain_text[..plain_size],
+        )
+        .map_err(|_| spdm_err!(ESEC))?;
+        // an older state would replay the sequence numbers of its sessions
+        if saved_generation != generation {
+            error!(
+                "!!! restore_state : generation {} expected, got {} !!!\n",
+                generation, saved_generation
+            );
+            return spdm_result_err!(ESEC);
+        }
+        self.restore_plain_state(&plain_text[..plain_size])
+    }
+
+    fn save_plain_state(&self, buffer: &mut [u8]) -> SpdmResult<usize> {
+        let mut writer = Writer::init(buffer);
+        let negotiate_info = &self.negotiate_info;
+        negotiate_info.spdm_version_sel.encode(&mut writer);
+        negotiate_info.req_capabilities_sel.encode(&mut writer);
+        negotiate_info.rsp_capabilities_sel.encode(&mut writer);
+        negotiate_info.req_ct_exponent_sel.encode(&mut writer);
+        negotiate_info.rsp_ct_exponent_sel.encode(&mut writer);
+        negotiate_info
+            .measurement_specification_sel
+            .encode(&mut writer);
+        negotiate_info.measurement_hash_sel.encode(&mut writer);
+        negotiate_info.base_hash_sel.encode(&mut writer);
+        negotiate_info.base_asym_sel.encode(&mut writer);
+        negotiate_info.dhe_sel.encode(&mut writer);
+        negotiate_info.aead_sel.encode(&mut writer);
+        negotiate_info.req_asym_sel.encode(&mut writer);
+        negotiate_info.key_schedule_sel.encode(&mut writer);
+        negotiate_info.opaque_data_support.encode(&mut writer);
+        negotiate_info
+            .req_data_transfer_size_sel
+            .encode(&mut writer);
+        negotiate_info.req_max_spdm_msg_size_sel.encode(&mut writer);
+        negotiate_info
+            .rsp_data_transfer_size_sel
+            .encode(&mut writer);
+        negotiate_info.rsp_max_spdm_msg_size_sel.encode(&mut writer);
+
+        let message_a = self.runtime_info.message_a.as_ref();
+        (message_a.len() as u32).encode(&mut writer);
+        writer
+            .extend_from_slice(message_a)
+            .ok_or(spdm_err!(ENOMEM))?;
+
+        let established = |session: &&SpdmSession| {
+            session.get_session_state() == SpdmSessionState::SpdmSessionEstablished
+        };
+        (self.session.iter().filter(established).count() as u8).encode(&mut writer);
+        for session in self.session.iter().filter(established) {
+            session.save(&mut writer)?;
+        }
+        Ok(writer.used())
+    }
+
+    fn restore_plain_state(&mut self, buffer: &[u8]) -> SpdmResult {
+        let mut reader = Reader::init(buffer);
+        let r = &mut reader;
+        self.negotiate_info = SpdmNegotiateInfo {
+            spdm_version_sel: SpdmVersion::read(r).ok_or(spdm_err!(EINVAL))?,
+            req_capabilities_sel: SpdmRequestCapabilityFlags::read(r).ok_or(spdm_err!(EINVAL))?,
+            rsp_capabilities_sel: SpdmResponseCapabilityFlags::read(r).ok_or(spdm_err!(EINVAL))?,
+            req_ct_exponent_sel: u8::read(r).ok_or(spdm_err!(EINVAL))?,
+            rsp_ct_exponent_sel: u8::read(r).ok_or(spdm_err!(EINVAL))?,
+            measurement_specification_sel: SpdmMeasurementSpecification::read(r)
+                .ok_or(spdm_err!(EINVAL))?,
+            measurement_hash_sel: SpdmMeasurementHashAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            base_hash_sel: SpdmBaseHashAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            base_asym_sel: SpdmBaseAsymAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            dhe_sel: SpdmDheAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            aead_sel: SpdmAeadAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            req_asym_sel: SpdmReqAsymAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            key_schedule_sel: SpdmKeyScheduleAlgo::read(r).ok_or(spdm_err!(EINVAL))?,
+            opaque_data_support: SpdmOpaqueSupport::read(r).ok_or(spdm_err!(EINVAL))?,
+            req_data_transfer_size_sel: u32::read(r).ok_or(spdm_err!(EINVAL))?,
+            req_max_spdm_msg_size_sel: u32::read(r).ok_or(spdm_err!(EINVAL))?,
+            rsp_data_transfer_size_sel: u32::read(r).ok_or(spdm_err!(EINVAL))?,
+            rsp_max_spdm_msg_size_sel: u32::read(r).ok_or(spdm_err!(EINVAL))?,
+        };
+
+        self.reset_runtime_info();
+        let message_a_size = u32::read(r).ok_or(spdm_err!(EINVAL))? as usize;
+        let message_a = r.take(message_a_size).ok_or(spdm_err!(EINVAL))?;
+        self.runtime_info
+            .message_a
+            .append_message(message_a)
+            .ok_or(spdm_err!(EINVAL))?;
+
+        let spdm_version = self.negotiate_info.spdm_version_sel;
+        let session_count = u8::read(r).ok_or(spdm_err!(EINVAL))? as usize;
+        if session_count > self.get_max_session_count() {
+            return spdm_result_err!(EINVAL);
+        }
+        for index in 0..session_count {
+            let restored = self.session[index].restore(spdm_version, r);
+            if let Err(e) = restored {
+                for session in self.session.iter_mut() {
+                    let session_id = session.get_session_id();
+                    if session_id != 0 {
+                        let _ = session.teardown(session_id);
+                    }
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::testlib::*;
+
+    const WRAPPING_KEY: [u8; SPDM_SAVED_STATE_WRAPPING_KEY_SIZE] = [0x5A; 32];
+
+    fn negotiate(context: &mut SpdmContext) {
+        context.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.negotiate_info.rsp_capabilities_sel = SpdmResponseCapabilityFlags::CACHE_CAP;
+        context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        context.negotiate_info.dhe_sel = SpdmDheAlgo::SECP_384_R1;
+        context.negotiate_info.aead_sel = SpdmAeadAlgo::AES_256_GCM;
+        context.negotiate_info.key_schedule_sel = SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE;
+        context.negotiate_info.rsp_data_transfer_size_sel = 0x1200;
+        context
+            .runtime_info
+            .message_a
+            .append_message(&[0x10, 0x84, 0, 0])
+            .unwrap();
+    }
+
+    fn start_session(context: &mut SpdmContext, session_id: u32) {
+        let session = context.get_next_avaiable_session().unwrap();
+        session.setup(session_id).unwrap();
+        session.set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        let dhe_secret = SpdmDheFinalKeyStruct {
+            data_size: 48,
+            data: Box::new([0xAA; SPDM_MAX_DHE_KEY_SIZE]),
+        };
+        session
+            .set_dhe_secret(SpdmVersion::SpdmVersion12, dhe_secret)
+            .unwrap();
+        let th2 = SpdmDigestStruct {
+            data_size: 48,
+            data: Box::new([0xBB; SPDM_MAX_HASH_SIZE]),
+        };
+        session
+            .generate_data_secret(SpdmVersion::SpdmVersion12, &th2)
+            .unwrap();
+        session.set_session_state(SpdmSessionState::SpdmSessionHandshaking);
+    }
+
+    #[test]
+    fn test_case0_save_restore_state() {
+        let mut device_io = MySpdmDeviceIo;
+        let mut transport_encap = PciDoeTransportEncap {};
+        let mut context = new_context(&mut device_io, &mut transport_encap);
+        negotiate(&mut context);
+        start_session(&mut context, 0xFFFE_FFFE);
+        context
+            .get_session_via_id(0xFFFE_FFFE)
+            .unwrap()
+            .set_session_state(SpdmSessionState::SpdmSessionEstablished);
+        // still handshaking, not saved
+        start_session(&mut context, 0xFFFD_FFFD);
+
+        let mut app_buffer = [0u8; 64];
+        let mut secured_buffer = [0u8; 256];
+        let session = context.get_session_via_id(0xFFFE_FFFE).unwrap();
+        session
+            .encode_spdm_secured_message(&[1, 2, 3], &mut secured_buffer, true)
+            .unwrap();
+        // the requester end of the session
+        let mut peer_session = session.clone();
+
+        let mut buffer = Box::new([0u8; SPDM_SAVED_STATE_MAX_SIZE]);
+        assert!(context
+            .save_state(&WRAPPING_KEY[..16], 7, &mut buffer[..])
+            .is_err());
+        assert_eq!(
+            context
+                .save_state(&WRAPPING_KEY, 7, &mut buffer[..SAVED_STATE_HEADER_SIZE])
+                .unwrap_err()
+                .num(),
+            crate::error::SpdmErrorNum::ENOMEM
+        );
+        assert!(context.get_session_via_id(0xFFFE_FFFE).is_some());
+        let used = context
+            .save_state(&WRAPPING_KEY, 7, &mut buffer[..])
+            .unwrap();
+        // handed over to the saved state
+        assert!(context.get_session_via_id(0xFFFE_FFFE).is_none());
+        assert!(context.get_session_via_id(0xFFFD_FFFD).is_some());
+
+        let mut device_io2 = MySpdmDeviceIo;
+        let mut transport_encap2 = PciDoeTransportEncap {};
+        let mut restored = new_context(&mut device_io2, &mut transport_encap2);
+        assert!(restored
+            .restore_state(&[0x5B; 32], 7, &buffer[..used])
+            .is_err());
+        let mut tampered = buffer.clone();
+        tampered[SAVED_STATE_HEADER_SIZE] ^= 1;
+        assert!(restored
+            .restore_state(&WRAPPING_KEY, 7, &tampered[..used])
+            .is_err());
+        // the generation is authenticated
+        let mut tampered = buffer.clone();
+        tampered[8] ^= 1;
+        assert!(restored
+            .restore_state(&WRAPPING_KEY, 6, &tampered[..used])
+            .is_err());
+        // a saved state of another generation is never taken
+        for generation in [6, 8] {
+            assert_eq!(
+                restored
+                    .restore_state(&WRAPPING_KEY, generation, &buffer[..used])
+                    .unwrap_err()
+                    .num(),
+                crate::error::SpdmErrorNum::ESEC
+            );
+        }
+        assert_eq!(restored.get_session_count(), 0);
+
+        assert!(restored
+            .restore_state(&WRAPPING_KEY, 7, &buffer[..used])
+            .is_ok());
+        assert_eq!(
+            restored.negotiate_info.spdm_version_sel,
+            SpdmVersion::SpdmVersion12
+        );
+        assert_eq!(
+            restored.negotiate_info.rsp_capabilities_sel,
+            SpdmResponseCapabilityFlags::CACHE_CAP
+        );
+        assert_eq!(restored.negotiate_info.rsp_data_transfer_size_sel, 0x1200);
+        assert_eq!(
+            restored.runtime_info.message_a.as_ref(),
+            &[0x10, 0x84, 0, 0]
+        );
+        assert_eq!(restored.get_session_count(), 1);
+        assert_eq!(
+            restored
+                .restore_state(&WRAPPING_KEY, 7, &buffer[..used])
+                .unwrap_err()
+                .num(),
+            crate::error::SpdmErrorNum::EBUSY
+        );
+
+        // the restored session goes on where the saved one was
+        let secured_size = peer_session
+            .encode_spdm_secured_message(&[4, 5, 6], &mut secured_buffer, true)
+            .unwrap();
+        let restored_session = restored.get_session_via_id(0xFFFE_FFFE).unwrap();
+        assert_eq!(
+            restored_session.get_session_state(),
+            SpdmSessionState::SpdmSessionEstablished
+        );
+        assert_eq!(restored_session.get_request_direction_sequence_number(), 1);
+        let app_size = restored_session
+            .decode_spdm_secured_message(&secured_buffer[..secured_size], &mut app_buffer, true)
+            .unwrap();
+        assert_eq!(&app_buffer[..app_size], &[4, 5, 6]);
+
+        let secured_size = restored_session
+            .encode_spdm_secured_message(&[7, 8], &mut secured_buffer, false)
+            .unwrap();
+        let app_size = peer_session
+            .decode_spdm_secured_message(&secured_buffer[..secured_size], &mut app_buffer, false)
+            .unwrap();
+        assert_eq!(&app_buffer[..app_size], &[7, 8]);
+    }
+}
@@ -5,12 +5,19 @@
 use super::key_schedule::SpdmKeySchedule;
 use crate::config;
 use crate::crypto;
+use crate::crypto::SpdmAeadKeyHandle;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::{
+    SpdmEventGroupListStruct, SpdmEventStruct, SpdmKeyExchangeMutAuthAttributes,
+    SpdmKeyUpdateOperation, KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_MASK,
+    KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_VALUE,
+};
 
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use codec::enum_builder;
 use codec::{Codec, Reader, Writer};
+use core::any::Any;
 
 use super::*;
 
@@ -28,6 +35,83 @@ enum_builder! {
     }
 }
 
+/// A session ID, ReqSessionID in the upper half and RspSessionID in the
+/// lower one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SpdmSessionId(u32);
+
+impl SpdmSessionId {
+    pub const fn new(req_session_id: u16, rsp_session_id: u16) -> Self {
+        SpdmSessionId(((req_session_id as u32) << 16) | rsp_session_id as u32)
+    }
+
+    pub const fn get_req_session_id(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub const fn get_rsp_session_id(&self) -> u16 {
+        self.0 as u16
+    }
+
+    pub const fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for SpdmSessionId {
+    fn from(session_id: u32) -> Self {
+        SpdmSessionId(session_id)
+    }
+}
+
+impl From<SpdmSessionId> for u32 {
+    fn from(session_id: SpdmSessionId) -> Self {
+        session_id.0
+    }
+}
+
+/// A session of a context, as looked up by SpdmContext::get_session and
+/// get_session_mut. The lookup fails once the session is gone, and for the
+/// null handle, which would name a free session otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpdmSessionHandle(SpdmSessionId);
+
+impl SpdmSessionHandle {
+    pub const fn new(session_id: SpdmSessionId) -> Self {
+        SpdmSessionHandle(session_id)
+    }
+
+    pub const fn get_session_id(&self) -> SpdmSessionId {
+        self.0
+    }
+
+    pub const fn as_u32(&self) -> u32 {
+        self.0.as_u32()
+    }
+
+    pub const fn is_null(&self) -> bool {
+        self.0.as_u32() == 0
+    }
+}
+
+impl From<SpdmSessionId> for SpdmSessionHandle {
+    fn from(session_id: SpdmSessionId) -> Self {
+        SpdmSessionHandle(session_id)
+    }
+}
+
+impl From<u32> for SpdmSessionHandle {
+    fn from(session_id: u32) -> Self {
+        SpdmSessionHandle(SpdmSessionId(session_id))
+    }
+}
+
+impl From<SpdmSessionHandle> for u32 {
+    fn from(handle: SpdmSessionHandle) -> Self {
+        handle.as_u32()
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SpdmSessionCryptoParam {
     pub base_hash_algo: SpdmBaseHashAlgo,
@@ -48,6 +132,82 @@ pub struct SpdmSessionSecretParam {
     pub encryption_key: SpdmAeadKeyStruct,
     pub salt: SpdmAeadIvStruct,
     pub sequence_number: u64,
+    /// Sequence numbers received behind sequence_number, bit 0 for
+    /// sequence_number - 1. Only used with a replay window.
+    pub replay_bitmap: u64,
+    /// Set when the key is held by the AEAD offload engine instead of
+    /// encryption_key.
+    pub key_handle: Option<SpdmAeadKeyHandle>,
+}
+
+impl SpdmSessionSecretParam {
+    pub fn is_key_set(&self) -> bool {
+        self.encryption_key.data_size != 0 || self.key_handle.is_some()
+    }
+
+    // Hand the key over to the AEAD offload engine, if any, and wipe the
+    // software copy.
+    fn offload_key(&mut self, aead_algo: SpdmAeadAlgo) -> SpdmResult {
+        if !crypto::aead::is_offload_registered() {
+            return Ok(());
+        }
+        let key_handle = crypto::aead::import_key(
+            aead_algo,
+            &self.encryption_key.data[..(aead_algo.get_key_size() as usize)],
+        )?;
+        self.key_handle = Some(key_handle);
+        self.encryption_key.zeroize();
+        Ok(())
+    }
+
+    // Full sequence number of a received message from the low bytes its
+    // header carries, None when out of the replay window or received before.
+    fn received_sequence_number(
+        &self,
+        header_sequence_number: u64,
+        transport_param: &SpdmSessionTransportParam,
+    ) -> Option<u64> {
+        let mask = transport_param.sequence_number_mask();
+        let replay_window_size = transport_param.replay_window_size as u64;
+        let ahead = header_sequence_number.wrapping_sub(self.sequence_number) & mask;
+        if ahead == 0 || ahead < replay_window_size {
+            return self.sequence_number.checked_add(ahead);
+        }
+        let behind = self.sequence_number.wrapping_sub(header_sequence_number) & mask;
+        if behind > replay_window_size
+            || behind > self.sequence_number
+            || self.replay_bitmap & (1u64 << (behind - 1)) != 0
+        {
+            return None;
+        }
+        Some(self.sequence_number - behind)
+    }
+
+    // Move past the sequence number of a received message, None when it
+    // could not be decoded.
+    fn update_sequence_number(&mut self, received: Option<u64>, replay_window_size: u8) {
+        match received {
+            // in order, a message failing to decode still used its number
+            None if replay_window_size == 0 => {
+                self.sequence_number = self.sequence_number.saturating_add(1)
+            }
+            None => {}
+            Some(sequence_number) if sequence_number >= self.sequence_number => {
+                let shift = sequence_number - self.sequence_number + 1;
+                self.replay_bitmap = self.replay_bitmap.checked_shl(shift as u32).unwrap_or(0) | 1;
+                self.sequence_number = sequence_number + 1;
+            }
+            Some(sequence_number) => {
+                self.replay_bitmap |= 1u64 << (self.sequence_number - 1 - sequence_number);
+            }
+        }
+    }
+
+    fn release_key(&mut self) {
+        if let Some(key_handle) = self.key_handle.take() {
+            crypto::aead::release_key(key_handle);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
@@ -69,10 +229,118 @@ pub struct SpdmSessionAppliationSecret {
     pub response_direction: SpdmSessionSecretParam,
 }
 
-#[derive(Debug, Clone, Default)]
+impl SpdmSessionAppliationSecret {
+    fn direction(&self, is_requester: bool) -> &SpdmSessionSecretParam {
+        if is_requester {
+            &self.request_direction
+        } else {
+            &self.response_direction
+        }
+    }
+
+    fn direction_mut(&mut self, is_requester: bool) -> &mut SpdmSessionSecretParam {
+        if is_requester {
+            &mut self.request_direction
+        } else {
+            &mut self.response_direction
+        }
+    }
+}
+
+/// Intermediate values of the key schedule of a session, recorded with the
+/// key-schedule-trace feature to check the derivations against reference
+/// vectors. The data keys are the ones of the end of the handshake, before
+/// any KEY_UPDATE. Keys handed to an AEAD offload engine are recorded before.
+#[cfg(feature = "key-schedule-trace")]
+#[derive(Debug, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SpdmKeyScheduleTrace {
+    pub handshake_secret: SpdmDigestStruct,
+    pub master_secret: SpdmDigestStruct,
+    pub th1: SpdmDigestStruct,
+    pub request_handshake_secret: SpdmDigestStruct,
+    pub response_handshake_secret: SpdmDigestStruct,
+    pub request_finished_key: SpdmDigestStruct,
+    pub response_finished_key: SpdmDigestStruct,
+    pub request_handshake_key: SpdmAeadKeyStruct,
+    pub request_handshake_iv: SpdmAeadIvStruct,
+    pub response_handshake_key: SpdmAeadKeyStruct,
+    pub response_handshake_iv: SpdmAeadIvStruct,
+    pub export_master_secret: SpdmDigestStruct,
+    pub th2: SpdmDigestStruct,
+    pub request_data_secret: SpdmDigestStruct,
+    pub response_data_secret: SpdmDigestStruct,
+    pub request_data_key: SpdmAeadKeyStruct,
+    pub request_data_iv: SpdmAeadIvStruct,
+    pub response_data_key: SpdmAeadKeyStruct,
+    pub response_data_iv: SpdmAeadIvStruct,
+}
+
+/// Byte order of the sequence number in the secured message header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmSequenceNumberEndian {
+    Little,
+    Big,
+}
+
+impl Default for SpdmSequenceNumberEndian {
+    fn default() -> Self {
+        SpdmSequenceNumberEndian::Little
+    }
+}
+
+/// Largest replay window, the sequence numbers received behind the expected
+/// one are tracked in a u64.
+pub const SPDM_MAX_REPLAY_WINDOW_SIZE: u8 = 64;
+
+/// Last sequence number of a key whatever the transport.
+pub const SPDM_MAX_SEQUENCE_NUMBER: u64 = u64::MAX - 1;
+
+#[derive(Debug, Clone)]
 pub struct SpdmSessionTransportParam {
     pub sequence_number_count: u8,
     pub max_random_count: u16,
+    pub sequence_number_endian: SpdmSequenceNumberEndian,
+    /// 0 when the transport keeps messages in order. Else how far from the
+    /// expected sequence number a reordered message may be, each number being
+    /// accepted once. Needs the sequence number in the header.
+    pub replay_window_size: u8,
+    /// Last sequence number a key may be used with, u64::MAX - 1 at most so
+    /// that the number never wraps.
+    pub max_sequence_number: u64,
+}
+
+impl Default for SpdmSessionTransportParam {
+    fn default() -> Self {
+        SpdmSessionTransportParam {
+            sequence_number_count: 0,
+            max_random_count: 0,
+            sequence_number_endian: SpdmSequenceNumberEndian::default(),
+            replay_window_size: 0,
+            max_sequence_number: SPDM_MAX_SEQUENCE_NUMBER,
+        }
+    }
+}
+
+impl SpdmSessionTransportParam {
+    // Shift of the sequence number byte at index i of the header.
+    fn sequence_number_shift(&self, i: u8) -> u32 {
+        match self.sequence_number_endian {
+            SpdmSequenceNumberEndian::Little => 8 * i as u32,
+            SpdmSequenceNumberEndian::Big => 8 * (self.sequence_number_count - 1 - i) as u32,
+        }
+    }
+
+    fn sequence_number_left(&self, sequence_number: u64) -> u64 {
+        (self.max_sequence_number + 1).saturating_sub(sequence_number)
+    }
+
+    fn sequence_number_mask(&self) -> u64 {
+        if self.sequence_number_count >= 8 {
+            u64::MAX
+        } else {
+            (1u64 << (8 * self.sequence_number_count)) - 1
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -91,10 +359,102 @@ pub struct SpdmSessionRuntimeInfo {
     pub message_m: Option<HashCtx>,
 }
 
+/// Thresholds rolling the keys of an established session, checked by the
+/// requester before an application message. 0 is no limit.
+///
+/// Messages and bytes (of plain text) count both directions, since the
+/// session was established or its keys last updated. max_age is in the unit
+/// of time::now(), it has no effect without a registered clock. UpdateKey
+/// only rolls the request key, update_all_keys also rolls the response key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpdmKeyUpdatePolicy {
+    pub max_messages: u64,
+    pub max_bytes: u64,
+    pub max_age: u64,
+    pub update_all_keys: bool,
+}
+
+impl SpdmKeyUpdatePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_messages != 0 || self.max_bytes != 0 || self.max_age != 0
+    }
+}
+
+// What the current keys protected so far.
+#[derive(Debug, Clone, Copy, Default)]
+struct SpdmKeyUsage {
+    messages: u64,
+    bytes: u64,
+    since: u64,
+}
+
+pub const SPDM_MAX_SESSION_EVENT_QUEUE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSessionEventInfo {
+    pub subscribe_event_group_count: u8,
+    pub subscribe_list: SpdmEventGroupListStruct,
+    pub event_queue: [SpdmEventStruct; SPDM_MAX_SESSION_EVENT_QUEUE_SIZE],
+    pub event_count: usize,
+    pub sent_event_count: usize,
+    pub next_event_instance_id: u32,
+}
+
+/// Data of the application attached to a session, e.g. the IDE streams set
+/// up in it, dropped when the session is torn down. A clone of the session
+/// starts without any.
+#[derive(Default)]
+pub struct SpdmSessionAppData {
+    data: Option<Box<dyn Any + Send>>,
+}
+
+impl Clone for SpdmSessionAppData {
+    fn clone(&self) -> Self {
+        SpdmSessionAppData::default()
+    }
+}
+
+impl SpdmSessionAppData {
+    /// Replace the data, of any type.
+    pub fn set<T: Any + Send>(&mut self, data: T) {
+        self.data = Some(Box::new(data));
+    }
+
+    /// The data, None if there is none or it is not a T.
+    pub fn get<T: Any + Send>(&self) -> Option<&T> {
+        self.data.as_ref()?.downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: Any + Send>(&mut self) -> Option<&mut T> {
+        self.data.as_mut()?.downcast_mut::<T>()
+    }
+
+    /// Remove the data and return it if it is a T, otherwise it is kept.
+    pub fn take<T: Any + Send>(&mut self) -> Option<T> {
+        match self.data.take()?.downcast::<T>() {
+            Ok(data) => Some(*data),
+            Err(data) => {
+                self.data = Some(data);
+                None
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_none()
+    }
+}
+
 #[derive(Clone)]
 pub struct SpdmSession {
     session_id: u32,
     use_psk: bool,
+    // FINISH and FINISH_RSP go in the clear, no handshake keys
+    handshake_in_the_clear: bool,
     session_state: SpdmSessionState,
     crypto_param: SpdmSessionCryptoParam,
     master_secret: SpdmSessionMasterSecret,
@@ -102,10 +462,23 @@ pub struct SpdmSession {
     application_secret: SpdmSessionAppliationSecret,
     application_secret_backup: SpdmSessionAppliationSecret,
     transport_param: SpdmSessionTransportParam,
-    pub runtime_info: SpdmSessionRuntimeInfo,
+    // boxed, the transcripts are most of a session
+    pub runtime_info: Box<SpdmSessionRuntimeInfo>,
     key_schedule: SpdmKeySchedule,
     pub heartbeat_period: u8, // valid only when HEARTBEAT cap set
+    heartbeat_idle_time: u32,
     pub secure_spdm_version_sel: u8,
+    pub mut_auth_requested: SpdmKeyExchangeMutAuthAttributes, // valid only when MUT_AUTH cap set
+    pub slot_id: u8, // responder cert chain slot, INVALID_SLOT for a PSK session
+    pub req_slot_id: u8,
+    pub session_policy: u8, // KEY_EXCHANGE SessionPolicy, spdm 1.2
+    event_info: SpdmSessionEventInfo,
+    key_update_policy: SpdmKeyUpdatePolicy,
+    key_usage: SpdmKeyUsage,
+    pub app_data: SpdmSessionAppData,
+    last_activity: u64,
+    #[cfg(feature = "key-schedule-trace")]
+    key_schedule_trace: SpdmKeyScheduleTrace,
 }
 
 impl Default for SpdmSession {
@@ -119,6 +492,7 @@ impl SpdmSession {
         SpdmSession {
             session_id: 0,
             use_psk: false,
+            handshake_in_the_clear: false,
             session_state: SpdmSessionState::default(),
             crypto_param: SpdmSessionCryptoParam::default(),
             master_secret: SpdmSessionMasterSecret::default(),
@@ -126,10 +500,22 @@ impl SpdmSession {
             application_secret: SpdmSessionAppliationSecret::default(),
             application_secret_backup: SpdmSessionAppliationSecret::default(),
             transport_param: SpdmSessionTransportParam::default(),
-            runtime_info: SpdmSessionRuntimeInfo::default(),
+            runtime_info: Box::default(),
             key_schedule: SpdmKeySchedule::new(),
             heartbeat_period: 0,
+            heartbeat_idle_time: 0,
             secure_spdm_version_sel: DMTF_SECURE_SPDM_VERSION_11,
+            mut_auth_requested: SpdmKeyExchangeMutAuthAttributes::empty(),
+            slot_id: 0,
+            req_slot_id: 0,
+            session_policy: 0,
+            event_info: SpdmSessionEventInfo::default(),
+            key_update_policy: SpdmKeyUpdatePolicy::default(),
+            key_usage: SpdmKeyUsage::default(),
+            app_data: SpdmSessionAppData::default(),
+            last_activity: 0,
+            #[cfg(feature = "key-schedule-trace")]
+            key_schedule_trace: SpdmKeyScheduleTrace::default(),
         }
     }
 
@@ -152,21 +538,64 @@ impl SpdmSession {
     fn set_default(&mut self) {
         self.session_id = 0;
         self.use_psk = false;
+        self.handshake_in_the_clear = false;
         self.session_state = SpdmSessionState::default();
         self.crypto_param = SpdmSessionCryptoParam::default();
-        self.master_secret = SpdmSessionMasterSecret::default();
-        self.handshake_secret = SpdmSessionHandshakeSecret::default();
-        self.application_secret = SpdmSessionAppliationSecret::default();
+        self.handshake_secret.request_direction.release_key();
+        self.handshake_secret.response_direction.release_key();
+        self.application_secret.request_direction.release_key();
+        self.application_secret.response_direction.release_key();
+        self.application_secret_backup
+            .request_direction
+            .release_key();
+        self.application_secret_backup
+            .response_direction
+            .release_key();
+        self.master_secret.zeroize();
+        self.handshake_secret.zeroize();
+        self.application_secret.zeroize();
+        self.application_secret_backup.zeroize();
+        *self.runtime_info = SpdmSessionRuntimeInfo::default();
+        self.heartbeat_period = 0;
+        self.heartbeat_idle_time = 0;
+        self.mut_auth_requested = SpdmKeyExchangeMutAuthAttributes::empty();
+        self.slot_id = 0;
+        self.req_slot_id = 0;
+        self.session_policy = 0;
+        self.event_info = SpdmSessionEventInfo::default();
+        self.key_update_policy = SpdmKeyUpdatePolicy::default();
+        self.key_usage = SpdmKeyUsage::default();
+        self.app_data.clear();
+        self.last_activity = 0;
+        #[cfg(feature = "key-schedule-trace")]
+        self.key_schedule_trace.zeroize();
+    }
+
+    /// With the termination policy set the responder may keep the session
+    /// across a runtime code or configuration update, otherwise it must
+    /// terminate the session.
+    pub fn is_termination_policy_set(&self) -> bool {
+        self.session_policy & KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_MASK
+            == KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_VALUE
     }
 
     pub fn get_session_id(&self) -> u32 {
         self.session_id
     }
 
+    pub fn get_spdm_session_id(&self) -> SpdmSessionId {
+        SpdmSessionId(self.session_id)
+    }
+
+    pub fn get_handle(&self) -> SpdmSessionHandle {
+        SpdmSessionHandle(self.get_spdm_session_id())
+    }
+
     pub fn setup(&mut self, session_id: u32) -> SpdmResult {
         if self.session_id == 0 {
             self.set_default();
             self.session_id = session_id;
+            self.last_activity = crate::time::now();
             Ok(())
         } else {
             panic!("setup session occupied!");
@@ -186,6 +615,20 @@ impl SpdmSession {
         self.use_psk = use_psk;
     }
 
+    pub fn get_use_psk(&self) -> bool {
+        self.use_psk
+    }
+
+    /// Set before generate_handshake_secret, a session handshaking in the
+    /// clear derives the finished keys only.
+    pub fn set_handshake_in_the_clear(&mut self, handshake_in_the_clear: bool) {
+        self.handshake_in_the_clear = handshake_in_the_clear;
+    }
+
+    pub fn get_handshake_in_the_clear(&self) -> bool {
+        self.handshake_in_the_clear
+    }
+
     pub fn set_dhe_secret(
         &mut self,
         spdm_version: SpdmVersion,
@@ -216,8 +659,15 @@ impl SpdmSession {
             return spdm_result_err!(ESEC);
         };
 
+        #[cfg(feature = "key-schedule-trace")]
+        {
+            self.key_schedule_trace.handshake_secret = handshake_secret.clone();
+            self.key_schedule_trace.master_secret = master_secret.clone();
+        }
         self.master_secret.handshake_secret = handshake_secret;
         self.master_secret.master_secret = master_secret;
+        // the DHE secret is only the input of the handshake secret
+        self.master_secret.dhe_secret.zeroize();
 
         debug!(
             "!!! handshake_secret !!!: {:02x?}\n",
@@ -231,6 +681,10 @@ impl SpdmSession {
         Ok(())
     }
 
+    pub fn get_crypto_param(&self) -> &SpdmSessionCryptoParam {
+        &self.crypto_param
+    }
+
     pub fn set_crypto_param(
         &mut self,
         base_hash_algo: SpdmBaseHashAlgo,
@@ -244,15 +698,211 @@ impl SpdmSession {
         self.crypto_param.key_schedule_algo = key_schedule_algo;
     }
 
-    pub fn set_transport_param(&mut self, sequence_number_count: u8, max_random_count: u16) {
-        self.transport_param.sequence_number_count = sequence_number_count;
-        self.transport_param.max_random_count = max_random_count;
+    pub fn set_transport_param(&mut self, mut transport_param: SpdmSessionTransportParam) {
+        transport_param.sequence_number_count = transport_param.sequence_number_count.min(8);
+        // nothing can be reordered without the sequence number in the header
+        if transport_param.sequence_number_count == 0 {
+            transport_param.replay_window_size = 0;
+        }
+        transport_param.replay_window_size = transport_param
+            .replay_window_size
+            .min(SPDM_MAX_REPLAY_WINDOW_SIZE);
+        transport_param.max_sequence_number = transport_param
+            .max_sequence_number
+            .min(SPDM_MAX_SEQUENCE_NUMBER);
+        self.transport_param = transport_param;
     }
 
     pub fn set_session_state(&mut self, session_state: SpdmSessionState) {
+        if session_state == SpdmSessionState::SpdmSessionEstablished {
+            self.clear_handshake_secret();
+            self.reset_key_usage();
+        }
         self.session_state = session_state;
     }
 
+    // Once the session is established only the data secrets and the export
+    // master secret are needed, wipe everything used by the handshake.
+    fn clear_handshake_secret(&mut self) {
+        let export_master_secret = core::mem::take(&mut self.handshake_secret.export_master_secret);
+        self.handshake_secret.request_direction.release_key();
+        self.handshake_secret.response_direction.release_key();
+        self.handshake_secret.zeroize();
+        self.handshake_secret.export_master_secret = export_master_secret;
+        self.master_secret.zeroize();
+    }
+
+    pub fn get_session_state(&self) -> SpdmSessionState {
+        self.session_state
+    }
+
+    pub fn set_key_update_policy(&mut self, key_update_policy: SpdmKeyUpdatePolicy) {
+        self.key_update_policy = key_update_policy;
+    }
+
+    pub fn get_key_update_policy(&self) -> SpdmKeyUpdatePolicy {
+        self.key_update_policy
+    }
+
+    /// True once the keys protected more than the key update policy allows,
+    /// or a direction is running out of sequence numbers.
+    pub fn is_key_update_due(&self) -> bool {
+        self.get_due_key_update().is_some()
+    }
+
+    /// The KEY_UPDATE operation due, if any. A direction left with the last
+    /// 1/16 of its sequence numbers needs its key updated even without a key
+    /// update policy, UpdateAllKeys being due for the response direction.
+    pub fn get_due_key_update(&self) -> Option<SpdmKeyUpdateOperation> {
+        if self.session_state != SpdmSessionState::SpdmSessionEstablished {
+            return None;
+        }
+        let policy_due = self.is_key_update_policy_due();
+        if self.is_running_out(&self.application_secret.response_direction)
+            || (policy_due && self.key_update_policy.update_all_keys)
+        {
+            Some(SpdmKeyUpdateOperation::SpdmUpdateAllKeys)
+        } else if self.is_running_out(&self.application_secret.request_direction) || policy_due {
+            Some(SpdmKeyUpdateOperation::SpdmUpdateSingleKey)
+        } else {
+            None
+        }
+    }
+
+    fn is_key_update_policy_due(&self) -> bool {
+        let policy = &self.key_update_policy;
+        let usage = &self.key_usage;
+        (policy.max_messages != 0 && usage.messages >= policy.max_messages)
+            || (policy.max_bytes != 0 && usage.bytes >= policy.max_bytes)
+            || (policy.max_age != 0
+                && crate::time::now().saturating_sub(usage.since) >= policy.max_age)
+    }
+
+    fn is_running_out(&self, secret_param: &SpdmSessionSecretParam) -> bool {
+        let count = self.transport_param.max_sequence_number + 1;
+        self.transport_param
+            .sequence_number_left(secret_param.sequence_number)
+            <= count / 16
+    }
+
+    /// Messages the request key may still protect.
+    pub fn get_request_direction_sequence_number_left(&self) -> u64 {
+        self.transport_param
+            .sequence_number_left(self.application_secret.request_direction.sequence_number)
+    }
+
+    /// Messages the response key may still protect.
+    pub fn get_response_direction_sequence_number_left(&self) -> u64 {
+        self.transport_param
+            .sequence_number_left(self.application_secret.response_direction.sequence_number)
+    }
+
+    fn reset_key_usage(&mut self) {
+        self.key_usage = SpdmKeyUsage {
+            messages: 0,
+            bytes: 0,
+            since: crate::time::now(),
+        };
+    }
+
+    fn add_key_usage(&mut self, size: usize) {
+        self.key_usage.messages = self.key_usage.messages.saturating_add(1);
+        self.key_usage.bytes = self.key_usage.bytes.saturating_add(size as u64);
+    }
+
+    /// time::now() when the session was set up or last received a secured
+    /// message from the peer.
+    pub fn get_last_activity(&self) -> u64 {
+        self.last_activity
+    }
+
+    #[cfg(feature = "key-schedule-trace")]
+    pub fn get_key_schedule_trace(&self) -> &SpdmKeyScheduleTrace {
+        &self.key_schedule_trace
+    }
+
+    pub fn reset_heartbeat_idle_time(&mut self) {
+        self.heartbeat_idle_time = 0;
+    }
+
+    // Return true once the session has been idle for the heartbeat period,
+    // elapsed and heartbeat_period are in seconds.
+    pub fn add_heartbeat_idle_time(&mut self, elapsed: u32) -> bool {
+        if self.heartbeat_period == 0 {
+            return false;
+        }
+        self.heartbeat_idle_time = self.heartbeat_idle_time.saturating_add(elapsed);
+        self.heartbeat_idle_time >= self.heartbeat_period as u32
+    }
+
+    pub fn set_event_subscription(
+        &mut self,
+        subscribe_event_group_count: u8,
+        subscribe_list: &SpdmEventGroupListStruct,
+    ) {
+        self.event_info.subscribe_event_group_count = subscribe_event_group_count;
+        self.event_info.subscribe_list = subscribe_list.clone();
+        if subscribe_event_group_count == 0 {
+            // unsubscribe drops all the events not delivered yet.
+            self.event_info.event_count = 0;
+            self.event_info.sent_event_count = 0;
+        }
+    }
+
+    pub fn get_event_subscription(&self) -> (u8, &SpdmEventGroupListStruct) {
+        (
+            self.event_info.subscribe_event_group_count,
+            &self.event_info.subscribe_list,
+        )
+    }
+
+    /// Queue an event for delivery and return its EventInstanceID.
+    pub fn push_event(&mut self, event: &SpdmEventStruct) -> SpdmResult<u32> {
+        if self.event_info.subscribe_event_group_count == 0 {
+            return spdm_result_err!(EPERM);
+        }
+        if self.event_info.event_count >= SPDM_MAX_SESSION_EVENT_QUEUE_SIZE {
+            return spdm_result_err!(EBUSY);
+        }
+        let event_instance_id = self.event_info.next_event_instance_id;
+        self.event_info.next_event_instance_id =
+            self.event_info.next_event_instance_id.wrapping_add(1);
+
+        let index = self.event_info.event_count;
+        self.event_info.event_queue[index] = event.clone();
+        self.event_info.event_queue[index].event_instance_id = event_instance_id;
+        self.event_info.event_count += 1;
+        Ok(event_instance_id)
+    }
+
+    pub fn get_pending_events(&self) -> &[SpdmEventStruct] {
+        &self.event_info.event_queue[..self.event_info.event_count]
+    }
+
+    /// Drop the first count events once they are acknowledged.
+    pub fn remove_events(&mut self, count: usize) {
+        let count = core::cmp::min(count, self.event_info.event_count);
+        self.event_info.event_queue[..self.event_info.event_count].rotate_left(count);
+        self.event_info.event_count -= count;
+    }
+
+    /// Record how many pending events went out in the last SEND_EVENT.
+    pub fn set_sent_event_count(&mut self, count: usize) {
+        self.event_info.sent_event_count = core::cmp::min(count, self.event_info.event_count);
+    }
+
+    pub fn get_sent_event_count(&self) -> usize {
+        self.event_info.sent_event_count
+    }
+
+    /// Drop the events carried by the last SEND_EVENT on EVENT_ACK.
+    pub fn ack_sent_events(&mut self) -> usize {
+        let count = self.event_info.sent_event_count;
+        self.remove_events(count);
+        self.event_info.sent_event_count = 0;
+        count
+    }
+
     pub fn generate_handshake_secret(
         &mut self,
         spdm_version: SpdmVersion,
@@ -261,7 +911,6 @@ impl SpdmSession {
         // generate key
         info!("!!! generate_handshake_secret !!!:\n");
         let hash_algo = self.crypto_param.base_hash_algo;
-        let aead_algo = self.crypto_param.aead_algo;
 
         self.handshake_secret.request_handshake_secret = if let Some(rhs) =
             self.key_schedule.derive_request_handshake_secret(
@@ -322,6 +971,42 @@ impl SpdmSession {
             self.handshake_secret.response_finished_key.as_ref()
         );
 
+        if !self.handshake_in_the_clear {
+            self.generate_handshake_key(spdm_version)?;
+        }
+
+        self.handshake_secret.export_master_secret = if let Some(ems) =
+            self.key_schedule.derive_export_master_secret(
+                spdm_version,
+                hash_algo,
+                self.master_secret.master_secret.as_ref(),
+            ) {
+            ems
+        } else {
+            return spdm_result_err!(ESEC);
+        };
+        #[cfg(feature = "key-schedule-trace")]
+        {
+            let trace = &mut self.key_schedule_trace;
+            trace.th1 = th1.clone();
+            trace.request_handshake_secret = self.handshake_secret.request_handshake_secret.clone();
+            trace.response_handshake_secret =
+                self.handshake_secret.response_handshake_secret.clone();
+            trace.request_finished_key = self.handshake_secret.request_finished_key.clone();
+            trace.response_finished_key = self.handshake_secret.response_finished_key.clone();
+            trace.export_master_secret = self.handshake_secret.export_master_secret.clone();
+        }
+        // all the handshake keys are derived, only the master secret is left
+        self.master_secret.handshake_secret.zeroize();
+
+        Ok(())
+    }
+
+    // The AEAD keys of the handshake, from the handshake secrets.
+    fn generate_handshake_key(&mut self, spdm_version: SpdmVersion) -> SpdmResult {
+        let hash_algo = self.crypto_param.base_hash_algo;
+        let aead_algo = self.crypto_param.aead_algo;
+
         let res = if let Some(aki) = self.key_schedule.derive_aead_key_iv(
             spdm_version,
             hash_algo,
@@ -371,16 +1056,23 @@ impl SpdmSession {
             self.handshake_secret.response_direction.salt.as_ref()
         );
 
-        self.handshake_secret.export_master_secret = if let Some(ems) =
-            self.key_schedule.derive_export_master_secret(
-                spdm_version,
-                hash_algo,
-                self.master_secret.master_secret.as_ref(),
-            ) {
-            ems
-        } else {
-            return spdm_result_err!(ESEC);
-        };
+        #[cfg(feature = "key-schedule-trace")]
+        {
+            let trace = &mut self.key_schedule_trace;
+            let request_direction = &self.handshake_secret.request_direction;
+            let response_direction = &self.handshake_secret.response_direction;
+            trace.request_handshake_key = request_direction.encryption_key.clone();
+            trace.request_handshake_iv = request_direction.salt.clone();
+            trace.response_handshake_key = response_direction.encryption_key.clone();
+            trace.response_handshake_iv = response_direction.salt.clone();
+        }
+
+        self.handshake_secret
+            .request_direction
+            .offload_key(aead_algo)?;
+        self.handshake_secret
+            .response_direction
+            .offload_key(aead_algo)?;
 
         Ok(())
     }
@@ -474,6 +1166,26 @@ impl SpdmSession {
             self.application_secret.response_direction.salt.as_ref()
         );
 
+        #[cfg(feature = "key-schedule-trace")]
+        {
+            let trace = &mut self.key_schedule_trace;
+            let application_secret = &self.application_secret;
+            trace.th2 = th2.clone();
+            trace.request_data_secret = application_secret.request_data_secret.clone();
+            trace.response_data_secret = application_secret.response_data_secret.clone();
+            trace.request_data_key = application_secret.request_direction.encryption_key.clone();
+            trace.request_data_iv = application_secret.request_direction.salt.clone();
+            trace.response_data_key = application_secret.response_direction.encryption_key.clone();
+            trace.response_data_iv = application_secret.response_direction.salt.clone();
+        }
+
+        self.application_secret
+            .request_direction
+            .offload_key(aead_algo)?;
+        self.application_secret
+            .response_direction
+            .offload_key(aead_algo)?;
+
         Ok(())
     }
 
@@ -535,6 +1247,10 @@ impl SpdmSession {
                 self.application_secret.request_direction.salt.as_ref()
             );
             self.application_secret.request_direction.sequence_number = 0;
+            self.application_secret.request_direction.replay_bitmap = 0;
+            self.application_secret
+                .request_direction
+                .offload_key(aead_algo)?;
         }
 
         if update_responder {
@@ -582,6 +1298,10 @@ impl SpdmSession {
                 self.application_secret.response_direction.salt.as_ref()
             );
             self.application_secret.response_direction.sequence_number = 0;
+            self.application_secret.response_direction.replay_bitmap = 0;
+            self.application_secret
+                .response_direction
+                .offload_key(aead_algo)?;
         }
         Ok(())
     }
@@ -595,29 +1315,183 @@ impl SpdmSession {
     ) -> SpdmResult {
         if !use_new_key {
             if update_requester {
+                self.application_secret.request_direction.release_key();
                 self.application_secret.request_data_secret =
-                    self.application_secret_backup.request_data_secret.clone();
+                    core::mem::take(&mut self.application_secret_backup.request_data_secret);
                 self.application_secret.request_direction =
-                    self.application_secret_backup.request_direction.clone();
+                    core::mem::take(&mut self.application_secret_backup.request_direction);
             }
             if update_responder {
+                self.application_secret.response_direction.release_key();
                 self.application_secret.response_data_secret =
-                    self.application_secret_backup.response_data_secret.clone();
+                    core::mem::take(&mut self.application_secret_backup.response_data_secret);
                 self.application_secret.response_direction =
-                    self.application_secret_backup.response_direction.clone();
+                    core::mem::take(&mut self.application_secret_backup.response_direction);
             }
         } else {
             if update_requester {
-                self.application_secret_backup.request_data_secret = SpdmDigestStruct::default();
-                self.application_secret_backup.request_direction =
-                    SpdmSessionSecretParam::default();
+                self.application_secret_backup.request_data_secret.zeroize();
+                self.application_secret_backup
+                    .request_direction
+                    .release_key();
+                self.application_secret_backup.request_direction.zeroize();
             }
             if update_responder {
-                self.application_secret_backup.response_data_secret = SpdmDigestStruct::default();
-                self.application_secret_backup.response_direction =
-                    SpdmSessionSecretParam::default();
+                self.application_secret_backup
+                    .response_data_secret
+                    .zeroize();
+                self.application_secret_backup
+                    .response_direction
+                    .release_key();
+                self.application_secret_backup.response_direction.zeroize();
             }
+            if update_requester || update_responder {
+                self.reset_key_usage();
+            }
+        }
+        Ok(())
+    }
+
+    /// Write what an established session needs to go on after a device
+    /// reset: algorithms, transport parameters, data secrets and sequence
+    /// numbers. The secrets are written in the clear, the caller protects
+    /// the result. EBUSY while a key update waits for its KEY_UPDATE ACK.
+    pub(crate) fn save(&self, writer: &mut Writer) -> SpdmResult {
+        if self.session_state != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+        let backup = &self.application_secret_backup;
+        if backup.request_direction.is_key_set() || backup.response_direction.is_key_set() {
+            return spdm_result_err!(EBUSY);
+        }
+
+        self.session_id.encode(writer);
+        (self.use_psk as u8).encode(writer);
+        self.crypto_param.base_hash_algo.encode(writer);
+        self.crypto_param.dhe_algo.encode(writer);
+        self.crypto_param.aead_algo.encode(writer);
+        self.crypto_param.key_schedule_algo.encode(writer);
+        self.transport_param.sequence_number_count.encode(writer);
+        self.transport_param.max_random_count.encode(writer);
+        ((self.transport_param.sequence_number_endian == SpdmSequenceNumberEndian::Big) as u8)
+            .encode(writer);
+        self.transport_param.replay_window_size.encode(writer);
+        self.transport_param.max_sequence_number.encode(writer);
+        self.secure_spdm_version_sel.encode(writer);
+        self.heartbeat_period.encode(writer);
+        self.slot_id.encode(writer);
+        self.req_slot_id.encode(writer);
+        self.session_policy.encode(writer);
+        save_secret(&self.handshake_secret.export_master_secret, writer)?;
+        save_secret(&self.application_secret.request_data_secret, writer)?;
+        save_secret(&self.application_secret.response_data_secret, writer)?;
+        for direction in [
+            &self.application_secret.request_direction,
+            &self.application_secret.response_direction,
+        ] {
+            direction.sequence_number.encode(writer);
+            direction.replay_bitmap.encode(writer);
+        }
+        Ok(())
+    }
+
+    /// Bring back a session written by save, the AEAD keys being derived
+    /// again from the data secrets. The session must be free.
+    pub(crate) fn restore(&mut self, spdm_version: SpdmVersion, reader: &mut Reader) -> SpdmResult {
+        if self.session_id != 0 {
+            return spdm_result_err!(EBUSY);
+        }
+        self.set_default();
+        let r = self.read_saved(spdm_version, reader);
+        if r.is_err() {
+            self.set_default();
+        }
+        r
+    }
+
+    fn read_saved(&mut self, spdm_version: SpdmVersion, reader: &mut Reader) -> SpdmResult {
+        let session_id = u32::read(reader).ok_or(spdm_err!(EINVAL))?;
+        if session_id == 0 {
+            return spdm_result_err!(EINVAL);
+        }
+        self.use_psk = u8::read(reader).ok_or(spdm_err!(EINVAL))? != 0;
+        self.crypto_param.base_hash_algo =
+            SpdmBaseHashAlgo::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.crypto_param.dhe_algo = SpdmDheAlgo::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.crypto_param.aead_algo = SpdmAeadAlgo::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.crypto_param.key_schedule_algo =
+            SpdmKeyScheduleAlgo::read(reader).ok_or(spdm_err!(EINVAL))?;
+        let transport_param = SpdmSessionTransportParam {
+            sequence_number_count: u8::read(reader).ok_or(spdm_err!(EINVAL))?,
+            max_random_count: u16::read(reader).ok_or(spdm_err!(EINVAL))?,
+            sequence_number_endian: match u8::read(reader).ok_or(spdm_err!(EINVAL))? {
+                0 => SpdmSequenceNumberEndian::Little,
+                _ => SpdmSequenceNumberEndian::Big,
+            },
+            replay_window_size: u8::read(reader).ok_or(spdm_err!(EINVAL))?,
+            max_sequence_number: u64::read(reader).ok_or(spdm_err!(EINVAL))?,
+        };
+        self.set_transport_param(transport_param);
+        self.secure_spdm_version_sel = u8::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.heartbeat_period = u8::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.slot_id = u8::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.req_slot_id = u8::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.session_policy = u8::read(reader).ok_or(spdm_err!(EINVAL))?;
+        self.handshake_secret.export_master_secret = read_secret(reader)?;
+        self.application_secret.request_data_secret = read_secret(reader)?;
+        self.application_secret.response_data_secret = read_secret(reader)?;
+        for direction in [
+            &mut self.application_secret.request_direction,
+            &mut self.application_secret.response_direction,
+        ] {
+            direction.sequence_number = u64::read(reader).ok_or(spdm_err!(EINVAL))?;
+            direction.replay_bitmap = u64::read(reader).ok_or(spdm_err!(EINVAL))?;
+        }
+        // the session id claims the slot, set_default clears it on failure
+        self.session_id = session_id;
+
+        let hash_algo = self.crypto_param.base_hash_algo;
+        let aead_algo = self.crypto_param.aead_algo;
+        let hash_size = hash_algo.get_size();
+        if hash_size == 0
+            || self.application_secret.request_data_secret.data_size != hash_size
+            || self.application_secret.response_data_secret.data_size != hash_size
+            || aead_algo.get_key_size() == 0
+        {
+            return spdm_result_err!(EINVAL);
         }
+        let (key, salt) = self
+            .key_schedule
+            .derive_aead_key_iv(
+                spdm_version,
+                hash_algo,
+                aead_algo,
+                self.application_secret.request_data_secret.as_ref(),
+            )
+            .ok_or(spdm_err!(ESEC))?;
+        self.application_secret.request_direction.encryption_key = key;
+        self.application_secret.request_direction.salt = salt;
+        let (key, salt) = self
+            .key_schedule
+            .derive_aead_key_iv(
+                spdm_version,
+                hash_algo,
+                aead_algo,
+                self.application_secret.response_data_secret.as_ref(),
+            )
+            .ok_or(spdm_err!(ESEC))?;
+        self.application_secret.response_direction.encryption_key = key;
+        self.application_secret.response_direction.salt = salt;
+        self.application_secret
+            .request_direction
+            .offload_key(aead_algo)?;
+        self.application_secret
+            .response_direction
+            .offload_key(aead_algo)?;
+
+        self.session_state = SpdmSessionState::SpdmSessionEstablished;
+        self.reset_key_usage();
+        self.last_activity = crate::time::now();
         Ok(())
     }
 
@@ -737,6 +1611,34 @@ impl SpdmSession {
         )
     }
 
+    /// Derive len bytes of keying material bound to this session from the
+    /// export master secret, e.g. for IDE key programming. Different label or
+    /// context values give independent keys. len is at most SPDM_MAX_HASH_SIZE.
+    pub fn export_keying_material(
+        &self,
+        spdm_version: SpdmVersion,
+        label: &[u8],
+        context: &[u8],
+        len: u16,
+    ) -> SpdmResult<SpdmDigestStruct> {
+        if self.session_state != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+        if len == 0 || len as usize > SPDM_MAX_HASH_SIZE {
+            return spdm_result_err!(EINVAL);
+        }
+        self.key_schedule
+            .derive_exported_keying_material(
+                spdm_version,
+                self.crypto_param.base_hash_algo,
+                self.handshake_secret.export_master_secret.as_ref(),
+                label,
+                context,
+                len,
+            )
+            .ok_or_else(|| spdm_err!(ESEC))
+    }
+
     pub fn export_keys(&mut self) -> (SpdmSessionSecretParam, SpdmSessionSecretParam) {
         (
             SpdmSessionSecretParam {
@@ -747,6 +1649,8 @@ impl SpdmSession {
                     .clone(),
                 salt: self.application_secret.request_direction.salt.clone(),
                 sequence_number: self.application_secret.request_direction.sequence_number,
+                replay_bitmap: self.application_secret.request_direction.replay_bitmap,
+                key_handle: self.application_secret.request_direction.key_handle,
             },
             SpdmSessionSecretParam {
                 encryption_key: self
@@ -756,6 +1660,8 @@ impl SpdmSession {
                     .clone(),
                 salt: self.application_secret.response_direction.salt.clone(),
                 sequence_number: self.application_secret.response_direction.sequence_number,
+                replay_bitmap: self.application_secret.response_direction.replay_bitmap,
+                key_handle: self.application_secret.response_direction.key_handle,
             },
         )
     }
@@ -768,6 +1674,9 @@ impl SpdmSession {
     ) -> SpdmResult<usize> {
         match self.session_state {
             SpdmSessionState::SpdmSessionNotStarted => spdm_result_err!(EINVAL),
+            SpdmSessionState::SpdmSessionHandshaking if self.handshake_in_the_clear => {
+                spdm_result_err!(EINVAL)
+            }
             SpdmSessionState::SpdmSessionHandshaking => {
                 if is_requester {
                     let r = self.encode_msg(
@@ -775,7 +1684,9 @@ impl SpdmSession {
                         secured_buffer,
                         &self.handshake_secret.request_direction,
                     );
-                    self.handshake_secret.request_direction.sequence_number += 1;
+                    if r.is_ok() {
+                        self.handshake_secret.request_direction.sequence_number += 1;
+                    }
                     r
                 } else {
                     let r = self.encode_msg(
@@ -783,7 +1694,9 @@ impl SpdmSession {
                         secured_buffer,
                         &self.handshake_secret.response_direction,
                     );
-                    self.handshake_secret.response_direction.sequence_number += 1;
+                    if r.is_ok() {
+                        self.handshake_secret.response_direction.sequence_number += 1;
+                    }
                     r
                 }
             }
@@ -794,7 +1707,10 @@ impl SpdmSession {
                         secured_buffer,
                         &self.application_secret.request_direction,
                     );
-                    self.application_secret.request_direction.sequence_number += 1;
+                    if r.is_ok() {
+                        self.application_secret.request_direction.sequence_number += 1;
+                    }
+                    self.add_key_usage(app_buffer.len());
                     r
                 } else {
                     let r = self.encode_msg(
@@ -802,7 +1718,10 @@ impl SpdmSession {
                         secured_buffer,
                         &self.application_secret.response_direction,
                     );
-                    self.application_secret.response_direction.sequence_number += 1;
+                    if r.is_ok() {
+                        self.application_secret.response_direction.sequence_number += 1;
+                    }
+                    self.add_key_usage(app_buffer.len());
                     r
                 }
             }
@@ -816,8 +1735,25 @@ impl SpdmSession {
         app_buffer: &mut [u8],
         is_requester: bool,
     ) -> SpdmResult<usize> {
+        let r = self.decode_secured_message_by_state(secured_buffer, app_buffer, is_requester);
+        if r.is_ok() {
+            self.last_activity = crate::time::now();
+        }
+        r
+    }
+
+    fn decode_secured_message_by_state(
+        &mut self,
+        secured_buffer: &[u8],
+        app_buffer: &mut [u8],
+        is_requester: bool,
+    ) -> SpdmResult<usize> {
+        let replay_window_size = self.transport_param.replay_window_size;
         match self.session_state {
             SpdmSessionState::SpdmSessionNotStarted => spdm_result_err!(EINVAL),
+            SpdmSessionState::SpdmSessionHandshaking if self.handshake_in_the_clear => {
+                spdm_result_err!(EINVAL)
+            }
             SpdmSessionState::SpdmSessionHandshaking => {
                 if is_requester {
                     let r = self.decode_msg(
@@ -825,41 +1761,71 @@ impl SpdmSession {
                         app_buffer,
                         &self.handshake_secret.request_direction,
                     );
-                    self.handshake_secret.request_direction.sequence_number += 1;
-                    r
+                    self.handshake_secret
+                        .request_direction
+                        .update_sequence_number(decoded_sequence_number(&r), replay_window_size);
+                    r.map(|(app_length, _)| app_length)
                 } else {
                     let r = self.decode_msg(
                         secured_buffer,
                         app_buffer,
                         &self.handshake_secret.response_direction,
                     );
-                    self.handshake_secret.response_direction.sequence_number += 1;
-                    r
+                    self.handshake_secret
+                        .response_direction
+                        .update_sequence_number(decoded_sequence_number(&r), replay_window_size);
+                    r.map(|(app_length, _)| app_length)
                 }
             }
             SpdmSessionState::SpdmSessionEstablished => {
-                if is_requester {
-                    let r = self.decode_msg(
-                        secured_buffer,
-                        app_buffer,
-                        &self.application_secret.request_direction,
-                    );
-                    self.application_secret.request_direction.sequence_number += 1;
-                    r
-                } else {
-                    let r = self.decode_msg(
-                        secured_buffer,
-                        app_buffer,
-                        &self.application_secret.response_direction,
-                    );
-                    self.application_secret.response_direction.sequence_number += 1;
-                    r
-                }
+                self.decode_application_msg(secured_buffer, app_buffer, is_requester)
             }
             _ => spdm_result_err!(ENOMEM),
         }
     }
 
+    // While a key update is pending, the peer may still use the old key.
+    fn decode_application_msg(
+        &mut self,
+        secured_buffer: &[u8],
+        app_buffer: &mut [u8],
+        is_requester: bool,
+    ) -> SpdmResult<usize> {
+        let replay_window_size = self.transport_param.replay_window_size;
+        let r = self.decode_msg(
+            secured_buffer,
+            app_buffer,
+            self.application_secret.direction(is_requester),
+        );
+        if r.is_err()
+            && self
+                .application_secret_backup
+                .direction(is_requester)
+                .is_key_set()
+        {
+            let backup_r = self.decode_msg(
+                secured_buffer,
+                app_buffer,
+                self.application_secret_backup.direction(is_requester),
+            );
+            self.application_secret_backup
+                .direction_mut(is_requester)
+                .update_sequence_number(decoded_sequence_number(&backup_r), replay_window_size);
+            if backup_r.is_ok() {
+                return backup_r.map(|(app_length, _)| app_length);
+            }
+            // neither key decodes it, the current key moves past it as the
+            // backup key did
+        }
+        self.application_secret
+            .direction_mut(is_requester)
+            .update_sequence_number(decoded_sequence_number(&r), replay_window_size);
+        r.map(|(app_length, _)| {
+            self.add_key_usage(app_length);
+            app_length
+        })
+    }
+
     fn encode_msg(
         &self,
         app_buffer: &[u8],
@@ -870,6 +1836,12 @@ impl SpdmSession {
         let aead_algo = self.crypto_param.aead_algo;
         let transport_param = &self.transport_param;
 
+        // fail closed rather than reuse a nonce
+        if secret_param.sequence_number > transport_param.max_sequence_number {
+            error!("!!! sequence number exhausted, the key must be updated !!!\n");
+            return spdm_result_err!(ERANGE);
+        }
+
         let cipher_text_size = app_buffer.len() + 2;
         let tag_size = aead_algo.get_tag_size() as usize;
 
@@ -881,7 +1853,8 @@ impl SpdmSession {
         if transport_param.sequence_number_count != 0 {
             let sequence_number = secret_param.sequence_number;
             for i in 0..transport_param.sequence_number_count {
-                let s = ((sequence_number >> (8 * i)) & 0xFF) as u8;
+                let s =
+                    ((sequence_number >> transport_param.sequence_number_shift(i)) & 0xFF) as u8;
                 s.encode(&mut writer);
             }
         }
@@ -910,15 +1883,31 @@ impl SpdmSession {
         salt[6] ^= ((sequence_number >> 48) & 0xFF) as u8;
         salt[7] ^= ((sequence_number >> 56) & 0xFF) as u8;
 
-        let (ret_cipher_text_size, ret_tag_size) = crypto::aead::encrypt(
-            aead_algo,
-            &secret_param.encryption_key.data[..(aead_algo.get_key_size() as usize)],
-            &salt[..(aead_algo.get_iv_size() as usize)],
-            &aad_buffer[..aad_size],
-            &plain_text_buf[0..cipher_text_size],
-            &mut tag_buffer[0..tag_size],
-            &mut secured_buffer[aad_size..(aad_size + cipher_text_size)],
-        )?;
+        let iv = &salt[..(aead_algo.get_iv_size() as usize)];
+        let aad = &aad_buffer[..aad_size];
+        let plain_text = &plain_text_buf[0..cipher_text_size];
+        let tag = &mut tag_buffer[0..tag_size];
+        let cipher_text = &mut secured_buffer[aad_size..(aad_size + cipher_text_size)];
+        let (ret_cipher_text_size, ret_tag_size) = match secret_param.key_handle {
+            Some(key_handle) => crypto::aead::encrypt_with_handle(
+                aead_algo,
+                key_handle,
+                iv,
+                aad,
+                plain_text,
+                tag,
+                cipher_text,
+            )?,
+            None => crypto::aead::encrypt(
+                aead_algo,
+                &secret_param.encryption_key.data[..(aead_algo.get_key_size() as usize)],
+                iv,
+                aad,
+                plain_text,
+                tag,
+                cipher_text,
+            )?,
+        };
         assert_eq!(ret_tag_size, tag_size);
         assert_eq!(ret_cipher_text_size, cipher_text_size);
 
@@ -936,7 +1925,7 @@ impl SpdmSession {
         secured_buffer: &[u8],
         app_buffer: &mut [u8],
         secret_param: &SpdmSessionSecretParam,
-    ) -> SpdmResult<usize> {
+    ) -> SpdmResult<(usize, u64)> {
         let session_id = self.session_id;
         let aead_algo = self.crypto_param.aead_algo;
         let transport_param = &self.transport_param;
@@ -948,15 +1937,25 @@ impl SpdmSession {
             error!("session_id mismatch!\n");
             return spdm_result_err!(EINVAL);
         }
-        if transport_param.sequence_number_count != 0 {
-            let sequence_number = secret_param.sequence_number;
+        let sequence_number = if transport_param.sequence_number_count != 0 {
+            let mut header_sequence_number = 0u64;
             for i in 0..transport_param.sequence_number_count {
                 let s = u8::read(&mut reader).ok_or(spdm_err!(EIO))?;
-                if s != ((sequence_number >> (8 * i)) & 0xFF) as u8 {
+                header_sequence_number |= (s as u64) << transport_param.sequence_number_shift(i);
+            }
+            match secret_param.received_sequence_number(header_sequence_number, transport_param) {
+                Some(sequence_number) => sequence_number,
+                None => {
                     info!("sequence_num mismatch!\n");
                     return spdm_result_err!(EINVAL);
                 }
             }
+        } else {
+            secret_param.sequence_number
+        };
+        if sequence_number > transport_param.max_sequence_number {
+            error!("!!! sequence number exhausted, the key must be updated !!!\n");
+            return spdm_result_err!(ERANGE);
         }
         let length = u16::read(&mut reader).ok_or(spdm_err!(EIO))?;
         let aad_size = reader.used();
@@ -978,7 +1977,6 @@ impl SpdmSession {
         let mut plain_text_buf = [0; config::DATA_TRANSFER_SIZE];
 
         let mut salt = secret_param.salt.data.clone();
-        let sequence_number = secret_param.sequence_number;
         salt[0] ^= (sequence_number & 0xFF) as u8;
         salt[1] ^= ((sequence_number >> 8) & 0xFF) as u8;
         salt[2] ^= ((sequence_number >> 16) & 0xFF) as u8;
@@ -988,16 +1986,32 @@ impl SpdmSession {
         salt[6] ^= ((sequence_number >> 48) & 0xFF) as u8;
         salt[7] ^= ((sequence_number >> 56) & 0xFF) as u8;
 
-        let ret_plain_text_size = crypto::aead::decrypt(
-            aead_algo,
-            &secret_param.encryption_key.data[..(aead_algo.get_key_size() as usize)],
-            &salt[..(aead_algo.get_iv_size() as usize)],
-            &secured_buffer[..aad_size],
-            &secured_buffer[aad_size..(aad_size + cipher_text_size)],
-            &secured_buffer
-                [(aad_size + cipher_text_size)..(aad_size + cipher_text_size + tag_size)],
-            &mut plain_text_buf[..cipher_text_size],
-        )?;
+        let iv = &salt[..(aead_algo.get_iv_size() as usize)];
+        let aad = &secured_buffer[..aad_size];
+        let cipher_text = &secured_buffer[aad_size..(aad_size + cipher_text_size)];
+        let tag = &secured_buffer
+            [(aad_size + cipher_text_size)..(aad_size + cipher_text_size + tag_size)];
+        let plain_text = &mut plain_text_buf[..cipher_text_size];
+        let ret_plain_text_size = match secret_param.key_handle {
+            Some(key_handle) => crypto::aead::decrypt_with_handle(
+                aead_algo,
+                key_handle,
+                iv,
+                aad,
+                cipher_text,
+                tag,
+                plain_text,
+            )?,
+            None => crypto::aead::decrypt(
+                aead_algo,
+                &secret_param.encryption_key.data[..(aead_algo.get_key_size() as usize)],
+                iv,
+                aad,
+                cipher_text,
+                tag,
+                plain_text,
+            )?,
+        };
 
         let mut reader = Reader::init(&plain_text_buf);
         let app_length = u16::read(&mut reader).ok_or(spdm_err!(EIO))? as usize;
@@ -1006,10 +2020,33 @@ impl SpdmSession {
         }
 
         app_buffer[..app_length].copy_from_slice(&plain_text_buf[2..(app_length + 2)]);
-        Ok(app_length)
+        Ok((app_length, sequence_number))
     }
 }
 
+fn decoded_sequence_number(r: &SpdmResult<(usize, u64)>) -> Option<u64> {
+    r.as_ref().ok().map(|(_, sequence_number)| *sequence_number)
+}
+fn save_secret(secret: &SpdmDigestStruct, writer: &mut Writer) -> SpdmResult {
+    secret.data_size.encode(writer);
+    writer
+        .extend_from_slice(secret.as_ref())
+        .ok_or(spdm_err!(ENOMEM))?;
+    Ok(())
+}
+
+fn read_secret(reader: &mut Reader) -> SpdmResult<SpdmDigestStruct> {
+    let data_size = u16::read(reader).ok_or(spdm_err!(EINVAL))?;
+    if data_size as usize > SPDM_MAX_HASH_SIZE {
+        return spdm_result_err!(EINVAL);
+    }
+    let mut secret = SpdmDigestStruct::default();
+    secret.data_size = data_size;
+    secret.data[..data_size as usize]
+        .copy_from_slice(reader.take(data_size as usize).ok_or(spdm_err!(EINVAL))?);
+    Ok(secret)
+}
+
 #[cfg(all(test,))]
 mod tests_session {
     use super::*;
@@ -1033,6 +2070,66 @@ mod tests_session {
         assert!(status);
     }
     #[test]
+    fn test_case0_export_keying_material() {
+        let spdm_version = SpdmVersion::SpdmVersion12;
+        let mut session = SpdmSession::new();
+        session.setup(4294901758u32).unwrap();
+        session.set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        let dhe_secret = SpdmDheFinalKeyStruct {
+            data_size: 48,
+            data: Box::new([0x5au8; SPDM_MAX_DHE_KEY_SIZE]),
+        };
+        session.set_dhe_secret(spdm_version, dhe_secret).unwrap();
+        let th1 = SpdmDigestStruct {
+            data_size: 48,
+            data: Box::new([0xa5u8; SPDM_MAX_HASH_SIZE]),
+        };
+        session
+            .generate_handshake_secret(spdm_version, &th1)
+            .unwrap();
+
+        // not before the session is established
+        assert!(session
+            .export_keying_material(spdm_version, b"ide key", b"stream 0", 32)
+            .is_err());
+        session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+
+        let key = session
+            .export_keying_material(spdm_version, b"ide key", b"stream 0", 32)
+            .unwrap();
+        assert_eq!(key.data_size, 32);
+        let same_key = session
+            .export_keying_material(spdm_version, b"ide key", b"stream 0", 32)
+            .unwrap();
+        assert_eq!(key.as_ref(), same_key.as_ref());
+
+        let other_context = session
+            .export_keying_material(spdm_version, b"ide key", b"stream 1", 32)
+            .unwrap();
+        assert_ne!(key.as_ref(), other_context.as_ref());
+        let other_label = session
+            .export_keying_material(spdm_version, b"app key", b"stream 0", 32)
+            .unwrap();
+        assert_ne!(key.as_ref(), other_label.as_ref());
+
+        assert!(session
+            .export_keying_material(spdm_version, b"ide key", b"stream 0", 0)
+            .is_err());
+        assert!(session
+            .export_keying_material(
+                spdm_version,
+                b"ide key",
+                b"stream 0",
+                SPDM_MAX_HASH_SIZE as u16 + 1
+            )
+            .is_err());
+    }
+    #[test]
     fn test_case0_decode_msg() {
         let mut session = SpdmSession::default();
         let session_id = 4294901758u32;
@@ -1058,6 +2155,8 @@ mod tests_session {
                 data: Box::new([10u8; SPDM_MAX_AEAD_IV_SIZE]),
             },
             sequence_number: 100u64,
+            replay_bitmap: 0,
+            key_handle: None,
         };
         session.transport_param.sequence_number_count = 1;
 
@@ -1123,4 +2222,542 @@ mod tests_session {
         let session_id = 4294901758u32;
         let _ = session.teardown(session_id).is_err();
     }
+    #[test]
+    fn test_case1_teardown() {
+        let spdm_version = SpdmVersion::SpdmVersion12;
+        let session_id = 4294901758u32;
+        let mut session = SpdmSession::new();
+        session.setup(session_id).unwrap();
+        session.set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        let dhe_secret = SpdmDheFinalKeyStruct {
+            data_size: 48,
+            data: Box::new([0x5au8; SPDM_MAX_DHE_KEY_SIZE]),
+        };
+        session.set_dhe_secret(spdm_version, dhe_secret).unwrap();
+        assert_eq!(session.master_secret.dhe_secret.data_size, 0);
+        assert!(session
+            .master_secret
+            .dhe_secret
+            .data
+            .iter()
+            .all(|b| *b == 0));
+
+        let th = SpdmDigestStruct {
+            data_size: 48,
+            data: Box::new([0xa5u8; SPDM_MAX_HASH_SIZE]),
+        };
+        session
+            .generate_handshake_secret(spdm_version, &th)
+            .unwrap();
+        assert_eq!(session.master_secret.handshake_secret.data_size, 0);
+        session.generate_data_secret(spdm_version, &th).unwrap();
+
+        session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+        assert_eq!(session.master_secret.master_secret.data_size, 0);
+        assert_eq!(session.handshake_secret.request_finished_key.data_size, 0);
+        assert_eq!(
+            session.handshake_secret.response_handshake_secret.data_size,
+            0
+        );
+        assert!(session
+            .handshake_secret
+            .request_direction
+            .encryption_key
+            .data
+            .iter()
+            .all(|b| *b == 0));
+        assert!(session
+            .export_keying_material(spdm_version, b"ide key", b"stream 0", 32)
+            .is_ok());
+
+        // rolling back a key update leaves no copy in the backup
+        session
+            .create_data_secret_update(spdm_version, true, true)
+            .unwrap();
+        session
+            .activate_data_secret_update(spdm_version, true, true, false)
+            .unwrap();
+        assert_eq!(
+            session
+                .application_secret_backup
+                .request_data_secret
+                .data_size,
+            0
+        );
+        assert_eq!(
+            session
+                .application_secret_backup
+                .response_data_secret
+                .data_size,
+            0
+        );
+
+        session.teardown(session_id).unwrap();
+        assert_eq!(session.application_secret.request_data_secret.data_size, 0);
+        assert!(session
+            .application_secret
+            .response_data_secret
+            .data
+            .iter()
+            .all(|b| *b == 0));
+        assert_eq!(session.handshake_secret.export_master_secret.data_size, 0);
+    }
+    #[test]
+    fn test_case0_push_event() {
+        let mut session = SpdmSession::default();
+        let event = SpdmEventStruct::default();
+        assert!(session.push_event(&event).is_err());
+
+        let subscribe_list = SpdmEventGroupListStruct {
+            data_size: 4,
+            ..Default::default()
+        };
+        session.set_event_subscription(1, &subscribe_list);
+        for i in 0..SPDM_MAX_SESSION_EVENT_QUEUE_SIZE {
+            assert_eq!(session.push_event(&event).unwrap(), i as u32);
+        }
+        assert!(session.push_event(&event).is_err());
+
+        session.remove_events(3);
+        assert_eq!(
+            session.get_pending_events().len(),
+            SPDM_MAX_SESSION_EVENT_QUEUE_SIZE - 3
+        );
+        assert_eq!(session.get_pending_events()[0].event_instance_id, 3);
+
+        session.set_event_subscription(0, &SpdmEventGroupListStruct::default());
+        assert!(session.get_pending_events().is_empty());
+    }
+    #[test]
+    fn test_case0_chacha20_poly1305_secured_message() {
+        let session_id = 0xfffefffeu32;
+        let secret_param = SpdmSessionSecretParam {
+            encryption_key: SpdmAeadKeyStruct {
+                data_size: 32,
+                data: {
+                    let mut k = Box::new([0u8; SPDM_MAX_AEAD_KEY_SIZE]);
+                    for (i, d) in k.iter_mut().enumerate() {
+                        *d = i as u8 + 1;
+                    }
+                    k
+                },
+            },
+            salt: SpdmAeadIvStruct {
+                data_size: 12,
+                data: {
+                    let mut k = Box::new([0u8; SPDM_MAX_AEAD_IV_SIZE]);
+                    for (i, d) in k.iter_mut().enumerate() {
+                        *d = i as u8 + 0x40;
+                    }
+                    k
+                },
+            },
+            sequence_number: 1u64,
+            replay_bitmap: 0,
+            key_handle: None,
+        };
+        let mut requester_session = SpdmSession::default();
+        let mut responder_session = SpdmSession::default();
+        for session in [&mut requester_session, &mut responder_session] {
+            session.setup(session_id).unwrap();
+            session.set_crypto_param(
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+                SpdmDheAlgo::SECP_384_R1,
+                SpdmAeadAlgo::CHACHA20_POLY1305,
+                SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+            );
+            session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+            session.transport_param.sequence_number_count = 2;
+            session.application_secret.request_direction = secret_param.clone();
+        }
+
+        // same key, salt and sequence number give the bytes libspdm puts on the wire
+        let expected = [
+            0xfe, 0xff, 0xfe, 0xff, 0x01, 0x00, 0x16, 0x00, 0x9d, 0x78, 0xb1, 0x8b, 0xb5, 0x4b,
+            0x24, 0x6b, 0xfa, 0x83, 0xdd, 0xfc, 0x01, 0x20, 0x39, 0xb6, 0x72, 0xe5, 0xd7, 0xfd,
+            0xd2, 0x0a,
+        ];
+        let app_buffer = [0x12u8, 0x84, 0x00, 0x00];
+        let mut secured_buffer = [0u8; 64];
+        let mut decoded_buffer = [0u8; 64];
+        let used = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut secured_buffer, true)
+            .unwrap();
+        assert_eq!(&secured_buffer[..used], &expected[..]);
+
+        let decoded = responder_session
+            .decode_spdm_secured_message(&secured_buffer[..used], &mut decoded_buffer, true)
+            .unwrap();
+        assert_eq!(&decoded_buffer[..decoded], &app_buffer);
+
+        let used = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut secured_buffer, true)
+            .unwrap();
+        secured_buffer[used - 1] ^= 0x01;
+        assert!(responder_session
+            .decode_spdm_secured_message(&secured_buffer[..used], &mut decoded_buffer, true)
+            .is_err());
+    }
+    #[test]
+    fn test_case0_replay_window() {
+        let session_id = 4294901758u32;
+        let secret_param = SpdmSessionSecretParam {
+            encryption_key: SpdmAeadKeyStruct {
+                data_size: 32,
+                data: Box::new([10u8; SPDM_MAX_AEAD_KEY_SIZE]),
+            },
+            salt: SpdmAeadIvStruct {
+                data_size: 12,
+                data: Box::new([10u8; SPDM_MAX_AEAD_IV_SIZE]),
+            },
+            sequence_number: 0x00fe,
+            replay_bitmap: 0,
+            key_handle: None,
+        };
+        let mut requester_session = SpdmSession::default();
+        let mut responder_session = SpdmSession::default();
+        for session in [&mut requester_session, &mut responder_session] {
+            session.setup(session_id).unwrap();
+            session.set_crypto_param(
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+                SpdmDheAlgo::SECP_384_R1,
+                SpdmAeadAlgo::AES_256_GCM,
+                SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+            );
+            session.set_transport_param(SpdmSessionTransportParam {
+                sequence_number_count: 1,
+                max_random_count: 0,
+                sequence_number_endian: SpdmSequenceNumberEndian::Little,
+                replay_window_size: 4,
+                ..Default::default()
+            });
+            session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+            session.application_secret.request_direction = secret_param.clone();
+        }
+
+        // sequence numbers 0xfe to 0x102, the 1 byte header wraps
+        let app_buffer = [100u8; 16];
+        let mut secured_buffer = [[0u8; 64]; 5];
+        let mut used = [0usize; 5];
+        for (secured_buffer, used) in secured_buffer.iter_mut().zip(used.iter_mut()) {
+            *used = requester_session
+                .encode_spdm_secured_message(&app_buffer, secured_buffer, true)
+                .unwrap();
+        }
+        assert_eq!(secured_buffer[2][4], 0x00);
+
+        let mut decoded_buffer = [0u8; 64];
+        for (i, accepted) in [
+            (1, true),
+            (0, true),
+            (0, false),
+            (3, true),
+            (2, true),
+            (3, false),
+            (4, true),
+        ] {
+            let r = responder_session.decode_spdm_secured_message(
+                &secured_buffer[i][..used[i]],
+                &mut decoded_buffer,
+                true,
+            );
+            assert_eq!(r.is_ok(), accepted);
+        }
+        assert_eq!(
+            responder_session
+                .application_secret
+                .request_direction
+                .sequence_number,
+            0x103
+        );
+
+        // in order, a message skipped is an error
+        responder_session.set_transport_param(SpdmSessionTransportParam {
+            sequence_number_count: 1,
+            ..Default::default()
+        });
+        responder_session.application_secret.request_direction = secret_param.clone();
+        assert!(responder_session
+            .decode_spdm_secured_message(&secured_buffer[1][..used[1]], &mut decoded_buffer, true)
+            .is_err());
+    }
+    #[test]
+    fn test_case0_sequence_number_endian() {
+        let mut session = SpdmSession::default();
+        session.setup(4294901758u32).unwrap();
+        session.set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+        session.application_secret.request_direction.sequence_number = 0x0102;
+
+        let mut secured_buffer = [0u8; 64];
+        for (endian, header) in [
+            (SpdmSequenceNumberEndian::Little, [0x02, 0x01]),
+            (SpdmSequenceNumberEndian::Big, [0x01, 0x02]),
+        ] {
+            session.set_transport_param(SpdmSessionTransportParam {
+                sequence_number_count: 2,
+                max_random_count: 0,
+                sequence_number_endian: endian,
+                replay_window_size: 0,
+                ..Default::default()
+            });
+            session
+                .encode_spdm_secured_message(&[1u8; 4], &mut secured_buffer, true)
+                .unwrap();
+            assert_eq!(&secured_buffer[4..6], &header);
+            session.application_secret.request_direction.sequence_number = 0x0102;
+        }
+    }
+    #[test]
+    fn test_case0_sequence_number_exhaustion() {
+        let session_id = 4294901758u32;
+        let secret_param = SpdmSessionSecretParam {
+            encryption_key: SpdmAeadKeyStruct {
+                data_size: 32,
+                data: Box::new([10u8; SPDM_MAX_AEAD_KEY_SIZE]),
+            },
+            salt: SpdmAeadIvStruct {
+                data_size: 12,
+                data: Box::new([10u8; SPDM_MAX_AEAD_IV_SIZE]),
+            },
+            sequence_number: 29,
+            replay_bitmap: 0,
+            key_handle: None,
+        };
+        let mut requester_session = SpdmSession::default();
+        let mut responder_session = SpdmSession::default();
+        for session in [&mut requester_session, &mut responder_session] {
+            session.setup(session_id).unwrap();
+            session.set_crypto_param(
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+                SpdmDheAlgo::SECP_384_R1,
+                SpdmAeadAlgo::AES_256_GCM,
+                SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+            );
+            session.set_transport_param(SpdmSessionTransportParam {
+                sequence_number_count: 1,
+                max_sequence_number: 31,
+                ..Default::default()
+            });
+            session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+            session.application_secret.request_direction = secret_param.clone();
+        }
+
+        let app_buffer = [100u8; 16];
+        let mut secured_buffer = [[0u8; 64]; 3];
+        let mut used = [0usize; 3];
+        assert_eq!(requester_session.get_due_key_update(), None);
+        for (secured_buffer, used) in secured_buffer.iter_mut().zip(used.iter_mut()) {
+            *used = requester_session
+                .encode_spdm_secured_message(&app_buffer, secured_buffer, true)
+                .unwrap();
+        }
+        // the last 1/16 of the sequence numbers
+        assert_eq!(
+            requester_session.get_due_key_update(),
+            Some(SpdmKeyUpdateOperation::SpdmUpdateSingleKey)
+        );
+
+        // no wrap, 32 is past the last sequence number
+        assert_eq!(
+            requester_session.get_request_direction_sequence_number_left(),
+            0
+        );
+        let err = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut [0u8; 64], true)
+            .unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ERANGE);
+        assert_eq!(
+            requester_session.get_request_direction_sequence_number(),
+            32
+        );
+
+        let mut decoded_buffer = [0u8; 64];
+        for (secured_buffer, used) in secured_buffer.iter().zip(used.iter()) {
+            assert!(responder_session
+                .decode_spdm_secured_message(&secured_buffer[..*used], &mut decoded_buffer, true)
+                .is_ok());
+        }
+        assert_eq!(
+            responder_session.get_request_direction_sequence_number_left(),
+            0
+        );
+
+        responder_session
+            .application_secret
+            .response_direction
+            .sequence_number = 30;
+        assert_eq!(
+            responder_session.get_due_key_update(),
+            Some(SpdmKeyUpdateOperation::SpdmUpdateAllKeys)
+        );
+    }
+    #[test]
+    fn test_case0_decode_spdm_secured_message_key_update() {
+        let session_id = 4294901758u32;
+        let secret_param = SpdmSessionSecretParam {
+            encryption_key: SpdmAeadKeyStruct {
+                data_size: 32,
+                data: Box::new([10u8; SPDM_MAX_AEAD_KEY_SIZE]),
+            },
+            salt: SpdmAeadIvStruct {
+                data_size: 12,
+                data: Box::new([10u8; SPDM_MAX_AEAD_IV_SIZE]),
+            },
+            sequence_number: 0u64,
+            replay_bitmap: 0,
+            key_handle: None,
+        };
+        let mut requester_session = SpdmSession::default();
+        let mut responder_session = SpdmSession::default();
+        for session in [&mut requester_session, &mut responder_session] {
+            session.setup(session_id).unwrap();
+            session.set_crypto_param(
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+                SpdmDheAlgo::SECP_384_R1,
+                SpdmAeadAlgo::AES_256_GCM,
+                SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+            );
+            session.set_session_state(SpdmSessionState::SpdmSessionEstablished);
+            session.application_secret.request_direction = secret_param.clone();
+        }
+
+        // the responder rolled its request key, the requester did not yet
+        responder_session
+            .application_secret_backup
+            .request_direction = secret_param.clone();
+        responder_session
+            .application_secret
+            .request_direction
+            .encryption_key
+            .data = Box::new([20u8; SPDM_MAX_AEAD_KEY_SIZE]);
+
+        let app_buffer = [100u8; 16];
+        let mut secured_buffer = [0u8; 64];
+        let mut decoded_buffer = [0u8; 64];
+        let used = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut secured_buffer, true)
+            .unwrap();
+        let decoded = responder_session
+            .decode_spdm_secured_message(&secured_buffer[..used], &mut decoded_buffer, true)
+            .unwrap();
+        assert_eq!(&decoded_buffer[..decoded], &app_buffer);
+
+        // neither key decodes it, both move past its sequence number
+        let used = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut secured_buffer, true)
+            .unwrap();
+        secured_buffer[used - 1] ^= 1;
+        assert!(responder_session
+            .decode_spdm_secured_message(&secured_buffer[..used], &mut decoded_buffer, true)
+            .is_err());
+        assert_eq!(
+            responder_session
+                .application_secret_backup
+                .request_direction
+                .sequence_number,
+            2
+        );
+        assert_eq!(
+            responder_session
+                .application_secret
+                .request_direction
+                .sequence_number,
+            1
+        );
+
+        // once the new key is verified the old one is gone
+        responder_session
+            .activate_data_secret_update(SpdmVersion::SpdmVersion12, true, false, true)
+            .unwrap();
+        let used = requester_session
+            .encode_spdm_secured_message(&app_buffer, &mut secured_buffer, true)
+            .unwrap();
+        assert!(responder_session
+            .decode_spdm_secured_message(&secured_buffer[..used], &mut decoded_buffer, true)
+            .is_err());
+    }
+
+    #[test]
+    fn test_case0_session_id() {
+        let session_id = SpdmSessionId::new(0x1234, 0xABCD);
+        assert_eq!(session_id.get_req_session_id(), 0x1234);
+        assert_eq!(session_id.get_rsp_session_id(), 0xABCD);
+        assert_eq!(session_id.as_u32(), 0x1234_ABCD);
+        assert_eq!(SpdmSessionId::from(0x1234_ABCDu32), session_id);
+        assert_eq!(u32::from(session_id), 0x1234_ABCD);
+
+        let mut session = SpdmSession::new();
+        session.setup(0x1234_ABCD).unwrap();
+        assert_eq!(session.get_spdm_session_id(), session_id);
+    }
+
+    #[test]
+    fn test_case0_session_handle() {
+        use crate::testlib::*;
+        let mut device_io = MySpdmDeviceIo;
+        let mut transport_encap = PciDoeTransportEncap {};
+        let mut context = new_context(&mut device_io, &mut transport_encap);
+        let handle = SpdmSessionHandle::from(SpdmSessionId::new(0x1234, 0xABCD));
+        assert_eq!(handle.as_u32(), 0x1234_ABCD);
+        assert_eq!(SpdmSessionHandle::from(0x1234_ABCDu32), handle);
+        assert!(SpdmSessionHandle::from(0u32).is_null());
+
+        assert!(context.get_session(handle).is_err());
+        context.session[1].setup(handle.as_u32()).unwrap();
+        assert_eq!(context.session[1].get_handle(), handle);
+        assert_eq!(context.get_session(handle).unwrap().get_handle(), handle);
+        context
+            .get_session_mut(0x1234_ABCDu32)
+            .unwrap()
+            .set_session_state(SpdmSessionState::SpdmSessionEstablished);
+        assert_eq!(
+            context.get_session(handle).unwrap().get_session_state(),
+            SpdmSessionState::SpdmSessionEstablished
+        );
+
+        // the free sessions are not reachable through the null handle
+        assert!(context.get_session(0u32).is_err());
+        assert!(context.get_session_mut(0u32).is_err());
+
+        context.session[1].teardown(handle.as_u32()).unwrap();
+        assert!(context.get_session_mut(handle).is_err());
+    }
+
+    #[test]
+    fn test_case0_generate_session_id_half() {
+        use crate::testlib::*;
+        let mut device_io = MySpdmDeviceIo;
+        let mut transport_encap = PciDoeTransportEncap {};
+        let mut context = new_context(&mut device_io, &mut transport_encap);
+        context.session[0]
+            .setup(SpdmSessionId::new(0xFFFF, 0xFFFF).as_u32())
+            .unwrap();
+        context.session[1]
+            .setup(SpdmSessionId::new(0x0001, 0x0001).as_u32())
+            .unwrap();
+
+        for is_requester in [true, false] {
+            let half = context.generate_session_id_half(is_requester).unwrap();
+            assert_ne!(half, 0);
+            assert!(!context.session.iter().any(|session| {
+                let session_id = session.get_spdm_session_id();
+                if is_requester {
+                    session_id.get_req_session_id() == half
+                } else {
+                    session_id.get_rsp_session_id() == half
+                }
+            }));
+        }
+    }
 }
@@ -0,0 +1,101 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Transcript and parameters of an established session, for a platform to
+//! archive as evidence of the attestation the session carries and check it
+//! offline later.
+//!
+//! TH2, which the data keys of the session are derived from, is the hash of
+//! message_a, the hash of the responder certificate chain (none for a PSK
+//! session), message_k and message_f. With hash-update only the running
+//! hashes are kept, so only TH2 is exported.
+
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct SpdmSessionAudit {
+    pub session_id: u32,
+    pub use_psk: bool,
+    pub spdm_version: SpdmVersion,
+    pub secure_spdm_version: u8,
+    pub base_hash_algo: SpdmBaseHashAlgo,
+    pub base_asym_algo: SpdmBaseAsymAlgo,
+    pub req_asym_algo: SpdmReqAsymAlgo,
+    pub dhe_algo: SpdmDheAlgo,
+    pub aead_algo: SpdmAeadAlgo,
+    pub key_schedule_algo: SpdmKeyScheduleAlgo,
+    pub slot_id: u8,
+    pub req_slot_id: u8,
+    pub mut_auth_requested: SpdmKeyExchangeMutAuthAttributes,
+    pub session_policy: u8,
+    pub heartbeat_period: u8,
+    pub cert_chain_hash: Option<SpdmDigestStruct>,
+    #[cfg(not(feature = "hash-update"))]
+    pub message_a: ManagedBuffer,
+    #[cfg(not(feature = "hash-update"))]
+    pub message_k: ManagedBuffer,
+    #[cfg(not(feature = "hash-update"))]
+    pub message_f: ManagedBuffer,
+    pub th2: SpdmDigestStruct,
+}
+
+impl<'a> SpdmContext<'a> {
+    /// Audit record of the established session session_id. is_requester
+    /// tells where the responder certificate chain hash is taken from.
+    pub(crate) fn get_session_audit(
+        &self,
+        session_id: u32,
+        is_requester: bool,
+    ) -> SpdmResult<Box<SpdmSessionAudit>> {
+        let session = self
+            .get_immutable_session_via_id(session_id)
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        if session.get_session_state() != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+        let use_psk = session.get_use_psk();
+        let cert_chain_hash = if use_psk {
+            None
+        } else if is_requester {
+            Some(
+                self.get_certchain_hash_req(session.slot_id, false)
+                    .ok_or_else(|| spdm_err!(EINVAL))?,
+            )
+        } else {
+            Some(
+                self.get_certchain_hash_rsp(session.slot_id, false)
+                    .ok_or_else(|| spdm_err!(EINVAL))?,
+            )
+        };
+
+        let th2 = self.get_session_transcript_hash(session_id, is_requester)?;
+
+        let crypto_param = session.get_crypto_param();
+        Ok(Box::new(SpdmSessionAudit {
+            session_id,
+            use_psk,
+            spdm_version: self.negotiate_info.spdm_version_sel,
+            secure_spdm_version: session.secure_spdm_version_sel,
+            base_hash_algo: crypto_param.base_hash_algo,
+            base_asym_algo: self.negotiate_info.base_asym_sel,
+            req_asym_algo: self.negotiate_info.req_asym_sel,
+            dhe_algo: crypto_param.dhe_algo,
+            aead_algo: crypto_param.aead_algo,
+            key_schedule_algo: crypto_param.key_schedule_algo,
+            slot_id: session.slot_id,
+            req_slot_id: session.req_slot_id,
+            mut_auth_requested: session.mut_auth_requested,
+            session_policy: session.session_policy,
+            heartbeat_period: session.heartbeat_period,
+            cert_chain_hash,
+            #[cfg(not(feature = "hash-update"))]
+            message_a: self.runtime_info.message_a.clone(),
+            #[cfg(not(feature = "hash-update"))]
+            message_k: session.runtime_info.message_k.clone(),
+            #[cfg(not(feature = "hash-update"))]
+            message_f: session.runtime_info.message_f.clone(),
+            th2,
+        }))
+    }
+}
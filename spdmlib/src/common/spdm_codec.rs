@@ -8,7 +8,7 @@ use crate::protocol::{
     gen_array, SpdmCertChain, SpdmCertChainData, SpdmDheExchangeStruct, SpdmDigestStruct,
     SpdmDmtfMeasurementRepresentation, SpdmDmtfMeasurementStructure, SpdmDmtfMeasurementType,
     SpdmMeasurementBlockStructure, SpdmMeasurementRecordStructure, SpdmMeasurementSpecification,
-    SpdmSignatureStruct, SPDM_MAX_ASYM_KEY_SIZE, SPDM_MAX_DHE_KEY_SIZE, SPDM_MAX_HASH_SIZE,
+    SpdmSignatureStruct, SPDM_MAX_ASYM_KEY_SIZE, SPDM_MAX_HASH_SIZE,
 };
 use codec::{u24, Codec, Reader, Writer};
 use core::fmt::Debug;
@@ -166,12 +166,7 @@ impl SpdmCodec for SpdmDheExchangeStruct {
         }
     }
     fn spdm_read(context: &mut SpdmContext, r: &mut Reader) -> Option<SpdmDheExchangeStruct> {
-        let data_size = context.get_dhe_key_size();
-        let mut data = [0u8; SPDM_MAX_DHE_KEY_SIZE];
-        for d in data.iter_mut().take(data_size as usize) {
-            *d = u8::read(r)?;
-        }
-        Some(SpdmDheExchangeStruct { data_size, data })
+        SpdmDheExchangeStruct::read_with_size(r, context.get_dhe_key_size())
     }
 }
 
@@ -231,6 +226,9 @@ impl SpdmCodec for SpdmDmtfMeasurementStructure {
         // TBD: Check measurement_hash
 
         let value_size = u16::read(r)?;
+        if value_size as usize > config::MAX_SPDM_MEASUREMENT_VALUE_LEN {
+            return None;
+        }
         let mut value = [0u8; config::MAX_SPDM_MEASUREMENT_VALUE_LEN];
         for v in value.iter_mut().take(value_size as usize) {
             *v = u8::read(r)?;
@@ -264,6 +262,7 @@ impl SpdmCodec for SpdmMeasurementBlockStructure {
             measurement_specification,
             measurement_size,
             measurement,
+            tcb: false,
         })
     }
 }
@@ -0,0 +1,88 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Hashes of the transcripts signed or keyed so far, for a policy engine or
+//! a debugging tool to compare with the ones the peer logged.
+//!
+//! M1/M2 is the transcript of CHALLENGE_AUTH: message_a, message_b and
+//! message_c. TH2 is the transcript of an established session: message_a,
+//! the hash of the responder certificate chain (none for a PSK session),
+//! message_k and message_f.
+
+use super::*;
+
+impl<'a> SpdmContext<'a> {
+    /// Hash of the M1/M2 transcript so far, the one the signature of the
+    /// next CHALLENGE_AUTH covers.
+    pub fn get_m1m2_hash(&self) -> SpdmResult<SpdmDigestStruct> {
+        #[cfg(not(feature = "hash-update"))]
+        let m1m2_hash = {
+            let mut transcript = ManagedBuffer::default();
+            transcript
+                .append_message(self.runtime_info.message_a.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            transcript
+                .append_message(self.runtime_info.message_b.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            transcript
+                .append_message(self.runtime_info.message_c.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            crypto::hash::hash_all(self.negotiate_info.base_hash_sel, transcript.as_ref())
+                .ok_or_else(|| spdm_err!(EFAULT))?
+        };
+        #[cfg(feature = "hash-update")]
+        let m1m2_hash = crypto::hash::hash_ctx_finalize(
+            self.runtime_info
+                .message_m
+                .clone()
+                .ok_or_else(|| spdm_err!(EINVAL))?,
+        )
+        .ok_or_else(|| spdm_err!(EFAULT))?;
+
+        Ok(m1m2_hash)
+    }
+
+    /// TH2 of session session_id, an error until the session is
+    /// established. is_requester tells where the responder certificate
+    /// chain hash is taken from.
+    #[cfg_attr(feature = "hash-update", allow(unused_variables))]
+    pub(crate) fn get_session_transcript_hash(
+        &self,
+        session_id: u32,
+        is_requester: bool,
+    ) -> SpdmResult<SpdmDigestStruct> {
+        let session = self
+            .get_immutable_session_via_id(session_id)
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        // message_k holds the verify data by the time TH1 is computed
+        if session.get_session_state() != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+
+        #[cfg(not(feature = "hash-update"))]
+        let transcript_hash = {
+            let use_psk = session.get_use_psk();
+            let message_k = &session.runtime_info.message_k;
+            let message_f = Some(&session.runtime_info.message_f);
+            if is_requester {
+                self.calc_req_transcript_hash(session.slot_id, use_psk, message_k, message_f)?
+            } else {
+                self.calc_rsp_transcript_hash(session.slot_id, use_psk, message_k, message_f)?
+            }
+        };
+        // the running hash of message_f starts from the one of message_k
+        #[cfg(feature = "hash-update")]
+        let transcript_hash = {
+            let message_kf = session
+                .runtime_info
+                .message_f
+                .as_ref()
+                .or(session.runtime_info.message_k.as_ref())
+                .ok_or_else(|| spdm_err!(EINVAL))?;
+            crypto::hash::hash_ctx_finalize(message_kf.clone()).ok_or_else(|| spdm_err!(EFAULT))?
+        };
+
+        Ok(transcript_hash)
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Read-only inspection of an X.509 certificate: validity, subject, SAN, key
+//! usage and the TCG DICE TcbInfo / UeID extensions. Signatures are not checked here,
+//! so only inspect certificates of an already verified chain.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::crypto;
+use crate::error::{spdm_result_err, SpdmResult};
+
+const DER_TAG_BOOLEAN: u8 = 0x01;
+const DER_TAG_INTEGER: u8 = 0x02;
+const DER_TAG_BIT_STRING: u8 = 0x03;
+const DER_TAG_OCTET_STRING: u8 = 0x04;
+const DER_TAG_OID: u8 = 0x06;
+const DER_TAG_UTC_TIME: u8 = 0x17;
+const DER_TAG_GENERALIZED_TIME: u8 = 0x18;
+const DER_TAG_SEQUENCE: u8 = 0x30;
+const DER_TAG_SET: u8 = 0x31;
+const DER_TAG_CONTEXT_0: u8 = 0xa0;
+const DER_TAG_CONTEXT_3: u8 = 0xa3;
+
+// 2.5.4.3
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+// 2.5.29.15
+const OID_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+// 2.5.29.37
+const OID_EXTENDED_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25];
+// 2.5.29.17
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+// 1.3.6.1.4.1.412.274.1, DMTF device identity otherName
+pub const OID_DMTF_DEVICE_INFO: &[u8] =
+    &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0x1c, 0x82, 0x12, 0x01];
+// 2.23.133.5.4.1, tcg-dice-TcbInfo
+pub const OID_DICE_TCB_INFO: &[u8] = &[0x67, 0x81, 0x05, 0x05, 0x04, 0x01];
+// 2.23.133.5.4.4, tcg-dice-Ueid
+pub const OID_DICE_UEID: &[u8] = &[0x67, 0x81, 0x05, 0x05, 0x04, 0x04];
+
+bitflags! {
+    /// KeyUsage bits, bit n of the extension is 1 << n.
+    #[derive(Default)]
+    pub struct SpdmX509KeyUsage: u16 {
+        const DIGITAL_SIGNATURE = 1 << 0;
+        const NON_REPUDIATION = 1 << 1;
+        const KEY_ENCIPHERMENT = 1 << 2;
+        const DATA_ENCIPHERMENT = 1 << 3;
+        const KEY_AGREEMENT = 1 << 4;
+        const KEY_CERT_SIGN = 1 << 5;
+        const CRL_SIGN = 1 << 6;
+        const ENCIPHER_ONLY = 1 << 7;
+        const DECIPHER_ONLY = 1 << 8;
+    }
+}
+
+/// One FWID of a DICE TcbInfo: digest of a firmware component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpdmDiceFwid<'a> {
+    /// DER content of the hash algorithm OID.
+    pub hash_alg: &'a [u8],
+    pub digest: &'a [u8],
+}
+
+/// DICE TcbInfo extension (TCG DICE Attestation Architecture). String
+/// fields hold the raw UTF8String content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpdmDiceTcbInfo<'a> {
+    pub vendor: Option<&'a [u8]>,
+    pub model: Option<&'a [u8]>,
+    pub version: Option<&'a [u8]>,
+    pub svn: Option<u64>,
+    pub layer: Option<u64>,
+    pub index: Option<u64>,
+    pub fwids: Vec<SpdmDiceFwid<'a>>,
+    /// BIT STRING content, leading unused-bits byte included.
+    pub flags: Option<&'a [u8]>,
+    pub vendor_info: Option<&'a [u8]>,
+    pub tcb_type: Option<&'a [u8]>,
+}
+
+/// Fields of a certificate, borrowed from its DER encoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpdmCertInfo<'a> {
+    /// DER encoding of the subject Name.
+    pub subject: &'a [u8],
+    pub subject_common_name: Option<&'a [u8]>,
+    /// SAN otherName value with type OID_DMTF_DEVICE_INFO.
+    pub dmtf_device_info: Option<&'a [u8]>,
+    pub key_usage: Option<SpdmX509KeyUsage>,
+    /// KeyPurposeId OIDs of the ExtKeyUsage extension, DER content.
+    pub extended_key_usage: Option<Vec<&'a [u8]>>,
+    pub dice_tcb_info: Option<SpdmDiceTcbInfo<'a>>,
+    pub dice_ueid: Option<&'a [u8]>,
+}
+
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    // (tag, value, whole TLV)
+    fn read_any(&mut self) -> SpdmResult<(u8, &'a [u8], &'a [u8])> {
+        if self.data.len() < 2 {
+            return spdm_result_err!(EINVAL);
+        }
+        let tag = self.data[0];
+        let (length, header_size) = match self.data[1] {
+            length @ 0x00..=0x7f => (length as usize, 2),
+            length_size @ 0x81..=0x84 => {
+                let length_size = (length_size & 0x7f) as usize;
+                if self.data.len() < 2 + length_size {
+                    return spdm_result_err!(EINVAL);
+                }
+                let length = self.data[2..(2 + length_size)]
+                    .iter()
+                    .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+                (length, 2 + length_size)
+            }
+            _ => return spdm_result_err!(EINVAL),
+        };
+        if self.data.len() - header_size < length {
+            return spdm_result_err!(EINVAL);
+        }
+        let tlv = &self.data[..(header_size + length)];
+        let value = &tlv[header_size..];
+        self.data = &self.data[tlv.len()..];
+        Ok((tag, value, tlv))
+    }
+
+    fn read(&mut self, tag: u8) -> SpdmResult<&'a [u8]> {
+        match self.read_any()? {
+            (this_tag, value, _) if this_tag == tag => Ok(value),
+            _ => spdm_result_err!(EINVAL),
+        }
+    }
+
+    fn read_optional(&mut self, tag: u8) -> SpdmResult<Option<&'a [u8]>> {
+        if self.peek_tag() == Some(tag) {
+            Ok(Some(self.read(tag)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn der_to_u64(value: &[u8]) -> SpdmResult<u64> {
+    // negative values are rejected, the sign padding of positive ones skipped
+    let value = match value {
+        [first, ..] if first & 0x80 != 0 => return spdm_result_err!(EINVAL),
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => value,
+    };
+    if value.is_empty() || value.len() > 8 {
+        return spdm_result_err!(EINVAL);
+    }
+    Ok(value
+        .iter()
+        .fold(0u64, |result, byte| (result << 8) | *byte as u64))
+}
+
+/// Parse the fields of one DER certificate.
+pub fn spdm_parse_cert(cert_der: &[u8]) -> SpdmResult<SpdmCertInfo> {
+    let mut cert = DerReader::new(DerReader::new(cert_der).read(DER_TAG_SEQUENCE)?);
+    let mut tbs = DerReader::new(cert.read(DER_TAG_SEQUENCE)?);
+
+    tbs.read_optional(DER_TAG_CONTEXT_0)?; // version
+    tbs.read(DER_TAG_INTEGER)?; // serialNumber
+    tbs.read(DER_TAG_SEQUENCE)?; // signature
+    tbs.read(DER_TAG_SEQUENCE)?; // issuer
+    tbs.read(DER_TAG_SEQUENCE)?; // validity
+    let (tag, subject_name, subject) = tbs.read_any()?;
+    if tag != DER_TAG_SEQUENCE {
+        return spdm_result_err!(EINVAL);
+    }
+    tbs.read(DER_TAG_SEQUENCE)?; // subjectPublicKeyInfo
+
+    let mut cert_info = SpdmCertInfo {
+        subject,
+        subject_common_name: get_common_name(subject_name)?,
+        ..Default::default()
+    };
+
+    // issuerUniqueID and subjectUniqueID are skipped, extensions are [3]
+    while !tbs.is_empty() {
+        let (tag, value, _) = tbs.read_any()?;
+        if tag == DER_TAG_CONTEXT_3 {
+            parse_extensions(
+                DerReader::new(value).read(DER_TAG_SEQUENCE)?,
+                &mut cert_info,
+            )?;
+        }
+    }
+    Ok(cert_info)
+}
+
+/// notBefore and notAfter of one DER certificate, in seconds since the Unix
+/// epoch.
+pub fn spdm_get_cert_validity(cert_der: &[u8]) -> SpdmResult<(u64, u64)> {
+    let mut cert = DerReader::new(DerReader::new(cert_der).read(DER_TAG_SEQUENCE)?);
+    let mut tbs = DerReader::new(cert.read(DER_TAG_SEQUENCE)?);
+
+    tbs.read_optional(DER_TAG_CONTEXT_0)?; // version
+    tbs.read(DER_TAG_INTEGER)?; // serialNumber
+    tbs.read(DER_TAG_SEQUENCE)?; // signature
+    tbs.read(DER_TAG_SEQUENCE)?; // issuer
+    let mut validity = DerReader::new(tbs.read(DER_TAG_SEQUENCE)?);
+    let not_before = read_time(&mut validity)?;
+    let not_after = read_time(&mut validity)?;
+    Ok((not_before, not_after))
+}
+
+// UTCTime YYMMDDHHMMSSZ or GeneralizedTime YYYYMMDDHHMMSSZ, as RFC 5280 requires
+fn read_time(reader: &mut DerReader) -> SpdmResult<u64> {
+    let (tag, value, _) = reader.read_any()?;
+    let (year, value) = match (tag, value.len()) {
+        (DER_TAG_UTC_TIME, 13) => match read_digits(&value[..2])? {
+            year @ 50..=99 => (1900 + year, &value[2..]),
+            year => (2000 + year, &value[2..]),
+        },
+        (DER_TAG_GENERALIZED_TIME, 15) => (read_digits(&value[..4])?, &value[4..]),
+        _ => return spdm_result_err!(EINVAL),
+    };
+    if value[10] != b'Z' {
+        return spdm_result_err!(EINVAL);
+    }
+    let month = read_digits(&value[0..2])?;
+    let day = read_digits(&value[2..4])?;
+    let hour = read_digits(&value[4..6])?;
+    let minute = read_digits(&value[6..8])?;
+    let second = read_digits(&value[8..10])?;
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return spdm_result_err!(EINVAL);
+    }
+
+    // days from 1970-01-01 to the civil date, with the year starting in March
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let days = year * 365 + year / 4 - year / 100 + year / 400 + day_of_year;
+    // 719468 days from 0000-03-01 to 1970-01-01
+    if days < 719468 {
+        return spdm_result_err!(EINVAL);
+    }
+    Ok((days - 719468) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+fn read_digits(digits: &[u8]) -> SpdmResult<u64> {
+    digits.iter().try_fold(0u64, |value, digit| match digit {
+        b'0'..=b'9' => Ok(value * 10 + (digit - b'0') as u64),
+        _ => spdm_result_err!(EINVAL),
+    })
+}
+
+/// Parse the fields of the leaf certificate of concatenated DER certificates.
+pub fn spdm_parse_leaf_cert(cert_chain_der: &[u8]) -> SpdmResult<SpdmCertInfo> {
+    let (leaf_cert_begin, leaf_cert_end) =
+        crypto::cert_operation::get_cert_from_cert_chain(cert_chain_der, -1)?;
+    spdm_parse_cert(&cert_chain_der[leaf_cert_begin..leaf_cert_end])
+}
+
+fn get_common_name(name: &[u8]) -> SpdmResult<Option<&[u8]>> {
+    let mut rdns = DerReader::new(name);
+    while !rdns.is_empty() {
+        let mut attributes = DerReader::new(rdns.read(DER_TAG_SET)?);
+        while !attributes.is_empty() {
+            let mut attribute = DerReader::new(attributes.read(DER_TAG_SEQUENCE)?);
+            if attribute.read(DER_TAG_OID)? == OID_COMMON_NAME {
+                let (_, value, _) = attribute.read_any()?;
+                return Ok(Some(value));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn parse_extensions<'a>(extensions: &'a [u8], cert_info: &mut SpdmCertInfo<'a>) -> SpdmResult {
+    let mut extensions = DerReader::new(extensions);
+    while !extensions.is_empty() {
+        let mut extension = DerReader::new(extensions.read(DER_TAG_SEQUENCE)?);
+        let oid = extension.read(DER_TAG_OID)?;
+        extension.read_optional(DER_TAG_BOOLEAN)?; // critical
+        let value = extension.read(DER_TAG_OCTET_STRING)?;
+
+        if oid == OID_KEY_USAGE {
+            cert_info.key_usage = Some(parse_key_usage(value)?);
+        } else if oid == OID_EXTENDED_KEY_USAGE {
+            cert_info.extended_key_usage = Some(parse_extended_key_usage(value)?);
+        } else if oid == OID_SUBJECT_ALT_NAME {
+            cert_info.dmtf_device_info = parse_dmtf_device_info(value)?;
+        } else if oid == OID_DICE_TCB_INFO {
+            cert_info.dice_tcb_info = Some(parse_dice_tcb_info(value)?);
+        } else if oid == OID_DICE_UEID {
+            let mut ueid = DerReader::new(DerReader::new(value).read(DER_TAG_SEQUENCE)?);
+            cert_info.dice_ueid = Some(ueid.read(DER_TAG_OCTET_STRING)?);
+        }
+    }
+    Ok(())
+}
+
+fn parse_key_usage(value: &[u8]) -> SpdmResult<SpdmX509KeyUsage> {
+    let bits = DerReader::new(value).read(DER_TAG_BIT_STRING)?;
+    if bits.is_empty() {
+        return spdm_result_err!(EINVAL);
+    }
+    let mut key_usage = 0u16;
+    for (i, byte) in bits[1..].iter().take(2).enumerate() {
+        key_usage |= (byte.reverse_bits() as u16) << (i * 8);
+    }
+    Ok(SpdmX509KeyUsage::from_bits_truncate(key_usage))
+}
+
+fn parse_extended_key_usage(value: &[u8]) -> SpdmResult<Vec<&[u8]>> {
+    let mut key_purposes = DerReader::new(DerReader::new(value).read(DER_TAG_SEQUENCE)?);
+    let mut extended_key_usage = Vec::new();
+    while !key_purposes.is_empty() {
+        extended_key_usage.push(key_purposes.read(DER_TAG_OID)?);
+    }
+    Ok(extended_key_usage)
+}
+
+fn parse_dmtf_device_info(value: &[u8]) -> SpdmResult<Option<&[u8]>> {
+    let mut general_names = DerReader::new(DerReader::new(value).read(DER_TAG_SEQUENCE)?);
+    while !general_names.is_empty() {
+        let (tag, other_name, _) = general_names.read_any()?;
+        if tag != DER_TAG_CONTEXT_0 {
+            continue;
+        }
+        let mut other_name = DerReader::new(other_name);
+        if other_name.read(DER_TAG_OID)? == OID_DMTF_DEVICE_INFO {
+            let (_, value, _) = DerReader::new(other_name.read(DER_TAG_CONTEXT_0)?).read_any()?;
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_dice_tcb_info(value: &[u8]) -> SpdmResult<SpdmDiceTcbInfo> {
+    let mut fields = DerReader::new(DerReader::new(value).read(DER_TAG_SEQUENCE)?);
+    let mut tcb_info = SpdmDiceTcbInfo::default();
+    while !fields.is_empty() {
+        let (tag, value, _) = fields.read_any()?;
+        match tag {
+            0x80 => tcb_info.vendor = Some(value),
+            0x81 => tcb_info.model = Some(value),
+            0x82 => tcb_info.version = Some(value),
+            0x83 => tcb_info.svn = Some(der_to_u64(value)?),
+            0x84 => tcb_info.layer = Some(der_to_u64(value)?),
+            0x85 => tcb_info.index = Some(der_to_u64(value)?),
+            0xa6 => {
+                let mut fwids = DerReader::new(value);
+                while !fwids.is_empty() {
+                    let mut fwid = DerReader::new(fwids.read(DER_TAG_SEQUENCE)?);
+                    tcb_info.fwids.push(SpdmDiceFwid {
+                        hash_alg: fwid.read(DER_TAG_OID)?,
+                        digest: fwid.read(DER_TAG_OCTET_STRING)?,
+                    });
+                }
+            }
+            0x87 => tcb_info.flags = Some(value),
+            0x88 => tcb_info.vendor_info = Some(value),
+            0x89 => tcb_info.tcb_type = Some(value),
+            // fields added by later DICE revisions
+            _ => {}
+        }
+    }
+    Ok(tcb_info)
+}
+
+impl<'a> SpdmDiceTcbInfo<'a> {
+    /// Digest of the first FWID using the given hash algorithm OID.
+    pub fn get_fwid(&self, hash_alg: &[u8]) -> Option<&'a [u8]> {
+        self.fwids
+            .iter()
+            .find(|fwid| fwid.hash_alg == hash_alg)
+            .map(|fwid| fwid.digest)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    // 2.16.840.1.101.3.4.2.2
+    const OID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+
+    #[test]
+    fn test_case0_parse_cert() {
+        let cert_der = &include_bytes!("../../../test_key/dice/end_responder.cert.der")[..];
+        let cert_info = spdm_parse_cert(cert_der).unwrap();
+
+        assert_eq!(
+            cert_info.subject_common_name,
+            Some(&b"intel test DICE responder cert"[..])
+        );
+        assert_eq!(cert_info.subject[0], DER_TAG_SEQUENCE);
+        assert_eq!(
+            cert_info.dmtf_device_info,
+            Some(&b"ACME:WIDGET:1234567890"[..])
+        );
+        assert_eq!(
+            cert_info.key_usage,
+            Some(SpdmX509KeyUsage::DIGITAL_SIGNATURE | SpdmX509KeyUsage::KEY_AGREEMENT)
+        );
+        assert_eq!(
+            cert_info.dice_ueid,
+            Some(&[0x01u8, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef][..])
+        );
+
+        let tcb_info = cert_info.dice_tcb_info.unwrap();
+        assert_eq!(tcb_info.vendor, Some(&b"ACME"[..]));
+        assert_eq!(tcb_info.model, Some(&b"WIDGET"[..]));
+        assert_eq!(tcb_info.version, Some(&b"1.2.3"[..]));
+        assert_eq!(tcb_info.svn, Some(7));
+        assert_eq!(tcb_info.layer, Some(1));
+        assert_eq!(tcb_info.index, None);
+        assert_eq!(tcb_info.fwids.len(), 1);
+        let mut digest = [0u8; 48];
+        for (i, byte) in digest.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        assert_eq!(tcb_info.get_fwid(OID_SHA384), Some(&digest[..]));
+    }
+
+    #[test]
+    fn test_case1_parse_leaf_cert() {
+        let cert_chain_der =
+            &include_bytes!("../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        let cert_info = spdm_parse_leaf_cert(cert_chain_der).unwrap();
+        assert_eq!(
+            cert_info.subject_common_name,
+            Some(&b"intel test ECP256 responder cert"[..])
+        );
+        assert_eq!(
+            cert_info.dmtf_device_info,
+            Some(&b"ACME:WIDGET:1234567890"[..])
+        );
+        assert_eq!(
+            cert_info.key_usage,
+            Some(
+                SpdmX509KeyUsage::DIGITAL_SIGNATURE
+                    | SpdmX509KeyUsage::NON_REPUDIATION
+                    | SpdmX509KeyUsage::KEY_ENCIPHERMENT
+            )
+        );
+        // id-kp-serverAuth, id-kp-clientAuth, id-kp-OCSPSigning
+        assert_eq!(
+            cert_info.extended_key_usage,
+            Some(
+                [
+                    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01][..],
+                    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02][..],
+                    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09][..],
+                ]
+                .to_vec()
+            )
+        );
+        assert!(cert_info.dice_tcb_info.is_none());
+        assert!(cert_info.dice_ueid.is_none());
+    }
+
+    #[test]
+    fn test_case2_parse_cert() {
+        let cert_der = &include_bytes!("../../../test_key/dice/end_responder.cert.der")[..];
+        assert!(spdm_parse_cert(&cert_der[..(cert_der.len() - 1)]).is_err());
+        assert!(spdm_parse_cert(&[0x30, 0x80]).is_err());
+        assert!(spdm_parse_cert(&[]).is_err());
+        assert_eq!(der_to_u64(&[0x00, 0xff]).unwrap(), 0xff);
+        assert!(der_to_u64(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_case3_cert_validity() {
+        let cert_der = &include_bytes!("../../../test_key/EcP384/end_responder.cert.der")[..];
+        // 2022-01-05 06:08:34Z, 2032-01-03 06:08:34Z
+        assert_eq!(
+            spdm_get_cert_validity(cert_der).unwrap(),
+            (1641362914, 1956722914)
+        );
+
+        let mut reader = DerReader::new(b"\x17\x0d491231235959Z");
+        assert_eq!(read_time(&mut reader).unwrap(), 2524607999);
+        let mut reader = DerReader::new(b"\x18\x0f20500101000000Z");
+        assert_eq!(read_time(&mut reader).unwrap(), 2524608000);
+        let mut reader = DerReader::new(b"\x17\x0d491331235959Z");
+        assert!(read_time(&mut reader).is_err());
+        let mut reader = DerReader::new(b"\x17\x0d4912312359590");
+        assert!(read_time(&mut reader).is_err());
+        let mut reader = DerReader::new(b"\x18\x0f19691231235959Z");
+        assert!(read_time(&mut reader).is_err());
+    }
+}
@@ -7,7 +7,8 @@ use crate::error::SpdmResult;
 extern crate alloc;
 use alloc::boxed::Box;
 
-use super::spdm_ring::hash_impl::HashCtx;
+use super::HashCtx;
+use zeroize::Zeroize;
 
 use crate::protocol::{
     SpdmAeadAlgo, SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmDheAlgo, SpdmDheExchangeStruct,
@@ -22,6 +23,19 @@ pub struct SpdmHash {
     pub hash_ctx_finalize_cb: fn(ctx: HashCtx) -> Option<SpdmDigestStruct>,
 }
 
+/// Running hash state of a hash provider, handed around as `HashCtx`.
+pub trait SpdmHashContext {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Option<SpdmDigestStruct>;
+    fn box_clone(&self) -> Box<dyn SpdmHashContext>;
+}
+
+impl Clone for Box<dyn SpdmHashContext> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
 #[derive(Clone)]
 pub struct SpdmHmac {
     pub hmac_cb:
@@ -62,6 +76,44 @@ pub struct SpdmAead {
     pub decrypt_cb: DecryptCb,
 }
 
+/// Reference to a session key held by an `SpdmAeadOffload` engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Zeroize)]
+pub struct SpdmAeadKeyHandle(pub u32);
+
+/// Inline crypto engine that encrypts and decrypts secured messages with
+/// keys it holds, so the session keys do not stay in software.
+pub trait SpdmAeadOffload {
+    /// Load a session key, the caller wipes its copy afterwards.
+    fn import_key(&self, aead_algo: SpdmAeadAlgo, key: &[u8]) -> SpdmResult<SpdmAeadKeyHandle>;
+
+    /// Drop a key that no session uses any more.
+    fn release_key(&self, key_handle: SpdmAeadKeyHandle);
+
+    #[allow(clippy::too_many_arguments)]
+    fn encrypt(
+        &self,
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        plain_text: &[u8],
+        tag: &mut [u8],
+        cipher_text: &mut [u8],
+    ) -> SpdmResult<(usize, usize)>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn decrypt(
+        &self,
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        cipher_text: &[u8],
+        tag: &[u8],
+        plain_text: &mut [u8],
+    ) -> SpdmResult<usize>;
+}
+
 #[derive(Clone)]
 pub struct SpdmAsymSign {
     pub sign_cb: fn(
@@ -71,6 +123,28 @@ pub struct SpdmAsymSign {
     ) -> Option<SpdmSignatureStruct>,
 }
 
+/// Progress of a signature produced by an `SpdmAsymSignOperation`.
+pub enum SpdmAsymSignPoll {
+    Ready(SpdmSignatureStruct),
+    Pending,
+    Failed,
+}
+
+/// A signature still being produced by an external signer (HSM, TPM, ...).
+pub trait SpdmAsymSignOperation {
+    fn poll(&mut self) -> SpdmAsymSignPoll;
+}
+
+/// External signer that may complete a signature later.
+#[derive(Clone)]
+pub struct SpdmAsymSignDeferred {
+    pub sign_start_cb: fn(
+        base_hash_algo: SpdmBaseHashAlgo,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> Option<Box<dyn SpdmAsymSignOperation>>,
+}
+
 #[derive(Clone)]
 pub struct SpdmAsymVerify {
     pub verify_cb: fn(
@@ -84,6 +158,9 @@ pub struct SpdmAsymVerify {
 
 #[derive(Clone)]
 pub struct SpdmHkdf {
+    /// HKDF-Extract(salt, ikm), the PRK is one digest of hash_algo.
+    pub hkdf_extract_cb:
+        fn(hash_algo: SpdmBaseHashAlgo, salt: &[u8], ikm: &[u8]) -> Option<SpdmDigestStruct>,
     pub hkdf_expand_cb: fn(
         hash_algo: SpdmBaseHashAlgo,
         pk: &[u8],
@@ -98,18 +175,39 @@ type GetCertFromCertChainCb = fn(cert_chain: &[u8], index: isize) -> SpdmResult<
 pub struct SpdmCertOperation {
     pub get_cert_from_cert_chain_cb: GetCertFromCertChainCb,
 
-    pub verify_cert_chain_cb: fn(cert_chain: &[u8]) -> SpdmResult,
+    /// required_ekus: the leaf must allow one of these KeyPurposeId OIDs,
+    /// None skips the EKU check.
+    pub verify_cert_chain_cb: fn(
+        cert_chain: &[u8],
+        required_ekus: Option<&[&'static [u8]]>,
+        validity_time: SpdmCertValidityTime,
+    ) -> SpdmResult,
+}
+
+/// Time certificate validity periods are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmCertValidityTime {
+    /// The platform clock of the crypto provider.
+    PlatformClock,
+    /// Trusted time, in seconds since the Unix epoch.
+    At(u64),
+    /// Validity periods are not checked.
+    Skip,
 }
 
-type GenerateKeyPairCb =
-    fn(dhe_algo: SpdmDheAlgo) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)>;
+type GenerateKeyPairCb = fn(
+    dhe_algo: SpdmDheAlgo,
+    rng: &mut dyn SpdmRng,
+) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)>;
 
 #[derive(Clone)]
 pub struct SpdmDhe {
     pub generate_key_pair_cb: GenerateKeyPairCb,
 }
 
-pub trait SpdmDheKeyExchange {
+/// Send, so that key pairs pre-generated by crypto::dhe_pool can be
+/// refilled from another thread.
+pub trait SpdmDheKeyExchange: Send {
     fn compute_final_key(
         self: Box<Self>,
         peer_pub_key: &SpdmDheExchangeStruct,
@@ -120,3 +218,9 @@ pub trait SpdmDheKeyExchange {
 pub struct SpdmCryptoRandom {
     pub get_random_cb: fn(data: &mut [u8]) -> SpdmResult<usize>,
 }
+
+/// Entropy source for nonces and ephemeral keys, e.g. a hardware TRNG.
+pub trait SpdmRng {
+    /// Fill data with random bytes, returning the number of bytes written.
+    fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize>;
+}
@@ -0,0 +1,179 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Pre-generated ephemeral DHE key pairs.
+//!
+//! Key pair generation dominates KEY_EXCHANGE latency on small cores. The
+//! pool is empty until `set_capacity` enables an algorithm, then `refill`
+//! tops it up from a background thread or the idle loop and
+//! `dhe::generate_key_pair` takes a pooled key pair before generating one.
+
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::crypto::{SpdmDheKeyExchange, SpdmRng};
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::protocol::{SpdmDheAlgo, SpdmDheExchangeStruct};
+
+type SpdmDheKeyPair = (SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>);
+
+struct SpdmDhePoolEntry {
+    dhe_algo: SpdmDheAlgo,
+    capacity: usize,
+    key_pairs: Vec<SpdmDheKeyPair>,
+}
+
+pub struct SpdmDhePool {
+    entries: spin::Mutex<Vec<SpdmDhePoolEntry>>,
+}
+
+impl Default for SpdmDhePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpdmDhePool {
+    pub const fn new() -> Self {
+        SpdmDhePool {
+            entries: spin::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Keep up to capacity key pairs of dhe_algo, 0 disables the algorithm
+    /// and drops its key pairs.
+    pub fn set_capacity(&self, dhe_algo: SpdmDheAlgo, capacity: usize) -> SpdmResult {
+        if dhe_algo.bits().count_ones() != 1 {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut entries = self.entries.lock();
+        match entries.iter_mut().find(|e| e.dhe_algo == dhe_algo) {
+            Some(entry) => {
+                entry.capacity = capacity;
+                entry.key_pairs.truncate(capacity);
+            }
+            None => entries.push(SpdmDhePoolEntry {
+                dhe_algo,
+                capacity,
+                key_pairs: Vec::new(),
+            }),
+        }
+        entries.retain(|e| e.capacity != 0);
+        Ok(())
+    }
+
+    /// Number of key pairs of dhe_algo ready to be taken.
+    pub fn available(&self, dhe_algo: SpdmDheAlgo) -> usize {
+        self.entries
+            .lock()
+            .iter()
+            .find(|e| e.dhe_algo == dhe_algo)
+            .map_or(0, |e| e.key_pairs.len())
+    }
+
+    /// Generate key pairs until every enabled algorithm is at capacity, and
+    /// return how many were added.
+    ///
+    /// The pool is not locked while a key pair is generated, so take()
+    /// from a session never waits for a refill.
+    pub fn refill(&self, rng: &mut dyn SpdmRng) -> SpdmResult<usize> {
+        let mut count = 0;
+        loop {
+            let dhe_algo = match self
+                .entries
+                .lock()
+                .iter()
+                .find(|e| e.key_pairs.len() < e.capacity)
+            {
+                Some(entry) => entry.dhe_algo,
+                None => return Ok(count),
+            };
+
+            let key_pair = super::dhe::generate_new_key_pair(dhe_algo, rng)
+                .ok_or_else(|| spdm_err!(EFAULT))?;
+
+            let mut entries = self.entries.lock();
+            match entries.iter_mut().find(|e| e.dhe_algo == dhe_algo) {
+                // the capacity may have changed meanwhile
+                Some(entry) if entry.key_pairs.len() < entry.capacity => {
+                    entry.key_pairs.push(key_pair);
+                    count += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Take a pooled key pair of dhe_algo, each one is handed out once.
+    pub fn take(&self, dhe_algo: SpdmDheAlgo) -> Option<SpdmDheKeyPair> {
+        self.entries
+            .lock()
+            .iter_mut()
+            .find(|e| e.dhe_algo == dhe_algo)?
+            .key_pairs
+            .pop()
+    }
+
+    /// Drop every pooled key pair, the capacities are kept.
+    pub fn clear(&self) {
+        for entry in self.entries.lock().iter_mut() {
+            entry.key_pairs.clear();
+        }
+    }
+}
+
+static DHE_POOL: SpdmDhePool = SpdmDhePool::new();
+
+/// The pool `dhe::generate_key_pair` takes from.
+pub fn get() -> &'static SpdmDhePool {
+    &DHE_POOL
+}
+
+pub fn set_capacity(dhe_algo: SpdmDheAlgo, capacity: usize) -> SpdmResult {
+    DHE_POOL.set_capacity(dhe_algo, capacity)
+}
+
+pub fn refill(rng: &mut dyn SpdmRng) -> SpdmResult<usize> {
+    DHE_POOL.refill(rng)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::crypto::rand::SpdmCryptoRng;
+
+    #[test]
+    fn test_case0_dhe_pool() {
+        let pool = SpdmDhePool::new();
+        assert_eq!(pool.refill(&mut SpdmCryptoRng).unwrap(), 0);
+        assert!(pool.take(SpdmDheAlgo::SECP_384_R1).is_none());
+        assert!(pool
+            .set_capacity(SpdmDheAlgo::SECP_256_R1 | SpdmDheAlgo::SECP_384_R1, 2)
+            .is_err());
+
+        pool.set_capacity(SpdmDheAlgo::SECP_384_R1, 2).unwrap();
+        assert_eq!(pool.refill(&mut SpdmCryptoRng).unwrap(), 2);
+        assert_eq!(pool.refill(&mut SpdmCryptoRng).unwrap(), 0);
+        assert_eq!(pool.available(SpdmDheAlgo::SECP_384_R1), 2);
+        assert_eq!(pool.available(SpdmDheAlgo::SECP_256_R1), 0);
+
+        let (exchange1, private1) = pool.take(SpdmDheAlgo::SECP_384_R1).unwrap();
+        let (exchange2, private2) = pool.take(SpdmDheAlgo::SECP_384_R1).unwrap();
+        assert!(pool.take(SpdmDheAlgo::SECP_384_R1).is_none());
+        assert_ne!(exchange1.as_ref(), exchange2.as_ref());
+        assert_eq!(
+            private1.compute_final_key(&exchange2).unwrap().as_ref(),
+            private2.compute_final_key(&exchange1).unwrap().as_ref()
+        );
+
+        assert_eq!(pool.refill(&mut SpdmCryptoRng).unwrap(), 2);
+        pool.set_capacity(SpdmDheAlgo::SECP_384_R1, 1).unwrap();
+        assert_eq!(pool.available(SpdmDheAlgo::SECP_384_R1), 1);
+        pool.clear();
+        assert_eq!(pool.available(SpdmDheAlgo::SECP_384_R1), 0);
+        pool.set_capacity(SpdmDheAlgo::SECP_384_R1, 0).unwrap();
+        assert_eq!(pool.refill(&mut SpdmCryptoRng).unwrap(), 0);
+    }
+}
@@ -0,0 +1,640 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! ML-KEM-768 (FIPS 203) for the experimental hybrid key exchange. This is a
+//! straightforward reference implementation and is not hardened against
+//! timing side channels beyond the constant time ciphertext comparison.
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub const MLKEM_768_ENCAPS_KEY_SIZE: usize = 384 * K + 32;
+pub const MLKEM_768_DECAPS_KEY_SIZE: usize = 768 * K + 96;
+pub const MLKEM_768_CIPHERTEXT_SIZE: usize = 32 * (DU * K + DV);
+pub const MLKEM_SHARED_SECRET_SIZE: usize = 32;
+pub const MLKEM_SEED_SIZE: usize = 32;
+
+const N: usize = 256;
+const Q: u32 = 3329;
+const K: usize = 3;
+// eta1 and eta2 are both 2 for ML-KEM-768
+const ETA: usize = 2;
+const DU: usize = 10;
+const DV: usize = 4;
+
+type Poly = [u16; N];
+
+const POLY_BYTES: usize = 384;
+
+// 17^BitRev7(i) mod q
+const ZETAS: [u16; 128] = [
+    1, 1729, 2580, 3289, 2642, 630, 1897, 848, 1062, 1919, 193, 797, 2786, 3260, 569, 1746, 296,
+    2447, 1339, 1476, 3046, 56, 2240, 1333, 1426, 2094, 535, 2882, 2393, 2879, 1974, 821, 289, 331,
+    3253, 1756, 1197, 2304, 2277, 2055, 650, 1977, 2513, 632, 2865, 33, 1320, 1915, 2319, 1435,
+    807, 452, 1438, 2868, 1534, 2402, 2647, 2617, 1481, 648, 2474, 3110, 1227, 910, 17, 2761, 583,
+    2649, 1637, 723, 2288, 1100, 1409, 2662, 3281, 233, 756, 2156, 3015, 3050, 1703, 1651, 2789,
+    1789, 1847, 952, 1461, 2687, 939, 2308, 2437, 2388, 733, 2337, 268, 641, 1584, 2298, 2037,
+    3220, 375, 2549, 2090, 1645, 1063, 319, 2773, 757, 2099, 561, 2466, 2594, 2804, 1092, 403,
+    1026, 1143, 2150, 2775, 886, 1722, 1212, 1874, 1029, 2110, 2935, 885, 2154,
+];
+
+// 17^(2*BitRev7(i)+1) mod q
+const GAMMAS: [u16; 128] = [
+    17, 3312, 2761, 568, 583, 2746, 2649, 680, 1637, 1692, 723, 2606, 2288, 1041, 1100, 2229, 1409,
+    1920, 2662, 667, 3281, 48, 233, 3096, 756, 2573, 2156, 1173, 3015, 314, 3050, 279, 1703, 1626,
+    1651, 1678, 2789, 540, 1789, 1540, 1847, 1482, 952, 2377, 1461, 1868, 2687, 642, 939, 2390,
+    2308, 1021, 2437, 892, 2388, 941, 733, 2596, 2337, 992, 268, 3061, 641, 2688, 1584, 1745, 2298,
+    1031, 2037, 1292, 3220, 109, 375, 2954, 2549, 780, 2090, 1239, 1645, 1684, 1063, 2266, 319,
+    3010, 2773, 556, 757, 2572, 2099, 1230, 561, 2768, 2466, 863, 2594, 735, 2804, 525, 1092, 2237,
+    403, 2926, 1026, 2303, 1143, 2186, 2150, 1179, 2775, 554, 886, 2443, 1722, 1607, 1212, 2117,
+    1874, 1455, 1029, 2300, 2110, 1219, 2935, 394, 885, 2444, 2154, 1175,
+];
+
+/// Derive an ML-KEM-768 key pair from the seeds d and z (ML-KEM.KeyGen_internal).
+/// Returns (encapsulation key, decapsulation key).
+pub fn keygen(
+    d: &[u8; MLKEM_SEED_SIZE],
+    z: &[u8; MLKEM_SEED_SIZE],
+) -> (
+    [u8; MLKEM_768_ENCAPS_KEY_SIZE],
+    [u8; MLKEM_768_DECAPS_KEY_SIZE],
+) {
+    let mut ek = [0u8; MLKEM_768_ENCAPS_KEY_SIZE];
+    let mut dk = [0u8; MLKEM_768_DECAPS_KEY_SIZE];
+
+    let mut g_input = [0u8; MLKEM_SEED_SIZE + 1];
+    g_input[..MLKEM_SEED_SIZE].copy_from_slice(d);
+    g_input[MLKEM_SEED_SIZE] = K as u8;
+    let mut rho_sigma = sha3_512(&[&g_input]);
+    g_input.zeroize();
+    let (rho, sigma) = rho_sigma.split_at(32);
+
+    let a_hat = sample_matrix(rho);
+    let mut nonce = 0u8;
+    let mut s_hat = [[0u16; N]; K];
+    for s in s_hat.iter_mut() {
+        *s = sample_cbd(sigma, nonce);
+        ntt(s);
+        nonce += 1;
+    }
+    let mut e_hat = [[0u16; N]; K];
+    for e in e_hat.iter_mut() {
+        *e = sample_cbd(sigma, nonce);
+        ntt(e);
+        nonce += 1;
+    }
+
+    for i in 0..K {
+        let mut t_hat = e_hat[i];
+        for j in 0..K {
+            poly_add_assign(&mut t_hat, &multiply_ntts(&a_hat[i][j], &s_hat[j]));
+        }
+        byte_encode(
+            &t_hat,
+            12,
+            &mut ek[(i * POLY_BYTES)..((i + 1) * POLY_BYTES)],
+        );
+        byte_encode(
+            &s_hat[i],
+            12,
+            &mut dk[(i * POLY_BYTES)..((i + 1) * POLY_BYTES)],
+        );
+    }
+    ek[(K * POLY_BYTES)..].copy_from_slice(rho);
+
+    dk[(K * POLY_BYTES)..(K * POLY_BYTES + MLKEM_768_ENCAPS_KEY_SIZE)].copy_from_slice(&ek);
+    let h_ek = sha3_256(&[&ek]);
+    dk[(MLKEM_768_DECAPS_KEY_SIZE - 64)..(MLKEM_768_DECAPS_KEY_SIZE - 32)].copy_from_slice(&h_ek);
+    dk[(MLKEM_768_DECAPS_KEY_SIZE - 32)..].copy_from_slice(z);
+
+    rho_sigma.zeroize();
+    s_hat.zeroize();
+    e_hat.zeroize();
+    (ek, dk)
+}
+
+/// Encapsulate to ek with the random message m (ML-KEM.Encaps_internal).
+/// Returns (ciphertext, shared secret), or None if ek fails the modulus check.
+pub fn encaps(
+    ek: &[u8],
+    m: &[u8; MLKEM_SEED_SIZE],
+) -> Option<(
+    [u8; MLKEM_768_CIPHERTEXT_SIZE],
+    [u8; MLKEM_SHARED_SECRET_SIZE],
+)> {
+    if ek.len() != MLKEM_768_ENCAPS_KEY_SIZE {
+        return None;
+    }
+    // every coefficient of t_hat must already be reduced mod q
+    for i in 0..K {
+        let encoded = &ek[(i * POLY_BYTES)..((i + 1) * POLY_BYTES)];
+        let mut reencoded = [0u8; POLY_BYTES];
+        byte_encode(&byte_decode(encoded, 12), 12, &mut reencoded);
+        if reencoded[..] != encoded[..] {
+            return None;
+        }
+    }
+
+    let h_ek = sha3_256(&[ek]);
+    let mut k_r = sha3_512(&[m, &h_ek]);
+    let mut ct = [0u8; MLKEM_768_CIPHERTEXT_SIZE];
+    pke_encrypt(ek, m, &k_r[32..], &mut ct);
+
+    let mut shared_secret = [0u8; MLKEM_SHARED_SECRET_SIZE];
+    shared_secret.copy_from_slice(&k_r[..32]);
+    k_r.zeroize();
+    Some((ct, shared_secret))
+}
+
+/// Decapsulate ct with dk (ML-KEM.Decaps_internal). A ciphertext that fails
+/// the re-encryption check yields the implicit rejection secret.
+pub fn decaps(dk: &[u8; MLKEM_768_DECAPS_KEY_SIZE], ct: &[u8]) -> Option<[u8; 32]> {
+    if ct.len() != MLKEM_768_CIPHERTEXT_SIZE {
+        return None;
+    }
+    let dk_pke = &dk[..(K * POLY_BYTES)];
+    let ek = &dk[(K * POLY_BYTES)..(K * POLY_BYTES + MLKEM_768_ENCAPS_KEY_SIZE)];
+    let h_ek = &dk[(MLKEM_768_DECAPS_KEY_SIZE - 64)..(MLKEM_768_DECAPS_KEY_SIZE - 32)];
+    let z = &dk[(MLKEM_768_DECAPS_KEY_SIZE - 32)..];
+
+    let mut m = pke_decrypt(dk_pke, ct);
+    let mut k_r = sha3_512(&[&m, h_ek]);
+    let k_bar = shake256::<32>(&[z, ct]);
+    let mut ct_prime = [0u8; MLKEM_768_CIPHERTEXT_SIZE];
+    pke_encrypt(ek, &m, &k_r[32..], &mut ct_prime);
+
+    let diff = ct
+        .iter()
+        .zip(ct_prime.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    // 0xff when the ciphertexts match, 0x00 otherwise
+    let mask = ((diff as u16).wrapping_sub(1) >> 8) as u8;
+    let mut shared_secret = [0u8; MLKEM_SHARED_SECRET_SIZE];
+    for i in 0..MLKEM_SHARED_SECRET_SIZE {
+        shared_secret[i] = (k_r[i] & mask) | (k_bar[i] & !mask);
+    }
+
+    m.zeroize();
+    k_r.zeroize();
+    Some(shared_secret)
+}
+
+fn pke_encrypt(ek: &[u8], m: &[u8], r: &[u8], ct: &mut [u8; MLKEM_768_CIPHERTEXT_SIZE]) {
+    let rho = &ek[(K * POLY_BYTES)..];
+    let a_hat = sample_matrix(rho);
+    let mut t_hat = [[0u16; N]; K];
+    for (i, t) in t_hat.iter_mut().enumerate() {
+        *t = byte_decode(&ek[(i * POLY_BYTES)..((i + 1) * POLY_BYTES)], 12);
+    }
+
+    let mut nonce = 0u8;
+    let mut y_hat = [[0u16; N]; K];
+    for y in y_hat.iter_mut() {
+        *y = sample_cbd(r, nonce);
+        ntt(y);
+        nonce += 1;
+    }
+
+    for i in 0..K {
+        let mut u = [0u16; N];
+        for j in 0..K {
+            poly_add_assign(&mut u, &multiply_ntts(&a_hat[j][i], &y_hat[j]));
+        }
+        inverse_ntt(&mut u);
+        poly_add_assign(&mut u, &sample_cbd(r, nonce));
+        nonce += 1;
+        compress(&mut u, DU);
+        byte_encode(&u, DU, &mut ct[(i * 32 * DU)..((i + 1) * 32 * DU)]);
+    }
+
+    let mut v = [0u16; N];
+    for j in 0..K {
+        poly_add_assign(&mut v, &multiply_ntts(&t_hat[j], &y_hat[j]));
+    }
+    inverse_ntt(&mut v);
+    poly_add_assign(&mut v, &sample_cbd(r, nonce));
+    let mut mu = byte_decode(m, 1);
+    decompress(&mut mu, 1);
+    poly_add_assign(&mut v, &mu);
+    compress(&mut v, DV);
+    byte_encode(&v, DV, &mut ct[(K * 32 * DU)..]);
+
+    y_hat.zeroize();
+    mu.zeroize();
+}
+
+fn pke_decrypt(dk_pke: &[u8], ct: &[u8]) -> [u8; 32] {
+    let mut w = byte_decode(&ct[(K * 32 * DU)..], DV);
+    decompress(&mut w, DV);
+
+    let mut s_u = [0u16; N];
+    for i in 0..K {
+        let mut u = byte_decode(&ct[(i * 32 * DU)..((i + 1) * 32 * DU)], DU);
+        decompress(&mut u, DU);
+        ntt(&mut u);
+        let mut s_hat = byte_decode(&dk_pke[(i * POLY_BYTES)..((i + 1) * POLY_BYTES)], 12);
+        poly_add_assign(&mut s_u, &multiply_ntts(&s_hat, &u));
+        s_hat.zeroize();
+    }
+    inverse_ntt(&mut s_u);
+    for (w, s_u) in w.iter_mut().zip(s_u.iter()) {
+        *w = ((*w as u32 + Q - *s_u as u32) % Q) as u16;
+    }
+
+    compress(&mut w, 1);
+    let mut m = [0u8; 32];
+    byte_encode(&w, 1, &mut m);
+    w.zeroize();
+    s_u.zeroize();
+    m
+}
+
+fn sample_matrix(rho: &[u8]) -> [[Poly; K]; K] {
+    let mut a_hat = [[[0u16; N]; K]; K];
+    for (i, row) in a_hat.iter_mut().enumerate() {
+        for (j, a) in row.iter_mut().enumerate() {
+            *a = sample_ntt(rho, j as u8, i as u8);
+        }
+    }
+    a_hat
+}
+
+fn sample_ntt(rho: &[u8], j: u8, i: u8) -> Poly {
+    let mut xof = Keccak::new(SHAKE128_RATE, SHAKE_DOMAIN);
+    xof.absorb(rho);
+    xof.absorb(&[j, i]);
+    xof.finalize();
+
+    let mut a = [0u16; N];
+    let mut count = 0;
+    let mut c = [0u8; 3];
+    while count < N {
+        xof.squeeze(&mut c);
+        let d1 = c[0] as u16 + 256 * (c[1] as u16 & 0x0f);
+        let d2 = (c[1] as u16 >> 4) + 16 * c[2] as u16;
+        if (d1 as u32) < Q {
+            a[count] = d1;
+            count += 1;
+        }
+        if (d2 as u32) < Q && count < N {
+            a[count] = d2;
+            count += 1;
+        }
+    }
+    a
+}
+
+fn sample_cbd(seed: &[u8], nonce: u8) -> Poly {
+    let mut buf = shake256::<{ 64 * ETA }>(&[seed, &[nonce]]);
+    let bit = |index: usize| ((buf[index / 8] >> (index % 8)) & 1) as u32;
+
+    let mut f = [0u16; N];
+    for (i, f) in f.iter_mut().enumerate() {
+        let x: u32 = (0..ETA).map(|j| bit(2 * i * ETA + j)).sum();
+        let y: u32 = (0..ETA).map(|j| bit(2 * i * ETA + ETA + j)).sum();
+        *f = ((x + Q - y) % Q) as u16;
+    }
+    buf.zeroize();
+    f
+}
+
+fn ntt(f: &mut Poly) {
+    let mut i = 1;
+    let mut len = 128;
+    while len >= 2 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i] as u32;
+            i += 1;
+            for j in start..(start + len) {
+                let t = zeta * f[j + len] as u32 % Q;
+                f[j + len] = ((f[j] as u32 + Q - t) % Q) as u16;
+                f[j] = ((f[j] as u32 + t) % Q) as u16;
+            }
+        }
+        len /= 2;
+    }
+}
+
+fn inverse_ntt(f: &mut Poly) {
+    let mut i = 127;
+    let mut len = 2;
+    while len <= 128 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i] as u32;
+            i -= 1;
+            for j in start..(start + len) {
+                let t = f[j] as u32;
+                f[j] = ((t + f[j + len] as u32) % Q) as u16;
+                f[j + len] = (zeta * ((f[j + len] as u32 + Q - t) % Q) % Q) as u16;
+            }
+        }
+        len *= 2;
+    }
+    // 128^-1 mod q
+    for c in f.iter_mut() {
+        *c = (*c as u32 * 3303 % Q) as u16;
+    }
+}
+
+fn multiply_ntts(a: &Poly, b: &Poly) -> Poly {
+    let mut c = [0u16; N];
+    for i in 0..(N / 2) {
+        let (a0, a1) = (a[2 * i] as u32, a[2 * i + 1] as u32);
+        let (b0, b1) = (b[2 * i] as u32, b[2 * i + 1] as u32);
+        let a1_b1 = a1 * b1 % Q;
+        c[2 * i] = ((a0 * b0 + a1_b1 * GAMMAS[i] as u32) % Q) as u16;
+        c[2 * i + 1] = ((a0 * b1 + a1 * b0) % Q) as u16;
+    }
+    c
+}
+
+fn poly_add_assign(a: &mut Poly, b: &Poly) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a = ((*a as u32 + *b as u32) % Q) as u16;
+    }
+}
+
+fn compress(f: &mut Poly, d: usize) {
+    for c in f.iter_mut() {
+        // round(2^d * c / q), q is odd so there is never a tie
+        *c = (((((*c as u32) << (d + 1)) + Q) / (2 * Q)) & ((1 << d) - 1)) as u16;
+    }
+}
+
+fn decompress(f: &mut Poly, d: usize) {
+    for c in f.iter_mut() {
+        *c = ((*c as u32 * Q + (1 << (d - 1))) >> d) as u16;
+    }
+}
+
+fn byte_encode(f: &Poly, d: usize, out: &mut [u8]) {
+    out.iter_mut().for_each(|b| *b = 0);
+    for (i, c) in f.iter().enumerate() {
+        for j in 0..d {
+            let bit = i * d + j;
+            out[bit / 8] |= (((c >> j) & 1) as u8) << (bit % 8);
+        }
+    }
+}
+
+fn byte_decode(input: &[u8], d: usize) -> Poly {
+    let mut f = [0u16; N];
+    for (i, c) in f.iter_mut().enumerate() {
+        for j in 0..d {
+            let bit = i * d + j;
+            *c |= (((input[bit / 8] >> (bit % 8)) & 1) as u16) << j;
+        }
+        if d == 12 {
+            *c = (*c as u32 % Q) as u16;
+        }
+    }
+    f
+}
+
+const SHA3_256_RATE: usize = 136;
+const SHA3_512_RATE: usize = 72;
+const SHAKE128_RATE: usize = 168;
+const SHAKE256_RATE: usize = 136;
+const SHA3_DOMAIN: u8 = 0x06;
+const SHAKE_DOMAIN: u8 = 0x1f;
+
+fn sha3_256(input: &[&[u8]]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    keccak_digest(SHA3_256_RATE, SHA3_DOMAIN, input, &mut out);
+    out
+}
+
+fn sha3_512(input: &[&[u8]]) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    keccak_digest(SHA3_512_RATE, SHA3_DOMAIN, input, &mut out);
+    out
+}
+
+fn shake256<const L: usize>(input: &[&[u8]]) -> [u8; L] {
+    let mut out = [0u8; L];
+    keccak_digest(SHAKE256_RATE, SHAKE_DOMAIN, input, &mut out);
+    out
+}
+
+fn keccak_digest(rate: usize, domain: u8, input: &[&[u8]], out: &mut [u8]) {
+    let mut keccak = Keccak::new(rate, domain);
+    for data in input {
+        keccak.absorb(data);
+    }
+    keccak.finalize();
+    keccak.squeeze(out);
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Keccak {
+    state: [u64; 25],
+    rate: usize,
+    offset: usize,
+    domain: u8,
+}
+
+impl Keccak {
+    fn new(rate: usize, domain: u8) -> Self {
+        Keccak {
+            state: [0u64; 25],
+            rate,
+            offset: 0,
+            domain,
+        }
+    }
+
+    fn xor_byte(&mut self, index: usize, byte: u8) {
+        self.state[index / 8] ^= (byte as u64) << (8 * (index % 8));
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        for byte in data {
+            self.xor_byte(self.offset, *byte);
+            self.offset += 1;
+            if self.offset == self.rate {
+                keccak_f1600(&mut self.state);
+                self.offset = 0;
+            }
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.xor_byte(self.offset, self.domain);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f1600(&mut self.state);
+        self.offset = 0;
+    }
+
+    fn squeeze(&mut self, out: &mut [u8]) {
+        for byte in out.iter_mut() {
+            if self.offset == self.rate {
+                keccak_f1600(&mut self.state);
+                self.offset = 0;
+            }
+            *byte = (self.state[self.offset / 8] >> (8 * (self.offset % 8))) as u8;
+            self.offset += 1;
+        }
+    }
+}
+
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+const KECCAK_RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const KECCAK_PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f1600(a: &mut [u64; 25]) {
+    for rc in KECCAK_ROUND_CONSTANTS.iter() {
+        // theta
+        let mut c = [0u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+        // rho and pi
+        let mut last = a[1];
+        for (rho, pi) in KECCAK_RHO.iter().zip(KECCAK_PI.iter()) {
+            let next = a[*pi];
+            a[*pi] = last.rotate_left(*rho);
+            last = next;
+        }
+        // chi
+        for y in 0..5 {
+            let row = [
+                a[5 * y],
+                a[5 * y + 1],
+                a[5 * y + 2],
+                a[5 * y + 3],
+                a[5 * y + 4],
+            ];
+            for x in 0..5 {
+                a[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // iota
+        a[0] ^= rc;
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    fn kat_key_pair() -> (
+        [u8; MLKEM_768_ENCAPS_KEY_SIZE],
+        [u8; MLKEM_768_DECAPS_KEY_SIZE],
+    ) {
+        let mut d = [0u8; MLKEM_SEED_SIZE];
+        let mut z = [0u8; MLKEM_SEED_SIZE];
+        for i in 0..MLKEM_SEED_SIZE {
+            d[i] = i as u8;
+            z[i] = (i + MLKEM_SEED_SIZE) as u8;
+        }
+        keygen(&d, &z)
+    }
+
+    #[test]
+    fn test_case0_keccak() {
+        assert_eq!(
+            sha3_256(&[b"abc"]),
+            [
+                0x3a, 0x98, 0x5d, 0xa7, 0x4f, 0xe2, 0x25, 0xb2, 0x04, 0x5c, 0x17, 0x2d, 0x6b, 0xd3,
+                0x90, 0xbd, 0x85, 0x5f, 0x08, 0x6e, 0x3e, 0x9d, 0x52, 0x5b, 0x46, 0xbf, 0xe2, 0x45,
+                0x11, 0x43, 0x15, 0x32
+            ]
+        );
+        // input spanning several SHAKE256 blocks, split across absorb calls
+        let data = [0xa3u8; 200];
+        assert_eq!(
+            shake256::<16>(&[&data[..100], &data[100..]]),
+            [
+                0xcd, 0x8a, 0x92, 0x0e, 0xd1, 0x41, 0xaa, 0x04, 0x07, 0xa2, 0x2d, 0x59, 0x28, 0x86,
+                0x52, 0xe9
+            ]
+        );
+    }
+
+    // seed d || z = 00..3f, ciphertext and shared secrets produced by an
+    // independent ML-KEM-768 implementation (OpenSSL 3.5)
+    #[test]
+    fn test_case1_mlkem_768_kat() {
+        let (ek, dk) = kat_key_pair();
+        assert_eq!(
+            sha3_256(&[&ek]),
+            [
+                0xa2, 0x4e, 0x16, 0xd8, 0xf8, 0xf9, 0x38, 0x3a, 0x95, 0xb7, 0x70, 0x50, 0xf4, 0xd9,
+                0xfd, 0x2f, 0x57, 0x33, 0xee, 0xc1, 0xd6, 0x3e, 0xf3, 0xc2, 0x3e, 0xbf, 0x99, 0x18,
+                0x17, 0x36, 0x69, 0xa7
+            ]
+        );
+
+        let mut ct = *include_bytes!("../../../test_key/mlkem/mlkem768.ct.bin");
+        assert_eq!(
+            decaps(&dk, &ct).unwrap(),
+            [
+                0xf6, 0x66, 0x3f, 0x13, 0x02, 0x4a, 0x26, 0x82, 0xdc, 0xee, 0xc8, 0xee, 0x9f, 0x1f,
+                0x6c, 0xc3, 0xe1, 0x6f, 0xb0, 0xdc, 0x9f, 0x63, 0xde, 0xd9, 0x1c, 0x1b, 0x26, 0x12,
+                0xd8, 0x7b, 0xe1, 0xeb
+            ]
+        );
+
+        // implicit rejection
+        ct[0] ^= 1;
+        assert_eq!(
+            decaps(&dk, &ct).unwrap(),
+            [
+                0xe5, 0x53, 0xa5, 0xb1, 0xec, 0xd2, 0xa8, 0xbb, 0x6b, 0x9c, 0x99, 0xea, 0x46, 0x1a,
+                0x5d, 0x7c, 0xf7, 0xee, 0x4e, 0x5d, 0x26, 0x03, 0xd4, 0x2e, 0xee, 0x17, 0xcb, 0xc3,
+                0xca, 0xff, 0x4d, 0x5e
+            ]
+        );
+        assert!(decaps(&dk, &ct[1..]).is_none());
+    }
+
+    #[test]
+    fn test_case2_mlkem_768_encaps_decaps() {
+        let (mut ek, dk) = kat_key_pair();
+        let (ct, shared_secret) = encaps(&ek, &[0x5au8; MLKEM_SEED_SIZE]).unwrap();
+        assert_eq!(decaps(&dk, &ct).unwrap(), shared_secret);
+
+        assert!(encaps(&ek[1..], &[0x5au8; MLKEM_SEED_SIZE]).is_none());
+        // coefficient 0xfff is not reduced mod q
+        ek[0] = 0xff;
+        ek[1] |= 0x0f;
+        assert!(encaps(&ek, &[0x5au8; MLKEM_SEED_SIZE]).is_none());
+    }
+}
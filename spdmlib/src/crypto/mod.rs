@@ -3,23 +3,38 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 mod crypto_callbacks;
+pub mod dhe_pool;
+pub mod ffdhe;
+#[cfg(feature = "spdm-pqc-hybrid")]
+pub mod mlkem;
+#[cfg(feature = "spdm-pqc-hybrid")]
+pub mod pqc_hybrid;
+#[cfg(feature = "crypto-self-test")]
+pub mod self_test;
 
 #[cfg(feature = "spdm-ring")]
 mod spdm_ring;
 
 pub use crypto_callbacks::{
-    SpdmAead, SpdmAsymSign, SpdmAsymVerify, SpdmCertOperation, SpdmCryptoRandom, SpdmDhe,
-    SpdmDheKeyExchange, SpdmHash, SpdmHkdf, SpdmHmac,
+    SpdmAead, SpdmAeadKeyHandle, SpdmAeadOffload, SpdmAsymSign, SpdmAsymSignDeferred,
+    SpdmAsymSignOperation, SpdmAsymSignPoll, SpdmAsymVerify, SpdmCertOperation,
+    SpdmCertValidityTime, SpdmCryptoRandom, SpdmDhe, SpdmDheKeyExchange, SpdmHash, SpdmHashContext,
+    SpdmHkdf, SpdmHmac, SpdmRng,
 };
 
-pub type HashCtx = spdm_ring::hash_impl::HashCtx;
+extern crate alloc;
+
+/// Running hash state, supplied by the registered hash provider.
+pub type HashCtx = alloc::boxed::Box<dyn SpdmHashContext>;
 
 use conquer_once::spin::OnceCell;
 
 static CRYPTO_HASH: OnceCell<SpdmHash> = OnceCell::uninit();
 static CRYPTO_HMAC: OnceCell<SpdmHmac> = OnceCell::uninit();
 static CRYPTO_AEAD: OnceCell<SpdmAead> = OnceCell::uninit();
+static CRYPTO_AEAD_OFFLOAD: OnceCell<&'static (dyn SpdmAeadOffload + Sync)> = OnceCell::uninit();
 static CRYPTO_ASYM_SIGN: OnceCell<SpdmAsymSign> = OnceCell::uninit();
+static CRYPTO_ASYM_SIGN_DEFERRED: OnceCell<SpdmAsymSignDeferred> = OnceCell::uninit();
 static CRYPTO_ASYM_VERIFY: OnceCell<SpdmAsymVerify> = OnceCell::uninit();
 static CRYPTO_DHE: OnceCell<SpdmDhe> = OnceCell::uninit();
 static CRYPTO_CERT_OPERATION: OnceCell<SpdmCertOperation> = OnceCell::uninit();
@@ -36,6 +51,11 @@ pub mod hash {
         hash_all_cb: |_base_hash_algo: SpdmBaseHashAlgo,
                       _data: &[u8]|
          -> Option<SpdmDigestStruct> { unimplemented!() },
+        hash_ctx_init_cb: |_base_hash_algo: SpdmBaseHashAlgo| -> Option<HashCtx> {
+            unimplemented!()
+        },
+        hash_ctx_update_cb: |_ctx: &mut HashCtx, _data: &[u8]| unimplemented!(),
+        hash_ctx_finalize_cb: |_ctx: HashCtx| -> Option<SpdmDigestStruct> { unimplemented!() },
     };
 
     #[cfg(feature = "spdm-ring")]
@@ -125,14 +145,65 @@ pub mod hmac {
 }
 
 pub mod asym_sign {
-    use super::CRYPTO_ASYM_SIGN;
-    use crate::crypto::SpdmAsymSign;
+    extern crate alloc;
+    use alloc::boxed::Box;
+
+    use super::{CRYPTO_ASYM_SIGN, CRYPTO_ASYM_SIGN_DEFERRED};
+    use crate::crypto::{
+        SpdmAsymSign, SpdmAsymSignDeferred, SpdmAsymSignOperation, SpdmAsymSignPoll,
+    };
     use crate::protocol::{SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmSignatureStruct};
 
     pub fn register(context: SpdmAsymSign) -> bool {
         CRYPTO_ASYM_SIGN.try_init_once(|| context).is_ok()
     }
 
+    /// Register an external signer. Once registered it serves every
+    /// signature instead of the synchronous `SpdmAsymSign`.
+    pub fn register_deferred(context: SpdmAsymSignDeferred) -> bool {
+        CRYPTO_ASYM_SIGN_DEFERRED.try_init_once(|| context).is_ok()
+    }
+
+    struct SpdmAsymSignReady(Option<SpdmSignatureStruct>);
+
+    impl SpdmAsymSignOperation for SpdmAsymSignReady {
+        fn poll(&mut self) -> SpdmAsymSignPoll {
+            match self.0.take() {
+                Some(signature) => SpdmAsymSignPoll::Ready(signature),
+                None => SpdmAsymSignPoll::Failed,
+            }
+        }
+    }
+
+    /// Start a signature that may complete later.
+    pub fn sign_start(
+        base_hash_algo: SpdmBaseHashAlgo,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> Option<Box<dyn SpdmAsymSignOperation>> {
+        if let Ok(deferred) = CRYPTO_ASYM_SIGN_DEFERRED.try_get() {
+            return (deferred.sign_start_cb)(base_hash_algo, base_asym_algo, data);
+        }
+        let signature = sign(base_hash_algo, base_asym_algo, data)?;
+        Some(Box::new(SpdmAsymSignReady(Some(signature))))
+    }
+
+    /// Start a signature with `asym_sign` instead of the registered signer.
+    pub fn sign_start_with(
+        asym_sign: &SpdmAsymSign,
+        base_hash_algo: SpdmBaseHashAlgo,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> Option<Box<dyn SpdmAsymSignOperation>> {
+        let signature = (asym_sign.sign_cb)(base_hash_algo, base_asym_algo, data)?;
+        Some(Box::new(SpdmAsymSignReady(Some(signature))))
+    }
+
+    // wait budget of sign for an external signer: 100 polls, 10ms apart
+    const SPDM_DEFERRED_SIGN_MAX_POLL: usize = 100;
+    const SPDM_DEFERRED_SIGN_POLL_INTERVAL_US: usize = 10_000;
+
+    #[cfg(not(any(feature = "spdm-ring")))]
     static DEFAULT: SpdmAsymSign = SpdmAsymSign {
         sign_cb: |_base_hash_algo: SpdmBaseHashAlgo,
                   _base_asym_algo: SpdmBaseAsymAlgo,
@@ -140,15 +211,51 @@ pub mod asym_sign {
          -> Option<SpdmSignatureStruct> { unimplemented!() },
     };
 
+    #[cfg(feature = "spdm-ring")]
+    use super::spdm_ring::asym_sign_impl::DEFAULT;
+
+    #[cfg(feature = "spdm-ring")]
+    pub use super::spdm_ring::asym_sign_impl::set_signing_key;
+
     pub fn sign(
         base_hash_algo: SpdmBaseHashAlgo,
         base_asym_algo: SpdmBaseAsymAlgo,
         data: &[u8],
     ) -> Option<SpdmSignatureStruct> {
-        (CRYPTO_ASYM_SIGN
-            .try_get_or_init(|| DEFAULT.clone())
-            .ok()?
-            .sign_cb)(base_hash_algo, base_asym_algo, data)
+        // callers that cannot defer their answer wait a bounded time for the
+        // external signer, responders defer through sign_start instead
+        if let Ok(deferred) = CRYPTO_ASYM_SIGN_DEFERRED.try_get() {
+            let mut operation = (deferred.sign_start_cb)(base_hash_algo, base_asym_algo, data)?;
+            for _ in 0..SPDM_DEFERRED_SIGN_MAX_POLL {
+                match operation.poll() {
+                    SpdmAsymSignPoll::Ready(signature) => return Some(signature),
+                    SpdmAsymSignPoll::Pending => {
+                        crate::time::sleep(SPDM_DEFERRED_SIGN_POLL_INTERVAL_US)
+                    }
+                    SpdmAsymSignPoll::Failed => return None,
+                }
+            }
+            error!("!!! sign : external signer timed out !!!\n");
+            return None;
+        }
+
+        // the default is not latched, a signer may still be registered later
+        let sign_cb = CRYPTO_ASYM_SIGN
+            .try_get()
+            .map_or(DEFAULT.sign_cb, |asym_sign| asym_sign.sign_cb);
+        sign_cb(base_hash_algo, base_asym_algo, data)
+    }
+
+    /// Whether sign can produce a signature: a signer is registered, or the
+    /// default one was given a signing key.
+    pub fn is_available() -> bool {
+        #[cfg(feature = "spdm-ring")]
+        let default_available = super::spdm_ring::asym_sign_impl::has_signing_key();
+        #[cfg(not(feature = "spdm-ring"))]
+        let default_available = false;
+        CRYPTO_ASYM_SIGN.is_initialized()
+            || CRYPTO_ASYM_SIGN_DEFERRED.is_initialized()
+            || default_available
     }
 }
 
@@ -200,17 +307,18 @@ pub mod dhe {
     use alloc::boxed::Box;
 
     use super::CRYPTO_DHE;
-    use crate::crypto::{SpdmDhe, SpdmDheKeyExchange};
-    use crate::protocol::{SpdmDheAlgo, SpdmDheExchangeStruct};
+    use crate::crypto::{SpdmDhe, SpdmDheKeyExchange, SpdmRng};
+    use crate::protocol::{SpdmDheAlgo, SpdmDheExchangeStruct, SpdmDheFinalKeyStruct};
 
     #[cfg(not(any(feature = "spdm-ring")))]
-    static DEFAULT: SpdmDhe =
-        SpdmDhe {
-            generate_key_pair_cb: |_dhe_algo: SpdmDheAlgo| -> Option<(
-                SpdmDheExchangeStruct,
-                Box<dyn SpdmDheKeyExchange>,
-            )> { unimplemented!() },
-        };
+    static DEFAULT: SpdmDhe = SpdmDhe {
+        generate_key_pair_cb:
+            |_dhe_algo: SpdmDheAlgo,
+             _rng: &mut dyn SpdmRng|
+             -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+                unimplemented!()
+            },
+    };
     #[cfg(feature = "spdm-ring")]
     use super::spdm_ring::dhe_impl::DEFAULT;
 
@@ -218,19 +326,52 @@ pub mod dhe {
         CRYPTO_DHE.try_init_once(|| context).is_ok()
     }
 
+    /// A key pair from crypto::dhe_pool if one is ready, else a new one.
     pub fn generate_key_pair(
         dhe_algo: SpdmDheAlgo,
+        rng: &mut dyn SpdmRng,
     ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        if let Some(key_pair) = super::dhe_pool::get().take(dhe_algo) {
+            return Some(key_pair);
+        }
+        generate_new_key_pair(dhe_algo, rng)
+    }
+
+    pub(crate) fn generate_new_key_pair(
+        dhe_algo: SpdmDheAlgo,
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        #[cfg(feature = "spdm-pqc-hybrid")]
+        if dhe_algo == SpdmDheAlgo::SECP_384_R1_MLKEM_768 {
+            return super::pqc_hybrid::generate_key_pair(rng);
+        }
         (CRYPTO_DHE
             .try_get_or_init(|| DEFAULT.clone())
             .ok()?
-            .generate_key_pair_cb)(dhe_algo)
+            .generate_key_pair_cb)(dhe_algo, rng)
+    }
+
+    /// Responder side of the exchange: the responder ExchangeData and the
+    /// DHE secret for the requester ExchangeData peer_pub_key.
+    pub fn generate_response(
+        dhe_algo: SpdmDheAlgo,
+        peer_pub_key: &SpdmDheExchangeStruct,
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, SpdmDheFinalKeyStruct)> {
+        // a KEM share depends on the requester share, unlike a DHE key pair
+        #[cfg(feature = "spdm-pqc-hybrid")]
+        if dhe_algo == SpdmDheAlgo::SECP_384_R1_MLKEM_768 {
+            return super::pqc_hybrid::encapsulate(peer_pub_key, rng);
+        }
+        let (exchange, key_exchange) = generate_key_pair(dhe_algo, rng)?;
+        let final_key = key_exchange.compute_final_key(peer_pub_key)?;
+        Some((exchange, final_key))
     }
 }
 
 pub mod cert_operation {
     use super::CRYPTO_CERT_OPERATION;
-    use crate::crypto::SpdmCertOperation;
+    use crate::crypto::{SpdmCertOperation, SpdmCertValidityTime};
     use crate::error::{spdm_err, SpdmResult};
 
     #[cfg(not(any(feature = "spdm-ring")))]
@@ -238,7 +379,10 @@ pub mod cert_operation {
         get_cert_from_cert_chain_cb: |_cert_chain: &[u8],
                                       _index: isize|
          -> SpdmResult<(usize, usize)> { unimplemented!() },
-        verify_cert_chain_cb: |_cert_chain: &[u8]| -> SpdmResult { unimplemented!() },
+        verify_cert_chain_cb: |_cert_chain: &[u8],
+                               _required_ekus: Option<&[&'static [u8]]>,
+                               _validity_time: SpdmCertValidityTime|
+         -> SpdmResult { unimplemented!() },
     };
 
     #[cfg(feature = "spdm-ring")]
@@ -255,11 +399,15 @@ pub mod cert_operation {
             .get_cert_from_cert_chain_cb)(cert_chain, index)
     }
 
-    pub fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
+    pub fn verify_cert_chain(
+        cert_chain: &[u8],
+        required_ekus: Option<&[&'static [u8]]>,
+        validity_time: SpdmCertValidityTime,
+    ) -> SpdmResult {
         (CRYPTO_CERT_OPERATION
             .try_get_or_init(|| DEFAULT.clone())
             .map_err(|_| spdm_err!(EFAULT))?
-            .verify_cert_chain_cb)(cert_chain)
+            .verify_cert_chain_cb)(cert_chain, required_ekus, validity_time)
     }
 }
 
@@ -270,6 +418,10 @@ pub mod hkdf {
 
     #[cfg(not(any(feature = "spdm-ring")))]
     static DEFAULT: SpdmHkdf = SpdmHkdf {
+        hkdf_extract_cb: |_hash_algo: SpdmBaseHashAlgo,
+                          _salt: &[u8],
+                          _ikm: &[u8]|
+         -> Option<SpdmDigestStruct> { unimplemented!() },
         hkdf_expand_cb: |_hash_algo: SpdmBaseHashAlgo,
                          _pk: &[u8],
                          _info: &[u8],
@@ -284,6 +436,17 @@ pub mod hkdf {
         CRYPTO_HKDF.try_init_once(|| context).is_ok()
     }
 
+    pub fn hkdf_extract(
+        hash_algo: SpdmBaseHashAlgo,
+        salt: &[u8],
+        ikm: &[u8],
+    ) -> Option<SpdmDigestStruct> {
+        (CRYPTO_HKDF
+            .try_get_or_init(|| DEFAULT.clone())
+            .ok()?
+            .hkdf_extract_cb)(hash_algo, salt, ikm)
+    }
+
     pub fn hkdf_expand(
         hash_algo: SpdmBaseHashAlgo,
         pk: &[u8],
@@ -298,8 +461,8 @@ pub mod hkdf {
 }
 
 pub mod aead {
-    use super::CRYPTO_AEAD;
-    use crate::crypto::SpdmAead;
+    use super::{CRYPTO_AEAD, CRYPTO_AEAD_OFFLOAD};
+    use crate::crypto::{SpdmAead, SpdmAeadKeyHandle, SpdmAeadOffload};
     use crate::error::{spdm_err, SpdmResult};
     use crate::protocol::SpdmAeadAlgo;
 
@@ -359,11 +522,64 @@ pub mod aead {
             .map_err(|_| spdm_err!(EFAULT))?
             .decrypt_cb)(aead_algo, key, iv, aad, cipher_text, tag, plain_text)
     }
+
+    /// Register an inline AEAD engine. Once registered, session keys are
+    /// loaded in it as they are derived and secured messages go through it.
+    pub fn register_offload(offload: &'static (dyn SpdmAeadOffload + Sync)) -> bool {
+        CRYPTO_AEAD_OFFLOAD.try_init_once(|| offload).is_ok()
+    }
+
+    pub fn is_offload_registered() -> bool {
+        CRYPTO_AEAD_OFFLOAD.is_initialized()
+    }
+
+    pub fn import_key(aead_algo: SpdmAeadAlgo, key: &[u8]) -> SpdmResult<SpdmAeadKeyHandle> {
+        CRYPTO_AEAD_OFFLOAD
+            .try_get()
+            .map_err(|_| spdm_err!(EFAULT))?
+            .import_key(aead_algo, key)
+    }
+
+    pub fn release_key(key_handle: SpdmAeadKeyHandle) {
+        if let Ok(offload) = CRYPTO_AEAD_OFFLOAD.try_get() {
+            offload.release_key(key_handle)
+        }
+    }
+
+    pub fn encrypt_with_handle(
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        plain_text: &[u8],
+        tag: &mut [u8],
+        cipher_text: &mut [u8],
+    ) -> SpdmResult<(usize, usize)> {
+        CRYPTO_AEAD_OFFLOAD
+            .try_get()
+            .map_err(|_| spdm_err!(EFAULT))?
+            .encrypt(aead_algo, key_handle, iv, aad, plain_text, tag, cipher_text)
+    }
+
+    pub fn decrypt_with_handle(
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        cipher_text: &[u8],
+        tag: &[u8],
+        plain_text: &mut [u8],
+    ) -> SpdmResult<usize> {
+        CRYPTO_AEAD_OFFLOAD
+            .try_get()
+            .map_err(|_| spdm_err!(EFAULT))?
+            .decrypt(aead_algo, key_handle, iv, aad, cipher_text, tag, plain_text)
+    }
 }
 
 pub mod rand {
     use super::CRYPTO_RAND;
-    use crate::crypto::SpdmCryptoRandom;
+    use crate::crypto::{SpdmCryptoRandom, SpdmRng};
     use crate::error::{spdm_err, SpdmResult};
 
     #[cfg(not(any(feature = "spdm-ring")))]
@@ -384,6 +600,15 @@ pub mod rand {
             .map_err(|_| spdm_err!(EFAULT))?
             .get_random_cb)(data)
     }
+
+    /// SpdmRng backed by the registered SpdmCryptoRandom.
+    pub struct SpdmCryptoRng;
+
+    impl SpdmRng for SpdmCryptoRng {
+        fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+            get_random(data)
+        }
+    }
 }
 
 #[cfg(all(test,))]
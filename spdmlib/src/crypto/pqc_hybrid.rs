@@ -0,0 +1,173 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Experimental hybrid SECP_384_R1 ECDHE + ML-KEM-768 key exchange.
+//!
+//! KEY_EXCHANGE ExchangeData carries the requester ECDHE public key followed
+//! by its ML-KEM encapsulation key, KEY_EXCHANGE_RSP ExchangeData carries the
+//! responder ECDHE public key followed by the ML-KEM ciphertext. The DHE
+//! secret fed to the key schedule is the ECDHE secret followed by the ML-KEM
+//! shared secret, so the session stays secure while either one holds.
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+use super::mlkem;
+use crate::crypto::{self, SpdmDheKeyExchange, SpdmRng};
+use crate::protocol::{
+    SpdmDheAlgo, SpdmDheExchangeStruct, SpdmDheFinalKeyStruct, SECP_384_R1_KEY_SIZE,
+    SECP_384_R1_MLKEM_768_REQ_KEY_SIZE, SECP_384_R1_MLKEM_768_RSP_KEY_SIZE,
+};
+use bytes::BytesMut;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub fn generate_key_pair(
+    rng: &mut dyn SpdmRng,
+) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+    let (ecdhe_exchange, ecdhe) = crypto::dhe::generate_key_pair(SpdmDheAlgo::SECP_384_R1, rng)?;
+
+    let mut seed = [0u8; 2 * mlkem::MLKEM_SEED_SIZE];
+    if rng.get_random(&mut seed).ok()? != seed.len() {
+        return None;
+    }
+    let mut d = [0u8; mlkem::MLKEM_SEED_SIZE];
+    let mut z = [0u8; mlkem::MLKEM_SEED_SIZE];
+    d.copy_from_slice(&seed[..mlkem::MLKEM_SEED_SIZE]);
+    z.copy_from_slice(&seed[mlkem::MLKEM_SEED_SIZE..]);
+    let (ek, dk) = mlkem::keygen(&d, &z);
+    seed.zeroize();
+    d.zeroize();
+    z.zeroize();
+
+    let mut exchange = BytesMut::with_capacity(SECP_384_R1_MLKEM_768_REQ_KEY_SIZE);
+    exchange.extend_from_slice(ecdhe_exchange.as_ref());
+    exchange.extend_from_slice(&ek);
+
+    let res: Box<dyn SpdmDheKeyExchange> = Box::new(SpdmDheKeyExchangeHybrid {
+        ecdhe: Some(ecdhe),
+        dk: Box::new(dk),
+    });
+    Some((SpdmDheExchangeStruct::from(exchange), res))
+}
+
+/// Responder side: ECDHE against the requester public key and encapsulate
+/// to its ML-KEM key. Returns (responder ExchangeData, DHE secret).
+pub fn encapsulate(
+    peer_exchange: &SpdmDheExchangeStruct,
+    rng: &mut dyn SpdmRng,
+) -> Option<(SpdmDheExchangeStruct, SpdmDheFinalKeyStruct)> {
+    let peer_exchange = peer_exchange.as_ref();
+    if peer_exchange.len() != SECP_384_R1_MLKEM_768_REQ_KEY_SIZE {
+        return None;
+    }
+    let (peer_ecdhe, peer_ek) = peer_exchange.split_at(SECP_384_R1_KEY_SIZE);
+
+    let (ecdhe_exchange, ecdhe) = crypto::dhe::generate_key_pair(SpdmDheAlgo::SECP_384_R1, rng)?;
+    let ecdhe_final_key =
+        ecdhe.compute_final_key(&SpdmDheExchangeStruct::from(BytesMut::from(peer_ecdhe)))?;
+
+    let mut m = [0u8; mlkem::MLKEM_SEED_SIZE];
+    if rng.get_random(&mut m).ok()? != m.len() {
+        return None;
+    }
+    let encapsulated = mlkem::encaps(peer_ek, &m);
+    m.zeroize();
+    let (ct, mut shared_secret) = encapsulated?;
+
+    let mut exchange = BytesMut::with_capacity(SECP_384_R1_MLKEM_768_RSP_KEY_SIZE);
+    exchange.extend_from_slice(ecdhe_exchange.as_ref());
+    exchange.extend_from_slice(&ct);
+
+    let final_key = combine_final_key(&ecdhe_final_key, &shared_secret);
+    shared_secret.zeroize();
+    Some((SpdmDheExchangeStruct::from(exchange), final_key))
+}
+
+fn combine_final_key(
+    ecdhe_final_key: &SpdmDheFinalKeyStruct,
+    mlkem_shared_secret: &[u8],
+) -> SpdmDheFinalKeyStruct {
+    let ecdhe_len = ecdhe_final_key.data_size as usize;
+    let mut final_key = SpdmDheFinalKeyStruct {
+        data_size: (ecdhe_len + mlkem_shared_secret.len()) as u16,
+        ..Default::default()
+    };
+    final_key.data[..ecdhe_len].copy_from_slice(ecdhe_final_key.as_ref());
+    final_key.data[ecdhe_len..(final_key.data_size as usize)].copy_from_slice(mlkem_shared_secret);
+    final_key
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct SpdmDheKeyExchangeHybrid {
+    #[zeroize(skip)]
+    ecdhe: Option<Box<dyn SpdmDheKeyExchange>>,
+    dk: Box<[u8; mlkem::MLKEM_768_DECAPS_KEY_SIZE]>,
+}
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeHybrid {
+    fn compute_final_key(
+        mut self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        let peer_pub_key = peer_pub_key.as_ref();
+        if peer_pub_key.len() != SECP_384_R1_MLKEM_768_RSP_KEY_SIZE {
+            error!("!!! hybrid dhe : invalid peer exchange data size !!!\n");
+            return None;
+        }
+        let (peer_ecdhe, ct) = peer_pub_key.split_at(SECP_384_R1_KEY_SIZE);
+
+        let ecdhe_final_key = self
+            .ecdhe
+            .take()?
+            .compute_final_key(&SpdmDheExchangeStruct::from(BytesMut::from(peer_ecdhe)))?;
+        let mut shared_secret = mlkem::decaps(&self.dk, ct)?;
+
+        let final_key = combine_final_key(&ecdhe_final_key, &shared_secret);
+        shared_secret.zeroize();
+        Some(final_key)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_hybrid_key_exchange() {
+        let rng = &mut crypto::rand::SpdmCryptoRng;
+        let (req_exchange, req_key_exchange) = generate_key_pair(rng).unwrap();
+        assert_eq!(
+            req_exchange.data_size as usize,
+            SECP_384_R1_MLKEM_768_REQ_KEY_SIZE
+        );
+
+        let (rsp_exchange, rsp_final_key) = encapsulate(&req_exchange, rng).unwrap();
+        assert_eq!(
+            rsp_exchange.data_size as usize,
+            SECP_384_R1_MLKEM_768_RSP_KEY_SIZE
+        );
+        assert_eq!(
+            rsp_final_key.data_size as usize,
+            48 + mlkem::MLKEM_SHARED_SECRET_SIZE
+        );
+
+        let req_final_key = req_key_exchange.compute_final_key(&rsp_exchange).unwrap();
+        assert_eq!(req_final_key.as_ref(), rsp_final_key.as_ref());
+    }
+
+    #[test]
+    fn test_case1_hybrid_key_exchange() {
+        let rng = &mut crypto::rand::SpdmCryptoRng;
+        let (req_exchange, req_key_exchange) = generate_key_pair(rng).unwrap();
+        let (mut rsp_exchange, rsp_final_key) = encapsulate(&req_exchange, rng).unwrap();
+
+        // a responder share is not a valid requester share
+        assert!(encapsulate(&rsp_exchange, rng).is_none());
+
+        // a tampered ciphertext still decapsulates, to a different secret
+        rsp_exchange.data[SECP_384_R1_KEY_SIZE] ^= 1;
+        let req_final_key = req_key_exchange.compute_final_key(&rsp_exchange).unwrap();
+        assert_ne!(req_final_key.as_ref(), rsp_final_key.as_ref());
+    }
+}
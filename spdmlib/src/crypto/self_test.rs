@@ -0,0 +1,496 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Power-on self-test of the registered crypto primitives.
+//!
+//! Hash, HMAC, HKDF, AEAD and ECDSA verification run known-answer tests,
+//! ECDHE runs a pairwise consistency test since the key pair comes from the
+//! RNG. Signing runs the known answer registered with `register_sign_kat`,
+//! the device key is not known here. The tests run once, on the first call
+//! to `run`, and the outcome sticks: no SPDM message is sent until the
+//! self-test passed.
+//!
+//! Register the crypto providers before the first `run`, the self-test
+//! locks in whatever is registered at that point.
+
+use super::{aead, asym_sign, asym_verify, dhe, hash, hkdf, hmac, rand};
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::protocol::{
+    SpdmAeadAlgo, SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmDheAlgo, SpdmDigestStruct,
+    SpdmSignatureStruct,
+};
+use conquer_once::spin::OnceCell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmSelfTestStatus {
+    NotRun,
+    Passed,
+    Failed,
+}
+
+/// Known answer of the device signer: `signature` is what the provisioned
+/// key gives for `message`. Only deterministic schemes (RSASSA) have one.
+#[derive(Debug, Clone, Copy)]
+pub struct SpdmSignKat {
+    pub base_hash_algo: SpdmBaseHashAlgo,
+    pub base_asym_algo: SpdmBaseAsymAlgo,
+    pub message: &'static [u8],
+    pub signature: &'static [u8],
+}
+
+static SELF_TEST_RESULT: OnceCell<bool> = OnceCell::uninit();
+static SIGN_KAT: OnceCell<SpdmSignKat> = OnceCell::uninit();
+
+/// Register the known answer of `asym_sign`, before the first `run`.
+pub fn register_sign_kat(kat: SpdmSignKat) -> bool {
+    SIGN_KAT.try_init_once(|| kat).is_ok()
+}
+
+/// Run the self-test if it has not run yet, Ok once it passed.
+pub fn run() -> SpdmResult {
+    match SELF_TEST_RESULT.try_get_or_init(|| run_all().is_ok()) {
+        Ok(true) => Ok(()),
+        _ => spdm_result_err!(ESEC),
+    }
+}
+
+pub fn status() -> SpdmSelfTestStatus {
+    match SELF_TEST_RESULT.try_get() {
+        Ok(true) => SpdmSelfTestStatus::Passed,
+        Ok(false) => SpdmSelfTestStatus::Failed,
+        Err(_) => SpdmSelfTestStatus::NotRun,
+    }
+}
+
+fn run_all() -> SpdmResult {
+    hash_self_test()?;
+    hmac_self_test()?;
+    hkdf_self_test()?;
+    aead_self_test()?;
+    asym_verify_self_test()?;
+    sign_self_test()?;
+    dhe_self_test()?;
+    info!("!!! crypto self test pass !!!\n");
+    Ok(())
+}
+
+fn check(pass: bool, name: &str) -> SpdmResult {
+    if pass {
+        Ok(())
+    } else {
+        error!("!!! crypto self test : {} fail !!!\n", name);
+        spdm_result_err!(ESEC)
+    }
+}
+
+// FIPS 180-4 examples, SHA(abc)
+static HASH_KAT: [(SpdmBaseHashAlgo, &[u8]); 3] = [
+    (
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+        &[
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ],
+    ),
+    (
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+        &[
+            0xcb, 0x00, 0x75, 0x3f, 0x45, 0xa3, 0x5e, 0x8b, 0xb5, 0xa0, 0x3d, 0x69, 0x9a, 0xc6,
+            0x50, 0x07, 0x27, 0x2c, 0x32, 0xab, 0x0e, 0xde, 0xd1, 0x63, 0x1a, 0x8b, 0x60, 0x5a,
+            0x43, 0xff, 0x5b, 0xed, 0x80, 0x86, 0x07, 0x2b, 0xa1, 0xe7, 0xcc, 0x23, 0x58, 0xba,
+            0xec, 0xa1, 0x34, 0xc8, 0x25, 0xa7,
+        ],
+    ),
+    (
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512,
+        &[
+            0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20,
+            0x41, 0x31, 0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6,
+            0x4b, 0x55, 0xd3, 0x9a, 0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba,
+            0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd, 0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e,
+            0x2a, 0x9a, 0xc9, 0x4f, 0xa5, 0x4c, 0xa4, 0x9f,
+        ],
+    ),
+];
+
+fn hash_self_test() -> SpdmResult {
+    for (hash_algo, expected) in HASH_KAT.iter() {
+        let digest = hash::hash_all(*hash_algo, b"abc").ok_or(spdm_err!(ESEC))?;
+        check(digest.as_ref() == *expected, "hash")?;
+
+        let mut ctx = hash::hash_ctx_init(*hash_algo).ok_or(spdm_err!(ESEC))?;
+        hash::hash_ctx_update(&mut ctx, b"a");
+        hash::hash_ctx_update(&mut ctx, b"bc");
+        let digest = hash::hash_ctx_finalize(ctx).ok_or(spdm_err!(ESEC))?;
+        check(digest.as_ref() == *expected, "hash context")?;
+    }
+    Ok(())
+}
+
+// RFC 4231 test case 2
+static HMAC_KAT_KEY: &[u8] = b"Jefe";
+static HMAC_KAT_DATA: &[u8] = b"what do ya want for nothing?";
+static HMAC_KAT: [(SpdmBaseHashAlgo, &[u8]); 2] = [
+    (
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+        &[
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+            0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+            0x64, 0xec, 0x38, 0x43,
+        ],
+    ),
+    (
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+        &[
+            0xaf, 0x45, 0xd2, 0xe3, 0x76, 0x48, 0x40, 0x31, 0x61, 0x7f, 0x78, 0xd2, 0xb5, 0x8a,
+            0x6b, 0x1b, 0x9c, 0x7e, 0xf4, 0x64, 0xf5, 0xa0, 0x1b, 0x47, 0xe4, 0x2e, 0xc3, 0x73,
+            0x63, 0x22, 0x44, 0x5e, 0x8e, 0x22, 0x40, 0xca, 0x5e, 0x69, 0xe2, 0xc7, 0x8b, 0x32,
+            0x39, 0xec, 0xfa, 0xb2, 0x16, 0x49,
+        ],
+    ),
+];
+
+fn hmac_self_test() -> SpdmResult {
+    for (hash_algo, expected) in HMAC_KAT.iter() {
+        let mac = hmac::hmac(*hash_algo, HMAC_KAT_KEY, HMAC_KAT_DATA).ok_or(spdm_err!(ESEC))?;
+        check(mac.as_ref() == *expected, "hmac")?;
+
+        let mut mac = SpdmDigestStruct::from(*expected);
+        check(
+            hmac::hmac_verify(*hash_algo, HMAC_KAT_KEY, HMAC_KAT_DATA, &mac).is_ok(),
+            "hmac verify",
+        )?;
+        mac.data[0] ^= 1;
+        check(
+            hmac::hmac_verify(*hash_algo, HMAC_KAT_KEY, HMAC_KAT_DATA, &mac).is_err(),
+            "hmac verify",
+        )?;
+    }
+    Ok(())
+}
+
+// RFC 5869 test case 1
+static HKDF_KAT_SALT: &[u8] = &[
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+];
+static HKDF_KAT_IKM: &[u8] = &[0x0b; 22];
+static HKDF_KAT_PRK: &[u8] = &[
+    0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b, 0xba, 0x63,
+    0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a, 0xd7, 0xc2, 0xb3, 0xe5,
+];
+static HKDF_KAT_INFO: &[u8] = &[0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+static HKDF_KAT_OKM: &[u8] = &[
+    0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36, 0x2f, 0x2a,
+    0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4, 0xc5, 0xbf,
+    0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+];
+
+fn hkdf_self_test() -> SpdmResult {
+    let prk = hkdf::hkdf_extract(
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+        HKDF_KAT_SALT,
+        HKDF_KAT_IKM,
+    )
+    .ok_or(spdm_err!(ESEC))?;
+    check(prk.as_ref() == HKDF_KAT_PRK, "hkdf extract")?;
+
+    let okm = hkdf::hkdf_expand(
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+        HKDF_KAT_PRK,
+        HKDF_KAT_INFO,
+        HKDF_KAT_OKM.len() as u16,
+    )
+    .ok_or(spdm_err!(ESEC))?;
+    check(okm.as_ref() == HKDF_KAT_OKM, "hkdf")
+}
+
+struct SpdmAeadKat {
+    aead_algo: SpdmAeadAlgo,
+    key: &'static [u8],
+    iv: &'static [u8],
+    aad: &'static [u8],
+    plain_text: &'static [u8],
+    cipher_text: &'static [u8],
+    tag: &'static [u8],
+}
+
+static AEAD_KAT: [SpdmAeadKat; 2] = [
+    // SP 800-38D test vectors, [Keylen = 256] Count = 0
+    SpdmAeadKat {
+        aead_algo: SpdmAeadAlgo::AES_256_GCM,
+        key: &[
+            0x92, 0xe1, 0x1d, 0xcd, 0xaa, 0x86, 0x6f, 0x5c, 0xe7, 0x90, 0xfd, 0x24, 0x50, 0x1f,
+            0x92, 0x50, 0x9a, 0xac, 0xf4, 0xcb, 0x8b, 0x13, 0x39, 0xd5, 0x0c, 0x9c, 0x12, 0x40,
+            0x93, 0x5d, 0xd0, 0x8b,
+        ],
+        iv: &[
+            0xac, 0x93, 0xa1, 0xa6, 0x14, 0x52, 0x99, 0xbd, 0xe9, 0x02, 0xf2, 0x1a,
+        ],
+        aad: &[
+            0x1e, 0x08, 0x89, 0x01, 0x6f, 0x67, 0x60, 0x1c, 0x8e, 0xbe, 0xa4, 0x94, 0x3b, 0xc2,
+            0x3a, 0xd6,
+        ],
+        plain_text: &[
+            0x2d, 0x71, 0xbc, 0xfa, 0x91, 0x4e, 0x4a, 0xc0, 0x45, 0xb2, 0xaa, 0x60, 0x95, 0x5f,
+            0xad, 0x24,
+        ],
+        cipher_text: &[
+            0x89, 0x95, 0xae, 0x2e, 0x6d, 0xf3, 0xdb, 0xf9, 0x6f, 0xac, 0x7b, 0x71, 0x37, 0xba,
+            0xe6, 0x7f,
+        ],
+        tag: &[
+            0xec, 0xa5, 0xaa, 0x77, 0xd5, 0x1d, 0x4a, 0x0a, 0x14, 0xd9, 0xc5, 0x1e, 0x1d, 0xa4,
+            0x74, 0xab,
+        ],
+    },
+    // RFC 8439 section 2.8.2
+    SpdmAeadKat {
+        aead_algo: SpdmAeadAlgo::CHACHA20_POLY1305,
+        key: &[
+            0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d,
+            0x8e, 0x8f, 0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0x9b,
+            0x9c, 0x9d, 0x9e, 0x9f,
+        ],
+        iv: &[
+            0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ],
+        aad: &[
+            0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        ],
+        plain_text: b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.",
+        cipher_text: &[
+            0xd3, 0x1a, 0x8d, 0x34, 0x64, 0x8e, 0x60, 0xdb, 0x7b, 0x86, 0xaf, 0xbc, 0x53, 0xef,
+            0x7e, 0xc2, 0xa4, 0xad, 0xed, 0x51, 0x29, 0x6e, 0x08, 0xfe, 0xa9, 0xe2, 0xb5, 0xa7,
+            0x36, 0xee, 0x62, 0xd6, 0x3d, 0xbe, 0xa4, 0x5e, 0x8c, 0xa9, 0x67, 0x12, 0x82, 0xfa,
+            0xfb, 0x69, 0xda, 0x92, 0x72, 0x8b, 0x1a, 0x71, 0xde, 0x0a, 0x9e, 0x06, 0x0b, 0x29,
+            0x05, 0xd6, 0xa5, 0xb6, 0x7e, 0xcd, 0x3b, 0x36, 0x92, 0xdd, 0xbd, 0x7f, 0x2d, 0x77,
+            0x8b, 0x8c, 0x98, 0x03, 0xae, 0xe3, 0x28, 0x09, 0x1b, 0x58, 0xfa, 0xb3, 0x24, 0xe4,
+            0xfa, 0xd6, 0x75, 0x94, 0x55, 0x85, 0x80, 0x8b, 0x48, 0x31, 0xd7, 0xbc, 0x3f, 0xf4,
+            0xde, 0xf0, 0x8e, 0x4b, 0x7a, 0x9d, 0xe5, 0x76, 0xd2, 0x65, 0x86, 0xce, 0xc6, 0x4b,
+            0x61, 0x16,
+        ],
+        tag: &[
+            0x1a, 0xe1, 0x0b, 0x59, 0x4f, 0x09, 0xe2, 0x6a, 0x7e, 0x90, 0x2e, 0xcb, 0xd0, 0x60,
+            0x06, 0x91,
+        ],
+    },
+];
+
+fn aead_self_test() -> SpdmResult {
+    for kat in AEAD_KAT.iter() {
+        let size = kat.plain_text.len();
+        let mut cipher_text = [0u8; 128];
+        let mut plain_text = [0u8; 128];
+        let mut tag = [0u8; 16];
+
+        aead::encrypt(
+            kat.aead_algo,
+            kat.key,
+            kat.iv,
+            kat.aad,
+            kat.plain_text,
+            &mut tag,
+            &mut cipher_text[..size],
+        )?;
+        check(
+            &cipher_text[..size] == kat.cipher_text && &tag[..] == kat.tag,
+            "aead encrypt",
+        )?;
+
+        aead::decrypt(
+            kat.aead_algo,
+            kat.key,
+            kat.iv,
+            kat.aad,
+            kat.cipher_text,
+            kat.tag,
+            &mut plain_text[..size],
+        )?;
+        check(&plain_text[..size] == kat.plain_text, "aead decrypt")?;
+
+        tag.copy_from_slice(kat.tag);
+        tag[0] ^= 1;
+        check(
+            aead::decrypt(
+                kat.aead_algo,
+                kat.key,
+                kat.iv,
+                kat.aad,
+                kat.cipher_text,
+                &tag,
+                &mut plain_text[..size],
+            )
+            .is_err(),
+            "aead decrypt",
+        )?;
+    }
+    Ok(())
+}
+
+// self-signed ECDSA P-384 certificate and its SHA-384 signature of
+// ECDSA_KAT_MESSAGE, r || s
+static ECDSA_KAT_CERT: &[u8] = include_bytes!("self_test_cert.der");
+static ECDSA_KAT_MESSAGE: &[u8] = b"spdm crypto self test";
+static ECDSA_KAT_SIGNATURE: &[u8] = &[
+    0xcc, 0xd1, 0xda, 0xb4, 0xf4, 0x20, 0x3b, 0xcd, 0x34, 0x5f, 0xd5, 0xd3, 0x27, 0x8a, 0x90, 0xc6,
+    0x78, 0xff, 0x63, 0xa8, 0x27, 0x1c, 0xf4, 0xcc, 0xe5, 0xee, 0x54, 0xb1, 0xb0, 0xc1, 0x42, 0xf4,
+    0x40, 0xc6, 0xb5, 0x9c, 0x49, 0xa8, 0x7f, 0x10, 0x6a, 0x94, 0x34, 0xae, 0x64, 0x5d, 0xb4, 0x94,
+    0x63, 0x80, 0x1d, 0x2f, 0x14, 0x7e, 0xda, 0x1f, 0xab, 0x70, 0x5f, 0x14, 0xdf, 0x4c, 0xa8, 0x50,
+    0x5e, 0xa2, 0xdb, 0x1c, 0x89, 0x9d, 0x20, 0x91, 0x39, 0x87, 0x52, 0xe8, 0x04, 0x54, 0x1e, 0xc1,
+    0x1c, 0xfd, 0x5c, 0x40, 0x2d, 0x58, 0xfd, 0x44, 0x1b, 0x66, 0x4f, 0x55, 0xfb, 0xba, 0xd0, 0xf2,
+];
+
+fn asym_verify_self_test() -> SpdmResult {
+    let mut signature = SpdmSignatureStruct {
+        data_size: ECDSA_KAT_SIGNATURE.len() as u16,
+        ..Default::default()
+    };
+    signature.data[..ECDSA_KAT_SIGNATURE.len()].copy_from_slice(ECDSA_KAT_SIGNATURE);
+
+    check(
+        asym_verify::verify(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
+            ECDSA_KAT_CERT,
+            ECDSA_KAT_MESSAGE,
+            &signature,
+        )
+        .is_ok(),
+        "ecdsa verify",
+    )?;
+    check(
+        asym_verify::verify(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
+            ECDSA_KAT_CERT,
+            b"spdm crypto self tesT",
+            &signature,
+        )
+        .is_err(),
+        "ecdsa verify",
+    )
+}
+
+type SpdmSignFn = fn(SpdmBaseHashAlgo, SpdmBaseAsymAlgo, &[u8]) -> Option<SpdmSignatureStruct>;
+
+fn sign_self_test() -> SpdmResult {
+    match SIGN_KAT.try_get() {
+        Ok(kat) => sign_kat(kat, asym_sign::sign),
+        Err(_) => {
+            info!("crypto self test : no sign known answer registered\n");
+            Ok(())
+        }
+    }
+}
+
+fn sign_kat(kat: &SpdmSignKat, sign: SpdmSignFn) -> SpdmResult {
+    let signature =
+        sign(kat.base_hash_algo, kat.base_asym_algo, kat.message).ok_or(spdm_err!(ESEC))?;
+    check(signature.as_ref() == kat.signature, "sign")
+}
+
+fn dhe_self_test() -> SpdmResult {
+    for dhe_algo in [SpdmDheAlgo::SECP_256_R1, SpdmDheAlgo::SECP_384_R1].iter() {
+        let rng = &mut rand::SpdmCryptoRng;
+        let (exchange_a, key_exchange_a) =
+            dhe::generate_key_pair(*dhe_algo, rng).ok_or(spdm_err!(ESEC))?;
+        let (exchange_b, key_exchange_b) =
+            dhe::generate_key_pair(*dhe_algo, rng).ok_or(spdm_err!(ESEC))?;
+        check(exchange_a.as_ref() != exchange_b.as_ref(), "ecdhe")?;
+
+        let final_key_a = key_exchange_a.compute_final_key(&exchange_b);
+        let final_key_b = key_exchange_b.compute_final_key(&exchange_a);
+        match (final_key_a, final_key_b) {
+            (Some(a), Some(b)) => check(a.data_size != 0 && a.as_ref() == b.as_ref(), "ecdhe")?,
+            _ => return spdm_result_err!(ESEC),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_self_test() {
+        assert!(run_all().is_ok());
+        assert!(run().is_ok());
+        assert_eq!(status(), SpdmSelfTestStatus::Passed);
+        // the outcome is kept
+        assert!(run().is_ok());
+    }
+
+    // RSASSA-3072 SHA-384 signature of ECDSA_KAT_MESSAGE with
+    // test_key/Rsa3072/end_responder.key.der
+    static RSASSA_KAT_SIGNATURE: &[u8] = &[
+        0x55, 0x69, 0x5a, 0x52, 0x3e, 0xd9, 0x7e, 0x00, 0x40, 0xd5, 0x1a, 0x92, 0x95, 0xd0, 0xde,
+        0xd6, 0xc6, 0x11, 0x20, 0xa1, 0xa8, 0x5f, 0x6d, 0x25, 0x0f, 0xd4, 0x89, 0x51, 0x85, 0x2a,
+        0x62, 0x34, 0x68, 0x77, 0x41, 0x8d, 0x43, 0x56, 0xaa, 0xa9, 0x93, 0x17, 0x7d, 0xf8, 0x3d,
+        0x82, 0x1e, 0xf7, 0x03, 0x24, 0x0a, 0xa9, 0x76, 0xf8, 0x59, 0x1c, 0xef, 0x91, 0xa3, 0x72,
+        0xdb, 0xb3, 0x35, 0x47, 0x7e, 0x18, 0x20, 0x21, 0x00, 0x4f, 0x18, 0xe5, 0x13, 0x5e, 0xb1,
+        0xa0, 0x90, 0x87, 0xdc, 0x4e, 0x07, 0xbc, 0x7e, 0x5b, 0x24, 0xb1, 0x28, 0x0b, 0xba, 0x89,
+        0xe1, 0x01, 0xa5, 0x49, 0x47, 0xee, 0x9b, 0xe7, 0xde, 0x96, 0xa2, 0xd1, 0xa0, 0x55, 0x6b,
+        0x14, 0x00, 0x6f, 0x0b, 0xc4, 0xc6, 0x55, 0xfb, 0x74, 0x40, 0xb5, 0x58, 0xc8, 0x82, 0xf4,
+        0x42, 0x95, 0xcc, 0x85, 0xc2, 0xbd, 0x2f, 0x43, 0x02, 0x97, 0x59, 0xf8, 0x83, 0x9b, 0x06,
+        0x44, 0x0e, 0xd8, 0x7c, 0x6f, 0xec, 0x77, 0x68, 0x48, 0x21, 0xa6, 0x81, 0x5d, 0x3f, 0x4d,
+        0xb5, 0x50, 0x18, 0xff, 0xfa, 0x03, 0x4e, 0xd9, 0x32, 0x20, 0x8b, 0x93, 0xbe, 0x52, 0x9d,
+        0x0b, 0xe4, 0xd0, 0xe1, 0xd3, 0x90, 0x94, 0x81, 0xdd, 0x96, 0xde, 0xde, 0x0a, 0x62, 0x5a,
+        0xcf, 0x9d, 0xa2, 0xc9, 0x37, 0x59, 0x58, 0x68, 0x7c, 0x87, 0x40, 0xef, 0xa4, 0xb7, 0xf3,
+        0xe5, 0x96, 0x90, 0x84, 0x71, 0x5f, 0xf7, 0x67, 0x66, 0xfe, 0xec, 0x5a, 0x7e, 0x7c, 0xee,
+        0xe5, 0x6b, 0x88, 0x21, 0x0f, 0xae, 0xbb, 0xde, 0x96, 0x7d, 0x5e, 0x84, 0x17, 0xd3, 0x35,
+        0x0b, 0x0e, 0x36, 0x85, 0xbc, 0x23, 0x9e, 0xe7, 0xa8, 0xcd, 0x32, 0x9f, 0xda, 0x63, 0xf4,
+        0xde, 0x8a, 0x9d, 0x72, 0x46, 0x0e, 0xf4, 0x76, 0xc7, 0xce, 0x89, 0x8c, 0x6a, 0x67, 0x6b,
+        0x5b, 0x5b, 0x11, 0x81, 0x28, 0x9b, 0xe8, 0x9b, 0x6a, 0xdc, 0xcd, 0x9c, 0xca, 0xf3, 0xea,
+        0x15, 0x6c, 0xa0, 0xdc, 0xf6, 0xc6, 0xa5, 0xd2, 0x1d, 0x93, 0xa1, 0xbc, 0xa1, 0x0c, 0xb8,
+        0xae, 0x63, 0x40, 0x65, 0xde, 0xde, 0x54, 0xd2, 0x95, 0xba, 0x15, 0xe3, 0x98, 0x8a, 0xec,
+        0x32, 0xc5, 0xe1, 0x8e, 0x48, 0xad, 0x0c, 0xe9, 0xda, 0x84, 0x9f, 0xa1, 0xdd, 0x8d, 0x61,
+        0xb5, 0xb5, 0xc2, 0x86, 0xcb, 0xb8, 0xbc, 0xd0, 0x3b, 0x67, 0xa7, 0x44, 0xe6, 0xf9, 0xd5,
+        0xff, 0x1b, 0xb3, 0x97, 0x0e, 0xab, 0x2d, 0x3b, 0x91, 0x0c, 0x09, 0x95, 0x78, 0xb9, 0x0d,
+        0x19, 0x2e, 0xbf, 0x91, 0xe0, 0x53, 0xa6, 0xb4, 0xee, 0xd3, 0xf4, 0x4e, 0xdc, 0xde, 0xc8,
+        0x01, 0x96, 0x5a, 0xa1, 0x24, 0xf9, 0xea, 0x7b, 0x40, 0x0e, 0x47, 0x44, 0x96, 0xba, 0xcd,
+        0x6b, 0xcf, 0x74, 0x37, 0xee, 0xb2, 0x83, 0xf3, 0x3a,
+    ];
+
+    fn rsassa_sign(
+        base_hash_algo: SpdmBaseHashAlgo,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> Option<SpdmSignatureStruct> {
+        assert_eq!(base_hash_algo, SpdmBaseHashAlgo::TPM_ALG_SHA_384);
+        assert_eq!(base_asym_algo, SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072);
+        let key_pair = ring::signature::RsaKeyPair::from_der(include_bytes!(
+            "../../../test_key/Rsa3072/end_responder.key.der"
+        ))
+        .ok()?;
+        let mut signature = SpdmSignatureStruct {
+            data_size: key_pair.public_modulus_len() as u16,
+            ..Default::default()
+        };
+        key_pair
+            .sign(
+                &ring::signature::RSA_PKCS1_SHA384,
+                &ring::rand::SystemRandom::new(),
+                data,
+                &mut signature.data[..signature.data_size as usize],
+            )
+            .ok()?;
+        Some(signature)
+    }
+
+    #[test]
+    fn test_case1_sign_kat() {
+        let mut kat = SpdmSignKat {
+            base_hash_algo: SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            base_asym_algo: SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072,
+            message: ECDSA_KAT_MESSAGE,
+            signature: RSASSA_KAT_SIGNATURE,
+        };
+        assert!(sign_kat(&kat, rsassa_sign).is_ok());
+
+        kat.message = b"spdm crypto self tesT";
+        assert!(sign_kat(&kat, rsassa_sign).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::crypto::SpdmAsymSign;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmSignatureStruct, SPDM_MAX_ASYM_KEY_SIZE,
+};
+use conquer_once::spin::OnceCell;
+
+enum SigningKey {
+    Rsa(ring::signature::RsaKeyPair),
+    Ecdsa(ring::signature::EcdsaKeyPair),
+}
+
+static SIGNING_KEY: OnceCell<(SpdmBaseAsymAlgo, SigningKey)> = OnceCell::uninit();
+
+pub static DEFAULT: SpdmAsymSign = SpdmAsymSign { sign_cb: asym_sign };
+
+/// Provision the device private key used by `DEFAULT`, once.
+///
+/// RSA keys are PKCS#1 RSAPrivateKey or PKCS#8 DER; ECDSA keys are PKCS#8 DER.
+pub fn set_signing_key(base_asym_algo: SpdmBaseAsymAlgo, private_key_der: &[u8]) -> SpdmResult {
+    let signing_key = match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096 => {
+            let key_pair = match ring::signature::RsaKeyPair::from_der(private_key_der) {
+                Ok(key_pair) => key_pair,
+                Err(_) => match ring::signature::RsaKeyPair::from_pkcs8(private_key_der) {
+                    Ok(key_pair) => key_pair,
+                    Err(_) => return spdm_result_err!(EINVAL),
+                },
+            };
+            if key_pair.public_modulus_len() != base_asym_algo.get_size() as usize {
+                return spdm_result_err!(EINVAL);
+            }
+            SigningKey::Rsa(key_pair)
+        }
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => {
+            let algorithm = get_ecdsa_algorithm(base_asym_algo);
+            match ring::signature::EcdsaKeyPair::from_pkcs8(algorithm, private_key_der) {
+                Ok(key_pair) => SigningKey::Ecdsa(key_pair),
+                Err(_) => return spdm_result_err!(EINVAL),
+            }
+        }
+        _ => return spdm_result_err!(EINVAL),
+    };
+
+    if SIGNING_KEY
+        .try_init_once(|| (base_asym_algo, signing_key))
+        .is_err()
+    {
+        return spdm_result_err!(EEXIST);
+    }
+    Ok(())
+}
+
+/// Whether set_signing_key was called.
+pub fn has_signing_key() -> bool {
+    SIGNING_KEY.is_initialized()
+}
+
+// ring binds each ECDSA curve to one digest
+fn get_ecdsa_algorithm(
+    base_asym_algo: SpdmBaseAsymAlgo,
+) -> &'static ring::signature::EcdsaSigningAlgorithm {
+    if base_asym_algo == SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 {
+        &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING
+    } else {
+        &ring::signature::ECDSA_P384_SHA384_FIXED_SIGNING
+    }
+}
+
+fn get_rsa_padding(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+) -> Option<&'static dyn ring::signature::RsaEncoding> {
+    let pss = match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096 => false,
+        SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096 => true,
+        _ => return None,
+    };
+    match (base_hash_algo, pss) {
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_256, false) => Some(&ring::signature::RSA_PKCS1_SHA256),
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_384, false) => Some(&ring::signature::RSA_PKCS1_SHA384),
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_512, false) => Some(&ring::signature::RSA_PKCS1_SHA512),
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_256, true) => Some(&ring::signature::RSA_PSS_SHA256),
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_384, true) => Some(&ring::signature::RSA_PSS_SHA384),
+        (SpdmBaseHashAlgo::TPM_ALG_SHA_512, true) => Some(&ring::signature::RSA_PSS_SHA512),
+        _ => None,
+    }
+}
+
+fn asym_sign(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    let (key_asym_algo, signing_key) = SIGNING_KEY.try_get().ok()?;
+
+    let rng = ring::rand::SystemRandom::new();
+    let mut signature = SpdmSignatureStruct {
+        data_size: base_asym_algo.get_size(),
+        data: [0u8; SPDM_MAX_ASYM_KEY_SIZE],
+    };
+
+    match signing_key {
+        // one RSA key serves both RSASSA and RSAPSS of its size
+        SigningKey::Rsa(key_pair) => {
+            let padding_alg = get_rsa_padding(base_hash_algo, base_asym_algo)?;
+            if key_pair.public_modulus_len() != signature.data_size as usize {
+                error!("!!! asym_sign : RSA key size mismatch !!!\n");
+                return None;
+            }
+            key_pair
+                .sign(
+                    padding_alg,
+                    &rng,
+                    data,
+                    &mut signature.data[..(signature.data_size as usize)],
+                )
+                .ok()?;
+        }
+        SigningKey::Ecdsa(key_pair) => {
+            if *key_asym_algo != base_asym_algo {
+                error!("!!! asym_sign : ECDSA curve mismatch !!!\n");
+                return None;
+            }
+            let ecdsa_hash_algo = if base_asym_algo == SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
+            {
+                SpdmBaseHashAlgo::TPM_ALG_SHA_256
+            } else {
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384
+            };
+            if base_hash_algo != ecdsa_hash_algo {
+                return None;
+            }
+            let sig = key_pair.sign(&rng, data).ok()?;
+            let sig = sig.as_ref();
+            if sig.len() != signature.data_size as usize {
+                return None;
+            }
+            signature.data[..sig.len()].copy_from_slice(sig);
+        }
+    }
+
+    Some(signature)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_asym_sign() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072;
+        let private_key_der =
+            &include_bytes!("../../../../test_key/Rsa3072/end_responder.key.der")[..];
+        let public_cert_der =
+            &include_bytes!("../../../../test_key/Rsa3072/bundle_responder.certchain.der")[..];
+
+        assert!(set_signing_key(SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048, private_key_der).is_err());
+        assert!(set_signing_key(base_asym_algo, &private_key_der[1..]).is_err());
+        assert!(set_signing_key(base_asym_algo, private_key_der).is_ok());
+        assert!(set_signing_key(base_asym_algo, private_key_der).is_err());
+
+        let data = &mut [0x10u8; 256];
+        let signature = asym_sign(base_hash_algo, base_asym_algo, data).unwrap();
+        assert_eq!(signature.data_size, 384);
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_ok());
+
+        let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072;
+        let signature = asym_sign(base_hash_algo, base_asym_algo, data).unwrap();
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_ok());
+
+        let signature = asym_sign(base_hash_algo, SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048, data);
+        assert!(signature.is_none());
+        let signature = asym_sign(
+            base_hash_algo,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
+            data,
+        );
+        assert!(signature.is_none());
+    }
+}
@@ -61,6 +61,14 @@ fn asym_verify(
         | (SpdmBaseHashAlgo::TPM_ALG_SHA_512, SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096) => {
             &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY
         }
+        // ring has neither P-521 nor ECDSA with SHA-512
+        (_, SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521)
+        | (SpdmBaseHashAlgo::TPM_ALG_SHA_512, SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256)
+        | (SpdmBaseHashAlgo::TPM_ALG_SHA_512, SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384) => {
+            return spdm_result_err!(EINVAL);
+        }
+        // PureEdDSA signs the message itself, whatever the negotiated hash
+        (_, SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519) => &webpki::ED25519,
         _ => {
             panic!();
         }
@@ -100,7 +108,7 @@ fn asym_verify(
                     }
                 }
                 _ => {
-                    // RSASSA or RSAPSS
+                    // RSASSA, RSAPSS or EdDSA
                     match cert.verify_signature(algorithm, data, signature.as_ref()) {
                         Ok(()) => Ok(()),
                         Err(_) => spdm_result_err!(EFAULT),
@@ -299,7 +307,6 @@ mod tests {
         }
     }
     #[test]
-    #[should_panic]
     fn test_case3_asym_verify() {
         let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
         let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
@@ -322,4 +329,42 @@ mod tests {
         );
         assert!(asym_verify.is_err());
     }
+    #[test]
+    fn test_case4_asym_verify() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519;
+        let public_cert_der =
+            &include_bytes!("../../../../test_key/ed25519/bundle_responder.certchain.der")[..];
+        let key_pair = ring::signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(
+            &include_bytes!("../../../../test_key/ed25519/end_responder.key.p8")[..],
+        )
+        .unwrap();
+        let data = &mut [0x10u8; 4096];
+
+        let mut signature = SpdmSignatureStruct {
+            data_size: base_asym_algo.get_size(),
+            data: [0x00u8; crate::protocol::SPDM_MAX_ASYM_KEY_SIZE],
+        };
+        signature.data[..(signature.data_size as usize)]
+            .copy_from_slice(key_pair.sign(data).as_ref());
+
+        assert!(asym_verify(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature
+        )
+        .is_ok());
+
+        data[0] = 0x20;
+        assert!(asym_verify(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature
+        )
+        .is_err());
+    }
 }
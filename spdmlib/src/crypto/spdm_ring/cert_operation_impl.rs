@@ -3,11 +3,16 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 extern crate alloc;
+use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::convert::TryFrom;
 
-use crate::crypto::SpdmCertOperation;
+use crate::common::cert_chain::{
+    EKU_CLIENT_AUTH, EKU_SERVER_AUTH, EKU_SPDM_REQUESTER_AUTH, EKU_SPDM_RESPONDER_AUTH,
+};
+use crate::common::x509::{spdm_get_cert_validity, spdm_parse_cert};
+use crate::crypto::{SpdmCertOperation, SpdmCertValidityTime};
 use crate::error::{spdm_result_err, SpdmResult};
 use ring::io::der;
 
@@ -42,10 +47,102 @@ fn get_cert_from_cert_chain(cert_chain: &[u8], index: isize) -> SpdmResult<(usiz
     }
 }
 
-fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
-    // TBD
-    static EKU_SPDM_RESPONDER_AUTH: &[u8] = &[40 + 3, 6, 1, 5, 5, 7, 3, 1];
+// usages webpki can be asked for, it takes 'static OIDs
+static EKU_KNOWN: &[&[u8]] = &[
+    EKU_SPDM_RESPONDER_AUTH,
+    EKU_SPDM_REQUESTER_AUTH,
+    EKU_SERVER_AUTH,
+    EKU_CLIENT_AUTH,
+    // id-kp-codeSigning, id-kp-emailProtection, id-kp-timeStamping, id-kp-OCSPSigning
+    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03],
+    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x04],
+    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x08],
+    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09],
+];
+
+// usages of leaves outside EKU_KNOWN, copied once to give webpki the
+// 'static OID it takes. Bounded, as the peer picks them.
+const EKU_OTHER_MAX: usize = 16;
+static EKU_OTHER: spin::Mutex<Vec<&'static [u8]>> = spin::Mutex::new(Vec::new());
+
+fn get_static_eku(eku: &[u8]) -> Option<&'static [u8]> {
+    let mut eku_other = EKU_OTHER.lock();
+    if let Some(static_eku) = eku_other
+        .iter()
+        .copied()
+        .find(|static_eku| *static_eku == eku)
+    {
+        return Some(static_eku);
+    }
+    if eku_other.len() >= EKU_OTHER_MAX {
+        error!("!!! too many vendor EKUs to verify against !!!\n");
+        return None;
+    }
+    let static_eku: &'static [u8] = Box::leak(eku.to_vec().into_boxed_slice());
+    eku_other.push(static_eku);
+    Some(static_eku)
+}
+
+// webpki always checks the usage it is given against the certificates
+// carrying the EKU extension. Not enforcing EKU asks for a usage the leaf
+// has, or any one when it has no EKU extension.
+fn get_leaf_ekus(leaf: &[u8]) -> SpdmResult<Vec<&'static [u8]>> {
+    match spdm_parse_cert(leaf)?.extended_key_usage {
+        None => Ok(vec![EKU_SPDM_RESPONDER_AUTH]),
+        Some(leaf_ekus) => {
+            let known_ekus: Vec<&'static [u8]> = EKU_KNOWN
+                .iter()
+                .copied()
+                .filter(|eku| leaf_ekus.contains(eku))
+                .collect();
+            if !known_ekus.is_empty() {
+                return Ok(known_ekus);
+            }
+            // vendor usages only
+            Ok(leaf_ekus
+                .iter()
+                .filter_map(|eku| get_static_eku(eku))
+                .collect())
+        }
+    }
+}
+
+#[cfg(any(target_os = "uefi", target_os = "none"))]
+fn get_platform_time() -> SpdmResult<u64> {
+    Ok(uefi_time::get_rtc_time() as u64)
+}
 
+#[cfg(not(any(target_os = "uefi", target_os = "none")))]
+fn get_platform_time() -> SpdmResult<u64> {
+    extern crate std;
+    if let Ok(ds) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(ds.as_secs())
+    } else {
+        spdm_result_err!(EDEV)
+    }
+}
+
+// webpki always checks validity periods, skipping them verifies at the
+// latest notBefore, where every certificate must still be valid
+fn get_common_validity_time(certs: &[&[u8]]) -> SpdmResult<u64> {
+    let mut time = 0u64;
+    let mut not_after_min = u64::MAX;
+    for cert in certs {
+        let (not_before, not_after) = spdm_get_cert_validity(cert)?;
+        time = time.max(not_before);
+        not_after_min = not_after_min.min(not_after);
+    }
+    if time > not_after_min {
+        return spdm_result_err!(ESEC);
+    }
+    Ok(time)
+}
+
+fn verify_cert_chain(
+    cert_chain: &[u8],
+    required_ekus: Option<&[&'static [u8]]>,
+    validity_time: SpdmCertValidityTime,
+) -> SpdmResult {
     static ALL_SIGALGS: &[&webpki::SignatureAlgorithm] = &[
         &webpki::RSA_PKCS1_2048_8192_SHA256,
         &webpki::RSA_PKCS1_2048_8192_SHA384,
@@ -54,6 +151,7 @@ fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
         &webpki::ECDSA_P256_SHA384,
         &webpki::ECDSA_P384_SHA256,
         &webpki::ECDSA_P384_SHA384,
+        &webpki::ED25519,
     ];
 
     let certs_der = untrusted::Input::from(cert_chain);
@@ -88,16 +186,10 @@ fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
         return spdm_result_err!(ESEC);
     };
 
-    #[cfg(any(target_os = "uefi", target_os = "none"))]
-    let timestamp = uefi_time::get_rtc_time() as u64;
-    #[cfg(not(any(target_os = "uefi", target_os = "none")))]
-    let timestamp = {
-        extern crate std;
-        if let Ok(ds) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
-            ds.as_secs()
-        } else {
-            return spdm_result_err!(EDEV);
-        }
+    let timestamp = match validity_time {
+        SpdmCertValidityTime::PlatformClock => get_platform_time()?,
+        SpdmCertValidityTime::At(timestamp) => timestamp,
+        SpdmCertValidityTime::Skip => get_common_validity_time(&certs)?,
     };
     let time = webpki::Time::from_seconds_since_unix_epoch(timestamp);
 
@@ -108,17 +200,18 @@ fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
     };
 
     // we cannot call verify_is_valid_tls_server_cert because it will check verify_cert::EKU_SERVER_AUTH.
-    if cert
-        .verify_cert_chain_with_eku(
-            EKU_SPDM_RESPONDER_AUTH,
-            ALL_SIGALGS,
-            &anchors,
-            inters,
-            time,
-            0,
-        )
-        .is_ok()
-    {
+    let leaf_ekus;
+    let required_ekus = match required_ekus {
+        Some(required_ekus) => required_ekus,
+        None => {
+            leaf_ekus = get_leaf_ekus(ee)?;
+            &leaf_ekus[..]
+        }
+    };
+    if required_ekus.iter().any(|eku| {
+        cert.verify_cert_chain_with_eku(*eku, ALL_SIGALGS, &anchors, inters, time, 0)
+            .is_ok()
+    }) {
         info!("Cert verification Pass\n");
         Ok(())
     } else {
@@ -169,7 +262,12 @@ mod tests {
         let status = get_cert_from_cert_chain(cert_chain, -1).is_ok();
         assert!(status);
 
-        let status = verify_cert_chain(cert_chain).is_ok();
+        let status = verify_cert_chain(
+            cert_chain,
+            Some(&[EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock,
+        )
+        .is_ok();
         assert!(status);
     }
 
@@ -178,18 +276,135 @@ mod tests {
     fn test_verify_cert_chain_case1() {
         let bundle_certs_der =
             &include_bytes!("../../../../test_key/crypto_chains/ca_selfsigned.crt.der")[..];
-        assert!(verify_cert_chain(bundle_certs_der).is_ok());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
 
         let bundle_certs_der =
             &include_bytes!("../../../../test_key/crypto_chains/bundle_two_level_cert.der")[..];
-        assert!(verify_cert_chain(bundle_certs_der).is_ok());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
 
         let bundle_certs_der =
             &include_bytes!("../../../../test_key/EcP384/bundle_requester.certchain.der")[..];
-        assert!(verify_cert_chain(bundle_certs_der).is_ok());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
 
         let bundle_certs_der =
             &include_bytes!("../../../../test_key/crypto_chains/bundle_cert.der")[..];
-        assert!(verify_cert_chain(bundle_certs_der).is_ok())
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok())
+    }
+
+    #[test]
+    fn test_verify_cert_chain_eku() {
+        let bundle_certs_der =
+            &include_bytes!("../../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_CLIENT_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SPDM_RESPONDER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SPDM_RESPONDER_AUTH, EKU_SERVER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_ok());
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+        assert!(
+            verify_cert_chain(bundle_certs_der, None, SpdmCertValidityTime::PlatformClock).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_get_leaf_ekus() {
+        let leaf = &include_bytes!("../../../../test_key/EcP384/end_responder.cert.der")[..];
+        assert_eq!(
+            get_leaf_ekus(leaf).unwrap(),
+            [
+                EKU_SERVER_AUTH,
+                EKU_CLIENT_AUTH,
+                &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09][..]
+            ]
+            .to_vec()
+        );
+        // a vendor usage only, the leaf's own
+        let leaf = &include_bytes!("../../../../test_key/EcP384/end_vendor_eku.cert.der")[..];
+        let vendor_ekus = get_leaf_ekus(leaf).unwrap();
+        assert_eq!(
+            vendor_ekus,
+            [&[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0x1c, 0x82, 0x12, 0x02][..]].to_vec()
+        );
+        assert_eq!(
+            get_leaf_ekus(leaf).unwrap()[0].as_ptr(),
+            vendor_ekus[0].as_ptr()
+        );
+        // without the extension any usage is allowed
+        let leaf = &include_bytes!("../../../../test_key/EcP384/ca.cert.der")[..];
+        assert_eq!(get_leaf_ekus(leaf).unwrap().len(), 1);
+        assert!(get_leaf_ekus(&leaf[..(leaf.len() - 1)]).is_err());
+    }
+
+    #[test]
+    fn test_verify_cert_chain_vendor_eku() {
+        let bundle_certs_der =
+            &include_bytes!("../../../../test_key/EcP384/bundle_vendor_eku.certchain.der")[..];
+        assert!(
+            verify_cert_chain(bundle_certs_der, None, SpdmCertValidityTime::PlatformClock).is_ok()
+        );
+        assert!(verify_cert_chain(
+            bundle_certs_der,
+            Some(&[EKU_SPDM_RESPONDER_AUTH]),
+            SpdmCertValidityTime::PlatformClock
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_cert_chain_validity_time() {
+        let bundle_certs_der =
+            &include_bytes!("../../../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        let required_ekus = Some(&[EKU_SERVER_AUTH][..]);
+        // the test chain is valid from 2022-01-05 06:08:34Z to 2032-01-03 06:08:33Z
+        for (validity_time, verified) in [
+            (SpdmCertValidityTime::At(1641362914), true),
+            (SpdmCertValidityTime::At(1956722913), true),
+            (SpdmCertValidityTime::At(1641362913), false),
+            (SpdmCertValidityTime::At(1956722915), false),
+            (SpdmCertValidityTime::Skip, true),
+        ] {
+            assert_eq!(
+                verify_cert_chain(bundle_certs_der, required_ekus, validity_time).is_ok(),
+                verified
+            );
+        }
     }
 }
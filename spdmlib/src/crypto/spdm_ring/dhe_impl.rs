@@ -5,7 +5,7 @@
 extern crate alloc;
 use alloc::boxed::Box;
 
-use crate::crypto::{SpdmDhe, SpdmDheKeyExchange};
+use crate::crypto::{SpdmDhe, SpdmDheKeyExchange, SpdmRng};
 use crate::protocol::{SpdmDheAlgo, SpdmDheExchangeStruct, SpdmDheFinalKeyStruct};
 use bytes::{BufMut, BytesMut};
 
@@ -15,18 +15,43 @@ pub static DEFAULT: SpdmDhe = SpdmDhe {
 
 fn generate_key_pair(
     dhe_algo: SpdmDheAlgo,
+    rng: &mut dyn SpdmRng,
 ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
     match dhe_algo {
-        SpdmDheAlgo::SECP_256_R1 => SpdmDheKeyExchangeP256::generate_key_pair(),
-        SpdmDheAlgo::SECP_384_R1 => SpdmDheKeyExchangeP384::generate_key_pair(),
+        SpdmDheAlgo::SECP_256_R1 => SpdmDheKeyExchangeP256::generate_key_pair(rng),
+        SpdmDheAlgo::SECP_384_R1 => SpdmDheKeyExchangeP384::generate_key_pair(rng),
         SpdmDheAlgo::SECP_521_R1 => None,
-        SpdmDheAlgo::FFDHE_2048 => None,
-        SpdmDheAlgo::FFDHE_3072 => None,
-        SpdmDheAlgo::FFDHE_4096 => None,
+        // ring has no public bignum, FFDHE needs the RustCrypto or mbedtls provider
         _ => None,
     }
 }
 
+// Draws of a private scalar out of [1, n) before giving up on the rng.
+const PRIVATE_KEY_ATTEMPTS: usize = 8;
+
+// ring only takes its own SecureRandom, which cannot be implemented outside of
+// ring, so the private scalar is drawn from rng and handed over as a fixed slice.
+fn generate_private_key(
+    alg: &'static ring::agreement::Algorithm,
+    rng: &mut dyn SpdmRng,
+    scalar: &mut [u8],
+) -> Option<ring::agreement::EphemeralPrivateKey> {
+    let mut private_key = None;
+    for _ in 0..PRIVATE_KEY_ATTEMPTS {
+        match rng.get_random(scalar) {
+            Ok(used) if used == scalar.len() => {}
+            _ => break,
+        }
+        let fixed_rng = ring::test::rand::FixedSliceRandom { bytes: scalar };
+        private_key = ring::agreement::EphemeralPrivateKey::generate(alg, &fixed_rng).ok();
+        if private_key.is_some() {
+            break;
+        }
+    }
+    scalar.iter_mut().for_each(|byte| *byte = 0);
+    private_key
+}
+
 struct SpdmDheKeyExchangeP256(ring::agreement::EphemeralPrivateKey);
 
 impl SpdmDheKeyExchange for SpdmDheKeyExchangeP256 {
@@ -57,11 +82,10 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP256 {
 }
 
 impl SpdmDheKeyExchangeP256 {
-    fn generate_key_pair() -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
-        let rng = ring::rand::SystemRandom::new();
-        let private_key =
-            ring::agreement::EphemeralPrivateKey::generate(&ring::agreement::ECDH_P256, &rng)
-                .ok()?;
+    fn generate_key_pair(
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let private_key = generate_private_key(&ring::agreement::ECDH_P256, rng, &mut [0u8; 32])?;
         let public_key_old = private_key.compute_public_key().ok()?;
         let public_key = BytesMut::from(&public_key_old.as_ref()[1..]);
 
@@ -101,11 +125,10 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP384 {
 }
 
 impl SpdmDheKeyExchangeP384 {
-    fn generate_key_pair() -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
-        let rng = ring::rand::SystemRandom::new();
-        let private_key =
-            ring::agreement::EphemeralPrivateKey::generate(&ring::agreement::ECDH_P384, &rng)
-                .ok()?;
+    fn generate_key_pair(
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let private_key = generate_private_key(&ring::agreement::ECDH_P384, rng, &mut [0u8; 48])?;
         let public_key_old = private_key.compute_public_key().ok()?;
         let public_key = BytesMut::from(&public_key_old.as_ref()[1..]);
 
@@ -118,12 +141,15 @@ impl SpdmDheKeyExchangeP384 {
 #[cfg(all(test,))]
 mod tests {
     use super::*;
+    use crate::error::{spdm_result_err, SpdmResult};
 
     #[test]
     fn test_case0_dhe() {
         for dhe_algo in [SpdmDheAlgo::SECP_256_R1, SpdmDheAlgo::SECP_384_R1].iter() {
-            let (exchange1, private1) = generate_key_pair(*dhe_algo).unwrap();
-            let (exchange2, private2) = generate_key_pair(*dhe_algo).unwrap();
+            let (exchange1, private1) =
+                generate_key_pair(*dhe_algo, &mut crate::crypto::rand::SpdmCryptoRng).unwrap();
+            let (exchange2, private2) =
+                generate_key_pair(*dhe_algo, &mut crate::crypto::rand::SpdmCryptoRng).unwrap();
 
             let peer1 = private1.compute_final_key(&exchange2).unwrap();
             let peer2 = private2.compute_final_key(&exchange1).unwrap();
@@ -142,7 +168,25 @@ mod tests {
         ]
         .iter()
         {
-            assert_eq!(generate_key_pair(*dhe_algo).is_none(), true);
+            assert_eq!(
+                generate_key_pair(*dhe_algo, &mut crate::crypto::rand::SpdmCryptoRng).is_none(),
+                true
+            );
+        }
+    }
+
+    struct FailingRng;
+
+    impl SpdmRng for FailingRng {
+        fn get_random(&mut self, _data: &mut [u8]) -> SpdmResult<usize> {
+            spdm_result_err!(ESEC)
+        }
+    }
+
+    #[test]
+    fn test_case2_dhe() {
+        for dhe_algo in [SpdmDheAlgo::SECP_256_R1, SpdmDheAlgo::SECP_384_R1].iter() {
+            assert!(generate_key_pair(*dhe_algo, &mut FailingRng).is_none());
         }
     }
 }
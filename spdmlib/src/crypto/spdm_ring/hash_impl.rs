@@ -2,10 +2,29 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::crypto::SpdmHash;
+extern crate alloc;
+use alloc::boxed::Box;
+
+use crate::crypto::{HashCtx, SpdmHash, SpdmHashContext};
 use crate::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
 
-pub type HashCtx = ring::digest::Context;
+#[derive(Clone)]
+struct RingHashCtx(ring::digest::Context);
+
+impl SpdmHashContext for RingHashCtx {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+
+    fn finalize(self: Box<Self>) -> Option<SpdmDigestStruct> {
+        let digest_value = self.0.finish();
+        Some(SpdmDigestStruct::from(digest_value.as_ref()))
+    }
+
+    fn box_clone(&self) -> Box<dyn SpdmHashContext> {
+        Box::new(self.clone())
+    }
+}
 
 pub static DEFAULT: SpdmHash = SpdmHash {
     hash_all_cb: hash_all,
@@ -32,7 +51,7 @@ fn hash_ctx_init(base_hash_algo: SpdmBaseHashAlgo) -> Option<HashCtx> {
         SpdmBaseHashAlgo::TPM_ALG_SHA_512 => &ring::digest::SHA512,
         _ => return None,
     };
-    Some(HashCtx::new(algorithm))
+    Some(Box::new(RingHashCtx(ring::digest::Context::new(algorithm))))
 }
 
 fn hash_ctx_update(ctx: &mut HashCtx, data: &[u8]) {
@@ -40,8 +59,7 @@ fn hash_ctx_update(ctx: &mut HashCtx, data: &[u8]) {
 }
 
 fn hash_ctx_finalize(ctx: HashCtx) -> Option<SpdmDigestStruct> {
-    let digest_value = ctx.finish();
-    Some(SpdmDigestStruct::from(digest_value.as_ref()))
+    ctx.finalize()
 }
 
 #[cfg(all(test,))]
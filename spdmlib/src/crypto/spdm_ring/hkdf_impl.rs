@@ -6,9 +6,24 @@ use crate::crypto::SpdmHkdf;
 use crate::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
 
 pub static DEFAULT: SpdmHkdf = SpdmHkdf {
+    hkdf_extract_cb: hkdf_extract,
     hkdf_expand_cb: hkdf_expand,
 };
 
+fn hkdf_extract(hash_algo: SpdmBaseHashAlgo, salt: &[u8], ikm: &[u8]) -> Option<SpdmDigestStruct> {
+    let algo = match hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => ring::hmac::HMAC_SHA256,
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => ring::hmac::HMAC_SHA384,
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => ring::hmac::HMAC_SHA512,
+        _ => return None,
+    };
+
+    // ring::hkdf::Prk does not expose its bytes, PRK = HMAC-Hash(salt, IKM)
+    let key = ring::hmac::Key::new(algo, salt);
+    let tag = ring::hmac::sign(&key, ikm);
+    Some(SpdmDigestStruct::from(tag.as_ref()))
+}
+
 fn hkdf_expand(
     hash_algo: SpdmBaseHashAlgo,
     pk: &[u8],
@@ -63,6 +78,51 @@ impl ring::hkdf::KeyType for SpdmCryptoHkdfKeyLen {
 mod tests {
     use super::*;
 
+    // RFC 5869 test case 1
+    const TC1_SALT: [u8; 13] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+    ];
+    const TC1_INFO: [u8; 10] = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+    const TC1_PRK: [u8; 32] = [
+        0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b, 0xba,
+        0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a, 0xd7, 0xc2,
+        0xb3, 0xe5,
+    ];
+    const TC1_OKM: [u8; 42] = [
+        0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36, 0x2f,
+        0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4,
+        0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+    ];
+    // RFC 5869 test case 3, empty salt and info
+    const TC3_PRK: [u8; 32] = [
+        0x19, 0xef, 0x24, 0xa3, 0x2c, 0x71, 0x7b, 0x16, 0x7f, 0x33, 0xa9, 0x1d, 0x6f, 0x64, 0x8b,
+        0xdf, 0x96, 0x59, 0x67, 0x76, 0xaf, 0xdb, 0x63, 0x77, 0xac, 0x43, 0x4c, 0x1c, 0x29, 0x3c,
+        0xcb, 0x04,
+    ];
+    const TC3_OKM: [u8; 42] = [
+        0x8d, 0xa4, 0xe7, 0x75, 0xa5, 0x63, 0xc1, 0x8f, 0x71, 0x5f, 0x80, 0x2a, 0x06, 0x3c, 0x5a,
+        0x31, 0xb8, 0xa1, 0x1f, 0x5c, 0x5e, 0xe1, 0x87, 0x9e, 0xc3, 0x45, 0x4e, 0x5f, 0x3c, 0x73,
+        0x8d, 0x2d, 0x9d, 0x20, 0x13, 0x95, 0xfa, 0xa4, 0xb6, 0x1a, 0x96, 0xc8,
+    ];
+
+    #[test]
+    fn test_case0_hkdf_extract() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        let ikm = [0x0bu8; 22];
+
+        let prk = hkdf_extract(base_hash_algo, &TC1_SALT, &ikm).unwrap();
+        assert_eq!(prk.as_ref(), &TC1_PRK[..]);
+        let okm = hkdf_expand(base_hash_algo, prk.as_ref(), &TC1_INFO, 42).unwrap();
+        assert_eq!(okm.as_ref(), &TC1_OKM[..]);
+
+        let prk = hkdf_extract(base_hash_algo, &[], &ikm).unwrap();
+        assert_eq!(prk.as_ref(), &TC3_PRK[..]);
+        let okm = hkdf_expand(base_hash_algo, prk.as_ref(), &[], 42).unwrap();
+        assert_eq!(okm.as_ref(), &TC3_OKM[..]);
+
+        assert!(hkdf_extract(SpdmBaseHashAlgo::empty(), &TC1_SALT, &ikm).is_none());
+    }
+
     #[test]
     fn test_case0_hkdf_expand() {
         let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
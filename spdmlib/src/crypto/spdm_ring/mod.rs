@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 pub mod aead_impl;
+pub mod asym_sign_impl;
 pub mod asym_verify_impl;
 pub mod cert_operation_impl;
 pub mod dhe_impl;
@@ -4,18 +4,21 @@
 
 use core::fmt::{Debug, Formatter, Result};
 
+use crate::message::SpdmErrorCode;
+
 /// POSIX errno + custom errno(bigger than 0xFFFF)
 /// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/errno.h
 /// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/errno-base.h
 #[repr(u32)]
 #[allow(dead_code)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SpdmErrorNum {
     EUNDEF = 0,
     EPERM = 1,
     ENOENT = 2,
     EIO = 5,
     E2BIG = 7,
+    EAGAIN = 11,
     ENOMEM = 12,
     EFAULT = 14,
     EBUSY = 16,
@@ -24,16 +27,51 @@ pub enum SpdmErrorNum {
     EINVAL = 22,
     ERANGE = 34,
     ENOSYS = 38,
+    ECANCELED = 125,
     ESEC = 0xFFFF + 1, //Security violation was observed
     EDEV = 0xFFFF + 2, //Device error
 }
 
-pub struct SpdmError {
-    pub num: SpdmErrorNum,
-    pub loc_file: &'static str,
-    pub loc_line: u32,
-    pub loc_col: u32,
-    pub msg: &'static str,
+/// Where in the source an SpdmError was raised.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SpdmErrorLocation {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Debug for SpdmErrorLocation {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[{}:{}:{}]", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum SpdmError {
+    /// The peer answered ERROR(code) with ErrorData data. The requester
+    /// keeps any ExtendedErrorData in RequesterContext::last_error.
+    PeerError { code: SpdmErrorCode, data: u8 },
+    /// A signature, HMAC, certificate chain or digest of the peer does not
+    /// verify.
+    VerificationFailed(SpdmErrorLocation),
+    /// The nonce of a signed response is not fresh: it echoes the nonce of
+    /// the requester's own last request, or repeats the nonce of the
+    /// previous response verified.
+    StaleNonce(SpdmErrorLocation),
+    /// No response in time, retransmissions included.
+    Timeout,
+    /// A message does not decode, or is not the one expected.
+    Codec(SpdmErrorLocation),
+    /// The device IO or the transport encap failed.
+    Transport(SpdmErrorLocation),
+    /// A crypto primitive failed or is not registered.
+    Crypto(SpdmErrorLocation),
+    /// Any other failure, see spdm_err!.
+    Errno {
+        num: SpdmErrorNum,
+        location: SpdmErrorLocation,
+        msg: &'static str,
+    },
 }
 
 pub type SpdmResult<T = ()> = core::result::Result<T, SpdmError>;
@@ -47,6 +85,7 @@ impl SpdmErrorNum {
             ENOENT => "No such file or directory",
             EIO => "I/O error",
             E2BIG => "Argument list too long",
+            EAGAIN => "Try again",
             ENOMEM => "Out of memory",
             EFAULT => "Bad address",
             EBUSY => "Device or resource busy",
@@ -55,6 +94,7 @@ impl SpdmErrorNum {
             EINVAL => "Invalid argument",
             ERANGE => "Math result not representable",
             ENOSYS => "Function not implemented",
+            ECANCELED => "Operation canceled",
             ESEC => "Security violation",
             EDEV => "Device error",
         }
@@ -69,37 +109,101 @@ impl SpdmError {
         loc_col: u32,
         msg: &'static str,
     ) -> Self {
-        Self {
+        SpdmError::Errno {
             num,
-            loc_file,
-            loc_line,
-            loc_col,
+            location: SpdmErrorLocation {
+                file: loc_file,
+                line: loc_line,
+                column: loc_col,
+            },
             msg,
         }
     }
 
+    /// The errno the error used to be reported as.
+    pub fn num(&self) -> SpdmErrorNum {
+        match self {
+            SpdmError::PeerError { code, .. } => match code {
+                SpdmErrorCode::SpdmErrorBusy => SpdmErrorNum::EBUSY,
+                SpdmErrorCode::SpdmErrorSessionLimitExceeded => SpdmErrorNum::ENOMEM,
+                _ => SpdmErrorNum::EDEV,
+            },
+            SpdmError::VerificationFailed(_) | SpdmError::StaleNonce(_) => SpdmErrorNum::ESEC,
+            SpdmError::Timeout | SpdmError::Codec(_) | SpdmError::Transport(_) => SpdmErrorNum::EIO,
+            SpdmError::Crypto(_) => SpdmErrorNum::EFAULT,
+            SpdmError::Errno { num, .. } => *num,
+        }
+    }
+
     pub fn code(&self) -> i32 {
-        -(self.num.clone() as u32 as i32)
+        -(self.num() as u32 as i32)
+    }
+
+    pub fn location(&self) -> Option<SpdmErrorLocation> {
+        match self {
+            SpdmError::PeerError { .. } | SpdmError::Timeout => None,
+            SpdmError::VerificationFailed(location)
+            | SpdmError::StaleNonce(location)
+            | SpdmError::Codec(location)
+            | SpdmError::Transport(location)
+            | SpdmError::Crypto(location)
+            | SpdmError::Errno { location, .. } => Some(*location),
+        }
     }
 }
 
 impl Debug for SpdmError {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(
-            f,
-            "[{}:{}:{}] {}: {}",
-            self.loc_file,
-            self.loc_line,
-            self.loc_col,
-            self.num.as_str(),
-            self.msg
-        )?;
-        Ok(())
+        match self {
+            SpdmError::PeerError { code, data } => {
+                write!(f, "Peer error: {:?}, data {:#x}", code, data)
+            }
+            SpdmError::VerificationFailed(location) => {
+                write!(f, "{:?} Verification failed", location)
+            }
+            SpdmError::StaleNonce(location) => write!(f, "{:?} Stale nonce", location),
+            SpdmError::Timeout => write!(f, "Timeout"),
+            SpdmError::Codec(location) => write!(f, "{:?} Malformed message", location),
+            SpdmError::Transport(location) => write!(f, "{:?} Transport error", location),
+            SpdmError::Crypto(location) => write!(f, "{:?} Crypto error", location),
+            SpdmError::Errno { num, location, msg } => {
+                write!(f, "{:?} {}: {}", location, num.as_str(), msg)
+            }
+        }
     }
 }
 
+/// spdm_err!(EINVAL) or spdm_err!(EINVAL, "msg") for an errno, and
+/// spdm_err!(VerificationFailed), spdm_err!(StaleNonce), spdm_err!(Timeout),
+/// spdm_err!(Codec), spdm_err!(Transport) or spdm_err!(Crypto) for those
+/// SpdmError variants.
 #[macro_export]
 macro_rules! spdm_err {
+    (@location) => {
+        $crate::error::SpdmErrorLocation {
+            file: file!(),
+            line: line!(),
+            column: column!(),
+        }
+    };
+    (VerificationFailed) => {
+        $crate::error::SpdmError::VerificationFailed($crate::spdm_err!(@location))
+    };
+    (StaleNonce) => {
+        $crate::error::SpdmError::StaleNonce($crate::spdm_err!(@location))
+    };
+    (Timeout) => {
+        $crate::error::SpdmError::Timeout
+    };
+    (Codec) => {
+        $crate::error::SpdmError::Codec($crate::spdm_err!(@location))
+    };
+    (Transport) => {
+        $crate::error::SpdmError::Transport($crate::spdm_err!(@location))
+    };
+    (Crypto) => {
+        $crate::error::SpdmError::Crypto($crate::spdm_err!(@location))
+    };
     ($num: ident) => {{
         use $crate::error::{SpdmError, SpdmErrorNum::*};
         SpdmError::new($num, file!(), line!(), column!(), "")
@@ -122,3 +226,38 @@ macro_rules! spdm_result_err {
     };
 }
 pub use spdm_result_err;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_spdm_error_num() {
+        let error = spdm_err!(EINVAL);
+        assert_eq!(error.num(), SpdmErrorNum::EINVAL);
+        assert_eq!(error.code(), -22);
+        assert_eq!(error.location().unwrap().file, file!());
+
+        let error = SpdmError::PeerError {
+            code: SpdmErrorCode::SpdmErrorBusy,
+            data: 0,
+        };
+        assert_eq!(error.num(), SpdmErrorNum::EBUSY);
+        assert!(error.location().is_none());
+        let error = SpdmError::PeerError {
+            code: SpdmErrorCode::SpdmErrorUnsupportedRequest,
+            data: 0x81,
+        };
+        assert_eq!(error.num(), SpdmErrorNum::EDEV);
+
+        let result: SpdmResult = spdm_result_err!(VerificationFailed);
+        assert!(matches!(result, Err(SpdmError::VerificationFailed(_))));
+        assert_eq!(result.unwrap_err().num(), SpdmErrorNum::ESEC);
+        assert!(matches!(spdm_err!(StaleNonce), SpdmError::StaleNonce(_)));
+        assert_eq!(spdm_err!(StaleNonce).num(), SpdmErrorNum::ESEC);
+        assert_eq!(spdm_err!(Timeout), SpdmError::Timeout);
+        assert_eq!(spdm_err!(Codec).num(), SpdmErrorNum::EIO);
+        assert_eq!(spdm_err!(Crypto).num(), SpdmErrorNum::EFAULT);
+        assert!(matches!(spdm_err!(Transport), SpdmError::Transport(_)));
+    }
+}
@@ -19,13 +19,18 @@ pub mod protocol;
 pub mod error;
 pub mod common;
 pub mod crypto;
+pub mod measurement;
 pub mod message;
 pub mod requester;
 pub mod responder;
 pub mod secret;
 pub mod time;
+pub mod verifier;
 
 pub mod config;
 
+#[cfg(all(any(test, feature = "testutil"), not(feature = "downcast")))]
+pub mod testutil;
+
 #[cfg(test)]
 mod testlib;
@@ -0,0 +1,229 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Measurement blocks from raw firmware regions, for spdm_measurement_collection_cb
+//! implementations.
+
+use crate::config;
+use crate::crypto;
+use crate::message::SpdmMeasurementOperation;
+use crate::protocol::{
+    SpdmBaseHashAlgo, SpdmDmtfMeasurementRepresentation, SpdmDmtfMeasurementStructure,
+    SpdmDmtfMeasurementType, SpdmMeasurementBlockStructure, SpdmMeasurementRecordStructure,
+    SpdmMeasurementSpecification,
+};
+
+/// What a firmware region holds, it selects the DMTF measurement type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmFirmwareRegionKind {
+    ImmutableRom,
+    MutableFirmware,
+    HardwareConfig,
+    FirmwareConfig,
+    Manifest,
+}
+
+impl From<SpdmFirmwareRegionKind> for SpdmDmtfMeasurementType {
+    fn from(kind: SpdmFirmwareRegionKind) -> Self {
+        match kind {
+            SpdmFirmwareRegionKind::ImmutableRom => SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom,
+            SpdmFirmwareRegionKind::MutableFirmware => {
+                SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware
+            }
+            SpdmFirmwareRegionKind::HardwareConfig => {
+                SpdmDmtfMeasurementType::SpdmDmtfMeasurementHardwareConfig
+            }
+            SpdmFirmwareRegionKind::FirmwareConfig => {
+                SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmwareConfig
+            }
+            SpdmFirmwareRegionKind::Manifest => {
+                SpdmDmtfMeasurementType::SpdmDmtfMeasurementManifest
+            }
+        }
+    }
+}
+
+/// One measurement: the digest of the concatenation of data.
+#[derive(Debug, Clone, Copy)]
+pub struct SpdmFirmwareRegion<'a> {
+    pub kind: SpdmFirmwareRegionKind,
+    /// The region is part of the TCB.
+    pub tcb: bool,
+    pub data: &'a [&'a [u8]],
+}
+
+/// Digest measurement of the concatenation of regions, hashed with the
+/// measurement hash algorithm passed to spdm_measurement_collection_cb.
+pub fn spdm_hash_firmware_region(
+    kind: SpdmFirmwareRegionKind,
+    regions: &[&[u8]],
+    measurement_hash_algo: SpdmBaseHashAlgo,
+) -> Option<SpdmDmtfMeasurementStructure> {
+    let mut ctx = crypto::hash::hash_ctx_init(measurement_hash_algo)?;
+    for region in regions {
+        crypto::hash::hash_ctx_update(&mut ctx, region);
+    }
+    let digest = crypto::hash::hash_ctx_finalize(ctx)?;
+
+    let mut value = [0u8; config::MAX_SPDM_MEASUREMENT_VALUE_LEN];
+    value[..digest.data_size as usize].copy_from_slice(digest.as_ref());
+    Some(SpdmDmtfMeasurementStructure {
+        r#type: kind.into(),
+        representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementDigest,
+        value_size: digest.data_size,
+        value,
+    })
+}
+
+/// DMTF measurement block `index` (1 based) carrying measurement.
+pub fn spdm_measurement_block(
+    index: u8,
+    measurement: SpdmDmtfMeasurementStructure,
+) -> SpdmMeasurementBlockStructure {
+    SpdmMeasurementBlockStructure {
+        index,
+        measurement_specification: SpdmMeasurementSpecification::DMTF,
+        // DMTFSpecMeasurementValueType(1) + DMTFSpecMeasurementValueSize(2)
+        measurement_size: measurement.value_size + 3,
+        measurement,
+        tcb: false,
+    }
+}
+
+/// Answer to spdm_measurement_collection_cb for a device measuring
+/// firmware_regions, block N being firmware_regions[N - 1].
+///
+/// Handles the QueryTotalNumber, RequestAll and single index operations,
+/// None for any other measurement_index.
+pub fn spdm_build_measurement_record(
+    firmware_regions: &[SpdmFirmwareRegion],
+    measurement_hash_algo: SpdmBaseHashAlgo,
+    measurement_index: usize,
+) -> Option<SpdmMeasurementRecordStructure> {
+    if firmware_regions.len() > config::MAX_SPDM_MEASUREMENT_BLOCK_COUNT {
+        return None;
+    }
+    let mut measurement_record = SpdmMeasurementRecordStructure::default();
+
+    let indexes = if measurement_index
+        == SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber.get_u8() as usize
+    {
+        measurement_record.number_of_blocks = firmware_regions.len() as u8;
+        return Some(measurement_record);
+    } else if measurement_index
+        == SpdmMeasurementOperation::SpdmMeasurementRequestAll.get_u8() as usize
+    {
+        1..=firmware_regions.len()
+    } else if measurement_index <= firmware_regions.len() {
+        measurement_index..=measurement_index
+    } else {
+        return None;
+    };
+
+    for index in indexes {
+        let region = &firmware_regions[index - 1];
+        let measurement =
+            spdm_hash_firmware_region(region.kind, region.data, measurement_hash_algo)?;
+        let mut block = spdm_measurement_block(index as u8, measurement);
+        block.tcb = region.tcb;
+        measurement_record.record[measurement_record.number_of_blocks as usize] = block;
+        measurement_record.number_of_blocks += 1;
+    }
+    Some(measurement_record)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::protocol::SpdmMeasurementSummaryHashType;
+
+    #[test]
+    fn test_case0_spdm_hash_firmware_region() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let measurement = spdm_hash_firmware_region(
+            SpdmFirmwareRegionKind::ImmutableRom,
+            &[&b"dead"[..], &b"beef"[..]],
+            base_hash_algo,
+        )
+        .unwrap();
+        let digest = crypto::hash::hash_all(base_hash_algo, b"deadbeef").unwrap();
+        assert_eq!(
+            measurement.r#type,
+            SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom
+        );
+        assert!(!measurement.is_raw_bit_stream());
+        assert_eq!(
+            &measurement.value[..measurement.value_size as usize],
+            digest.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_case1_spdm_build_measurement_record() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        let firmware_regions = [
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::ImmutableRom,
+                tcb: true,
+                data: &[&b"rom"[..]],
+            },
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::MutableFirmware,
+                tcb: true,
+                data: &[&b"firmware"[..], &b"patch"[..]],
+            },
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::FirmwareConfig,
+                tcb: false,
+                data: &[&b"config"[..]],
+            },
+        ];
+
+        let record = spdm_build_measurement_record(&firmware_regions, base_hash_algo, 0).unwrap();
+        assert_eq!(record.number_of_blocks, 3);
+
+        let record =
+            spdm_build_measurement_record(&firmware_regions, base_hash_algo, 0xff).unwrap();
+        assert_eq!(record.number_of_blocks, 3);
+        for (i, block) in record.record.iter().take(3).enumerate() {
+            assert_eq!(block.index as usize, i + 1);
+            assert_eq!(block.measurement_size, 32 + 3);
+            assert_eq!(
+                block.measurement.r#type,
+                SpdmDmtfMeasurementType::from(firmware_regions[i].kind)
+            );
+            assert_eq!(block.tcb, firmware_regions[i].tcb);
+        }
+
+        // the TCB summary hash leaves out the config block, not in the TCB
+        let tcb = crate::common::spdm_calc_measurement_summary_hash(
+            &record,
+            base_hash_algo,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb,
+        )
+        .unwrap();
+        let mut tcb_record = record.clone();
+        tcb_record.number_of_blocks = 2;
+        let expected = crate::common::spdm_calc_measurement_summary_hash(
+            &tcb_record,
+            base_hash_algo,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
+        )
+        .unwrap();
+        assert_eq!(tcb.as_ref(), expected.as_ref());
+
+        let record = spdm_build_measurement_record(&firmware_regions, base_hash_algo, 2).unwrap();
+        assert_eq!(record.number_of_blocks, 1);
+        assert_eq!(record.record[0].index, 2);
+        assert_eq!(
+            &record.record[0].measurement.value[..32],
+            crypto::hash::hash_all(base_hash_algo, b"firmwarepatch")
+                .unwrap()
+                .as_ref()
+        );
+
+        assert!(spdm_build_measurement_record(&firmware_regions, base_hash_algo, 4).is_none());
+        assert!(spdm_build_measurement_record(&firmware_regions, base_hash_algo, 0xfd).is_none());
+    }
+}
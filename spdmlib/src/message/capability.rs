@@ -26,7 +26,7 @@ impl SpdmCodec for SpdmGetCapabilitiesRequestPayload {
         0u16.encode(bytes); // reserved2
         self.flags.encode(bytes);
 
-        if context.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
             self.data_transfer_size.encode(bytes);
             self.max_spdm_msg_size.encode(bytes);
         }
@@ -44,11 +44,18 @@ impl SpdmCodec for SpdmGetCapabilitiesRequestPayload {
         u16::read(r)?; // reserved2
         let flags = SpdmRequestCapabilityFlags::read(r)?;
 
-        if context.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
             let data_transfer_size = u32::read(r)?;
             let max_spdm_msg_size = u32::read(r)?;
-            if data_transfer_size < 42 || max_spdm_msg_size < 42 {
-                log::error!("responder: data_transfer_size or max_spdm_msg_size < 42");
+            if data_transfer_size < 42 || max_spdm_msg_size < data_transfer_size {
+                log::error!("responder: invalid data_transfer_size or max_spdm_msg_size");
+                return None;
+            }
+            // without chunking a message can never exceed a single transfer
+            if !flags.contains(SpdmRequestCapabilityFlags::CHUNK_CAP)
+                && data_transfer_size != max_spdm_msg_size
+            {
+                log::error!("responder: max_spdm_msg_size != data_transfer_size without CHUNK_CAP");
                 return None;
             }
             Some(SpdmGetCapabilitiesRequestPayload {
@@ -86,7 +93,7 @@ impl SpdmCodec for SpdmCapabilitiesResponsePayload {
         0u16.encode(bytes); // reserved2
         self.flags.encode(bytes);
 
-        if context.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
             self.data_transfer_size.encode(bytes);
             self.max_spdm_msg_size.encode(bytes);
         }
@@ -104,11 +111,18 @@ impl SpdmCodec for SpdmCapabilitiesResponsePayload {
         u16::read(r)?; // reserved2
         let flags = SpdmResponseCapabilityFlags::read(r)?;
 
-        if context.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
             let data_transfer_size = u32::read(r)?;
             let max_spdm_msg_size = u32::read(r)?;
-            if data_transfer_size < 42 || max_spdm_msg_size < 42 {
-                panic!("requester: data_transfer_size or max_spdm_msg_size < 42");
+            if data_transfer_size < 42 || max_spdm_msg_size < data_transfer_size {
+                log::error!("requester: invalid data_transfer_size or max_spdm_msg_size");
+                return None;
+            }
+            if !flags.contains(SpdmResponseCapabilityFlags::CHUNK_CAP)
+                && data_transfer_size != max_spdm_msg_size
+            {
+                log::error!("requester: max_spdm_msg_size != data_transfer_size without CHUNK_CAP");
+                return None;
             }
             Some(SpdmCapabilitiesResponsePayload {
                 ct_exponent,
@@ -70,7 +70,20 @@ impl SpdmCodec for SpdmChallengeAuthResponsePayload {
             self.measurement_summary_hash.spdm_encode(context, bytes);
         }
         self.opaque.spdm_encode(context, bytes);
-        self.signature.spdm_encode(context, bytes);
+        if context.runtime_info.req_challenge_auth {
+            // the requester signs with the negotiated ReqBaseAsymAlg, a
+            // signature of another size is cut or padded to it
+            for d in self
+                .signature
+                .data
+                .iter()
+                .take(context.get_req_asym_key_size() as usize)
+            {
+                d.encode(bytes);
+            }
+        } else {
+            self.signature.spdm_encode(context, bytes);
+        }
     }
 
     fn spdm_read(
@@ -89,7 +102,16 @@ impl SpdmCodec for SpdmChallengeAuthResponsePayload {
             SpdmDigestStruct::default()
         };
         let opaque = SpdmOpaqueStruct::spdm_read(context, r)?;
-        let signature = SpdmSignatureStruct::spdm_read(context, r)?;
+        let signature = if context.runtime_info.req_challenge_auth {
+            let mut signature = SpdmSignatureStruct::default();
+            signature.data_size = context.get_req_asym_key_size();
+            for d in signature.data.iter_mut().take(signature.data_size as usize) {
+                *d = u8::read(r)?;
+            }
+            signature
+        } else {
+            SpdmSignatureStruct::spdm_read(context, r)?
+        };
         Some(SpdmChallengeAuthResponsePayload {
             slot_id,
             slot_mask,
@@ -258,4 +280,44 @@ mod tests {
             assert_eq!(spdm_read_data.measurement_summary_hash.data[i], 0);
         }
     }
+    #[test]
+    fn test_case2_spdm_challenge_auth_response_payload() {
+        let u8_slice = &mut [0u8; 800];
+        let mut writer = Writer::init(u8_slice);
+        // an RSA 4096 signature with ECDSA P-384 negotiated for the requester
+        let value = SpdmChallengeAuthResponsePayload {
+            slot_id: 0,
+            slot_mask: 1,
+            challenge_auth_attribute: SpdmChallengeAuthAttribute::empty(),
+            cert_chain_hash: SpdmDigestStruct {
+                data_size: 48,
+                data: Box::new([0xAAu8; SPDM_MAX_HASH_SIZE]),
+            },
+            nonce: SpdmNonceStruct {
+                data: [100u8; SPDM_NONCE_SIZE],
+            },
+            measurement_summary_hash: SpdmDigestStruct::default(),
+            opaque: SpdmOpaqueStruct::default(),
+            signature: SpdmSignatureStruct {
+                data_size: 512,
+                data: [0x55u8; SPDM_MAX_ASYM_KEY_SIZE],
+            },
+        };
+
+        create_spdm_context!(context);
+
+        context.runtime_info.req_challenge_auth = true;
+        context.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+
+        value.spdm_encode(&mut context, &mut writer);
+        let used = writer.used();
+        assert_eq!(used, 2 + 48 + SPDM_NONCE_SIZE + 2 + 96);
+
+        let mut reader = Reader::init(&u8_slice[..used]);
+        let spdm_read_data =
+            SpdmChallengeAuthResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_read_data.signature.data_size, 96);
+        assert_eq!(reader.left(), 0);
+    }
 }
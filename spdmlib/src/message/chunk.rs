@@ -0,0 +1,366 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use crate::config;
+use codec::{Codec, Reader, Writer};
+
+// SPDM header, Param1, Param2, ChunkSeqNo, Reserved and ChunkSize of
+// CHUNK_SEND and CHUNK_RESPONSE, the first chunk adds LargeMessageSize.
+pub const SPDM_CHUNK_HEADER_SIZE: usize = 12;
+pub const SPDM_FIRST_CHUNK_HEADER_SIZE: usize = SPDM_CHUNK_HEADER_SIZE + 4;
+// SPDM header, Param1, Param2 and ChunkSeqNo of CHUNK_SEND_ACK.
+pub const SPDM_CHUNK_SEND_ACK_HEADER_SIZE: usize = 6;
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmChunkAttributes: u8 {
+        const LAST_CHUNK = 0b0000_0001;
+    }
+}
+
+impl Codec for SpdmChunkAttributes {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmChunkAttributes> {
+        let bits = u8::read(r)?;
+
+        Some(SpdmChunkAttributes::from_bits_truncate(bits))
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmChunkSendAckAttributes: u8 {
+        const EARLY_ERROR_DETECTED = 0b0000_0001;
+    }
+}
+
+impl Codec for SpdmChunkSendAckAttributes {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmChunkSendAckAttributes> {
+        let bits = u8::read(r)?;
+
+        Some(SpdmChunkSendAckAttributes::from_bits_truncate(bits))
+    }
+}
+
+/// One chunk of a large SPDM message, as CHUNK_SEND and CHUNK_RESPONSE
+/// carry it. LargeMessageSize is only on the wire in chunk 0.
+#[derive(Debug, Clone)]
+pub struct SpdmChunk {
+    pub attributes: SpdmChunkAttributes,
+    pub handle: u8,
+    pub chunk_seq_num: u16,
+    pub large_message_size: u32,
+    pub chunk_size: u32,
+    pub chunk: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+}
+
+impl Default for SpdmChunk {
+    fn default() -> Self {
+        SpdmChunk {
+            attributes: SpdmChunkAttributes::default(),
+            handle: 0,
+            chunk_seq_num: 0,
+            large_message_size: 0,
+            chunk_size: 0,
+            chunk: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+        }
+    }
+}
+
+impl SpdmChunk {
+    pub fn is_last(&self) -> bool {
+        self.attributes.contains(SpdmChunkAttributes::LAST_CHUNK)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.chunk[..(self.chunk_size as usize)]
+    }
+}
+
+impl Codec for SpdmChunk {
+    fn encode(&self, bytes: &mut Writer) {
+        self.attributes.encode(bytes); // param1
+        self.handle.encode(bytes); // param2
+        self.chunk_seq_num.encode(bytes);
+        0u16.encode(bytes); // reserved
+        self.chunk_size.encode(bytes);
+        if self.chunk_seq_num == 0 {
+            self.large_message_size.encode(bytes);
+        }
+        for d in self.as_slice().iter() {
+            d.encode(bytes);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmChunk> {
+        let attributes = SpdmChunkAttributes::read(r)?; // param1
+        let handle = u8::read(r)?; // param2
+        let chunk_seq_num = u16::read(r)?;
+        u16::read(r)?; // reserved
+        let chunk_size = u32::read(r)?;
+        let large_message_size = if chunk_seq_num == 0 { u32::read(r)? } else { 0 };
+        if chunk_size == 0 || chunk_size as usize > config::MAX_SPDM_MESSAGE_BUFFER_SIZE {
+            return None;
+        }
+        let mut chunk = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        for d in chunk[..(chunk_size as usize)].iter_mut() {
+            *d = u8::read(r)?;
+        }
+
+        Some(SpdmChunk {
+            attributes,
+            handle,
+            chunk_seq_num,
+            large_message_size,
+            chunk_size,
+            chunk,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmChunkSendRequestPayload {
+    pub chunk: SpdmChunk,
+}
+
+impl SpdmCodec for SpdmChunkSendRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.chunk.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmChunkSendRequestPayload> {
+        let chunk = SpdmChunk::read(r)?;
+
+        Some(SpdmChunkSendRequestPayload { chunk })
+    }
+}
+
+/// CHUNK_SEND_ACK. The response to the large request rides on the ACK of
+/// its last chunk, or on an earlier one with EARLY_ERROR_DETECTED.
+#[derive(Debug, Clone)]
+pub struct SpdmChunkSendAckResponsePayload {
+    pub attributes: SpdmChunkSendAckAttributes,
+    pub handle: u8,
+    pub chunk_seq_num: u16,
+    pub response_size: u32,
+    pub response: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+}
+
+impl Default for SpdmChunkSendAckResponsePayload {
+    fn default() -> Self {
+        SpdmChunkSendAckResponsePayload {
+            attributes: SpdmChunkSendAckAttributes::default(),
+            handle: 0,
+            chunk_seq_num: 0,
+            response_size: 0,
+            response: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+        }
+    }
+}
+
+impl SpdmCodec for SpdmChunkSendAckResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.attributes.encode(bytes); // param1
+        self.handle.encode(bytes); // param2
+        self.chunk_seq_num.encode(bytes);
+        for d in self.response[..(self.response_size as usize)].iter() {
+            d.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmChunkSendAckResponsePayload> {
+        let attributes = SpdmChunkSendAckAttributes::read(r)?; // param1
+        let handle = u8::read(r)?; // param2
+        let chunk_seq_num = u16::read(r)?;
+        let response_size = r.left();
+        if response_size > config::MAX_SPDM_MESSAGE_BUFFER_SIZE {
+            return None;
+        }
+        let mut response = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        for d in response[..response_size].iter_mut() {
+            *d = u8::read(r)?;
+        }
+
+        Some(SpdmChunkSendAckResponsePayload {
+            attributes,
+            handle,
+            chunk_seq_num,
+            response_size: response_size as u32,
+            response,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmChunkGetRequestPayload {
+    pub handle: u8,
+    pub chunk_seq_num: u16,
+}
+
+impl SpdmCodec for SpdmChunkGetRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        self.handle.encode(bytes); // param2
+        self.chunk_seq_num.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmChunkGetRequestPayload> {
+        u8::read(r)?; // param1
+        let handle = u8::read(r)?; // param2
+        let chunk_seq_num = u16::read(r)?;
+
+        Some(SpdmChunkGetRequestPayload {
+            handle,
+            chunk_seq_num,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmChunkResponsePayload {
+    pub chunk: SpdmChunk,
+}
+
+impl SpdmCodec for SpdmChunkResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.chunk.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmChunkResponsePayload> {
+        let chunk = SpdmChunk::read(r)?;
+
+        Some(SpdmChunkResponsePayload { chunk })
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_chunk_send_request_payload() {
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmChunkSendRequestPayload {
+            chunk: SpdmChunk {
+                handle: 3,
+                large_message_size: 100,
+                chunk_size: 10,
+                ..Default::default()
+            },
+        };
+        value.chunk.chunk[..10].copy_from_slice(&[0xa5u8; 10]);
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), SPDM_FIRST_CHUNK_HEADER_SIZE - 2 + 10);
+        let mut reader = Reader::init(&u8_slice[..writer.used()]);
+        let payload = SpdmChunkSendRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert!(!payload.chunk.is_last());
+        assert_eq!(payload.chunk.handle, 3);
+        assert_eq!(payload.chunk.chunk_seq_num, 0);
+        assert_eq!(payload.chunk.large_message_size, 100);
+        assert_eq!(payload.chunk.as_slice(), &[0xa5u8; 10]);
+        assert_eq!(0, reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_chunk_send_request_payload() {
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmChunkSendRequestPayload {
+            chunk: SpdmChunk {
+                attributes: SpdmChunkAttributes::LAST_CHUNK,
+                handle: 3,
+                chunk_seq_num: 2,
+                large_message_size: 100,
+                chunk_size: 4,
+                ..Default::default()
+            },
+        };
+
+        create_spdm_context!(context);
+
+        // LargeMessageSize is only in chunk 0
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), SPDM_CHUNK_HEADER_SIZE - 2 + 4);
+        let mut reader = Reader::init(&u8_slice[..writer.used()]);
+        let payload = SpdmChunkSendRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert!(payload.chunk.is_last());
+        assert_eq!(payload.chunk.chunk_seq_num, 2);
+        assert_eq!(payload.chunk.large_message_size, 0);
+
+        // a chunk cut short
+        let mut reader = Reader::init(&u8_slice[..(writer.used() - 1)]);
+        assert!(SpdmChunkSendRequestPayload::spdm_read(&mut context, &mut reader).is_none());
+    }
+    #[test]
+    fn test_case0_spdm_chunk_send_ack_response_payload() {
+        let u8_slice = &mut [0u8; 16];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmChunkSendAckResponsePayload {
+            handle: 3,
+            chunk_seq_num: 7,
+            response_size: 4,
+            ..Default::default()
+        };
+        value.response[..4].copy_from_slice(&[0x12, 0x7f, 0x01, 0x00]);
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(&u8_slice[..writer.used()]);
+        let payload =
+            SpdmChunkSendAckResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(payload.handle, 3);
+        assert_eq!(payload.chunk_seq_num, 7);
+        assert_eq!(payload.response_size, 4);
+        assert_eq!(&payload.response[..4], &[0x12, 0x7f, 0x01, 0x00]);
+    }
+    #[test]
+    fn test_case0_spdm_chunk_get_request_payload() {
+        let u8_slice = &mut [0u8; 4];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmChunkGetRequestPayload {
+            handle: 9,
+            chunk_seq_num: 0x102,
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        let payload = SpdmChunkGetRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(payload.handle, 9);
+        assert_eq!(payload.chunk_seq_num, 0x102);
+        assert_eq!(0, reader.left());
+    }
+}
@@ -4,8 +4,20 @@
 
 use crate::common;
 use crate::common::spdm_codec::SpdmCodec;
+use crate::message::SpdmKeyUsageBitMask;
 use crate::protocol::{gen_array_clone, SpdmDigestStruct, SPDM_MAX_SLOT_NUMBER};
-use codec::{Codec, Reader, Writer};
+use codec::{enum_builder, Codec, Reader, Writer};
+
+// CertificateInfo: the certificate model of a slot, Unknown(0) for none.
+enum_builder! {
+    @U8
+    EnumName: SpdmCertificateModel;
+    EnumVal{
+        DeviceCert => 0x1,
+        AliasCert => 0x2,
+        GenericCert => 0x3
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct SpdmGetDigestsRequestPayload {}
@@ -32,6 +44,10 @@ pub struct SpdmDigestsResponsePayload {
     pub slot_mask: u8,
     pub slot_count: u8,
     pub digests: [SpdmDigestStruct; SPDM_MAX_SLOT_NUMBER],
+    // present in a multi-key connection only
+    pub key_pair_id: [u8; SPDM_MAX_SLOT_NUMBER],
+    pub certificate_info: [SpdmCertificateModel; SPDM_MAX_SLOT_NUMBER],
+    pub key_usage_mask: [SpdmKeyUsageBitMask; SPDM_MAX_SLOT_NUMBER],
 }
 
 impl SpdmCodec for SpdmDigestsResponsePayload {
@@ -53,6 +69,18 @@ impl SpdmCodec for SpdmDigestsResponsePayload {
         for digest in self.digests.iter().take(count as usize) {
             digest.spdm_encode(context, bytes);
         }
+
+        if context.is_multi_key_conn_negotiated() {
+            for key_pair_id in self.key_pair_id.iter().take(count as usize) {
+                key_pair_id.encode(bytes);
+            }
+            for certificate_info in self.certificate_info.iter().take(count as usize) {
+                certificate_info.encode(bytes);
+            }
+            for key_usage_mask in self.key_usage_mask.iter().take(count as usize) {
+                key_usage_mask.encode(bytes);
+            }
+        }
     }
 
     fn spdm_read(
@@ -73,10 +101,29 @@ impl SpdmCodec for SpdmDigestsResponsePayload {
         for digest in digests.iter_mut().take(slot_count as usize) {
             *digest = SpdmDigestStruct::spdm_read(context, r)?;
         }
+
+        let mut key_pair_id = [0u8; SPDM_MAX_SLOT_NUMBER];
+        let mut certificate_info = [SpdmCertificateModel::default(); SPDM_MAX_SLOT_NUMBER];
+        let mut key_usage_mask = [SpdmKeyUsageBitMask::default(); SPDM_MAX_SLOT_NUMBER];
+        if context.is_multi_key_conn_negotiated() {
+            for id in key_pair_id.iter_mut().take(slot_count as usize) {
+                *id = u8::read(r)?;
+            }
+            for info in certificate_info.iter_mut().take(slot_count as usize) {
+                *info = SpdmCertificateModel::read(r)?;
+            }
+            for mask in key_usage_mask.iter_mut().take(slot_count as usize) {
+                *mask = SpdmKeyUsageBitMask::read(r)?;
+            }
+        }
+
         Some(SpdmDigestsResponsePayload {
             slot_mask,
             slot_count,
             digests,
+            key_pair_id,
+            certificate_info,
+            key_usage_mask,
         })
     }
 }
@@ -107,6 +154,9 @@ mod tests {
                 },
                 SPDM_MAX_SLOT_NUMBER,
             ),
+            key_pair_id: [0u8; SPDM_MAX_SLOT_NUMBER],
+            certificate_info: [SpdmCertificateModel::default(); SPDM_MAX_SLOT_NUMBER],
+            key_usage_mask: [SpdmKeyUsageBitMask::default(); SPDM_MAX_SLOT_NUMBER],
         };
         for i in 0..8 {
             for j in 0..64 {
@@ -177,4 +227,63 @@ mod tests {
         let mut reader = Reader::init(u8_slice);
         SpdmGetDigestsRequestPayload::spdm_read(&mut context, &mut reader);
     }
+    #[test]
+    fn test_case2_spdm_digests_response_payload() {
+        let u8_slice = &mut [0u8; 2 + 2 * 32 + 2 * 4];
+        let mut writer = Writer::init(u8_slice);
+
+        let mut value = SpdmDigestsResponsePayload::default();
+        value.slot_mask = 0b00000101;
+        value.slot_count = 2;
+        value.digests = gen_array_clone(
+            SpdmDigestStruct {
+                data_size: 32,
+                data: Box::new([0x5au8; SPDM_MAX_HASH_SIZE]),
+            },
+            SPDM_MAX_SLOT_NUMBER,
+        );
+        value.key_pair_id[0] = 1;
+        value.key_pair_id[1] = 2;
+        value.certificate_info[0] = SpdmCertificateModel::DeviceCert;
+        value.certificate_info[1] = SpdmCertificateModel::AliasCert;
+        value.key_usage_mask[0] = SpdmKeyUsageBitMask::KEY_EX_USE;
+        value.key_usage_mask[1] =
+            SpdmKeyUsageBitMask::CHALLENGE_USE | SpdmKeyUsageBitMask::MEASUREMENT_USE;
+
+        create_spdm_context!(context);
+
+        context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        context.negotiate_info.opaque_data_support =
+            crate::common::SpdmOpaqueSupport::MULTI_KEY_CONN;
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(0, writer.left());
+        let mut reader = Reader::init(u8_slice);
+        let digests = SpdmDigestsResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(0, reader.left());
+        assert_eq!(digests.key_pair_id[..2], [1, 2]);
+        assert_eq!(digests.certificate_info[1], SpdmCertificateModel::AliasCert);
+        assert_eq!(digests.key_usage_mask[0], SpdmKeyUsageBitMask::KEY_EX_USE);
+
+        context.update_peer_key_info(&digests);
+        assert_eq!(
+            context.peer_info.peer_cert_model[2],
+            SpdmCertificateModel::AliasCert
+        );
+        assert!(context.is_key_usage_allowed(
+            &context.peer_info.peer_key_usage_mask,
+            0,
+            SpdmKeyUsageBitMask::KEY_EX_USE
+        ));
+        assert!(!context.is_key_usage_allowed(
+            &context.peer_info.peer_key_usage_mask,
+            0,
+            SpdmKeyUsageBitMask::CHALLENGE_USE
+        ));
+        assert!(context.is_key_usage_allowed(
+            &context.peer_info.peer_key_usage_mask,
+            2,
+            SpdmKeyUsageBitMask::MEASUREMENT_USE
+        ));
+    }
 }
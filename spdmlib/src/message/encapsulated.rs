@@ -0,0 +1,228 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use crate::protocol::SpdmVersion;
+use codec::enum_builder;
+use codec::{Codec, Reader, Writer};
+
+pub const SPDM_ENCAPSULATED_RESPONSE_ACK_HEADER_SIZE_12: usize = 8;
+
+enum_builder! {
+    @U8
+    EnumName: SpdmEncapsulatedResponseAckPayloadType;
+    EnumVal{
+        SpdmEncapsulatedResponseAckPayloadTypeAbsent => 0x0,
+        SpdmEncapsulatedResponseAckPayloadTypePresent => 0x1,
+        SpdmEncapsulatedResponseAckPayloadTypeReqSlotNumber => 0x2
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmGetEncapsulatedRequestPayload {}
+
+impl SpdmCodec for SpdmGetEncapsulatedRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmGetEncapsulatedRequestPayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmGetEncapsulatedRequestPayload {})
+    }
+}
+
+/// The encapsulated request message follows this payload on the wire.
+#[derive(Debug, Clone, Default)]
+pub struct SpdmEncapsulatedRequestPayload {
+    pub request_id: u8,
+}
+
+impl SpdmCodec for SpdmEncapsulatedRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.request_id.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmEncapsulatedRequestPayload> {
+        let request_id = u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmEncapsulatedRequestPayload { request_id })
+    }
+}
+
+/// The encapsulated response message follows this payload on the wire.
+#[derive(Debug, Clone, Default)]
+pub struct SpdmDeliverEncapsulatedResponsePayload {
+    pub request_id: u8,
+}
+
+impl SpdmCodec for SpdmDeliverEncapsulatedResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.request_id.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmDeliverEncapsulatedResponsePayload> {
+        let request_id = u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmDeliverEncapsulatedResponsePayload { request_id })
+    }
+}
+
+/// When payload_type is Present, the next encapsulated request follows
+/// this payload on the wire. When it is ReqSlotNumber, one byte follows.
+#[derive(Debug, Clone, Default)]
+pub struct SpdmEncapsulatedResponseAckPayload {
+    pub request_id: u8,
+    pub payload_type: SpdmEncapsulatedResponseAckPayloadType,
+    pub ack_request_id: u8,
+}
+
+impl SpdmCodec for SpdmEncapsulatedResponseAckPayload {
+    fn spdm_encode(&self, context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.request_id.encode(bytes); // param1
+        self.payload_type.encode(bytes); // param2
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
+            self.ack_request_id.encode(bytes);
+            for _ in 0..3 {
+                0u8.encode(bytes); // reserved
+            }
+        }
+    }
+
+    fn spdm_read(
+        context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmEncapsulatedResponseAckPayload> {
+        let request_id = u8::read(r)?; // param1
+        let payload_type = SpdmEncapsulatedResponseAckPayloadType::read(r)?; // param2
+        if let SpdmEncapsulatedResponseAckPayloadType::Unknown(_) = payload_type {
+            return None;
+        }
+        let mut ack_request_id = 0u8;
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
+            ack_request_id = u8::read(r)?;
+            for _ in 0..3 {
+                u8::read(r)?; // reserved
+            }
+        }
+
+        Some(SpdmEncapsulatedResponseAckPayload {
+            request_id,
+            payload_type,
+            ack_request_id,
+        })
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_encapsulated_request_payload() {
+        let u8_slice = &mut [0u8; 4];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmEncapsulatedRequestPayload { request_id: 0x5a };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(4, reader.left());
+        let encapsulated_request =
+            SpdmEncapsulatedRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(encapsulated_request.request_id, 0x5a);
+        assert_eq!(2, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_deliver_encapsulated_response_payload() {
+        let u8_slice = &mut [0u8; 4];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmDeliverEncapsulatedResponsePayload { request_id: 0xa5 };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        let deliver_encapsulated_response =
+            SpdmDeliverEncapsulatedResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(deliver_encapsulated_response.request_id, 0xa5);
+        assert_eq!(2, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_encapsulated_response_ack_payload() {
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmEncapsulatedResponseAckPayload {
+            request_id: 2,
+            payload_type:
+                SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypePresent,
+            ack_request_id: 1,
+        };
+
+        create_spdm_context!(context);
+        context.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(
+            SPDM_ENCAPSULATED_RESPONSE_ACK_HEADER_SIZE_12 - 2,
+            writer.used()
+        );
+        let mut reader = Reader::init(u8_slice);
+        let encapsulated_response_ack =
+            SpdmEncapsulatedResponseAckPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(encapsulated_response_ack.request_id, 2);
+        assert_eq!(
+            encapsulated_response_ack.payload_type,
+            SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypePresent
+        );
+        assert_eq!(encapsulated_response_ack.ack_request_id, 1);
+        assert_eq!(2, reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_encapsulated_response_ack_payload() {
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmEncapsulatedResponseAckPayload {
+            request_id: 2,
+            payload_type:
+                SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeAbsent,
+            ack_request_id: 1,
+        };
+
+        create_spdm_context!(context);
+        context.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(2, writer.used());
+        let mut reader = Reader::init(u8_slice);
+        let encapsulated_response_ack =
+            SpdmEncapsulatedResponseAckPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(encapsulated_response_ack.ack_request_id, 0);
+        assert_eq!(6, reader.left());
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use crate::protocol::{SpdmNonceStruct, SpdmSignatureStruct};
+use codec::enum_builder;
+use codec::{Codec, Reader, Writer};
+
+pub const SPDM_ENDPOINT_INFO_SLOT_ID_MASK: u8 = 0x0F;
+
+/// Max size of the endpoint information carried in ENDPOINT_INFO.
+pub const SPDM_MAX_ENDPOINT_INFO_LEN: usize = 1024;
+
+enum_builder! {
+    @U8
+    EnumName: SpdmEndpointInfoSubCode;
+    EnumVal{
+        SpdmEndpointInfoDeviceClassIdentifier => 0x1
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmEndpointInfoRequestAttributes: u8 {
+        const SIGNATURE_REQUESTED = 0b00000001;
+    }
+}
+
+impl Codec for SpdmEndpointInfoRequestAttributes {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmEndpointInfoRequestAttributes> {
+        let bits = u8::read(r)?;
+
+        SpdmEndpointInfoRequestAttributes::from_bits(bits)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmGetEndpointInfoRequestPayload {
+    pub sub_code: SpdmEndpointInfoSubCode,
+    pub slot_id: u8,
+    pub request_attributes: SpdmEndpointInfoRequestAttributes,
+    pub nonce: SpdmNonceStruct,
+}
+
+impl SpdmCodec for SpdmGetEndpointInfoRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.sub_code.encode(bytes); // param1
+        (self.slot_id & SPDM_ENDPOINT_INFO_SLOT_ID_MASK).encode(bytes); // param2
+        self.request_attributes.encode(bytes);
+        for _ in 0..3 {
+            0u8.encode(bytes); // reserved
+        }
+        if self
+            .request_attributes
+            .contains(SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED)
+        {
+            self.nonce.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmGetEndpointInfoRequestPayload> {
+        let sub_code = SpdmEndpointInfoSubCode::read(r)?; // param1
+        let slot_id = u8::read(r)? & SPDM_ENDPOINT_INFO_SLOT_ID_MASK; // param2
+        let request_attributes = SpdmEndpointInfoRequestAttributes::read(r)?;
+        for _ in 0..3 {
+            u8::read(r)?; // reserved
+        }
+        let nonce = if request_attributes
+            .contains(SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED)
+        {
+            SpdmNonceStruct::read(r)?
+        } else {
+            SpdmNonceStruct::default()
+        };
+
+        Some(SpdmGetEndpointInfoRequestPayload {
+            sub_code,
+            slot_id,
+            request_attributes,
+            nonce,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpdmEndpointInfoResponsePayload {
+    pub slot_id: u8,
+    pub nonce: SpdmNonceStruct,
+    pub ep_info_len: u32,
+    pub ep_info: [u8; SPDM_MAX_ENDPOINT_INFO_LEN],
+    pub signature: SpdmSignatureStruct,
+}
+
+impl Default for SpdmEndpointInfoResponsePayload {
+    fn default() -> Self {
+        SpdmEndpointInfoResponsePayload {
+            slot_id: 0,
+            nonce: SpdmNonceStruct::default(),
+            ep_info_len: 0,
+            ep_info: [0u8; SPDM_MAX_ENDPOINT_INFO_LEN],
+            signature: SpdmSignatureStruct::default(),
+        }
+    }
+}
+
+impl SpdmCodec for SpdmEndpointInfoResponsePayload {
+    fn spdm_encode(&self, context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        (self.slot_id & SPDM_ENDPOINT_INFO_SLOT_ID_MASK).encode(bytes); // param2
+        for _ in 0..4 {
+            0u8.encode(bytes); // reserved
+        }
+        if context.runtime_info.need_endpoint_info_signature {
+            self.nonce.encode(bytes);
+        }
+        self.ep_info_len.encode(bytes);
+        for d in self.ep_info[..(self.ep_info_len as usize)].iter() {
+            d.encode(bytes);
+        }
+        if context.runtime_info.need_endpoint_info_signature {
+            self.signature.spdm_encode(context, bytes);
+        }
+    }
+
+    fn spdm_read(
+        context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmEndpointInfoResponsePayload> {
+        u8::read(r)?; // param1
+        let slot_id = u8::read(r)? & SPDM_ENDPOINT_INFO_SLOT_ID_MASK; // param2
+        for _ in 0..4 {
+            u8::read(r)?; // reserved
+        }
+        let nonce = if context.runtime_info.need_endpoint_info_signature {
+            SpdmNonceStruct::read(r)?
+        } else {
+            SpdmNonceStruct::default()
+        };
+        let ep_info_len = u32::read(r)?;
+        if ep_info_len as usize > SPDM_MAX_ENDPOINT_INFO_LEN {
+            return None;
+        }
+        let mut ep_info = [0u8; SPDM_MAX_ENDPOINT_INFO_LEN];
+        for d in ep_info[..(ep_info_len as usize)].iter_mut() {
+            *d = u8::read(r)?;
+        }
+        let signature = if context.runtime_info.need_endpoint_info_signature {
+            SpdmSignatureStruct::spdm_read(context, r)?
+        } else {
+            SpdmSignatureStruct::default()
+        };
+
+        Some(SpdmEndpointInfoResponsePayload {
+            slot_id,
+            nonce,
+            ep_info_len,
+            ep_info,
+            signature,
+        })
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use crate::protocol::*;
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_get_endpoint_info_request_payload() {
+        let u8_slice = &mut [0u8; 48];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmGetEndpointInfoRequestPayload {
+            sub_code: SpdmEndpointInfoSubCode::SpdmEndpointInfoDeviceClassIdentifier,
+            slot_id: 0x13,
+            request_attributes: SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED,
+            nonce: SpdmNonceStruct {
+                data: [100u8; SPDM_NONCE_SIZE],
+            },
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(48, reader.left());
+        let get_endpoint_info =
+            SpdmGetEndpointInfoRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            get_endpoint_info.sub_code,
+            SpdmEndpointInfoSubCode::SpdmEndpointInfoDeviceClassIdentifier
+        );
+        assert_eq!(get_endpoint_info.slot_id, 3);
+        assert_eq!(
+            get_endpoint_info.request_attributes,
+            SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED
+        );
+        for i in 0..32 {
+            assert_eq!(get_endpoint_info.nonce.data[i], 100u8);
+        }
+        assert_eq!(10, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_endpoint_info_response_payload() {
+        let u8_slice = &mut [0u8; 256];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmEndpointInfoResponsePayload {
+            slot_id: 1,
+            nonce: SpdmNonceStruct {
+                data: [0x5au8; SPDM_NONCE_SIZE],
+            },
+            ep_info_len: 8,
+            ..Default::default()
+        };
+        value.ep_info[..8].copy_from_slice(&[0xa5u8; 8]);
+        value.signature = SpdmSignatureStruct {
+            data_size: 96,
+            data: [0xccu8; SPDM_MAX_ASYM_KEY_SIZE],
+        };
+
+        create_spdm_context!(context);
+        context.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        context.runtime_info.need_endpoint_info_signature = true;
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        let endpoint_info =
+            SpdmEndpointInfoResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(endpoint_info.slot_id, 1);
+        assert_eq!(endpoint_info.ep_info_len, 8);
+        for i in 0..8 {
+            assert_eq!(endpoint_info.ep_info[i], 0xa5);
+        }
+        for i in 0..32 {
+            assert_eq!(endpoint_info.nonce.data[i], 0x5a);
+        }
+        assert_eq!(endpoint_info.signature.data_size, 96);
+        for i in 0..96 {
+            assert_eq!(endpoint_info.signature.data[i], 0xcc);
+        }
+        assert_eq!(256 - (2 + 4 + 32 + 4 + 8 + 96), reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_endpoint_info_response_payload() {
+        let u8_slice = &mut [0u8; 32];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmEndpointInfoResponsePayload {
+            ep_info_len: 4,
+            ..Default::default()
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(2 + 4 + 4 + 4, writer.used());
+        let mut reader = Reader::init(u8_slice);
+        let endpoint_info =
+            SpdmEndpointInfoResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(endpoint_info.ep_info_len, 4);
+    }
+}
@@ -4,6 +4,8 @@
 
 use crate::common;
 use crate::common::spdm_codec::SpdmCodec;
+use crate::config;
+use crate::message::{SpdmVersion, VendorIDStruct};
 use codec::enum_builder;
 use codec::{Codec, Reader, Writer};
 
@@ -30,6 +32,7 @@ enum_builder! {
         SpdmErrorVersionMismatch => 0x41,
         SpdmErrorResponseNotReady => 0x42,
         SpdmErrorRequestResynch => 0x43,
+        SpdmErrorOperationFailed => 0x44,
         SpdmErrorVendorDefined => 0xFF
     }
 }
@@ -56,6 +59,20 @@ pub struct SpdmErrorResponseNotReadyExtData {
     pub rdtm: u8,
 }
 
+impl SpdmErrorResponseNotReadyExtData {
+    /// Time to wait before RESPOND_IF_READY, in us (RDT).
+    pub fn get_rdt(&self) -> usize {
+        2usize
+            .checked_shl(self.rdt_exponent as u32)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Longest time the responder may take to get ready, in us (WT_Max).
+    pub fn get_max_wait(&self) -> usize {
+        self.get_rdt().saturating_mul(self.rdtm as usize)
+    }
+}
+
 impl Codec for SpdmErrorResponseNotReadyExtData {
     fn encode(&self, bytes: &mut Writer) {
         self.rdt_exponent.encode(bytes);
@@ -104,14 +121,33 @@ impl SpdmCodec for SpdmErrorResponseNotReadyExtData {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub const SPDM_MAX_ERROR_VENDOR_OPAQUE_DATA_SIZE: usize = 32;
+
+/// ExtendedErrorData of ERROR(VendorDefined). The registry or standards body
+/// ID is carried in ErrorData, the vendor ID and opaque data follow here.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpdmErrorResponseVendorExtData {
+    pub vendor_id: VendorIDStruct,
     pub data_size: u8,
-    pub data: [u8; 32],
+    pub data: [u8; SPDM_MAX_ERROR_VENDOR_OPAQUE_DATA_SIZE],
+}
+
+impl Default for SpdmErrorResponseVendorExtData {
+    fn default() -> SpdmErrorResponseVendorExtData {
+        SpdmErrorResponseVendorExtData {
+            vendor_id: VendorIDStruct {
+                len: 0,
+                vendor_id: [0u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN],
+            },
+            data_size: 0,
+            data: [0u8; SPDM_MAX_ERROR_VENDOR_OPAQUE_DATA_SIZE],
+        }
+    }
 }
 
 impl SpdmCodec for SpdmErrorResponseVendorExtData {
     fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.vendor_id.encode(bytes);
         for d in self.data.iter().take(self.data_size as usize) {
             d.encode(bytes);
         }
@@ -121,23 +157,65 @@ impl SpdmCodec for SpdmErrorResponseVendorExtData {
         _context: &mut common::SpdmContext,
         r: &mut Reader,
     ) -> Option<SpdmErrorResponseVendorExtData> {
-        let mut data_size = 0;
-        let mut data = [0u8; 32];
-
-        for d in &mut data {
-            let result = u8::read(r);
-            match result {
-                Some(v) => {
-                    *d = v;
-                    data_size += 1;
-                }
-                None => {
-                    break;
-                }
-            }
+        let vendor_id = VendorIDStruct::read(r)?;
+
+        // the opaque data runs to the end of the message
+        if r.left() > SPDM_MAX_ERROR_VENDOR_OPAQUE_DATA_SIZE {
+            return None;
+        }
+        let data_size = r.left() as u8;
+        let mut data = [0u8; SPDM_MAX_ERROR_VENDOR_OPAQUE_DATA_SIZE];
+        for d in data.iter_mut().take(data_size as usize) {
+            *d = u8::read(r)?;
         }
 
-        Some(SpdmErrorResponseVendorExtData { data_size, data })
+        Some(SpdmErrorResponseVendorExtData {
+            vendor_id,
+            data_size,
+            data,
+        })
+    }
+}
+
+/// ExtendedErrorData of ERROR(LargeResponse), spdm 1.2.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpdmErrorResponseLargeExtData {
+    pub handle: u8,
+}
+
+impl SpdmCodec for SpdmErrorResponseLargeExtData {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.handle.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmErrorResponseLargeExtData> {
+        let handle = u8::read(r)?;
+
+        Some(SpdmErrorResponseLargeExtData { handle })
+    }
+}
+
+/// ExtendedErrorData of ERROR(ResponseTooLarge), spdm 1.2.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpdmErrorResponseTooLargeExtData {
+    pub response_size: u32,
+}
+
+impl SpdmCodec for SpdmErrorResponseTooLargeExtData {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.response_size.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmErrorResponseTooLargeExtData> {
+        let response_size = u32::read(r)?;
+
+        Some(SpdmErrorResponseTooLargeExtData { response_size })
     }
 }
 
@@ -146,6 +224,8 @@ pub enum SpdmErrorResponseExtData {
     SpdmErrorExtDataNone(SpdmErrorResponseNoneExtData),
     SpdmErrorExtDataNotReady(SpdmErrorResponseNotReadyExtData),
     SpdmErrorExtDataVendorDefined(SpdmErrorResponseVendorExtData),
+    SpdmErrorExtDataLargeResponse(SpdmErrorResponseLargeExtData),
+    SpdmErrorExtDataResponseTooLarge(SpdmErrorResponseTooLargeExtData),
 }
 impl Default for SpdmErrorResponseExtData {
     fn default() -> SpdmErrorResponseExtData {
@@ -172,6 +252,12 @@ impl SpdmCodec for SpdmErrorResponsePayload {
             SpdmErrorResponseExtData::SpdmErrorExtDataVendorDefined(extended_data) => {
                 extended_data.spdm_encode(context, bytes);
             }
+            SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(extended_data) => {
+                extended_data.spdm_encode(context, bytes);
+            }
+            SpdmErrorResponseExtData::SpdmErrorExtDataResponseTooLarge(extended_data) => {
+                extended_data.spdm_encode(context, bytes);
+            }
             SpdmErrorResponseExtData::SpdmErrorExtDataNone(extended_data) => {
                 extended_data.spdm_encode(context, bytes);
             }
@@ -196,6 +282,19 @@ impl SpdmCodec for SpdmErrorResponsePayload {
                     SpdmErrorResponseVendorExtData::spdm_read(context, r)?,
                 ))
             }
+            SpdmErrorCode::SpdmErrorLargeResponse => {
+                Some(SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(
+                    SpdmErrorResponseLargeExtData::spdm_read(context, r)?,
+                ))
+            }
+            SpdmErrorCode::SpdmErrorResponseTooLarge
+                if context.negotiate_info.spdm_version_sel.get_u8()
+                    >= SpdmVersion::SpdmVersion12.get_u8() =>
+            {
+                Some(SpdmErrorResponseExtData::SpdmErrorExtDataResponseTooLarge(
+                    SpdmErrorResponseTooLargeExtData::spdm_read(context, r)?,
+                ))
+            }
             _ => Some(SpdmErrorResponseExtData::SpdmErrorExtDataNone(
                 SpdmErrorResponseNoneExtData::spdm_read(context, r)?,
             )),
@@ -247,29 +346,49 @@ mod tests {
         assert_eq!(4, reader.left());
     }
     #[test]
+    fn test_case1_spdm_error_response_not_ready_ext_data() {
+        let mut value = SpdmErrorResponseNotReadyExtData {
+            rdt_exponent: 4,
+            request_code: 0xaa,
+            token: 0x55,
+            rdtm: 3,
+        };
+        assert_eq!(value.get_rdt(), 32);
+        assert_eq!(value.get_max_wait(), 96);
+
+        value.rdt_exponent = 0xaa;
+        assert_eq!(value.get_rdt(), usize::MAX);
+        assert_eq!(value.get_max_wait(), usize::MAX);
+    }
+    #[test]
     fn test_case0_spdm_error_response_vendor_ext_data() {
-        let u8_slice = &mut [0u8; 32];
+        let u8_slice = &mut [0u8; 35];
         let mut writer = Writer::init(u8_slice);
-        let value = SpdmErrorResponseVendorExtData {
+        let mut value = SpdmErrorResponseVendorExtData {
             data_size: 32,
             data: [100u8; 32],
+            ..Default::default()
         };
+        value.vendor_id.len = 2;
+        value.vendor_id.vendor_id[..2].copy_from_slice(&[0x86, 0x80]);
 
         create_spdm_context!(context);
 
         value.spdm_encode(&mut context, &mut writer);
         let mut reader = Reader::init(u8_slice);
-        assert_eq!(32, reader.left());
+        assert_eq!(35, reader.left());
         let response_vendor_ext_data =
             SpdmErrorResponseVendorExtData::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(response_vendor_ext_data.vendor_id, value.vendor_id);
         assert_eq!(response_vendor_ext_data.data_size, 32);
         for i in 0..32 {
             assert_eq!(response_vendor_ext_data.data[i], 100u8);
         }
+        assert_eq!(0, reader.left());
     }
     #[test]
     fn test_case1_spdm_error_response_vendor_ext_data() {
-        let u8_slice = &mut [0u8; 32];
+        let u8_slice = &mut [0u8; 1];
         let mut writer = Writer::init(u8_slice);
         let value = SpdmErrorResponseVendorExtData::default();
 
@@ -277,13 +396,16 @@ mod tests {
 
         value.spdm_encode(&mut context, &mut writer);
         let mut reader = Reader::init(u8_slice);
-        assert_eq!(32, reader.left());
+        assert_eq!(1, reader.left());
         let response_vendor_ext_data =
             SpdmErrorResponseVendorExtData::spdm_read(&mut context, &mut reader).unwrap();
-        assert_eq!(response_vendor_ext_data.data_size, 32);
-        for i in 0..32 {
-            assert_eq!(response_vendor_ext_data.data[i], 0);
-        }
+        assert_eq!(response_vendor_ext_data.vendor_id.len, 0);
+        assert_eq!(response_vendor_ext_data.data_size, 0);
+
+        // opaque data larger than supported is rejected
+        let u8_slice = &mut [0u8; 34];
+        let mut reader = Reader::init(u8_slice);
+        assert!(SpdmErrorResponseVendorExtData::spdm_read(&mut context, &mut reader).is_none());
     }
     #[test]
     fn test_case0_spdm_error_response_payload() {
@@ -327,6 +449,7 @@ mod tests {
             SpdmErrorResponseVendorExtData {
                 data_size: 32,
                 data: [100u8; 32],
+                ..Default::default()
             },
         );
         spdm_error_response_payload = new_spdm_response(value, &mut context);
@@ -334,12 +457,30 @@ mod tests {
         if let SpdmErrorResponseExtData::SpdmErrorExtDataVendorDefined(extended_data) =
             &spdm_error_response_payload.extended_data
         {
+            assert_eq!(extended_data.vendor_id.len, 0);
             assert_eq!(extended_data.data_size, 32);
             for i in 0..32 {
                 assert_eq!(extended_data.data[i], 100u8);
             }
+        } else {
+            panic!("vendor defined extended data expected");
         }
 
+        let value = SpdmErrorResponsePayload {
+            error_code: SpdmErrorCode::SpdmErrorLargeResponse,
+            error_data: 0,
+            extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(
+                SpdmErrorResponseLargeExtData { handle: 0x5a },
+            ),
+        };
+        spdm_error_response_payload = new_spdm_response(value, &mut context);
+        assert_eq!(
+            spdm_error_response_payload.extended_data,
+            SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(
+                SpdmErrorResponseLargeExtData { handle: 0x5a },
+            )
+        );
+
         let mut value = SpdmErrorResponsePayload {
             error_code: SpdmErrorCode::SpdmErrorInvalidRequest,
             error_data: 100,
@@ -357,7 +498,8 @@ mod tests {
         let u8_slice = &mut [0u8; 100];
         let mut writer = Writer::init(u8_slice);
         value.spdm_encode(context, &mut writer);
-        let mut reader = Reader::init(u8_slice);
+        let used = writer.used();
+        let mut reader = Reader::init(&u8_slice[..used]);
 
         SpdmErrorResponsePayload::spdm_read(context, &mut reader).unwrap()
     }
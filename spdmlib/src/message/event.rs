@@ -0,0 +1,396 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use crate::config;
+use crate::message::VendorIDStruct;
+use codec::{Codec, Reader, Writer};
+
+/// Max size of the SupportedEventGroupsList and SubscribeList.
+pub const SPDM_MAX_EVENT_GROUP_LIST_LEN: usize = 256;
+/// Max size of the EventDetail of a single event.
+pub const SPDM_MAX_EVENT_DETAIL_LEN: usize = 64;
+/// Max number of events carried in a SEND_EVENT message.
+pub const SPDM_MAX_EVENT_COUNT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct SpdmEventGroupListStruct {
+    pub data_size: u32,
+    pub data: [u8; SPDM_MAX_EVENT_GROUP_LIST_LEN],
+}
+
+impl Default for SpdmEventGroupListStruct {
+    fn default() -> Self {
+        SpdmEventGroupListStruct {
+            data_size: 0,
+            data: [0u8; SPDM_MAX_EVENT_GROUP_LIST_LEN],
+        }
+    }
+}
+
+impl AsRef<[u8]> for SpdmEventGroupListStruct {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[0..(self.data_size as usize)]
+    }
+}
+
+impl Codec for SpdmEventGroupListStruct {
+    fn encode(&self, bytes: &mut Writer) {
+        self.data_size.encode(bytes);
+        for d in self.data.iter().take(self.data_size as usize) {
+            d.encode(bytes);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmEventGroupListStruct> {
+        let data_size = u32::read(r)?;
+        if data_size as usize > SPDM_MAX_EVENT_GROUP_LIST_LEN {
+            return None;
+        }
+        let mut data = [0u8; SPDM_MAX_EVENT_GROUP_LIST_LEN];
+        for d in data.iter_mut().take(data_size as usize) {
+            *d = u8::read(r)?;
+        }
+        Some(SpdmEventGroupListStruct { data_size, data })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpdmEventStruct {
+    pub event_instance_id: u32,
+    pub svh_id: u8,
+    pub svh_vendor_id: VendorIDStruct,
+    pub event_type_id: u16,
+    pub event_detail_len: u16,
+    pub event_detail: [u8; SPDM_MAX_EVENT_DETAIL_LEN],
+}
+
+impl Default for SpdmEventStruct {
+    fn default() -> Self {
+        SpdmEventStruct {
+            event_instance_id: 0,
+            svh_id: 0,
+            svh_vendor_id: VendorIDStruct {
+                len: 0,
+                vendor_id: [0u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN],
+            },
+            event_type_id: 0,
+            event_detail_len: 0,
+            event_detail: [0u8; SPDM_MAX_EVENT_DETAIL_LEN],
+        }
+    }
+}
+
+impl Codec for SpdmEventStruct {
+    fn encode(&self, bytes: &mut Writer) {
+        self.event_instance_id.encode(bytes);
+        0u32.encode(bytes); // reserved
+        self.svh_id.encode(bytes);
+        self.svh_vendor_id.encode(bytes);
+        self.event_type_id.encode(bytes);
+        self.event_detail_len.encode(bytes);
+        for d in self
+            .event_detail
+            .iter()
+            .take(self.event_detail_len as usize)
+        {
+            d.encode(bytes);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmEventStruct> {
+        let event_instance_id = u32::read(r)?;
+        u32::read(r)?; // reserved
+        let svh_id = u8::read(r)?;
+        let svh_vendor_id = VendorIDStruct::read(r)?;
+        if svh_vendor_id.len as usize > config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN {
+            return None;
+        }
+        let event_type_id = u16::read(r)?;
+        let event_detail_len = u16::read(r)?;
+        if event_detail_len as usize > SPDM_MAX_EVENT_DETAIL_LEN {
+            return None;
+        }
+        let mut event_detail = [0u8; SPDM_MAX_EVENT_DETAIL_LEN];
+        for d in event_detail.iter_mut().take(event_detail_len as usize) {
+            *d = u8::read(r)?;
+        }
+        Some(SpdmEventStruct {
+            event_instance_id,
+            svh_id,
+            svh_vendor_id,
+            event_type_id,
+            event_detail_len,
+            event_detail,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmGetSupportedEventTypesRequestPayload {}
+
+impl SpdmCodec for SpdmGetSupportedEventTypesRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmGetSupportedEventTypesRequestPayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmGetSupportedEventTypesRequestPayload {})
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSupportedEventTypesResponsePayload {
+    pub event_group_count: u8,
+    pub supported_event_groups_list: SpdmEventGroupListStruct,
+}
+
+impl SpdmCodec for SpdmSupportedEventTypesResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.event_group_count.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+        self.supported_event_groups_list.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSupportedEventTypesResponsePayload> {
+        let event_group_count = u8::read(r)?; // param1
+        u8::read(r)?; // param2
+        let supported_event_groups_list = SpdmEventGroupListStruct::read(r)?;
+
+        Some(SpdmSupportedEventTypesResponsePayload {
+            event_group_count,
+            supported_event_groups_list,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSubscribeEventTypesRequestPayload {
+    pub subscribe_event_group_count: u8,
+    pub subscribe_list: SpdmEventGroupListStruct,
+}
+
+impl SpdmCodec for SpdmSubscribeEventTypesRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.subscribe_event_group_count.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+
+        // A zero count clears all subscriptions and carries no list.
+        if self.subscribe_event_group_count != 0 {
+            self.subscribe_list.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSubscribeEventTypesRequestPayload> {
+        let subscribe_event_group_count = u8::read(r)?; // param1
+        u8::read(r)?; // param2
+        let subscribe_list = if subscribe_event_group_count != 0 {
+            SpdmEventGroupListStruct::read(r)?
+        } else {
+            SpdmEventGroupListStruct::default()
+        };
+
+        Some(SpdmSubscribeEventTypesRequestPayload {
+            subscribe_event_group_count,
+            subscribe_list,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSubscribeEventTypesAckResponsePayload {}
+
+impl SpdmCodec for SpdmSubscribeEventTypesAckResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSubscribeEventTypesAckResponsePayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmSubscribeEventTypesAckResponsePayload {})
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSendEventRequestPayload {
+    pub event_count: u32,
+    pub event_list: [SpdmEventStruct; SPDM_MAX_EVENT_COUNT],
+}
+
+impl SpdmCodec for SpdmSendEventRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+        self.event_count.encode(bytes);
+        for event in self.event_list.iter().take(self.event_count as usize) {
+            event.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSendEventRequestPayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+        let event_count = u32::read(r)?;
+        if event_count == 0 || event_count as usize > SPDM_MAX_EVENT_COUNT {
+            return None;
+        }
+        let mut event_list = <[SpdmEventStruct; SPDM_MAX_EVENT_COUNT]>::default();
+        for event in event_list.iter_mut().take(event_count as usize) {
+            *event = SpdmEventStruct::read(r)?;
+        }
+
+        Some(SpdmSendEventRequestPayload {
+            event_count,
+            event_list,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmEventAckResponsePayload {}
+
+impl SpdmCodec for SpdmEventAckResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmEventAckResponsePayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmEventAckResponsePayload {})
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_supported_event_types_response_payload() {
+        let u8_slice = &mut [0u8; 32];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmSupportedEventTypesResponsePayload {
+            event_group_count: 1,
+            supported_event_groups_list: SpdmEventGroupListStruct {
+                data_size: 8,
+                ..Default::default()
+            },
+        };
+        value.supported_event_groups_list.data[..8].copy_from_slice(&[0xa5u8; 8]);
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(32, reader.left());
+        let supported_event_types =
+            SpdmSupportedEventTypesResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(supported_event_types.event_group_count, 1);
+        assert_eq!(
+            supported_event_types.supported_event_groups_list.as_ref(),
+            &[0xa5u8; 8]
+        );
+        assert_eq!(32 - 14, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_subscribe_event_types_request_payload() {
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSubscribeEventTypesRequestPayload {
+            subscribe_event_group_count: 0,
+            subscribe_list: SpdmEventGroupListStruct {
+                data_size: 4,
+                ..Default::default()
+            },
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(2, writer.used());
+        let mut reader = Reader::init(u8_slice);
+        let subscribe_event_types =
+            SpdmSubscribeEventTypesRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(subscribe_event_types.subscribe_event_group_count, 0);
+        assert_eq!(subscribe_event_types.subscribe_list.data_size, 0);
+    }
+    #[test]
+    fn test_case0_spdm_send_event_request_payload() {
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmSendEventRequestPayload {
+            event_count: 2,
+            ..Default::default()
+        };
+        value.event_list[0].event_instance_id = 1;
+        value.event_list[0].event_type_id = 3;
+        value.event_list[1].event_instance_id = 2;
+        value.event_list[1].svh_id = 3;
+        value.event_list[1].svh_vendor_id.len = 2;
+        value.event_list[1].svh_vendor_id.vendor_id[..2].copy_from_slice(&[0x86, 0x80]);
+        value.event_list[1].event_detail_len = 4;
+        value.event_list[1].event_detail[..4].copy_from_slice(&[0xa5u8; 4]);
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        let send_event = SpdmSendEventRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(send_event.event_count, 2);
+        assert_eq!(send_event.event_list[0].event_instance_id, 1);
+        assert_eq!(send_event.event_list[0].event_type_id, 3);
+        assert_eq!(send_event.event_list[1].event_instance_id, 2);
+        assert_eq!(send_event.event_list[1].svh_id, 3);
+        assert_eq!(send_event.event_list[1].svh_vendor_id.len, 2);
+        assert_eq!(send_event.event_list[1].event_detail_len, 4);
+        assert_eq!(send_event.event_list[1].event_detail[..4], [0xa5u8; 4]);
+        assert_eq!(64 - (6 + 14 + 20), reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_send_event_request_payload() {
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSendEventRequestPayload::default();
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert!(SpdmSendEventRequestPayload::spdm_read(&mut context, &mut reader).is_none());
+    }
+}
@@ -4,9 +4,7 @@
 
 use crate::common;
 use crate::common::spdm_codec::SpdmCodec;
-use crate::protocol::{
-    SpdmDigestStruct, SpdmRequestCapabilityFlags, SpdmResponseCapabilityFlags, SpdmSignatureStruct,
-};
+use crate::protocol::{SpdmDigestStruct, SpdmSignatureStruct};
 use codec::{Codec, Reader, Writer};
 
 bitflags! {
@@ -44,7 +42,16 @@ impl SpdmCodec for SpdmFinishRequestPayload {
             .finish_request_attributes
             .contains(SpdmFinishRequestAttributes::SIGNATURE_INCLUDED)
         {
-            self.signature.spdm_encode(context, bytes);
+            // the requester signs with the negotiated ReqBaseAsymAlg, a
+            // signature of another size is cut or padded to it
+            for d in self
+                .signature
+                .data
+                .iter()
+                .take(context.get_req_asym_key_size() as usize)
+            {
+                d.encode(bytes);
+            }
         }
         self.verify_data.spdm_encode(context, bytes);
     }
@@ -57,7 +64,10 @@ impl SpdmCodec for SpdmFinishRequestPayload {
         let req_slot_id = u8::read(r)?; // param2
         let mut signature = SpdmSignatureStruct::default();
         if finish_request_attributes.contains(SpdmFinishRequestAttributes::SIGNATURE_INCLUDED) {
-            signature = SpdmSignatureStruct::spdm_read(context, r)?;
+            signature.data_size = context.get_req_asym_key_size();
+            for d in signature.data.iter_mut().take(signature.data_size as usize) {
+                *d = u8::read(r)?;
+            }
         }
         let verify_data = SpdmDigestStruct::spdm_read(context, r)?;
 
@@ -79,14 +89,7 @@ impl SpdmCodec for SpdmFinishResponsePayload {
     fn spdm_encode(&self, context: &mut common::SpdmContext, bytes: &mut Writer) {
         0u8.encode(bytes); // param1
         0u8.encode(bytes); // param2
-        let in_clear_text = context
-            .negotiate_info
-            .req_capabilities_sel
-            .contains(SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
-            && context
-                .negotiate_info
-                .rsp_capabilities_sel
-                .contains(SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP);
+        let in_clear_text = context.is_handshake_in_the_clear();
         if in_clear_text {
             self.verify_data.spdm_encode(context, bytes);
         }
@@ -99,14 +102,7 @@ impl SpdmCodec for SpdmFinishResponsePayload {
         u8::read(r)?; // param1
         u8::read(r)?; // param2
 
-        let in_clear_text = context
-            .negotiate_info
-            .req_capabilities_sel
-            .contains(SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
-            && context
-                .negotiate_info
-                .rsp_capabilities_sel
-                .contains(SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP);
+        let in_clear_text = context.is_handshake_in_the_clear();
 
         let mut verify_data = SpdmDigestStruct::default();
         if in_clear_text {
@@ -148,6 +144,7 @@ mod tests {
         create_spdm_context!(context);
 
         context.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096;
+        context.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSASSA_4096;
         context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
 
         value.spdm_encode(&mut context, &mut writer);
@@ -189,6 +186,7 @@ mod tests {
         create_spdm_context!(context);
 
         context.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096;
+        context.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSASSA_4096;
         context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
 
         value.spdm_encode(&mut context, &mut writer);
@@ -207,6 +205,38 @@ mod tests {
         }
     }
     #[test]
+    fn test_case2_spdm_finish_request_payload() {
+        let u8_slice = &mut [0u8; 680];
+        let mut writer = Writer::init(u8_slice);
+        // an RSA 4096 signature with ECDSA P-256 negotiated
+        let value = SpdmFinishRequestPayload {
+            finish_request_attributes: SpdmFinishRequestAttributes::SIGNATURE_INCLUDED,
+            req_slot_id: 0,
+            signature: SpdmSignatureStruct {
+                data_size: 512,
+                data: [0xa5u8; SPDM_MAX_ASYM_KEY_SIZE],
+            },
+            verify_data: SpdmDigestStruct {
+                data_size: 32,
+                data: Box::new([0x5au8; SPDM_MAX_HASH_SIZE]),
+            },
+        };
+
+        create_spdm_context!(context);
+
+        context.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256;
+        context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 2 + 64 + 32);
+        let mut reader = Reader::init(&u8_slice[..2 + 64 + 32]);
+        let spdm_finish_request_payload =
+            SpdmFinishRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_finish_request_payload.signature.data_size, 64);
+        assert_eq!(spdm_finish_request_payload.verify_data.data_size, 32);
+        assert_eq!(reader.left(), 0);
+    }
+    #[test]
     fn test_case0_spdm_finish_response_payload() {
         let u8_slice = &mut [0u8; 68];
         let mut writer = Writer::init(u8_slice);
@@ -33,7 +33,7 @@ impl SpdmCodec for SpdmKeyExchangeRequestPayload {
         self.slot_id.encode(bytes); // param2
         self.req_session_id.encode(bytes);
 
-        if context.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if context.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
             self.session_policy.encode(bytes);
         } else {
             0u8.encode(bytes); // reserved
@@ -122,7 +122,9 @@ impl SpdmCodec for SpdmKeyExchangeResponsePayload {
         }
         self.opaque.spdm_encode(context, bytes);
         self.signature.spdm_encode(context, bytes);
-        self.verify_data.spdm_encode(context, bytes);
+        if !context.is_handshake_in_the_clear() {
+            self.verify_data.spdm_encode(context, bytes);
+        }
     }
 
     fn spdm_read(
@@ -136,7 +138,10 @@ impl SpdmCodec for SpdmKeyExchangeResponsePayload {
         let mut_auth_req = SpdmKeyExchangeMutAuthAttributes::read(r)?;
         let req_slot_id = u8::read(r)?;
         let random = SpdmRandomStruct::read(r)?;
-        let exchange = SpdmDheExchangeStruct::spdm_read(context, r)?;
+        let exchange = SpdmDheExchangeStruct::read_with_size(
+            r,
+            context.negotiate_info.dhe_sel.get_rsp_size(),
+        )?;
         let measurement_summary_hash = if context.runtime_info.need_measurement_summary_hash {
             SpdmDigestStruct::spdm_read(context, r)?
         } else {
@@ -144,7 +149,11 @@ impl SpdmCodec for SpdmKeyExchangeResponsePayload {
         };
         let opaque = SpdmOpaqueStruct::spdm_read(context, r)?;
         let signature = SpdmSignatureStruct::spdm_read(context, r)?;
-        let verify_data = SpdmDigestStruct::spdm_read(context, r)?;
+        let verify_data = if context.is_handshake_in_the_clear() {
+            SpdmDigestStruct::default()
+        } else {
+            SpdmDigestStruct::spdm_read(context, r)?
+        };
 
         Some(SpdmKeyExchangeResponsePayload {
             heartbeat_period,
@@ -394,4 +403,60 @@ mod tests {
         }
         assert_eq!(64, reader.left());
     }
+    #[test]
+    fn test_case2_spdm_key_exchange_response_payload() {
+        let u8_slice = &mut [0u8; 1256];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmKeyExchangeResponsePayload {
+            heartbeat_period: 100u8,
+            rsp_session_id: 100u16,
+            mut_auth_req: SpdmKeyExchangeMutAuthAttributes::empty(),
+            req_slot_id: 0u8,
+            random: SpdmRandomStruct {
+                data: [100u8; SPDM_RANDOM_SIZE],
+            },
+            exchange: SpdmDheExchangeStruct {
+                data_size: 512u16,
+                data: [0xa5u8; SPDM_MAX_DHE_KEY_SIZE],
+            },
+            measurement_summary_hash: SpdmDigestStruct::default(),
+            opaque: SpdmOpaqueStruct {
+                data_size: 64u16,
+                data: [0x22u8; crate::config::MAX_SPDM_OPAQUE_SIZE],
+            },
+            signature: SpdmSignatureStruct {
+                data_size: 512u16,
+                data: [0x5au8; SPDM_MAX_ASYM_KEY_SIZE],
+            },
+            verify_data: SpdmDigestStruct {
+                data_size: 64u16,
+                data: Box::new([0x33u8; SPDM_MAX_HASH_SIZE]),
+            },
+        };
+
+        create_spdm_context!(context);
+
+        context.negotiate_info.dhe_sel = SpdmDheAlgo::FFDHE_4096;
+        context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
+        context.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096;
+        context.negotiate_info.req_capabilities_sel =
+            SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP;
+        context.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP;
+        context.runtime_info.need_measurement_summary_hash = false;
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(1256, reader.left());
+        let exchange_request_payload =
+            SpdmKeyExchangeResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+
+        assert_eq!(exchange_request_payload.signature.data_size, 512);
+        for i in 0..512 {
+            assert_eq!(exchange_request_payload.signature.data[i], 0x5a);
+        }
+        // no ResponderVerifyData in the clear
+        assert_eq!(exchange_request_payload.verify_data.data_size, 0);
+        assert_eq!(128, reader.left());
+    }
 }
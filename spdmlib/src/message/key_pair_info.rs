@@ -0,0 +1,415 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use codec::enum_builder;
+use codec::{Codec, Reader, Writer};
+
+/// Max size of the DER encoded SubjectPublicKeyInfo carried in KEY_PAIR_INFO.
+pub const SPDM_MAX_PUBLIC_KEY_INFO_LEN: usize = 1024;
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmKeyPairCapabilities: u16 {
+        const GEN_KEY_CAP = 0b0000_0001;
+        const ERASABLE_CAP = 0b0000_0010;
+        const CERT_ASSOC_CAP = 0b0000_0100;
+        const KEY_USAGE_CAP = 0b0000_1000;
+        const ASYM_ALGO_CAP = 0b0001_0000;
+        const SHAREABLE_CAP = 0b0010_0000;
+    }
+}
+
+impl Codec for SpdmKeyPairCapabilities {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmKeyPairCapabilities> {
+        let bits = u16::read(r)?;
+
+        SpdmKeyPairCapabilities::from_bits(bits)
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmKeyUsageBitMask: u16 {
+        const KEY_EX_USE = 0b0000_0001;
+        const CHALLENGE_USE = 0b0000_0010;
+        const MEASUREMENT_USE = 0b0000_0100;
+        const ENDPOINT_INFO_USE = 0b0000_1000;
+        const STANDARDS_KEY_USE = 0b0100_0000_0000_0000;
+        const VENDOR_KEY_USE = 0b1000_0000_0000_0000;
+    }
+}
+
+impl Codec for SpdmKeyUsageBitMask {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmKeyUsageBitMask> {
+        let bits = u16::read(r)?;
+
+        SpdmKeyUsageBitMask::from_bits(bits)
+    }
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct SpdmKeyPairAsymAlgo: u32 {
+        const RSA2048 = 0b0000_0001;
+        const RSA3072 = 0b0000_0010;
+        const RSA4096 = 0b0000_0100;
+        const ECC256 = 0b0000_1000;
+        const ECC384 = 0b0001_0000;
+        const ECC521 = 0b0010_0000;
+        const SM2 = 0b0100_0000;
+        const ED25519 = 0b1000_0000;
+        const ED448 = 0b0000_0001_0000_0000;
+    }
+}
+
+impl Codec for SpdmKeyPairAsymAlgo {
+    fn encode(&self, bytes: &mut Writer) {
+        self.bits().encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<SpdmKeyPairAsymAlgo> {
+        let bits = u32::read(r)?;
+
+        SpdmKeyPairAsymAlgo::from_bits(bits)
+    }
+}
+
+enum_builder! {
+    @U8
+    EnumName: SpdmSetKeyPairInfoOperation;
+    EnumVal{
+        SpdmSetKeyPairInfoChange => 0x0,
+        SpdmSetKeyPairInfoErase => 0x1,
+        SpdmSetKeyPairInfoGenerate => 0x2
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmGetKeyPairInfoRequestPayload {
+    pub key_pair_id: u8,
+}
+
+impl SpdmCodec for SpdmGetKeyPairInfoRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+        self.key_pair_id.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmGetKeyPairInfoRequestPayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+        let key_pair_id = u8::read(r)?;
+
+        Some(SpdmGetKeyPairInfoRequestPayload { key_pair_id })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpdmKeyPairInfoResponsePayload {
+    pub total_key_pairs: u8,
+    pub key_pair_id: u8,
+    pub capabilities: SpdmKeyPairCapabilities,
+    pub key_usage_capabilities: SpdmKeyUsageBitMask,
+    pub current_key_usage: SpdmKeyUsageBitMask,
+    pub asym_algo_capabilities: SpdmKeyPairAsymAlgo,
+    pub current_asym_algo: SpdmKeyPairAsymAlgo,
+    pub assoc_cert_slot_mask: u8,
+    pub public_key_info_len: u16,
+    pub public_key_info: [u8; SPDM_MAX_PUBLIC_KEY_INFO_LEN],
+}
+
+impl Default for SpdmKeyPairInfoResponsePayload {
+    fn default() -> Self {
+        SpdmKeyPairInfoResponsePayload {
+            total_key_pairs: 0,
+            key_pair_id: 0,
+            capabilities: SpdmKeyPairCapabilities::default(),
+            key_usage_capabilities: SpdmKeyUsageBitMask::default(),
+            current_key_usage: SpdmKeyUsageBitMask::default(),
+            asym_algo_capabilities: SpdmKeyPairAsymAlgo::default(),
+            current_asym_algo: SpdmKeyPairAsymAlgo::default(),
+            assoc_cert_slot_mask: 0,
+            public_key_info_len: 0,
+            public_key_info: [0u8; SPDM_MAX_PUBLIC_KEY_INFO_LEN],
+        }
+    }
+}
+
+impl SpdmCodec for SpdmKeyPairInfoResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+        self.total_key_pairs.encode(bytes);
+        self.key_pair_id.encode(bytes);
+        self.capabilities.encode(bytes);
+        self.key_usage_capabilities.encode(bytes);
+        self.current_key_usage.encode(bytes);
+        self.asym_algo_capabilities.encode(bytes);
+        self.current_asym_algo.encode(bytes);
+        self.public_key_info_len.encode(bytes);
+        self.assoc_cert_slot_mask.encode(bytes);
+        for d in self.public_key_info[..(self.public_key_info_len as usize)].iter() {
+            d.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmKeyPairInfoResponsePayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+        let total_key_pairs = u8::read(r)?;
+        let key_pair_id = u8::read(r)?;
+        let capabilities = SpdmKeyPairCapabilities::read(r)?;
+        let key_usage_capabilities = SpdmKeyUsageBitMask::read(r)?;
+        let current_key_usage = SpdmKeyUsageBitMask::read(r)?;
+        let asym_algo_capabilities = SpdmKeyPairAsymAlgo::read(r)?;
+        let current_asym_algo = SpdmKeyPairAsymAlgo::read(r)?;
+        let public_key_info_len = u16::read(r)?;
+        if public_key_info_len as usize > SPDM_MAX_PUBLIC_KEY_INFO_LEN {
+            return None;
+        }
+        let assoc_cert_slot_mask = u8::read(r)?;
+        let mut public_key_info = [0u8; SPDM_MAX_PUBLIC_KEY_INFO_LEN];
+        for d in public_key_info[..(public_key_info_len as usize)].iter_mut() {
+            *d = u8::read(r)?;
+        }
+
+        Some(SpdmKeyPairInfoResponsePayload {
+            total_key_pairs,
+            key_pair_id,
+            capabilities,
+            key_usage_capabilities,
+            current_key_usage,
+            asym_algo_capabilities,
+            current_asym_algo,
+            assoc_cert_slot_mask,
+            public_key_info_len,
+            public_key_info,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSetKeyPairInfoRequestPayload {
+    pub operation: SpdmSetKeyPairInfoOperation,
+    pub key_pair_id: u8,
+    pub desired_key_usage: SpdmKeyUsageBitMask,
+    pub desired_asym_algo: SpdmKeyPairAsymAlgo,
+    pub desired_assoc_cert_slot_mask: u8,
+}
+
+impl SpdmCodec for SpdmSetKeyPairInfoRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        self.operation.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+        self.key_pair_id.encode(bytes);
+        if self.operation == SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoErase {
+            return;
+        }
+        0u8.encode(bytes); // reserved
+        self.desired_key_usage.encode(bytes);
+        self.desired_asym_algo.encode(bytes);
+        self.desired_assoc_cert_slot_mask.encode(bytes);
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSetKeyPairInfoRequestPayload> {
+        let operation = SpdmSetKeyPairInfoOperation::read(r)?; // param1
+        u8::read(r)?; // param2
+        let key_pair_id = u8::read(r)?;
+        if operation == SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoErase {
+            return Some(SpdmSetKeyPairInfoRequestPayload {
+                operation,
+                key_pair_id,
+                ..Default::default()
+            });
+        }
+        u8::read(r)?; // reserved
+        let desired_key_usage = SpdmKeyUsageBitMask::read(r)?;
+        let desired_asym_algo = SpdmKeyPairAsymAlgo::read(r)?;
+        let desired_assoc_cert_slot_mask = u8::read(r)?;
+
+        Some(SpdmSetKeyPairInfoRequestPayload {
+            operation,
+            key_pair_id,
+            desired_key_usage,
+            desired_asym_algo,
+            desired_assoc_cert_slot_mask,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSetKeyPairInfoAckResponsePayload {}
+
+impl SpdmCodec for SpdmSetKeyPairInfoAckResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        0u8.encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSetKeyPairInfoAckResponsePayload> {
+        u8::read(r)?; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmSetKeyPairInfoAckResponsePayload {})
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_get_key_pair_info_request_payload() {
+        let u8_slice = &mut [0u8; 4];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmGetKeyPairInfoRequestPayload { key_pair_id: 2 };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(4, reader.left());
+        let spdm_get_key_pair_info_request_payload =
+            SpdmGetKeyPairInfoRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_get_key_pair_info_request_payload.key_pair_id, 2);
+        assert_eq!(1, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_key_pair_info_response_payload() {
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmKeyPairInfoResponsePayload {
+            total_key_pairs: 3,
+            key_pair_id: 1,
+            capabilities: SpdmKeyPairCapabilities::GEN_KEY_CAP
+                | SpdmKeyPairCapabilities::CERT_ASSOC_CAP,
+            key_usage_capabilities: SpdmKeyUsageBitMask::KEY_EX_USE
+                | SpdmKeyUsageBitMask::CHALLENGE_USE,
+            current_key_usage: SpdmKeyUsageBitMask::KEY_EX_USE,
+            asym_algo_capabilities: SpdmKeyPairAsymAlgo::ECC256 | SpdmKeyPairAsymAlgo::ECC384,
+            current_asym_algo: SpdmKeyPairAsymAlgo::ECC384,
+            assoc_cert_slot_mask: 0x03,
+            public_key_info_len: 16,
+            ..Default::default()
+        };
+        value.public_key_info[..16].copy_from_slice(&[0xa5u8; 16]);
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(64, reader.left());
+        let spdm_key_pair_info_response_payload =
+            SpdmKeyPairInfoResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_key_pair_info_response_payload.total_key_pairs, 3);
+        assert_eq!(spdm_key_pair_info_response_payload.key_pair_id, 1);
+        assert_eq!(
+            spdm_key_pair_info_response_payload.capabilities,
+            SpdmKeyPairCapabilities::GEN_KEY_CAP | SpdmKeyPairCapabilities::CERT_ASSOC_CAP
+        );
+        assert_eq!(
+            spdm_key_pair_info_response_payload.current_asym_algo,
+            SpdmKeyPairAsymAlgo::ECC384
+        );
+        assert_eq!(
+            spdm_key_pair_info_response_payload.assoc_cert_slot_mask,
+            0x03
+        );
+        assert_eq!(spdm_key_pair_info_response_payload.public_key_info_len, 16);
+        for i in 0..16 {
+            assert_eq!(spdm_key_pair_info_response_payload.public_key_info[i], 0xa5);
+        }
+        assert_eq!(64 - 37, reader.left());
+    }
+    #[test]
+    fn test_case0_spdm_set_key_pair_info_request_payload() {
+        let u8_slice = &mut [0u8; 16];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSetKeyPairInfoRequestPayload {
+            operation: SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoChange,
+            key_pair_id: 1,
+            desired_key_usage: SpdmKeyUsageBitMask::MEASUREMENT_USE,
+            desired_asym_algo: SpdmKeyPairAsymAlgo::ECC256,
+            desired_assoc_cert_slot_mask: 0x80,
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(16, reader.left());
+        let spdm_set_key_pair_info_request_payload =
+            SpdmSetKeyPairInfoRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            spdm_set_key_pair_info_request_payload.operation,
+            SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoChange
+        );
+        assert_eq!(spdm_set_key_pair_info_request_payload.key_pair_id, 1);
+        assert_eq!(
+            spdm_set_key_pair_info_request_payload.desired_key_usage,
+            SpdmKeyUsageBitMask::MEASUREMENT_USE
+        );
+        assert_eq!(
+            spdm_set_key_pair_info_request_payload.desired_asym_algo,
+            SpdmKeyPairAsymAlgo::ECC256
+        );
+        assert_eq!(
+            spdm_set_key_pair_info_request_payload.desired_assoc_cert_slot_mask,
+            0x80
+        );
+        assert_eq!(5, reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_set_key_pair_info_request_payload() {
+        let u8_slice = &mut [0u8; 16];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSetKeyPairInfoRequestPayload {
+            operation: SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoErase,
+            key_pair_id: 4,
+            ..Default::default()
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        assert_eq!(3, writer.used());
+        let mut reader = Reader::init(u8_slice);
+        let spdm_set_key_pair_info_request_payload =
+            SpdmSetKeyPairInfoRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            spdm_set_key_pair_info_request_payload.operation,
+            SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoErase
+        );
+        assert_eq!(spdm_set_key_pair_info_request_payload.key_pair_id, 4);
+    }
+}
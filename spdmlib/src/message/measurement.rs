@@ -42,6 +42,8 @@ enum_builder! {
     EnumName: SpdmMeasurementOperation;
     EnumVal{
         SpdmMeasurementQueryTotalNumber => 0x0,
+        SpdmMeasurementRequestManifest => 0xFD, // spdm 1.2
+        SpdmMeasurementRequestDeviceMode => 0xFE, // spdm 1.2
         SpdmMeasurementRequestAll => 0xFF
     }
 }
@@ -269,6 +271,7 @@ mod tests {
                         index: 100u8,
                         measurement_specification: SpdmMeasurementSpecification::DMTF,
                         measurement_size: 67u16,
+                        tcb: false,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom,
                             representation:
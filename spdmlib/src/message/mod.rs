@@ -19,6 +19,7 @@ pub mod vendor;
 pub mod version;
 
 // SPDM 1.1
+pub mod encapsulated;
 pub mod end_session;
 pub mod finish;
 pub mod heartbeat;
@@ -28,20 +29,35 @@ pub mod psk_exchange;
 pub mod psk_finish;
 pub mod respond_if_ready;
 
+// SPDM 1.2
+pub mod chunk;
+pub mod set_certificate;
+
+// SPDM 1.3
+pub mod endpoint_info;
+pub mod event;
+pub mod key_pair_info;
+
 pub use algorithm::*;
 pub use capability::*;
 pub use certificate::*;
 pub use challenge::*;
+pub use chunk::*;
 pub use digest::*;
+pub use encapsulated::*;
 pub use end_session::*;
+pub use endpoint_info::*;
 pub use error::*;
+pub use event::*;
 pub use finish::*;
 pub use heartbeat::*;
 pub use key_exchange::*;
+pub use key_pair_info::*;
 pub use key_update::*;
 pub use measurement::*;
 pub use psk_exchange::*;
 pub use psk_finish::*;
+pub use set_certificate::*;
 pub use version::*;
 // Add new SPDM command here.
 pub use respond_if_ready::*;
@@ -68,9 +84,20 @@ enum_builder! {
         SpdmResponsePskFinishRsp => 0x67,
         SpdmResponseHeartbeatAck => 0x68,
         SpdmResponseKeyUpdateAck => 0x69,
-//        SpdmResponseEncapsulatedRequest => 0x6A,
-//        SpdmResponseEncapsulatedResponseAck => 0x6B,
+        SpdmResponseEncapsulatedRequest => 0x6A,
+        SpdmResponseEncapsulatedResponseAck => 0x6B,
         SpdmResponseEndSessionAck => 0x6C,
+        // 1.2 response
+        SpdmResponseSetCertificateRsp => 0x6E,
+        SpdmResponseChunkSendAck => 0x05,
+        SpdmResponseChunkResponse => 0x06,
+        // 1.3 response
+        SpdmResponseKeyPairInfo => 0x7C,
+        SpdmResponseSetKeyPairInfoAck => 0x7D,
+        SpdmResponseEndpointInfo => 0x07,
+        SpdmResponseSupportedEventTypes => 0x62,
+        SpdmResponseSubscribeEventTypesAck => 0x70,
+        SpdmResponseEventAck => 0x71,
 
         // 1.0 rerquest
         SpdmRequestGetDigests => 0x81,
@@ -89,9 +116,20 @@ enum_builder! {
         SpdmRequestPskFinish => 0xE7,
         SpdmRequestHeartbeat => 0xE8,
         SpdmRequestKeyUpdate => 0xE9,
-//        SpdmRequestGetEncapsulatedRequest => 0xEA,
-//        SpdmRequestDeliverEncapsulatedResponse => 0xEB,
-        SpdmRequestEndSession => 0xEC
+        SpdmRequestGetEncapsulatedRequest => 0xEA,
+        SpdmRequestDeliverEncapsulatedResponse => 0xEB,
+        SpdmRequestEndSession => 0xEC,
+        // 1.2 request
+        SpdmRequestSetCertificate => 0xEE,
+        SpdmRequestChunkSend => 0x85,
+        SpdmRequestChunkGet => 0x86,
+        // 1.3 request
+        SpdmRequestGetKeyPairInfo => 0xFC,
+        SpdmRequestSetKeyPairInfo => 0xFD,
+        SpdmRequestGetEndpointInfo => 0x87,
+        SpdmRequestGetSupportedEventTypes => 0xE2,
+        SpdmRequestSubscribeEventTypes => 0xF0,
+        SpdmRequestSendEvent => 0xF1
     }
 }
 
@@ -208,6 +246,39 @@ pub enum SpdmMessagePayload {
     SpdmEndSessionRequest(SpdmEndSessionRequestPayload),
     SpdmEndSessionResponse(SpdmEndSessionResponsePayload),
 
+    SpdmSetCertificateRequest(SpdmSetCertificateRequestPayload),
+    SpdmSetCertificateResponse(SpdmSetCertificateResponsePayload),
+
+    SpdmChunkSendRequest(SpdmChunkSendRequestPayload),
+    SpdmChunkSendAckResponse(SpdmChunkSendAckResponsePayload),
+
+    SpdmChunkGetRequest(SpdmChunkGetRequestPayload),
+    SpdmChunkResponse(SpdmChunkResponsePayload),
+
+    SpdmGetKeyPairInfoRequest(SpdmGetKeyPairInfoRequestPayload),
+    SpdmKeyPairInfoResponse(SpdmKeyPairInfoResponsePayload),
+
+    SpdmSetKeyPairInfoRequest(SpdmSetKeyPairInfoRequestPayload),
+    SpdmSetKeyPairInfoAckResponse(SpdmSetKeyPairInfoAckResponsePayload),
+
+    SpdmGetEndpointInfoRequest(SpdmGetEndpointInfoRequestPayload),
+    SpdmEndpointInfoResponse(SpdmEndpointInfoResponsePayload),
+
+    SpdmGetSupportedEventTypesRequest(SpdmGetSupportedEventTypesRequestPayload),
+    SpdmSupportedEventTypesResponse(SpdmSupportedEventTypesResponsePayload),
+
+    SpdmSubscribeEventTypesRequest(SpdmSubscribeEventTypesRequestPayload),
+    SpdmSubscribeEventTypesAckResponse(SpdmSubscribeEventTypesAckResponsePayload),
+
+    SpdmSendEventRequest(SpdmSendEventRequestPayload),
+    SpdmEventAckResponse(SpdmEventAckResponsePayload),
+
+    SpdmGetEncapsulatedRequestRequest(SpdmGetEncapsulatedRequestPayload),
+    SpdmEncapsulatedRequestResponse(SpdmEncapsulatedRequestPayload),
+
+    SpdmDeliverEncapsulatedResponseRequest(SpdmDeliverEncapsulatedResponsePayload),
+    SpdmEncapsulatedResponseAckResponse(SpdmEncapsulatedResponseAckPayload),
+
     // Add new SPDM command here.
     SpdmErrorResponse(SpdmErrorResponsePayload),
     SpdmVendorDefinedRequest(SpdmVendorDefinedRequestPayload),
@@ -376,6 +447,127 @@ impl SpdmMessage {
                 ))
             }
 
+            SpdmRequestResponseCode::SpdmResponseSetCertificateRsp => {
+                Some(SpdmMessagePayload::SpdmSetCertificateResponse(
+                    SpdmSetCertificateResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestSetCertificate => {
+                Some(SpdmMessagePayload::SpdmSetCertificateRequest(
+                    SpdmSetCertificateRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseChunkSendAck => {
+                Some(SpdmMessagePayload::SpdmChunkSendAckResponse(
+                    SpdmChunkSendAckResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestChunkSend => {
+                Some(SpdmMessagePayload::SpdmChunkSendRequest(
+                    SpdmChunkSendRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseChunkResponse => {
+                Some(SpdmMessagePayload::SpdmChunkResponse(
+                    SpdmChunkResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestChunkGet => {
+                Some(SpdmMessagePayload::SpdmChunkGetRequest(
+                    SpdmChunkGetRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseKeyPairInfo => {
+                Some(SpdmMessagePayload::SpdmKeyPairInfoResponse(
+                    SpdmKeyPairInfoResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo => {
+                Some(SpdmMessagePayload::SpdmGetKeyPairInfoRequest(
+                    SpdmGetKeyPairInfoRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck => {
+                Some(SpdmMessagePayload::SpdmSetKeyPairInfoAckResponse(
+                    SpdmSetKeyPairInfoAckResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo => {
+                Some(SpdmMessagePayload::SpdmSetKeyPairInfoRequest(
+                    SpdmSetKeyPairInfoRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseEndpointInfo => {
+                Some(SpdmMessagePayload::SpdmEndpointInfoResponse(
+                    SpdmEndpointInfoResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestGetEndpointInfo => {
+                Some(SpdmMessagePayload::SpdmGetEndpointInfoRequest(
+                    SpdmGetEndpointInfoRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseSupportedEventTypes => {
+                Some(SpdmMessagePayload::SpdmSupportedEventTypesResponse(
+                    SpdmSupportedEventTypesResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes => {
+                Some(SpdmMessagePayload::SpdmGetSupportedEventTypesRequest(
+                    SpdmGetSupportedEventTypesRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck => {
+                Some(SpdmMessagePayload::SpdmSubscribeEventTypesAckResponse(
+                    SpdmSubscribeEventTypesAckResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes => {
+                Some(SpdmMessagePayload::SpdmSubscribeEventTypesRequest(
+                    SpdmSubscribeEventTypesRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseEventAck => {
+                Some(SpdmMessagePayload::SpdmEventAckResponse(
+                    SpdmEventAckResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestSendEvent => {
+                Some(SpdmMessagePayload::SpdmSendEventRequest(
+                    SpdmSendEventRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest => {
+                Some(SpdmMessagePayload::SpdmEncapsulatedRequestResponse(
+                    SpdmEncapsulatedRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest => {
+                Some(SpdmMessagePayload::SpdmGetEncapsulatedRequestRequest(
+                    SpdmGetEncapsulatedRequestPayload::spdm_read(context, r)?,
+                ))
+            }
+
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck => {
+                Some(SpdmMessagePayload::SpdmEncapsulatedResponseAckResponse(
+                    SpdmEncapsulatedResponseAckPayload::spdm_read(context, r)?,
+                ))
+            }
+            SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse => {
+                Some(SpdmMessagePayload::SpdmDeliverEncapsulatedResponseRequest(
+                    SpdmDeliverEncapsulatedResponsePayload::spdm_read(context, r)?,
+                ))
+            }
+
             // Add new SPDM command here.
             SpdmRequestResponseCode::SpdmResponseError => {
                 Some(SpdmMessagePayload::SpdmErrorResponse(
@@ -495,6 +687,83 @@ impl SpdmCodec for SpdmMessage {
                 payload.spdm_encode(context, bytes);
             }
 
+            SpdmMessagePayload::SpdmSetCertificateRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmSetCertificateResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmChunkSendRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmChunkSendAckResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmChunkGetRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmChunkResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmGetKeyPairInfoRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmKeyPairInfoResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmSetKeyPairInfoRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmSetKeyPairInfoAckResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmGetEndpointInfoRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmEndpointInfoResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmGetSupportedEventTypesRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmSupportedEventTypesResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmSubscribeEventTypesRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmSubscribeEventTypesAckResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmSendEventRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmEventAckResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmGetEncapsulatedRequestRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmEncapsulatedRequestResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
+            SpdmMessagePayload::SpdmDeliverEncapsulatedResponseRequest(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+            SpdmMessagePayload::SpdmEncapsulatedResponseAckResponse(payload) => {
+                payload.spdm_encode(context, bytes);
+            }
+
             // Add new SPDM command here.
             SpdmMessagePayload::SpdmErrorResponse(payload) => {
                 payload.spdm_encode(context, bytes);
@@ -940,6 +1209,7 @@ mod tests {
                                 index: 100u8,
                                 measurement_specification: SpdmMeasurementSpecification::DMTF,
                                 measurement_size: 67u16,
+                                tcb: false,
                                 measurement: SpdmDmtfMeasurementStructure {
                                     r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom,
                                     representation:
@@ -1099,6 +1369,7 @@ mod tests {
         };
         create_spdm_context!(context);
         context.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096;
+        context.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_RSASSA_4096;
         context.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
         let spdm_message = new_spdm_message(value, context);
         assert_eq!(
@@ -1518,6 +1789,9 @@ mod tests {
                     },
                     SPDM_MAX_SLOT_NUMBER,
                 ),
+                key_pair_id: [0u8; SPDM_MAX_SLOT_NUMBER],
+                certificate_info: [SpdmCertificateModel::default(); SPDM_MAX_SLOT_NUMBER],
+                key_usage_mask: [SpdmKeyUsageBitMask::default(); SPDM_MAX_SLOT_NUMBER],
             }),
         };
         create_spdm_context!(context);
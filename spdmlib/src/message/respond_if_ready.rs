@@ -8,22 +8,28 @@ use crate::config;
 use codec::{Codec, Reader, Writer};
 
 #[derive(Debug, Clone, Default)]
-pub struct SpdmRespondIfReadyRequestPayload {}
+pub struct SpdmRespondIfReadyRequestPayload {
+    pub request_code: u8,
+    pub token: u8,
+}
 
 impl SpdmCodec for SpdmRespondIfReadyRequestPayload {
     fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
-        0u8.encode(bytes); // param1
-        0u8.encode(bytes); // param2
+        self.request_code.encode(bytes); // param1
+        self.token.encode(bytes); // param2
     }
 
     fn spdm_read(
         _context: &mut common::SpdmContext,
         r: &mut Reader,
     ) -> Option<SpdmRespondIfReadyRequestPayload> {
-        u8::read(r)?; // param1
-        u8::read(r)?; // param2
+        let request_code = u8::read(r)?; // param1
+        let token = u8::read(r)?; // param2
 
-        Some(SpdmRespondIfReadyRequestPayload {})
+        Some(SpdmRespondIfReadyRequestPayload {
+            request_code,
+            token,
+        })
     }
 }
 
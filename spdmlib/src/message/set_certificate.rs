@@ -0,0 +1,158 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common;
+use crate::common::spdm_codec::SpdmCodec;
+use crate::config;
+use crate::protocol::SpdmCertChainData;
+use codec::{Codec, Reader, Writer};
+
+pub const SPDM_SET_CERTIFICATE_SLOT_ID_MASK: u8 = 0x0F;
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSetCertificateRequestPayload {
+    pub slot_id: u8,
+    pub cert_chain: SpdmCertChainData,
+}
+
+impl SpdmCodec for SpdmSetCertificateRequestPayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        (self.slot_id & SPDM_SET_CERTIFICATE_SLOT_ID_MASK).encode(bytes); // param1
+        0u8.encode(bytes); // param2
+
+        for d in self.cert_chain.as_ref().iter() {
+            d.encode(bytes);
+        }
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSetCertificateRequestPayload> {
+        let slot_id = u8::read(r)? & SPDM_SET_CERTIFICATE_SLOT_ID_MASK; // param1
+        u8::read(r)?; // param2
+
+        // CertChain starts with its own total length (2 bytes) and 2 reserved bytes.
+        let length = u16::read(r)?;
+        if (length as usize) < 4 || length as usize > config::MAX_SPDM_CERT_CHAIN_DATA_SIZE {
+            return None;
+        }
+        let mut cert_chain = SpdmCertChainData {
+            data_size: length,
+            ..Default::default()
+        };
+        cert_chain.data[0] = (length & 0xFF) as u8;
+        cert_chain.data[1] = (length >> 8) as u8;
+        for d in cert_chain.data[2..(length as usize)].iter_mut() {
+            *d = u8::read(r)?;
+        }
+
+        Some(SpdmSetCertificateRequestPayload {
+            slot_id,
+            cert_chain,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmSetCertificateResponsePayload {
+    pub slot_id: u8,
+}
+
+impl SpdmCodec for SpdmSetCertificateResponsePayload {
+    fn spdm_encode(&self, _context: &mut common::SpdmContext, bytes: &mut Writer) {
+        (self.slot_id & SPDM_SET_CERTIFICATE_SLOT_ID_MASK).encode(bytes); // param1
+        0u8.encode(bytes); // param2
+    }
+
+    fn spdm_read(
+        _context: &mut common::SpdmContext,
+        r: &mut Reader,
+    ) -> Option<SpdmSetCertificateResponsePayload> {
+        let slot_id = u8::read(r)? & SPDM_SET_CERTIFICATE_SLOT_ID_MASK; // param1
+        u8::read(r)?; // param2
+
+        Some(SpdmSetCertificateResponsePayload { slot_id })
+    }
+}
+
+#[cfg(all(test,))]
+#[path = "mod_test.common.inc.rs"]
+mod testlib;
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::{SpdmConfigInfo, SpdmContext, SpdmProvisionInfo};
+    use testlib::{create_spdm_context, DeviceIO, TransportEncap};
+
+    #[test]
+    fn test_case0_spdm_set_certificate_request_payload() {
+        let u8_slice = &mut [0u8; 520];
+        let mut writer = Writer::init(u8_slice);
+        let mut value = SpdmSetCertificateRequestPayload {
+            slot_id: 3,
+            cert_chain: SpdmCertChainData {
+                data_size: 512,
+                data: [100u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+            },
+        };
+        value.cert_chain.data[0] = 0x00;
+        value.cert_chain.data[1] = 0x02;
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(520, reader.left());
+        let spdm_set_certificate_request_payload =
+            SpdmSetCertificateRequestPayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_set_certificate_request_payload.slot_id, 3);
+        assert_eq!(
+            spdm_set_certificate_request_payload.cert_chain.data_size,
+            512
+        );
+        for i in 2..512 {
+            assert_eq!(
+                spdm_set_certificate_request_payload.cert_chain.data[i],
+                100u8
+            );
+        }
+        assert_eq!(6, reader.left());
+    }
+    #[test]
+    fn test_case1_spdm_set_certificate_request_payload() {
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSetCertificateRequestPayload {
+            slot_id: 0,
+            cert_chain: SpdmCertChainData {
+                data_size: 2,
+                data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+            },
+        };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert!(SpdmSetCertificateRequestPayload::spdm_read(&mut context, &mut reader).is_none());
+    }
+    #[test]
+    fn test_case0_spdm_set_certificate_response_payload() {
+        let u8_slice = &mut [0u8; 4];
+        let mut writer = Writer::init(u8_slice);
+        let value = SpdmSetCertificateResponsePayload { slot_id: 7 };
+
+        create_spdm_context!(context);
+
+        value.spdm_encode(&mut context, &mut writer);
+        let mut reader = Reader::init(u8_slice);
+        assert_eq!(4, reader.left());
+        let spdm_set_certificate_response_payload =
+            SpdmSetCertificateResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(spdm_set_certificate_response_payload.slot_id, 7);
+        assert_eq!(2, reader.left());
+    }
+}
@@ -43,12 +43,23 @@ impl RegistryOrStandardsBodyID {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct VendorIDStruct {
     pub len: u8,
     pub vendor_id: [u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN],
 }
 
+impl PartialEq for VendorIDStruct {
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.len as usize;
+        len <= config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN
+            && self.len == other.len
+            && self.vendor_id[..len] == other.vendor_id[..len]
+    }
+}
+
+impl Eq for VendorIDStruct {}
+
 impl Codec for VendorIDStruct {
     fn encode(&self, bytes: &mut Writer) {
         self.len.encode(bytes);
@@ -59,6 +70,9 @@ impl Codec for VendorIDStruct {
 
     fn read(r: &mut Reader) -> Option<VendorIDStruct> {
         let len = u8::read(r)?;
+        if len as usize > config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN {
+            return None;
+        }
         let mut vendor_id = [0u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN];
         for d in vendor_id.iter_mut().take(len as usize) {
             *d = u8::read(r)?;
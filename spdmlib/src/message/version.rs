@@ -28,12 +28,31 @@ impl SpdmCodec for SpdmGetVersionRequestPayload {
     }
 }
 
+// VersionNumberEntry: `update` carries UpdateVersionNumber (bits 7:4) and
+// Alpha (bits 3:0), `version` carries MajorVersion and MinorVersion.
 #[derive(Debug, Clone, Default)]
 pub struct SpdmVersionStruct {
     pub update: u8,
     pub version: SpdmVersion,
 }
 
+impl SpdmVersionStruct {
+    pub fn new(version: SpdmVersion, update_version_number: u8, alpha: u8) -> Self {
+        SpdmVersionStruct {
+            update: (update_version_number << 4) | (alpha & 0xf),
+            version,
+        }
+    }
+
+    pub fn get_update_version_number(&self) -> u8 {
+        self.update >> 4
+    }
+
+    pub fn get_alpha(&self) -> u8 {
+        self.update & 0xf
+    }
+}
+
 impl Codec for SpdmVersionStruct {
     fn encode(&self, bytes: &mut Writer) {
         self.update.encode(bytes);
@@ -77,14 +96,15 @@ impl SpdmCodec for SpdmVersionResponsePayload {
         u8::read(r)?; // param2
 
         u8::read(r)?; // reserved
-        let version_number_entry_count = u8::read(r)?;
-
-        if version_number_entry_count < 1
-            || version_number_entry_count > config::MAX_SPDM_VERSION_COUNT as u8
-        {
+        let entry_count = u8::read(r)?;
+        if entry_count < 1 {
             return None;
         }
 
+        // The responder may list any number of entries. Versions this
+        // implementation does not know are skipped, and once the table is
+        // full the lowest stored version makes room for a higher one.
+        let mut version_number_entry_count = 0u8;
         let mut versions = gen_array_clone(
             SpdmVersionStruct {
                 update: 0,
@@ -92,12 +112,28 @@ impl SpdmCodec for SpdmVersionResponsePayload {
             },
             config::MAX_SPDM_VERSION_COUNT,
         );
-        for version in versions
-            .iter_mut()
-            .take(version_number_entry_count as usize)
-        {
-            *version = SpdmVersionStruct::read(r)?;
+        for _ in 0..entry_count {
+            let version = SpdmVersionStruct::read(r)?;
+            if let SpdmVersion::Unknown(_) = version.version {
+                continue;
+            }
+            if (version_number_entry_count as usize) < config::MAX_SPDM_VERSION_COUNT {
+                versions[version_number_entry_count as usize] = version;
+                version_number_entry_count += 1;
+                continue;
+            }
+            if let Some(lowest) = versions
+                .iter_mut()
+                .min_by_key(|v| v.version.get_u8())
+                .filter(|v| v.version.get_u8() < version.version.get_u8())
+            {
+                *lowest = version;
+            }
+        }
+        if version_number_entry_count == 0 {
+            return None;
         }
+
         Some(SpdmVersionResponsePayload {
             version_number_entry_count,
             versions,
@@ -188,4 +224,30 @@ mod tests {
         let mut reader = Reader::init(u8_slice);
         SpdmGetVersionRequestPayload::spdm_read(&mut context, &mut reader);
     }
+    #[test]
+    fn test_case1_spdm_version_response_payload() {
+        // Five entries: 1.0, 1.1, an unknown 2.0, 1.2 and 1.3.1 alpha 2.
+        let u8_slice = &[
+            0u8, 0, 0, 5, 0x00, 0x10, 0x00, 0x11, 0x00, 0x20, 0x00, 0x12, 0x12, 0x13,
+        ];
+
+        create_spdm_context!(context);
+
+        let mut reader = Reader::init(u8_slice);
+        let version_response =
+            SpdmVersionResponsePayload::spdm_read(&mut context, &mut reader).unwrap();
+        assert_eq!(0, reader.left());
+        assert_eq!(
+            version_response.version_number_entry_count as usize,
+            config::MAX_SPDM_VERSION_COUNT
+        );
+        let versions = &version_response.versions[..config::MAX_SPDM_VERSION_COUNT];
+        assert!(versions
+            .iter()
+            .all(|v| v.version != SpdmVersion::Unknown(0x20)));
+        let highest = versions.iter().max_by_key(|v| v.version.get_u8()).unwrap();
+        assert_eq!(highest.version, SpdmVersion::SpdmVersion13);
+        assert_eq!(highest.get_update_version_number(), 1);
+        assert_eq!(highest.get_alpha(), 2);
+    }
 }
@@ -29,6 +29,8 @@ pub const ECDSA_ECC_NIST_P256_KEY_SIZE: usize = 32 * 2;
 pub const ECDSA_ECC_NIST_P384_KEY_SIZE: usize = 48 * 2;
 pub const ECDSA_ECC_NIST_P521_KEY_SIZE: usize = 66 * 2;
 
+pub const EDDSA_ED25519_KEY_SIZE: usize = 32 * 2;
+
 pub const FFDHE_2048_KEY_SIZE: usize = 256;
 pub const FFDHE_3072_KEY_SIZE: usize = 384;
 pub const FFDHE_4096_KEY_SIZE: usize = 512;
@@ -37,6 +39,11 @@ pub const SECP_256_R1_KEY_SIZE: usize = 32 * 2;
 pub const SECP_384_R1_KEY_SIZE: usize = 48 * 2;
 pub const SECP_521_R1_KEY_SIZE: usize = 66 * 2;
 
+// experimental hybrid exchange, ECDHE public key followed by the ML-KEM-768
+// encapsulation key (request) or ciphertext (response)
+pub const SECP_384_R1_MLKEM_768_REQ_KEY_SIZE: usize = SECP_384_R1_KEY_SIZE + 1184;
+pub const SECP_384_R1_MLKEM_768_RSP_KEY_SIZE: usize = SECP_384_R1_KEY_SIZE + 1088;
+
 pub const AEAD_AES_128_GCM_KEY_SIZE: usize = 16;
 pub const AEAD_AES_256_GCM_KEY_SIZE: usize = 32;
 pub const AEAD_CHACHA20_POLY1305_KEY_SIZE: usize = 32;
@@ -57,9 +64,13 @@ pub const SPDM_NONCE_SIZE: usize = 32;
 pub const SPDM_RANDOM_SIZE: usize = 32;
 pub const SPDM_MAX_HASH_SIZE: usize = 64;
 pub const SPDM_MAX_ASYM_KEY_SIZE: usize = 512;
+#[cfg(not(feature = "spdm-pqc-hybrid"))]
 pub const SPDM_MAX_DHE_KEY_SIZE: usize = 512;
+#[cfg(feature = "spdm-pqc-hybrid")]
+pub const SPDM_MAX_DHE_KEY_SIZE: usize = SECP_384_R1_MLKEM_768_REQ_KEY_SIZE;
 pub const SPDM_MAX_AEAD_KEY_SIZE: usize = 32;
 pub const SPDM_MAX_AEAD_IV_SIZE: usize = 12;
+pub const SPDM_MAX_AEAD_TAG_SIZE: usize = 16;
 
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SpdmDigestStruct {
@@ -158,6 +169,20 @@ impl SpdmMeasurementHashAlgo {
             }
         }
     }
+
+    /// The hash algorithm measurement digests are computed with,
+    /// None for RAW_BIT_STREAM.
+    pub fn to_base_hash_algo(&self) -> Option<SpdmBaseHashAlgo> {
+        match *self {
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA_256 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA_256),
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA_384 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA_384),
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA_512 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA_512),
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA3_256 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA3_256),
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA3_384 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA3_384),
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA3_512 => Some(SpdmBaseHashAlgo::TPM_ALG_SHA3_512),
+            _ => None,
+        }
+    }
 }
 impl Codec for SpdmMeasurementHashAlgo {
     fn encode(&self, bytes: &mut Writer) {
@@ -183,12 +208,14 @@ bitflags! {
         const TPM_ALG_RSAPSS_4096 = 0b0100_0000;
         const TPM_ALG_ECDSA_ECC_NIST_P384 = 0b1000_0000;
         const TPM_ALG_ECDSA_ECC_NIST_P521 = 0b0000_0001_0000_0000;
+        const TPM_ALG_EDDSA_ED25519 = 0b0000_0100_0000_0000;
     }
 }
 
 impl SpdmBaseAsymAlgo {
     pub fn prioritize(&mut self, peer: SpdmBaseAsymAlgo) {
         let prio_table = [
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521,
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256,
             SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096,
@@ -197,6 +224,7 @@ impl SpdmBaseAsymAlgo {
             SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096,
             SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072,
             SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048,
+            SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519,
         ];
 
         *self &= peer;
@@ -218,6 +246,7 @@ impl SpdmBaseAsymAlgo {
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 => ECDSA_ECC_NIST_P256_KEY_SIZE as u16,
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => ECDSA_ECC_NIST_P384_KEY_SIZE as u16,
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521 => ECDSA_ECC_NIST_P521_KEY_SIZE as u16,
+            SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519 => EDDSA_ED25519_KEY_SIZE as u16,
             _ => {
                 panic!("invalid AsymAlgo");
             }
@@ -344,12 +373,18 @@ bitflags! {
         const SECP_256_R1 = 0b0000_1000;
         const SECP_384_R1 = 0b0001_0000;
         const SECP_521_R1 = 0b0010_0000;
+        // experimental, not assigned by DMTF
+        #[cfg(feature = "spdm-pqc-hybrid")]
+        const SECP_384_R1_MLKEM_768 = 0b1000_0000_0000_0000;
     }
 }
 
 impl SpdmDheAlgo {
     pub fn prioritize(&mut self, peer: SpdmDheAlgo) {
         let prio_table = [
+            #[cfg(feature = "spdm-pqc-hybrid")]
+            SpdmDheAlgo::SECP_384_R1_MLKEM_768,
+            SpdmDheAlgo::SECP_521_R1,
             SpdmDheAlgo::SECP_384_R1,
             SpdmDheAlgo::SECP_256_R1,
             SpdmDheAlgo::FFDHE_4096,
@@ -373,11 +408,21 @@ impl SpdmDheAlgo {
             SpdmDheAlgo::SECP_256_R1 => SECP_256_R1_KEY_SIZE as u16,
             SpdmDheAlgo::SECP_384_R1 => SECP_384_R1_KEY_SIZE as u16,
             SpdmDheAlgo::SECP_521_R1 => SECP_521_R1_KEY_SIZE as u16,
+            #[cfg(feature = "spdm-pqc-hybrid")]
+            SpdmDheAlgo::SECP_384_R1_MLKEM_768 => SECP_384_R1_MLKEM_768_REQ_KEY_SIZE as u16,
             _ => {
                 panic!("invalid DheAlgo");
             }
         }
     }
+    /// Size of the KEY_EXCHANGE_RSP ExchangeData, get_size() is the KEY_EXCHANGE one.
+    pub fn get_rsp_size(&self) -> u16 {
+        match *self {
+            #[cfg(feature = "spdm-pqc-hybrid")]
+            SpdmDheAlgo::SECP_384_R1_MLKEM_768 => SECP_384_R1_MLKEM_768_RSP_KEY_SIZE as u16,
+            _ => self.get_size(),
+        }
+    }
 }
 
 impl Codec for SpdmDheAlgo {
@@ -473,6 +518,7 @@ bitflags! {
         const TPM_ALG_RSAPSS_4096 = 0b0100_0000;
         const TPM_ALG_ECDSA_ECC_NIST_P384 = 0b1000_0000;
         const TPM_ALG_ECDSA_ECC_NIST_P521 = 0b0000_0001_0000_0000;
+        const TPM_ALG_EDDSA_ED25519 = 0b0000_0100_0000_0000;
     }
 }
 
@@ -485,8 +531,10 @@ impl SpdmReqAsymAlgo {
             SpdmReqAsymAlgo::TPM_ALG_RSASSA_4096,
             SpdmReqAsymAlgo::TPM_ALG_RSASSA_3072,
             SpdmReqAsymAlgo::TPM_ALG_RSASSA_2048,
+            SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521,
             SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
             SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256,
+            SpdmReqAsymAlgo::TPM_ALG_EDDSA_ED25519,
         ];
 
         *self &= peer;
@@ -508,6 +556,7 @@ impl SpdmReqAsymAlgo {
             SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 => ECDSA_ECC_NIST_P256_KEY_SIZE as u16,
             SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => ECDSA_ECC_NIST_P384_KEY_SIZE as u16,
             SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521 => ECDSA_ECC_NIST_P521_KEY_SIZE as u16,
+            SpdmReqAsymAlgo::TPM_ALG_EDDSA_ED25519 => EDDSA_ED25519_KEY_SIZE as u16,
             _ => {
                 panic!("invalid ReqAsymAlgo");
             }
@@ -834,6 +883,12 @@ pub struct SpdmDmtfMeasurementStructure {
     pub value_size: u16,
     pub value: [u8; config::MAX_SPDM_MEASUREMENT_VALUE_LEN],
 }
+impl SpdmDmtfMeasurementStructure {
+    pub fn is_raw_bit_stream(&self) -> bool {
+        self.representation == SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementRawBit
+    }
+}
+
 impl Default for SpdmDmtfMeasurementStructure {
     fn default() -> SpdmDmtfMeasurementStructure {
         SpdmDmtfMeasurementStructure {
@@ -851,6 +906,9 @@ pub struct SpdmMeasurementBlockStructure {
     pub measurement_specification: SpdmMeasurementSpecification,
     pub measurement_size: u16,
     pub measurement: SpdmDmtfMeasurementStructure,
+    /// Set by the measurement provider on blocks of the TCB, what the TCB
+    /// measurement summary hash covers. Not carried on the wire.
+    pub tcb: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -897,6 +955,19 @@ impl From<BytesMut> for SpdmDheExchangeStruct {
     }
 }
 
+impl SpdmDheExchangeStruct {
+    pub fn read_with_size(r: &mut Reader, data_size: u16) -> Option<SpdmDheExchangeStruct> {
+        if data_size as usize > SPDM_MAX_DHE_KEY_SIZE {
+            return None;
+        }
+        let mut data = [0u8; SPDM_MAX_DHE_KEY_SIZE];
+        for d in data.iter_mut().take(data_size as usize) {
+            *d = u8::read(r)?;
+        }
+        Some(SpdmDheExchangeStruct { data_size, data })
+    }
+}
+
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SpdmDheFinalKeyStruct {
     pub data_size: u16,
@@ -1063,6 +1134,21 @@ mod tests {
         assert_eq!(0, reader.left());
     }
     #[test]
+    fn test_case1_spdm_measurement_hash_algo() {
+        assert_eq!(
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA_384.to_base_hash_algo(),
+            Some(SpdmBaseHashAlgo::TPM_ALG_SHA_384)
+        );
+        assert_eq!(
+            SpdmMeasurementHashAlgo::TPM_ALG_SHA3_256.to_base_hash_algo(),
+            Some(SpdmBaseHashAlgo::TPM_ALG_SHA3_256)
+        );
+        assert_eq!(
+            SpdmMeasurementHashAlgo::RAW_BIT_STREAM.to_base_hash_algo(),
+            None
+        );
+    }
+    #[test]
     fn test_case0_spdm_base_asym_algo() {
         let u8_slice = &mut [0u8; 4];
         let mut writer = Writer::init(u8_slice);
@@ -1156,6 +1242,15 @@ mod tests {
         assert_eq!(2, reader.left());
     }
     #[test]
+    fn test_case5_spdm_aead_algo() {
+        let mut value = SpdmAeadAlgo::AES_128_GCM | SpdmAeadAlgo::CHACHA20_POLY1305;
+        value.prioritize(SpdmAeadAlgo::CHACHA20_POLY1305);
+        assert_eq!(value, SpdmAeadAlgo::CHACHA20_POLY1305);
+        assert_eq!(value.get_key_size(), AEAD_CHACHA20_POLY1305_KEY_SIZE as u16);
+        assert_eq!(value.get_iv_size(), AEAD_CHACHA20_POLY1305_IV_SIZE as u16);
+        assert_eq!(value.get_tag_size(), AEAD_CHACHA20_POLY1305_TAG_SIZE as u16);
+    }
+    #[test]
     fn test_case0_spdm_req_asym_algo() {
         let u8_slice = &mut [0u8; 4];
         let mut writer = Writer::init(u8_slice);
@@ -1170,6 +1265,31 @@ mod tests {
         assert_eq!(2, reader.left());
     }
     #[test]
+    fn test_case2_spdm_ecc_p521_prioritize() {
+        let mut value = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+            | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521;
+        value.prioritize(
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
+                | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+                | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521,
+        );
+        assert_eq!(value, SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521);
+        assert_eq!(value.get_size(), ECDSA_ECC_NIST_P521_KEY_SIZE as u16);
+
+        let mut value = SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+            | SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521;
+        value.prioritize(
+            SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+                | SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521,
+        );
+        assert_eq!(value, SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521);
+
+        let mut value = SpdmDheAlgo::SECP_384_R1 | SpdmDheAlgo::SECP_521_R1;
+        value.prioritize(SpdmDheAlgo::SECP_384_R1 | SpdmDheAlgo::SECP_521_R1);
+        assert_eq!(value, SpdmDheAlgo::SECP_521_R1);
+        assert_eq!(value.get_size(), SECP_521_R1_KEY_SIZE as u16);
+    }
+    #[test]
     fn test_case0_spdm_key_schedule_algo() {
         let u8_slice = &mut [0u8; 4];
         let mut writer = Writer::init(u8_slice);
@@ -1383,6 +1503,9 @@ mod tests {
 
         value = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521;
         assert_eq!(value.get_size(), ECDSA_ECC_NIST_P521_KEY_SIZE as u16);
+
+        value = SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519;
+        assert_eq!(value.get_size(), EDDSA_ED25519_KEY_SIZE as u16);
         value = SpdmBaseAsymAlgo::empty();
         value.get_size();
     }
@@ -16,6 +16,7 @@ bitflags! {
         const KEY_UPD_CAP = 0b0100_0000_0000_0000;
         const HANDSHAKE_IN_THE_CLEAR_CAP = 0b1000_0000_0000_0000;
         const PUB_KEY_ID_CAP = 0b0000_0001_0000_0000_0000_0000;
+        const CHUNK_CAP = 0b0000_0010_0000_0000_0000_0000;
     }
 }
 
@@ -60,6 +61,15 @@ bitflags! {
         const SET_CERT_CAP = 0b0000_1000_0000_0000_0000_0000;
         const CSR_CAP = 0b0001_0000_0000_0000_0000_0000;
         const CERT_INSTALL_RESET_CAP = 0b0010_0000_0000_0000_0000_0000;
+        const EP_INFO_CAP_NO_SIG = 0b0100_0000_0000_0000_0000_0000;
+        const EP_INFO_CAP_SIG = 0b1000_0000_0000_0000_0000_0000;
+        const EP_INFO_CAP_MASK = Self::EP_INFO_CAP_NO_SIG.bits | Self::EP_INFO_CAP_SIG.bits;
+        const EVENT_CAP = 0b0000_0010_0000_0000_0000_0000_0000_0000;
+        const MULTI_KEY_CAP_ONLY = 0b0000_0100_0000_0000_0000_0000_0000_0000;
+        const MULTI_KEY_CAP_CONN_SEL = 0b0000_1000_0000_0000_0000_0000_0000_0000;
+        const MULTI_KEY_CAP_MASK = Self::MULTI_KEY_CAP_ONLY.bits | Self::MULTI_KEY_CAP_CONN_SEL.bits;
+        const GET_KEY_PAIR_INFO_CAP = 0b0001_0000_0000_0000_0000_0000_0000_0000;
+        const SET_KEY_PAIR_INFO_CAP = 0b0010_0000_0000_0000_0000_0000_0000_0000;
     }
 }
 
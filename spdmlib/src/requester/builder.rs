@@ -0,0 +1,112 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! A RequesterContext from a configuration checked before any message is
+//! sent, see SpdmConfigInfo::check_requester.
+//!
+//! ```ignore
+//! let mut requester = RequesterBuilder::new()
+//!     .config_info(config_info)
+//!     .provision_info(provision_info)
+//!     .psk_store(&psk_table)
+//!     .build(&mut device_io, &mut transport_encap)?;
+//! requester.init_connection()?;
+//! ```
+
+use crate::common::{
+    SpdmConfigError, SpdmConfigInfo, SpdmDeviceIo, SpdmProvisionInfo, SpdmPskStore,
+    SpdmSessionObserver, SpdmTimeProvider, SpdmTracer, SpdmTransportEncap,
+};
+use crate::crypto::SpdmRng;
+use crate::protocol::SpdmRequestCapabilityFlags;
+use crate::requester::RequesterContext;
+
+#[derive(Default)]
+pub struct RequesterBuilder<'a> {
+    config_info: SpdmConfigInfo,
+    provision_info: SpdmProvisionInfo,
+    psk_store: Option<&'a dyn SpdmPskStore>,
+    rng: Option<&'a mut dyn SpdmRng>,
+    tracer: Option<&'a dyn SpdmTracer>,
+    session_observer: Option<&'a dyn SpdmSessionObserver>,
+    time_provider: Option<&'a dyn SpdmTimeProvider>,
+}
+
+impl<'a> RequesterBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config_info(mut self, config_info: SpdmConfigInfo) -> Self {
+        self.config_info = config_info;
+        self
+    }
+
+    pub fn provision_info(mut self, provision_info: SpdmProvisionInfo) -> Self {
+        self.provision_info = provision_info;
+        self
+    }
+
+    pub fn capabilities(mut self, req_capabilities: SpdmRequestCapabilityFlags) -> Self {
+        self.config_info.req_capabilities = req_capabilities;
+        self
+    }
+
+    /// Required with PSK_CAP.
+    pub fn psk_store(mut self, psk_store: &'a dyn SpdmPskStore) -> Self {
+        self.psk_store = Some(psk_store);
+        self
+    }
+
+    pub fn rng(mut self, rng: &'a mut dyn SpdmRng) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    pub fn tracer(mut self, tracer: &'a dyn SpdmTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    pub fn session_observer(mut self, session_observer: &'a dyn SpdmSessionObserver) -> Self {
+        self.session_observer = Some(session_observer);
+        self
+    }
+
+    pub fn time_provider(mut self, time_provider: &'a dyn SpdmTimeProvider) -> Self {
+        self.time_provider = Some(time_provider);
+        self
+    }
+
+    pub fn build(
+        self,
+        device_io: &'a mut dyn SpdmDeviceIo,
+        transport_encap: &'a mut dyn SpdmTransportEncap,
+    ) -> Result<RequesterContext<'a>, SpdmConfigError> {
+        self.config_info
+            .check_requester(&self.provision_info, self.psk_store.is_some())?;
+        let mut requester = RequesterContext::new(
+            device_io,
+            transport_encap,
+            self.config_info,
+            self.provision_info,
+        );
+        if let Some(psk_store) = self.psk_store {
+            requester.register_psk_store(psk_store);
+        }
+        if let Some(rng) = self.rng {
+            requester.register_rng(rng);
+        }
+        if let Some(tracer) = self.tracer {
+            requester.register_tracer(tracer);
+        }
+        if let Some(session_observer) = self.session_observer {
+            requester.register_session_observer(session_observer);
+        }
+        if let Some(time_provider) = self.time_provider {
+            requester.register_time_provider(time_provider);
+        }
+        Ok(requester)
+    }
+}
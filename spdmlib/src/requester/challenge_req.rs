@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::crypto;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
@@ -13,6 +14,36 @@ impl<'a> RequesterContext<'a> {
         &mut self,
         slot_id: u8,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    ) -> SpdmResult {
+        self.send_receive_spdm_challenge_ex(slot_id, measurement_summary_hash_type, None)
+    }
+
+    /// Same as send_receive_spdm_challenge, but also verify the measurement
+    /// summary hash against measurement_record collected by GET_MEASUREMENTS.
+    /// For the TCB type, the caller sets the tcb bit of the TCB blocks.
+    pub fn send_receive_spdm_challenge_with_measurements(
+        &mut self,
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        measurement_record: &SpdmMeasurementRecordStructure,
+    ) -> SpdmResult {
+        if measurement_summary_hash_type
+            == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone
+        {
+            return spdm_result_err!(EINVAL);
+        }
+        self.send_receive_spdm_challenge_ex(
+            slot_id,
+            measurement_summary_hash_type,
+            Some(measurement_record),
+        )
+    }
+
+    fn send_receive_spdm_challenge_ex(
+        &mut self,
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        measurement_record: Option<&SpdmMeasurementRecordStructure>,
     ) -> SpdmResult {
         info!("send spdm challenge\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
@@ -24,12 +55,18 @@ impl<'a> RequesterContext<'a> {
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.receive_message(&mut receive_buffer, true)?;
         self.handle_spdm_challenge_response(
-            0, // NULL
+            SpdmSessionId::default(), // NULL
             slot_id,
             measurement_summary_hash_type,
+            measurement_record,
             &send_buffer[..send_used],
             &receive_buffer[..used],
-        )
+        )?;
+
+        if self.common.runtime_info.basic_mut_auth_requested {
+            self.send_receive_spdm_basic_mut_auth()?;
+        }
+        Ok(())
     }
 
     pub fn encode_spdm_challenge(
@@ -38,10 +75,15 @@ impl<'a> RequesterContext<'a> {
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
         buf: &mut [u8],
     ) -> SpdmResult<usize> {
+        if !self.common.is_peer_slot_provisioned(slot_id) {
+            error!("!!! slot {} is not in the peer slot mask !!!\n", slot_id);
+            return spdm_result_err!(EINVAL);
+        }
+
         let mut writer = Writer::init(buf);
 
         let mut nonce = [0u8; SPDM_NONCE_SIZE];
-        crypto::rand::get_random(&mut nonce)?;
+        self.common.get_random(&mut nonce)?;
 
         let request = SpdmMessage {
             header: SpdmMessageHeader {
@@ -60,9 +102,10 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_challenge_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         slot_id: u8,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        measurement_record: Option<&SpdmMeasurementRecordStructure>,
         send_buffer: &[u8],
         receive_buffer: &[u8],
     ) -> SpdmResult {
@@ -85,6 +128,10 @@ impl<'a> RequesterContext<'a> {
                     let used = reader.used();
                     if let Some(challenge_auth) = challenge_auth {
                         debug!("!!! challenge_auth : {:02x?}\n", challenge_auth);
+                        if challenge_auth.slot_id != slot_id {
+                            error!("slot id is not match between requester and responder!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
 
                         // verify signature
                         let base_asym_size =
@@ -114,20 +161,60 @@ impl<'a> RequesterContext<'a> {
                             );
                         }
 
+                        if !self.common.is_key_usage_allowed(
+                            &self.common.peer_info.peer_key_usage_mask,
+                            slot_id,
+                            SpdmKeyUsageBitMask::CHALLENGE_USE,
+                        ) {
+                            error!("slot {} key is not for challenge\n", slot_id);
+                            return spdm_result_err!(EFAULT);
+                        }
+
                         if self
                             .verify_challenge_auth_signature(slot_id, &challenge_auth.signature)
                             .is_err()
                         {
                             error!("verify_challenge_auth_signature fail");
-                            return spdm_result_err!(EFAULT);
+                            return spdm_result_err!(VerificationFailed);
                         } else {
                             info!("verify_challenge_auth_signature pass");
                         }
 
+                        self.common.runtime_info.basic_mut_auth_requested = false;
+                        if challenge_auth
+                            .challenge_auth_attribute
+                            .contains(SpdmChallengeAuthAttribute::BASIC_MUT_AUTH_REQ)
+                        {
+                            if !self.common.negotiate_info.req_capabilities_sel.contains(
+                                SpdmRequestCapabilityFlags::MUT_AUTH_CAP
+                                    | SpdmRequestCapabilityFlags::ENCAP_CAP,
+                            ) || self.common.negotiate_info.spdm_version_sel.get_u8()
+                                > SpdmVersion::SpdmVersion11.get_u8()
+                            {
+                                error!("basic mutual authentication is not supported\n");
+                                return spdm_result_err!(EFAULT);
+                            }
+                            self.common.runtime_info.basic_mut_auth_requested = true;
+                        }
+
+                        if let Some(measurement_record) = measurement_record {
+                            let expected = spdm_calc_measurement_summary_hash(
+                                measurement_record,
+                                self.common.negotiate_info.base_hash_sel,
+                                measurement_summary_hash_type,
+                            )
+                            .ok_or_else(|| spdm_err!(EFAULT))?;
+                            if expected.as_ref() != challenge_auth.measurement_summary_hash.as_ref()
+                            {
+                                error!("measurement summary hash mismatch\n");
+                                return spdm_result_err!(VerificationFailed);
+                            }
+                        }
+
                         Ok(())
                     } else {
                         error!("!!! challenge_auth : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -145,16 +232,17 @@ impl<'a> RequesterContext<'a> {
                                 session_id,
                                 slot_id,
                                 measurement_summary_hash_type,
+                                measurement_record,
                                 send_buffer,
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 
@@ -275,7 +363,7 @@ mod tests_requester {
         );
 
         responder.common.reset_runtime_info();
-        responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -318,6 +406,7 @@ mod tests_requester {
             .as_mut()
             .unwrap()
             .cert_chain = REQ_CERT_CHAIN_DATA;
+        requester.common.peer_info.peer_slot_mask = 0x1;
         requester.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
         requester.common.runtime_info.message_m = Some(
             crypto::hash::hash_ctx_init(requester.common.negotiate_info.base_hash_sel).unwrap(),
@@ -331,4 +420,36 @@ mod tests_requester {
             .is_ok();
         assert!(status);
     }
+
+    #[test]
+    fn test_case1_encode_spdm_challenge_rng() {
+        let (req_config_info, req_provision_info) = create_info();
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_requester = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut rng = CountingRng(0x10);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_rng(&mut rng);
+        requester.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = requester
+            .encode_spdm_challenge(
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                &mut send_buffer,
+            )
+            .unwrap();
+        assert_eq!(used, 4 + SPDM_NONCE_SIZE);
+        for (i, byte) in send_buffer[4..used].iter().enumerate() {
+            assert_eq!(*byte, 0x10 + i as u8);
+        }
+    }
 }
@@ -0,0 +1,308 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionId;
+use crate::common::SpdmCodec;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    /// Send request, larger than the responder takes in one transfer, in
+    /// CHUNK_SEND chunks. The response to it, out of the last
+    /// CHUNK_SEND_ACK, is the next one received.
+    pub(crate) fn send_spdm_chunks(
+        &mut self,
+        session_id: Option<u32>,
+        request: &[u8],
+    ) -> SpdmResult {
+        info!("send large request of {} bytes\n", request.len());
+        let max_transfer_size = self.get_max_transfer_size(session_id.is_some());
+        let handle = self.chunk_handle;
+        self.chunk_handle = self.chunk_handle.wrapping_add(1);
+
+        let mut offset = 0;
+        let mut chunk_seq_num = 0u16;
+        loop {
+            let header_size = if chunk_seq_num == 0 {
+                SPDM_FIRST_CHUNK_HEADER_SIZE
+            } else {
+                SPDM_CHUNK_HEADER_SIZE
+            };
+            let chunk_size =
+                (request.len() - offset).min(max_transfer_size.saturating_sub(header_size));
+            if chunk_size == 0 {
+                return spdm_result_err!(ENOMEM);
+            }
+            let last = offset + chunk_size == request.len();
+
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let used = self.encode_spdm_chunk_send(
+                handle,
+                chunk_seq_num,
+                request.len(),
+                &request[offset..(offset + chunk_size)],
+                last,
+                &mut send_buffer,
+            );
+            match session_id {
+                Some(session_id) => {
+                    self.send_secured_message(session_id, &send_buffer[..used], false)?
+                }
+                None => self.send_message(&send_buffer[..used])?,
+            }
+
+            let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let used = self.receive_spdm_message(session_id, &mut receive_buffer, last)?;
+            let chunk_send_ack =
+                self.handle_spdm_chunk_send_ack(session_id, &receive_buffer[..used])?;
+            if chunk_send_ack.handle != handle || chunk_send_ack.chunk_seq_num != chunk_seq_num {
+                error!("!!! chunk_send_ack : out of sequence !!!\n");
+                return spdm_result_err!(EFAULT);
+            }
+
+            // the response to the large request comes with the last chunk,
+            // or earlier for an error the responder already found
+            if last
+                || chunk_send_ack
+                    .attributes
+                    .contains(SpdmChunkSendAckAttributes::EARLY_ERROR_DETECTED)
+            {
+                let response_size = chunk_send_ack.response_size as usize;
+                if response_size == 0 {
+                    error!("!!! chunk_send_ack : no response !!!\n");
+                    return spdm_result_err!(EFAULT);
+                }
+                self.chunk_response[..response_size]
+                    .copy_from_slice(&chunk_send_ack.response[..response_size]);
+                self.chunk_response_size = response_size;
+                break;
+            }
+            offset += chunk_size;
+            chunk_seq_num = chunk_seq_num.wrapping_add(1);
+        }
+
+        self.last_request_code = request[1];
+        // the large request is not sent again after a DecryptError
+        self.last_secured_request_size = 0;
+        Ok(())
+    }
+
+    pub fn encode_spdm_chunk_send(
+        &mut self,
+        handle: u8,
+        chunk_seq_num: u16,
+        large_message_size: usize,
+        data: &[u8],
+        last: bool,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut chunk = SpdmChunk {
+            attributes: SpdmChunkAttributes::empty(),
+            handle,
+            chunk_seq_num,
+            large_message_size: large_message_size as u32,
+            chunk_size: data.len() as u32,
+            ..Default::default()
+        };
+        if last {
+            chunk.attributes = SpdmChunkAttributes::LAST_CHUNK;
+        }
+        chunk.chunk[..data.len()].copy_from_slice(data);
+
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestChunkSend,
+            },
+            payload: SpdmMessagePayload::SpdmChunkSendRequest(SpdmChunkSendRequestPayload {
+                chunk,
+            }),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    fn handle_spdm_chunk_send_ack(
+        &mut self,
+        session_id: Option<u32>,
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmChunkSendAckResponsePayload> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseChunkSendAck => {
+                    SpdmChunkSendAckResponsePayload::spdm_read(&mut self.common, &mut reader)
+                        .ok_or_else(|| spdm_err!(Codec))
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    self.spdm_handle_error_response_main(
+                        session_id.map(SpdmSessionId::from),
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestChunkSend,
+                        SpdmRequestResponseCode::SpdmResponseChunkSendAck,
+                    )?;
+                    spdm_result_err!(EFAULT)
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    // The response to the latest large request, taken once.
+    pub(super) fn take_chunk_response(&mut self, receive_buffer: &mut [u8]) -> SpdmResult<usize> {
+        let used = self.chunk_response_size;
+        self.chunk_response_size = 0;
+        if used > receive_buffer.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        receive_buffer[..used].copy_from_slice(&self.chunk_response[..used]);
+        Ok(used)
+    }
+
+    /// If the response in receive_buffer is ERROR(LargeResponse), get
+    /// the large response with CHUNK_GET, in session session_id if the
+    /// request was sent in one, into receive_buffer. Returns the size of
+    /// the response finally received. The async and poll requesters do not
+    /// chunk.
+    pub(super) fn resolve_large_response(
+        &mut self,
+        session_id: Option<u32>,
+        receive_buffer: &mut [u8],
+        used: usize,
+    ) -> SpdmResult<usize> {
+        if !self.common.is_chunk_cap_negotiated() || self.common.deferred_io.is_some() {
+            return Ok(used);
+        }
+        let handle = match get_large_response(&receive_buffer[..used]) {
+            Some(handle) => handle,
+            None => return Ok(used),
+        };
+        let request_code = self.last_request_code;
+        let max_size = self
+            .common
+            .get_req_max_message_size()
+            .unwrap_or(0)
+            .min(receive_buffer.len());
+
+        let mut large_message_size = 0;
+        let mut large_message_used = 0;
+        let mut chunk_seq_num = 0u16;
+        loop {
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let send_used = self.encode_spdm_chunk_get(handle, chunk_seq_num, &mut send_buffer);
+            match session_id {
+                Some(session_id) => {
+                    self.send_secured_message(session_id, &send_buffer[..send_used], false)?
+                }
+                None => self.send_message(&send_buffer[..send_used])?,
+            }
+
+            let mut chunk_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let chunk_used = self.receive_spdm_message(session_id, &mut chunk_buffer, false)?;
+            let chunk = self.handle_spdm_chunk_response(session_id, &chunk_buffer[..chunk_used])?;
+            if chunk.handle != handle || chunk.chunk_seq_num != chunk_seq_num {
+                error!("!!! chunk_response : out of sequence !!!\n");
+                return spdm_result_err!(EFAULT);
+            }
+            if chunk_seq_num == 0 {
+                large_message_size = chunk.large_message_size as usize;
+                if large_message_size > max_size {
+                    error!(
+                        "!!! large response size {} exceeds requester limit {} !!!\n",
+                        large_message_size, max_size
+                    );
+                    return spdm_result_err!(ENOMEM);
+                }
+            }
+
+            // the last chunk completes the large response, no other does
+            let chunk_size = chunk.chunk_size as usize;
+            let next_used = large_message_used + chunk_size;
+            if next_used > large_message_size
+                || chunk.is_last() != (next_used == large_message_size)
+            {
+                error!("!!! chunk_response : chunks do not add up !!!\n");
+                return spdm_result_err!(EFAULT);
+            }
+            receive_buffer[large_message_used..next_used].copy_from_slice(chunk.as_slice());
+            large_message_used = next_used;
+            if chunk.is_last() {
+                break;
+            }
+            chunk_seq_num = chunk_seq_num.wrapping_add(1);
+        }
+
+        // the response is to the request, not to CHUNK_GET
+        self.last_request_code = request_code;
+        Ok(large_message_used)
+    }
+
+    pub fn encode_spdm_chunk_get(
+        &mut self,
+        handle: u8,
+        chunk_seq_num: u16,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestChunkGet,
+            },
+            payload: SpdmMessagePayload::SpdmChunkGetRequest(SpdmChunkGetRequestPayload {
+                handle,
+                chunk_seq_num,
+            }),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    fn handle_spdm_chunk_response(
+        &mut self,
+        session_id: Option<u32>,
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmChunk> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseChunkResponse => {
+                    SpdmChunkResponsePayload::spdm_read(&mut self.common, &mut reader)
+                        .map(|chunk_response| chunk_response.chunk)
+                        .ok_or_else(|| spdm_err!(Codec))
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    self.spdm_handle_error_response_main(
+                        session_id.map(SpdmSessionId::from),
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestChunkGet,
+                        SpdmRequestResponseCode::SpdmResponseChunkResponse,
+                    )?;
+                    spdm_result_err!(EFAULT)
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+}
+
+// The handle of the large response to get with CHUNK_GET, None unless
+// response is ERROR(LargeResponse).
+fn get_large_response(response: &[u8]) -> Option<u8> {
+    let mut reader = Reader::init(response);
+    let message_header = SpdmMessageHeader::read(&mut reader)?;
+    if message_header.request_response_code != SpdmRequestResponseCode::SpdmResponseError {
+        return None;
+    }
+    let general_payload = SpdmMessageGeneralPayload::read(&mut reader)?;
+    if general_payload.param1 != SpdmErrorCode::SpdmErrorLargeResponse.get_u8() {
+        return None;
+    }
+    // ExtendedErrorData is the Handle
+    u8::read(&mut reader)
+}
@@ -2,14 +2,119 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::connection_info::SpdmConnectionInfo;
+use crate::common::session::SpdmSessionId;
+use crate::common::session_audit::SpdmSessionAudit;
+use crate::common::x509::SpdmCertInfo;
 use crate::common::ST1;
-use crate::common::{self, SpdmDeviceIo, SpdmTransportEncap};
+use crate::common::{
+    self, SpdmAppMessageHandler, SpdmDeviceIo, SpdmSessionEvent, SpdmSessionObserver,
+    SpdmTimeProvider, SpdmTracer, SpdmTransportEncap,
+};
 use crate::config;
+use crate::crypto::SpdmRng;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::SpdmErrorResponsePayload;
 use crate::protocol::*;
+use crate::time::sleep;
+
+extern crate alloc;
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 pub struct RequesterContext<'a> {
     pub common: common::SpdmContext<'a>,
+    // the latest ERROR response, including any ExtendedErrorData
+    pub last_error: Option<SpdmErrorResponsePayload>,
+    // the latest request as sent, for retransmission
+    last_transport_message: [u8; config::DATA_TRANSFER_SIZE],
+    last_transport_message_size: usize,
+    // the latest SPDM request sent in a session, in the clear, to encrypt
+    // again after a DecryptError
+    pub(crate) last_secured_request: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    pub(crate) last_secured_request_size: usize,
+    pub(crate) decrypt_error_recovery: bool,
+    resynch_handler: Option<SpdmResynchHandler<'a>>,
+    pub(crate) resynch_in_progress: bool,
+    pub(crate) peer_cert_policy: Option<SpdmPeerCertPolicy<'a>>,
+    cancel_token: Option<&'a SpdmCancelToken>,
+    // the session of the latest request sent, until its response arrives
+    pending_session_id: Option<u32>,
+    // the latest SPDM request sent, for ERROR(ResponseNotReady) to refer to
+    pub(crate) last_request_code: u8,
+    // the nonce of the latest signed GET_MEASUREMENTS, and the one of the
+    // latest MEASUREMENTS verified, for the next one to be fresh
+    pub(crate) measurement_request_nonce: Option<SpdmNonceStruct>,
+    pub(crate) measurement_response_nonce: Option<SpdmNonceStruct>,
+    // the handle of the next large request sent with CHUNK_SEND, and the
+    // response to the latest one, out of its last CHUNK_SEND_ACK
+    pub(crate) chunk_handle: u8,
+    pub(crate) chunk_response: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    pub(crate) chunk_response_size: usize,
+}
+
+/// Abort of the pending request of a RequesterContext, from another thread
+/// or an interrupt handler, e.g. a static token the context registered.
+///
+/// The requester checks the token before each request, before each receive
+/// and before waiting on ERROR(ResponseNotReady), and fails with ECANCELED.
+/// A receive already started runs to its timeout, unless the device IO
+/// checks is_cancelled and gives up early. The cancellation is consumed by
+/// the request it aborts, or by the next one if none is pending.
+#[derive(Debug, Default)]
+pub struct SpdmCancelToken {
+    cancelled: AtomicBool,
+}
+
+impl SpdmCancelToken {
+    pub const fn new() -> Self {
+        SpdmCancelToken {
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Approval of the new connection a responder asks for with
+/// ERROR(RequestResynch), false to leave it to the application. The
+/// sessions are lost either way, the certificate chains retrieved before
+/// are retrieved again once approved.
+pub type SpdmResynchHandler<'a> = &'a dyn Fn() -> bool;
+
+/// Acceptance of the peer certificate chain of a slot, given the slot id,
+/// the parsed leaf certificate and the DER certificates of the chain, false
+/// to reject the peer.
+pub type SpdmPeerCertPolicy<'a> = &'a dyn Fn(u8, &SpdmCertInfo, &[u8]) -> bool;
+
+/// How start_session_with_policy attests the responder before the session.
+#[derive(Debug, Clone, Copy)]
+pub struct SpdmStartSessionPolicy {
+    pub slot_id: u8,
+    pub measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    // CHALLENGE the responder first, if it supports it
+    pub challenge: bool,
+}
+
+impl Default for SpdmStartSessionPolicy {
+    fn default() -> Self {
+        SpdmStartSessionPolicy {
+            slot_id: 0,
+            measurement_summary_hash_type:
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            challenge: true,
+        }
+    }
 }
 
 impl<'a> RequesterContext<'a> {
@@ -26,9 +131,159 @@ impl<'a> RequesterContext<'a> {
                 config_info,
                 provision_info,
             ),
+            last_error: None,
+            last_transport_message: [0u8; config::DATA_TRANSFER_SIZE],
+            last_transport_message_size: 0,
+            last_secured_request: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            last_secured_request_size: 0,
+            decrypt_error_recovery: false,
+            resynch_handler: None,
+            resynch_in_progress: false,
+            peer_cert_policy: None,
+            cancel_token: None,
+            pending_session_id: None,
+            last_request_code: 0,
+            measurement_request_nonce: None,
+            measurement_response_nonce: None,
+            chunk_handle: 0,
+            chunk_response: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            chunk_response_size: 0,
+        }
+    }
+
+    /// Draw nonces and ephemeral keys from rng instead of the crypto provider.
+    pub fn register_rng(&mut self, rng: &'a mut dyn SpdmRng) {
+        self.common.rng = Some(rng);
+    }
+
+    /// Check certificate validity periods against, and timestamp traced
+    /// messages with, time_provider instead of the platform clock.
+    pub fn register_time_provider(&mut self, time_provider: &'a dyn SpdmTimeProvider) {
+        self.common.time_provider = Some(time_provider);
+    }
+
+    /// Hand the application messages the responder sends in a session while
+    /// an SPDM response is awaited to handler, instead of failing the
+    /// request. Not used by the async requester.
+    pub fn register_app_message_handler(&mut self, handler: SpdmAppMessageHandler<'a>) {
+        self.common.app_message_handler = Some(handler);
+    }
+
+    /// Report every transport message sent and received to tracer.
+    pub fn register_tracer(&mut self, tracer: &'a dyn SpdmTracer) {
+        self.common.tracer = Some(tracer);
+    }
+
+    /// Report the lifecycle events of every session to session_observer.
+    pub fn register_session_observer(&mut self, session_observer: &'a dyn SpdmSessionObserver) {
+        self.common.session_observer = Some(session_observer);
+    }
+
+    /// Ask handler before negotiating the connection again on
+    /// ERROR(RequestResynch), instead of doing it right away.
+    pub fn register_resynch_handler(&mut self, handler: SpdmResynchHandler<'a>) {
+        self.resynch_handler = Some(handler);
+    }
+
+    /// Ask policy to accept every peer certificate chain once verified, e.g.
+    /// against a subject allow-list or the DICE TCB info of the leaf.
+    pub fn register_peer_cert_policy(&mut self, policy: SpdmPeerCertPolicy<'a>) {
+        self.peer_cert_policy = Some(policy);
+    }
+
+    /// Abort the pending request once token is cancelled, see
+    /// SpdmCancelToken.
+    pub fn register_cancel_token(&mut self, token: &'a SpdmCancelToken) {
+        self.cancel_token = Some(token);
+    }
+
+    // ECANCELED once the cancel token is triggered. The request pending is
+    // not retransmitted and a late response to it is flushed. A session
+    // whose request got no response is torn down, as its sequence numbers
+    // are out of step with the responder; the connection itself is kept.
+    pub(crate) fn check_cancelled(&mut self) -> SpdmResult {
+        match self.cancel_token {
+            Some(token) if token.take() => {}
+            _ => return Ok(()),
         }
+        warn!("!!! request cancelled !!!\n");
+        self.last_transport_message_size = 0;
+        let _ = self.common.device_io.flush_all();
+        if let Some(session_id) = self.pending_session_id.take() {
+            if let Some(session) = self.common.get_session_via_id(session_id) {
+                session.teardown(session_id)?;
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
+        }
+        spdm_result_err!(ECANCELED)
+    }
+
+    /// Talk to another endpoint reachable through the same device IO, e.g.
+    /// an MCTP EID behind a bridge. Connection state and sessions of the
+    /// previous peer are dropped, so init_connection has to run again.
+    pub fn set_peer_address(&mut self, peer_address: u32) -> SpdmResult {
+        self.common.transport_encap.set_peer_address(peer_address)?;
+        self.reset_connection()
     }
 
+    // Drop the connection state and the sessions of the peer, keeping the
+    // configuration and provisioning.
+    pub(crate) fn reset_connection(&mut self) -> SpdmResult {
+        self.common.reset_negotiate_info();
+        self.common.peer_info = common::SpdmPeerInfo::default();
+        for i in 0..self.common.session.len() {
+            let session_id = self.common.session[i].get_session_id();
+            if session_id != 0 {
+                self.common.session[i].teardown(session_id)?;
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
+        }
+        self.last_error = None;
+        self.last_secured_request_size = 0;
+        self.last_transport_message_size = 0;
+        self.chunk_response_size = 0;
+        Ok(())
+    }
+
+    // Whether to negotiate the connection again after ERROR(RequestResynch),
+    // done unless a registered resynch handler refuses.
+    pub(crate) fn approve_resynch(&self) -> bool {
+        self.resynch_handler.map_or(true, |handler| handler())
+    }
+
+    /// Transcript and parameters of the established session session_id, to
+    /// archive as evidence of the attestation it carries.
+    pub fn get_session_audit(
+        &self,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult<Box<SpdmSessionAudit>> {
+        self.common.get_session_audit(session_id.as_u32(), true)
+    }
+
+    /// Versions, capabilities and algorithms negotiated with the responder,
+    /// the responder cert chains retrieved and the sessions open.
+    pub fn get_connection_info(&self) -> SpdmConnectionInfo {
+        self.common.get_connection_info()
+    }
+
+    /// Hash of the M1/M2 transcript so far, to compare with the one the
+    /// responder logged.
+    pub fn get_m1m2_hash(&self) -> SpdmResult<SpdmDigestStruct> {
+        self.common.get_m1m2_hash()
+    }
+
+    /// TH2 of session session_id, once it is established.
+    pub fn get_session_transcript_hash(
+        &self,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult<SpdmDigestStruct> {
+        self.common
+            .get_session_transcript_hash(session_id.as_u32(), true)
+    }
+
+    /// GET_VERSION, GET_CAPABILITIES and NEGOTIATE_ALGORITHMS.
     pub fn init_connection(&mut self) -> SpdmResult {
         self.send_receive_spdm_version()?;
         self.send_receive_spdm_capability()?;
@@ -40,44 +295,98 @@ impl<'a> RequesterContext<'a> {
         use_psk: bool,
         slot_id: u8,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
-    ) -> SpdmResult<u32> {
+    ) -> SpdmResult<SpdmSessionId> {
+        // ENOMEM once the requester or the responder has no session left
         if !use_psk {
-            let result =
-                self.send_receive_spdm_key_exchange(slot_id, measurement_summary_hash_type);
-            if let Ok(session_id) = result {
-                let result = self.send_receive_spdm_finish(slot_id, session_id);
-                if result.is_ok() {
-                    Ok(session_id)
-                } else {
-                    spdm_result_err!(EIO)
-                }
+            let session_id =
+                self.send_receive_spdm_key_exchange(slot_id, measurement_summary_hash_type)?;
+            if self.is_encap_mut_auth_requested(session_id)
+                && self
+                    .send_receive_spdm_encapsulated_request(session_id)
+                    .is_err()
+            {
+                return spdm_result_err!(EIO);
+            }
+            let result = self.send_receive_spdm_finish(slot_id, session_id);
+            if result.is_ok() {
+                Ok(session_id)
             } else {
                 spdm_result_err!(EIO)
             }
         } else {
-            let result = self.send_receive_spdm_psk_exchange(measurement_summary_hash_type);
-            if let Ok(session_id) = result {
-                let result = self.send_receive_spdm_psk_finish(session_id);
-                if result.is_ok() {
-                    Ok(session_id)
-                } else {
-                    spdm_result_err!(EIO)
-                }
+            let session_id = self.send_receive_spdm_psk_exchange(measurement_summary_hash_type)?;
+            let result = self.send_receive_spdm_psk_finish(session_id);
+            if result.is_ok() {
+                Ok(session_id)
             } else {
                 spdm_result_err!(EIO)
             }
         }
     }
 
-    pub fn end_session(&mut self, session_id: u32) -> SpdmResult {
+    /// Attest the responder as policy asks and start a session, once
+    /// init_connection is done: GET_DIGESTS and GET_CERTIFICATE unless the
+    /// certificate chain of the slot was retrieved already, CHALLENGE, then
+    /// KEY_EXCHANGE and FINISH, or PSK_EXCHANGE and PSK_FINISH. The
+    /// certificate chain is only needed by a PSK session for CHALLENGE.
+    pub fn start_session_with_policy(
+        &mut self,
+        use_psk: bool,
+        policy: &SpdmStartSessionPolicy,
+    ) -> SpdmResult<SpdmSessionId> {
+        let slot_id = policy.slot_id;
+        if slot_id as usize >= SPDM_MAX_SLOT_NUMBER {
+            return spdm_result_err!(EINVAL);
+        }
+        let rsp_capabilities = self.common.negotiate_info.rsp_capabilities_sel;
+        let challenge =
+            policy.challenge && rsp_capabilities.contains(SpdmResponseCapabilityFlags::CHAL_CAP);
+        if (!use_psk || challenge)
+            && rsp_capabilities.contains(SpdmResponseCapabilityFlags::CERT_CAP)
+            && self.common.peer_info.peer_cert_chain[slot_id as usize].is_none()
+        {
+            self.send_receive_spdm_digest(None)?;
+            self.send_receive_spdm_certificate(None, slot_id)?;
+        }
+        if challenge {
+            self.send_receive_spdm_challenge(
+                slot_id,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )?;
+        }
+        self.start_session(use_psk, slot_id, policy.measurement_summary_hash_type)
+    }
+
+    pub fn end_session(&mut self, session_id: SpdmSessionId) -> SpdmResult {
         let _result = self.send_receive_spdm_end_session(session_id);
         Ok(())
     }
 
     pub fn send_message(&mut self, send_buffer: &[u8]) -> SpdmResult {
+        self.pending_session_id = None;
+        self.chunk_response_size = 0;
+        self.check_cancelled()?;
+        if self.check_request_size(send_buffer, false)? {
+            return self.send_spdm_chunks(None, send_buffer);
+        }
         let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         let used = self.common.encap(send_buffer, &mut transport_buffer)?;
-        self.common.device_io.send(&transport_buffer[..used])
+        self.send_transport_message(&transport_buffer[..used])?;
+        self.last_request_code = send_buffer.get(1).copied().unwrap_or_default();
+        Ok(())
+    }
+
+    // A request of session_id sent in the clear, FINISH with
+    // HANDSHAKE_IN_THE_CLEAR_CAP: the session is torn down as well if it is
+    // cancelled before the response.
+    pub(crate) fn send_session_message_in_clear(
+        &mut self,
+        session_id: u32,
+        send_buffer: &[u8],
+    ) -> SpdmResult {
+        self.send_message(send_buffer)?;
+        self.pending_session_id = Some(session_id);
+        Ok(())
     }
 
     pub fn send_secured_message(
@@ -86,6 +395,12 @@ impl<'a> RequesterContext<'a> {
         send_buffer: &[u8],
         is_app_message: bool,
     ) -> SpdmResult {
+        self.pending_session_id = None;
+        self.chunk_response_size = 0;
+        self.check_cancelled()?;
+        if !is_app_message && self.check_request_size(send_buffer, true)? {
+            return self.send_spdm_chunks(Some(session_id), send_buffer);
+        }
         let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         let used = self.common.encode_secured_message(
             session_id,
@@ -94,32 +409,156 @@ impl<'a> RequesterContext<'a> {
             true,
             is_app_message,
         )?;
-        self.common.device_io.send(&transport_buffer[..used])
+        self.send_transport_message(&transport_buffer[..used])?;
+        self.pending_session_id = Some(session_id);
+        if !is_app_message {
+            self.last_request_code = send_buffer.get(1).copied().unwrap_or_default();
+            self.last_secured_request_size = 0;
+            if send_buffer.len() <= self.last_secured_request.len() {
+                self.last_secured_request[..send_buffer.len()].copy_from_slice(send_buffer);
+                self.last_secured_request_size = send_buffer.len();
+            }
+        }
+
+        // any request counts as activity for the heartbeat period
+        if let Some(session) = self.common.get_session_via_id(session_id) {
+            session.reset_heartbeat_idle_time();
+        }
+        Ok(())
     }
 
-    pub fn receive_message(
+    // A request the responder cannot take is refused before it goes on the
+    // wire. True for one to send with CHUNK_SEND, larger than a single
+    // transfer once CHUNK_CAP is negotiated, which the async and poll
+    // requesters do not do.
+    fn check_request_size(&mut self, send_buffer: &[u8], secured: bool) -> SpdmResult<bool> {
+        match self.common.get_rsp_max_message_size() {
+            Some(max_size) if send_buffer.len() > max_size => {
+                error!(
+                    "!!! request size {} exceeds responder limit {} !!!\n",
+                    send_buffer.len(),
+                    max_size
+                );
+                return spdm_result_err!(ENOMEM);
+            }
+            _ => {}
+        }
+        if !self.common.is_chunk_cap_negotiated()
+            || send_buffer.len() <= self.get_max_transfer_size(secured)
+        {
+            return Ok(false);
+        }
+        if self.common.deferred_io.is_some() {
+            error!(
+                "!!! request size {} needs chunking !!!\n",
+                send_buffer.len()
+            );
+            return spdm_result_err!(ENOMEM);
+        }
+        Ok(true)
+    }
+
+    // The largest request the responder takes in one transfer.
+    pub(crate) fn get_max_transfer_size(&mut self, secured: bool) -> usize {
+        let mut max_size = self.common.get_max_transport_message_size(secured);
+        if let Some(rsp_transfer_size) = self.common.get_rsp_data_transfer_size() {
+            max_size = max_size.min(rsp_transfer_size);
+        }
+        max_size
+    }
+
+    fn send_transport_message(&mut self, transport_message: &[u8]) -> SpdmResult {
+        self.last_transport_message_size = 0;
+        self.common.send_transport_message(transport_message)?;
+        if transport_message.len() <= self.last_transport_message.len() {
+            self.last_transport_message[..transport_message.len()]
+                .copy_from_slice(transport_message);
+            self.last_transport_message_size = transport_message.len();
+        }
+        Ok(())
+    }
+
+    /// Receive the response to the latest request, resending the request as
+    /// the retry policy allows while none arrives in time.
+    fn receive_transport_message(
         &mut self,
-        receive_buffer: &mut [u8],
+        transport_buffer: &mut [u8],
         crypto_request: bool,
     ) -> SpdmResult<usize> {
-        info!("receive_message!\n");
+        let retry_policy = self.common.config_info.retry_policy;
+        let timeout = self.response_timeout(crypto_request);
+        let mut retry = 0u8;
+        loop {
+            self.check_cancelled()?;
+            match self.common.receive_transport_message(
+                transport_buffer,
+                retry_policy.get_timeout(timeout, retry),
+            ) {
+                Ok(used) => {
+                    self.pending_session_id = None;
+                    return Ok(used);
+                }
+                // Err(0) is a timeout, anything else was received but is no response
+                Err(0)
+                    if retry < retry_policy.retry_count
+                        && self.last_transport_message_size != 0
+                        && self.common.deferred_io.is_none() =>
+                {
+                    retry += 1;
+                    warn!(
+                        "no response, retransmission {} of {}\n",
+                        retry, retry_policy.retry_count
+                    );
+                    let backoff = retry_policy.get_backoff(retry);
+                    if backoff != 0 {
+                        sleep(backoff);
+                    }
+                    self.common.send_transport_message(
+                        &self.last_transport_message[..self.last_transport_message_size],
+                    )?;
+                }
+                Err(0) => return spdm_result_err!(Timeout),
+                Err(_) => return spdm_result_err!(Transport),
+            }
+        }
+    }
 
-        let timeout: usize = if crypto_request {
-            2 << self.common.negotiate_info.rsp_ct_exponent_sel
+    /// RTT plus CT of the responder for a request with cryptographic
+    /// processing, else plus ST1, in us.
+    pub(crate) fn response_timeout(&self, crypto_request: bool) -> usize {
+        let timeout = if crypto_request {
+            2usize
+                .checked_shl(self.common.negotiate_info.rsp_ct_exponent_sel as u32)
+                .unwrap_or(usize::MAX)
         } else {
             ST1
         };
+        timeout.saturating_add(self.common.config_info.round_trip_time)
+    }
 
-        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
-        let used = self
-            .common
-            .device_io
-            .receive(&mut transport_buffer, timeout)
-            .map_err(|_| spdm_err!(EIO))?;
+    /// Receive the response to the latest request, RESPOND_IF_READY first
+    /// while the responder answers ERROR(ResponseNotReady).
+    pub fn receive_message(
+        &mut self,
+        receive_buffer: &mut [u8],
+        crypto_request: bool,
+    ) -> SpdmResult<usize> {
+        info!("receive_message!\n");
 
-        self.common.decap(&transport_buffer[..used], receive_buffer)
+        let used = self.receive_spdm_message(None, receive_buffer, crypto_request)?;
+        let used = self.resolve_response_not_ready(
+            None,
+            self.last_request_code,
+            receive_buffer,
+            used,
+            crypto_request,
+        )?;
+        self.resolve_large_response(None, receive_buffer, used)
     }
 
+    /// Receive the response to the latest request in session session_id,
+    /// RESPOND_IF_READY first in the session while the responder answers
+    /// ERROR(ResponseNotReady).
     pub fn receive_secured_message(
         &mut self,
         session_id: u32,
@@ -128,29 +567,107 @@ impl<'a> RequesterContext<'a> {
     ) -> SpdmResult<usize> {
         info!("receive_secured_message!\n");
 
-        let timeout: usize = if crypto_request {
-            2 << self.common.negotiate_info.rsp_ct_exponent_sel
-        } else {
-            ST1
+        let used = self.receive_spdm_message(Some(session_id), receive_buffer, crypto_request)?;
+        let used = self.resolve_response_not_ready(
+            Some(session_id),
+            self.last_request_code,
+            receive_buffer,
+            used,
+            crypto_request,
+        )?;
+        self.resolve_large_response(Some(session_id), receive_buffer, used)
+    }
+
+    // One SPDM message, in the clear or in session session_id.
+    pub(crate) fn receive_spdm_message(
+        &mut self,
+        session_id: Option<u32>,
+        receive_buffer: &mut [u8],
+        crypto_request: bool,
+    ) -> SpdmResult<usize> {
+        if self.chunk_response_size != 0 {
+            return self.take_chunk_response(receive_buffer);
+        }
+        let session_id = match session_id {
+            Some(session_id) => session_id,
+            None => {
+                let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+                let used = self.receive_transport_message(&mut transport_buffer, crypto_request)?;
+                return self.common.decap(&transport_buffer[..used], receive_buffer);
+            }
         };
 
-        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        loop {
+            let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            let used = self.receive_transport_message(&mut transport_buffer, crypto_request)?;
 
-        let used = self
-            .common
-            .device_io
-            .receive(&mut transport_buffer, timeout)
-            .map_err(|_| spdm_err!(EIO))?;
+            let (used, is_app_message) = self.common.decode_secured_message(
+                session_id,
+                &transport_buffer[..used],
+                receive_buffer,
+            )?;
+            let handler = match self.common.app_message_handler {
+                Some(handler) if is_app_message && self.common.deferred_io.is_none() => handler,
+                _ => return Ok(used),
+            };
+            self.dispatch_app_message(handler, session_id, &receive_buffer[..used])?;
+        }
+    }
 
-        self.common
-            .decode_secured_message(session_id, &transport_buffer[..used], receive_buffer)
+    /// Exchange an application message over the secured session, updating
+    /// the session keys first when the key update policy says so.
+    pub fn send_receive_app_message(
+        &mut self,
+        session_id: SpdmSessionId,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> SpdmResult<usize> {
+        self.key_update_if_due(session_id)?;
+        let session_id = session_id.as_u32();
+        self.send_secured_message(session_id, request, true)?;
+
+        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let used = self.receive_transport_message(&mut transport_buffer, false)?;
+        let (used, is_app_message) =
+            self.common
+                .decode_secured_message(session_id, &transport_buffer[..used], response)?;
+        if !is_app_message {
+            error!("!!! app message : SPDM message received !!!\n");
+            return spdm_result_err!(EFAULT);
+        }
+        Ok(used)
+    }
+
+    fn dispatch_app_message(
+        &mut self,
+        handler: SpdmAppMessageHandler<'a>,
+        session_id: u32,
+        app_message: &[u8],
+    ) -> SpdmResult {
+        let session = self.common.get_session_mut(session_id)?;
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        match handler(
+            session_id,
+            &mut session.app_data,
+            app_message,
+            &mut send_buffer,
+        ) {
+            Ok(0) => Ok(()),
+            Ok(used) if used <= send_buffer.len() => {
+                self.send_secured_message(session_id, &send_buffer[..used], true)
+            }
+            _ => {
+                error!("!!! app message : handler failed !!!\n");
+                Ok(())
+            }
+        }
     }
 }
 
 #[cfg(all(test,))]
 mod tests_requester {
     use super::*;
-    use crate::common::session::SpdmSession;
+    use crate::common::session::{SpdmKeyUpdatePolicy, SpdmSession};
     use crate::common::*;
     use crate::message::*;
     use crate::testlib::*;
@@ -216,6 +733,130 @@ mod tests_requester {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_case1_start_session() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+        rsp_config_info.max_session_count = 1;
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+
+        // ERROR(SessionLimitExceeded) from the responder
+        let err = requester
+            .start_session(
+                true,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ENOMEM);
+        assert_eq!(requester.common.get_session_count(), 1);
+
+        assert!(requester.end_session(session_id).is_ok());
+        let session_id = requester
+            .start_session(
+                true,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+
+        // the requester limit is checked before sending
+        requester.common.config_info.max_session_count = 1;
+        let err = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ENOMEM);
+        assert!(requester.end_session(session_id).is_ok());
+        assert_eq!(requester.common.get_session_count(), 0);
+    }
+
+    #[test]
+    fn test_case2_start_session_with_policy() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        let policy = SpdmStartSessionPolicy::default();
+        let session_id = requester.start_session_with_policy(false, &policy).unwrap();
+        assert!(requester.common.peer_info.peer_cert_chain[0].is_some());
+        assert!(requester.end_session(session_id).is_ok());
+
+        let policy = SpdmStartSessionPolicy {
+            challenge: false,
+            ..Default::default()
+        };
+        let session_id = requester.start_session_with_policy(true, &policy).unwrap();
+        assert!(requester.end_session(session_id).is_ok());
+
+        let policy = SpdmStartSessionPolicy {
+            slot_id: SPDM_MAX_SLOT_NUMBER as u8,
+            ..Default::default()
+        };
+        assert!(requester.start_session_with_policy(false, &policy).is_err());
+        assert_eq!(requester.common.get_session_count(), 0);
+    }
+
     #[test]
     fn test_case0_receive_secured_message() {
         let (rsp_config_info, rsp_provision_info) = create_info();
@@ -300,4 +941,371 @@ mod tests_requester {
             .is_ok();
         assert!(status);
     }
+
+    // Drops the first drop_count requests, as a lossy transport would.
+    struct LossySpdmDeviceIo<'a> {
+        data: &'a SharedBuffer,
+        responder: &'a mut responder::ResponderContext<'a>,
+        drop_count: usize,
+    }
+
+    impl SpdmDeviceIo for LossySpdmDeviceIo<'_> {
+        fn receive(&mut self, read_buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+            match self.data.get_buffer(read_buffer) {
+                0 => Err(0),
+                len => Ok(len),
+            }
+        }
+
+        fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+            if self.drop_count != 0 {
+                self.drop_count -= 1;
+                return Ok(());
+            }
+            self.data.set_buffer(buffer);
+            if self.responder.process_message(ST1).is_err() {
+                return spdm_result_err!(ENOMEM);
+            }
+            Ok(())
+        }
+
+        fn flush_all(&mut self) -> SpdmResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case0_retry_policy() {
+        let retry_policy = SpdmRetryPolicy {
+            retry_count: 3,
+            backoff: 100,
+            backoff_factor: 2,
+        };
+        assert_eq!(retry_policy.get_timeout(ST1, 0), ST1);
+        assert_eq!(retry_policy.get_timeout(ST1, 2), 4 * ST1);
+        assert_eq!(retry_policy.get_backoff(1), 100);
+        assert_eq!(retry_policy.get_backoff(3), 400);
+        assert_eq!(retry_policy.get_timeout(usize::MAX, 1), usize::MAX);
+
+        for (retry_count, drop_count, result) in [(0, 1, false), (2, 2, true), (2, 3, false)] {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (mut req_config_info, req_provision_info) = create_info();
+            req_config_info.retry_policy.retry_count = retry_count;
+
+            let shared_buffer = SharedBuffer::new();
+            let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+            let mut responder = responder::ResponderContext::new(
+                &mut device_io_responder,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut device_io_requester = LossySpdmDeviceIo {
+                data: &shared_buffer,
+                responder: &mut responder,
+                drop_count,
+            };
+
+            let mut requester = RequesterContext::new(
+                &mut device_io_requester,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            assert_eq!(requester.send_receive_spdm_version().is_ok(), result);
+        }
+    }
+
+    // Drops the first request and cancels token meanwhile, as an interrupt
+    // handler giving up on the responder would.
+    struct CancelSpdmDeviceIo<'a> {
+        data: &'a SharedBuffer,
+        responder: &'a mut responder::ResponderContext<'a>,
+        token: &'a SpdmCancelToken,
+        send_count: usize,
+    }
+
+    impl SpdmDeviceIo for CancelSpdmDeviceIo<'_> {
+        fn receive(&mut self, read_buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+            match self.data.get_buffer(read_buffer) {
+                0 => Err(0),
+                len => Ok(len),
+            }
+        }
+
+        fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+            self.send_count += 1;
+            if self.send_count == 1 {
+                self.token.cancel();
+                return Ok(());
+            }
+            self.data.set_buffer(buffer);
+            if self.responder.process_message(ST1).is_err() {
+                return spdm_result_err!(ENOMEM);
+            }
+            Ok(())
+        }
+
+        fn flush_all(&mut self) -> SpdmResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case0_cancel_token() {
+        static CANCEL_TOKEN: SpdmCancelToken = SpdmCancelToken::new();
+
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.retry_policy.retry_count = 2;
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = CancelSpdmDeviceIo {
+            data: &shared_buffer,
+            responder: &mut responder,
+            token: &CANCEL_TOKEN,
+            send_count: 0,
+        };
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_cancel_token(&CANCEL_TOKEN);
+
+        // cancelled while waiting for the response, not retransmitted
+        let err = requester.send_receive_spdm_version().unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ECANCELED);
+        assert!(!CANCEL_TOKEN.is_cancelled());
+
+        // cancelled before the request is sent
+        CANCEL_TOKEN.cancel();
+        let err = requester.send_receive_spdm_version().unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ECANCELED);
+
+        // the cancellations are consumed, the connection goes on
+        assert!(requester.send_receive_spdm_version().is_ok());
+    }
+
+    #[test]
+    fn test_case1_cancel_token_session() {
+        static CANCEL_TOKEN: SpdmCancelToken = SpdmCancelToken::new();
+
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = CancelSpdmDeviceIo {
+            data: &shared_buffer,
+            responder: &mut responder,
+            token: &CANCEL_TOKEN,
+            send_count: 0,
+        };
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_cancel_token(&CANCEL_TOKEN);
+
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let session_id = 0xffu32 << 16 | 0xff;
+        requester.common.session = gen_array_clone(SpdmSession::new(), 4);
+        requester.common.session[0].setup(session_id).unwrap();
+        requester.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        requester.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
+
+        // cancelled while waiting for the HEARTBEAT_ACK, the session is
+        // out of step with the responder
+        let err = requester
+            .send_receive_spdm_heartbeat(SpdmSessionId::from(session_id))
+            .unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ECANCELED);
+        assert_eq!(requester.common.session[0].get_session_id(), 0);
+
+        // FINISH in the clear belongs to its session all the same
+        requester.common.session[0].setup(session_id).unwrap();
+        requester.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionHandshaking);
+        // FINISH header, SPDM 1.2
+        let send_buffer = [0x12u8, 0xe5, 0x00, 0x00];
+        requester
+            .send_session_message_in_clear(session_id, &send_buffer)
+            .unwrap();
+        CANCEL_TOKEN.cancel();
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let err = requester
+            .receive_message(&mut receive_buffer, true)
+            .unwrap_err();
+        assert_eq!(err.num(), crate::error::SpdmErrorNum::ECANCELED);
+        assert_eq!(requester.common.session[0].get_session_id(), 0);
+    }
+
+    // PCI DOE with a type byte in front of the application data, as MCTP
+    struct AppTransportEncap {
+        pcidoe_transport_encap: PciDoeTransportEncap,
+    }
+
+    impl SpdmTransportEncap for AppTransportEncap {
+        fn encap(
+            &mut self,
+            spdm_buffer: &[u8],
+            transport_buffer: &mut [u8],
+            secured_message: bool,
+        ) -> SpdmResult<usize> {
+            self.pcidoe_transport_encap
+                .encap(spdm_buffer, transport_buffer, secured_message)
+        }
+
+        fn decap(
+            &mut self,
+            transport_buffer: &[u8],
+            spdm_buffer: &mut [u8],
+        ) -> SpdmResult<(usize, bool)> {
+            self.pcidoe_transport_encap
+                .decap(transport_buffer, spdm_buffer)
+        }
+
+        fn encap_app(
+            &mut self,
+            spdm_buffer: &[u8],
+            app_buffer: &mut [u8],
+            is_app_message: bool,
+        ) -> SpdmResult<usize> {
+            app_buffer[0] = is_app_message as u8;
+            app_buffer[1..(1 + spdm_buffer.len())].copy_from_slice(spdm_buffer);
+            Ok(1 + spdm_buffer.len())
+        }
+
+        fn decap_app(
+            &mut self,
+            app_buffer: &[u8],
+            spdm_buffer: &mut [u8],
+        ) -> SpdmResult<(usize, bool)> {
+            if app_buffer.is_empty() {
+                return spdm_result_err!(EIO);
+            }
+            spdm_buffer[..(app_buffer.len() - 1)].copy_from_slice(&app_buffer[1..]);
+            Ok((app_buffer.len() - 1, app_buffer[0] != 0))
+        }
+
+        fn get_sequence_number_count(&mut self) -> u8 {
+            0
+        }
+
+        fn get_max_random_count(&mut self) -> u16 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_case1_app_message() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.key_update_policy = SpdmKeyUpdatePolicy {
+            max_messages: 4,
+            ..Default::default()
+        };
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let transport_encap = &mut AppTransportEncap {
+            pcidoe_transport_encap: PciDoeTransportEncap {},
+        };
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let transport_encap2 = &mut AppTransportEncap {
+            pcidoe_transport_encap: PciDoeTransportEncap {},
+        };
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+
+        // two requests and their responses reach the limit
+        let mut response = [0u8; 8];
+        for _ in 0..2 {
+            assert!(requester
+                .send_receive_app_message(session_id, &[1], &mut response)
+                .is_ok());
+        }
+        let session = requester
+            .common
+            .get_session_via_id(session_id.as_u32())
+            .unwrap();
+        assert!(session.is_key_update_due());
+        assert_eq!(session.get_request_direction_sequence_number(), 2);
+
+        // KEY_UPDATE with the old key, VERIFY_NEW_KEY and the request with the new one
+        assert!(requester
+            .send_receive_app_message(session_id, &[1], &mut response)
+            .is_ok());
+        let session = requester
+            .common
+            .get_session_via_id(session_id.as_u32())
+            .unwrap();
+        assert!(!session.is_key_update_due());
+        assert_eq!(session.get_request_direction_sequence_number(), 2);
+        assert!(!requester.key_update_if_due(session_id).unwrap());
+    }
 }
@@ -0,0 +1,791 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::{SpdmSessionId, SpdmSessionState};
+use crate::crypto;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    /// Serve the requests the responder tunnels in session_id until it has no more.
+    /// During the handshake this lets it retrieve the requester certificate chain
+    /// before FINISH, once established it may ask for digests or a key update.
+    pub fn send_receive_spdm_encapsulated_request(
+        &mut self,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult {
+        let session = self.common.get_session(session_id)?;
+        let implicit_get_digests = session.get_session_state()
+            == SpdmSessionState::SpdmSessionHandshaking
+            && session
+                .mut_auth_requested
+                .contains(SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_GET_DIGESTS);
+
+        let mut encap_request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let (mut request_id, mut encap_request_used) = if implicit_get_digests {
+            // the responder expects the DIGESTS of an implicit GET_DIGESTS with request_id 0
+            let mut writer = Writer::init(&mut encap_request);
+            let request = SpdmMessage {
+                header: SpdmMessageHeader {
+                    version: self.common.negotiate_info.spdm_version_sel,
+                    request_response_code: SpdmRequestResponseCode::SpdmRequestGetDigests,
+                },
+                payload: SpdmMessagePayload::SpdmGetDigestsRequest(SpdmGetDigestsRequestPayload {}),
+            };
+            request.spdm_encode(&mut self.common, &mut writer);
+            (0u8, writer.used())
+        } else {
+            info!("send spdm get_encapsulated_request\n");
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let send_used = self.encode_spdm_get_encapsulated_request(&mut send_buffer);
+            self.send_secured_message(session_id.as_u32(), &send_buffer[..send_used], false)?;
+
+            let used =
+                self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
+            self.handle_spdm_encapsulated_request_response(
+                Some(session_id),
+                &receive_buffer[..used],
+                &mut encap_request,
+            )?
+        };
+
+        loop {
+            info!("send spdm deliver_encapsulated_response\n");
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let send_used = self.encode_spdm_deliver_encapsulated_response(
+                Some(session_id),
+                request_id,
+                &encap_request[..encap_request_used],
+                &mut send_buffer,
+            )?;
+            self.send_secured_message(session_id.as_u32(), &send_buffer[..send_used], false)?;
+
+            let used =
+                self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
+            match self.handle_spdm_encapsulated_response_ack(
+                Some(session_id),
+                &receive_buffer[..used],
+                &mut encap_request,
+            )? {
+                Some((next_request_id, next_request_used)) => {
+                    request_id = next_request_id;
+                    encap_request_used = next_request_used;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Serve the encapsulated requests of basic mutual authentication, out of
+    /// session, after a CHALLENGE_AUTH with BASIC_MUT_AUTH_REQ.
+    pub fn send_receive_spdm_basic_mut_auth(&mut self) -> SpdmResult {
+        if !self.common.runtime_info.basic_mut_auth_requested {
+            return spdm_result_err!(EINVAL);
+        }
+        self.common.runtime_info.basic_mut_auth_requested = false;
+        self.common.runtime_info.message_mut_c.reset_message();
+
+        info!("send spdm get_encapsulated_request\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = self.encode_spdm_get_encapsulated_request(&mut send_buffer);
+        self.send_message(&send_buffer[..send_used])?;
+
+        let mut encap_request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.receive_message(&mut receive_buffer, false)?;
+        let (mut request_id, mut encap_request_used) = self
+            .handle_spdm_encapsulated_request_response(
+                None,
+                &receive_buffer[..used],
+                &mut encap_request,
+            )?;
+
+        loop {
+            info!("send spdm deliver_encapsulated_response\n");
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let send_used = self.encode_spdm_deliver_encapsulated_response(
+                None,
+                request_id,
+                &encap_request[..encap_request_used],
+                &mut send_buffer,
+            )?;
+            self.send_message(&send_buffer[..send_used])?;
+
+            let used = self.receive_message(&mut receive_buffer, false)?;
+            match self.handle_spdm_encapsulated_response_ack(
+                None,
+                &receive_buffer[..used],
+                &mut encap_request,
+            )? {
+                Some((next_request_id, next_request_used)) => {
+                    request_id = next_request_id;
+                    encap_request_used = next_request_used;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    pub fn is_encap_mut_auth_requested(&self, session_id: SpdmSessionId) -> bool {
+        match self
+            .common
+            .get_immutable_session_via_id(session_id.as_u32())
+        {
+            Some(session) => session.mut_auth_requested.intersects(
+                SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_ENCAP_REQUEST
+                    | SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_GET_DIGESTS,
+            ),
+            None => false,
+        }
+    }
+
+    pub fn encode_spdm_get_encapsulated_request(&mut self, buf: &mut [u8]) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+            },
+            payload: SpdmMessagePayload::SpdmGetEncapsulatedRequestRequest(
+                SpdmGetEncapsulatedRequestPayload {},
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn encode_spdm_deliver_encapsulated_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        request_id: u8,
+        encap_request: &[u8],
+        buf: &mut [u8],
+    ) -> SpdmResult<usize> {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code:
+                    SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse,
+            },
+            payload: SpdmMessagePayload::SpdmDeliverEncapsulatedResponseRequest(
+                SpdmDeliverEncapsulatedResponsePayload { request_id },
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        self.write_spdm_encap_response(session_id, encap_request, &mut writer)?;
+        Ok(writer.used())
+    }
+
+    // Return the request_id and the size of the encapsulated request copied to encap_request.
+    pub fn handle_spdm_encapsulated_request_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        receive_buffer: &[u8],
+        encap_request: &mut [u8],
+    ) -> SpdmResult<(u8, usize)> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest => {
+                    let encapsulated_request =
+                        SpdmEncapsulatedRequestPayload::spdm_read(&mut self.common, &mut reader);
+                    if let Some(encapsulated_request) = encapsulated_request {
+                        debug!("!!! encapsulated_request : {:02x?}\n", encapsulated_request);
+                        let encap_request_data = &receive_buffer[reader.used()..];
+                        if encap_request_data.len() > encap_request.len() {
+                            return spdm_result_err!(ENOMEM);
+                        }
+                        encap_request[..encap_request_data.len()]
+                            .copy_from_slice(encap_request_data);
+                        Ok((encapsulated_request.request_id, encap_request_data.len()))
+                    } else {
+                        error!("!!! encapsulated_request : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+                        SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_encapsulated_request_response(
+                                session_id,
+                                &receive_buffer[..used],
+                                encap_request,
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    // Return the next encapsulated request, or None once the responder is done.
+    pub fn handle_spdm_encapsulated_response_ack(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        receive_buffer: &[u8],
+        encap_request: &mut [u8],
+    ) -> SpdmResult<Option<(u8, usize)>> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck => {
+                    let encapsulated_response_ack = SpdmEncapsulatedResponseAckPayload::spdm_read(
+                        &mut self.common,
+                        &mut reader,
+                    );
+                    let encapsulated_response_ack =
+                        if let Some(encapsulated_response_ack) = encapsulated_response_ack {
+                            debug!(
+                                "!!! encapsulated_response_ack : {:02x?}\n",
+                                encapsulated_response_ack
+                            );
+                            encapsulated_response_ack
+                        } else {
+                            error!("!!! encapsulated_response_ack : fail !!!\n");
+                            return spdm_result_err!(Codec);
+                        };
+
+                    match encapsulated_response_ack.payload_type {
+                        SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypePresent => {
+                            let encap_request_data = &receive_buffer[reader.used()..];
+                            if encap_request_data.len() > encap_request.len() {
+                                return spdm_result_err!(ENOMEM);
+                            }
+                            encap_request[..encap_request_data.len()]
+                                .copy_from_slice(encap_request_data);
+                            Ok(Some((
+                                encapsulated_response_ack.request_id,
+                                encap_request_data.len(),
+                            )))
+                        }
+                        SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeReqSlotNumber => {
+                            let req_slot_id = u8::read(&mut reader).ok_or_else(|| spdm_err!(EIO))?;
+                            if req_slot_id as usize >= SPDM_MAX_SLOT_NUMBER {
+                                return spdm_result_err!(EINVAL);
+                            }
+                            let session = self
+                                .common
+                                .get_session_mut(session_id.ok_or_else(|| spdm_err!(EINVAL))?)?;
+                            session.req_slot_id = req_slot_id;
+                            Ok(None)
+                        }
+                        SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeAbsent => {
+                            Ok(None)
+                        }
+                        _ => spdm_result_err!(EINVAL),
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse,
+                        SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_encapsulated_response_ack(
+                                session_id,
+                                &receive_buffer[..used],
+                                encap_request,
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    // Out of session, the encapsulated messages of basic mutual authentication
+    // are recorded for the CHALLENGE_AUTH signature.
+    fn write_spdm_encap_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        encap_request: &[u8],
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        if session_id.is_some() {
+            return self.dispatch_spdm_encap_request(session_id, encap_request, writer);
+        }
+
+        self.common
+            .runtime_info
+            .message_mut_c
+            .append_message(encap_request)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let used = writer.used();
+        self.dispatch_spdm_encap_request(session_id, encap_request, writer)?;
+        let is_challenge = matches!(
+            SpdmMessageHeader::read(&mut Reader::init(encap_request)),
+            Some(SpdmMessageHeader {
+                request_response_code: SpdmRequestResponseCode::SpdmRequestChallenge,
+                ..
+            })
+        );
+        // CHALLENGE_AUTH is recorded before it is signed
+        if !is_challenge {
+            self.common
+                .runtime_info
+                .message_mut_c
+                .append_message(&writer.used_slice()[used..])
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+        Ok(())
+    }
+
+    // Dispatch the encapsulated request to its handler, a request this
+    // requester cannot serve is answered with an encapsulated ERROR.
+    fn dispatch_spdm_encap_request(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        encap_request: &[u8],
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        let mut reader = Reader::init(encap_request);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmRequestGetDigests => {
+                    if SpdmGetDigestsRequestPayload::spdm_read(&mut self.common, &mut reader)
+                        .is_none()
+                    {
+                        self.write_spdm_encap_error(
+                            SpdmErrorCode::SpdmErrorInvalidRequest,
+                            0,
+                            writer,
+                        );
+                        return Ok(());
+                    }
+                    self.write_spdm_encap_digests_response(writer)
+                }
+                SpdmRequestResponseCode::SpdmRequestGetCertificate => {
+                    match SpdmGetCertificateRequestPayload::spdm_read(&mut self.common, &mut reader)
+                    {
+                        Some(get_certificate) => {
+                            self.write_spdm_encap_certificate_response(&get_certificate, writer)
+                        }
+                        None => {
+                            self.write_spdm_encap_error(
+                                SpdmErrorCode::SpdmErrorInvalidRequest,
+                                0,
+                                writer,
+                            );
+                            Ok(())
+                        }
+                    }
+                }
+                SpdmRequestResponseCode::SpdmRequestKeyUpdate => {
+                    let session_id = if let Some(session_id) = session_id {
+                        session_id
+                    } else {
+                        self.write_spdm_encap_error(
+                            SpdmErrorCode::SpdmErrorUnexpectedRequest,
+                            0,
+                            writer,
+                        );
+                        return Ok(());
+                    };
+                    match SpdmKeyUpdateRequestPayload::spdm_read(&mut self.common, &mut reader) {
+                        Some(key_update) => self.write_spdm_encap_key_update_response(
+                            session_id,
+                            &key_update,
+                            writer,
+                        ),
+                        None => {
+                            self.write_spdm_encap_error(
+                                SpdmErrorCode::SpdmErrorInvalidRequest,
+                                0,
+                                writer,
+                            );
+                            Ok(())
+                        }
+                    }
+                }
+                SpdmRequestResponseCode::SpdmRequestChallenge => {
+                    // CHALLENGE is only allowed out of session
+                    if session_id.is_some() {
+                        self.write_spdm_encap_error(
+                            SpdmErrorCode::SpdmErrorUnexpectedRequest,
+                            0,
+                            writer,
+                        );
+                        return Ok(());
+                    }
+                    match SpdmChallengeRequestPayload::spdm_read(&mut self.common, &mut reader) {
+                        Some(challenge) => {
+                            self.write_spdm_encap_challenge_auth_response(&challenge, writer)
+                        }
+                        None => {
+                            self.write_spdm_encap_error(
+                                SpdmErrorCode::SpdmErrorInvalidRequest,
+                                0,
+                                writer,
+                            );
+                            Ok(())
+                        }
+                    }
+                }
+                request_response_code => {
+                    self.write_spdm_encap_error(
+                        SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                        request_response_code.get_u8(),
+                        writer,
+                    );
+                    Ok(())
+                }
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    fn write_spdm_encap_digests_response(&mut self, writer: &mut Writer) -> SpdmResult {
+        let digests = self
+            .common
+            .get_my_digests()
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+
+        info!("send encapsulated spdm digest\n");
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseDigests,
+            },
+            payload: SpdmMessagePayload::SpdmDigestsResponse(digests),
+        };
+        response.spdm_encode(&mut self.common, writer);
+        Ok(())
+    }
+
+    fn write_spdm_encap_certificate_response(
+        &mut self,
+        get_certificate: &SpdmGetCertificateRequestPayload,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        let my_cert_chain = match self.common.get_my_cert_chain(get_certificate.slot_id) {
+            Some(my_cert_chain) if get_certificate.offset < my_cert_chain.data_size => {
+                my_cert_chain
+            }
+            _ => {
+                self.write_spdm_encap_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return Ok(());
+            }
+        };
+
+        let offset = get_certificate.offset;
+
+        let mut length = get_certificate.length;
+        if length > config::MAX_SPDM_CERT_PORTION_LEN as u16 {
+            length = config::MAX_SPDM_CERT_PORTION_LEN as u16;
+        }
+        if length > my_cert_chain.data_size - offset {
+            length = my_cert_chain.data_size - offset;
+        }
+        let remainder_length = my_cert_chain.data_size - (length + offset);
+
+        let mut cert_chain = [0u8; config::MAX_SPDM_CERT_PORTION_LEN];
+        cert_chain[..(length as usize)].copy_from_slice(
+            &my_cert_chain.data[(offset as usize)..(offset as usize + length as usize)],
+        );
+
+        info!("send encapsulated spdm certificate\n");
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseCertificate,
+            },
+            payload: SpdmMessagePayload::SpdmCertificateResponse(SpdmCertificateResponsePayload {
+                slot_id: get_certificate.slot_id,
+                portion_length: length,
+                remainder_length,
+                cert_chain,
+            }),
+        };
+        response.spdm_encode(&mut self.common, writer);
+        Ok(())
+    }
+
+    // Sign the CHALLENGE_AUTH with the requester key over VCA and the
+    // encapsulated exchange of basic mutual authentication.
+    fn write_spdm_encap_challenge_auth_response(
+        &mut self,
+        challenge: &SpdmChallengeRequestPayload,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        debug!("!!! encapsulated challenge : {:02x?}\n", challenge);
+        let my_cert_chain = match self.common.get_my_cert_chain(challenge.slot_id) {
+            Some(my_cert_chain)
+                if challenge.measurement_summary_hash_type
+                    == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone =>
+            {
+                my_cert_chain
+            }
+            _ => {
+                self.write_spdm_encap_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return Ok(());
+            }
+        };
+        let cert_chain_hash = crypto::hash::hash_all(
+            self.common.negotiate_info.base_hash_sel,
+            my_cert_chain.as_ref(),
+        )
+        .ok_or_else(|| spdm_err!(Crypto))?;
+
+        let mut nonce = [0u8; SPDM_NONCE_SIZE];
+        self.common.get_random(&mut nonce)?;
+
+        info!("send encapsulated spdm challenge_auth\n");
+        let req_asym_size = self.common.negotiate_info.req_asym_sel.get_size();
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseChallengeAuth,
+            },
+            payload: SpdmMessagePayload::SpdmChallengeAuthResponse(
+                SpdmChallengeAuthResponsePayload {
+                    slot_id: challenge.slot_id,
+                    slot_mask: self.common.get_my_slot_mask(),
+                    challenge_auth_attribute: SpdmChallengeAuthAttribute::empty(),
+                    cert_chain_hash,
+                    nonce: SpdmNonceStruct { data: nonce },
+                    measurement_summary_hash: SpdmDigestStruct::default(),
+                    opaque: SpdmOpaqueStruct::default(),
+                    signature: SpdmSignatureStruct {
+                        data_size: req_asym_size,
+                        data: [0xbb; SPDM_MAX_ASYM_KEY_SIZE],
+                    },
+                },
+            ),
+        };
+        let begin = writer.used();
+        self.common.runtime_info.need_measurement_summary_hash = false;
+        self.common.runtime_info.req_challenge_auth = true;
+        response.spdm_encode(&mut self.common, writer);
+        self.common.runtime_info.req_challenge_auth = false;
+        let used = writer.used();
+
+        let temp_used = used - req_asym_size as usize;
+        self.common
+            .runtime_info
+            .message_mut_c
+            .append_message(&writer.used_slice()[begin..temp_used])
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let message = self.common.calc_basic_mut_auth_sign_data()?;
+        let signature = self.common.sign_with_slot_key(
+            challenge.slot_id,
+            SpdmBaseAsymAlgo::from_bits_truncate(
+                self.common.negotiate_info.req_asym_sel.bits() as u32
+            ),
+            message.as_ref(),
+        )?;
+        // patch the message before send
+        writer.mut_used_slice()[temp_used..used].copy_from_slice(signature.as_ref());
+        Ok(())
+    }
+
+    // The responder updates the key of its own direction only, the new key
+    // is used from the ENCAPSULATED_RESPONSE_ACK on.
+    fn write_spdm_encap_key_update_response(
+        &mut self,
+        session_id: SpdmSessionId,
+        key_update: &SpdmKeyUpdateRequestPayload,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        debug!("!!! encapsulated key_update : {:02x?}\n", key_update);
+        let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
+        let session = self.common.get_session_mut(session_id)?;
+        if session.get_session_state() != SpdmSessionState::SpdmSessionEstablished {
+            self.write_spdm_encap_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return Ok(());
+        }
+        match key_update.key_update_operation {
+            SpdmKeyUpdateOperation::SpdmUpdateSingleKey => {
+                session.create_data_secret_update(spdm_version_sel, false, true)?;
+                session.activate_data_secret_update(spdm_version_sel, false, true, true)?;
+                self.common
+                    .notify_session_event(session_id.as_u32(), SpdmSessionEvent::KeyUpdated);
+            }
+            SpdmKeyUpdateOperation::SpdmVerifyNewKey => {}
+            _ => {
+                self.write_spdm_encap_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return Ok(());
+            }
+        }
+
+        info!("send encapsulated spdm key_update_ack\n");
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseKeyUpdateAck,
+            },
+            payload: SpdmMessagePayload::SpdmKeyUpdateResponse(SpdmKeyUpdateResponsePayload {
+                key_update_operation: key_update.key_update_operation,
+                tag: key_update.tag,
+            }),
+        };
+        response.spdm_encode(&mut self.common, writer);
+        Ok(())
+    }
+
+    fn write_spdm_encap_error(
+        &mut self,
+        error_code: SpdmErrorCode,
+        error_data: u8,
+        writer: &mut Writer,
+    ) {
+        let error = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseError,
+            },
+            payload: SpdmMessagePayload::SpdmErrorResponse(SpdmErrorResponsePayload {
+                error_code,
+                error_data,
+                extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataNone(
+                    SpdmErrorResponseNoneExtData {},
+                ),
+            }),
+        };
+        error.spdm_encode(&mut self.common, writer);
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::testlib::*;
+
+    #[test]
+    fn test_case0_encode_spdm_deliver_encapsulated_response() {
+        let (req_config_info, req_provision_info) = create_info();
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_requester = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        requester.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
+            data_size: 512u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        });
+
+        let encap_request = &mut [0u8; 16];
+        let mut writer = Writer::init(encap_request);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetDigests,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetDigestsRequestPayload {};
+        value.spdm_encode(&mut requester.common, &mut writer);
+        let encap_request_used = writer.used();
+
+        let send_buffer = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = requester
+            .encode_spdm_deliver_encapsulated_response(
+                Some(SpdmSessionId::from(4294901758)),
+                3,
+                &encap_request[..encap_request_used],
+                send_buffer,
+            )
+            .unwrap();
+
+        let mut reader = Reader::init(&send_buffer[..send_used]);
+        let spdm_message = SpdmMessage::spdm_read(&mut requester.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse
+        );
+        if let SpdmMessagePayload::SpdmDeliverEncapsulatedResponseRequest(payload) =
+            &spdm_message.payload
+        {
+            assert_eq!(payload.request_id, 3);
+        }
+        let encap_response = SpdmMessage::spdm_read(&mut requester.common, &mut reader).unwrap();
+        assert_eq!(
+            encap_response.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseDigests
+        );
+        if let SpdmMessagePayload::SpdmDigestsResponse(payload) = &encap_response.payload {
+            assert_eq!(payload.slot_mask, 0x1);
+        }
+    }
+
+    #[test]
+    fn test_case1_encode_spdm_deliver_encapsulated_response() {
+        let (req_config_info, req_provision_info) = create_info();
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_requester = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        let encap_request = &mut [0u8; 16];
+        let mut writer = Writer::init(encap_request);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetVersion,
+        };
+        value.encode(&mut writer);
+        let encap_request_used = writer.used();
+
+        let send_buffer = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = requester
+            .encode_spdm_deliver_encapsulated_response(
+                Some(SpdmSessionId::from(4294901758)),
+                1,
+                &encap_request[..encap_request_used],
+                send_buffer,
+            )
+            .unwrap();
+
+        let mut reader = Reader::init(&send_buffer[..send_used]);
+        SpdmMessage::spdm_read(&mut requester.common, &mut reader).unwrap();
+        let encap_response = SpdmMessage::spdm_read(&mut requester.common, &mut reader).unwrap();
+        assert_eq!(
+            encap_response.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &encap_response.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnsupportedRequest
+            );
+            assert_eq!(
+                payload.error_data,
+                SpdmRequestResponseCode::SpdmRequestGetVersion.get_u8()
+            );
+        }
+    }
+}
@@ -2,19 +2,21 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
+use crate::protocol::*;
 use crate::requester::*;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_end_session(&mut self, session_id: u32) -> SpdmResult {
+    pub fn send_receive_spdm_end_session(&mut self, session_id: SpdmSessionId) -> SpdmResult {
         info!("send spdm end_session\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.encode_spdm_end_session(&mut send_buffer);
-        self.send_secured_message(session_id, &send_buffer[..used], false)?;
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], false)?;
 
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let used = self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
         self.handle_spdm_end_session_response(session_id, &receive_buffer[..used])
     }
 
@@ -27,7 +29,7 @@ impl<'a> RequesterContext<'a> {
                 request_response_code: SpdmRequestResponseCode::SpdmRequestEndSession,
             },
             payload: SpdmMessagePayload::SpdmEndSessionRequest(SpdmEndSessionRequestPayload {
-                end_session_request_attributes: SpdmEndSessionRequestAttributes::empty(),
+                end_session_request_attributes: self.get_spdm_end_session_attributes(),
             }),
         };
         request.spdm_encode(&mut self.common, &mut writer);
@@ -36,7 +38,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_end_session_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         receive_buffer: &[u8],
     ) -> SpdmResult {
         let mut reader = Reader::init(receive_buffer);
@@ -48,17 +50,29 @@ impl<'a> RequesterContext<'a> {
                     if let Some(end_session_rsp) = end_session_rsp {
                         debug!("!!! end_session rsp : {:02x?}\n", end_session_rsp);
 
-                        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                            s
-                        } else {
-                            return spdm_result_err!(EFAULT);
-                        };
-                        session.teardown(session_id)?;
+                        let session = self.common.get_session_mut(session_id)?;
+                        session.teardown(session_id.as_u32())?;
+                        self.common.notify_session_event(
+                            session_id.as_u32(),
+                            SpdmSessionEvent::Terminated,
+                        );
+
+                        if !self
+                            .get_spdm_end_session_attributes()
+                            .contains(SpdmEndSessionRequestAttributes::PRESERVE_NEGOTIATED_STATE)
+                            && self
+                                .common
+                                .negotiate_info
+                                .rsp_capabilities_sel
+                                .contains(SpdmResponseCapabilityFlags::CACHE_CAP)
+                        {
+                            self.common.reset_negotiate_info();
+                        }
 
                         Ok(())
                     } else {
                         error!("!!! end_session : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -77,12 +91,26 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    // The negotiated state can only be preserved by a responder that caches it.
+    fn get_spdm_end_session_attributes(&self) -> SpdmEndSessionRequestAttributes {
+        if self
+            .common
+            .negotiate_info
+            .rsp_capabilities_sel
+            .contains(SpdmResponseCapabilityFlags::CACHE_CAP)
+        {
+            self.common.config_info.end_session_attributes
+        } else {
+            SpdmEndSessionRequestAttributes::empty()
         }
     }
 }
@@ -150,7 +178,81 @@ mod tests_requester {
         requester.common.session[0]
             .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
 
-        let status = requester.end_session(session_id).is_ok();
+        let status = requester
+            .end_session(SpdmSessionId::from(session_id))
+            .is_ok();
         assert!(status);
     }
+
+    #[test]
+    fn test_case1_send_receive_spdm_end_session() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        responder.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::CACHE_CAP;
+        let rsp_session_id = 0xffu16;
+        let session_id = (0xffu32 << 16) + rsp_session_id as u32;
+        responder.common.session = gen_array_clone(SpdmSession::new(), 4);
+        responder.common.session[0].setup(session_id).unwrap();
+        responder.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        responder.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        requester.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::CACHE_CAP;
+        let rsp_session_id = 0xffu16;
+        let session_id = (0xffu32 << 16) + rsp_session_id as u32;
+        requester.common.session = gen_array_clone(SpdmSession::new(), 4);
+        requester.common.session[0].setup(session_id).unwrap();
+        requester.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        requester.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
+
+        requester
+            .send_receive_spdm_end_session(SpdmSessionId::from(session_id))
+            .unwrap();
+        assert_eq!(requester.common.session[0].get_session_id(), 0);
+        // the negotiated state is not preserved without PRESERVE_NEGOTIATED_STATE
+        assert!(requester
+            .common
+            .negotiate_info
+            .rsp_capabilities_sel
+            .is_empty());
+    }
 }
@@ -0,0 +1,350 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionId;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    pub fn send_receive_spdm_get_supported_event_types(
+        &mut self,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult<SpdmSupportedEventTypesResponsePayload> {
+        info!("send spdm get_supported_event_types\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.encode_spdm_get_supported_event_types(&mut send_buffer);
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], false)?;
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
+        self.handle_spdm_supported_event_types_response(session_id, &receive_buffer[..used])
+    }
+
+    pub fn encode_spdm_get_supported_event_types(&mut self, buf: &mut [u8]) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes,
+            },
+            payload: SpdmMessagePayload::SpdmGetSupportedEventTypesRequest(
+                SpdmGetSupportedEventTypesRequestPayload {},
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn handle_spdm_supported_event_types_response(
+        &mut self,
+        session_id: SpdmSessionId,
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmSupportedEventTypesResponsePayload> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseSupportedEventTypes => {
+                    let supported_event_types = SpdmSupportedEventTypesResponsePayload::spdm_read(
+                        &mut self.common,
+                        &mut reader,
+                    );
+                    if let Some(supported_event_types) = supported_event_types {
+                        debug!(
+                            "!!! supported_event_types : {:02x?}\n",
+                            supported_event_types.event_group_count
+                        );
+                        Ok(supported_event_types)
+                    } else {
+                        error!("!!! supported_event_types : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        Some(session_id),
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes,
+                        SpdmRequestResponseCode::SpdmResponseSupportedEventTypes,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_supported_event_types_response(
+                                session_id,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    pub fn send_receive_spdm_subscribe_event_types(
+        &mut self,
+        session_id: SpdmSessionId,
+        subscribe_event_group_count: u8,
+        subscribe_list: &SpdmEventGroupListStruct,
+    ) -> SpdmResult {
+        info!("send spdm subscribe_event_types\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.encode_spdm_subscribe_event_types(
+            subscribe_event_group_count,
+            subscribe_list,
+            &mut send_buffer,
+        );
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], false)?;
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
+        self.handle_spdm_subscribe_event_types_ack_response(session_id, &receive_buffer[..used])
+    }
+
+    pub fn encode_spdm_subscribe_event_types(
+        &mut self,
+        subscribe_event_group_count: u8,
+        subscribe_list: &SpdmEventGroupListStruct,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes,
+            },
+            payload: SpdmMessagePayload::SpdmSubscribeEventTypesRequest(
+                SpdmSubscribeEventTypesRequestPayload {
+                    subscribe_event_group_count,
+                    subscribe_list: subscribe_list.clone(),
+                },
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn handle_spdm_subscribe_event_types_ack_response(
+        &mut self,
+        session_id: SpdmSessionId,
+        receive_buffer: &[u8],
+    ) -> SpdmResult {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck => {
+                    let subscribe_event_types_ack =
+                        SpdmSubscribeEventTypesAckResponsePayload::spdm_read(
+                            &mut self.common,
+                            &mut reader,
+                        );
+                    if let Some(subscribe_event_types_ack) = subscribe_event_types_ack {
+                        debug!(
+                            "!!! subscribe_event_types_ack : {:02x?}\n",
+                            subscribe_event_types_ack
+                        );
+                        Ok(())
+                    } else {
+                        error!("!!! subscribe_event_types_ack : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        Some(session_id),
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes,
+                        SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_subscribe_event_types_ack_response(
+                                session_id,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    /// Wait for a SEND_EVENT from the responder and acknowledge it with EVENT_ACK.
+    pub fn receive_spdm_event(
+        &mut self,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult<SpdmSendEventRequestPayload> {
+        let session_id = session_id.as_u32();
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+
+        let mut reader = Reader::init(&receive_buffer[..used]);
+        let send_event = match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmRequestSendEvent => {
+                    SpdmSendEventRequestPayload::spdm_read(&mut self.common, &mut reader)
+                }
+                _ => return spdm_result_err!(EINVAL),
+            },
+            None => return spdm_result_err!(Codec),
+        };
+        let send_event = if let Some(send_event) = send_event {
+            debug!("!!! send_event : {:02x?}\n", send_event.event_count);
+            send_event
+        } else {
+            error!("!!! send_event : fail !!!\n");
+            return spdm_result_err!(Codec);
+        };
+
+        info!("send spdm event_ack\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseEventAck,
+            },
+            payload: SpdmMessagePayload::SpdmEventAckResponse(SpdmEventAckResponsePayload {}),
+        };
+        response.spdm_encode(&mut self.common, &mut writer);
+        let used = writer.used();
+        self.send_secured_message(session_id, &send_buffer[..used], false)?;
+
+        Ok(send_event)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::common::session::{SpdmSession, SpdmSessionState};
+    use crate::secret::SpdmEventProvider;
+    use crate::testlib::*;
+    use crate::{crypto, responder, secret};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    const EVENT_GROUPS_LIST: [u8; 4] = [0x01, 0x00, 0x01, 0x00];
+
+    static SUBSCRIBED_SESSION_ID: AtomicU32 = AtomicU32::new(0);
+
+    static EVENT_PROVIDER: SpdmEventProvider = SpdmEventProvider {
+        get_supported_event_types_cb:
+            |supported_event_groups_list: &mut SpdmEventGroupListStruct| -> SpdmResult<u8> {
+                supported_event_groups_list.data[..EVENT_GROUPS_LIST.len()]
+                    .copy_from_slice(&EVENT_GROUPS_LIST);
+                supported_event_groups_list.data_size = EVENT_GROUPS_LIST.len() as u32;
+                Ok(1)
+            },
+        subscribe_event_types_cb: |session_id: u32,
+                                   subscribe_event_group_count: u8,
+                                   subscribe_list: &SpdmEventGroupListStruct|
+         -> SpdmResult {
+            if subscribe_event_group_count != 1 || subscribe_list.as_ref() != EVENT_GROUPS_LIST {
+                return spdm_result_err!(EINVAL);
+            }
+            SUBSCRIBED_SESSION_ID.store(session_id, Ordering::SeqCst);
+            Ok(())
+        },
+    };
+
+    fn setup_session(common: &mut crate::common::SpdmContext, session_id: u32) {
+        common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion13;
+        common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        common.session = gen_array_clone(SpdmSession::new(), 4);
+        common.session[0].setup(session_id).unwrap();
+        common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        common.session[0].set_session_state(SpdmSessionState::SpdmSessionEstablished);
+    }
+
+    #[test]
+    fn test_case0_send_receive_spdm_events() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::EVENT_CAP;
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        secret::register_event_provider(EVENT_PROVIDER.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let session_id = (0xffu32 << 16) + 0xff;
+        setup_session(&mut responder.common, session_id);
+
+        // an event of a group the requester subscribed to, sent before any
+        // request of the requester
+        let mut subscribe_list = SpdmEventGroupListStruct::default();
+        subscribe_list.data[..EVENT_GROUPS_LIST.len()].copy_from_slice(&EVENT_GROUPS_LIST);
+        subscribe_list.data_size = EVENT_GROUPS_LIST.len() as u32;
+        responder.common.session[0].set_event_subscription(1, &subscribe_list);
+        let mut event = SpdmEventStruct {
+            event_instance_id: 7,
+            event_type_id: 1,
+            event_detail_len: 2,
+            ..Default::default()
+        };
+        event.event_detail[..2].copy_from_slice(&[0x5a, 0xa5]);
+        responder.publish_spdm_event(session_id, &event).unwrap();
+        responder.send_spdm_events(session_id).unwrap();
+        assert_eq!(responder.common.session[0].get_sent_event_count(), 1);
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        setup_session(&mut requester.common, session_id);
+        let spdm_session_id = SpdmSessionId::from(session_id);
+
+        let send_event = requester.receive_spdm_event(spdm_session_id).unwrap();
+        assert_eq!(send_event.event_count, 1);
+        assert_eq!(send_event.event_list[0].event_instance_id, 7);
+        assert_eq!(send_event.event_list[0].event_type_id, 1);
+        assert_eq!(send_event.event_list[0].event_detail[..2], [0x5a, 0xa5]);
+
+        let supported_event_types = requester
+            .send_receive_spdm_get_supported_event_types(spdm_session_id)
+            .unwrap();
+        assert_eq!(supported_event_types.event_group_count, 1);
+        assert_eq!(
+            supported_event_types.supported_event_groups_list.as_ref(),
+            EVENT_GROUPS_LIST
+        );
+
+        requester
+            .send_receive_spdm_subscribe_event_types(
+                spdm_session_id,
+                1,
+                &supported_event_types.supported_event_groups_list,
+            )
+            .unwrap();
+        assert_eq!(SUBSCRIBED_SESSION_ID.load(Ordering::SeqCst), session_id);
+    }
+}
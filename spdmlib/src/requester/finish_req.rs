@@ -2,7 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-#[cfg(feature = "hash-update")]
+use crate::common::session::SpdmSessionId;
 use crate::crypto;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
@@ -14,15 +14,27 @@ use alloc::boxed::Box;
 use crate::common::ManagedBuffer;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_finish(&mut self, slot_id: u8, session_id: u32) -> SpdmResult {
+    pub fn send_receive_spdm_finish(
+        &mut self,
+        slot_id: u8,
+        session_id: SpdmSessionId,
+    ) -> SpdmResult {
         info!("send spdm finish\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let in_clear_text = self
+            .common
+            .get_session(session_id)?
+            .get_handshake_in_the_clear();
         let (send_used, base_hash_size, message_f) =
             self.encode_spdm_finish(session_id, slot_id, &mut send_buffer)?;
-        self.send_secured_message(session_id, &send_buffer[..send_used], false)?;
-
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let receive_used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let receive_used = if in_clear_text {
+            self.send_session_message_in_clear(session_id.as_u32(), &send_buffer[..send_used])?;
+            self.receive_message(&mut receive_buffer, true)?
+        } else {
+            self.send_secured_message(session_id.as_u32(), &send_buffer[..send_used], false)?;
+            self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?
+        };
         self.handle_spdm_finish_response(
             session_id,
             slot_id,
@@ -34,10 +46,53 @@ impl<'a> RequesterContext<'a> {
 
     pub fn encode_spdm_finish(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         slot_id: u8,
         buf: &mut [u8],
     ) -> SpdmResult<(usize, usize, ManagedBuffer)> {
+        let session = self.common.get_session(session_id)?;
+        let mut_auth_requested = !session.mut_auth_requested.is_empty();
+        let (finish_request_attributes, req_slot_id, signature) = if mut_auth_requested {
+            (
+                SpdmFinishRequestAttributes::SIGNATURE_INCLUDED,
+                session.req_slot_id,
+                SpdmSignatureStruct {
+                    data_size: self.common.negotiate_info.req_asym_sel.get_size(),
+                    data: [0xbb; SPDM_MAX_ASYM_KEY_SIZE],
+                },
+            )
+        } else {
+            (
+                SpdmFinishRequestAttributes::empty(),
+                slot_id,
+                SpdmSignatureStruct::default(),
+            )
+        };
+
+        if mut_auth_requested {
+            // the requester certificate chain hash follows message_k in the transcript
+            let cert_chain_hash = self
+                .common
+                .get_certchain_hash_rsp(req_slot_id, false)
+                .ok_or_else(|| spdm_err!(EINVAL))?;
+            let session = self.common.get_session_mut(session_id)?;
+            #[cfg(not(feature = "hash-update"))]
+            session
+                .runtime_info
+                .message_k
+                .append_message(cert_chain_hash.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            #[cfg(feature = "hash-update")]
+            crypto::hash::hash_ctx_update(
+                session
+                    .runtime_info
+                    .message_k
+                    .as_mut()
+                    .ok_or_else(|| spdm_err!(EFAULT))?,
+                cert_chain_hash.as_ref(),
+            );
+        }
+
         let mut writer = Writer::init(buf);
 
         let request = SpdmMessage {
@@ -46,9 +101,9 @@ impl<'a> RequesterContext<'a> {
                 request_response_code: SpdmRequestResponseCode::SpdmRequestFinish,
             },
             payload: SpdmMessagePayload::SpdmFinishRequest(SpdmFinishRequestPayload {
-                finish_request_attributes: SpdmFinishRequestAttributes::empty(),
-                req_slot_id: slot_id,
-                signature: SpdmSignatureStruct::default(),
+                finish_request_attributes,
+                req_slot_id,
+                signature,
                 verify_data: SpdmDigestStruct {
                     data_size: self.common.negotiate_info.base_hash_sel.get_size(),
                     data: Box::new([0xcc; SPDM_MAX_HASH_SIZE]),
@@ -62,6 +117,17 @@ impl<'a> RequesterContext<'a> {
         let base_hash_size = self.common.negotiate_info.base_hash_sel.get_size() as usize;
         let temp_used = send_used - base_hash_size;
 
+        if mut_auth_requested {
+            let req_asym_size = self.common.negotiate_info.req_asym_sel.get_size() as usize;
+            let signature = self.generate_finish_req_signature(
+                slot_id,
+                session_id,
+                &buf[..(temp_used - req_asym_size)],
+            )?;
+            // patch the message before send
+            buf[(temp_used - req_asym_size)..temp_used].copy_from_slice(signature.as_ref());
+        }
+
         #[cfg(not(feature = "hash-update"))]
         {
             let mut message_f = ManagedBuffer::default();
@@ -69,11 +135,7 @@ impl<'a> RequesterContext<'a> {
                 .append_message(&buf[..temp_used])
                 .ok_or(spdm_err!(ENOMEM))?;
 
-            let session = if let Some(s) = self.common.get_immutable_session_via_id(session_id) {
-                s
-            } else {
-                return spdm_result_err!(EFAULT);
-            };
+            let session = self.common.get_session(session_id)?;
             let message_k = &session.runtime_info.message_k;
 
             let transcript_data = self.common.calc_req_transcript_data(
@@ -82,11 +144,7 @@ impl<'a> RequesterContext<'a> {
                 message_k,
                 Some(&message_f),
             )?;
-            let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                s
-            } else {
-                return spdm_result_err!(EFAULT);
-            };
+            let session = self.common.get_session_mut(session_id)?;
 
             let hmac = session.generate_hmac_with_request_finished_key(transcript_data.as_ref())?;
             message_f
@@ -100,11 +158,7 @@ impl<'a> RequesterContext<'a> {
 
         #[cfg(feature = "hash-update")]
         {
-            let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                s
-            } else {
-                return spdm_result_err!(EFAULT);
-            };
+            let session = self.common.get_session_mut(session_id)?;
             let mut message_f = session.runtime_info.message_k.as_mut().cloned();
             crypto::hash::hash_ctx_update(message_f.as_mut().unwrap(), &buf[..temp_used]);
             let hmac = session.generate_hmac_with_request_finished_key(
@@ -120,25 +174,124 @@ impl<'a> RequesterContext<'a> {
         }
     }
 
+    #[cfg(feature = "hash-update")]
+    pub fn generate_finish_req_signature(
+        &mut self,
+        _slot_id: u8,
+        session_id: SpdmSessionId,
+        message_f: &[u8],
+    ) -> SpdmResult<SpdmSignatureStruct> {
+        let session = self.common.get_session(session_id)?;
+        let mut message_k = session
+            .runtime_info
+            .message_k
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| spdm_err!(EFAULT))?;
+        crypto::hash::hash_ctx_update(&mut message_k, message_f);
+        let message_hash =
+            crypto::hash::hash_ctx_finalize(message_k).ok_or_else(|| spdm_err!(Crypto))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        let mut message = ManagedBuffer::default();
+        if let Some(signing_prefix_context) = self
+            .common
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            message
+                .append_message(signing_prefix_context)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_12)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_FINISH_SIGN_CONTEXT)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+        message
+            .append_message(message_hash.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+        // signed with the requester key of the slot KEY_EXCHANGE_RSP asked for
+        let req_slot_id = self.common.get_session(session_id)?.req_slot_id;
+        self.common.sign_with_slot_key(
+            req_slot_id,
+            SpdmBaseAsymAlgo::from_bits_truncate(
+                self.common.negotiate_info.req_asym_sel.bits() as u32
+            ),
+            message.as_ref(),
+        )
+    }
+
+    #[cfg(not(feature = "hash-update"))]
+    pub fn generate_finish_req_signature(
+        &mut self,
+        slot_id: u8,
+        session_id: SpdmSessionId,
+        message_f: &[u8],
+    ) -> SpdmResult<SpdmSignatureStruct> {
+        let session = self.common.get_session(session_id)?;
+        let mut message_f_buffer = ManagedBuffer::default();
+        message_f_buffer
+            .append_message(message_f)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let mut message = self.common.calc_req_transcript_data(
+            slot_id,
+            false,
+            &session.runtime_info.message_k,
+            Some(&message_f_buffer),
+        )?;
+        // we dont need create message hash for verify
+        // we just print message hash for debug purpose
+        let message_hash =
+            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
+                .ok_or_else(|| spdm_err!(EFAULT))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        if let Some(signing_prefix_context) = self
+            .common
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            message.reset_message();
+            message
+                .append_message(signing_prefix_context)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_12)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_FINISH_SIGN_CONTEXT)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(message_hash.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+
+        // signed with the requester key of the slot KEY_EXCHANGE_RSP asked for
+        let req_slot_id = self.common.get_session(session_id)?.req_slot_id;
+        self.common.sign_with_slot_key(
+            req_slot_id,
+            SpdmBaseAsymAlgo::from_bits_truncate(
+                self.common.negotiate_info.req_asym_sel.bits() as u32
+            ),
+            message.as_ref(),
+        )
+    }
+
     pub fn handle_spdm_finish_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         slot_id: u8,
         base_hash_size: usize,
         #[cfg(not(feature = "hash-update"))] mut message_f: ManagedBuffer,
         #[cfg(feature = "hash-update")] message_f: ManagedBuffer, // never use message_f for hash-update, use session.runtime_info.message_f
         receive_buffer: &[u8],
     ) -> SpdmResult {
-        let in_clear_text = self
-            .common
-            .negotiate_info
-            .req_capabilities_sel
-            .contains(SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
-            && self
-                .common
-                .negotiate_info
-                .rsp_capabilities_sel
-                .contains(SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP);
+        let in_clear_text = self.common.is_handshake_in_the_clear();
 
         let mut reader = Reader::init(receive_buffer);
         match SpdmMessageHeader::read(&mut reader) {
@@ -152,13 +305,7 @@ impl<'a> RequesterContext<'a> {
 
                         if in_clear_text {
                             #[cfg(not(feature = "hash-update"))]
-                            let session = if let Some(s) =
-                                self.common.get_immutable_session_via_id(session_id)
-                            {
-                                s
-                            } else {
-                                return spdm_result_err!(EFAULT);
-                            };
+                            let session = self.common.get_session(session_id)?;
                             #[cfg(not(feature = "hash-update"))]
                             let message_k = &session.runtime_info.message_k;
 
@@ -177,12 +324,7 @@ impl<'a> RequesterContext<'a> {
                                 Some(&message_f),
                             )?;
 
-                            let session =
-                                if let Some(s) = self.common.get_session_via_id(session_id) {
-                                    s
-                                } else {
-                                    return spdm_result_err!(EFAULT);
-                                };
+                            let session = self.common.get_session_mut(session_id)?;
 
                             #[cfg(feature = "hash-update")]
                             crypto::hash::hash_ctx_update(
@@ -206,8 +348,16 @@ impl<'a> RequesterContext<'a> {
                                 .is_err()
                             {
                                 error!("verify_hmac_with_response_finished_key fail");
-                                let _ = session.teardown(session_id);
-                                return spdm_result_err!(EFAULT);
+                                let _ = session.teardown(session_id.as_u32());
+                                self.common.notify_session_event(
+                                    session_id.as_u32(),
+                                    SpdmSessionEvent::HmacFailure,
+                                );
+                                self.common.notify_session_event(
+                                    session_id.as_u32(),
+                                    SpdmSessionEvent::Terminated,
+                                );
+                                return spdm_result_err!(VerificationFailed);
                             } else {
                                 info!("verify_hmac_with_response_finished_key pass");
                             }
@@ -225,12 +375,7 @@ impl<'a> RequesterContext<'a> {
                                 finish_rsp.verify_data.as_ref(),
                             );
                         } else {
-                            let session =
-                                if let Some(s) = self.common.get_session_via_id(session_id) {
-                                    s
-                                } else {
-                                    return spdm_result_err!(EFAULT);
-                                };
+                            let session = self.common.get_session_mut(session_id)?;
                             #[cfg(not(feature = "hash-update"))]
                             message_f
                                 .append_message(&receive_buffer[..receive_used])
@@ -247,12 +392,7 @@ impl<'a> RequesterContext<'a> {
                         }
 
                         #[cfg(not(feature = "hash-update"))]
-                        let session =
-                            if let Some(s) = self.common.get_immutable_session_via_id(session_id) {
-                                s
-                            } else {
-                                return spdm_result_err!(EFAULT);
-                            };
+                        let session = self.common.get_session(session_id)?;
                         #[cfg(not(feature = "hash-update"))]
                         let message_k = &session.runtime_info.message_k;
                         // generate the data secret
@@ -265,11 +405,7 @@ impl<'a> RequesterContext<'a> {
                         )?;
 
                         #[cfg(feature = "hash-update")]
-                        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                            s
-                        } else {
-                            return spdm_result_err!(EFAULT);
-                        };
+                        let session = self.common.get_session_mut(session_id)?;
 
                         #[cfg(feature = "hash-update")]
                         let th2 = crypto::hash::hash_ctx_finalize(
@@ -278,11 +414,7 @@ impl<'a> RequesterContext<'a> {
                         .unwrap();
                         debug!("!!! th2 : {:02x?}\n", th2.as_ref());
                         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-                        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                            s
-                        } else {
-                            return spdm_result_err!(EFAULT);
-                        };
+                        let session = self.common.get_session_mut(session_id)?;
                         match session.generate_data_secret(spdm_version_sel, &th2) {
                             Ok(_) => {}
                             Err(e) => {
@@ -292,16 +424,25 @@ impl<'a> RequesterContext<'a> {
                         session.set_session_state(
                             crate::common::session::SpdmSessionState::SpdmSessionEstablished,
                         );
+                        self.common.notify_session_event(
+                            session_id.as_u32(),
+                            SpdmSessionEvent::Established,
+                        );
 
                         Ok(())
                     } else {
                         error!("!!! finish : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
+                    // RESPOND_IF_READY goes the way FINISH went
                     let erm = self.spdm_handle_error_response_main(
-                        Some(session_id),
+                        if in_clear_text {
+                            None
+                        } else {
+                            Some(session_id)
+                        },
                         receive_buffer,
                         SpdmRequestResponseCode::SpdmRequestFinish,
                         SpdmRequestResponseCode::SpdmResponseFinishRsp,
@@ -318,12 +459,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -366,7 +507,7 @@ mod tests_requester {
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
 
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         responder.common.reset_runtime_info();
 
@@ -468,8 +609,9 @@ mod tests_requester {
                 data: Box::new([0; SPDM_MAX_HASH_SIZE]),
             },
         );
-        // let _ = requester.send_receive_spdm_finish(4294901758);
-        let status = requester.send_receive_spdm_finish(0, 4294901758).is_ok();
+        let status = requester
+            .send_receive_spdm_finish(0, SpdmSessionId::from(4294901758u32))
+            .is_ok();
         assert!(status);
     }
 }
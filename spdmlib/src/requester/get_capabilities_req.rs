@@ -2,20 +2,27 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
 use crate::requester::*;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_capability(&mut self) -> SpdmResult {
+    /// GET_CAPABILITIES, returns the capabilities of the responder.
+    pub fn send_receive_spdm_capability(&mut self) -> SpdmResult<SpdmResponseCapabilityFlags> {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let send_used = self.encode_spdm_capability(&mut send_buffer);
         self.send_message(&send_buffer[..send_used])?;
 
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.receive_message(&mut receive_buffer, false)?;
-        self.handle_spdm_capability_response(0, &send_buffer[..send_used], &receive_buffer[..used])
+        self.handle_spdm_capability_response(
+            SpdmSessionId::default(),
+            &send_buffer[..send_used],
+            &receive_buffer[..used],
+        )?;
+        Ok(self.common.negotiate_info.rsp_capabilities_sel)
     }
 
     pub fn encode_spdm_capability(&mut self, buf: &mut [u8]) -> usize {
@@ -40,7 +47,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_capability_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         send_buffer: &[u8],
         receive_buffer: &[u8],
     ) -> SpdmResult {
@@ -60,7 +67,8 @@ impl<'a> RequesterContext<'a> {
                         self.common.negotiate_info.rsp_ct_exponent_sel = capabilities.ct_exponent;
                         self.common.negotiate_info.rsp_capabilities_sel = capabilities.flags;
 
-                        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12
+                        if self.common.negotiate_info.spdm_version_sel.get_u8()
+                            >= SpdmVersion::SpdmVersion12.get_u8()
                         {
                             self.common.negotiate_info.req_data_transfer_size_sel =
                                 self.common.config_info.data_transfer_size;
@@ -71,6 +79,8 @@ impl<'a> RequesterContext<'a> {
                             self.common.negotiate_info.rsp_max_spdm_msg_size_sel =
                                 capabilities.max_spdm_msg_size;
                         }
+                        // chunking applies from the next request on
+                        self.common.update_chunk_cap_negotiated();
 
                         let message_a = &mut self.common.runtime_info.message_a;
                         message_a
@@ -81,7 +91,7 @@ impl<'a> RequesterContext<'a> {
                             .map_or_else(|| spdm_result_err!(ENOMEM), |_| Ok(()))
                     } else {
                         error!("!!! capabilities : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -101,12 +111,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -2,16 +2,52 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::cert_chain::{
+    spdm_parse_cert_chain, spdm_split_cert_chain, spdm_verify_cert_chain_with_trust_anchors,
+};
+use crate::common::session::SpdmSessionId;
+use crate::common::x509::{spdm_parse_leaf_cert, SpdmCertInfo};
 use crate::crypto;
-use crate::error::{spdm_result_err, SpdmResult};
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
 use crate::requester::*;
 
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// The certificate chain of a slot, retrieved and verified by
+/// get_all_certificates, or why it could not be.
+#[derive(Debug)]
+pub struct SpdmSlotCertChain {
+    pub slot_id: u8,
+    pub cert_chain: SpdmResult<SpdmCertChainData>,
+}
+
+/// The slots the responder reported in DIGESTS, in slot order.
+#[derive(Debug, Default)]
+pub struct SpdmCertChainReport {
+    pub slots: Vec<SpdmSlotCertChain>,
+}
+
+impl SpdmCertChainReport {
+    /// Every slot holds a verified certificate chain.
+    pub fn is_success(&self) -> bool {
+        self.slots.iter().all(|slot| slot.cert_chain.is_ok())
+    }
+
+    pub fn get_cert_chain(&self, slot_id: u8) -> Option<&SpdmCertChainData> {
+        self.slots
+            .iter()
+            .find(|slot| slot.slot_id == slot_id)
+            .and_then(|slot| slot.cert_chain.as_ref().ok())
+    }
+}
+
 impl<'a> RequesterContext<'a> {
     fn send_receive_spdm_certificate_partial(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         slot_id: u8,
         offset: u16,
         length: u16,
@@ -28,6 +64,7 @@ impl<'a> RequesterContext<'a> {
             session_id,
             slot_id,
             offset,
+            length,
             &send_buffer[..send_used],
             &receive_buffer[..used],
         )
@@ -60,9 +97,10 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_certificate_partial_response(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         slot_id: u8,
         offset: u16,
+        length: u16,
         send_buffer: &[u8],
         receive_buffer: &[u8],
     ) -> SpdmResult<(u16, u16)> {
@@ -81,6 +119,13 @@ impl<'a> RequesterContext<'a> {
                         {
                             return spdm_result_err!(ENOMEM);
                         }
+                        if certificate.portion_length > length
+                            || (certificate.portion_length == 0
+                                && certificate.remainder_length != 0)
+                        {
+                            error!("portion length invalid!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
                         if certificate.slot_id != slot_id {
                             error!("slot id is not match between requester and responder!\n");
                             return spdm_result_err!(EINVAL);
@@ -108,6 +153,7 @@ impl<'a> RequesterContext<'a> {
                             .unwrap()
                             .cert_chain
                             .data_size = offset + certificate.portion_length;
+                        self.common.peer_info.peer_cert_chain_hash[slot_id as usize] = None;
 
                         #[cfg(not(feature = "hash-update"))]
                         {
@@ -135,7 +181,7 @@ impl<'a> RequesterContext<'a> {
                         Ok((certificate.portion_length, certificate.remainder_length))
                     } else {
                         error!("!!! certificate : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -153,113 +199,168 @@ impl<'a> RequesterContext<'a> {
                                 session_id,
                                 slot_id,
                                 offset,
+                                length,
                                 send_buffer,
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 
     pub fn send_receive_spdm_certificate(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         slot_id: u8,
-    ) -> SpdmResult {
+    ) -> SpdmResult<SpdmCertChainData> {
+        if !self.common.is_peer_slot_provisioned(slot_id) {
+            error!("!!! slot {} is not in the peer slot mask !!!\n", slot_id);
+            return spdm_result_err!(EINVAL);
+        }
+
+        let max_portion_len = self.common.get_max_cert_portion_len(session_id.is_some());
         let mut offset = 0u16;
-        let mut length = config::MAX_SPDM_CERT_PORTION_LEN as u16;
+        let mut length = max_portion_len;
+        let mut total_length: Option<usize> = None;
         while length != 0 {
             let result =
                 self.send_receive_spdm_certificate_partial(session_id, slot_id, offset, length);
             match result {
                 Ok((portion_length, remainder_length)) => {
-                    offset += portion_length;
-                    length = remainder_length;
-                    if length > config::MAX_SPDM_CERT_PORTION_LEN as u16 {
-                        length = config::MAX_SPDM_CERT_PORTION_LEN as u16;
-                    }
+                    let (next_offset, next_length) = self.next_certificate_portion(
+                        session_id,
+                        offset,
+                        portion_length,
+                        remainder_length,
+                        &mut total_length,
+                    )?;
+                    offset = next_offset;
+                    length = next_length;
                 }
                 Err(_) => return spdm_result_err!(EIO),
             }
         }
-        self.verify_spdm_certificate_chain(slot_id)
+        self.verify_spdm_certificate_chain(slot_id)?;
+        self.common.cache_peer_cert_chain_hash(slot_id)?;
+        self.get_peer_cert_chain_data(slot_id)
     }
 
-    pub fn verify_spdm_certificate_chain(&mut self, slot_id: u8) -> SpdmResult {
-        // verify
-        if let Some(peer_cert_chain_data) = &self.common.provision_info.peer_cert_chain_data {
-            //
-            // TBD: Verify cert chain
-            //
-            if self.common.peer_info.peer_cert_chain[slot_id as usize].is_none() {
-                error!("peer_cert_chain is not populated!\n");
-                return spdm_result_err!(EIO);
+    /// GET_DIGESTS, then GET_CERTIFICATE and verification of every slot in
+    /// its slot mask. A slot failing does not stop the others, only a
+    /// failure of GET_DIGESTS is an error.
+    pub fn get_all_certificates(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+    ) -> SpdmResult<SpdmCertChainReport> {
+        let digests = self.send_receive_spdm_digest(session_id)?;
+        let mut report = SpdmCertChainReport::default();
+        let mut index = 0;
+        for slot_id in 0..SPDM_MAX_SLOT_NUMBER as u8 {
+            if digests.slot_mask & (1 << slot_id) == 0 {
+                continue;
             }
-            if self.common.peer_info.peer_cert_chain[slot_id as usize]
-                .as_ref()
-                .unwrap()
-                .cert_chain
-                .data_size
-                <= (4 + self.common.negotiate_info.base_hash_sel.get_size())
-            {
-                return spdm_result_err!(EIO);
+            let cert_chain = match self.send_receive_spdm_certificate(session_id, slot_id) {
+                Ok(cert_chain) => self
+                    .check_peer_cert_chain_digest(slot_id, &digests.digests[index])
+                    .map(|_| cert_chain),
+                Err(error) => Err(error),
+            };
+            if let Err(error) = &cert_chain {
+                error!("!!! cert_chain of slot {} : {:?} !!!\n", slot_id, error);
             }
+            report.slots.push(SpdmSlotCertChain {
+                slot_id,
+                cert_chain,
+            });
+            index += 1;
+        }
+        Ok(report)
+    }
 
-            let data_size = self.common.peer_info.peer_cert_chain[slot_id as usize]
-                .as_ref()
-                .unwrap()
-                .cert_chain
-                .data_size
-                - 4
-                - self.common.negotiate_info.base_hash_sel.get_size();
-            let mut data = [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE];
-            data[0..(data_size as usize)].copy_from_slice(
-                &self.common.peer_info.peer_cert_chain[slot_id as usize]
-                    .as_ref()
-                    .unwrap()
-                    .cert_chain
-                    .data[(4usize
-                    + self.common.negotiate_info.base_hash_sel.get_size() as usize)
-                    ..(self.common.peer_info.peer_cert_chain[slot_id as usize]
-                        .as_ref()
-                        .unwrap()
-                        .cert_chain
-                        .data_size as usize)],
-            );
-            let runtime_peer_cert_chain_data = SpdmCertChainData { data_size, data };
-
-            let (root_cert_begin, root_cert_end) =
-                crypto::cert_operation::get_cert_from_cert_chain(
-                    &runtime_peer_cert_chain_data.data
-                        [..(runtime_peer_cert_chain_data.data_size as usize)],
-                    0,
-                )?;
-            let root_cert = &runtime_peer_cert_chain_data.data[root_cert_begin..root_cert_end];
-            let root_hash = if let Some(rh) =
-                crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, root_cert)
-            {
-                rh
-            } else {
-                return spdm_result_err!(ESEC);
-            };
-            if root_hash.data[..(root_hash.data_size as usize)]
-                != self.common.peer_info.peer_cert_chain[slot_id as usize]
-                    .as_ref()
-                    .unwrap()
-                    .cert_chain
-                    .data[4usize
-                    ..(4usize + self.common.negotiate_info.base_hash_sel.get_size() as usize)]
-            {
-                error!("root_hash - fail!\n");
-                return spdm_result_err!(EINVAL);
+    // The chain retrieved must be the one DIGESTS reported for slot_id.
+    fn check_peer_cert_chain_digest(
+        &mut self,
+        slot_id: u8,
+        digest: &SpdmDigestStruct,
+    ) -> SpdmResult {
+        match self.common.get_certchain_hash_req(slot_id, false) {
+            Some(hash) if hash.as_ref() == digest.as_ref() => Ok(()),
+            _ => {
+                error!("cert_chain digest - fail!\n");
+                self.common.peer_info.peer_cert_chain[slot_id as usize] = None;
+                self.common.peer_info.peer_cert_chain_hash[slot_id as usize] = None;
+                spdm_result_err!(VerificationFailed)
             }
+        }
+    }
+
+    /// The certificate chain of slot_id retrieved from the responder.
+    pub fn get_peer_cert_chain_data(&self, slot_id: u8) -> SpdmResult<SpdmCertChainData> {
+        match self.common.peer_info.peer_cert_chain.get(slot_id as usize) {
+            Some(Some(peer_cert_chain)) => Ok(peer_cert_chain.cert_chain.clone()),
+            _ => spdm_result_err!(EINVAL),
+        }
+    }
+
+    /// Offset and length of the GET_CERTIFICATE following a portion, length
+    /// is 0 once the chain is complete.
+    pub(crate) fn next_certificate_portion(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        offset: u16,
+        portion_length: u16,
+        remainder_length: u16,
+        total_length: &mut Option<usize>,
+    ) -> SpdmResult<(u16, u16)> {
+        // offset + PortionLength + RemainderLength is the size of
+        // the whole chain and must not change between round trips.
+        let total = offset as usize + portion_length as usize + remainder_length as usize;
+        if total > config::MAX_SPDM_CERT_CHAIN_DATA_SIZE {
+            error!("cert chain too large!\n");
+            return spdm_result_err!(ENOMEM);
+        }
+        if *total_length.get_or_insert(total) != total {
+            error!("remainder length inconsistent!\n");
+            return spdm_result_err!(EFAULT);
+        }
+        let length =
+            remainder_length.min(self.common.get_max_cert_portion_len(session_id.is_some()));
+        Ok((offset + portion_length, length))
+    }
+
+    pub fn verify_spdm_certificate_chain(&mut self, slot_id: u8) -> SpdmResult {
+        let provision_info = &self.common.provision_info;
+        let has_trust_anchor = provision_info
+            .peer_trust_anchors
+            .iter()
+            .any(Option::is_some);
+        // nothing to verify the chain against, so no policy to apply either
+        if provision_info.peer_cert_chain_data.is_none() && !has_trust_anchor {
+            return Ok(());
+        }
 
-            if runtime_peer_cert_chain_data.data_size != peer_cert_chain_data.data_size {
+        let peer_cert_chain = match &self.common.peer_info.peer_cert_chain[slot_id as usize] {
+            Some(peer_cert_chain) => peer_cert_chain,
+            None => {
+                error!("peer_cert_chain is not populated!\n");
+                return spdm_result_err!(EIO);
+            }
+        };
+        let base_hash_algo = self.common.negotiate_info.base_hash_sel;
+        // only a trusted root cert can stand in for a root the peer left out
+        let (_, runtime_peer_cert_chain_data) = if has_trust_anchor {
+            spdm_split_cert_chain(base_hash_algo, peer_cert_chain.cert_chain.as_ref())?
+        } else {
+            spdm_parse_cert_chain(base_hash_algo, peer_cert_chain.cert_chain.as_ref())?
+        };
+
+        if let Some(peer_cert_chain_data) = &provision_info.peer_cert_chain_data {
+            if runtime_peer_cert_chain_data.len() != peer_cert_chain_data.data_size as usize {
                 error!("cert_chain size - fail!\n");
                 debug!(
                     "provision cert_chain data size - {:?}\n",
@@ -267,33 +368,88 @@ impl<'a> RequesterContext<'a> {
                 );
                 debug!(
                     "runtime cert_chain data size - {:?}\n",
-                    runtime_peer_cert_chain_data.data_size
+                    runtime_peer_cert_chain_data.len()
                 );
                 return spdm_result_err!(EINVAL);
             }
-            if runtime_peer_cert_chain_data.data != peer_cert_chain_data.data {
+            if runtime_peer_cert_chain_data != peer_cert_chain_data.as_ref() {
                 error!("cert_chain data - fail!\n");
-                return spdm_result_err!(EINVAL);
+                return spdm_result_err!(VerificationFailed);
             }
+        }
 
-            if crypto::cert_operation::verify_cert_chain(
-                &runtime_peer_cert_chain_data.data
-                    [..(runtime_peer_cert_chain_data.data_size as usize)],
-            )
-            .is_err()
-            {
-                error!("cert_chain verification - fail! - TBD later\n");
-                return spdm_result_err!(EFAULT);
-            }
-            info!("cert_chain verification - pass!\n");
+        let required_ekus = self
+            .common
+            .config_info
+            .responder_cert_eku_policy
+            .required_ekus(false);
+        let validity_time = self.common.get_cert_validity_time()?;
+        if has_trust_anchor {
+            spdm_verify_cert_chain_with_trust_anchors(
+                base_hash_algo,
+                peer_cert_chain.cert_chain.as_ref(),
+                &provision_info.peer_trust_anchors,
+                required_ekus,
+                validity_time,
+            )?;
+        } else if crypto::cert_operation::verify_cert_chain(
+            runtime_peer_cert_chain_data,
+            required_ekus,
+            validity_time,
+        )
+        .is_err()
+        {
+            error!("cert_chain verification - fail! - TBD later\n");
+            return spdm_result_err!(VerificationFailed);
+        }
+        info!("cert_chain verification - pass!\n");
+        self.check_peer_cert_policy(slot_id)
+    }
+
+    // A chain the registered peer cert policy rejects is dropped, so that
+    // neither CHALLENGE nor KEY_EXCHANGE can use it.
+    fn check_peer_cert_policy(&mut self, slot_id: u8) -> SpdmResult {
+        let policy = match self.peer_cert_policy {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        let cert_info = self.get_peer_leaf_cert_info(slot_id)?;
+        let peer_cert_chain = self.get_peer_cert_chain_data(slot_id)?;
+        let (_, cert_chain_der) = spdm_split_cert_chain(
+            self.common.negotiate_info.base_hash_sel,
+            peer_cert_chain.as_ref(),
+        )?;
+        let accepted = policy(slot_id, &cert_info, cert_chain_der);
+        if !accepted {
+            error!("cert_chain policy - fail!\n");
+            self.common.peer_info.peer_cert_chain[slot_id as usize] = None;
+            return spdm_result_err!(EPERM);
         }
         Ok(())
     }
+
+    /// Subject, SAN, key usage and DICE extensions of the peer leaf
+    /// certificate in slot_id. Only meaningful once the chain is verified.
+    pub fn get_peer_leaf_cert_info(&self, slot_id: u8) -> SpdmResult<SpdmCertInfo> {
+        let peer_cert_chain = self
+            .common
+            .peer_info
+            .peer_cert_chain
+            .get(slot_id as usize)
+            .and_then(|peer_cert_chain| peer_cert_chain.as_ref())
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        let (_, cert_chain_der) = spdm_split_cert_chain(
+            self.common.negotiate_info.base_hash_sel,
+            peer_cert_chain.cert_chain.as_ref(),
+        )?;
+        spdm_parse_leaf_cert(cert_chain_der)
+    }
 }
 
 #[cfg(all(test,))]
 mod tests_requester {
     use super::*;
+    use crate::common::cert_chain::{spdm_build_cert_chain_without_root, SpdmTrustAnchor};
     use crate::testlib::*;
     use crate::{crypto, responder};
 
@@ -320,7 +476,7 @@ mod tests_requester {
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.base_asym_sel =
             SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.runtime_info.message_m = Some(
             crypto::hash::hash_ctx_init(responder.common.negotiate_info.base_hash_sel).unwrap(),
         );
@@ -342,7 +498,363 @@ mod tests_requester {
             crypto::hash::hash_ctx_init(requester.common.negotiate_info.base_hash_sel).unwrap(),
         );
 
-        let status = requester.send_receive_spdm_certificate(None, 0).is_ok();
-        assert!(status);
+        // before DIGESTS any slot may be asked for
+        assert!(requester.common.is_peer_slot_provisioned(1));
+
+        // slots missing from the DIGESTS slot mask are not asked for
+        requester.common.peer_info.peer_slot_mask = 0x1;
+        assert!(!requester.common.is_peer_slot_provisioned(1));
+        assert!(requester.send_receive_spdm_certificate(None, 1).is_err());
+
+        let cert_chain = requester.send_receive_spdm_certificate(None, 0).unwrap();
+        let cert_chain_hash = crypto::hash::hash_all(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            &cert_chain.data[..cert_chain.data_size as usize],
+        )
+        .unwrap();
+        assert_eq!(
+            requester.common.peer_info.peer_cert_chain_hash[0]
+                .as_ref()
+                .unwrap()
+                .as_ref(),
+            cert_chain_hash.as_ref()
+        );
+
+        requester.common.reset_runtime_info();
+        assert!(requester.common.peer_info.peer_cert_chain_hash[0].is_none());
+    }
+
+    #[test]
+    fn test_case1_send_receive_spdm_certificate_trust_anchor() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let ca_cert = &include_bytes!("../../../test_key/EcP384/ca.cert.der")[..];
+        let inter_cert = &include_bytes!("../../../test_key/EcP384/inter.cert.der")[..];
+
+        for (pinned_cert, expected) in [(ca_cert, true), (inter_cert, false)] {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (req_config_info, mut req_provision_info) = create_info();
+            req_provision_info.peer_trust_anchors[0] = Some(SpdmTrustAnchor::RootHash(
+                crypto::hash::hash_all(base_hash_algo, pinned_cert).unwrap(),
+            ));
+
+            let shared_buffer = SharedBuffer::new();
+            let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+            crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+            let mut responder = responder::ResponderContext::new(
+                &mut device_io_responder,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+
+            responder.common.reset_runtime_info();
+            responder.common.negotiate_info.base_hash_sel = base_hash_algo;
+            responder.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
+            responder.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+            let mut requester = RequesterContext::new(
+                &mut device_io_requester,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            requester.common.negotiate_info.base_hash_sel = base_hash_algo;
+            requester.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            requester.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+            requester.common.peer_info.peer_slot_mask = 0x1;
+
+            let status = requester.send_receive_spdm_certificate(None, 0).is_ok();
+            assert_eq!(status, expected);
+        }
+    }
+
+    #[test]
+    fn test_case2_send_receive_spdm_certificate_policy() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let accept_responder = |slot_id: u8, cert_info: &SpdmCertInfo, cert_chain_der: &[u8]| {
+            slot_id == 0
+                && cert_info.subject_common_name == Some(&b"intel test ECP256 responder cert"[..])
+                && !cert_chain_der.is_empty()
+        };
+        let reject_all = |_: u8, _: &SpdmCertInfo, _: &[u8]| false;
+
+        for (policy, expected) in [
+            (&accept_responder as SpdmPeerCertPolicy, true),
+            (&reject_all, false),
+        ] {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (req_config_info, req_provision_info) = create_info();
+
+            let shared_buffer = SharedBuffer::new();
+            let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+            crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+            let mut responder = responder::ResponderContext::new(
+                &mut device_io_responder,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+
+            responder.common.reset_runtime_info();
+            responder.common.negotiate_info.base_hash_sel = base_hash_algo;
+            responder.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
+            responder.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+            let mut requester = RequesterContext::new(
+                &mut device_io_requester,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+            requester.register_peer_cert_policy(policy);
+
+            requester.common.negotiate_info.base_hash_sel = base_hash_algo;
+            requester.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            requester.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+            requester.common.peer_info.peer_slot_mask = 0x1;
+
+            let status = requester.send_receive_spdm_certificate(None, 0).is_ok();
+            assert_eq!(status, expected);
+            assert_eq!(
+                requester.common.peer_info.peer_cert_chain[0].is_some(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_case3_get_all_certificates() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let accept_slot0 = |slot_id: u8, _: &SpdmCertInfo, _: &[u8]| slot_id == 0;
+
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        responder.common.reset_runtime_info();
+        responder.common.negotiate_info.base_hash_sel = base_hash_algo;
+        responder.common.negotiate_info.base_asym_sel =
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[1] = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_peer_cert_policy(&accept_slot0);
+
+        requester.common.negotiate_info.base_hash_sel = base_hash_algo;
+        requester.common.negotiate_info.base_asym_sel =
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        requester.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+        let report = requester.get_all_certificates(None).unwrap();
+        assert_eq!(report.slots.len(), 2);
+        assert_eq!(report.slots[0].slot_id, 0);
+        assert!(report.slots[0].cert_chain.is_ok());
+        assert_eq!(report.slots[1].slot_id, 1);
+        assert_eq!(
+            report.slots[1]
+                .cert_chain
+                .as_ref()
+                .err()
+                .map(|error| error.num()),
+            Some(crate::error::SpdmErrorNum::EPERM)
+        );
+        assert!(!report.is_success());
+        assert!(report.get_cert_chain(0).is_some());
+        assert!(report.get_cert_chain(1).is_none());
+        assert!(requester.common.peer_info.peer_cert_chain[0].is_some());
+        assert!(requester.common.peer_info.peer_cert_chain[1].is_none());
+    }
+
+    #[test]
+    fn test_case4_send_receive_spdm_certificate_without_root() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let ca_cert = &include_bytes!("../../../test_key/EcP384/ca.cert.der")[..];
+        let inter_cert = &include_bytes!("../../../test_key/EcP384/inter.cert.der")[..];
+        let leaf_cert = &include_bytes!("../../../test_key/EcP384/end_responder.cert.der")[..];
+        // the responder leaves the root out, RootHash still names it
+        let cert_chain =
+            spdm_build_cert_chain_without_root(base_hash_algo, ca_cert, &[inter_cert, leaf_cert])
+                .unwrap();
+
+        let mut ca_anchor = SpdmCertChainData {
+            data_size: ca_cert.len() as u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        };
+        ca_anchor.data[..ca_cert.len()].copy_from_slice(ca_cert);
+        let mut inter_anchor = SpdmCertChainData {
+            data_size: inter_cert.len() as u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        };
+        inter_anchor.data[..inter_cert.len()].copy_from_slice(inter_cert);
+
+        for (trust_anchor, expected) in [
+            (Some(SpdmTrustAnchor::RootCert(ca_anchor)), true),
+            (Some(SpdmTrustAnchor::RootCert(inter_anchor)), false),
+            (
+                Some(SpdmTrustAnchor::RootHash(
+                    crypto::hash::hash_all(base_hash_algo, ca_cert).unwrap(),
+                )),
+                false,
+            ),
+        ] {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (req_config_info, mut req_provision_info) = create_info();
+            req_provision_info.peer_cert_chain_data = None;
+            req_provision_info.peer_trust_anchors[0] = trust_anchor;
+
+            let shared_buffer = SharedBuffer::new();
+            let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+            crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+            let mut responder = responder::ResponderContext::new(
+                &mut device_io_responder,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+
+            responder.common.reset_runtime_info();
+            responder.common.negotiate_info.base_hash_sel = base_hash_algo;
+            responder.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            responder.common.provision_info.my_cert_chain[0] = Some(cert_chain.clone());
+            responder.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+            let mut requester = RequesterContext::new(
+                &mut device_io_requester,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            requester.common.negotiate_info.base_hash_sel = base_hash_algo;
+            requester.common.negotiate_info.base_asym_sel =
+                SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+            requester.common.runtime_info.message_m =
+                Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+            requester.common.peer_info.peer_slot_mask = 0x1;
+
+            let status = requester.send_receive_spdm_certificate(None, 0).is_ok();
+            assert_eq!(status, expected);
+            if expected {
+                let cert_info = requester.get_peer_leaf_cert_info(0).unwrap();
+                assert_eq!(
+                    cert_info.subject_common_name,
+                    Some(&b"intel test ECP256 responder cert"[..])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_case5_peer_cert_policy_unverified_chain() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let reject_all = |_: u8, _: &SpdmCertInfo, _: &[u8]| false;
+
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, mut req_provision_info) = create_info();
+        req_provision_info.peer_cert_chain_data = None;
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        responder.common.reset_runtime_info();
+        responder.common.negotiate_info.base_hash_sel = base_hash_algo;
+        responder.common.negotiate_info.base_asym_sel =
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_peer_cert_policy(&reject_all);
+
+        requester.common.negotiate_info.base_hash_sel = base_hash_algo;
+        requester.common.negotiate_info.base_asym_sel =
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        requester.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(base_hash_algo).unwrap());
+        requester.common.peer_info.peer_slot_mask = 0x1;
+
+        // without provisioned chain or trust anchor the chain is not verified,
+        // so the policy is not asked about it
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        assert!(requester.common.peer_info.peer_cert_chain[0].is_some());
     }
 }
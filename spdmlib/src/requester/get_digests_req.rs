@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 #[cfg(feature = "hash-update")]
 use crate::crypto;
 use crate::error::{spdm_result_err, SpdmResult};
@@ -9,8 +10,13 @@ use crate::message::*;
 use crate::requester::*;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_digest(&mut self, session_id: Option<u32>) -> SpdmResult {
+    /// GET_DIGESTS, returns the slots and digests of the responder.
+    pub fn send_receive_spdm_digest(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+    ) -> SpdmResult<SpdmDigestsResponsePayload> {
         info!("send spdm digest\n");
+        let session_id = session_id.map(u32::from);
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let send_used = self.encode_spdm_digest(&mut send_buffer);
         if session_id.is_none() {
@@ -26,7 +32,11 @@ impl<'a> RequesterContext<'a> {
             self.receive_secured_message(session_id.unwrap(), &mut receive_buffer, false)?
         };
 
-        self.handle_spdm_digest_response(0, &send_buffer[..send_used], &receive_buffer[..used])
+        self.handle_spdm_digest_response(
+            SpdmSessionId::default(),
+            &send_buffer[..send_used],
+            &receive_buffer[..used],
+        )
     }
 
     pub fn encode_spdm_digest(&mut self, buf: &mut [u8]) -> usize {
@@ -44,10 +54,10 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_digest_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         send_buffer: &[u8],
         receive_buffer: &[u8],
-    ) -> SpdmResult {
+    ) -> SpdmResult<SpdmDigestsResponsePayload> {
         let mut reader = Reader::init(receive_buffer);
         match SpdmMessageHeader::read(&mut reader) {
             Some(message_header) => match message_header.request_response_code {
@@ -57,6 +67,8 @@ impl<'a> RequesterContext<'a> {
                     let used = reader.used();
                     if let Some(digests) = digests {
                         debug!("!!! digests : {:02x?}\n", digests);
+                        self.common.peer_info.peer_slot_mask = digests.slot_mask;
+                        self.common.update_peer_key_info(&digests);
 
                         #[cfg(not(feature = "hash-update"))]
                         {
@@ -81,10 +93,10 @@ impl<'a> RequesterContext<'a> {
                             );
                         }
 
-                        Ok(())
+                        Ok(digests)
                     } else {
                         error!("!!! digests : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -104,12 +116,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -137,7 +149,7 @@ mod tests_requester {
             rsp_config_info,
             rsp_provision_info,
         );
-        responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -160,7 +172,7 @@ mod tests_requester {
             crypto::hash::hash_ctx_init(requester.common.negotiate_info.base_hash_sel).unwrap(),
         );
 
-        let status = requester.send_receive_spdm_digest(None).is_ok();
-        assert!(status);
+        let digests = requester.send_receive_spdm_digest(None).unwrap();
+        assert_eq!(digests.slot_mask & 1, 1);
     }
 }
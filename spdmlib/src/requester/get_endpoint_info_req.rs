@@ -0,0 +1,329 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionId;
+use crate::common::ManagedBuffer;
+use crate::crypto;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    pub fn send_receive_spdm_endpoint_info(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        request_attributes: SpdmEndpointInfoRequestAttributes,
+    ) -> SpdmResult<SpdmEndpointInfoResponsePayload> {
+        info!("send spdm get_endpoint_info\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used =
+            self.encode_spdm_endpoint_info(slot_id, request_attributes, &mut send_buffer)?;
+        if session_id.is_none() {
+            self.send_message(&send_buffer[..send_used])?;
+        } else {
+            self.send_secured_message(
+                session_id.unwrap().as_u32(),
+                &send_buffer[..send_used],
+                false,
+            )?;
+        }
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = if session_id.is_none() {
+            self.receive_message(&mut receive_buffer, false)?
+        } else {
+            self.receive_secured_message(session_id.unwrap().as_u32(), &mut receive_buffer, false)?
+        };
+
+        self.handle_spdm_endpoint_info_response(
+            session_id,
+            slot_id,
+            &send_buffer[..send_used],
+            &receive_buffer[..used],
+        )
+    }
+
+    pub fn encode_spdm_endpoint_info(
+        &mut self,
+        slot_id: u8,
+        request_attributes: SpdmEndpointInfoRequestAttributes,
+        buf: &mut [u8],
+    ) -> SpdmResult<usize> {
+        let mut writer = Writer::init(buf);
+
+        let mut nonce = [0u8; SPDM_NONCE_SIZE];
+        if request_attributes.contains(SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED) {
+            self.common.get_random(&mut nonce)?;
+            self.common.runtime_info.need_endpoint_info_signature = true;
+        } else {
+            self.common.runtime_info.need_endpoint_info_signature = false;
+        }
+
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetEndpointInfo,
+            },
+            payload: SpdmMessagePayload::SpdmGetEndpointInfoRequest(
+                SpdmGetEndpointInfoRequestPayload {
+                    sub_code: SpdmEndpointInfoSubCode::SpdmEndpointInfoDeviceClassIdentifier,
+                    slot_id,
+                    request_attributes,
+                    nonce: SpdmNonceStruct { data: nonce },
+                },
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        Ok(writer.used())
+    }
+
+    pub fn handle_spdm_endpoint_info_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        send_buffer: &[u8],
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmEndpointInfoResponsePayload> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseEndpointInfo => {
+                    let endpoint_info =
+                        SpdmEndpointInfoResponsePayload::spdm_read(&mut self.common, &mut reader);
+                    let used = reader.used();
+                    if let Some(endpoint_info) = endpoint_info {
+                        debug!("!!! endpoint_info : {:02x?}\n", endpoint_info.ep_info_len);
+
+                        if !self.common.runtime_info.need_endpoint_info_signature {
+                            return Ok(endpoint_info);
+                        }
+
+                        if endpoint_info.slot_id != slot_id {
+                            error!("!!! endpoint_info : slot_id mismatch !!!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
+
+                        let base_asym_size =
+                            self.common.negotiate_info.base_asym_sel.get_size() as usize;
+                        let mut message_e = ManagedBuffer::default();
+                        message_e
+                            .append_message(send_buffer)
+                            .ok_or_else(|| spdm_err!(ENOMEM))?;
+                        message_e
+                            .append_message(&receive_buffer[..(used - base_asym_size)])
+                            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+                        if self
+                            .verify_endpoint_info_signature(
+                                slot_id,
+                                &message_e,
+                                &endpoint_info.signature,
+                            )
+                            .is_err()
+                        {
+                            error!("verify_endpoint_info_signature fail");
+                            return spdm_result_err!(VerificationFailed);
+                        } else {
+                            info!("verify_endpoint_info_signature pass");
+                        }
+
+                        Ok(endpoint_info)
+                    } else {
+                        error!("!!! endpoint_info : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestGetEndpointInfo,
+                        SpdmRequestResponseCode::SpdmResponseEndpointInfo,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_endpoint_info_response(
+                                session_id,
+                                slot_id,
+                                send_buffer,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    pub fn verify_endpoint_info_signature(
+        &mut self,
+        slot_id: u8,
+        message_e: &ManagedBuffer,
+        signature: &SpdmSignatureStruct,
+    ) -> SpdmResult {
+        let mut message = ManagedBuffer::default();
+        message
+            .append_message(self.common.runtime_info.message_a.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(message_e.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let message_hash =
+            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
+                .ok_or_else(|| spdm_err!(Crypto))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        if slot_id as usize >= SPDM_MAX_SLOT_NUMBER
+            || self.common.peer_info.peer_cert_chain[slot_id as usize].is_none()
+        {
+            error!("peer_cert_chain is not populated!\n");
+            return spdm_result_err!(EINVAL);
+        }
+
+        let peer_cert_chain = self.common.peer_info.peer_cert_chain[slot_id as usize]
+            .as_ref()
+            .unwrap();
+        let cert_chain_data = &peer_cert_chain.cert_chain.data[(4usize
+            + self.common.negotiate_info.base_hash_sel.get_size() as usize)
+            ..(peer_cert_chain.cert_chain.data_size as usize)];
+
+        message.reset_message();
+        message
+            .append_message(&SPDM_VERSION_1_3_SIGNING_PREFIX_CONTEXT)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_5)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(&SPDM_ENDPOINT_INFO_SIGN_CONTEXT)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(message_hash.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+        crypto::asym_verify::verify(
+            self.common.negotiate_info.base_hash_sel,
+            self.common.negotiate_info.base_asym_sel,
+            cert_chain_data,
+            message.as_ref(),
+            signature,
+        )
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::secret::SpdmEndpointInfoProvider;
+    use crate::testlib::*;
+    use crate::{crypto, responder, secret};
+
+    const EP_INFO: [u8; 4] = [0x01, 0x00, 0x02, 0x00];
+
+    static ENDPOINT_INFO_PROVIDER: SpdmEndpointInfoProvider = SpdmEndpointInfoProvider {
+        get_endpoint_info_cb: |_sub_code: SpdmEndpointInfoSubCode,
+                               ep_info: &mut [u8]|
+         -> SpdmResult<usize> {
+            ep_info[..EP_INFO.len()].copy_from_slice(&EP_INFO);
+            Ok(EP_INFO.len())
+        },
+    };
+
+    #[test]
+    fn test_case0_send_receive_spdm_endpoint_info_slots() {
+        let (mut rsp_config_info, mut rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        for config_info in [&mut rsp_config_info, &mut req_config_info] {
+            config_info.spdm_version = [
+                SpdmVersion::SpdmVersion11,
+                SpdmVersion::SpdmVersion12,
+                SpdmVersion::SpdmVersion13,
+            ];
+        }
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::EP_INFO_CAP_SIG;
+        // slots 1 and 5 come with their own signer, slot 3 has none
+        for slot_id in [1, 3, 5] {
+            rsp_provision_info.my_cert_chain_data[slot_id] =
+                rsp_provision_info.my_cert_chain_data[0].clone();
+        }
+        rsp_provision_info.my_asym_sign[1] = Some(ASYM_SIGN_IMPL.clone());
+        rsp_provision_info.my_asym_sign[5] = Some(ASYM_SIGN_IMPL.clone());
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        secret::register_endpoint_info_provider(ENDPOINT_INFO_PROVIDER.clone());
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert_eq!(
+            requester.common.negotiate_info.spdm_version_sel,
+            SpdmVersion::SpdmVersion13
+        );
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+
+        // signed with the key of the slot, verified against its cert chain
+        for slot_id in [0u8, 1, 5] {
+            assert!(requester
+                .send_receive_spdm_certificate(None, slot_id)
+                .is_ok());
+            let endpoint_info = requester
+                .send_receive_spdm_endpoint_info(
+                    None,
+                    slot_id,
+                    SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED,
+                )
+                .unwrap();
+            assert_eq!(endpoint_info.slot_id, slot_id);
+            assert_eq!(
+                endpoint_info.ep_info[..endpoint_info.ep_info_len as usize],
+                EP_INFO
+            );
+        }
+
+        // the key of slot 0 does not sign for slot 3
+        assert!(requester.send_receive_spdm_certificate(None, 3).is_ok());
+        assert!(requester
+            .send_receive_spdm_endpoint_info(
+                None,
+                3,
+                SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED,
+            )
+            .is_err());
+
+        // without a signature, any slot is fine
+        let endpoint_info = requester
+            .send_receive_spdm_endpoint_info(None, 3, SpdmEndpointInfoRequestAttributes::empty())
+            .unwrap();
+        assert_eq!(
+            endpoint_info.ep_info[..endpoint_info.ep_info_len as usize],
+            EP_INFO
+        );
+    }
+}
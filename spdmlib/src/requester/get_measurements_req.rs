@@ -2,16 +2,31 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::crypto;
-use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::error::{spdm_err, spdm_result_err, SpdmError, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
 use crate::requester::*;
+use crate::verifier::{SpdmAttestationReport, SpdmMeasurementVerifier};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// A MEASUREMENTS response as send_receive_spdm_measurement returns it.
+#[derive(Debug, Clone, Default)]
+pub struct SpdmMeasurements {
+    // the total number of measurement indices for
+    // SpdmMeasurementQueryTotalNumber, else the number of blocks in
+    // measurement_record
+    pub number_of_measurement: u8,
+    pub measurement_record: SpdmMeasurementRecordStructure,
+}
 
 impl<'a> RequesterContext<'a> {
     fn send_receive_spdm_measurement_record(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         measurement_attributes: SpdmMeasurementeAttributes,
         measurement_operation: SpdmMeasurementOperation,
         spdm_measurement_record_structure: &mut SpdmMeasurementRecordStructure,
@@ -27,7 +42,7 @@ impl<'a> RequesterContext<'a> {
         )?;
         match session_id {
             Some(session_id) => {
-                self.send_secured_message(session_id, &send_buffer[..send_used], false)?;
+                self.send_secured_message(session_id.as_u32(), &send_buffer[..send_used], false)?;
             }
             None => {
                 self.send_message(&send_buffer[..send_used])?;
@@ -38,7 +53,7 @@ impl<'a> RequesterContext<'a> {
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = match session_id {
             Some(session_id) => {
-                self.receive_secured_message(session_id, &mut receive_buffer, true)?
+                self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, true)?
             }
             None => self.receive_message(&mut receive_buffer, true)?,
         };
@@ -61,9 +76,22 @@ impl<'a> RequesterContext<'a> {
         slot_id: u8,
         buf: &mut [u8],
     ) -> SpdmResult<usize> {
+        if measurement_attributes.contains(SpdmMeasurementeAttributes::RAW_BIT_STREAM_REQUESTED)
+            && self.common.negotiate_info.spdm_version_sel.get_u8()
+                < SpdmVersion::SpdmVersion12.get_u8()
+        {
+            return spdm_result_err!(EINVAL);
+        }
+
         let mut writer = Writer::init(buf);
         let mut nonce = [0u8; SPDM_NONCE_SIZE];
-        crypto::rand::get_random(&mut nonce)?;
+        self.common.get_random(&mut nonce)?;
+        self.measurement_request_nonce =
+            if measurement_attributes.contains(SpdmMeasurementeAttributes::SIGNATURE_REQUESTED) {
+                Some(SpdmNonceStruct { data: nonce })
+            } else {
+                None
+            };
 
         let request = SpdmMessage {
             header: SpdmMessageHeader {
@@ -86,7 +114,7 @@ impl<'a> RequesterContext<'a> {
     #[allow(clippy::too_many_arguments)]
     pub fn handle_spdm_measurement_record_response(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         slot_id: u8,
         measurement_attributes: SpdmMeasurementeAttributes,
         measurement_operation: SpdmMeasurementOperation,
@@ -124,25 +152,25 @@ impl<'a> RequesterContext<'a> {
                         if measurement_attributes
                             .contains(SpdmMeasurementeAttributes::SIGNATURE_REQUESTED)
                         {
+                            // before the transcript update, a stale response
+                            // must not end up in L1/L2
+                            self.check_measurement_nonce(&measurements.nonce)?;
+
                             let base_asym_size =
                                 self.common.negotiate_info.base_asym_sel.get_size() as usize;
                             let temp_used = used - base_asym_size;
 
                             let message_m = match session_id {
                                 Some(session_id) => {
-                                    let session = if let Some(s) =
-                                        self.common.get_session_via_id(session_id)
-                                    {
-                                        s
-                                    } else {
-                                        return spdm_result_err!(EFAULT);
-                                    };
+                                    let session = self.common.get_session_mut(session_id)?;
 
                                     #[cfg(feature = "hash-update")]
                                     if session.runtime_info.message_m.is_none() {
                                         session.runtime_info.message_m =
                                             crypto::hash::hash_ctx_init(base_hash_sel);
-                                        if spdm_version_sel == SpdmVersion::SpdmVersion12 {
+                                        if spdm_version_sel.get_u8()
+                                            >= SpdmVersion::SpdmVersion12.get_u8()
+                                        {
                                             crypto::hash::hash_ctx_update(
                                                 session.runtime_info.message_m.as_mut().unwrap(),
                                                 message_a.as_ref(),
@@ -157,7 +185,9 @@ impl<'a> RequesterContext<'a> {
                                     if self.common.runtime_info.message_mes_no_session.is_none() {
                                         self.common.runtime_info.message_mes_no_session =
                                             crypto::hash::hash_ctx_init(base_hash_sel);
-                                        if spdm_version_sel == SpdmVersion::SpdmVersion12 {
+                                        if spdm_version_sel.get_u8()
+                                            >= SpdmVersion::SpdmVersion12.get_u8()
+                                        {
                                             crypto::hash::hash_ctx_update(
                                                 self.common
                                                     .runtime_info
@@ -201,6 +231,15 @@ impl<'a> RequesterContext<'a> {
                                 );
                             }
 
+                            if !self.common.is_key_usage_allowed(
+                                &self.common.peer_info.peer_key_usage_mask,
+                                slot_id,
+                                SpdmKeyUsageBitMask::MEASUREMENT_USE,
+                            ) {
+                                error!("slot {} key is not for measurement\n", slot_id);
+                                return spdm_result_err!(EFAULT);
+                            }
+
                             if self
                                 .verify_measurement_signature(
                                     slot_id,
@@ -210,51 +249,24 @@ impl<'a> RequesterContext<'a> {
                                 .is_err()
                             {
                                 error!("verify_measurement_signature fail");
-                                return spdm_result_err!(EFAULT);
+                                return spdm_result_err!(VerificationFailed);
                             } else {
                                 info!("verify_measurement_signature pass");
                             }
-                            match session_id {
-                                Some(session_id) => {
-                                    let session = if let Some(s) =
-                                        self.common.get_session_via_id(session_id)
-                                    {
-                                        s
-                                    } else {
-                                        return spdm_result_err!(EFAULT);
-                                    };
-                                    #[cfg(not(feature = "hash-update"))]
-                                    session.runtime_info.message_m.reset_message();
-                                    #[cfg(feature = "hash-update")]
-                                    {
-                                        session.runtime_info.message_m = None;
-                                    }
-                                }
-                                None => {
-                                    #[cfg(not(feature = "hash-update"))]
-                                    self.common.runtime_info.message_m.reset_message();
-                                    #[cfg(feature = "hash-update")]
-                                    {
-                                        self.common.runtime_info.message_mes_no_session = None;
-                                    }
-                                }
-                            };
+                            self.measurement_response_nonce = Some(measurements.nonce.clone());
+                            self.reset_message_m(session_id);
                         } else {
                             let message_m = match session_id {
                                 Some(session_id) => {
-                                    let session = if let Some(s) =
-                                        self.common.get_session_via_id(session_id)
-                                    {
-                                        s
-                                    } else {
-                                        return spdm_result_err!(EFAULT);
-                                    };
+                                    let session = self.common.get_session_mut(session_id)?;
 
                                     #[cfg(feature = "hash-update")]
                                     if session.runtime_info.message_m.is_none() {
                                         session.runtime_info.message_m =
                                             crypto::hash::hash_ctx_init(base_hash_sel);
-                                        if spdm_version_sel == SpdmVersion::SpdmVersion12 {
+                                        if spdm_version_sel.get_u8()
+                                            >= SpdmVersion::SpdmVersion12.get_u8()
+                                        {
                                             crypto::hash::hash_ctx_update(
                                                 session.runtime_info.message_m.as_mut().unwrap(),
                                                 message_a.as_ref(),
@@ -271,13 +283,13 @@ impl<'a> RequesterContext<'a> {
                                             crypto::hash::hash_ctx_init(
                                                 self.common.negotiate_info.base_hash_sel,
                                             );
-                                        if self.common.negotiate_info.spdm_version_sel
-                                            == SpdmVersion::SpdmVersion12
+                                        if spdm_version_sel.get_u8()
+                                            >= SpdmVersion::SpdmVersion12.get_u8()
                                         {
                                             crypto::hash::hash_ctx_update(
                                                 self.common
                                                     .runtime_info
-                                                    .message_m
+                                                    .message_mes_no_session
                                                     .as_mut()
                                                     .unwrap(),
                                                 message_a.as_ref(),
@@ -334,7 +346,7 @@ impl<'a> RequesterContext<'a> {
                         }
                     } else {
                         error!("!!! measurements : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -358,44 +370,348 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => {
+                            // the responder drops L1/L2 on an error, so do we
+                            self.reset_message_m(session_id);
+                            Err(e)
+                        }
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    fn reset_message_m(&mut self, session_id: Option<SpdmSessionId>) {
+        #[cfg(not(feature = "hash-update"))]
+        match session_id {
+            Some(session_id) => {
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    session.runtime_info.message_m.reset_message();
+                }
+            }
+            None => {
+                self.common.runtime_info.message_m.reset_message();
+            }
+        }
+        #[cfg(feature = "hash-update")]
+        match session_id {
+            Some(session_id) => {
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    session.runtime_info.message_m = None;
+                }
+            }
+            None => {
+                self.common.runtime_info.message_mes_no_session = None;
+            }
         }
     }
 
     pub fn send_receive_spdm_measurement(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         slot_id: u8,
         spdm_measuremente_attributes: SpdmMeasurementeAttributes,
         measurement_operation: SpdmMeasurementOperation,
-        out_total_number: &mut u8, // out, total number when measurement_operation = SpdmMeasurementQueryTotalNumber
-        //      number of blocks got measured.
-        spdm_measurement_record_structure: &mut SpdmMeasurementRecordStructure, // out
-    ) -> SpdmResult {
-        if let Ok(total_number) = self.send_receive_spdm_measurement_record(
+    ) -> SpdmResult<SpdmMeasurements> {
+        let mut measurement_record = SpdmMeasurementRecordStructure::default();
+        let number_of_measurement = self.send_receive_spdm_measurement_record(
             session_id,
             spdm_measuremente_attributes,
             measurement_operation,
-            spdm_measurement_record_structure,
+            &mut measurement_record,
             slot_id,
+        )?;
+        Ok(SpdmMeasurements {
+            number_of_measurement,
+            measurement_record,
+        })
+    }
+
+    /// Query the total number of measurement indices, then get the indices
+    /// one by one and return them in one record. Indices need not be
+    /// contiguous: if one of 1..=total is missing, the indices the
+    /// responder has are probed for before getting them again.
+    /// If SIGNATURE_REQUESTED is set, only the last request asks for a
+    /// signature, which covers the whole L1/L2 transcript. Without any
+    /// measurement, the total number query is sent again, signed.
+    pub fn get_all_measurements(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        spdm_measuremente_attributes: SpdmMeasurementeAttributes,
+    ) -> SpdmResult<SpdmMeasurementRecordStructure> {
+        let unsigned_attributes =
+            spdm_measuremente_attributes - SpdmMeasurementeAttributes::SIGNATURE_REQUESTED;
+
+        let mut record = SpdmMeasurementRecordStructure::default();
+        let total_number = self.send_receive_spdm_measurement_record(
+            session_id,
+            unsigned_attributes,
+            SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
+            &mut record,
+            slot_id,
+        )?;
+        if total_number as usize > config::MAX_SPDM_MEASUREMENT_BLOCK_COUNT {
+            return spdm_result_err!(ENOMEM);
+        }
+        if total_number == 0 {
+            if spdm_measuremente_attributes
+                .contains(SpdmMeasurementeAttributes::SIGNATURE_REQUESTED)
+            {
+                self.send_receive_spdm_measurement_record(
+                    session_id,
+                    spdm_measuremente_attributes,
+                    SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
+                    &mut record,
+                    slot_id,
+                )?;
+            }
+            return Ok(SpdmMeasurementRecordStructure::default());
+        }
+
+        let indices: Vec<u8> = (1..=total_number).collect();
+        match self.get_measurement_indices(
+            session_id,
+            slot_id,
+            spdm_measuremente_attributes,
+            &indices,
         ) {
-            *out_total_number = total_number;
-            Ok(())
-        } else {
-            spdm_result_err!(EFAULT)
+            Err(SpdmError::PeerError {
+                code: SpdmErrorCode::SpdmErrorInvalidRequest,
+                ..
+            }) => {}
+            result => return result,
+        }
+
+        // sparse indices, the error dropped L1/L2 so the blocks found are
+        // got again once all are known
+        let mut indices = Vec::new();
+        for index in 1..SpdmMeasurementOperation::SpdmMeasurementRequestManifest.get_u8() {
+            match self.send_receive_spdm_measurement_record(
+                session_id,
+                unsigned_attributes,
+                SpdmMeasurementOperation::Unknown(index),
+                &mut record,
+                slot_id,
+            ) {
+                Ok(_) => indices.push(index),
+                Err(SpdmError::PeerError {
+                    code: SpdmErrorCode::SpdmErrorInvalidRequest,
+                    ..
+                }) => {}
+                Err(e) => return Err(e),
+            }
+            if indices.len() == total_number as usize {
+                break;
+            }
+        }
+        if indices.len() != total_number as usize {
+            error!("measurement indices missing\n");
+            return spdm_result_err!(EFAULT);
+        }
+        self.get_measurement_indices(session_id, slot_id, spdm_measuremente_attributes, &indices)
+    }
+
+    /// Get the measurement blocks of indices one by one and return them in
+    /// one record, in the order of indices. Only the last request asks for
+    /// a signature, verified over the L1/L2 transcript of them all with the
+    /// certificate chain of slot_id.
+    pub fn get_signed_measurements(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        indices: &[u8],
+    ) -> SpdmResult<SpdmMeasurementRecordStructure> {
+        if indices.is_empty()
+            || indices.len() > config::MAX_SPDM_MEASUREMENT_BLOCK_COUNT
+            || indices.iter().any(|&index| index == 0 || index == 0xff)
+        {
+            return spdm_result_err!(EINVAL);
+        }
+        self.get_measurement_indices(
+            session_id,
+            slot_id,
+            SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
+            indices,
+        )
+    }
+
+    // SIGNATURE_REQUESTED in measurement_attributes goes with the last
+    // index only.
+    fn get_measurement_indices(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        measurement_attributes: SpdmMeasurementeAttributes,
+        indices: &[u8],
+    ) -> SpdmResult<SpdmMeasurementRecordStructure> {
+        let unsigned_attributes =
+            measurement_attributes - SpdmMeasurementeAttributes::SIGNATURE_REQUESTED;
+
+        let mut record = SpdmMeasurementRecordStructure::default();
+        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
+        for (i, &index) in indices.iter().enumerate() {
+            let attributes = if i + 1 == indices.len() {
+                measurement_attributes
+            } else {
+                unsigned_attributes
+            };
+            let number_of_blocks = self.send_receive_spdm_measurement_record(
+                session_id,
+                attributes,
+                SpdmMeasurementOperation::Unknown(index),
+                &mut record,
+                slot_id,
+            )?;
+            if number_of_blocks != 1 || record.record[0].index != index {
+                error!("measurement index {} mismatch\n", index);
+                return spdm_result_err!(EFAULT);
+            }
+            spdm_measurement_record_structure.record[i] = record.record[0].clone();
         }
+        spdm_measurement_record_structure.number_of_blocks = indices.len() as u8;
+
+        Ok(spdm_measurement_record_structure)
+    }
+
+    /// Get all measurements, signed by the certificate chain of slot_id, and
+    /// compare them to the reference values of verifier. An error if any
+    /// request fails or the signature does not verify, else the report of
+    /// the comparison.
+    pub fn attest_measurements(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        verifier: &SpdmMeasurementVerifier,
+    ) -> SpdmResult<SpdmAttestationReport> {
+        if !self
+            .common
+            .negotiate_info
+            .rsp_capabilities_sel
+            .contains(SpdmResponseCapabilityFlags::MEAS_CAP_SIG)
+        {
+            return spdm_result_err!(EINVAL);
+        }
+
+        let spdm_measurement_record_structure = self.get_all_measurements(
+            session_id,
+            slot_id,
+            SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
+        )?;
+        Ok(verifier.verify(
+            &spdm_measurement_record_structure,
+            self.common.negotiate_info.measurement_hash_sel,
+        ))
+    }
+
+    /// Get the measurement manifest (index 0xFD) and copy it to manifest for
+    /// policy evaluation. Returns the manifest size.
+    pub fn send_receive_spdm_measurement_manifest(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        spdm_measuremente_attributes: SpdmMeasurementeAttributes,
+        manifest: &mut [u8], // out
+    ) -> SpdmResult<usize> {
+        self.send_receive_spdm_measurement_value(
+            session_id,
+            slot_id,
+            spdm_measuremente_attributes,
+            SpdmMeasurementOperation::SpdmMeasurementRequestManifest,
+            SpdmDmtfMeasurementType::SpdmDmtfMeasurementManifest,
+            manifest,
+        )
+    }
+
+    /// Get the device mode (index 0xFE) in its structured representation.
+    /// Returns the device mode size.
+    pub fn send_receive_spdm_measurement_device_mode(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        spdm_measuremente_attributes: SpdmMeasurementeAttributes,
+        device_mode: &mut [u8], // out
+    ) -> SpdmResult<usize> {
+        self.send_receive_spdm_measurement_value(
+            session_id,
+            slot_id,
+            spdm_measuremente_attributes,
+            SpdmMeasurementOperation::SpdmMeasurementRequestDeviceMode,
+            SpdmDmtfMeasurementType::SpdmDmtfMeasurementStructuredRepresentationMode,
+            device_mode,
+        )
+    }
+
+    fn send_receive_spdm_measurement_value(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        spdm_measuremente_attributes: SpdmMeasurementeAttributes,
+        measurement_operation: SpdmMeasurementOperation,
+        measurement_type: SpdmDmtfMeasurementType,
+        value: &mut [u8],
+    ) -> SpdmResult<usize> {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            < SpdmVersion::SpdmVersion12.get_u8()
+        {
+            return spdm_result_err!(EINVAL);
+        }
+
+        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
+        let number_of_blocks = self.send_receive_spdm_measurement_record(
+            session_id,
+            spdm_measuremente_attributes | SpdmMeasurementeAttributes::RAW_BIT_STREAM_REQUESTED,
+            measurement_operation,
+            &mut spdm_measurement_record_structure,
+            slot_id,
+        )?;
+        if number_of_blocks != 1 {
+            return spdm_result_err!(EFAULT);
+        }
+
+        let block = &spdm_measurement_record_structure.record[0];
+        if block.index != measurement_operation.get_u8()
+            || block.measurement.r#type != measurement_type
+            || !block.measurement.is_raw_bit_stream()
+        {
+            return spdm_result_err!(EFAULT);
+        }
+        let value_size = block.measurement.value_size as usize;
+        if value_size > value.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+        value[..value_size].copy_from_slice(&block.measurement.value[..value_size]);
+        Ok(value_size)
+    }
+
+    // The responder nonce must neither echo the nonce of the request nor
+    // repeat the one of the previous MEASUREMENTS verified.
+    fn check_measurement_nonce(&mut self, nonce: &SpdmNonceStruct) -> SpdmResult {
+        let request_nonce = self
+            .measurement_request_nonce
+            .take()
+            .ok_or_else(|| spdm_err!(StaleNonce))?;
+
+        if nonce.data == request_nonce.data {
+            error!("!!! measurements : responder nonce echoes the request !!!\n");
+            return spdm_result_err!(StaleNonce);
+        }
+        if let Some(last_nonce) = &self.measurement_response_nonce {
+            if nonce.data == last_nonce.data {
+                error!("!!! measurements : responder nonce repeated !!!\n");
+                return spdm_result_err!(StaleNonce);
+            }
+        }
+        Ok(())
     }
 
     #[cfg(feature = "hash-update")]
     pub fn verify_measurement_signature(
         &mut self,
         slot_id: u8,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         signature: &SpdmSignatureStruct,
     ) -> SpdmResult {
         let message_hash = match session_id {
@@ -410,11 +726,7 @@ impl<'a> RequesterContext<'a> {
                 crypto::hash::hash_ctx_finalize(ctx)
             }
             Some(session_id) => {
-                let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                    s
-                } else {
-                    return spdm_result_err!(EINVAL);
-                };
+                let session = self.common.get_session_mut(session_id)?;
                 let ctx = session.runtime_info.message_m.as_mut().cloned().unwrap();
                 crypto::hash::hash_ctx_finalize(ctx)
             }
@@ -439,22 +751,10 @@ impl<'a> RequesterContext<'a> {
                 .cert_chain
                 .data_size as usize)];
 
-        let mut message = ManagedBuffer::default();
-        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
-            message.reset_message();
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_PREFIX_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_6)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_MEASUREMENTS_SIGN_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(message_hash.as_ref())
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-        }
+        // with hash-update only the hash of the transcript is kept
+        let message = self
+            .common
+            .get_measurement_signing_message(&[], message_hash.as_ref())?;
 
         crypto::asym_verify::verify(
             self.common.negotiate_info.base_hash_sel,
@@ -469,31 +769,29 @@ impl<'a> RequesterContext<'a> {
     pub fn verify_measurement_signature(
         &mut self,
         slot_id: u8,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         signature: &SpdmSignatureStruct,
     ) -> SpdmResult {
-        let mut message = ManagedBuffer::default();
+        let mut transcript = ManagedBuffer::default();
 
-        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion12.get_u8()
+        {
             let message_a = self.common.runtime_info.message_a.clone();
-            message
+            transcript
                 .append_message(message_a.as_ref())
                 .map_or_else(|| spdm_result_err!(ENOMEM), |_| Ok(()))?;
         }
 
         match session_id {
             None => {
-                message
+                transcript
                     .append_message(self.common.runtime_info.message_m.as_ref())
                     .ok_or_else(|| spdm_err!(ENOMEM))?;
             }
             Some(session_id) => {
-                let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                    s
-                } else {
-                    return spdm_result_err!(EINVAL);
-                };
-                message
+                let session = self.common.get_session(session_id)?;
+                transcript
                     .append_message(session.runtime_info.message_m.as_ref())
                     .ok_or_else(|| spdm_err!(ENOMEM))?;
             }
@@ -501,10 +799,12 @@ impl<'a> RequesterContext<'a> {
 
         // we dont need create message hash for verify
         // we just print message hash for debug purpose
-        debug!("message_m - {:02x?}", message.as_ref());
-        let message_hash =
-            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
-                .ok_or_else(|| spdm_err!(EFAULT))?;
+        debug!("message_m - {:02x?}", transcript.as_ref());
+        let message_hash = crypto::hash::hash_all(
+            self.common.negotiate_info.base_hash_sel,
+            transcript.as_ref(),
+        )
+        .ok_or_else(|| spdm_err!(Crypto))?;
         debug!("message_hash - {:02x?}", message_hash.as_ref());
 
         if self.common.peer_info.peer_cert_chain[slot_id as usize].is_none() {
@@ -523,21 +823,9 @@ impl<'a> RequesterContext<'a> {
                 .cert_chain
                 .data_size as usize)];
 
-        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
-            message.reset_message();
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_PREFIX_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_6)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_MEASUREMENTS_SIGN_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(message_hash.as_ref())
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-        }
+        let message = self
+            .common
+            .get_measurement_signing_message(transcript.as_ref(), message_hash.as_ref())?;
 
         crypto::asym_verify::verify(
             self.common.negotiate_info.base_hash_sel,
@@ -552,6 +840,7 @@ impl<'a> RequesterContext<'a> {
 #[cfg(all(test,))]
 mod tests_requester {
     use super::*;
+    use crate::error::SpdmError;
     use crate::testlib::*;
     use crate::{crypto, responder};
 
@@ -566,6 +855,9 @@ mod tests_requester {
         let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
 
         crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        crate::secret::register(SECRET_IMPL.clone());
+        // a fresh responder nonce for each MEASUREMENTS
+        let mut rng = CountingRng(0x80);
 
         let mut responder = responder::ResponderContext::new(
             &mut device_io_responder,
@@ -573,6 +865,7 @@ mod tests_requester {
             rsp_config_info,
             rsp_provision_info,
         );
+        responder.register_rng(&mut rng);
 
         responder.common.negotiate_info.req_ct_exponent_sel = 0;
         responder.common.negotiate_info.req_capabilities_sel = SpdmRequestCapabilityFlags::CERT_CAP;
@@ -599,6 +892,7 @@ mod tests_requester {
             .runtime_info
             .message_m
             .append_message(message_m);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
         responder.common.reset_runtime_info();
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
@@ -634,16 +928,12 @@ mod tests_requester {
         requester.common.reset_runtime_info();
 
         let measurement_operation = SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber;
-        let mut total_number: u8 = 0;
-        let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
         let status = requester
             .send_receive_spdm_measurement(
                 None,
                 0,
                 SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
                 measurement_operation,
-                &mut total_number,
-                &mut spdm_measurement_record_structure,
             )
             .is_ok();
         assert!(status);
@@ -655,8 +945,6 @@ mod tests_requester {
                 0,
                 SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
                 measurement_operation,
-                &mut total_number,
-                &mut spdm_measurement_record_structure,
             )
             .is_ok();
         assert!(status);
@@ -668,10 +956,101 @@ mod tests_requester {
                 0,
                 SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
                 measurement_operation,
-                &mut total_number,
-                &mut spdm_measurement_record_structure,
             )
             .is_ok();
         assert!(status);
     }
+
+    #[test]
+    fn test_case1_get_signed_measurements_invalid_indices() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        for indices in [&[][..], &[0], &[1, 0xff]] {
+            let error = requester
+                .get_signed_measurements(None, 0, indices)
+                .unwrap_err();
+            assert_eq!(error.num(), crate::error::SpdmErrorNum::EINVAL);
+        }
+    }
+
+    #[test]
+    fn test_case2_check_measurement_nonce() {
+        let (req_config_info, req_provision_info) = create_info();
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_requester = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut rng = CountingRng(0x10);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_rng(&mut rng);
+        requester.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        fn encode(requester: &mut RequesterContext, send_buffer: &mut [u8]) -> usize {
+            requester
+                .encode_spdm_measurement_record(
+                    SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
+                    SpdmMeasurementOperation::SpdmMeasurementRequestAll,
+                    0,
+                    send_buffer,
+                )
+                .unwrap()
+        }
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let fresh_nonce = SpdmNonceStruct {
+            data: [0xa5; SPDM_NONCE_SIZE],
+        };
+
+        encode(&mut requester, &mut send_buffer);
+        assert!(requester.check_measurement_nonce(&fresh_nonce).is_ok());
+        requester.measurement_response_nonce = Some(fresh_nonce.clone());
+
+        // the nonce of the previous MEASUREMENTS
+        encode(&mut requester, &mut send_buffer);
+        let error = requester.check_measurement_nonce(&fresh_nonce).unwrap_err();
+        assert!(matches!(error, SpdmError::StaleNonce(_)));
+
+        // the nonce of the request echoed
+        encode(&mut requester, &mut send_buffer);
+        let mut echoed_nonce = SpdmNonceStruct::default();
+        echoed_nonce
+            .data
+            .copy_from_slice(&send_buffer[4..4 + SPDM_NONCE_SIZE]);
+        let error = requester
+            .check_measurement_nonce(&echoed_nonce)
+            .unwrap_err();
+        assert!(matches!(error, SpdmError::StaleNonce(_)));
+
+        // no GET_MEASUREMENTS sent since
+        let mut other_nonce = fresh_nonce.clone();
+        other_nonce.data[0] = 0;
+        let error = requester.check_measurement_nonce(&other_nonce).unwrap_err();
+        assert!(matches!(error, SpdmError::StaleNonce(_)));
+    }
 }
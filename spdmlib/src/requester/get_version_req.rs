@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
@@ -15,7 +16,11 @@ impl<'a> RequesterContext<'a> {
 
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.receive_message(&mut receive_buffer, false)?;
-        self.handle_spdm_version_response(0, &send_buffer[..send_used], &receive_buffer[..used])
+        self.handle_spdm_version_response(
+            SpdmSessionId::default(),
+            &send_buffer[..send_used],
+            &receive_buffer[..used],
+        )
     }
 
     pub fn encode_spdm_version(&mut self, buf: &mut [u8]) -> usize {
@@ -33,7 +38,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_version_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         send_buffer: &[u8],
         receive_buffer: &[u8],
     ) -> SpdmResult {
@@ -52,18 +57,23 @@ impl<'a> RequesterContext<'a> {
                             versions,
                         } = version;
 
-                        self.common.negotiate_info.spdm_version_sel =
-                            versions[version_number_entry_count as usize - 1].version;
-
+                        // Select the highest version both endpoints offer.
+                        let mut version_sel: Option<SpdmVersion> = None;
                         for spdm_version_struct in
                             versions.iter().take(version_number_entry_count as usize)
                         {
-                            if spdm_version_struct.version
-                                == self.common.provision_info.default_version
+                            let version = spdm_version_struct.version;
+                            if self.common.config_info.spdm_version.contains(&version)
+                                && version_sel.map_or(true, |v| version.get_u8() > v.get_u8())
                             {
-                                self.common.negotiate_info.spdm_version_sel =
-                                    spdm_version_struct.version;
-                                break;
+                                version_sel = Some(version);
+                            }
+                        }
+                        match version_sel {
+                            Some(version) => self.common.negotiate_info.spdm_version_sel = version,
+                            None => {
+                                error!("!!! version : no common version !!!\n");
+                                return spdm_result_err!(EFAULT);
                             }
                         }
 
@@ -79,7 +89,7 @@ impl<'a> RequesterContext<'a> {
                             .map_or_else(|| spdm_result_err!(ENOMEM), |_| Ok(()))
                     } else {
                         error!("!!! version : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -99,12 +109,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
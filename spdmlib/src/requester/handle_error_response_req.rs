@@ -4,16 +4,22 @@
 
 use codec::{Codec, Reader};
 
-use crate::common::session::SpdmSessionState;
-use crate::error::{spdm_result_err, SpdmResult};
+use crate::common::session::SpdmSessionId;
+use crate::common::{SpdmCodec, SpdmDecryptErrorPolicy, SpdmSessionEvent};
+use crate::config;
+use crate::error::{spdm_err, spdm_result_err, SpdmError, SpdmResult};
 use crate::message::*;
+use crate::protocol::SPDM_MAX_SLOT_NUMBER;
+use crate::requester::respond_if_ready_req::check_respond_if_ready_response;
 use crate::requester::RequesterContext;
-use crate::time::sleep;
 
 impl<'a> RequesterContext<'a> {
+    // The same RESPOND_IF_READY as receive_message and
+    // receive_secured_message do, for a response handed to a
+    // handle_*_response some other way. session_id 0 is no session.
     fn spdm_handle_response_not_ready(
         &mut self,
-        _session_id: Option<u32>,
+        session_id: Option<u32>,
         response: &[u8],
         original_request_code: SpdmRequestResponseCode,
         expected_response_code: SpdmRequestResponseCode,
@@ -23,68 +29,210 @@ impl<'a> RequesterContext<'a> {
                 + core::mem::size_of::<SpdmMessageGeneralPayload>()
                 + core::mem::size_of::<SpdmErrorResponseNotReadyExtData>()
         {
-            spdm_result_err!(EDEV)
+            return spdm_result_err!(EDEV);
+        }
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        receive_buffer[..response.len()].copy_from_slice(response);
+        let used = self.resolve_response_not_ready(
+            session_id.filter(|session_id| *session_id != 0),
+            original_request_code.get_u8(),
+            &mut receive_buffer,
+            response.len(),
+            false,
+        )?;
+        check_respond_if_ready_response(
+            ReceivedMessage {
+                receive_buffer,
+                used,
+            },
+            expected_response_code,
+        )
+    }
+
+    fn spdm_handle_simple_error_response(
+        &mut self,
+        _session_id: Option<u32>,
+        error_code: u8,
+        error_data: u8,
+        original_request_code: SpdmRequestResponseCode,
+    ) -> SpdmResult<ReceivedMessage> {
+        /* NOT_READY is treated as error here.
+         * Use spdm_handle_error_response_main to handle NOT_READY message in long latency command.*/
+        if error_code == SpdmErrorCode::SpdmErrorRequestResynch.get_u8() {
+            self.spdm_handle_request_resynch(original_request_code)
         } else {
-            let extoff = core::mem::size_of::<SpdmMessageHeader>()
-                + core::mem::size_of::<SpdmMessageGeneralPayload>();
-            let mut extend_error_data_reader = Reader::init(&response[extoff..]);
-            let extend_error_data = if let Some(eed) =
-                SpdmErrorResponseNotReadyExtData::read(&mut extend_error_data_reader)
-            {
-                eed
-            } else {
-                return spdm_result_err!(EINVAL);
-            };
+            let code = SpdmErrorCode::read_bytes(&[error_code]).ok_or_else(|| spdm_err!(Codec))?;
+            Err(SpdmError::PeerError {
+                code,
+                data: error_data,
+            })
+        }
+    }
+
+    // The responder lost the connection state, and the sessions with it:
+    // GET_VERSION, GET_CAPABILITIES and NEGOTIATE_ALGORITHMS again with the
+    // same configuration, then GET_DIGESTS and GET_CERTIFICATE for the
+    // slots whose chain was retrieved before, unless the resynch handler
+    // refuses. EAGAIN tells the caller to retry its request on the new
+    // connection, sessions have to be started again. The async requester
+    // only resets the connection, init_connection is up to it.
+    fn spdm_handle_request_resynch(
+        &mut self,
+        original_request_code: SpdmRequestResponseCode,
+    ) -> SpdmResult<ReceivedMessage> {
+        warn!("!!! responder requests resynch !!!\n");
+        let retrieved_slot_mask = self
+            .common
+            .peer_info
+            .peer_cert_chain
+            .iter()
+            .enumerate()
+            .filter(|(_, peer_cert_chain)| peer_cert_chain.is_some())
+            .fold(0u8, |mask, (slot_id, _)| mask | (1 << slot_id));
+        let last_error = self.last_error.take();
+        self.reset_connection()?;
+        self.last_error = last_error;
+
+        if self.resynch_in_progress
+            || original_request_code == SpdmRequestResponseCode::SpdmRequestGetVersion
+            || self.common.deferred_io.is_some()
+            || !self.approve_resynch()
+        {
+            return spdm_result_err!(EDEV);
+        }
+
+        // a RequestResynch during the resynch ends it
+        self.resynch_in_progress = true;
+        let result = self
+            .init_connection()
+            .and_then(|_| self.retrieve_certificates(retrieved_slot_mask));
+        self.resynch_in_progress = false;
+        match result {
+            Ok(()) => spdm_result_err!(EAGAIN),
+            Err(_) => spdm_result_err!(EDEV),
+        }
+    }
+
+    // The chains verified before the resynch, that CHALLENGE, KEY_EXCHANGE
+    // and signed GET_MEASUREMENTS verify the responder with.
+    fn retrieve_certificates(&mut self, slot_mask: u8) -> SpdmResult {
+        if slot_mask == 0 {
+            return Ok(());
+        }
+        self.send_receive_spdm_digest(None)?;
+        for slot_id in 0..SPDM_MAX_SLOT_NUMBER as u8 {
+            if slot_mask & (1 << slot_id) != 0 {
+                self.send_receive_spdm_certificate(None, slot_id)?;
+            }
+        }
+        Ok(())
+    }
 
-            if extend_error_data.request_code != original_request_code.get_u8() {
-                return spdm_result_err!(EDEV);
+    // The responder could not decrypt the latest request of session_id: send
+    // it again as the decrypt error policy allows if the responder keeps the
+    // session, else tear the session down.
+    fn spdm_handle_decrypt_error(&mut self, session_id: u32) -> SpdmResult<ReceivedMessage> {
+        let decrypt_error_policy = self.common.config_info.decrypt_error_policy;
+        if decrypt_error_policy.is_enabled()
+            && !self.decrypt_error_recovery
+            && self.last_secured_request_size != 0
+        {
+            let mut request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let request_size = self.last_secured_request_size;
+            request[..request_size].copy_from_slice(&self.last_secured_request[..request_size]);
+
+            // a DecryptError during the recovery ends it
+            self.decrypt_error_recovery = true;
+            let result = self.spdm_recover_decrypt_error(
+                session_id,
+                decrypt_error_policy,
+                &request[..request_size],
+            );
+            self.decrypt_error_recovery = false;
+            if result.is_ok() {
+                return result;
             }
+        }
 
-            sleep(2 << extend_error_data.rdt_exponent);
+        error!(
+            "!!! decrypt error : session {:08x} closed !!!\n",
+            session_id
+        );
+        if let Some(session) = self.common.get_session_via_id(session_id) {
+            let _ = session.teardown(session_id);
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+        }
+        spdm_result_err!(ESEC)
+    }
 
-            self.spdm_requester_respond_if_ready(expected_response_code, extend_error_data)
+    fn spdm_recover_decrypt_error(
+        &mut self,
+        session_id: u32,
+        decrypt_error_policy: SpdmDecryptErrorPolicy,
+        request: &[u8],
+    ) -> SpdmResult<ReceivedMessage> {
+        for retry in 1..=decrypt_error_policy.retry_count {
+            warn!(
+                "decrypt error, request sent again {} of {}\n",
+                retry, decrypt_error_policy.retry_count
+            );
+            if let Ok(received_message) = self.spdm_resend_secured_request(session_id, request) {
+                return Ok(received_message);
+            }
+        }
+        if decrypt_error_policy.rekey {
+            warn!("decrypt error, keys updated and request sent again\n");
+            self.send_receive_spdm_key_update(
+                SpdmSessionId::from(session_id),
+                SpdmKeyUpdateOperation::SpdmUpdateAllKeys,
+            )?;
+            return self.spdm_resend_secured_request(session_id, request);
         }
+        spdm_result_err!(ESEC)
     }
 
-    fn spdm_handle_simple_error_response(
+    // Encrypt request again and send it, an error unless the responder
+    // answers it with anything but ERROR.
+    fn spdm_resend_secured_request(
         &mut self,
-        session_id: Option<u32>,
-        error_code: u8,
+        session_id: u32,
+        request: &[u8],
     ) -> SpdmResult<ReceivedMessage> {
-        /* NOT_READY is treated as error here.
-         * Use spdm_handle_error_response_main to handle NOT_READY message in long latency command.*/
-        if error_code == SpdmErrorCode::SpdmErrorResponseNotReady.get_u8() {
-            spdm_result_err!(EDEV)
-        } else if error_code == SpdmErrorCode::SpdmErrorBusy.get_u8() {
-            spdm_result_err!(EBUSY)
-        } else if error_code == SpdmErrorCode::SpdmErrorRequestResynch.get_u8() {
-            if let Some(sid) = session_id {
-                let session = if let Some(s) = self.common.get_session_via_id(sid) {
-                    s
-                } else {
-                    return spdm_result_err!(EFAULT);
-                };
-                session.set_session_state(SpdmSessionState::SpdmSessionNotStarted);
+        self.send_secured_message(session_id, request, false)?;
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.receive_secured_message(session_id, &mut receive_buffer, true)?;
+
+        let mut reader = Reader::init(&receive_buffer[..used]);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header)
+                if message_header.request_response_code
+                    != SpdmRequestResponseCode::SpdmResponseError =>
+            {
+                Ok(ReceivedMessage {
+                    receive_buffer,
+                    used,
+                })
             }
-            spdm_result_err!(EDEV)
-        } else {
-            spdm_result_err!(EDEV)
+            _ => spdm_result_err!(ESEC),
         }
     }
 
     pub fn spdm_handle_error_response_main(
         &mut self,
-        session_id: Option<u32>,
+        session_id: Option<SpdmSessionId>,
         response: &[u8],
         original_request_code: SpdmRequestResponseCode,
         expected_response_code: SpdmRequestResponseCode,
     ) -> SpdmResult<ReceivedMessage> {
+        let session_id = session_id.map(u32::from);
         let mut spdm_message_header_reader = Reader::init(response);
         let spdm_message_header =
             if let Some(smh) = SpdmMessageHeader::read(&mut spdm_message_header_reader) {
                 smh
             } else {
-                return spdm_result_err!(EINVAL);
+                return spdm_result_err!(Codec);
             };
         let header_size = spdm_message_header_reader.used();
 
@@ -96,22 +244,27 @@ impl<'a> RequesterContext<'a> {
             return spdm_result_err!(EINVAL);
         }
 
+        let mut spdm_error_reader = Reader::init(&response[header_size..]);
+        self.last_error =
+            SpdmErrorResponsePayload::spdm_read(&mut self.common, &mut spdm_error_reader);
+        if let Some(last_error) = &self.last_error {
+            debug!("!!! error : {:02x?}\n", last_error);
+        }
+
         let mut spdm_message_payload_reader = Reader::init(&response[header_size..]);
         let spdm_message_general_payload =
             if let Some(smgp) = SpdmMessageGeneralPayload::read(&mut spdm_message_payload_reader) {
                 smgp
             } else {
-                return spdm_result_err!(EINVAL);
+                return spdm_result_err!(Codec);
             };
 
         if spdm_message_general_payload.param1 == SpdmErrorCode::SpdmErrorDecryptError.get_u8() {
             if let Some(sid) = session_id {
-                let session = if let Some(s) = self.common.get_session_via_id(sid) {
-                    s
-                } else {
+                if self.common.get_session_via_id(sid).is_none() {
                     return spdm_result_err!(EFAULT);
-                };
-                let _ = session.teardown(sid);
+                }
+                return self.spdm_handle_decrypt_error(sid);
             }
             spdm_result_err!(ESEC)
         } else if spdm_message_general_payload.param1
@@ -124,7 +277,12 @@ impl<'a> RequesterContext<'a> {
                 expected_response_code,
             )
         } else {
-            self.spdm_handle_simple_error_response(session_id, spdm_message_general_payload.param1)
+            self.spdm_handle_simple_error_response(
+                session_id,
+                spdm_message_general_payload.param1,
+                spdm_message_general_payload.param2,
+                original_request_code,
+            )
         }
     }
 }
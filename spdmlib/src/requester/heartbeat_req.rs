@@ -2,20 +2,64 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::{SpdmSessionId, SpdmSessionState};
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::requester::*;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_heartbeat(&mut self, session_id: u32) -> SpdmResult {
+    /// Keep the established sessions alive, elapsed is the time in seconds
+    /// since the previous call. A HEARTBEAT is sent in every session that was
+    /// idle for its HeartbeatPeriod, and the session is torn down if the
+    /// responder does not acknowledge it.
+    /// Return the number of sessions torn down.
+    pub fn process_spdm_heartbeat_keepalive(&mut self, elapsed: u32) -> usize {
+        let mut due_session_ids = [0u32; config::MAX_SPDM_SESSION_COUNT];
+        for (session, due_session_id) in self
+            .common
+            .session
+            .iter_mut()
+            .zip(due_session_ids.iter_mut())
+        {
+            if session.get_session_id() != 0
+                && session.get_session_state() == SpdmSessionState::SpdmSessionEstablished
+                && session.add_heartbeat_idle_time(elapsed)
+            {
+                *due_session_id = session.get_session_id();
+            }
+        }
+
+        let mut expired = 0;
+        for session_id in due_session_ids.iter().copied().filter(|id| *id != 0) {
+            if self
+                .send_receive_spdm_heartbeat(SpdmSessionId::from(session_id))
+                .is_err()
+            {
+                error!(
+                    "!!! heartbeat : no response, session {:08x} closed !!!\n",
+                    session_id
+                );
+                // an ERROR response may already have terminated it
+                if let Some(session) = self.common.get_session_via_id(session_id) {
+                    let _ = session.teardown(session_id);
+                    self.common
+                        .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+                }
+                expired += 1;
+            }
+        }
+        expired
+    }
+
+    pub fn send_receive_spdm_heartbeat(&mut self, session_id: SpdmSessionId) -> SpdmResult {
         info!("send spdm heartbeat\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.encode_spdm_heartbeat(&mut send_buffer);
-        self.send_secured_message(session_id, &send_buffer[..used], false)?;
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], false)?;
 
         // Receive
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let used = self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
         self.handle_spdm_heartbeat_response(session_id, &receive_buffer[..used])
     }
 
@@ -34,7 +78,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_heartbeat_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         receive_buffer: &[u8],
     ) -> SpdmResult {
         let mut reader = Reader::init(receive_buffer);
@@ -48,7 +92,7 @@ impl<'a> RequesterContext<'a> {
                         Ok(())
                     } else {
                         error!("!!! heartbeat : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -64,12 +108,12 @@ impl<'a> RequesterContext<'a> {
                             let used = rm.used;
                             self.handle_spdm_heartbeat_response(session_id, &receive_buffer[..used])
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -102,6 +146,10 @@ mod tests_requester {
         let rsp_session_id = 0x11u16;
         let session_id = (0x11u32 << 16) + rsp_session_id as u32;
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        responder.common.negotiate_info.req_capabilities_sel =
+            SpdmRequestCapabilityFlags::HBEAT_CAP;
+        responder.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::HBEAT_CAP;
         responder.common.session = gen_array_clone(SpdmSession::new(), 4);
         responder.common.session[0].setup(session_id).unwrap();
         responder.common.session[0].set_crypto_param(
@@ -137,7 +185,71 @@ mod tests_requester {
         requester.common.session[0]
             .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionHandshaking);
 
-        let status = requester.send_receive_spdm_heartbeat(session_id).is_ok();
+        let status = requester
+            .send_receive_spdm_heartbeat(SpdmSessionId::from(session_id))
+            .is_ok();
         assert!(status);
     }
+
+    #[test]
+    fn test_case0_process_spdm_heartbeat_keepalive() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let rsp_session_id = 0x11u16;
+        let session_id = (0x11u32 << 16) + rsp_session_id as u32;
+        responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        responder.common.session = gen_array_clone(SpdmSession::new(), 4);
+        responder.common.session[0].setup(session_id).unwrap();
+        responder.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        responder.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        requester.common.session = gen_array_clone(SpdmSession::new(), 4);
+        requester.common.session[0].setup(session_id).unwrap();
+        requester.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        requester.common.session[0]
+            .set_session_state(crate::common::session::SpdmSessionState::SpdmSessionEstablished);
+        requester.common.session[0].heartbeat_period = 10;
+
+        // the responder did not negotiate HBEAT_CAP, so it never acknowledges
+        assert_eq!(requester.process_spdm_heartbeat_keepalive(5), 0);
+        assert_eq!(requester.common.session[0].get_session_id(), session_id);
+        assert_eq!(requester.process_spdm_heartbeat_keepalive(5), 1);
+        assert_eq!(requester.common.session[0].get_session_id(), 0);
+    }
 }
@@ -5,6 +5,7 @@
 extern crate alloc;
 use alloc::boxed::Box;
 
+use crate::common::session::SpdmSessionId;
 use crate::common::ManagedBuffer;
 use crate::protocol::*;
 use crate::requester::*;
@@ -15,14 +16,12 @@ use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::{SpdmMeasurementSummaryHashType, SpdmSignatureStruct, SpdmVersion};
 
-const INITIAL_SESSION_ID: u16 = 0xFFFE;
-
 impl<'a> RequesterContext<'a> {
     pub fn send_receive_spdm_key_exchange(
         &mut self,
         slot_id: u8,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
-    ) -> SpdmResult<u32> {
+    ) -> SpdmResult<SpdmSessionId> {
         info!("send spdm key exchange\n");
 
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
@@ -37,7 +36,7 @@ impl<'a> RequesterContext<'a> {
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let receive_used = self.receive_message(&mut receive_buffer, false)?;
         self.handle_spdm_key_exhcange_response(
-            0,
+            SpdmSessionId::default(),
             slot_id,
             &send_buffer[..send_used],
             &receive_buffer[..receive_used],
@@ -52,44 +51,32 @@ impl<'a> RequesterContext<'a> {
         slot_id: u8,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
     ) -> SpdmResult<(Box<dyn crypto::SpdmDheKeyExchange>, usize)> {
+        if !self.common.is_peer_slot_provisioned(slot_id) {
+            error!("!!! slot {} is not in the peer slot mask !!!\n", slot_id);
+            return spdm_result_err!(EINVAL);
+        }
+
         let mut writer = Writer::init(buf);
 
-        let req_session_id = INITIAL_SESSION_ID;
+        if self.common.get_next_avaiable_session().is_none() {
+            error!("!!! too many sessions : fail !!!\n");
+            return spdm_result_err!(ENOMEM);
+        }
+
+        let req_session_id = self.common.generate_session_id_half(true)?;
 
         let mut random = [0u8; SPDM_RANDOM_SIZE];
-        crypto::rand::get_random(&mut random)?;
+        self.common.get_random(&mut random)?;
 
-        let (exchange, key_exchange_context) =
-            crypto::dhe::generate_key_pair(self.common.negotiate_info.dhe_sel)
-                .ok_or(spdm_err!(EFAULT))?;
+        let (exchange, key_exchange_context) = self
+            .common
+            .generate_dhe_key_pair(self.common.negotiate_info.dhe_sel)
+            .ok_or(spdm_err!(EFAULT))?;
 
         debug!("!!! exchange data : {:02x?}\n", exchange);
 
-        let mut opaque;
-        if self
-            .common
-            .negotiate_info
-            .opaque_data_support
-            .contains(SpdmOpaqueSupport::OPAQUE_DATA_FMT1)
-        {
-            opaque = SpdmOpaqueStruct {
-                data_size: crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT1
-                    .len() as u16,
-                ..Default::default()
-            };
-            opaque.data[..(opaque.data_size as usize)].copy_from_slice(
-                crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT1.as_ref(),
-            );
-        } else {
-            opaque = SpdmOpaqueStruct {
-                data_size: crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT0
-                    .len() as u16,
-                ..Default::default()
-            };
-            opaque.data[..(opaque.data_size as usize)].copy_from_slice(
-                crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT0.as_ref(),
-            );
-        }
+        let opaque =
+            SpdmOpaqueStruct::req_dmtf_supported_secure_spdm_version_list(&mut self.common)?;
 
         let request = SpdmMessage {
             header: SpdmMessageHeader {
@@ -112,13 +99,13 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_key_exhcange_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         slot_id: u8,
         send_buffer: &[u8],
         receive_buffer: &[u8],
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
         key_exchange_context: Box<dyn crypto::SpdmDheKeyExchange>,
-    ) -> SpdmResult<u32> {
+    ) -> SpdmResult<SpdmSessionId> {
         if (measurement_summary_hash_type
             == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb)
             || (measurement_summary_hash_type
@@ -138,6 +125,16 @@ impl<'a> RequesterContext<'a> {
                     let receive_used = reader.used();
                     if let Some(key_exchange_rsp) = key_exchange_rsp {
                         debug!("!!! key_exchange rsp : {:02x?}\n", key_exchange_rsp);
+                        if !key_exchange_rsp.mut_auth_req.is_empty()
+                            && !self
+                                .common
+                                .negotiate_info
+                                .req_capabilities_sel
+                                .contains(SpdmRequestCapabilityFlags::MUT_AUTH_CAP)
+                        {
+                            error!("!!! key_exchange rsp : unexpected mut_auth_req !!!\n");
+                            return spdm_result_err!(EINVAL);
+                        }
                         debug!(
                             "!!! exchange data (peer) : {:02x?}\n",
                             &key_exchange_rsp.exchange
@@ -154,7 +151,10 @@ impl<'a> RequesterContext<'a> {
                             self.common.negotiate_info.base_asym_sel.get_size() as usize;
                         let base_hash_size =
                             self.common.negotiate_info.base_hash_sel.get_size() as usize;
-                        let temp_receive_used = receive_used - base_asym_size - base_hash_size;
+                        // no ResponderVerifyData in the clear
+                        let in_clear_text = self.common.is_handshake_in_the_clear();
+                        let verify_data_size = if in_clear_text { 0 } else { base_hash_size };
+                        let temp_receive_used = receive_used - base_asym_size - verify_data_size;
 
                         #[cfg(feature = "hash-update")]
                         let cert_chain_hash;
@@ -199,6 +199,15 @@ impl<'a> RequesterContext<'a> {
                             );
                         }
 
+                        if !self.common.is_key_usage_allowed(
+                            &self.common.peer_info.peer_key_usage_mask,
+                            slot_id,
+                            SpdmKeyUsageBitMask::KEY_EX_USE,
+                        ) {
+                            error!("slot {} key is not for key exchange\n", slot_id);
+                            return spdm_result_err!(EFAULT);
+                        }
+
                         if self
                             .verify_key_exchange_rsp_signature(
                                 slot_id,
@@ -211,7 +220,7 @@ impl<'a> RequesterContext<'a> {
                             .is_err()
                         {
                             error!("verify_key_exchange_rsp_signature fail");
-                            return spdm_result_err!(EFAULT);
+                            return spdm_result_err!(VerificationFailed);
                         } else {
                             info!("verify_key_exchange_rsp_signature pass");
                         }
@@ -239,16 +248,23 @@ impl<'a> RequesterContext<'a> {
                         let dhe_algo = self.common.negotiate_info.dhe_sel;
                         let aead_algo = self.common.negotiate_info.aead_sel;
                         let key_schedule_algo = self.common.negotiate_info.key_schedule_sel;
-                        let sequence_number_count =
-                            self.common.transport_encap.get_sequence_number_count();
-                        let max_random_count = self.common.transport_encap.get_max_random_count();
+                        let transport_param = self.common.get_session_transport_param();
+                        let key_update_policy = self.common.config_info.key_update_policy;
 
                         let secure_spdm_version_sel = if let Some(secured_message_version) =
                             key_exchange_rsp
                                 .opaque
                                 .req_get_dmtf_secure_spdm_version_selection(&mut self.common)
                         {
-                            secured_message_version.get_secure_spdm_version()
+                            let secure_spdm_version =
+                                secured_message_version.get_secure_spdm_version();
+                            if !is_supported_secure_spdm_version(secure_spdm_version) {
+                                error!(
+                                    "!!! key_exchange : unsupported secured message version !!!\n"
+                                );
+                                return spdm_result_err!(EFAULT);
+                            }
+                            secure_spdm_version
                         } else {
                             0
                         };
@@ -258,17 +274,34 @@ impl<'a> RequesterContext<'a> {
                             secure_spdm_version_sel
                         );
 
-                        let session_id = ((INITIAL_SESSION_ID as u32) << 16)
-                            + key_exchange_rsp.rsp_session_id as u32;
+                        let heartbeat_period = if self.common.is_heartbeat_negotiated() {
+                            key_exchange_rsp.heartbeat_period
+                        } else {
+                            0
+                        };
+                        let session_policy = if self.common.negotiate_info.spdm_version_sel.get_u8()
+                            >= SpdmVersion::SpdmVersion12.get_u8()
+                        {
+                            self.common.config_info.session_policy
+                        } else {
+                            0
+                        };
+
+                        let session_id = SpdmSessionId::new(
+                            get_req_session_id(send_buffer)?,
+                            key_exchange_rsp.rsp_session_id,
+                        )
+                        .as_u32();
                         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
                         let session = self
                             .common
                             .get_next_avaiable_session()
-                            .ok_or(spdm_err!(EINVAL))?;
+                            .ok_or(spdm_err!(ENOMEM))?;
 
                         session.setup(session_id)?;
 
                         session.set_use_psk(false);
+                        session.set_handshake_in_the_clear(in_clear_text);
 
                         session.set_crypto_param(
                             base_hash_algo,
@@ -276,53 +309,63 @@ impl<'a> RequesterContext<'a> {
                             aead_algo,
                             key_schedule_algo,
                         );
-                        session.set_transport_param(sequence_number_count, max_random_count);
-                        session.set_dhe_secret(spdm_version_sel, final_key)?;
-                        session.generate_handshake_secret(spdm_version_sel, &th1)?;
+                        session.set_transport_param(transport_param);
+                        session.set_key_update_policy(key_update_policy);
+                        if session.set_dhe_secret(spdm_version_sel, final_key).is_err()
+                            || session
+                                .generate_handshake_secret(spdm_version_sel, &th1)
+                                .is_err()
+                        {
+                            let _ = session.teardown(session_id);
+                            return spdm_result_err!(ESEC);
+                        }
 
                         // verify HMAC with finished_key
                         #[cfg(not(feature = "hash-update"))]
                         let transcript_data = self
                             .common
                             .calc_req_transcript_data(slot_id, false, &message_k, None)?;
-                        let mut session = self
-                            .common
-                            .get_session_via_id(session_id)
-                            .ok_or(spdm_err!(EINVAL))?;
-
-                        if session
-                            .verify_hmac_with_response_finished_key(
-                                #[cfg(not(feature = "hash-update"))]
-                                transcript_data.as_ref(),
-                                #[cfg(feature = "hash-update")]
-                                crypto::hash::hash_ctx_finalize(message_k.clone())
-                                    .unwrap()
-                                    .as_ref(),
-                                &key_exchange_rsp.verify_data,
-                            )
-                            .is_err()
-                        {
-                            error!("verify_hmac_with_response_finished_key fail");
-                            let _ = session.teardown(session_id);
-                            return spdm_result_err!(EFAULT);
-                        } else {
-                            info!("verify_hmac_with_response_finished_key pass");
-                        }
-                        #[cfg(not(feature = "hash-update"))]
-                        {
+                        let mut session = self.common.get_session_mut(session_id)?;
+
+                        if !in_clear_text {
+                            if session
+                                .verify_hmac_with_response_finished_key(
+                                    #[cfg(not(feature = "hash-update"))]
+                                    transcript_data.as_ref(),
+                                    #[cfg(feature = "hash-update")]
+                                    crypto::hash::hash_ctx_finalize(message_k.clone())
+                                        .unwrap()
+                                        .as_ref(),
+                                    &key_exchange_rsp.verify_data,
+                                )
+                                .is_err()
+                            {
+                                error!("verify_hmac_with_response_finished_key fail");
+                                let _ = session.teardown(session_id);
+                                self.common.notify_session_event(
+                                    session_id,
+                                    SpdmSessionEvent::HmacFailure,
+                                );
+                                return spdm_result_err!(VerificationFailed);
+                            } else {
+                                info!("verify_hmac_with_response_finished_key pass");
+                            }
+                            #[cfg(not(feature = "hash-update"))]
                             message_k
                                 .append_message(key_exchange_rsp.verify_data.as_ref())
                                 .ok_or(spdm_err!(ENOMEM))?;
-                            session.runtime_info.message_k = message_k;
-                        }
-
-                        #[cfg(feature = "hash-update")]
-                        {
+                            #[cfg(feature = "hash-update")]
                             crypto::hash::hash_ctx_update(
                                 &mut message_k,
                                 key_exchange_rsp.verify_data.as_ref(),
                             );
-
+                        }
+                        #[cfg(not(feature = "hash-update"))]
+                        {
+                            session.runtime_info.message_k = message_k;
+                        }
+                        #[cfg(feature = "hash-update")]
+                        {
                             session.runtime_info.message_k = Some(message_k);
                         }
 
@@ -331,12 +374,19 @@ impl<'a> RequesterContext<'a> {
                         );
 
                         session.secure_spdm_version_sel = secure_spdm_version_sel;
-                        session.heartbeat_period = key_exchange_rsp.heartbeat_period;
+                        session.heartbeat_period = heartbeat_period;
+                        session.mut_auth_requested = key_exchange_rsp.mut_auth_req;
+                        session.slot_id = slot_id;
+                        session.req_slot_id = key_exchange_rsp.req_slot_id;
+                        session.session_policy = session_policy;
+
+                        self.common
+                            .notify_session_event(session_id, SpdmSessionEvent::HandshakeStarted);
 
-                        Ok(session_id)
+                        Ok(SpdmSessionId::from(session_id))
                     } else {
                         error!("!!! key_exchange : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -359,12 +409,12 @@ impl<'a> RequesterContext<'a> {
                                 key_exchange_context,
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 
@@ -507,7 +557,7 @@ mod tests_requester {
             rsp_provision_info,
         );
 
-        responder.common.provision_info.my_cert_chain = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain[0] = Some(REQ_CERT_CHAIN_DATA);
 
         responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         responder.common.negotiate_info.aead_sel = SpdmAeadAlgo::AES_128_GCM;
@@ -532,7 +582,7 @@ mod tests_requester {
             responder.common.runtime_info.message_m.as_mut().unwrap(),
             message_m,
         );
-        responder.common.provision_info.my_cert_chain_data = Some(REQ_CERT_CHAIN_DATA);
+        responder.common.provision_info.my_cert_chain_data[0] = Some(REQ_CERT_CHAIN_DATA);
 
         let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
         let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
@@ -573,6 +623,7 @@ mod tests_requester {
             .as_mut()
             .unwrap()
             .cert_chain = REQ_CERT_CHAIN_DATA;
+        requester.common.peer_info.peer_slot_mask = 0x1;
 
         let measurement_summary_hash_type =
             SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll;
@@ -0,0 +1,354 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionId;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    pub fn send_receive_spdm_get_key_pair_info(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        key_pair_id: u8,
+    ) -> SpdmResult<SpdmKeyPairInfoResponsePayload> {
+        info!("send spdm get_key_pair_info\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = self.encode_spdm_get_key_pair_info(key_pair_id, &mut send_buffer);
+        if session_id.is_none() {
+            self.send_message(&send_buffer[..send_used])?;
+        } else {
+            self.send_secured_message(
+                session_id.unwrap().as_u32(),
+                &send_buffer[..send_used],
+                false,
+            )?;
+        }
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = if session_id.is_none() {
+            self.receive_message(&mut receive_buffer, false)?
+        } else {
+            self.receive_secured_message(session_id.unwrap().as_u32(), &mut receive_buffer, false)?
+        };
+
+        self.handle_spdm_key_pair_info_response(session_id, key_pair_id, &receive_buffer[..used])
+    }
+
+    pub fn encode_spdm_get_key_pair_info(&mut self, key_pair_id: u8, buf: &mut [u8]) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo,
+            },
+            payload: SpdmMessagePayload::SpdmGetKeyPairInfoRequest(
+                SpdmGetKeyPairInfoRequestPayload { key_pair_id },
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn handle_spdm_key_pair_info_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        key_pair_id: u8,
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmKeyPairInfoResponsePayload> {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseKeyPairInfo => {
+                    let key_pair_info =
+                        SpdmKeyPairInfoResponsePayload::spdm_read(&mut self.common, &mut reader);
+                    if let Some(key_pair_info) = key_pair_info {
+                        debug!("!!! key_pair_info : {:02x?}\n", key_pair_info.key_pair_id);
+                        if key_pair_info.key_pair_id != key_pair_id
+                            || key_pair_info.key_pair_id > key_pair_info.total_key_pairs
+                        {
+                            error!("!!! key_pair_info : key_pair_id mismatch !!!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
+                        Ok(key_pair_info)
+                    } else {
+                        error!("!!! key_pair_info : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo,
+                        SpdmRequestResponseCode::SpdmResponseKeyPairInfo,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_key_pair_info_response(
+                                session_id,
+                                key_pair_id,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    pub fn send_receive_spdm_set_key_pair_info(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload,
+    ) -> SpdmResult {
+        info!("send spdm set_key_pair_info\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = self.encode_spdm_set_key_pair_info(set_key_pair_info, &mut send_buffer);
+        if session_id.is_none() {
+            self.send_message(&send_buffer[..send_used])?;
+        } else {
+            self.send_secured_message(
+                session_id.unwrap().as_u32(),
+                &send_buffer[..send_used],
+                false,
+            )?;
+        }
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = if session_id.is_none() {
+            self.receive_message(&mut receive_buffer, false)?
+        } else {
+            self.receive_secured_message(session_id.unwrap().as_u32(), &mut receive_buffer, false)?
+        };
+
+        self.handle_spdm_set_key_pair_info_ack_response(session_id, &receive_buffer[..used])
+    }
+
+    pub fn encode_spdm_set_key_pair_info(
+        &mut self,
+        set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo,
+            },
+            payload: SpdmMessagePayload::SpdmSetKeyPairInfoRequest(set_key_pair_info.clone()),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn handle_spdm_set_key_pair_info_ack_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        receive_buffer: &[u8],
+    ) -> SpdmResult {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck => {
+                    let set_key_pair_info_ack = SpdmSetKeyPairInfoAckResponsePayload::spdm_read(
+                        &mut self.common,
+                        &mut reader,
+                    );
+                    if let Some(set_key_pair_info_ack) = set_key_pair_info_ack {
+                        debug!(
+                            "!!! set_key_pair_info_ack : {:02x?}\n",
+                            set_key_pair_info_ack
+                        );
+                        Ok(())
+                    } else {
+                        error!("!!! set_key_pair_info_ack : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo,
+                        SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_set_key_pair_info_ack_response(
+                                session_id,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::secret::SpdmKeyPairStore;
+    use crate::testlib::*;
+    use crate::{crypto, responder, secret};
+
+    const TOTAL_KEY_PAIRS: u8 = 2;
+    const PUBLIC_KEY_INFO: [u8; 4] = [0x30, 0x02, 0x05, 0x00];
+
+    // current key usage and cert slot mask of key pair 1
+    static KEY_PAIR_1: spin::Mutex<(SpdmKeyUsageBitMask, u8)> =
+        spin::Mutex::new((SpdmKeyUsageBitMask::CHALLENGE_USE, 0b0000_0001));
+
+    static KEY_PAIR_STORE: SpdmKeyPairStore = SpdmKeyPairStore {
+        get_key_pair_info_cb: |key_pair_id: u8| -> Option<SpdmKeyPairInfoResponsePayload> {
+            if key_pair_id > TOTAL_KEY_PAIRS {
+                return None;
+            }
+            let (current_key_usage, assoc_cert_slot_mask) = *KEY_PAIR_1.lock();
+            let mut key_pair_info = SpdmKeyPairInfoResponsePayload {
+                total_key_pairs: TOTAL_KEY_PAIRS,
+                key_pair_id,
+                capabilities: SpdmKeyPairCapabilities::CERT_ASSOC_CAP
+                    | SpdmKeyPairCapabilities::KEY_USAGE_CAP,
+                key_usage_capabilities: SpdmKeyUsageBitMask::CHALLENGE_USE
+                    | SpdmKeyUsageBitMask::MEASUREMENT_USE,
+                current_key_usage,
+                asym_algo_capabilities: SpdmKeyPairAsymAlgo::ECC384,
+                current_asym_algo: SpdmKeyPairAsymAlgo::ECC384,
+                assoc_cert_slot_mask,
+                public_key_info_len: PUBLIC_KEY_INFO.len() as u16,
+                ..Default::default()
+            };
+            key_pair_info.public_key_info[..PUBLIC_KEY_INFO.len()]
+                .copy_from_slice(&PUBLIC_KEY_INFO);
+            Some(key_pair_info)
+        },
+        set_key_pair_info_cb: |set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload| -> SpdmResult {
+            if set_key_pair_info.key_pair_id != 1
+                || set_key_pair_info.operation
+                    != SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoChange
+            {
+                return spdm_result_err!(EINVAL);
+            }
+            *KEY_PAIR_1.lock() = (
+                set_key_pair_info.desired_key_usage,
+                set_key_pair_info.desired_assoc_cert_slot_mask,
+            );
+            Ok(())
+        },
+    };
+
+    #[test]
+    fn test_case0_send_receive_spdm_key_pair_info() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        for config_info in [&mut rsp_config_info, &mut req_config_info] {
+            config_info.spdm_version = [
+                SpdmVersion::SpdmVersion11,
+                SpdmVersion::SpdmVersion12,
+                SpdmVersion::SpdmVersion13,
+            ];
+        }
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::GET_KEY_PAIR_INFO_CAP
+            | SpdmResponseCapabilityFlags::SET_KEY_PAIR_INFO_CAP;
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        secret::register_key_pair_store(KEY_PAIR_STORE.clone());
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert_eq!(
+            requester.common.negotiate_info.spdm_version_sel,
+            SpdmVersion::SpdmVersion13
+        );
+
+        let key_pair_info = requester
+            .send_receive_spdm_get_key_pair_info(None, 1)
+            .unwrap();
+        assert_eq!(key_pair_info.total_key_pairs, TOTAL_KEY_PAIRS);
+        assert_eq!(key_pair_info.key_pair_id, 1);
+        assert_eq!(
+            key_pair_info.current_key_usage,
+            SpdmKeyUsageBitMask::CHALLENGE_USE
+        );
+        assert_eq!(key_pair_info.assoc_cert_slot_mask, 0b0000_0001);
+        assert_eq!(
+            key_pair_info.public_key_info[..key_pair_info.public_key_info_len as usize],
+            PUBLIC_KEY_INFO
+        );
+
+        // KeyPairID starts from 1 and stops at TotalKeyPairs
+        assert!(requester
+            .send_receive_spdm_get_key_pair_info(None, 0)
+            .is_err());
+        assert!(requester
+            .send_receive_spdm_get_key_pair_info(None, TOTAL_KEY_PAIRS + 1)
+            .is_err());
+
+        let set_key_pair_info = SpdmSetKeyPairInfoRequestPayload {
+            operation: SpdmSetKeyPairInfoOperation::SpdmSetKeyPairInfoChange,
+            key_pair_id: 1,
+            desired_key_usage: SpdmKeyUsageBitMask::MEASUREMENT_USE,
+            desired_asym_algo: SpdmKeyPairAsymAlgo::ECC384,
+            desired_assoc_cert_slot_mask: 0b0000_0110,
+        };
+        assert!(requester
+            .send_receive_spdm_set_key_pair_info(None, &set_key_pair_info)
+            .is_ok());
+
+        let key_pair_info = requester
+            .send_receive_spdm_get_key_pair_info(None, 1)
+            .unwrap();
+        assert_eq!(
+            key_pair_info.current_key_usage,
+            SpdmKeyUsageBitMask::MEASUREMENT_USE
+        );
+        assert_eq!(key_pair_info.assoc_cert_slot_mask, 0b0000_0110);
+
+        // the store refuses the change, nothing is applied
+        let set_key_pair_info = SpdmSetKeyPairInfoRequestPayload {
+            key_pair_id: 2,
+            ..set_key_pair_info
+        };
+        assert!(requester
+            .send_receive_spdm_set_key_pair_info(None, &set_key_pair_info)
+            .is_err());
+        assert_eq!(
+            *KEY_PAIR_1.lock(),
+            (SpdmKeyUsageBitMask::MEASUREMENT_USE, 0b0000_0110)
+        );
+    }
+}
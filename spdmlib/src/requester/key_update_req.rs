@@ -2,42 +2,76 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::error::{spdm_result_err, SpdmResult};
+use crate::common::session::SpdmSessionId;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::requester::*;
 
 impl<'a> RequesterContext<'a> {
     fn send_receive_spdm_key_update_op(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         key_update_operation: SpdmKeyUpdateOperation,
         tag: u8,
     ) -> SpdmResult {
         info!("send spdm key_update\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.encode_spdm_key_update_op(key_update_operation, tag, &mut send_buffer);
-        self.send_secured_message(session_id, &send_buffer[..used], false)?;
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], false)?;
 
         // update key
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-            s
-        } else {
-            return spdm_result_err!(EFAULT);
-        };
+        let session = self.common.get_session_mut(session_id)?;
         let update_requester = key_update_operation == SpdmKeyUpdateOperation::SpdmUpdateSingleKey
             || key_update_operation == SpdmKeyUpdateOperation::SpdmUpdateAllKeys;
         let update_responder = key_update_operation == SpdmKeyUpdateOperation::SpdmUpdateAllKeys;
         session.create_data_secret_update(spdm_version_sel, update_requester, update_responder)?;
+
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let result =
+            match self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false) {
+                Ok(used) => self.handle_spdm_key_update_op_response(
+                    session_id,
+                    key_update_operation,
+                    tag,
+                    &receive_buffer[..used],
+                ),
+                Err(e) => Err(e),
+            };
 
-        self.handle_spdm_key_update_op_response(
-            session_id,
-            update_requester,
-            update_responder,
-            &receive_buffer[..used],
-        )
+        // The new response key is in use as soon as the KEY_UPDATE_ACK decrypts,
+        // the old request key is kept until the responder verified the new one.
+        // An ERROR response may already have terminated the session.
+        if let Some(session) = self.common.get_session_via_id(session_id.as_u32()) {
+            match key_update_operation {
+                SpdmKeyUpdateOperation::SpdmUpdateSingleKey => {
+                    if result.is_err() {
+                        session.activate_data_secret_update(
+                            spdm_version_sel,
+                            true,
+                            false,
+                            false,
+                        )?;
+                    }
+                }
+                SpdmKeyUpdateOperation::SpdmUpdateAllKeys => {
+                    if result.is_ok() {
+                        session.activate_data_secret_update(spdm_version_sel, false, true, true)?;
+                    } else {
+                        session.activate_data_secret_update(spdm_version_sel, true, true, false)?;
+                    }
+                }
+                _ => {
+                    session.activate_data_secret_update(
+                        spdm_version_sel,
+                        true,
+                        false,
+                        result.is_ok(),
+                    )?;
+                }
+            }
+        }
+        result
     }
 
     pub fn encode_spdm_key_update_op(
@@ -63,9 +97,9 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_key_update_op_response(
         &mut self,
-        session_id: u32,
-        update_requester: bool,
-        update_responder: bool,
+        session_id: SpdmSessionId,
+        key_update_operation: SpdmKeyUpdateOperation,
+        tag: u8,
         receive_buffer: &[u8],
     ) -> SpdmResult {
         let mut reader = Reader::init(receive_buffer);
@@ -74,30 +108,18 @@ impl<'a> RequesterContext<'a> {
                 SpdmRequestResponseCode::SpdmResponseKeyUpdateAck => {
                     let key_update_rsp =
                         SpdmKeyUpdateResponsePayload::spdm_read(&mut self.common, &mut reader);
-                    let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-                    let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                        s
-                    } else {
-                        return spdm_result_err!(EFAULT);
-                    };
-                    if let Some(key_update_rsp) = key_update_rsp {
-                        debug!("!!! key_update rsp : {:02x?}\n", key_update_rsp);
-                        session.activate_data_secret_update(
-                            spdm_version_sel,
-                            update_requester,
-                            update_responder,
-                            true,
-                        )?;
-                        Ok(())
-                    } else {
-                        error!("!!! key_update : fail !!!\n");
-                        session.activate_data_secret_update(
-                            spdm_version_sel,
-                            update_requester,
-                            update_responder,
-                            false,
-                        )?;
-                        spdm_result_err!(EFAULT)
+                    match key_update_rsp {
+                        Some(key_update_rsp)
+                            if key_update_rsp.key_update_operation == key_update_operation
+                                && key_update_rsp.tag == tag =>
+                        {
+                            debug!("!!! key_update rsp : {:02x?}\n", key_update_rsp);
+                            Ok(())
+                        }
+                        _ => {
+                            error!("!!! key_update : fail !!!\n");
+                            spdm_result_err!(Codec)
+                        }
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -113,23 +135,25 @@ impl<'a> RequesterContext<'a> {
                             let used = rm.used;
                             self.handle_spdm_key_update_op_response(
                                 session_id,
-                                update_requester,
-                                update_responder,
+                                key_update_operation,
+                                tag,
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 
+    /// Roll the session keys with UpdateKey or UpdateAllKeys, then confirm
+    /// the new request key with VerifyNewKey.
     pub fn send_receive_spdm_key_update(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         key_update_operation: SpdmKeyUpdateOperation,
     ) -> SpdmResult {
         if key_update_operation != SpdmKeyUpdateOperation::SpdmUpdateAllKeys
@@ -142,7 +166,30 @@ impl<'a> RequesterContext<'a> {
             session_id,
             SpdmKeyUpdateOperation::SpdmVerifyNewKey,
             2,
-        )
+        )?;
+        self.common
+            .notify_session_event(session_id.as_u32(), SpdmSessionEvent::KeyUpdated);
+        Ok(())
+    }
+
+    /// Update the session keys if the key update policy of the session says
+    /// they are due, or a direction runs out of sequence numbers. Returns
+    /// true if they were updated. Nothing is done unless both ends support
+    /// KEY_UPDATE, the session then fails once out of sequence numbers.
+    pub fn key_update_if_due(&mut self, session_id: SpdmSessionId) -> SpdmResult<bool> {
+        let session = self.common.get_session(session_id.as_u32())?;
+        let key_update_operation = match session.get_due_key_update() {
+            Some(key_update_operation) if self.common.is_key_update_negotiated() => {
+                key_update_operation
+            }
+            _ => return Ok(false),
+        };
+        info!(
+            "!!! key_update : keys due, {:?} !!!\n",
+            key_update_operation
+        );
+        self.send_receive_spdm_key_update(session_id, key_update_operation)?;
+        Ok(true)
     }
 }
 
@@ -246,13 +293,19 @@ mod tests_requester {
         );
         let measurement_summary_hash_type = SpdmKeyUpdateOperation::SpdmUpdateAllKeys;
         let status = requester
-            .send_receive_spdm_key_update(session_id, measurement_summary_hash_type)
+            .send_receive_spdm_key_update(
+                SpdmSessionId::from(session_id),
+                measurement_summary_hash_type,
+            )
             .is_ok();
         assert!(status);
 
         let measurement_summary_hash_type = SpdmKeyUpdateOperation::Unknown(0);
         let status = requester
-            .send_receive_spdm_key_update(session_id, measurement_summary_hash_type)
+            .send_receive_spdm_key_update(
+                SpdmSessionId::from(session_id),
+                measurement_summary_hash_type,
+            )
             .is_err();
         assert!(status);
     }
@@ -2,28 +2,54 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+mod async_context;
+mod builder;
 mod context;
+mod poll_context;
 
 mod challenge_req;
+mod chunk_req;
+mod encap_req;
 mod end_session_req;
+mod event_req;
 mod finish_req;
 mod get_capabilities_req;
 mod get_certificate_req;
 mod get_digests_req;
+mod get_endpoint_info_req;
 pub mod get_measurements_req;
 mod get_version_req;
 mod handle_error_response_req;
 mod heartbeat_req;
 mod key_exchange_req;
+mod key_pair_info_req;
 pub mod key_update_req;
 mod negotiate_algorithms_req;
 mod psk_exchange_req;
 mod psk_finish_req;
 mod respond_if_ready_req;
+mod set_certificate_req;
 mod vendor_req;
 
-pub use context::RequesterContext;
+pub use async_context::AsyncRequesterContext;
+pub use builder::RequesterBuilder;
+pub use context::{
+    RequesterContext, SpdmCancelToken, SpdmPeerCertPolicy, SpdmResynchHandler,
+    SpdmStartSessionPolicy,
+};
+pub use get_certificate_req::{SpdmCertChainReport, SpdmSlotCertChain};
+pub use get_measurements_req::SpdmMeasurements;
+pub use poll_context::{PollRequesterContext, SpdmPollOutcome, SpdmPollRequest, SpdmPollStatus};
 
 use crate::common::*;
 use crate::config;
 use codec::{Codec, Reader, Writer};
+
+// ReqSessionID of a KEY_EXCHANGE or PSK_EXCHANGE request, it follows the
+// header, Param1 and Param2 in both.
+fn get_req_session_id(request: &[u8]) -> crate::error::SpdmResult<u16> {
+    match request.get(4..6) {
+        Some(bytes) => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
+        None => crate::error::spdm_result_err!(EINVAL),
+    }
+}
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 #[cfg(feature = "hash-update")]
 use crate::crypto;
 use crate::error::{spdm_result_err, SpdmResult};
@@ -17,7 +18,11 @@ impl<'a> RequesterContext<'a> {
 
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let used = self.receive_message(&mut receive_buffer, false)?;
-        self.handle_spdm_algorithm_response(0, &send_buffer[..send_used], &receive_buffer[..used])
+        self.handle_spdm_algorithm_response(
+            SpdmSessionId::default(),
+            &send_buffer[..send_used],
+            &receive_buffer[..used],
+        )
     }
 
     pub fn encode_spdm_algorithm(&mut self, buf: &mut [u8]) -> usize {
@@ -75,7 +80,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_algorithm_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         send_buffer: &[u8],
         receive_buffer: &[u8],
     ) -> SpdmResult {
@@ -141,7 +146,7 @@ impl<'a> RequesterContext<'a> {
                         return Ok(());
                     }
                     error!("!!! algorithms : fail !!!\n");
-                    spdm_result_err!(EFAULT)
+                    spdm_result_err!(Codec)
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
                     let erm = self.spdm_handle_error_response_main(
@@ -160,12 +165,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
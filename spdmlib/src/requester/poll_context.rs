@@ -0,0 +1,996 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Sans-IO requester: a state machine handing out the transport units to
+//! send and taking the units received, for event loops and interrupt driven
+//! firmware that cannot block in SpdmDeviceIo.
+//!
+//! ```ignore
+//! let mut status = requester.start(SpdmPollRequest::InitConnection)?;
+//! loop {
+//!     match status {
+//!         SpdmPollStatus::Transmit { delay } => {
+//!             timer.sleep(delay);
+//!             while let Some(unit) = requester.poll_transmit()? {
+//!                 mailbox.write(unit);
+//!             }
+//!             status = SpdmPollStatus::Receive;
+//!         }
+//!         SpdmPollStatus::Receive => match mailbox.read(requester.receive_timeout()) {
+//!             Some(unit) => status = requester.handle_received(unit)?,
+//!             None => status = requester.handle_timeout()?,
+//!         },
+//!         SpdmPollStatus::Done(outcome) => break,
+//!     }
+//! }
+//! ```
+
+use crate::common::async_io::{SpdmDeferredDeviceIo, SpdmDeferredIo};
+use crate::common::session::SpdmSessionId;
+use crate::common::{self, ManagedBuffer, SpdmTransportEncap};
+use crate::config;
+use crate::crypto::SpdmDheKeyExchange;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::requester::async_context::get_response_not_ready;
+use crate::requester::respond_if_ready_req::SpdmNotReadyWait;
+use crate::requester::{RequesterContext, SpdmMeasurements};
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+/// What PollRequesterContext::start runs to completion.
+#[derive(Debug, Clone, Copy)]
+pub enum SpdmPollRequest {
+    // GET_VERSION, GET_CAPABILITIES and NEGOTIATE_ALGORITHMS
+    InitConnection,
+    GetDigests {
+        session_id: Option<SpdmSessionId>,
+    },
+    GetCertificate {
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+    },
+    Challenge {
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    },
+    GetMeasurements {
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        measurement_attributes: SpdmMeasurementeAttributes,
+        measurement_operation: SpdmMeasurementOperation,
+    },
+    // KEY_EXCHANGE and FINISH, or PSK_EXCHANGE and PSK_FINISH
+    StartSession {
+        use_psk: bool,
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    },
+    EndSession {
+        session_id: SpdmSessionId,
+    },
+}
+
+/// Result of a completed SpdmPollRequest.
+#[derive(Debug)]
+pub enum SpdmPollOutcome {
+    Connected,
+    Digests(SpdmDigestsResponsePayload),
+    CertChain(SpdmCertChainData),
+    Challenged,
+    Measurements(SpdmMeasurements),
+    SessionStarted(SpdmSessionId),
+    SessionEnded,
+}
+
+#[derive(Debug)]
+pub enum SpdmPollStatus {
+    /// poll_transmit has a request to send, delay microseconds from now.
+    Transmit {
+        delay: usize,
+    },
+    /// A response, or the next unit of it, is awaited for receive_timeout.
+    Receive,
+    Done(SpdmPollOutcome),
+}
+
+// The request in flight, with what handling its response takes.
+enum SpdmPollStep {
+    Idle,
+    Version,
+    Capabilities,
+    Algorithms,
+    Digests,
+    Certificate {
+        slot_id: u8,
+        offset: u16,
+        length: u16,
+        total_length: Option<usize>,
+    },
+    Challenge {
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    },
+    Measurements {
+        slot_id: u8,
+        measurement_attributes: SpdmMeasurementeAttributes,
+        measurement_operation: SpdmMeasurementOperation,
+    },
+    KeyExchange {
+        slot_id: u8,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        key_exchange_context: Box<dyn SpdmDheKeyExchange>,
+    },
+    Finish {
+        session_id: SpdmSessionId,
+        slot_id: u8,
+        base_hash_size: usize,
+        message_f: ManagedBuffer,
+    },
+    PskExchange {
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+    },
+    PskFinish {
+        message_f: ManagedBuffer,
+    },
+    EndSession,
+}
+
+/// RequesterContext that never calls an SpdmDeviceIo. One SpdmPollRequest
+/// runs at a time: start it, send what poll_transmit hands out, and feed
+/// every unit received to handle_received until the status is Done.
+///
+/// Requests are encoded and responses handled by the sync requester, as in
+/// AsyncRequesterContext.
+pub struct PollRequesterContext<'a> {
+    pub requester: RequesterContext<'a>,
+    step: SpdmPollStep,
+    // the session the request in flight goes through, if any
+    session_id: Option<SpdmSessionId>,
+    crypto_request: bool,
+    // the SPDM request in flight, as handle_*_response needs it
+    request: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    request_size: usize,
+    transmit_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    transmit_size: usize,
+    transmit_offset: usize,
+    fragment_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    receive_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    receive_size: usize,
+    retry: u8,
+    not_ready_wait: SpdmNotReadyWait,
+}
+
+impl<'a> PollRequesterContext<'a> {
+    pub fn new(
+        transport_encap: &'a mut dyn SpdmTransportEncap,
+        config_info: common::SpdmConfigInfo,
+        provision_info: common::SpdmProvisionInfo,
+    ) -> Self {
+        let mut requester = RequesterContext::new(
+            SpdmDeferredDeviceIo::leaked(),
+            transport_encap,
+            config_info,
+            provision_info,
+        );
+        requester.common.deferred_io = Some(SpdmDeferredIo::new());
+        PollRequesterContext {
+            requester,
+            step: SpdmPollStep::Idle,
+            session_id: None,
+            crypto_request: false,
+            request: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            request_size: 0,
+            transmit_buffer: [0u8; config::DATA_TRANSFER_SIZE],
+            transmit_size: 0,
+            transmit_offset: 0,
+            fragment_buffer: [0u8; config::DATA_TRANSFER_SIZE],
+            receive_buffer: [0u8; config::DATA_TRANSFER_SIZE],
+            receive_size: 0,
+            retry: 0,
+            not_ready_wait: SpdmNotReadyWait::default(),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        matches!(self.step, SpdmPollStep::Idle)
+    }
+
+    /// Encode the first request of request. EBUSY while another one runs.
+    pub fn start(&mut self, request: SpdmPollRequest) -> SpdmResult<SpdmPollStatus> {
+        if !self.is_idle() {
+            return spdm_result_err!(EBUSY);
+        }
+        let result = self.start_request(request);
+        self.abort_on_error(result)
+    }
+
+    /// Drop the request in flight, a late response to it is not handled.
+    pub fn abort(&mut self) {
+        self.step = SpdmPollStep::Idle;
+        self.transmit_size = 0;
+        self.transmit_offset = 0;
+        self.receive_size = 0;
+    }
+
+    /// The next transport unit to send, None once the request is all out.
+    pub fn poll_transmit(&mut self) -> SpdmResult<Option<&[u8]>> {
+        if self.transmit_offset >= self.transmit_size {
+            return Ok(None);
+        }
+        let transport_encap = &mut *self.requester.common.transport_encap;
+        if transport_encap.get_max_transport_unit().is_none() {
+            let offset = core::mem::replace(&mut self.transmit_offset, self.transmit_size);
+            return Ok(Some(&self.transmit_buffer[offset..self.transmit_size]));
+        }
+
+        let (consumed, used) = match transport_encap.encap_fragment(
+            &self.transmit_buffer[self.transmit_offset..self.transmit_size],
+            self.transmit_offset == 0,
+            &mut self.fragment_buffer,
+        ) {
+            Ok((0, _)) => {
+                self.abort();
+                return spdm_result_err!(EIO);
+            }
+            Ok(fragment) => fragment,
+            Err(error) => {
+                self.abort();
+                return Err(error);
+            }
+        };
+        self.transmit_offset += consumed;
+        Ok(Some(&self.fragment_buffer[..used]))
+    }
+
+    /// How long to wait for the response to the request in flight, in
+    /// microseconds, before handle_timeout.
+    pub fn receive_timeout(&self) -> usize {
+        let timeout = self.requester.response_timeout(self.crypto_request);
+        self.requester
+            .common
+            .config_info
+            .retry_policy
+            .get_timeout(timeout, self.retry)
+    }
+
+    /// No response within receive_timeout: send the request again as the
+    /// retry policy allows, else give up with SpdmError::Timeout.
+    pub fn handle_timeout(&mut self) -> SpdmResult<SpdmPollStatus> {
+        if self.is_idle() {
+            return spdm_result_err!(EINVAL);
+        }
+        let retry_policy = self.requester.common.config_info.retry_policy;
+        if self.retry >= retry_policy.retry_count || self.transmit_size == 0 {
+            self.abort();
+            return spdm_result_err!(Timeout);
+        }
+        self.retry += 1;
+        warn!(
+            "no response, retransmission {} of {}\n",
+            self.retry, retry_policy.retry_count
+        );
+        self.transmit_offset = 0;
+        self.receive_size = 0;
+        Ok(SpdmPollStatus::Transmit {
+            delay: retry_policy.get_backoff(self.retry),
+        })
+    }
+
+    /// Take one transport unit of the response to the request in flight.
+    pub fn handle_received(&mut self, unit: &[u8]) -> SpdmResult<SpdmPollStatus> {
+        if self.is_idle() {
+            return spdm_result_err!(EINVAL);
+        }
+        let result = self.receive_unit(unit);
+        self.abort_on_error(result)
+    }
+
+    fn abort_on_error(&mut self, result: SpdmResult<SpdmPollStatus>) -> SpdmResult<SpdmPollStatus> {
+        if result.is_err() {
+            self.abort();
+        }
+        result
+    }
+
+    fn start_request(&mut self, request: SpdmPollRequest) -> SpdmResult<SpdmPollStatus> {
+        let requester = &mut self.requester;
+        let buf = &mut self.request;
+        let (session_id, step, crypto_request, used) = match request {
+            SpdmPollRequest::InitConnection => (
+                None,
+                SpdmPollStep::Version,
+                false,
+                requester.encode_spdm_version(buf),
+            ),
+            SpdmPollRequest::GetDigests { session_id } => (
+                session_id,
+                SpdmPollStep::Digests,
+                false,
+                requester.encode_spdm_digest(buf),
+            ),
+            SpdmPollRequest::GetCertificate {
+                session_id,
+                slot_id,
+            } => {
+                if !requester.common.is_peer_slot_provisioned(slot_id) {
+                    return spdm_result_err!(EINVAL);
+                }
+                let length = requester
+                    .common
+                    .get_max_cert_portion_len(session_id.is_some());
+                (
+                    session_id,
+                    SpdmPollStep::Certificate {
+                        slot_id,
+                        offset: 0,
+                        length,
+                        total_length: None,
+                    },
+                    false,
+                    requester.encode_spdm_certificate_partial(slot_id, 0, length, buf),
+                )
+            }
+            SpdmPollRequest::Challenge {
+                slot_id,
+                measurement_summary_hash_type,
+            } => (
+                None,
+                SpdmPollStep::Challenge {
+                    slot_id,
+                    measurement_summary_hash_type,
+                },
+                true,
+                requester.encode_spdm_challenge(slot_id, measurement_summary_hash_type, buf)?,
+            ),
+            SpdmPollRequest::GetMeasurements {
+                session_id,
+                slot_id,
+                measurement_attributes,
+                measurement_operation,
+            } => (
+                session_id,
+                SpdmPollStep::Measurements {
+                    slot_id,
+                    measurement_attributes,
+                    measurement_operation,
+                },
+                true,
+                requester.encode_spdm_measurement_record(
+                    measurement_attributes,
+                    measurement_operation,
+                    slot_id,
+                    buf,
+                )?,
+            ),
+            SpdmPollRequest::StartSession {
+                use_psk: false,
+                slot_id,
+                measurement_summary_hash_type,
+            } => {
+                let (key_exchange_context, used) = requester.encode_spdm_key_exchange(
+                    buf,
+                    slot_id,
+                    measurement_summary_hash_type,
+                )?;
+                (
+                    None,
+                    SpdmPollStep::KeyExchange {
+                        slot_id,
+                        measurement_summary_hash_type,
+                        key_exchange_context,
+                    },
+                    false,
+                    used,
+                )
+            }
+            SpdmPollRequest::StartSession {
+                use_psk: true,
+                measurement_summary_hash_type,
+                ..
+            } => (
+                None,
+                SpdmPollStep::PskExchange {
+                    measurement_summary_hash_type,
+                },
+                false,
+                requester.encode_spdm_psk_exchange(
+                    measurement_summary_hash_type,
+                    &[],
+                    None,
+                    buf,
+                )?,
+            ),
+            SpdmPollRequest::EndSession { session_id } => (
+                Some(session_id),
+                SpdmPollStep::EndSession,
+                false,
+                requester.encode_spdm_end_session(buf),
+            ),
+        };
+        self.send_request(session_id, step, crypto_request, used)
+    }
+
+    // Encap the request encoded in self.request for poll_transmit.
+    fn send_request(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        step: SpdmPollStep,
+        crypto_request: bool,
+        request_size: usize,
+    ) -> SpdmResult<SpdmPollStatus> {
+        self.request_size = request_size;
+        self.not_ready_wait = SpdmNotReadyWait::default();
+        send_message(
+            &mut self.requester,
+            session_id,
+            &self.request[..request_size],
+        )?;
+        self.take_transmit()?;
+        self.step = step;
+        self.session_id = session_id;
+        self.crypto_request = crypto_request;
+        Ok(SpdmPollStatus::Transmit { delay: 0 })
+    }
+
+    // The transport message the sync requester parked, for poll_transmit.
+    fn take_transmit(&mut self) -> SpdmResult {
+        let deferred_io = self
+            .requester
+            .common
+            .deferred_io
+            .as_mut()
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        let transport_message = deferred_io.take_send().ok_or_else(|| spdm_err!(EIO))?;
+        self.transmit_buffer[..transport_message.len()].copy_from_slice(transport_message);
+        self.transmit_size = transport_message.len();
+        self.transmit_offset = 0;
+        self.receive_size = 0;
+        self.retry = 0;
+        Ok(())
+    }
+
+    fn receive_unit(&mut self, unit: &[u8]) -> SpdmResult<SpdmPollStatus> {
+        let transport_encap = &mut *self.requester.common.transport_encap;
+        if transport_encap.get_max_transport_unit().is_none() {
+            if unit.len() > self.receive_buffer.len() {
+                return spdm_result_err!(ENOMEM);
+            }
+            self.receive_buffer[..unit.len()].copy_from_slice(unit);
+            self.receive_size = unit.len();
+        } else {
+            let (payload_size, last) = transport_encap.decap_fragment(
+                unit,
+                self.receive_size == 0,
+                &mut self.receive_buffer[self.receive_size..],
+            )?;
+            self.receive_size += payload_size;
+            if !last {
+                return Ok(SpdmPollStatus::Receive);
+            }
+        }
+
+        let receive_size = core::mem::replace(&mut self.receive_size, 0);
+        self.requester
+            .common
+            .deferred_io
+            .as_mut()
+            .ok_or_else(|| spdm_err!(EINVAL))?
+            .put_received(&self.receive_buffer[..receive_size])?;
+        let mut response = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = match self.session_id {
+            Some(session_id) => self.requester.receive_secured_message(
+                session_id.as_u32(),
+                &mut response,
+                self.crypto_request,
+            )?,
+            None => self
+                .requester
+                .receive_message(&mut response, self.crypto_request)?,
+        };
+
+        // ERROR(ResponseNotReady) is resolved with RESPOND_IF_READY here, the
+        // sync handle_*_response would do that round trip itself.
+        if let Some(extend_error_data) = get_response_not_ready(&response[..used]) {
+            if self.request_size < 2 || extend_error_data.request_code != self.request[1] {
+                return spdm_result_err!(EDEV);
+            }
+            let delay = self.not_ready_wait.next(&extend_error_data)?;
+            let mut respond_if_ready = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let respond_if_ready_used = self
+                .requester
+                .encode_spdm_respond_if_ready(&extend_error_data, &mut respond_if_ready);
+            send_message(
+                &mut self.requester,
+                self.session_id,
+                &respond_if_ready[..respond_if_ready_used],
+            )?;
+            self.take_transmit()?;
+            return Ok(SpdmPollStatus::Transmit { delay });
+        }
+
+        let step = core::mem::replace(&mut self.step, SpdmPollStep::Idle);
+        self.handle_response(step, &response[..used])
+    }
+
+    fn handle_response(
+        &mut self,
+        step: SpdmPollStep,
+        response: &[u8],
+    ) -> SpdmResult<SpdmPollStatus> {
+        let requester = &mut self.requester;
+        let request = &self.request[..self.request_size];
+        let session_id = self.session_id;
+        let mut buf = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        match step {
+            SpdmPollStep::Idle => spdm_result_err!(EINVAL),
+            SpdmPollStep::Version => {
+                requester.handle_spdm_version_response(
+                    SpdmSessionId::default(),
+                    request,
+                    response,
+                )?;
+                let used = requester.encode_spdm_capability(&mut buf);
+                self.send_next(None, SpdmPollStep::Capabilities, false, &buf[..used])
+            }
+            SpdmPollStep::Capabilities => {
+                requester.handle_spdm_capability_response(
+                    SpdmSessionId::default(),
+                    request,
+                    response,
+                )?;
+                let used = requester.encode_spdm_algorithm(&mut buf);
+                self.send_next(None, SpdmPollStep::Algorithms, false, &buf[..used])
+            }
+            SpdmPollStep::Algorithms => {
+                requester.handle_spdm_algorithm_response(
+                    SpdmSessionId::default(),
+                    request,
+                    response,
+                )?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::Connected))
+            }
+            SpdmPollStep::Digests => {
+                let digests = requester.handle_spdm_digest_response(
+                    SpdmSessionId::default(),
+                    request,
+                    response,
+                )?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::Digests(digests)))
+            }
+            SpdmPollStep::Certificate {
+                slot_id,
+                offset,
+                length,
+                mut total_length,
+            } => {
+                let (portion_length, remainder_length) = requester
+                    .handle_spdm_certificate_partial_response(
+                        session_id, slot_id, offset, length, request, response,
+                    )
+                    .map_err(|_| spdm_err!(EIO))?;
+                let (offset, length) = requester.next_certificate_portion(
+                    session_id,
+                    offset,
+                    portion_length,
+                    remainder_length,
+                    &mut total_length,
+                )?;
+                if length == 0 {
+                    requester.verify_spdm_certificate_chain(slot_id)?;
+                    requester.common.cache_peer_cert_chain_hash(slot_id)?;
+                    let cert_chain = requester.get_peer_cert_chain_data(slot_id)?;
+                    return Ok(SpdmPollStatus::Done(SpdmPollOutcome::CertChain(cert_chain)));
+                }
+                let used =
+                    requester.encode_spdm_certificate_partial(slot_id, offset, length, &mut buf);
+                let step = SpdmPollStep::Certificate {
+                    slot_id,
+                    offset,
+                    length,
+                    total_length,
+                };
+                self.send_next(session_id, step, false, &buf[..used])
+            }
+            SpdmPollStep::Challenge {
+                slot_id,
+                measurement_summary_hash_type,
+            } => {
+                requester.handle_spdm_challenge_response(
+                    SpdmSessionId::default(),
+                    slot_id,
+                    measurement_summary_hash_type,
+                    None,
+                    request,
+                    response,
+                )?;
+                // the encapsulated flow interleaves its own round trips
+                if requester.common.runtime_info.basic_mut_auth_requested {
+                    return spdm_result_err!(ENOSYS);
+                }
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::Challenged))
+            }
+            SpdmPollStep::Measurements {
+                slot_id,
+                measurement_attributes,
+                measurement_operation,
+            } => {
+                let mut measurements = SpdmMeasurements::default();
+                measurements.number_of_measurement = requester
+                    .handle_spdm_measurement_record_response(
+                        session_id,
+                        slot_id,
+                        measurement_attributes,
+                        measurement_operation,
+                        &mut measurements.measurement_record,
+                        request,
+                        response,
+                    )?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::Measurements(
+                    measurements,
+                )))
+            }
+            SpdmPollStep::KeyExchange {
+                slot_id,
+                measurement_summary_hash_type,
+                key_exchange_context,
+            } => {
+                let new_session_id = requester.handle_spdm_key_exhcange_response(
+                    SpdmSessionId::default(),
+                    slot_id,
+                    request,
+                    response,
+                    measurement_summary_hash_type,
+                    key_exchange_context,
+                )?;
+                // the encapsulated flow interleaves its own round trips, drop
+                // the session rather than leave it half-open
+                if requester.is_encap_mut_auth_requested(new_session_id) {
+                    if let Some(session) =
+                        requester.common.get_session_via_id(new_session_id.as_u32())
+                    {
+                        let _ = session.teardown(new_session_id.as_u32());
+                    }
+                    return spdm_result_err!(ENOSYS);
+                }
+                let in_clear_text = requester
+                    .common
+                    .get_session(new_session_id)?
+                    .get_handshake_in_the_clear();
+                let (used, base_hash_size, message_f) =
+                    requester.encode_spdm_finish(new_session_id, slot_id, &mut buf)?;
+                let step = SpdmPollStep::Finish {
+                    session_id: new_session_id,
+                    slot_id,
+                    base_hash_size,
+                    message_f,
+                };
+                // FINISH goes in the clear with HANDSHAKE_IN_THE_CLEAR_CAP
+                let transport_session_id = if in_clear_text {
+                    None
+                } else {
+                    Some(new_session_id)
+                };
+                self.send_next(transport_session_id, step, true, &buf[..used])
+            }
+            SpdmPollStep::Finish {
+                session_id: finish_session_id,
+                slot_id,
+                base_hash_size,
+                message_f,
+            } => {
+                requester.handle_spdm_finish_response(
+                    finish_session_id,
+                    slot_id,
+                    base_hash_size,
+                    message_f,
+                    response,
+                )?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::SessionStarted(
+                    finish_session_id,
+                )))
+            }
+            SpdmPollStep::PskExchange {
+                measurement_summary_hash_type,
+            } => {
+                let new_session_id = requester.handle_spdm_psk_exchange_response(
+                    SpdmSessionId::default(),
+                    measurement_summary_hash_type,
+                    &[],
+                    request,
+                    response,
+                )?;
+                let (used, message_f) =
+                    requester.encode_spdm_psk_finish(new_session_id, &mut buf)?;
+                self.send_next(
+                    Some(new_session_id),
+                    SpdmPollStep::PskFinish { message_f },
+                    false,
+                    &buf[..used],
+                )
+            }
+            SpdmPollStep::PskFinish { message_f } => {
+                let psk_session_id = session_id.ok_or_else(|| spdm_err!(EINVAL))?;
+                requester.handle_spdm_psk_finish_response(psk_session_id, message_f, response)?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::SessionStarted(
+                    psk_session_id,
+                )))
+            }
+            SpdmPollStep::EndSession => {
+                let end_session_id = session_id.ok_or_else(|| spdm_err!(EINVAL))?;
+                requester.handle_spdm_end_session_response(end_session_id, response)?;
+                Ok(SpdmPollStatus::Done(SpdmPollOutcome::SessionEnded))
+            }
+        }
+    }
+
+    fn send_next(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        step: SpdmPollStep,
+        crypto_request: bool,
+        request: &[u8],
+    ) -> SpdmResult<SpdmPollStatus> {
+        self.request[..request.len()].copy_from_slice(request);
+        self.send_request(session_id, step, crypto_request, request.len())
+    }
+}
+
+fn send_message(
+    requester: &mut RequesterContext,
+    session_id: Option<SpdmSessionId>,
+    message: &[u8],
+) -> SpdmResult {
+    match session_id {
+        Some(session_id) => requester.send_secured_message(session_id.as_u32(), message, false),
+        None => requester.send_message(message),
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::common::{SpdmCodec, SpdmDeviceIo, ST1};
+    use crate::error::SpdmError;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::Writer;
+
+    fn run(
+        requester: &mut PollRequesterContext,
+        device_io: &mut dyn SpdmDeviceIo,
+        request: SpdmPollRequest,
+    ) -> SpdmResult<SpdmPollOutcome> {
+        let mut status = requester.start(request)?;
+        loop {
+            status = match status {
+                SpdmPollStatus::Transmit { .. } => {
+                    while let Some(unit) = requester.poll_transmit()? {
+                        device_io.send(unit)?;
+                    }
+                    SpdmPollStatus::Receive
+                }
+                SpdmPollStatus::Receive => {
+                    let mut unit = [0u8; config::DATA_TRANSFER_SIZE];
+                    match device_io.receive(&mut unit, requester.receive_timeout()) {
+                        Ok(used) => requester.handle_received(&unit[..used])?,
+                        Err(_) => requester.handle_timeout()?,
+                    }
+                }
+                SpdmPollStatus::Done(outcome) => return Ok(outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn test_case0_poll_start_session() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester =
+            PollRequesterContext::new(pcidoe_transport_encap2, req_config_info, req_provision_info);
+        assert!(requester.is_idle());
+        assert!(requester.handle_received(&[0u8; 8]).is_err());
+
+        assert!(requester.start(SpdmPollRequest::InitConnection).is_ok());
+        assert!(requester.start(SpdmPollRequest::InitConnection).is_err());
+        requester.abort();
+        assert!(requester.is_idle());
+
+        assert!(matches!(
+            run(
+                &mut requester,
+                &mut device_io_requester,
+                SpdmPollRequest::InitConnection
+            ),
+            Ok(SpdmPollOutcome::Connected)
+        ));
+        assert!(matches!(
+            run(
+                &mut requester,
+                &mut device_io_requester,
+                SpdmPollRequest::GetDigests { session_id: None }
+            ),
+            Ok(SpdmPollOutcome::Digests(_))
+        ));
+        let cert_chain = match run(
+            &mut requester,
+            &mut device_io_requester,
+            SpdmPollRequest::GetCertificate {
+                session_id: None,
+                slot_id: 0,
+            },
+        ) {
+            Ok(SpdmPollOutcome::CertChain(cert_chain)) => cert_chain,
+            _ => panic!("no certificate chain"),
+        };
+        assert!(cert_chain.data_size != 0);
+        assert!(requester.requester.common.peer_info.peer_cert_chain_hash[0].is_some());
+
+        for use_psk in [false, true] {
+            let session_id = match run(
+                &mut requester,
+                &mut device_io_requester,
+                SpdmPollRequest::StartSession {
+                    use_psk,
+                    slot_id: 0,
+                    measurement_summary_hash_type:
+                        SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
+                },
+            ) {
+                Ok(SpdmPollOutcome::SessionStarted(session_id)) => session_id,
+                _ => panic!("no session"),
+            };
+            assert!(matches!(
+                run(
+                    &mut requester,
+                    &mut device_io_requester,
+                    SpdmPollRequest::EndSession { session_id }
+                ),
+                Ok(SpdmPollOutcome::SessionEnded)
+            ));
+        }
+        assert!(requester.is_idle());
+    }
+
+    #[test]
+    fn test_case2_poll_start_session_encap_mut_auth() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::MUT_AUTH_CAP;
+        rsp_config_info.mut_auth_requested =
+            SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_ENCAP_REQUEST;
+        req_config_info.req_capabilities = SpdmRequestCapabilityFlags::CERT_CAP
+            | SpdmRequestCapabilityFlags::CHAL_CAP
+            | SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::MUT_AUTH_CAP
+            | SpdmRequestCapabilityFlags::ENCAP_CAP;
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester =
+            PollRequesterContext::new(pcidoe_transport_encap2, req_config_info, req_provision_info);
+
+        for request in [
+            SpdmPollRequest::InitConnection,
+            SpdmPollRequest::GetDigests { session_id: None },
+            SpdmPollRequest::GetCertificate {
+                session_id: None,
+                slot_id: 0,
+            },
+        ] {
+            assert!(run(&mut requester, &mut device_io_requester, request).is_ok());
+        }
+
+        // KEY_EXCHANGE_RSP asks for the encapsulated flow, not driven here
+        let error = run(
+            &mut requester,
+            &mut device_io_requester,
+            SpdmPollRequest::StartSession {
+                use_psk: false,
+                slot_id: 0,
+                measurement_summary_hash_type:
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
+            },
+        )
+        .err()
+        .map(|error| error.num());
+        assert_eq!(error, Some(crate::error::SpdmErrorNum::ENOSYS));
+        assert!(requester
+            .requester
+            .common
+            .session
+            .iter()
+            .all(|session| session.get_session_id() == 0));
+    }
+
+    #[test]
+    fn test_case1_poll_response_not_ready() {
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.round_trip_time = 1000;
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut requester =
+            PollRequesterContext::new(pcidoe_transport_encap, req_config_info, req_provision_info);
+
+        assert!(requester.start(SpdmPollRequest::InitConnection).is_ok());
+        assert_eq!(requester.receive_timeout(), ST1 + 1000);
+        while requester.poll_transmit().unwrap().is_some() {}
+
+        // GET_VERSION answered ERROR(ResponseNotReady), RDT 32 us and RDTM 2
+        let not_ready = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: SpdmVersion::SpdmVersion10,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseError,
+            },
+            payload: SpdmMessagePayload::SpdmErrorResponse(SpdmErrorResponsePayload {
+                error_code: SpdmErrorCode::SpdmErrorResponseNotReady,
+                error_data: 0,
+                extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataNotReady(
+                    SpdmErrorResponseNotReadyExtData {
+                        rdt_exponent: 4,
+                        request_code: SpdmRequestResponseCode::SpdmRequestGetVersion.get_u8(),
+                        token: 1,
+                        rdtm: 2,
+                    },
+                ),
+            }),
+        };
+        let mut spdm_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut spdm_buffer);
+        not_ready.spdm_encode(&mut requester.requester.common, &mut writer);
+        let used = writer.used();
+        let mut unit = [0u8; config::DATA_TRANSFER_SIZE];
+        let unit_size = PciDoeTransportEncap {}
+            .encap(&spdm_buffer[..used], &mut unit, false)
+            .unwrap();
+
+        // RESPOND_IF_READY after RDT, as long as WT_Max is not reached
+        for _ in 0..2 {
+            assert!(matches!(
+                requester.handle_received(&unit[..unit_size]),
+                Ok(SpdmPollStatus::Transmit { delay: 32 })
+            ));
+            while requester.poll_transmit().unwrap().is_some() {}
+        }
+        assert_eq!(
+            requester.handle_received(&unit[..unit_size]).err(),
+            Some(SpdmError::Timeout)
+        );
+        assert!(requester.is_idle());
+    }
+}
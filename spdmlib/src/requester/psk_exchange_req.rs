@@ -2,32 +2,50 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use config::MAX_SPDM_PSK_CONTEXT_SIZE;
+use config::{MAX_SPDM_PSK_CONTEXT_SIZE, MAX_SPDM_PSK_HINT_SIZE};
 
+use crate::common::session::SpdmSessionId;
+use crate::common::SpdmPskStore;
 use crate::crypto;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::SpdmMeasurementSummaryHashType;
 use crate::protocol::*;
 use crate::requester::*;
-extern crate alloc;
-use alloc::boxed::Box;
 
 #[cfg(not(feature = "hash-update"))]
 use crate::common::ManagedBuffer;
 
-const INITIAL_SESSION_ID: u16 = 0xFFFD;
-
 impl<'a> RequesterContext<'a> {
+    /// Register the store used to look up the PSK by the PSKHint sent in PSK_EXCHANGE.
+    pub fn register_psk_store(&mut self, psk_store: &'a dyn SpdmPskStore) {
+        self.common.psk_store = Some(psk_store);
+    }
+
     pub fn send_receive_spdm_psk_exchange(
         &mut self,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
-    ) -> SpdmResult<u32> {
+    ) -> SpdmResult<SpdmSessionId> {
+        self.send_receive_spdm_psk_exchange_with_hint(measurement_summary_hash_type, &[], None)
+    }
+
+    /// psk_context is the requester context, of at least hash size, a random one of hash
+    /// size is used when None.
+    pub fn send_receive_spdm_psk_exchange_with_hint(
+        &mut self,
+        measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        psk_hint: &[u8],
+        psk_context: Option<&[u8]>,
+    ) -> SpdmResult<SpdmSessionId> {
         info!("send spdm psk exchange\n");
 
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let send_used =
-            self.encode_spdm_psk_exchange(measurement_summary_hash_type, &mut send_buffer)?;
+        let send_used = self.encode_spdm_psk_exchange(
+            measurement_summary_hash_type,
+            psk_hint,
+            psk_context,
+            &mut send_buffer,
+        )?;
 
         self.send_message(&send_buffer[..send_used])?;
 
@@ -35,8 +53,9 @@ impl<'a> RequesterContext<'a> {
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let receive_used = self.receive_message(&mut receive_buffer, false)?;
         self.handle_spdm_psk_exchange_response(
-            0,
+            SpdmSessionId::default(),
             measurement_summary_hash_type,
+            psk_hint,
             &send_buffer[..send_used],
             &receive_buffer[..receive_used],
         )
@@ -45,41 +64,52 @@ impl<'a> RequesterContext<'a> {
     pub fn encode_spdm_psk_exchange(
         &mut self,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        psk_hint: &[u8],
+        psk_context: Option<&[u8]>,
         buf: &mut [u8],
     ) -> SpdmResult<usize> {
         let mut writer = Writer::init(buf);
 
-        let req_session_id = INITIAL_SESSION_ID;
+        if self.common.get_next_avaiable_session().is_none() {
+            error!("!!! too many sessions : fail !!!\n");
+            return spdm_result_err!(ENOMEM);
+        }
 
-        let mut psk_context = [0u8; MAX_SPDM_PSK_CONTEXT_SIZE];
-        crypto::rand::get_random(&mut psk_context)?;
+        let req_session_id = self.common.generate_session_id_half(true)?;
 
-        let mut opaque;
-        if self
-            .common
-            .negotiate_info
-            .opaque_data_support
-            .contains(SpdmOpaqueSupport::OPAQUE_DATA_FMT1)
-        {
-            opaque = SpdmOpaqueStruct {
-                data_size: crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT1
-                    .len() as u16,
-                ..Default::default()
-            };
-            opaque.data[..(opaque.data_size as usize)].copy_from_slice(
-                crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT1.as_ref(),
-            );
-        } else {
-            opaque = SpdmOpaqueStruct {
-                data_size: crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT0
-                    .len() as u16,
-                ..Default::default()
-            };
-            opaque.data[..(opaque.data_size as usize)].copy_from_slice(
-                crate::common::opaque::REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT0.as_ref(),
-            );
+        if psk_hint.len() > MAX_SPDM_PSK_HINT_SIZE {
+            return spdm_result_err!(EINVAL);
+        }
+        if self.common.get_psk(psk_hint).is_none() {
+            error!("!!! psk_exchange : unknown psk hint !!!\n");
+            return spdm_result_err!(EINVAL);
+        }
+        let mut req_psk_hint = SpdmPskHintStruct {
+            data_size: psk_hint.len() as u16,
+            ..Default::default()
+        };
+        req_psk_hint.data[..psk_hint.len()].copy_from_slice(psk_hint);
+
+        let mut req_psk_context = SpdmPskContextStruct::default();
+        match psk_context {
+            Some(psk_context) => {
+                if psk_context.len() < self.common.negotiate_info.base_hash_sel.get_size() as usize
+                    || psk_context.len() > MAX_SPDM_PSK_CONTEXT_SIZE
+                {
+                    return spdm_result_err!(EINVAL);
+                }
+                req_psk_context.data_size = psk_context.len() as u16;
+                req_psk_context.data[..psk_context.len()].copy_from_slice(psk_context);
+            }
+            None => {
+                req_psk_context.data_size = self.common.negotiate_info.base_hash_sel.get_size();
+                self.common.get_random(&mut req_psk_context.data)?;
+            }
         }
 
+        let opaque =
+            SpdmOpaqueStruct::req_dmtf_supported_secure_spdm_version_list(&mut self.common)?;
+
         let request = SpdmMessage {
             header: SpdmMessageHeader {
                 version: self.common.negotiate_info.spdm_version_sel,
@@ -88,11 +118,8 @@ impl<'a> RequesterContext<'a> {
             payload: SpdmMessagePayload::SpdmPskExchangeRequest(SpdmPskExchangeRequestPayload {
                 measurement_summary_hash_type,
                 req_session_id,
-                psk_hint: SpdmPskHintStruct::default(),
-                psk_context: SpdmPskContextStruct {
-                    data_size: self.common.negotiate_info.base_hash_sel.get_size(),
-                    data: psk_context,
-                },
+                psk_hint: req_psk_hint,
+                psk_context: req_psk_context,
                 opaque,
             }),
         };
@@ -102,11 +129,12 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_psk_exchange_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
+        psk_hint: &[u8],
         send_buffer: &[u8],
         receive_buffer: &[u8],
-    ) -> SpdmResult<u32> {
+    ) -> SpdmResult<SpdmSessionId> {
         if (measurement_summary_hash_type
             == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb)
             || (measurement_summary_hash_type
@@ -127,6 +155,18 @@ impl<'a> RequesterContext<'a> {
                     if let Some(psk_exchange_rsp) = psk_exchange_rsp {
                         debug!("!!! psk_exchange rsp : {:02x?}\n", psk_exchange_rsp);
 
+                        // ResponderContext is there if and only if the responder supports it
+                        if self
+                            .common
+                            .negotiate_info
+                            .rsp_capabilities_sel
+                            .contains(SpdmResponseCapabilityFlags::PSK_CAP_WITH_CONTEXT)
+                            != (psk_exchange_rsp.psk_context.data_size != 0)
+                        {
+                            error!("!!! psk_exchange : unexpected responder context !!!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
+
                         let base_hash_size =
                             self.common.negotiate_info.base_hash_sel.get_size() as usize;
                         let temp_receive_used = receive_used - base_hash_size;
@@ -176,46 +216,68 @@ impl<'a> RequesterContext<'a> {
                         let dhe_algo = self.common.negotiate_info.dhe_sel;
                         let aead_algo = self.common.negotiate_info.aead_sel;
                         let key_schedule_algo = self.common.negotiate_info.key_schedule_sel;
-                        let sequence_number_count =
-                            self.common.transport_encap.get_sequence_number_count();
-                        let max_random_count = self.common.transport_encap.get_max_random_count();
+                        let transport_param = self.common.get_session_transport_param();
+                        let key_update_policy = self.common.config_info.key_update_policy;
 
                         let secure_spdm_version_sel = if let Some(secured_message_version) =
                             psk_exchange_rsp
                                 .opaque
                                 .req_get_dmtf_secure_spdm_version_selection(&mut self.common)
                         {
-                            secured_message_version.get_secure_spdm_version()
+                            let secure_spdm_version =
+                                secured_message_version.get_secure_spdm_version();
+                            if !is_supported_secure_spdm_version(secure_spdm_version) {
+                                error!(
+                                    "!!! psk_exchange : unsupported secured message version !!!\n"
+                                );
+                                return spdm_result_err!(EFAULT);
+                            }
+                            secure_spdm_version
+                        } else {
+                            0
+                        };
+
+                        let heartbeat_period = if self.common.is_heartbeat_negotiated() {
+                            psk_exchange_rsp.heartbeat_period
                         } else {
                             0
                         };
 
-                        let session_id = ((INITIAL_SESSION_ID as u32) << 16)
-                            + psk_exchange_rsp.rsp_session_id as u32;
+                        let session_id = SpdmSessionId::new(
+                            get_req_session_id(send_buffer)?,
+                            psk_exchange_rsp.rsp_session_id,
+                        )
+                        .as_u32();
                         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
+                        let psk_info = self.common.get_psk(psk_hint).ok_or(spdm_err!(EINVAL))?;
                         let session = self
                             .common
                             .get_next_avaiable_session()
-                            .ok_or(spdm_err!(EINVAL))?;
+                            .ok_or(spdm_err!(ENOMEM))?;
 
                         session.setup(session_id)?;
 
                         session.set_use_psk(true);
-                        let mut psk_key = SpdmDheFinalKeyStruct {
-                            data_size: b"TestPskData\0".len() as u16,
-                            data: Box::new([0; SPDM_MAX_DHE_KEY_SIZE]),
-                        };
-                        psk_key.data[0..(psk_key.data_size as usize)]
-                            .copy_from_slice(b"TestPskData\0");
+                        session.slot_id = INVALID_SLOT;
                         session.set_crypto_param(
                             base_hash_algo,
                             dhe_algo,
                             aead_algo,
                             key_schedule_algo,
                         );
-                        session.set_transport_param(sequence_number_count, max_random_count);
-                        session.set_dhe_secret(spdm_version_sel, psk_key)?; // transfer the ownership out
-                        session.generate_handshake_secret(spdm_version_sel, &th1)?;
+                        session.set_transport_param(transport_param);
+                        session.set_key_update_policy(key_update_policy);
+                        // transfer the ownership out
+                        if session
+                            .set_dhe_secret(spdm_version_sel, psk_info.psk)
+                            .is_err()
+                            || session
+                                .generate_handshake_secret(spdm_version_sel, &th1)
+                                .is_err()
+                        {
+                            let _ = session.teardown(session_id);
+                            return spdm_result_err!(ESEC);
+                        }
 
                         // verify HMAC with finished_key
                         #[cfg(not(feature = "hash-update"))]
@@ -225,10 +287,7 @@ impl<'a> RequesterContext<'a> {
                             &message_k,
                             None,
                         )?;
-                        let session = self
-                            .common
-                            .get_session_via_id(session_id)
-                            .ok_or(spdm_err!(EINVAL))?;
+                        let session = self.common.get_session_mut(session_id)?;
                         if session
                             .verify_hmac_with_response_finished_key(
                                 #[cfg(not(feature = "hash-update"))]
@@ -243,7 +302,9 @@ impl<'a> RequesterContext<'a> {
                         {
                             error!("verify_hmac_with_response_finished_key fail");
                             let _ = session.teardown(session_id);
-                            return spdm_result_err!(EFAULT);
+                            self.common
+                                .notify_session_event(session_id, SpdmSessionEvent::HmacFailure);
+                            return spdm_result_err!(VerificationFailed);
                         } else {
                             info!("verify_hmac_with_response_finished_key pass");
                         }
@@ -268,12 +329,15 @@ impl<'a> RequesterContext<'a> {
                         );
 
                         session.secure_spdm_version_sel = secure_spdm_version_sel;
-                        session.heartbeat_period = psk_exchange_rsp.heartbeat_period;
+                        session.heartbeat_period = heartbeat_period;
+
+                        self.common
+                            .notify_session_event(session_id, SpdmSessionEvent::HandshakeStarted);
 
-                        Ok(session_id)
+                        Ok(SpdmSessionId::from(session_id))
                     } else {
                         error!("!!! psk_exchange : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -290,16 +354,17 @@ impl<'a> RequesterContext<'a> {
                             self.handle_spdm_psk_exchange_response(
                                 session_id,
                                 measurement_summary_hash_type,
+                                psk_hint,
                                 send_buffer,
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -328,7 +393,7 @@ mod tests_requester {
             rsp_provision_info,
         );
 
-        responder.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        responder.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -356,4 +421,160 @@ mod tests_requester {
             .is_ok();
         assert!(status);
     }
+
+    struct TestPskStore;
+
+    impl SpdmPskStore for TestPskStore {
+        fn lookup_psk(&self, psk_hint: &[u8]) -> Option<crate::common::SpdmPskInfo> {
+            if psk_hint != b"TestPskHint" {
+                return None;
+            }
+            let mut psk_info = crate::common::SpdmPskInfo::default();
+            psk_info.psk.data_size = 32;
+            psk_info.psk.data[..32].copy_from_slice(&[0x5au8; 32]);
+            Some(psk_info)
+        }
+    }
+
+    #[test]
+    fn test_case1_send_receive_spdm_psk_exchange() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+        let psk_store = TestPskStore;
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+        responder.register_psk_store(&psk_store);
+
+        responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        responder.common.negotiate_info.aead_sel = SpdmAeadAlgo::AES_128_GCM;
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_psk_store(&psk_store);
+
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        requester.common.negotiate_info.aead_sel = SpdmAeadAlgo::AES_128_GCM;
+        let measurement_summary_hash_type =
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone;
+
+        assert!(requester
+            .send_receive_spdm_psk_exchange_with_hint(
+                measurement_summary_hash_type,
+                b"UnknownHint",
+                None,
+            )
+            .is_err());
+
+        let status = requester
+            .send_receive_spdm_psk_exchange_with_hint(
+                measurement_summary_hash_type,
+                b"TestPskHint",
+                Some(&[0x11u8; 48]),
+            )
+            .is_ok();
+        assert!(status);
+    }
+
+    #[test]
+    fn test_case2_send_receive_spdm_psk_exchange_psk_table() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+        let psk_entries = [
+            SpdmPskEntry {
+                psk_hint: b"",
+                psk: b"TestPskData\0",
+                psk_context: b"",
+            },
+            SpdmPskEntry {
+                psk_hint: b"TestPskHint\0",
+                psk: &[0x5au8; 48],
+                psk_context: &[0x22u8; 48],
+            },
+        ];
+        let psk_table = SpdmPskTable::new(&psk_entries);
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+        responder.register_psk_store(&psk_table);
+
+        responder.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        responder.common.negotiate_info.aead_sel = SpdmAeadAlgo::AES_128_GCM;
+        responder.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::PSK_CAP_WITH_CONTEXT;
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_psk_store(&psk_table);
+
+        requester.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        requester.common.negotiate_info.aead_sel = SpdmAeadAlgo::AES_128_GCM;
+        requester.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::PSK_CAP_WITH_CONTEXT;
+        let measurement_summary_hash_type =
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone;
+
+        // the requester context is at least of hash size
+        assert!(requester
+            .send_receive_spdm_psk_exchange_with_hint(
+                measurement_summary_hash_type,
+                b"TestPskHint\0",
+                Some(&[0x11u8; 32]),
+            )
+            .is_err());
+
+        let session_id = requester
+            .send_receive_spdm_psk_exchange_with_hint(
+                measurement_summary_hash_type,
+                b"TestPskHint\0",
+                Some(&[0x11u8; 48]),
+            )
+            .unwrap();
+        assert!(requester
+            .common
+            .get_immutable_session_via_id(session_id.as_u32())
+            .is_some());
+
+        let session_id = requester
+            .send_receive_spdm_psk_exchange(measurement_summary_hash_type)
+            .unwrap();
+        assert!(requester
+            .common
+            .get_immutable_session_via_id(session_id.as_u32())
+            .is_some());
+    }
 }
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 #[cfg(feature = "hash-update")]
 use crate::crypto;
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
@@ -13,20 +14,21 @@ use crate::common::ManagedBuffer;
 use alloc::boxed::Box;
 
 impl<'a> RequesterContext<'a> {
-    pub fn send_receive_spdm_psk_finish(&mut self, session_id: u32) -> SpdmResult {
+    pub fn send_receive_spdm_psk_finish(&mut self, session_id: SpdmSessionId) -> SpdmResult {
         info!("send spdm psk_finish\n");
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let (send_used, message_f) = self.encode_spdm_psk_finish(session_id, &mut send_buffer)?;
-        self.send_secured_message(session_id, &send_buffer[..send_used], false)?;
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..send_used], false)?;
 
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let receive_used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let receive_used =
+            self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
         self.handle_spdm_psk_finish_response(session_id, message_f, &receive_buffer[..receive_used])
     }
 
     pub fn encode_spdm_psk_finish(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         buf: &mut [u8],
     ) -> SpdmResult<(usize, ManagedBuffer)> {
         let mut writer = Writer::init(buf);
@@ -58,11 +60,7 @@ impl<'a> RequesterContext<'a> {
             .ok_or(spdm_err!(ENOMEM))?;
 
         #[cfg(not(feature = "hash-update"))]
-        let session = if let Some(s) = self.common.get_immutable_session_via_id(session_id) {
-            s
-        } else {
-            return spdm_result_err!(EFAULT);
-        };
+        let session = self.common.get_session(session_id)?;
         #[cfg(not(feature = "hash-update"))]
         let message_k = &session.runtime_info.message_k;
         #[cfg(not(feature = "hash-update"))]
@@ -72,11 +70,7 @@ impl<'a> RequesterContext<'a> {
             message_k,
             Some(&message_f),
         )?;
-        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-            s
-        } else {
-            return spdm_result_err!(EFAULT);
-        };
+        let session = self.common.get_session_mut(session_id)?;
 
         #[cfg(feature = "hash-update")]
         let mut message_f = session.runtime_info.message_k.as_mut().cloned();
@@ -115,7 +109,7 @@ impl<'a> RequesterContext<'a> {
 
     pub fn handle_spdm_psk_finish_response(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         #[cfg(not(feature = "hash-update"))] mut message_f: ManagedBuffer,
         #[cfg(feature = "hash-update")] message_f: ManagedBuffer, // never use message_f for hash-update, use session.runtime_info.message_f
         receive_buffer: &[u8],
@@ -132,12 +126,7 @@ impl<'a> RequesterContext<'a> {
                         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
                         #[cfg(not(feature = "hash-update"))]
                         {
-                            let session =
-                                if let Some(s) = self.common.get_session_via_id(session_id) {
-                                    s
-                                } else {
-                                    return spdm_result_err!(EFAULT);
-                                };
+                            let session = self.common.get_session_mut(session_id)?;
 
                             message_f
                                 .append_message(&receive_buffer[..receive_used])
@@ -147,12 +136,7 @@ impl<'a> RequesterContext<'a> {
                         }
 
                         #[cfg(not(feature = "hash-update"))]
-                        let session =
-                            if let Some(s) = self.common.get_immutable_session_via_id(session_id) {
-                                s
-                            } else {
-                                return spdm_result_err!(EFAULT);
-                            };
+                        let session = self.common.get_session(session_id)?;
                         #[cfg(not(feature = "hash-update"))]
                         let message_k = &session.runtime_info.message_k; // generate the data secret
                         #[cfg(not(feature = "hash-update"))]
@@ -164,11 +148,7 @@ impl<'a> RequesterContext<'a> {
                         )?;
 
                         #[cfg(feature = "hash-update")]
-                        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                            s
-                        } else {
-                            return spdm_result_err!(EFAULT);
-                        };
+                        let session = self.common.get_session_mut(session_id)?;
                         #[cfg(feature = "hash-update")]
                         crypto::hash::hash_ctx_update(
                             session.runtime_info.message_f.as_mut().unwrap(),
@@ -182,20 +162,20 @@ impl<'a> RequesterContext<'a> {
                         .unwrap();
 
                         debug!("!!! th2 : {:02x?}\n", th2.as_ref());
-                        let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                            s
-                        } else {
-                            return spdm_result_err!(EFAULT);
-                        };
+                        let session = self.common.get_session_mut(session_id)?;
                         session.generate_data_secret(spdm_version_sel, &th2)?;
                         session.set_session_state(
                             crate::common::session::SpdmSessionState::SpdmSessionEstablished,
                         );
+                        self.common.notify_session_event(
+                            session_id.as_u32(),
+                            SpdmSessionEvent::Established,
+                        );
 
                         Ok(())
                     } else {
                         error!("!!! psk_finish : fail !!!\n");
-                        spdm_result_err!(EFAULT)
+                        spdm_result_err!(Codec)
                     }
                 }
                 SpdmRequestResponseCode::SpdmResponseError => {
@@ -215,12 +195,12 @@ impl<'a> RequesterContext<'a> {
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -322,7 +302,9 @@ mod tests_requester {
             data: Box::new([0; SPDM_MAX_DHE_KEY_SIZE]),
         };
         let _ = requester.common.session[0].set_dhe_secret(SpdmVersion::SpdmVersion12, dhe_secret);
-        let status = requester.send_receive_spdm_psk_finish(4294901758).is_ok();
+        let status = requester
+            .send_receive_spdm_psk_finish(SpdmSessionId::from(4294901758u32))
+            .is_ok();
         assert!(status);
     }
 }
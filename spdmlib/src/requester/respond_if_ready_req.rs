@@ -4,16 +4,110 @@
 
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
+use crate::requester::async_context::get_response_not_ready;
 use crate::requester::*;
+use crate::time::sleep;
+
+/// Time a request has been waiting for a responder answering
+/// ERROR(ResponseNotReady), bounded by WT_Max (RDT times RDTM) of the first
+/// such error.
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct SpdmNotReadyWait {
+    waited: usize,
+    max_wait: usize,
+}
+
+impl SpdmNotReadyWait {
+    /// The time to wait before RESPOND_IF_READY, SpdmError::Timeout once
+    /// that would go beyond WT_Max.
+    pub(super) fn next(
+        &mut self,
+        extend_error_data: &SpdmErrorResponseNotReadyExtData,
+    ) -> SpdmResult<usize> {
+        let rdt = extend_error_data.get_rdt();
+        if self.waited == 0 {
+            self.max_wait = extend_error_data.get_max_wait();
+        } else if self.waited.saturating_add(rdt) > self.max_wait {
+            error!("!!! responder not ready after {} us !!!\n", self.waited);
+            return spdm_result_err!(Timeout);
+        }
+        self.waited = self.waited.saturating_add(rdt);
+        Ok(rdt)
+    }
+}
 
 impl<'a> RequesterContext<'a> {
-    pub fn spdm_requester_respond_if_ready(
+    /// While the response in receive_buffer is ERROR(ResponseNotReady) to
+    /// request_code, wait RDT and RESPOND_IF_READY, in session session_id
+    /// if the request was sent in one, up to WT_Max. Returns the size of
+    /// the response finally received. The async and poll requesters do the
+    /// waiting themselves.
+    pub(super) fn resolve_response_not_ready(
         &mut self,
-        expected_response_code: SpdmRequestResponseCode,
-        extend_error_data: SpdmErrorResponseNotReadyExtData,
-    ) -> SpdmResult<ReceivedMessage> {
+        session_id: Option<u32>,
+        request_code: u8,
+        receive_buffer: &mut [u8],
+        used: usize,
+        crypto_request: bool,
+    ) -> SpdmResult<usize> {
+        if self.common.deferred_io.is_some() {
+            return Ok(used);
+        }
+
+        let mut used = used;
+        let mut not_ready_wait = SpdmNotReadyWait::default();
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let mut writer = Writer::init(&mut send_buffer);
+        while let Some((delay, send_used)) = self.prepare_respond_if_ready(
+            request_code,
+            &receive_buffer[..used],
+            &mut not_ready_wait,
+            &mut send_buffer,
+        )? {
+            self.check_cancelled()?;
+            sleep(delay);
+
+            match session_id {
+                Some(session_id) => {
+                    self.send_secured_message(session_id, &send_buffer[..send_used], false)?
+                }
+                None => self.send_message(&send_buffer[..send_used])?,
+            }
+            // the next ERROR(ResponseNotReady) still refers to the request
+            self.last_request_code = request_code;
+            used = self.receive_spdm_message(session_id, receive_buffer, crypto_request)?;
+        }
+        Ok(used)
+    }
+
+    /// None unless response is ERROR(ResponseNotReady), else the time to
+    /// wait and the size of the RESPOND_IF_READY encoded into buf to send
+    /// then. An error if the response is to another request than
+    /// request_code, or once WT_Max is up.
+    pub(super) fn prepare_respond_if_ready(
+        &mut self,
+        request_code: u8,
+        response: &[u8],
+        not_ready_wait: &mut SpdmNotReadyWait,
+        buf: &mut [u8],
+    ) -> SpdmResult<Option<(usize, usize)>> {
+        let extend_error_data = match get_response_not_ready(response) {
+            Some(extend_error_data) => extend_error_data,
+            None => return Ok(None),
+        };
+        if extend_error_data.request_code != request_code {
+            return spdm_result_err!(EDEV);
+        }
+        let delay = not_ready_wait.next(&extend_error_data)?;
+        let used = self.encode_spdm_respond_if_ready(&extend_error_data, buf);
+        Ok(Some((delay, used)))
+    }
+
+    pub fn encode_spdm_respond_if_ready(
+        &mut self,
+        extend_error_data: &SpdmErrorResponseNotReadyExtData,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut writer = Writer::init(buf);
         let request = SpdmMessage {
             header: SpdmMessageHeader {
                 version: self.common.negotiate_info.spdm_version_sel,
@@ -26,28 +120,27 @@ impl<'a> RequesterContext<'a> {
         };
         request.spdm_encode(&mut self.common, &mut writer);
 
-        let used = writer.used();
-        self.send_message(&send_buffer[..used])?;
-
-        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let used = self.receive_message(&mut receive_buffer, false)?;
-
-        //Have a sanity check!
-        let mut reader = Reader::init(&receive_buffer);
-        match SpdmMessageHeader::read(&mut reader) {
-            Some(message_header) => {
-                if message_header.request_response_code == expected_response_code {
-                    Ok(ReceivedMessage {
-                        receive_buffer,
-                        used,
-                    })
-                } else {
-                    spdm_result_err!(EDEV)
-                }
-            }
-            None => {
+        writer.used()
+    }
+}
+
+// The response RESPOND_IF_READY got must be the one to the original request.
+pub(super) fn check_respond_if_ready_response(
+    received_message: ReceivedMessage,
+    expected_response_code: SpdmRequestResponseCode,
+) -> SpdmResult<ReceivedMessage> {
+    //Have a sanity check!
+    let mut reader = Reader::init(&received_message.receive_buffer);
+    match SpdmMessageHeader::read(&mut reader) {
+        Some(message_header) => {
+            if message_header.request_response_code == expected_response_code {
+                Ok(received_message)
+            } else {
                 spdm_result_err!(EDEV)
             }
         }
+        None => {
+            spdm_result_err!(EDEV)
+        }
     }
 }
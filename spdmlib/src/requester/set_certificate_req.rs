@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionId;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::SpdmCertChainData;
+use crate::requester::*;
+
+impl<'a> RequesterContext<'a> {
+    pub fn send_receive_spdm_set_certificate(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        cert_chain: &SpdmCertChainData,
+    ) -> SpdmResult {
+        info!("send spdm set_certificate\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_used = self.encode_spdm_set_certificate(slot_id, cert_chain, &mut send_buffer);
+        if session_id.is_none() {
+            self.send_message(&send_buffer[..send_used])?;
+        } else {
+            self.send_secured_message(
+                session_id.unwrap().as_u32(),
+                &send_buffer[..send_used],
+                false,
+            )?;
+        }
+
+        let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = if session_id.is_none() {
+            self.receive_message(&mut receive_buffer, false)?
+        } else {
+            self.receive_secured_message(session_id.unwrap().as_u32(), &mut receive_buffer, false)?
+        };
+
+        self.handle_spdm_set_certificate_response(session_id, slot_id, &receive_buffer[..used])
+    }
+
+    pub fn encode_spdm_set_certificate(
+        &mut self,
+        slot_id: u8,
+        cert_chain: &SpdmCertChainData,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut writer = Writer::init(buf);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestSetCertificate,
+            },
+            payload: SpdmMessagePayload::SpdmSetCertificateRequest(
+                SpdmSetCertificateRequestPayload {
+                    slot_id,
+                    cert_chain: cert_chain.clone(),
+                },
+            ),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        writer.used()
+    }
+
+    pub fn handle_spdm_set_certificate_response(
+        &mut self,
+        session_id: Option<SpdmSessionId>,
+        slot_id: u8,
+        receive_buffer: &[u8],
+    ) -> SpdmResult {
+        let mut reader = Reader::init(receive_buffer);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseSetCertificateRsp => {
+                    let set_certificate_rsp =
+                        SpdmSetCertificateResponsePayload::spdm_read(&mut self.common, &mut reader);
+                    if let Some(set_certificate_rsp) = set_certificate_rsp {
+                        debug!("!!! set_certificate rsp : {:02x?}\n", set_certificate_rsp);
+                        if set_certificate_rsp.slot_id != slot_id {
+                            error!("!!! set_certificate : slot_id mismatch !!!\n");
+                            return spdm_result_err!(EFAULT);
+                        }
+                        Ok(())
+                    } else {
+                        error!("!!! set_certificate : fail !!!\n");
+                        spdm_result_err!(Codec)
+                    }
+                }
+                SpdmRequestResponseCode::SpdmResponseError => {
+                    let erm = self.spdm_handle_error_response_main(
+                        session_id,
+                        receive_buffer,
+                        SpdmRequestResponseCode::SpdmRequestSetCertificate,
+                        SpdmRequestResponseCode::SpdmResponseSetCertificateRsp,
+                    );
+                    match erm {
+                        Ok(rm) => {
+                            let receive_buffer = rm.receive_buffer;
+                            let used = rm.used;
+                            self.handle_spdm_set_certificate_response(
+                                session_id,
+                                slot_id,
+                                &receive_buffer[..used],
+                            )
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_requester {
+    use super::*;
+    use crate::secret::SpdmCertStore;
+    use crate::testlib::*;
+    use crate::{crypto, responder, secret};
+    use alloc::vec::Vec;
+
+    static STORED_CERT_CHAIN: spin::Mutex<Option<(u8, Vec<u8>)>> = spin::Mutex::new(None);
+
+    static CERT_STORE: SpdmCertStore = SpdmCertStore {
+        store_cert_chain_cb: |slot_id: u8, cert_chain: &[u8]| -> SpdmResult {
+            *STORED_CERT_CHAIN.lock() = Some((slot_id, cert_chain.to_vec()));
+            Ok(())
+        },
+    };
+
+    #[test]
+    fn test_case0_send_receive_spdm_set_certificate() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        for config_info in [&mut rsp_config_info, &mut req_config_info] {
+            config_info.spdm_version = [
+                SpdmVersion::SpdmVersion10,
+                SpdmVersion::SpdmVersion11,
+                SpdmVersion::SpdmVersion12,
+            ];
+        }
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::SET_CERT_CAP;
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        secret::register_cert_store(CERT_STORE.clone());
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        let mut responder = responder::ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut device_io_requester = FakeSpdmDeviceIo::new(&shared_buffer, &mut responder);
+
+        let mut requester = RequesterContext::new(
+            &mut device_io_requester,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+
+        // provision slot 2 with the chain the responder holds in slot 0
+        let cert_chain = requester.common.peer_info.peer_cert_chain[0]
+            .as_ref()
+            .unwrap()
+            .cert_chain
+            .clone();
+        assert!(requester
+            .send_receive_spdm_set_certificate(None, 2, &cert_chain)
+            .is_ok());
+
+        let (slot_id, stored_cert_chain) = STORED_CERT_CHAIN.lock().take().unwrap();
+        assert_eq!(slot_id, 2);
+        assert_eq!(stored_cert_chain.as_slice(), cert_chain.as_ref());
+
+        // the new slot is reported and served like any provisioned one
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 2).is_ok());
+        assert_eq!(
+            requester.common.peer_info.peer_cert_chain[2]
+                .as_ref()
+                .unwrap()
+                .cert_chain
+                .as_ref(),
+            cert_chain.as_ref()
+        );
+
+        // a chain that does not verify never reaches the store
+        let mut corrupted_cert_chain = cert_chain.clone();
+        let last = corrupted_cert_chain.data_size as usize - 1;
+        corrupted_cert_chain.data[last] ^= 0xff;
+        assert!(requester
+            .send_receive_spdm_set_certificate(None, 3, &corrupted_cert_chain)
+            .is_err());
+        assert!(STORED_CERT_CHAIN.lock().is_none());
+    }
+}
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionId;
 use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::requester::*;
@@ -9,7 +10,7 @@ use crate::requester::*;
 impl<'a> RequesterContext<'a> {
     pub fn send_spdm_vendor_defined_request(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         standard_id: RegistryOrStandardsBodyID,
         vendor_id_struct: VendorIDStruct,
         req_payload_struct: VendorDefinedReqPayloadStruct,
@@ -32,20 +33,38 @@ impl<'a> RequesterContext<'a> {
         };
         request.spdm_encode(&mut self.common, &mut writer);
         let used = writer.used();
-        self.send_secured_message(session_id, &send_buffer[..used], true)?;
+        self.send_secured_message(session_id.as_u32(), &send_buffer[..used], true)?;
 
         //receive
         let mut receive_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let receive_used = self.receive_secured_message(session_id, &mut receive_buffer, false)?;
+        let receive_used =
+            self.receive_secured_message(session_id.as_u32(), &mut receive_buffer, false)?;
 
-        self.handle_spdm_vendor_defined_respond(session_id, &receive_buffer[..receive_used])
+        let response = self.handle_spdm_vendor_defined_response_payload(
+            session_id,
+            &receive_buffer[..receive_used],
+        )?;
+        if response.standard_id != standard_id || response.vendor_id != vendor_id_struct {
+            error!("!!! vendor defined rsp : id mismatch !!!\n");
+            return spdm_result_err!(EFAULT);
+        }
+        Ok(response.rsp_payload)
     }
 
     pub fn handle_spdm_vendor_defined_respond(
         &mut self,
-        session_id: u32,
+        session_id: SpdmSessionId,
         receive_buffer: &[u8],
     ) -> SpdmResult<VendorDefinedRspPayloadStruct> {
+        self.handle_spdm_vendor_defined_response_payload(session_id, receive_buffer)
+            .map(|response| response.rsp_payload)
+    }
+
+    fn handle_spdm_vendor_defined_response_payload(
+        &mut self,
+        session_id: SpdmSessionId,
+        receive_buffer: &[u8],
+    ) -> SpdmResult<SpdmVendorDefinedResponsePayload> {
         let mut reader = Reader::init(receive_buffer);
         match SpdmMessageHeader::read(&mut reader) {
             Some(message_header) => match message_header.request_response_code {
@@ -53,7 +72,7 @@ impl<'a> RequesterContext<'a> {
                     match SpdmVendorDefinedResponsePayload::spdm_read(&mut self.common, &mut reader)
                     {
                         Some(spdm_vendor_defined_response_payload) => {
-                            Ok(spdm_vendor_defined_response_payload.rsp_payload)
+                            Ok(spdm_vendor_defined_response_payload)
                         }
                         None => spdm_result_err!(EFAULT),
                     }
@@ -69,17 +88,17 @@ impl<'a> RequesterContext<'a> {
                         Ok(rm) => {
                             let receive_buffer = rm.receive_buffer;
                             let used = rm.used;
-                            self.handle_spdm_vendor_defined_respond(
+                            self.handle_spdm_vendor_defined_response_payload(
                                 session_id,
                                 &receive_buffer[..used],
                             )
                         }
-                        _ => spdm_result_err!(EINVAL),
+                        Err(e) => Err(e),
                     }
                 }
                 _ => spdm_result_err!(EINVAL),
             },
-            None => spdm_result_err!(EIO),
+            None => spdm_result_err!(Codec),
         }
     }
 }
@@ -132,7 +151,7 @@ mod tests_requester {
 
         let status = requester
             .send_spdm_vendor_defined_request(
-                session_id,
+                SpdmSessionId::from(session_id),
                 standard_id,
                 vendor_idstruct,
                 req_payload_struct,
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::cert_chain::spdm_build_cert_chain_from_der;
 use crate::common::SpdmCodec;
 use crate::crypto;
 use crate::message::*;
@@ -97,36 +98,25 @@ impl<'a> ResponderContext<'a> {
         //
         // update cert chain - append root cert hash
         //
-        if self.common.provision_info.my_cert_chain.is_none()
-            && self.common.provision_info.my_cert_chain_data.is_some()
-        {
-            let cert_chain = self
-                .common
-                .provision_info
-                .my_cert_chain_data
+        for slot_id in 0..SPDM_MAX_SLOT_NUMBER {
+            if self.common.provision_info.my_cert_chain[slot_id].is_some()
+                || self.common.provision_info.my_cert_chain_data[slot_id].is_none()
+            {
+                continue;
+            }
+            let cert_chain_data = self.common.provision_info.my_cert_chain_data[slot_id]
                 .as_ref()
                 .unwrap();
-            let (root_cert_begin, root_cert_end) =
-                crypto::cert_operation::get_cert_from_cert_chain(
-                    &cert_chain.data[..(cert_chain.data_size as usize)],
-                    0,
-                )
-                .unwrap();
-            let root_cert = &cert_chain.data[root_cert_begin..root_cert_end];
-            if let Some(root_hash) =
-                crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, root_cert)
-            {
-                let data_size = 4 + root_hash.data_size + cert_chain.data_size;
-                let mut data = [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE];
-                data[0] = (data_size & 0xFF) as u8;
-                data[1] = (data_size >> 8) as u8;
-                data[4..(4 + root_hash.data_size as usize)]
-                    .copy_from_slice(&root_hash.data[..(root_hash.data_size as usize)]);
-                data[(4 + root_hash.data_size as usize)..(data_size as usize)]
-                    .copy_from_slice(&cert_chain.data[..(cert_chain.data_size as usize)]);
-                self.common.provision_info.my_cert_chain =
-                    Some(SpdmCertChainData { data_size, data });
-                debug!("my_cert_chain - {:02x?}\n", &data[..(data_size as usize)]);
+            if let Ok(cert_chain) = spdm_build_cert_chain_from_der(
+                self.common.negotiate_info.base_hash_sel,
+                cert_chain_data.as_ref(),
+            ) {
+                debug!(
+                    "my_cert_chain[{}] - {:02x?}\n",
+                    slot_id,
+                    cert_chain.as_ref()
+                );
+                self.common.provision_info.my_cert_chain[slot_id] = Some(cert_chain);
             } else {
                 return;
             }
@@ -134,7 +124,20 @@ impl<'a> ResponderContext<'a> {
 
         info!("send spdm algorithm\n");
 
-        let other_params_selection = self.common.config_info.opaque_support & other_params_support;
+        let mut other_params_selection = (self.common.config_info.opaque_support
+            & other_params_support)
+            - SpdmOpaqueSupport::MULTI_KEY_CONN;
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion13.get_u8()
+        {
+            let rsp_capabilities = self.common.negotiate_info.rsp_capabilities_sel;
+            if rsp_capabilities.contains(SpdmResponseCapabilityFlags::MULTI_KEY_CAP_ONLY)
+                || (rsp_capabilities.contains(SpdmResponseCapabilityFlags::MULTI_KEY_CAP_CONN_SEL)
+                    && other_params_support.contains(SpdmOpaqueSupport::MULTI_KEY_CONN))
+            {
+                other_params_selection.insert(SpdmOpaqueSupport::MULTI_KEY_CONN);
+            }
+        }
         self.common.negotiate_info.opaque_data_support = other_params_selection;
 
         let response = SpdmMessage {
@@ -0,0 +1,153 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::async_io::{SpdmAsyncDeviceIo, SpdmDeferredDeviceIo, SpdmDeferredIo};
+use crate::common::{SpdmConfigInfo, SpdmProvisionInfo, SpdmTransportEncap};
+use crate::config;
+use crate::responder::ResponderContext;
+
+/// ResponderContext driven through an SpdmAsyncDeviceIo: the request is
+/// awaited, processed by the sync responder, and its response awaited.
+pub struct AsyncResponderContext<'a> {
+    pub responder: ResponderContext<'a>,
+    device_io: &'a mut dyn SpdmAsyncDeviceIo,
+}
+
+impl<'a> AsyncResponderContext<'a> {
+    pub fn new(
+        device_io: &'a mut dyn SpdmAsyncDeviceIo,
+        transport_encap: &'a mut dyn SpdmTransportEncap,
+        config_info: SpdmConfigInfo,
+        provision_info: SpdmProvisionInfo,
+    ) -> Self {
+        let mut responder = ResponderContext::new(
+            SpdmDeferredDeviceIo::leaked(),
+            transport_encap,
+            config_info,
+            provision_info,
+        );
+        responder.common.deferred_io = Some(SpdmDeferredIo::new());
+        AsyncResponderContext {
+            responder,
+            device_io,
+        }
+    }
+
+    /// See ResponderContext::process_message.
+    pub async fn process_message(
+        &mut self,
+        timeout: usize,
+    ) -> Result<bool, (usize, [u8; config::DATA_TRANSFER_SIZE])> {
+        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        self.responder
+            .common
+            .receive_deferred(&mut *self.device_io, &mut transport_buffer, timeout)
+            .await
+            .map_err(|used| (used, transport_buffer))?;
+
+        let result = self.responder.process_message(timeout);
+
+        if self
+            .responder
+            .common
+            .flush_deferred_send(&mut *self.device_io)
+            .await
+            .is_err()
+        {
+            error!("!!! async send response fail !!!\n");
+        }
+        result
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::common::ST1;
+    use crate::error::SpdmResult;
+    use crate::testlib::*;
+    use core::task::{Context, Poll};
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    struct FakeAsyncSpdmDeviceIo {
+        request: [u8; 12],
+        response: [u8; 64],
+        response_size: usize,
+    }
+
+    impl SpdmAsyncDeviceIo for FakeAsyncSpdmDeviceIo {
+        fn poll_send(&mut self, _cx: &mut Context<'_>, buffer: &[u8]) -> Poll<SpdmResult> {
+            self.response[..buffer.len()].copy_from_slice(buffer);
+            self.response_size = buffer.len();
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_receive(
+            &mut self,
+            _cx: &mut Context<'_>,
+            buffer: &mut [u8],
+            _timeout: usize,
+        ) -> Poll<Result<usize, usize>> {
+            buffer[..self.request.len()].copy_from_slice(&self.request);
+            Poll::Ready(Ok(self.request.len()))
+        }
+
+        fn poll_flush_all(&mut self, _cx: &mut Context<'_>) -> Poll<SpdmResult> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_case0_async_process_message() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut device_io = FakeAsyncSpdmDeviceIo {
+            request: [0u8; 12],
+            response: [0u8; 64],
+            response_size: 0,
+        };
+        // GET_VERSION
+        let used = pcidoe_transport_encap
+            .encap(&[0x10, 0x84, 0x00, 0x00], &mut device_io.request, false)
+            .unwrap();
+        assert_eq!(used, 12);
+
+        {
+            let mut responder = AsyncResponderContext::new(
+                &mut device_io,
+                pcidoe_transport_encap,
+                config_info,
+                provision_info,
+            );
+            assert!(matches!(block_on(responder.process_message(ST1)), Ok(true)));
+        }
+
+        let spdm_buffer = &mut [0u8; 64];
+        let (size, secured_message) = PciDoeTransportEncap {}
+            .decap(&device_io.response[..device_io.response_size], spdm_buffer)
+            .unwrap();
+        assert!(!secured_message);
+        assert!(size > 4);
+        assert_eq!(spdm_buffer[1], 0x04); // VERSION
+    }
+}
@@ -13,6 +13,12 @@ impl<'a> ResponderContext<'a> {
         let mut writer = Writer::init(&mut send_buffer);
         self.write_spdm_capability_response(bytes, &mut writer);
         let _ = self.send_message(writer.used_slice());
+        // chunking applies from the message after CAPABILITIES on
+        if writer.used_slice().get(1)
+            == Some(&SpdmRequestResponseCode::SpdmResponseCapabilities.get_u8())
+        {
+            self.common.update_chunk_cap_negotiated();
+        }
     }
 
     pub fn write_spdm_capability_response(&mut self, bytes: &[u8], writer: &mut Writer) {
@@ -41,7 +47,9 @@ impl<'a> ResponderContext<'a> {
             self.common.negotiate_info.rsp_capabilities_sel =
                 self.common.config_info.rsp_capabilities;
 
-            if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+            if self.common.negotiate_info.spdm_version_sel.get_u8()
+                >= SpdmVersion::SpdmVersion12.get_u8()
+            {
                 self.common.negotiate_info.req_data_transfer_size_sel =
                     get_capabilities.data_transfer_size;
                 self.common.negotiate_info.req_max_spdm_msg_size_sel =
@@ -9,14 +9,19 @@ use crate::message::*;
 use crate::responder::*;
 
 impl<'a> ResponderContext<'a> {
-    pub fn handle_spdm_certificate(&mut self, bytes: &[u8], _session_id: Option<u32>) {
+    pub fn handle_spdm_certificate(&mut self, bytes: &[u8], session_id: Option<u32>) {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let mut writer = Writer::init(&mut send_buffer);
-        self.write_spdm_certificate_response(bytes, &mut writer);
+        self.write_spdm_certificate_response(bytes, session_id.is_some(), &mut writer);
         let _ = self.send_message(writer.used_slice());
     }
 
-    fn write_spdm_certificate_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+    fn write_spdm_certificate_response(
+        &mut self,
+        bytes: &[u8],
+        secured: bool,
+        writer: &mut Writer,
+    ) {
         let mut reader = Reader::init(bytes);
         SpdmMessageHeader::read(&mut reader);
 
@@ -51,25 +56,35 @@ impl<'a> ResponderContext<'a> {
         let get_certificate = get_certificate.unwrap();
         let slot_id = get_certificate.slot_id;
 
-        let my_cert_chain = self.common.provision_info.my_cert_chain.as_ref().unwrap();
-
-        let mut length = get_certificate.length;
-        if length > config::MAX_SPDM_CERT_PORTION_LEN as u16 {
-            length = config::MAX_SPDM_CERT_PORTION_LEN as u16;
-        }
+        let my_cert_chain = match self.common.get_my_cert_chain(slot_id) {
+            Some(my_cert_chain) => my_cert_chain,
+            None => {
+                error!(
+                    "!!! get_certificate : slot {} is not provisioned !!!\n",
+                    slot_id
+                );
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
 
         let offset = get_certificate.offset;
-        if offset > my_cert_chain.data_size {
+        if offset >= my_cert_chain.data_size {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return;
         }
 
+        let mut length = get_certificate.length;
+        let max_portion_len = self.common.get_max_cert_portion_len(secured);
+        if length > max_portion_len {
+            length = max_portion_len;
+        }
         if length > my_cert_chain.data_size - offset {
             length = my_cert_chain.data_size - offset;
         }
 
-        let portion_length = length as u16;
-        let remainder_length = my_cert_chain.data_size - (length + offset);
+        let portion_length = length;
+        let remainder_length = my_cert_chain.data_size - offset - length;
 
         let cert_chain_data =
             &my_cert_chain.data[(offset as usize)..(offset as usize + length as usize)];
@@ -90,6 +105,10 @@ impl<'a> ResponderContext<'a> {
             }),
         };
         response.spdm_encode(&mut self.common, writer);
+        // sent with send_message
+        if self.check_response_size(false, writer) {
+            return;
+        }
 
         #[cfg(not(feature = "hash-update"))]
         self.common
@@ -126,7 +145,7 @@ mod tests_responder {
 
         context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
 
-        context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -144,7 +163,7 @@ mod tests_responder {
         let capabilities = &mut [0u8; 1024];
         let mut writer = Writer::init(capabilities);
         let value = SpdmGetCertificateRequestPayload {
-            slot_id: 100,
+            slot_id: 0,
             offset: 100,
             length: 600,
         };
@@ -175,7 +194,7 @@ mod tests_responder {
             let spdm_get_certificate_request_payload =
                 SpdmGetCertificateRequestPayload::spdm_read(&mut context.common, &mut reader)
                     .unwrap();
-            assert_eq!(spdm_get_certificate_request_payload.slot_id, 100);
+            assert_eq!(spdm_get_certificate_request_payload.slot_id, 0);
             assert_eq!(spdm_get_certificate_request_payload.offset, 100);
             assert_eq!(spdm_get_certificate_request_payload.length, 600);
 
@@ -189,7 +208,7 @@ mod tests_responder {
                 SpdmRequestResponseCode::SpdmResponseCertificate
             );
             if let SpdmMessagePayload::SpdmCertificateResponse(payload) = &spdm_message.payload {
-                assert_eq!(payload.slot_id, 100);
+                assert_eq!(payload.slot_id, 0);
                 assert_eq!(payload.portion_length, 412);
                 assert_eq!(payload.remainder_length, 0);
                 for i in 0..412 {
@@ -198,4 +217,84 @@ mod tests_responder {
             }
         }
     }
+
+    #[test]
+    fn test_case1_handle_spdm_certificate() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.common.negotiate_info.req_data_transfer_size_sel = 0x108;
+
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
+            data_size: 512u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        });
+
+        context.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(SpdmBaseHashAlgo::TPM_ALG_SHA_384).unwrap());
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetCertificate,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetCertificateRequestPayload {
+            slot_id: 0,
+            offset: 0,
+            length: 512,
+        };
+        value.spdm_encode(&mut context.common, &mut writer);
+        let used = writer.used();
+
+        let response = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(response);
+        context.write_spdm_certificate_response(&bytes[..used], false, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message_header = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            spdm_message_header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseCertificate
+        );
+        let payload =
+            SpdmCertificateResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.portion_length, 256);
+        assert_eq!(payload.remainder_length, 256);
+
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetCertificate,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetCertificateRequestPayload {
+            slot_id: 0,
+            offset: 512,
+            length: 512,
+        };
+        value.spdm_encode(&mut context.common, &mut writer);
+        let used = writer.used();
+
+        let mut writer = Writer::init(response);
+        context.write_spdm_certificate_response(&bytes[..used], false, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message_header = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            spdm_message_header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+    }
 }
@@ -6,10 +6,13 @@ use crate::common::opaque::SpdmOpaqueStruct;
 use crate::common::ManagedBuffer;
 use crate::common::SpdmCodec;
 use crate::crypto;
+use crate::crypto::SpdmAsymSignOperation;
 use crate::error::{spdm_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
 use crate::responder::*;
+use crate::secret::spdm_generate_measurement_summary_hash;
+
 extern crate alloc;
 use alloc::boxed::Box;
 
@@ -26,9 +29,33 @@ impl<'a> ResponderContext<'a> {
         SpdmMessageHeader::read(&mut reader);
 
         let challenge = SpdmChallengeRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let measurement_summary_hash_type;
+        let slot_id;
         if let Some(challenge) = challenge {
             debug!("!!! challenge : {:02x?}\n", challenge);
 
+            if self.common.get_my_cert_chain(challenge.slot_id).is_none() {
+                error!(
+                    "!!! challenge : slot {} is not provisioned !!!\n",
+                    challenge.slot_id
+                );
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+
+            if !self.common.is_key_usage_allowed(
+                &self.common.provision_info.my_key_usage_mask,
+                challenge.slot_id,
+                SpdmKeyUsageBitMask::CHALLENGE_USE,
+            ) {
+                error!(
+                    "!!! challenge : slot {} key is not for challenge !!!\n",
+                    challenge.slot_id
+                );
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+
             if (challenge.measurement_summary_hash_type
                 == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb)
                 || (challenge.measurement_summary_hash_type
@@ -38,12 +65,37 @@ impl<'a> ResponderContext<'a> {
             } else {
                 self.common.runtime_info.need_measurement_summary_hash = false;
             }
+            measurement_summary_hash_type = challenge.measurement_summary_hash_type;
+            slot_id = challenge.slot_id;
         } else {
             error!("!!! challenge : fail !!!\n");
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return;
         }
 
+        let measurement_summary_hash = if self.common.runtime_info.need_measurement_summary_hash {
+            match spdm_generate_measurement_summary_hash(
+                self.common.negotiate_info.spdm_version_sel,
+                self.common.negotiate_info.base_hash_sel,
+                self.common.negotiate_info.measurement_specification_sel,
+                self.common.negotiate_info.base_hash_sel,
+                measurement_summary_hash_type,
+            ) {
+                Some(hash)
+                    if hash.data_size == self.common.negotiate_info.base_hash_sel.get_size() =>
+                {
+                    hash
+                }
+                _ => {
+                    error!("!!! challenge : measurement summary hash fail !!!\n");
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                    return;
+                }
+            }
+        } else {
+            SpdmDigestStruct::default()
+        };
+
         #[cfg(not(feature = "hash-update"))]
         if self
             .common
@@ -64,10 +116,22 @@ impl<'a> ResponderContext<'a> {
 
         info!("send spdm challenge_auth\n");
 
+        let basic_mut_auth = self.is_basic_mut_auth_supported();
+        let challenge_auth_attribute = if basic_mut_auth {
+            SpdmChallengeAuthAttribute::BASIC_MUT_AUTH_REQ
+        } else {
+            SpdmChallengeAuthAttribute::empty()
+        };
+        self.common.runtime_info.basic_mut_auth_verified = false;
+
         let mut nonce = [0u8; SPDM_NONCE_SIZE];
-        let _ = crypto::rand::get_random(&mut nonce);
+        if self.common.get_random(&mut nonce).is_err() {
+            error!("!!! challenge : no random !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
 
-        let my_cert_chain = self.common.provision_info.my_cert_chain.as_ref().unwrap();
+        let my_cert_chain = self.common.get_my_cert_chain(slot_id).unwrap();
         let cert_chain_hash = crypto::hash::hash_all(
             self.common.negotiate_info.base_hash_sel,
             my_cert_chain.as_ref(),
@@ -81,15 +145,12 @@ impl<'a> ResponderContext<'a> {
             },
             payload: SpdmMessagePayload::SpdmChallengeAuthResponse(
                 SpdmChallengeAuthResponsePayload {
-                    slot_id: 0x0,
-                    slot_mask: 0x1,
-                    challenge_auth_attribute: SpdmChallengeAuthAttribute::empty(),
+                    slot_id,
+                    slot_mask: self.common.get_my_slot_mask(),
+                    challenge_auth_attribute,
                     cert_chain_hash,
                     nonce: SpdmNonceStruct { data: nonce },
-                    measurement_summary_hash: SpdmDigestStruct {
-                        data_size: self.common.negotiate_info.base_hash_sel.get_size(),
-                        data: Box::new([0xaa; SPDM_MAX_HASH_SIZE]),
-                    },
+                    measurement_summary_hash,
                     opaque: SpdmOpaqueStruct {
                         data_size: 0,
                         data: [0u8; config::MAX_SPDM_OPAQUE_SIZE],
@@ -102,6 +163,9 @@ impl<'a> ResponderContext<'a> {
             ),
         };
         response.spdm_encode(&mut self.common, writer);
+        if self.check_response_size(false, writer) {
+            return;
+        }
         let used = writer.used();
 
         // generat signature
@@ -119,7 +183,7 @@ impl<'a> ResponderContext<'a> {
             &writer.used_slice()[..temp_used],
         );
         #[cfg(not(feature = "hash-update"))]
-        let signature = self.generate_challenge_auth_signature();
+        let sign_operation = self.generate_challenge_auth_signature(slot_id);
         #[cfg(feature = "hash-update")]
         let message_m_clone = self
             .common
@@ -129,23 +193,37 @@ impl<'a> ResponderContext<'a> {
             .cloned()
             .unwrap();
         #[cfg(feature = "hash-update")]
-        let signature = self.generate_challenge_auth_signature(
+        let sign_operation = self.generate_challenge_auth_signature(
+            slot_id,
             crypto::hash::hash_ctx_finalize(message_m_clone).unwrap(),
         );
-        if signature.is_err() {
+        // patch the message before send, or defer it while the signer is busy
+        if sign_operation
+            .and_then(|sign_operation| {
+                self.write_spdm_signed_response(
+                    None,
+                    SpdmRequestResponseCode::SpdmRequestChallenge,
+                    sign_operation,
+                    writer,
+                )
+            })
+            .is_err()
+        {
             self.send_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0);
             return;
         }
-        let signature = signature.unwrap();
-        // patch the message before send
-        writer.mut_used_slice()[(used - base_asym_size)..used].copy_from_slice(signature.as_ref());
+
+        if basic_mut_auth {
+            self.init_spdm_basic_mut_auth();
+        }
     }
 
     #[cfg(feature = "hash-update")]
     pub fn generate_challenge_auth_signature(
         &self,
+        slot_id: u8,
         message_hash: SpdmDigestStruct,
-    ) -> SpdmResult<SpdmSignatureStruct> {
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
         debug!("message_hash - {:02x?}", message_hash.as_ref());
 
         let mut message = ManagedBuffer::default();
@@ -165,16 +243,18 @@ impl<'a> ResponderContext<'a> {
                 .ok_or_else(|| spdm_err!(ENOMEM))?;
         }
 
-        crypto::asym_sign::sign(
-            self.common.negotiate_info.base_hash_sel,
+        self.common.sign_start_with_slot_key(
+            slot_id,
             self.common.negotiate_info.base_asym_sel,
             message.as_ref(),
         )
-        .ok_or_else(|| spdm_err!(EFAULT))
     }
 
     #[cfg(not(feature = "hash-update"))]
-    pub fn generate_challenge_auth_signature(&mut self) -> SpdmResult<SpdmSignatureStruct> {
+    pub fn generate_challenge_auth_signature(
+        &mut self,
+        slot_id: u8,
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
         let mut message = ManagedBuffer::default();
         message
             .append_message(self.common.runtime_info.message_a.as_ref())
@@ -208,12 +288,11 @@ impl<'a> ResponderContext<'a> {
                 .ok_or_else(|| spdm_err!(ENOMEM))?;
         }
 
-        crypto::asym_sign::sign(
-            self.common.negotiate_info.base_hash_sel,
+        self.common.sign_start_with_slot_key(
+            slot_id,
             self.common.negotiate_info.base_asym_sel,
             message.as_ref(),
         )
-        .ok_or_else(|| spdm_err!(EFAULT))
     }
 }
 
@@ -233,6 +312,7 @@ mod tests_responder {
         let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
 
         crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        crate::secret::register(SECRET_IMPL.clone());
         crypto::rand::register(DEFAULT_TEST.clone());
 
         let mut context = responder::ResponderContext::new(
@@ -241,7 +321,7 @@ mod tests_responder {
             config_info,
             provision_info,
         );
-        context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -266,7 +346,7 @@ mod tests_responder {
         let challenge = &mut [0u8; 1024];
         let mut writer = Writer::init(challenge);
         let value = SpdmChallengeRequestPayload {
-            slot_id: 100,
+            slot_id: 0,
             measurement_summary_hash_type:
                 SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
             nonce: SpdmNonceStruct { data: [100u8; 32] },
@@ -298,7 +378,7 @@ mod tests_responder {
             let mut reader = Reader::init(spdm_struct_slice);
             let spdm_challenge_request_payload =
                 SpdmChallengeRequestPayload::spdm_read(&mut context.common, &mut reader).unwrap();
-            assert_eq!(spdm_challenge_request_payload.slot_id, 100);
+            assert_eq!(spdm_challenge_request_payload.slot_id, 0);
             assert_eq!(
                 spdm_challenge_request_payload.measurement_summary_hash_type,
                 SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll
@@ -319,10 +399,8 @@ mod tests_responder {
 
             let cert_chain_hash = crypto::hash::hash_all(
                 context.common.negotiate_info.base_hash_sel,
-                context
-                    .common
-                    .provision_info
-                    .my_cert_chain
+                context.common.provision_info.my_cert_chain[0]
+                    .as_ref()
                     .unwrap()
                     .as_ref(),
             )
@@ -0,0 +1,603 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::SpdmCodec;
+use crate::message::*;
+use crate::responder::*;
+
+// Track the large request received with CHUNK_SEND and the large response
+// sent with CHUNK_GET, one of each at a time.
+pub struct SpdmChunkContext {
+    pub send_pending: bool,
+    pub send_session_id: Option<u32>,
+    pub send_handle: u8,
+    pub send_seq_num: u16,
+    pub large_request_size: usize,
+    pub large_request_used: usize,
+    pub large_request: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    // handle and ChunkSeqNo of the last CHUNK_SEND, whose CHUNK_SEND_ACK
+    // carries the response to the large request
+    pub ack: Option<(u8, u16)>,
+    pub get_pending: bool,
+    pub get_session_id: Option<u32>,
+    pub get_handle: u8,
+    pub get_seq_num: u16,
+    pub next_handle: u8,
+    pub large_response_size: usize,
+    pub large_response_used: usize,
+    pub large_response: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+}
+
+impl Default for SpdmChunkContext {
+    fn default() -> Self {
+        SpdmChunkContext {
+            send_pending: false,
+            send_session_id: None,
+            send_handle: 0,
+            send_seq_num: 0,
+            large_request_size: 0,
+            large_request_used: 0,
+            large_request: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            ack: None,
+            get_pending: false,
+            get_session_id: None,
+            get_handle: 0,
+            get_seq_num: 0,
+            next_handle: 0,
+            large_response_size: 0,
+            large_response_used: 0,
+            large_response: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+        }
+    }
+}
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_chunk_send(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        if self.write_spdm_chunk_send_ack(session_id, bytes, &mut writer) {
+            self.dispatch_spdm_large_request(session_id);
+            return;
+        }
+        match session_id {
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+        }
+    }
+
+    /// CHUNK_SEND_ACK to a chunk of a large request. False once the last
+    /// chunk is in, the response to the large request then goes into its
+    /// CHUNK_SEND_ACK.
+    pub fn write_spdm_chunk_send_ack(
+        &mut self,
+        session_id: Option<u32>,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) -> bool {
+        if !self.common.is_chunk_cap_negotiated() {
+            error!("!!! chunk_send : CHUNK_CAP not negotiated !!!\n");
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestChunkSend.get_u8(),
+                writer,
+            );
+            return false;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+        let chunk = match SpdmChunkSendRequestPayload::spdm_read(&mut self.common, &mut reader) {
+            Some(chunk_send) => chunk_send.chunk,
+            None => {
+                error!("!!! chunk_send : fail !!!\n");
+                self.chunk_context.send_pending = false;
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
+        debug!(
+            "!!! chunk_send : handle {} seq {} size {}\n",
+            chunk.handle, chunk.chunk_seq_num, chunk.chunk_size
+        );
+
+        if chunk.chunk_seq_num == 0 {
+            let large_message_size = chunk.large_message_size as usize;
+            let max_size = self
+                .common
+                .get_rsp_max_message_size()
+                .unwrap_or(0)
+                .min(config::MAX_SPDM_MESSAGE_BUFFER_SIZE);
+            if large_message_size > max_size {
+                error!(
+                    "!!! chunk_send : large request {} too large !!!\n",
+                    large_message_size
+                );
+                self.chunk_context.send_pending = false;
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorRequestTooLarge, 0, writer);
+                return false;
+            }
+            let ctx = &mut self.chunk_context;
+            ctx.send_pending = true;
+            ctx.send_session_id = session_id;
+            ctx.send_handle = chunk.handle;
+            ctx.send_seq_num = 0;
+            ctx.large_request_size = large_message_size;
+            ctx.large_request_used = 0;
+        } else {
+            let ctx = &mut self.chunk_context;
+            if !ctx.send_pending
+                || ctx.send_session_id != session_id
+                || ctx.send_handle != chunk.handle
+                || ctx.send_seq_num.wrapping_add(1) != chunk.chunk_seq_num
+            {
+                error!("!!! chunk_send : out of sequence !!!\n");
+                ctx.send_pending = false;
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+            ctx.send_seq_num = chunk.chunk_seq_num;
+        }
+
+        // the last chunk completes the large request, no other does
+        let ctx = &mut self.chunk_context;
+        let used = ctx.large_request_used + chunk.chunk_size as usize;
+        if used > ctx.large_request_size || chunk.is_last() != (used == ctx.large_request_size) {
+            error!("!!! chunk_send : chunks do not add up !!!\n");
+            ctx.send_pending = false;
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return false;
+        }
+        ctx.large_request[ctx.large_request_used..used].copy_from_slice(chunk.as_slice());
+        ctx.large_request_used = used;
+        if chunk.is_last() {
+            ctx.send_pending = false;
+            ctx.ack = Some((chunk.handle, chunk.chunk_seq_num));
+            return true;
+        }
+
+        SpdmMessageHeader {
+            version: self.common.negotiate_info.spdm_version_sel,
+            request_response_code: SpdmRequestResponseCode::SpdmResponseChunkSendAck,
+        }
+        .encode(writer);
+        SpdmChunkSendAckAttributes::empty().encode(writer); // param1
+        chunk.handle.encode(writer); // param2
+        chunk.chunk_seq_num.encode(writer);
+        false
+    }
+
+    // Dispatch the large request CHUNK_SEND put together, its response goes
+    // into the CHUNK_SEND_ACK of the last chunk.
+    fn dispatch_spdm_large_request(&mut self, session_id: Option<u32>) {
+        let mut large_request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = self.chunk_context.large_request_used;
+        large_request[..used].copy_from_slice(&self.chunk_context.large_request[..used]);
+        let large_request = &large_request[..used];
+
+        let request_code = match SpdmMessageHeader::read_bytes(large_request) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmRequestChunkSend
+                | SpdmRequestResponseCode::SpdmRequestChunkGet => None,
+                request_response_code => Some(request_response_code),
+            },
+            None => None,
+        };
+        if request_code.is_some() && !self.defer_spdm_request(session_id, large_request) {
+            match session_id {
+                None => self.dispatch_spdm_request(large_request),
+                Some(session_id) => self.dispatch_secured_spdm_request(session_id, large_request),
+            };
+        }
+
+        // nothing answered the large request
+        if self.chunk_context.ack.is_some() {
+            let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let mut writer = Writer::init(&mut send_buffer);
+            match request_code {
+                Some(request_code) => self.write_spdm_error(
+                    SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                    request_code.get_u8(),
+                    &mut writer,
+                ),
+                None => {
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, &mut writer)
+                }
+            }
+            match session_id {
+                None => {
+                    let _ = self.send_message(writer.used_slice());
+                }
+                Some(session_id) => {
+                    let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+                }
+            }
+        }
+    }
+
+    pub fn handle_spdm_chunk_get(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_chunk_response(session_id, bytes, &mut writer);
+        match session_id {
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+        }
+    }
+
+    pub fn write_spdm_chunk_response(
+        &mut self,
+        session_id: Option<u32>,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        if !self.common.is_chunk_cap_negotiated() {
+            error!("!!! chunk_get : CHUNK_CAP not negotiated !!!\n");
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestChunkGet.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+        let chunk_get = match SpdmChunkGetRequestPayload::spdm_read(&mut self.common, &mut reader) {
+            Some(chunk_get) => chunk_get,
+            None => {
+                error!("!!! chunk_get : fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
+        debug!("!!! chunk_get : {:02x?}\n", chunk_get);
+
+        let ctx = &self.chunk_context;
+        if !ctx.get_pending || ctx.get_session_id != session_id {
+            error!("!!! chunk_get : no large response !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return;
+        }
+        if ctx.get_handle != chunk_get.handle || ctx.get_seq_num != chunk_get.chunk_seq_num {
+            error!("!!! chunk_get : out of sequence !!!\n");
+            self.chunk_context.get_pending = false;
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        let header_size = if chunk_get.chunk_seq_num == 0 {
+            SPDM_FIRST_CHUNK_HEADER_SIZE
+        } else {
+            SPDM_CHUNK_HEADER_SIZE
+        };
+        let max_chunk_size = self
+            .get_max_transfer_size(session_id.is_some())
+            .saturating_sub(header_size);
+        let ctx = &mut self.chunk_context;
+        let remaining = ctx.large_response_size - ctx.large_response_used;
+        let chunk_size = remaining.min(max_chunk_size);
+        if chunk_size == 0 {
+            ctx.get_pending = false;
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
+
+        let mut chunk = SpdmChunk {
+            attributes: SpdmChunkAttributes::empty(),
+            handle: ctx.get_handle,
+            chunk_seq_num: ctx.get_seq_num,
+            large_message_size: ctx.large_response_size as u32,
+            chunk_size: chunk_size as u32,
+            ..Default::default()
+        };
+        chunk.chunk[..chunk_size].copy_from_slice(
+            &ctx.large_response[ctx.large_response_used..(ctx.large_response_used + chunk_size)],
+        );
+        ctx.large_response_used += chunk_size;
+        ctx.get_seq_num = ctx.get_seq_num.wrapping_add(1);
+        if chunk_size == remaining {
+            chunk.attributes = SpdmChunkAttributes::LAST_CHUNK;
+            ctx.get_pending = false;
+        }
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseChunkResponse,
+            },
+            payload: SpdmMessagePayload::SpdmChunkResponse(SpdmChunkResponsePayload { chunk }),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+
+    /// The response as it goes out once CHUNK_CAP is negotiated: inside the
+    /// CHUNK_SEND_ACK of a large request, and replaced with
+    /// ERROR(LargeResponse) and kept for CHUNK_GET when the requester does
+    /// not take it in one transfer. Call it after limit_response_size.
+    pub(crate) fn chunk_spdm_response<'b>(
+        &mut self,
+        session_id: Option<u32>,
+        response: &'b [u8],
+        chunk_buffer: &'b mut [u8],
+    ) -> &'b [u8] {
+        let ack = self.chunk_context.ack.take();
+        if !self.common.is_chunk_cap_negotiated() {
+            return response;
+        }
+        let ack_header_size = if ack.is_some() {
+            SPDM_CHUNK_SEND_ACK_HEADER_SIZE
+        } else {
+            0
+        };
+        let large_response = ack_header_size + response.len()
+            > self.get_max_transfer_size(session_id.is_some())
+            && response.len() <= self.chunk_context.large_response.len();
+        if ack.is_none() && !large_response {
+            return response;
+        }
+
+        let mut writer = Writer::init(chunk_buffer);
+        if let Some((handle, chunk_seq_num)) = ack {
+            SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseChunkSendAck,
+            }
+            .encode(&mut writer);
+            SpdmChunkSendAckAttributes::empty().encode(&mut writer); // param1
+            handle.encode(&mut writer); // param2
+            chunk_seq_num.encode(&mut writer);
+        }
+        if large_response {
+            let ctx = &mut self.chunk_context;
+            ctx.get_pending = true;
+            ctx.get_session_id = session_id;
+            ctx.get_handle = ctx.next_handle;
+            ctx.next_handle = ctx.next_handle.wrapping_add(1);
+            ctx.get_seq_num = 0;
+            ctx.large_response_size = response.len();
+            ctx.large_response_used = 0;
+            ctx.large_response[..response.len()].copy_from_slice(response);
+            info!("large response, handle {}\n", ctx.get_handle);
+            let handle = ctx.get_handle;
+            self.write_spdm_error_with_ext_data(
+                SpdmErrorCode::SpdmErrorLargeResponse,
+                0,
+                SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(
+                    SpdmErrorResponseLargeExtData { handle },
+                ),
+                &mut writer,
+            );
+        } else {
+            let _ = writer.extend_from_slice(response);
+        }
+        let used = writer.used();
+        &chunk_buffer[..used]
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+
+    fn negotiate_chunk_cap(context: &mut ResponderContext, data_transfer_size: u32) {
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.common.negotiate_info.req_capabilities_sel = SpdmRequestCapabilityFlags::CHUNK_CAP;
+        context.common.negotiate_info.rsp_capabilities_sel = SpdmResponseCapabilityFlags::CHUNK_CAP;
+        context.common.negotiate_info.req_data_transfer_size_sel = data_transfer_size;
+        context.common.negotiate_info.req_max_spdm_msg_size_sel = 0x1000;
+        context.common.negotiate_info.rsp_data_transfer_size_sel = data_transfer_size;
+        context.common.negotiate_info.rsp_max_spdm_msg_size_sel = 0x1000;
+        context.common.update_chunk_cap_negotiated();
+    }
+
+    fn encode_chunk_send(
+        context: &mut ResponderContext,
+        chunk_seq_num: u16,
+        last: bool,
+        data: &[u8],
+        large_message_size: u32,
+        buf: &mut [u8],
+    ) -> usize {
+        let mut chunk = SpdmChunk {
+            handle: 5,
+            chunk_seq_num,
+            large_message_size,
+            chunk_size: data.len() as u32,
+            ..Default::default()
+        };
+        if last {
+            chunk.attributes = SpdmChunkAttributes::LAST_CHUNK;
+        }
+        chunk.chunk[..data.len()].copy_from_slice(data);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: SpdmVersion::SpdmVersion12,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestChunkSend,
+            },
+            payload: SpdmMessagePayload::SpdmChunkSendRequest(SpdmChunkSendRequestPayload {
+                chunk,
+            }),
+        };
+        let mut writer = Writer::init(buf);
+        request.spdm_encode(&mut context.common, &mut writer);
+        writer.used()
+    }
+
+    #[test]
+    fn test_case0_handle_spdm_chunk_send() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        negotiate_chunk_cap(&mut context, 0x40);
+
+        // GET_VERSION padded to 0x50 bytes, in two chunks
+        let mut large_request = [0u8; 0x50];
+        large_request[0] = 0x10;
+        large_request[1] = SpdmRequestResponseCode::SpdmRequestGetVersion.get_u8();
+
+        let mut request = [0u8; 0x40];
+        let used = encode_chunk_send(
+            &mut context,
+            0,
+            false,
+            &large_request[..0x20],
+            0x50,
+            &mut request,
+        );
+        let mut response = [0u8; 0x40];
+        let mut writer = Writer::init(&mut response);
+        assert!(!context.write_spdm_chunk_send_ack(None, &request[..used], &mut writer));
+        let mut reader = Reader::init(writer.used_slice());
+        let header = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseChunkSendAck
+        );
+
+        // a chunk out of sequence drops the large request
+        let used = encode_chunk_send(
+            &mut context,
+            2,
+            true,
+            &large_request[0x20..],
+            0,
+            &mut request,
+        );
+        let mut writer = Writer::init(&mut response);
+        assert!(!context.write_spdm_chunk_send_ack(None, &request[..used], &mut writer));
+        assert_eq!(writer.used_slice()[1], 0x7f);
+        assert!(!context.chunk_context.send_pending);
+
+        let used = encode_chunk_send(
+            &mut context,
+            0,
+            false,
+            &large_request[..0x20],
+            0x50,
+            &mut request,
+        );
+        let mut writer = Writer::init(&mut response);
+        assert!(!context.write_spdm_chunk_send_ack(None, &request[..used], &mut writer));
+        let used = encode_chunk_send(
+            &mut context,
+            1,
+            true,
+            &large_request[0x20..],
+            0,
+            &mut request,
+        );
+        let mut writer = Writer::init(&mut response);
+        assert!(context.write_spdm_chunk_send_ack(None, &request[..used], &mut writer));
+        assert_eq!(
+            &context.chunk_context.large_request[..context.chunk_context.large_request_used],
+            &large_request[..]
+        );
+        assert_eq!(context.chunk_context.ack, Some((5, 1)));
+
+        // the response goes into the CHUNK_SEND_ACK of the last chunk
+        let mut chunk_buffer = [0u8; 0x40];
+        let response = context.chunk_spdm_response(None, &[0x12, 0x04, 0, 0], &mut chunk_buffer);
+        assert_eq!(response, &[0x12, 0x05, 0, 5, 1, 0, 0x12, 0x04, 0, 0]);
+        assert!(context.chunk_context.ack.is_none());
+    }
+    #[test]
+    fn test_case0_handle_spdm_chunk_get() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        negotiate_chunk_cap(&mut context, 0x40);
+
+        let mut large_response = [0u8; 0x50];
+        for (i, data) in large_response.iter_mut().enumerate() {
+            *data = i as u8;
+        }
+        let mut chunk_buffer = [0u8; 0x40];
+        let response = context.chunk_spdm_response(None, &large_response, &mut chunk_buffer);
+        let mut reader = Reader::init(response);
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        let handle = match spdm_message.payload {
+            SpdmMessagePayload::SpdmErrorResponse(SpdmErrorResponsePayload {
+                error_code: SpdmErrorCode::SpdmErrorLargeResponse,
+                extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataLargeResponse(ext_data),
+                ..
+            }) => ext_data.handle,
+            _ => panic!("expected ERROR(LargeResponse)"),
+        };
+
+        let mut received = [0u8; 0x50];
+        let mut received_used = 0;
+        for chunk_seq_num in 0..2u16 {
+            let mut request = [0u8; 6];
+            let mut writer = Writer::init(&mut request);
+            SpdmMessage {
+                header: SpdmMessageHeader {
+                    version: SpdmVersion::SpdmVersion12,
+                    request_response_code: SpdmRequestResponseCode::SpdmRequestChunkGet,
+                },
+                payload: SpdmMessagePayload::SpdmChunkGetRequest(SpdmChunkGetRequestPayload {
+                    handle,
+                    chunk_seq_num,
+                }),
+            }
+            .spdm_encode(&mut context.common, &mut writer);
+
+            let mut response = [0u8; 0x40];
+            let mut writer = Writer::init(&mut response);
+            context.write_spdm_chunk_response(None, &request, &mut writer);
+            assert!(writer.used() <= 0x40);
+            let mut reader = Reader::init(writer.used_slice());
+            let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+            let chunk = match spdm_message.payload {
+                SpdmMessagePayload::SpdmChunkResponse(payload) => payload.chunk,
+                _ => panic!("expected CHUNK_RESPONSE"),
+            };
+            if chunk_seq_num == 0 {
+                assert_eq!(chunk.large_message_size, 0x50);
+            }
+            assert_eq!(chunk.is_last(), chunk_seq_num == 1);
+            received[received_used..(received_used + chunk.chunk_size as usize)]
+                .copy_from_slice(chunk.as_slice());
+            received_used += chunk.chunk_size as usize;
+        }
+        assert_eq!(&received[..received_used], &large_response[..]);
+
+        // nothing left to get
+        let request = [0x12, 0x86, 0, handle, 2, 0];
+        let mut response = [0u8; 0x40];
+        let mut writer = Writer::init(&mut response);
+        context.write_spdm_chunk_response(None, &request, &mut writer);
+        assert_eq!(
+            writer.used_slice()[2],
+            SpdmErrorCode::SpdmErrorUnexpectedRequest.get_u8()
+        );
+    }
+}
@@ -2,14 +2,44 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::common::{SpdmDeviceIo, SpdmTransportEncap};
+use crate::common::connection_info::SpdmConnectionInfo;
+use crate::common::session::SpdmSessionState;
+use crate::common::session_audit::SpdmSessionAudit;
+use crate::common::{
+    SpdmAppMessageHandler, SpdmDeviceIo, SpdmSessionEvent, SpdmSessionObserver, SpdmTimeProvider,
+    SpdmTracer, SpdmTransportEncap,
+};
 use crate::config;
+use crate::crypto::SpdmRng;
 use crate::error::SpdmResult;
 use crate::message::*;
-use codec::{Codec, Reader};
+use crate::protocol::SpdmDigestStruct;
+use crate::responder::chunk_rsp::SpdmChunkContext;
+use crate::responder::encap_rsp::SpdmEncapContext;
+use crate::responder::respond_if_ready_rsp::{SpdmDeferralHandler, SpdmRespondIfReadyContext};
+use crate::responder::shared_state::SpdmSharedConnection;
+use crate::responder::vendor_rsp::{
+    SpdmVendorDefinedHandlerEntry, MAX_SPDM_VENDOR_DEFINED_HANDLER_COUNT,
+};
+use codec::{Codec, Reader, Writer};
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+// SPDM header, ErrorCode, ErrorData and the 4-byte ResponseSize
+const SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE: usize = 8;
 
 pub struct ResponderContext<'a> {
     pub common: crate::common::SpdmContext<'a>,
+    pub(crate) encap_context: SpdmEncapContext,
+    pub(crate) respond_if_ready_context: SpdmRespondIfReadyContext,
+    pub(crate) chunk_context: SpdmChunkContext,
+    pub(crate) deferral_handler: Option<SpdmDeferralHandler<'a>>,
+    pub(crate) vendor_defined_handlers:
+        [Option<SpdmVendorDefinedHandlerEntry<'a>>; MAX_SPDM_VENDOR_DEFINED_HANDLER_COUNT],
+    pub(crate) shared_connection: Option<SpdmSharedConnection<'a>>,
+    // the KEY_EXCHANGE session a FINISH in the clear is for
+    pub(crate) clear_handshake_session_id: Option<u32>,
 }
 
 pub const M_SECURE_SESSION_RESPONSE: &[u8; 5] = &[
@@ -33,13 +63,52 @@ impl<'a> ResponderContext<'a> {
                 config_info,
                 provision_info,
             ),
+            encap_context: SpdmEncapContext::default(),
+            respond_if_ready_context: SpdmRespondIfReadyContext::default(),
+            chunk_context: SpdmChunkContext::default(),
+            deferral_handler: None,
+            vendor_defined_handlers: [None; MAX_SPDM_VENDOR_DEFINED_HANDLER_COUNT],
+            shared_connection: None,
+            clear_handshake_session_id: None,
         }
     }
 
+    /// Draw nonces and ephemeral keys from rng instead of the crypto provider.
+    pub fn register_rng(&mut self, rng: &'a mut dyn SpdmRng) {
+        self.common.rng = Some(rng);
+    }
+
+    /// Check certificate validity periods against, and timestamp traced
+    /// messages with, time_provider instead of the platform clock.
+    pub fn register_time_provider(&mut self, time_provider: &'a dyn SpdmTimeProvider) {
+        self.common.time_provider = Some(time_provider);
+    }
+
+    /// Report every transport message sent and received to tracer.
+    pub fn register_tracer(&mut self, tracer: &'a dyn SpdmTracer) {
+        self.common.tracer = Some(tracer);
+    }
+
+    /// Report the lifecycle events of every session to session_observer.
+    pub fn register_session_observer(&mut self, session_observer: &'a dyn SpdmSessionObserver) {
+        self.common.session_observer = Some(session_observer);
+    }
+
+    /// Hand the application messages received in a session to handler. Without
+    /// one, a PLDM GetTID response is sent back.
+    pub fn register_app_message_handler(&mut self, handler: SpdmAppMessageHandler<'a>) {
+        self.common.app_message_handler = Some(handler);
+    }
+
     pub fn send_message(&mut self, send_buffer: &[u8]) -> SpdmResult {
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        let send_buffer = self.limit_response_size(send_buffer, false, &mut error_buffer);
+        let mut chunk_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_buffer = self.chunk_spdm_response(None, send_buffer, &mut chunk_buffer);
         let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         let used = self.common.encap(send_buffer, &mut transport_buffer)?;
-        self.common.device_io.send(&transport_buffer[..used])
+        self.common
+            .send_transport_message(&transport_buffer[..used])
     }
 
     pub fn send_secured_message(
@@ -48,6 +117,14 @@ impl<'a> ResponderContext<'a> {
         send_buffer: &[u8],
         is_app_message: bool,
     ) -> SpdmResult {
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        let mut chunk_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let send_buffer = if is_app_message {
+            send_buffer
+        } else {
+            let send_buffer = self.limit_response_size(send_buffer, true, &mut error_buffer);
+            self.chunk_spdm_response(Some(session_id), send_buffer, &mut chunk_buffer)
+        };
         let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         let used = self.common.encode_secured_message(
             session_id,
@@ -56,34 +133,265 @@ impl<'a> ResponderContext<'a> {
             false,
             is_app_message,
         )?;
-        self.common.device_io.send(&transport_buffer[..used])
+        self.common
+            .send_transport_message(&transport_buffer[..used])
+    }
+
+    /// Replace a response exceeding the requester's negotiated MaxSPDMmsgSize,
+    /// or without CHUNK_CAP its DataTransferSize or what the transport
+    /// carries, with ERROR(ResponseTooLarge).
+    fn limit_response_size<'b>(
+        &mut self,
+        send_buffer: &'b [u8],
+        secured: bool,
+        error_buffer: &'b mut [u8],
+    ) -> &'b [u8] {
+        if send_buffer.len() <= self.get_max_response_size(secured) {
+            return send_buffer;
+        }
+        let mut writer = Writer::init(error_buffer);
+        self.write_spdm_error_response_too_large(secured, send_buffer.len(), &mut writer);
+        let used = writer.used();
+        &error_buffer[..used]
+    }
+
+    /// Replace the response in writer with ERROR(ResponseTooLarge) if
+    /// limit_response_size would. Call it before the response goes into a
+    /// transcript, so the transcript holds what is actually sent. Returns
+    /// true if replaced.
+    pub(crate) fn check_response_size(&mut self, secured: bool, writer: &mut Writer) -> bool {
+        let response_size = writer.used();
+        if response_size <= self.get_max_response_size(secured) {
+            return false;
+        }
+        writer.clear();
+        self.write_spdm_error_response_too_large(secured, response_size, writer);
+        true
+    }
+
+    fn get_max_response_size(&mut self, secured: bool) -> usize {
+        // larger responses than one transfer go out with CHUNK_GET
+        if self.common.is_chunk_cap_negotiated() {
+            return self
+                .common
+                .get_req_max_message_size()
+                .unwrap_or(config::MAX_SPDM_MESSAGE_BUFFER_SIZE)
+                .min(config::MAX_SPDM_MESSAGE_BUFFER_SIZE);
+        }
+        self.get_max_transfer_size(secured)
+    }
+
+    // The largest response the requester takes in one transfer.
+    pub(crate) fn get_max_transfer_size(&mut self, secured: bool) -> usize {
+        let mut max_size = self.common.get_max_transport_message_size(secured);
+        if let Some(req_transfer_size) = self.common.get_req_data_transfer_size() {
+            max_size = max_size.min(req_transfer_size);
+        }
+        if let Some(req_max_size) = self.common.get_req_max_message_size() {
+            max_size = max_size.min(req_max_size);
+        }
+        max_size
+    }
+
+    fn write_spdm_error_response_too_large(
+        &mut self,
+        secured: bool,
+        response_size: usize,
+        writer: &mut Writer,
+    ) {
+        error!(
+            "!!! response size {} exceeds requester or transport limit {} !!!\n",
+            response_size,
+            self.get_max_response_size(secured)
+        );
+        self.write_spdm_error_with_ext_data(
+            SpdmErrorCode::SpdmErrorResponseTooLarge,
+            0,
+            SpdmErrorResponseExtData::SpdmErrorExtDataResponseTooLarge(
+                SpdmErrorResponseTooLargeExtData {
+                    response_size: response_size as u32,
+                },
+            ),
+            writer,
+        );
+    }
+
+    /// Reject a request exceeding the responder's own DataTransferSize or
+    /// MaxSPDMmsgSize with ERROR(RequestTooLarge). Returns true if rejected.
+    fn reject_large_request(&mut self, session_id: Option<u32>, bytes: &[u8]) -> bool {
+        let max_size = match (
+            self.common.get_rsp_data_transfer_size(),
+            self.common.get_rsp_max_message_size(),
+        ) {
+            (Some(transfer_size), Some(max_size)) => Some(transfer_size.min(max_size)),
+            _ => None,
+        };
+        match max_size {
+            Some(max_size) if bytes.len() > max_size => {
+                error!(
+                    "!!! request size {} exceeds responder limit {} !!!\n",
+                    bytes.len(),
+                    max_size
+                );
+                let mut send_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+                let mut writer = Writer::init(&mut send_buffer);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorRequestTooLarge, 0, &mut writer);
+                let used = writer.used();
+                let _ = match session_id {
+                    Some(session_id) => {
+                        self.send_secured_message(session_id, &send_buffer[..used], false)
+                    }
+                    None => self.send_message(&send_buffer[..used]),
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Apply the KEY_EXCHANGE termination policy before a runtime firmware update.
+    /// Sessions are kept only when the requester set the policy and the responder
+    /// preserves sessions across runtime updates. Returns the number torn down.
+    pub fn notify_spdm_runtime_update(&mut self) -> usize {
+        let preserves_sessions = self.common.config_info.runtime_update_preserves_sessions;
+        let mut count = 0;
+        for i in 0..self.common.session.len() {
+            let session = &mut self.common.session[i];
+            let session_id = session.get_session_id();
+            if session_id == 0 {
+                continue;
+            }
+            if !preserves_sessions || !session.is_termination_policy_set() {
+                let _ = session.teardown(session_id);
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Tear down the sessions the requester sent nothing in for max_idle
+    /// microseconds, to reclaim the slots of a requester that went away.
+    /// Returns the number torn down.
+    pub fn reap_idle_sessions(&mut self, max_idle: u64) -> usize {
+        self.reap_sessions(crate::time::now(), max_idle, false)
+    }
+
+    fn reap_sessions(&mut self, now: u64, max_idle: u64, without_heartbeat_only: bool) -> usize {
+        if max_idle == 0 {
+            return 0;
+        }
+        let mut count = 0;
+        for i in 0..self.common.session.len() {
+            let session = &mut self.common.session[i];
+            let session_id = session.get_session_id();
+            if session_id == 0 || (without_heartbeat_only && session.heartbeat_period != 0) {
+                continue;
+            }
+            if now.saturating_sub(session.get_last_activity()) >= max_idle {
+                info!("!!! session {:08x} idle, torn down !!!\n", session_id);
+                let _ = session.teardown(session_id);
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Save the negotiated state and the established sessions encrypted under
+    /// wrapping_key, before a warm reset or a suspend. Returns the size used
+    /// in buffer, SPDM_SAVED_STATE_MAX_SIZE at most. The sessions go with
+    /// the saved state, see common::saved_state for generation.
+    pub fn save_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        buffer: &mut [u8],
+    ) -> SpdmResult<usize> {
+        self.common.save_state(wrapping_key, generation, buffer)
+    }
+
+    /// Take over a state saved by save_state, right after the reset, if it
+    /// is of generation. Advance the anti-rollback counter once it is.
+    pub fn restore_state(
+        &mut self,
+        wrapping_key: &[u8],
+        generation: u64,
+        buffer: &[u8],
+    ) -> SpdmResult {
+        self.common.restore_state(wrapping_key, generation, buffer)
+    }
+
+    /// Transcript and parameters of the established session session_id, to
+    /// archive as evidence of the attestation it carries.
+    pub fn get_session_audit(&self, session_id: u32) -> SpdmResult<Box<SpdmSessionAudit>> {
+        self.common.get_session_audit(session_id, false)
+    }
+
+    /// Versions, capabilities and algorithms negotiated with the requester,
+    /// the requester cert chains retrieved and the sessions open.
+    pub fn get_connection_info(&self) -> SpdmConnectionInfo {
+        self.common.get_connection_info()
+    }
+
+    /// Hash of the M1/M2 transcript so far, to compare with the one the
+    /// requester logged.
+    pub fn get_m1m2_hash(&self) -> SpdmResult<SpdmDigestStruct> {
+        self.common.get_m1m2_hash()
+    }
+
+    /// TH2 of session session_id, once it is established.
+    pub fn get_session_transcript_hash(&self, session_id: u32) -> SpdmResult<SpdmDigestStruct> {
+        self.common.get_session_transcript_hash(session_id, false)
     }
 
     pub fn process_message(
         &mut self,
         timeout: usize,
+    ) -> Result<bool, (usize, [u8; config::DATA_TRANSFER_SIZE])> {
+        let result = self.receive_and_dispatch_message(timeout);
+        // the requester keeps a session with heartbeat alive itself, it is
+        // gone once it missed twice the HeartbeatPeriod
+        let session_idle_timeout = self.common.config_info.session_idle_timeout;
+        self.reap_sessions(crate::time::now(), session_idle_timeout, true);
+        self.process_spdm_heartbeat_liveness();
+        self.sync_shared_state();
+        result
+    }
+
+    fn receive_and_dispatch_message(
+        &mut self,
+        timeout: usize,
     ) -> Result<bool, (usize, [u8; config::DATA_TRANSFER_SIZE])> {
         let mut receive_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         match self.receive_message(&mut receive_buffer[..], timeout) {
-            Ok((used, secured_message)) => {
+            Ok((offset, used, secured_message)) => {
+                let message = &receive_buffer[offset..(offset + used)];
                 if secured_message {
-                    let mut read = Reader::init(&receive_buffer[0..used]);
-                    let session_id = u32::read(&mut read).ok_or((used, receive_buffer))?;
+                    let mut read = Reader::init(message);
+                    let session_id = u32::read(&mut read)
+                        .ok_or_else(|| raw_message(receive_buffer, offset, used))?;
 
                     let spdm_session = self
                         .common
                         .get_session_via_id(session_id)
-                        .ok_or((used, receive_buffer))?;
+                        .ok_or_else(|| raw_message(receive_buffer, offset, used))?;
 
                     let mut app_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
 
-                    let decode_size = spdm_session.decode_spdm_secured_message(
-                        &receive_buffer[..used],
-                        &mut app_buffer,
-                        true,
-                    );
+                    let decode_size =
+                        spdm_session.decode_spdm_secured_message(message, &mut app_buffer, true);
                     if decode_size.is_err() {
-                        return Err((used, receive_buffer));
+                        if spdm_session.get_session_state()
+                            == SpdmSessionState::SpdmSessionEstablished
+                            && self.common.config_info.keep_session_on_decrypt_error
+                        {
+                            self.send_spdm_decrypt_error(session_id);
+                            return Ok(true);
+                        }
+                        return Err(raw_message(receive_buffer, offset, used));
                     }
                     let decode_size = decode_size.unwrap();
 
@@ -93,7 +401,7 @@ impl<'a> ResponderContext<'a> {
                         .transport_encap
                         .decap_app(&app_buffer[0..decode_size], &mut spdm_buffer);
                     match decap_result {
-                        Err(_) => Err((used, receive_buffer)),
+                        Err(_) => Err(raw_message(receive_buffer, offset, used)),
                         Ok((decode_size, is_app_message)) => {
                             if !is_app_message {
                                 Ok(self.dispatch_secured_message(
@@ -101,12 +409,15 @@ impl<'a> ResponderContext<'a> {
                                     &spdm_buffer[0..decode_size],
                                 ))
                             } else {
-                                Ok(self.dispatch_secured_app_message(session_id))
+                                Ok(self.dispatch_secured_app_message(
+                                    session_id,
+                                    &spdm_buffer[0..decode_size],
+                                ))
                             }
                         }
                     }
                 } else {
-                    Ok(self.dispatch_message(&receive_buffer[0..used]))
+                    Ok(self.dispatch_message(message))
                 }
             }
             Err(used) => Err((used, receive_buffer)),
@@ -117,17 +428,39 @@ impl<'a> ResponderContext<'a> {
     // whose value is not normal, will return Err to caller to handle the raw packet,
     // So can't swap transport_buffer and receive_buffer, even though it should be by
     // their name suggestion. (03.01.2022)
+    //
+    // Returns the offset of the message in receive_buffer, its size and
+    // whether it is a secured message. With a transport encap decapping in
+    // place the message stays where the transport message put it.
     fn receive_message(
         &mut self,
         receive_buffer: &mut [u8],
         timeout: usize,
-    ) -> Result<(usize, bool), usize> {
+    ) -> Result<(usize, usize, bool), usize> {
         info!("receive_message!\n");
 
-        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let used = self
+            .common
+            .receive_transport_message(receive_buffer, timeout)?;
 
-        let used = self.common.device_io.receive(receive_buffer, timeout)?;
+        if self
+            .common
+            .transport_encap
+            .get_encap_header_size()
+            .is_some()
+        {
+            let (offset, size, secured_message) = self
+                .common
+                .transport_encap
+                .decap_in_place(&receive_buffer[..used])
+                .map_err(|_| used)?;
+            if offset + size > used {
+                return Err(used);
+            }
+            return Ok((offset, size, secured_message));
+        }
 
+        let mut transport_buffer = [0u8; config::DATA_TRANSFER_SIZE];
         let (used, secured_message) = self
             .common
             .transport_encap
@@ -135,15 +468,24 @@ impl<'a> ResponderContext<'a> {
             .map_err(|_| used)?;
 
         receive_buffer[..used].copy_from_slice(&transport_buffer[..used]);
-        Ok((used, secured_message))
+        Ok((0, used, secured_message))
     }
 
     fn dispatch_secured_message(&mut self, session_id: u32, bytes: &[u8]) -> bool {
+        if self.reject_large_request(Some(session_id), bytes)
+            || self.defer_spdm_request(Some(session_id), bytes)
+        {
+            return true;
+        }
+        self.dispatch_secured_spdm_request(session_id, bytes)
+    }
+
+    pub(crate) fn dispatch_secured_spdm_request(&mut self, session_id: u32, bytes: &[u8]) -> bool {
         let mut reader = Reader::init(bytes);
         match SpdmMessageHeader::read(&mut reader) {
             Some(message_header) => match message_header.request_response_code {
                 SpdmRequestResponseCode::SpdmRequestResponseIfReady => {
-                    self.handle_spdm_respond_if_ready(bytes);
+                    self.handle_spdm_respond_if_ready(Some(session_id), bytes);
                     true
                 }
                 SpdmRequestResponseCode::SpdmRequestGetVersion => false,
@@ -195,6 +537,10 @@ impl<'a> ResponderContext<'a> {
                     self.handle_spdm_vendor_defined_request(session_id, bytes);
                     true
                 }
+                SpdmRequestResponseCode::SpdmRequestSetCertificate => {
+                    self.handle_spdm_set_certificate(Some(session_id), bytes);
+                    true
+                }
                 SpdmRequestResponseCode::SpdmResponseDigests => false,
                 SpdmRequestResponseCode::SpdmResponseCertificate => false,
                 SpdmRequestResponseCode::SpdmResponseChallengeAuth => false,
@@ -211,23 +557,99 @@ impl<'a> ResponderContext<'a> {
                 SpdmRequestResponseCode::SpdmResponseEndSessionAck => false,
                 SpdmRequestResponseCode::SpdmResponseError => false,
                 SpdmRequestResponseCode::SpdmResponseVendorDefinedResponse => false,
+                SpdmRequestResponseCode::SpdmResponseSetCertificateRsp => false,
+                SpdmRequestResponseCode::SpdmRequestChunkSend => {
+                    self.handle_spdm_chunk_send(Some(session_id), bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseChunkSendAck => false,
+                SpdmRequestResponseCode::SpdmRequestChunkGet => {
+                    self.handle_spdm_chunk_get(Some(session_id), bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseChunkResponse => false,
+                SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo => {
+                    self.handle_spdm_get_key_pair_info(Some(session_id), bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseKeyPairInfo => false,
+                SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo => {
+                    self.handle_spdm_set_key_pair_info(Some(session_id), bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck => false,
+                SpdmRequestResponseCode::SpdmRequestGetEndpointInfo => {
+                    self.handle_spdm_endpoint_info(Some(session_id), bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEndpointInfo => false,
+                SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes => {
+                    self.handle_spdm_get_supported_event_types(session_id, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseSupportedEventTypes => false,
+                SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes => {
+                    self.handle_spdm_subscribe_event_types(session_id, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck => false,
+                SpdmRequestResponseCode::SpdmRequestSendEvent => false,
+                SpdmRequestResponseCode::SpdmResponseEventAck => {
+                    self.handle_spdm_event_ack(session_id, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest => {
+                    self.handle_spdm_get_encapsulated_request(session_id, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest => false,
+                SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse => {
+                    self.handle_spdm_deliver_encapsulated_response(session_id, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck => false,
                 SpdmRequestResponseCode::Unknown(_) => false,
             },
             None => false,
         }
     }
 
-    fn dispatch_secured_app_message(&mut self, session_id: u32) -> bool {
-        debug!("Send app secured message!(PLDM)\n");
-        let _ = self.send_secured_message(session_id, M_SECURE_SESSION_RESPONSE, true);
+    fn dispatch_secured_app_message(&mut self, session_id: u32, bytes: &[u8]) -> bool {
+        let handler = match self.common.app_message_handler {
+            Some(handler) => handler,
+            None => {
+                debug!("Send app secured message!(PLDM)\n");
+                let _ = self.send_secured_message(session_id, M_SECURE_SESSION_RESPONSE, true);
+                return true;
+            }
+        };
+        let session = match self.common.get_session_via_id(session_id) {
+            Some(session) => session,
+            None => return false,
+        };
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        match handler(session_id, &mut session.app_data, bytes, &mut send_buffer) {
+            Ok(0) => {}
+            Ok(used) if used <= send_buffer.len() => {
+                let _ = self.send_secured_message(session_id, &send_buffer[..used], true);
+            }
+            _ => error!("!!! app message : handler failed !!!\n"),
+        }
         true
     }
     pub fn dispatch_message(&mut self, bytes: &[u8]) -> bool {
+        if self.reject_large_request(None, bytes) || self.defer_spdm_request(None, bytes) {
+            return true;
+        }
+        self.dispatch_spdm_request(bytes)
+    }
+
+    pub(crate) fn dispatch_spdm_request(&mut self, bytes: &[u8]) -> bool {
         let mut reader = Reader::init(bytes);
         match SpdmMessageHeader::read(&mut reader) {
             Some(message_header) => match message_header.request_response_code {
                 SpdmRequestResponseCode::SpdmRequestResponseIfReady => {
-                    self.handle_spdm_respond_if_ready(bytes);
+                    self.handle_spdm_respond_if_ready(None, bytes);
                     true
                 }
                 SpdmRequestResponseCode::SpdmRequestGetVersion => {
@@ -263,7 +685,9 @@ impl<'a> ResponderContext<'a> {
                     matches!(self.handle_spdm_key_exchange(bytes), Ok(_))
                 }
 
-                SpdmRequestResponseCode::SpdmRequestFinish => false,
+                SpdmRequestResponseCode::SpdmRequestFinish => {
+                    self.handle_spdm_finish_in_the_clear(bytes)
+                }
 
                 SpdmRequestResponseCode::SpdmRequestPskExchange => {
                     matches!(self.handle_spdm_psk_exchange(bytes), Ok(_))
@@ -277,6 +701,10 @@ impl<'a> ResponderContext<'a> {
 
                 SpdmRequestResponseCode::SpdmRequestEndSession => false,
                 SpdmRequestResponseCode::SpdmRequestVendorDefinedRequest => false,
+                SpdmRequestResponseCode::SpdmRequestSetCertificate => {
+                    self.handle_spdm_set_certificate(None, bytes);
+                    true
+                }
                 SpdmRequestResponseCode::SpdmResponseDigests => false,
                 SpdmRequestResponseCode::SpdmResponseCertificate => false,
                 SpdmRequestResponseCode::SpdmResponseChallengeAuth => false,
@@ -293,6 +721,48 @@ impl<'a> ResponderContext<'a> {
                 SpdmRequestResponseCode::SpdmResponseEndSessionAck => false,
                 SpdmRequestResponseCode::SpdmResponseError => false,
                 SpdmRequestResponseCode::SpdmResponseVendorDefinedResponse => false,
+                SpdmRequestResponseCode::SpdmResponseSetCertificateRsp => false,
+                SpdmRequestResponseCode::SpdmRequestChunkSend => {
+                    self.handle_spdm_chunk_send(None, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseChunkSendAck => false,
+                SpdmRequestResponseCode::SpdmRequestChunkGet => {
+                    self.handle_spdm_chunk_get(None, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseChunkResponse => false,
+                SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo => {
+                    self.handle_spdm_get_key_pair_info(None, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseKeyPairInfo => false,
+                SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo => {
+                    self.handle_spdm_set_key_pair_info(None, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck => false,
+                SpdmRequestResponseCode::SpdmRequestGetEndpointInfo => {
+                    self.handle_spdm_endpoint_info(None, bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEndpointInfo => false,
+                SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes => false,
+                SpdmRequestResponseCode::SpdmResponseSupportedEventTypes => false,
+                SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes => false,
+                SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck => false,
+                SpdmRequestResponseCode::SpdmRequestSendEvent => false,
+                SpdmRequestResponseCode::SpdmResponseEventAck => false,
+                SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest => {
+                    self.handle_spdm_basic_mut_auth_get_encapsulated_request(bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest => false,
+                SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse => {
+                    self.handle_spdm_basic_mut_auth_deliver_encapsulated_response(bytes);
+                    true
+                }
+                SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck => false,
                 SpdmRequestResponseCode::Unknown(_) => false,
             },
             None => false,
@@ -300,6 +770,17 @@ impl<'a> ResponderContext<'a> {
     }
 }
 
+// The message process_message fails on, moved to the start of the receive
+// buffer for the caller to handle the raw packet.
+fn raw_message(
+    mut receive_buffer: [u8; config::DATA_TRANSFER_SIZE],
+    offset: usize,
+    used: usize,
+) -> (usize, [u8; config::DATA_TRANSFER_SIZE]) {
+    receive_buffer.copy_within(offset..(offset + used), 0);
+    (used, receive_buffer)
+}
+
 #[cfg(all(test,))]
 mod tests_responder {
     use super::*;
@@ -424,6 +905,43 @@ mod tests_responder {
         assert!(status);
     }
     #[test]
+    fn test_case1_receive_message() {
+        let transport_message = &mut [0u8; 12];
+        let mut writer = Writer::init(transport_message);
+        let value = PciDoeMessageHeader {
+            vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
+            data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeSpdm,
+            payload_length: 4,
+        };
+        value.encode(&mut writer);
+        transport_message[8..].copy_from_slice(&[0x10, 0x84, 0x00, 0x00]);
+
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        shared_buffer.set_buffer(transport_message);
+
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        // the request stays behind the PCI-DOE header
+        let mut receive_buffer = [0u8; config::DATA_TRANSFER_SIZE];
+        let (offset, used, secured_message) = context
+            .receive_message(&mut receive_buffer[..], ST1)
+            .unwrap();
+        assert_eq!((offset, used, secured_message), (8, 4, false));
+        assert_eq!(
+            &receive_buffer[offset..(offset + used)],
+            &[0x10, 0x84, 0x00, 0x00]
+        );
+    }
+    #[test]
     fn test_case0_process_message() {
         let receive_buffer = &mut [0u8; 1024];
         let mut writer = Writer::init(receive_buffer);
@@ -465,6 +983,165 @@ mod tests_responder {
         assert!(status);
     }
     #[test]
+    fn test_case0_notify_spdm_runtime_update() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        context.common.session[0].setup(0xfffffffe).unwrap();
+        context.common.session[0].session_policy =
+            KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_VALUE;
+        context.common.session[1].setup(0xfffffffd).unwrap();
+        context.common.session[1].session_policy = 0;
+
+        context.common.config_info.runtime_update_preserves_sessions = true;
+        assert_eq!(context.notify_spdm_runtime_update(), 1);
+        assert_eq!(context.common.session[0].get_session_id(), 0xfffffffe);
+        assert_eq!(context.common.session[1].get_session_id(), 0);
+
+        context.common.config_info.runtime_update_preserves_sessions = false;
+        assert_eq!(context.notify_spdm_runtime_update(), 1);
+        assert_eq!(context.common.session[0].get_session_id(), 0);
+    }
+    #[test]
+    fn test_case0_reap_idle_sessions() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        context.common.session[0].setup(0xfffffffe).unwrap();
+        context.common.session[0].heartbeat_period = 0;
+        context.common.session[1].setup(0xfffffffd).unwrap();
+        context.common.session[1].heartbeat_period = 1;
+        let now = crate::time::now() + 10;
+
+        assert_eq!(context.reap_sessions(now, 0, false), 0);
+        assert_eq!(context.reap_sessions(now, 1000, false), 0);
+        assert_eq!(context.reap_sessions(now, 5, true), 1);
+        assert_eq!(context.common.session[0].get_session_id(), 0);
+        assert_eq!(context.common.session[1].get_session_id(), 0xfffffffd);
+        assert_eq!(context.reap_sessions(now, 5, false), 1);
+        assert_eq!(context.common.session[1].get_session_id(), 0);
+    }
+    #[test]
+    fn test_case0_limit_response_size() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.common.negotiate_info.req_data_transfer_size_sel = 64;
+        context.common.negotiate_info.req_max_spdm_msg_size_sel = 64;
+
+        let send_buffer = [0u8; 100];
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        assert_eq!(
+            context
+                .limit_response_size(&send_buffer[..64], false, &mut error_buffer)
+                .len(),
+            64
+        );
+
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        let response = context.limit_response_size(&send_buffer, false, &mut error_buffer);
+        let mut reader = Reader::init(response);
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseTooLarge);
+            assert_eq!(
+                payload.extended_data,
+                SpdmErrorResponseExtData::SpdmErrorExtDataResponseTooLarge(
+                    SpdmErrorResponseTooLargeExtData { response_size: 100 }
+                )
+            );
+        } else {
+            panic!("expected ERROR response");
+        }
+    }
+    #[test]
+    fn test_case1_limit_response_size() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        // session ID, length, application data length and MAC
+        let max_secured_size = config::MAX_SPDM_MESSAGE_BUFFER_SIZE - 24;
+        assert_eq!(
+            context.common.get_max_transport_message_size(false),
+            config::MAX_SPDM_MESSAGE_BUFFER_SIZE
+        );
+        assert_eq!(
+            context.common.get_max_transport_message_size(true),
+            max_secured_size
+        );
+        assert_eq!(
+            context.common.get_max_cert_portion_len(true) as usize,
+            config::MAX_SPDM_CERT_PORTION_LEN.min(max_secured_size - 8)
+        );
+
+        let send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        assert_eq!(
+            context
+                .limit_response_size(&send_buffer, false, &mut error_buffer)
+                .len(),
+            config::MAX_SPDM_MESSAGE_BUFFER_SIZE
+        );
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        assert_eq!(
+            context
+                .limit_response_size(&send_buffer[..max_secured_size], true, &mut error_buffer)
+                .len(),
+            max_secured_size
+        );
+        let mut error_buffer = [0u8; SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE];
+        let response = context.limit_response_size(
+            &send_buffer[..max_secured_size + 1],
+            true,
+            &mut error_buffer,
+        );
+        assert_eq!(response.len(), SPDM_RESPONSE_TOO_LARGE_ERROR_SIZE);
+    }
+    #[test]
     #[should_panic(expected = "not implemented")]
     fn test_case0_dispatch_secured_message() {
         let (config_info, provision_info) = create_info();
@@ -480,6 +1157,7 @@ mod tests_responder {
         );
 
         crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        crate::secret::register(SECRET_IMPL.clone());
 
         context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         context.common.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
@@ -496,7 +1174,7 @@ mod tests_responder {
             SpdmAeadAlgo::AES_256_GCM,
             SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
         );
-        context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
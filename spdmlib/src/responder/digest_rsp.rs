@@ -7,9 +7,6 @@ use crate::crypto;
 use crate::message::*;
 use crate::protocol::*;
 use crate::responder::*;
-extern crate alloc;
-use crate::protocol::gen_array_clone;
-use alloc::boxed::Box;
 
 impl<'a> ResponderContext<'a> {
     pub fn handle_spdm_digest(&mut self, bytes: &[u8], _session_id: Option<u32>) {
@@ -50,7 +47,14 @@ impl<'a> ResponderContext<'a> {
             &bytes[..reader.used()],
         );
 
-        let digest_size = self.common.negotiate_info.base_hash_sel.get_size();
+        let digests = match self.common.get_my_digests() {
+            Some(digests) => digests,
+            None => {
+                error!("!!! get_digests : no cert chain provisioned !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+        };
 
         info!("send spdm digest\n");
         let response = SpdmMessage {
@@ -58,31 +62,12 @@ impl<'a> ResponderContext<'a> {
                 version: self.common.negotiate_info.spdm_version_sel,
                 request_response_code: SpdmRequestResponseCode::SpdmResponseDigests,
             },
-            payload: SpdmMessagePayload::SpdmDigestsResponse(SpdmDigestsResponsePayload {
-                slot_mask: 0x1,
-                slot_count: 1u8,
-                digests: gen_array_clone(
-                    SpdmDigestStruct {
-                        data_size: digest_size as u16,
-                        data: Box::new([0xffu8; SPDM_MAX_HASH_SIZE]),
-                    },
-                    SPDM_MAX_SLOT_NUMBER,
-                ),
-            }),
+            payload: SpdmMessagePayload::SpdmDigestsResponse(digests),
         };
         response.spdm_encode(&mut self.common, writer);
-
-        let my_cert_chain = self.common.provision_info.my_cert_chain.as_ref().unwrap();
-        let cert_chain_hash = crypto::hash::hash_all(
-            self.common.negotiate_info.base_hash_sel,
-            my_cert_chain.as_ref(),
-        )
-        .unwrap();
-
-        // patch the message before send
-        let used = writer.used();
-        writer.mut_used_slice()[(used - cert_chain_hash.data_size as usize)..used]
-            .copy_from_slice(cert_chain_hash.as_ref());
+        if self.check_response_size(false, writer) {
+            return;
+        }
 
         #[cfg(not(feature = "hash-update"))]
         self.common
@@ -121,7 +106,7 @@ mod tests_responder {
             config_info,
             provision_info,
         );
-        context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
@@ -140,4 +125,109 @@ mod tests_responder {
         let bytes = &mut [0u8; 1024];
         context.handle_spdm_digest(bytes, None);
     }
+
+    #[test]
+    fn test_case1_handle_spdm_digest() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        let mut slot3_cert_chain = SpdmCertChainData {
+            data_size: 256u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        };
+        slot3_cert_chain.data[0] = 0x33;
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
+            data_size: 512u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        });
+        context.common.provision_info.my_cert_chain[3] = Some(slot3_cert_chain.clone());
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        context.common.runtime_info.message_m =
+            Some(crypto::hash::hash_ctx_init(SpdmBaseHashAlgo::TPM_ALG_SHA_384).unwrap());
+
+        let bytes = &mut [0u8; 4];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetDigests,
+        };
+        value.encode(&mut writer);
+
+        let response = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(response);
+        context.write_spdm_digest_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message: SpdmMessage =
+            SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        if let SpdmMessagePayload::SpdmDigestsResponse(payload) = &spdm_message.payload {
+            assert_eq!(payload.slot_mask, 0x9);
+            assert_eq!(payload.slot_count, 2);
+            let slot3_hash = crypto::hash::hash_all(
+                SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+                slot3_cert_chain.as_ref(),
+            )
+            .unwrap();
+            assert_eq!(payload.digests[1].as_ref(), slot3_hash.as_ref());
+        } else {
+            panic!("unexpected response");
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "hash-update"))]
+    fn test_case2_handle_spdm_digest_response_too_large() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
+            data_size: 512u16,
+            data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
+        });
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        // DIGESTS takes 4 + 48 bytes
+        context.common.negotiate_info.req_data_transfer_size_sel = 42;
+        context.common.negotiate_info.req_max_spdm_msg_size_sel = 42;
+
+        let bytes = &mut [0u8; 4];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetDigests,
+        };
+        value.encode(&mut writer);
+
+        let response = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(response);
+        context.write_spdm_digest_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message: SpdmMessage =
+            SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseTooLarge);
+        } else {
+            panic!("unexpected response");
+        }
+        // the DIGESTS never sent is not in the transcript
+        assert_eq!(context.common.runtime_info.message_b.as_ref(), &bytes[..]);
+    }
 }
@@ -0,0 +1,1245 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionState;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
+use crate::crypto;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::responder::*;
+
+/// Requests the responder can tunnel to the requester in an encapsulated flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpdmEncapRequest {
+    GetDigests,
+    GetCertificate(u8),
+    KeyUpdate(SpdmKeyUpdateOperation),
+    Challenge(u8),
+}
+
+// Track the encapsulated requests the responder issued to the requester.
+#[derive(Debug, Clone, Default)]
+pub struct SpdmEncapContext {
+    pub session_id: u32,
+    pub request_id: u8,
+    pub last_request: SpdmRequestResponseCode,
+    pub pending_request: Option<SpdmEncapRequest>,
+    pub req_slot_id: u8,
+    pub cert_offset: u16,
+    pub key_update_operation: SpdmKeyUpdateOperation,
+    pub challenge_nonce: [u8; SPDM_NONCE_SIZE],
+    pub basic_mut_auth: bool,
+}
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_get_encapsulated_request(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_encapsulated_request_response(session_id, bytes, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+    }
+
+    pub fn write_spdm_encapsulated_request_response(
+        &mut self,
+        session_id: u32,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        let request = if self
+            .get_encap_mut_auth_requested(session_id)
+            .contains(SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_ENCAP_REQUEST)
+        {
+            Some(SpdmEncapRequest::GetDigests)
+        } else if self.encap_context.session_id == session_id && !self.encap_context.basic_mut_auth
+        {
+            self.encap_context.pending_request
+        } else {
+            None
+        };
+        let request = if let Some(request) = request {
+            request
+        } else {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return;
+        };
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let get_encapsulated_request =
+            SpdmGetEncapsulatedRequestPayload::spdm_read(&mut self.common, &mut reader);
+        if let Some(get_encapsulated_request) = get_encapsulated_request {
+            debug!(
+                "!!! get_encapsulated_request : {:02x?}\n",
+                get_encapsulated_request
+            );
+        } else {
+            error!("!!! get_encapsulated_request : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        self.encap_context = SpdmEncapContext {
+            session_id,
+            request_id: 1,
+            ..Default::default()
+        };
+        if self.start_spdm_encap_request(session_id, request).is_err() {
+            error!("!!! encapsulated_request : fail !!!\n");
+            self.encap_context = SpdmEncapContext::default();
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
+
+        info!("send spdm encapsulated_request\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest,
+            },
+            payload: SpdmMessagePayload::SpdmEncapsulatedRequestResponse(
+                SpdmEncapsulatedRequestPayload {
+                    request_id: self.encap_context.request_id,
+                },
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+        self.write_spdm_encap_request(request, writer);
+    }
+
+    pub fn handle_spdm_deliver_encapsulated_response(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_encapsulated_response_ack_response(session_id, bytes, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+    }
+
+    pub fn write_spdm_encapsulated_response_ack_response(
+        &mut self,
+        session_id: u32,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        if self.encap_context.session_id != session_id
+            || self.encap_context.basic_mut_auth
+            || self.encap_context.last_request == SpdmRequestResponseCode::Unknown(0)
+        {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let deliver_encapsulated_response =
+            SpdmDeliverEncapsulatedResponsePayload::spdm_read(&mut self.common, &mut reader);
+        let deliver_encapsulated_response =
+            if let Some(deliver_encapsulated_response) = deliver_encapsulated_response {
+                debug!(
+                    "!!! deliver_encapsulated_response : {:02x?}\n",
+                    deliver_encapsulated_response
+                );
+                deliver_encapsulated_response
+            } else {
+                error!("!!! deliver_encapsulated_response : fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            };
+
+        if self.encap_context.request_id != deliver_encapsulated_response.request_id {
+            error!("!!! deliver_encapsulated_response : request_id mismatch !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        let encap_response = &bytes[reader.used()..];
+        let ack_request_id = deliver_encapsulated_response.request_id;
+
+        let next_request = match self.process_spdm_encap_response(session_id, encap_response) {
+            Ok(next_request) => next_request,
+            Err(_) => {
+                error!("!!! encapsulated response : fail !!!\n");
+                self.encap_context = SpdmEncapContext::default();
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
+
+        match next_request {
+            Some(next_request) => {
+                let request_id = self.encap_context.request_id.wrapping_add(1);
+                self.encap_context.request_id = request_id;
+                if self
+                    .start_spdm_encap_request(session_id, next_request)
+                    .is_err()
+                {
+                    error!("!!! encapsulated_request : fail !!!\n");
+                    self.encap_context = SpdmEncapContext::default();
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                    return;
+                }
+                self.write_spdm_encap_response_ack(
+                    request_id,
+                    SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypePresent,
+                    ack_request_id,
+                    writer,
+                );
+                self.write_spdm_encap_request(next_request, writer);
+            }
+            None => {
+                let req_slot_id = self.encap_context.req_slot_id;
+                self.encap_context = SpdmEncapContext::default();
+                if self.get_encap_mut_auth_requested(session_id).is_empty() {
+                    self.write_spdm_encap_response_ack(
+                        0,
+                        SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeAbsent,
+                        ack_request_id,
+                        writer,
+                    );
+                } else {
+                    self.write_spdm_encap_response_ack(
+                        0,
+                        SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeReqSlotNumber,
+                        ack_request_id,
+                        writer,
+                    );
+                    req_slot_id.encode(writer);
+                }
+            }
+        }
+    }
+
+    pub fn handle_spdm_basic_mut_auth_get_encapsulated_request(&mut self, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_basic_mut_auth_encapsulated_request_response(bytes, &mut writer);
+        let _ = self.send_message(writer.used_slice());
+    }
+
+    // Basic mutual authentication runs out of session after a CHALLENGE_AUTH
+    // with BASIC_MUT_AUTH_REQ: GET_DIGESTS, GET_CERTIFICATE, then CHALLENGE.
+    pub fn write_spdm_basic_mut_auth_encapsulated_request_response(
+        &mut self,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        let request = if self.encap_context.basic_mut_auth {
+            self.encap_context.pending_request
+        } else {
+            None
+        };
+        let request = if let Some(request) = request {
+            request
+        } else {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return;
+        };
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let get_encapsulated_request =
+            SpdmGetEncapsulatedRequestPayload::spdm_read(&mut self.common, &mut reader);
+        if let Some(get_encapsulated_request) = get_encapsulated_request {
+            debug!(
+                "!!! get_encapsulated_request : {:02x?}\n",
+                get_encapsulated_request
+            );
+        } else {
+            error!("!!! get_encapsulated_request : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        self.encap_context = SpdmEncapContext {
+            request_id: 1,
+            basic_mut_auth: true,
+            ..Default::default()
+        };
+        self.common.runtime_info.message_mut_c.reset_message();
+        let mut encap_request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let encap_request_used =
+            match self.encode_spdm_basic_mut_auth_encap_request(request, &mut encap_request) {
+                Ok(used) => used,
+                Err(_) => {
+                    error!("!!! encapsulated_request : fail !!!\n");
+                    self.encap_context = SpdmEncapContext::default();
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                    return;
+                }
+            };
+
+        info!("send spdm encapsulated_request\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest,
+            },
+            payload: SpdmMessagePayload::SpdmEncapsulatedRequestResponse(
+                SpdmEncapsulatedRequestPayload {
+                    request_id: self.encap_context.request_id,
+                },
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+        writer.extend_from_slice(&encap_request[..encap_request_used]);
+    }
+
+    pub fn handle_spdm_basic_mut_auth_deliver_encapsulated_response(&mut self, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_basic_mut_auth_encapsulated_response_ack_response(bytes, &mut writer);
+        let _ = self.send_message(writer.used_slice());
+    }
+
+    pub fn write_spdm_basic_mut_auth_encapsulated_response_ack_response(
+        &mut self,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        if !self.encap_context.basic_mut_auth
+            || self.encap_context.last_request == SpdmRequestResponseCode::Unknown(0)
+        {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnexpectedRequest, 0, writer);
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let deliver_encapsulated_response =
+            SpdmDeliverEncapsulatedResponsePayload::spdm_read(&mut self.common, &mut reader);
+        let deliver_encapsulated_response =
+            if let Some(deliver_encapsulated_response) = deliver_encapsulated_response {
+                debug!(
+                    "!!! deliver_encapsulated_response : {:02x?}\n",
+                    deliver_encapsulated_response
+                );
+                deliver_encapsulated_response
+            } else {
+                error!("!!! deliver_encapsulated_response : fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            };
+
+        if self.encap_context.request_id != deliver_encapsulated_response.request_id {
+            error!("!!! deliver_encapsulated_response : request_id mismatch !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        let encap_response = &bytes[reader.used()..];
+        let ack_request_id = deliver_encapsulated_response.request_id;
+
+        let next_request = match self.process_spdm_basic_mut_auth_response(encap_response) {
+            Ok(next_request) => next_request,
+            Err(_) => {
+                error!("!!! encapsulated response : fail !!!\n");
+                self.encap_context = SpdmEncapContext::default();
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
+
+        match next_request {
+            Some(next_request) => {
+                let request_id = self.encap_context.request_id.wrapping_add(1);
+                self.encap_context.request_id = request_id;
+                let mut encap_request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+                let encap_request_used = match self
+                    .encode_spdm_basic_mut_auth_encap_request(next_request, &mut encap_request)
+                {
+                    Ok(used) => used,
+                    Err(_) => {
+                        error!("!!! encapsulated_request : fail !!!\n");
+                        self.encap_context = SpdmEncapContext::default();
+                        self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                        return;
+                    }
+                };
+                self.write_spdm_encap_response_ack(
+                    request_id,
+                    SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypePresent,
+                    ack_request_id,
+                    writer,
+                );
+                writer.extend_from_slice(&encap_request[..encap_request_used]);
+            }
+            None => {
+                self.encap_context = SpdmEncapContext::default();
+                self.write_spdm_encap_response_ack(
+                    0,
+                    SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeAbsent,
+                    ack_request_id,
+                    writer,
+                );
+            }
+        }
+    }
+
+    /// Ask the requester for basic mutual authentication in the next
+    /// CHALLENGE_AUTH. Both sides must support MUT_AUTH_CAP and ENCAP_CAP,
+    /// and SPDM 1.1 at most be negotiated: SPDM 1.2 dropped it.
+    pub fn is_basic_mut_auth_supported(&self) -> bool {
+        let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
+        self.common.config_info.basic_mut_auth_requested
+            && spdm_version_sel.get_u8() <= SpdmVersion::SpdmVersion11.get_u8()
+            && self.common.negotiate_info.req_capabilities_sel.contains(
+                SpdmRequestCapabilityFlags::MUT_AUTH_CAP | SpdmRequestCapabilityFlags::ENCAP_CAP,
+            )
+            && self.common.negotiate_info.rsp_capabilities_sel.contains(
+                SpdmResponseCapabilityFlags::MUT_AUTH_CAP | SpdmResponseCapabilityFlags::ENCAP_CAP,
+            )
+    }
+
+    /// The requester proved the possession of its private key in basic
+    /// mutual authentication since the last CHALLENGE.
+    pub fn is_basic_mut_auth_verified(&self) -> bool {
+        self.common.runtime_info.basic_mut_auth_verified
+    }
+
+    // Start the basic mutual authentication flow, the requester is expected
+    // to send GET_ENCAPSULATED_REQUEST after the CHALLENGE_AUTH.
+    pub fn init_spdm_basic_mut_auth(&mut self) {
+        self.encap_context = SpdmEncapContext {
+            pending_request: Some(SpdmEncapRequest::GetDigests),
+            basic_mut_auth: true,
+            ..Default::default()
+        };
+        self.common.runtime_info.message_mut_c.reset_message();
+    }
+
+    /// Queue a request to be tunneled to the requester of the established
+    /// session_id. It is sent when the requester issues GET_ENCAPSULATED_REQUEST.
+    pub fn set_spdm_encap_request(
+        &mut self,
+        session_id: u32,
+        request: SpdmEncapRequest,
+    ) -> SpdmResult {
+        if !self
+            .common
+            .negotiate_info
+            .req_capabilities_sel
+            .contains(SpdmRequestCapabilityFlags::ENCAP_CAP)
+            || !self
+                .common
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::ENCAP_CAP)
+        {
+            return spdm_result_err!(EINVAL);
+        }
+        match request {
+            SpdmEncapRequest::GetDigests => {}
+            SpdmEncapRequest::GetCertificate(slot_id) => {
+                if slot_id as usize >= SPDM_MAX_SLOT_NUMBER {
+                    return spdm_result_err!(EINVAL);
+                }
+            }
+            SpdmEncapRequest::KeyUpdate(key_update_operation) => {
+                if !self
+                    .common
+                    .negotiate_info
+                    .req_capabilities_sel
+                    .contains(SpdmRequestCapabilityFlags::KEY_UPD_CAP)
+                {
+                    return spdm_result_err!(EINVAL);
+                }
+                // the responder only updates its own direction
+                if key_update_operation != SpdmKeyUpdateOperation::SpdmUpdateSingleKey
+                    && key_update_operation != SpdmKeyUpdateOperation::SpdmVerifyNewKey
+                {
+                    return spdm_result_err!(EINVAL);
+                }
+            }
+            // CHALLENGE is only used by basic mutual authentication, out of session
+            SpdmEncapRequest::Challenge(_) => return spdm_result_err!(EINVAL),
+        }
+
+        let session = self.common.get_session(session_id)?;
+        if session.get_session_state() != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+        if self.encap_context.last_request != SpdmRequestResponseCode::Unknown(0) {
+            return spdm_result_err!(EBUSY);
+        }
+
+        self.encap_context = SpdmEncapContext {
+            session_id,
+            pending_request: Some(request),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    // Start the encapsulated flow for a requester that already sent its DIGESTS
+    // (MUT_AUTH_REQ_WITH_GET_DIGESTS), the implicit request uses request_id 0.
+    pub fn init_spdm_encap_get_digests(&mut self, session_id: u32) {
+        self.encap_context = SpdmEncapContext {
+            session_id,
+            request_id: 0,
+            last_request: SpdmRequestResponseCode::SpdmRequestGetDigests,
+            ..Default::default()
+        };
+    }
+
+    // Record the request about to be sent, the new keys of a KEY_UPDATE
+    // are created here and activated once the KEY_UPDATE_ACK is received.
+    fn start_spdm_encap_request(
+        &mut self,
+        session_id: u32,
+        request: SpdmEncapRequest,
+    ) -> SpdmResult {
+        match request {
+            SpdmEncapRequest::GetDigests => {
+                self.encap_context.last_request = SpdmRequestResponseCode::SpdmRequestGetDigests;
+            }
+            SpdmEncapRequest::GetCertificate(slot_id) => {
+                self.encap_context.last_request =
+                    SpdmRequestResponseCode::SpdmRequestGetCertificate;
+                self.encap_context.req_slot_id = slot_id;
+            }
+            SpdmEncapRequest::KeyUpdate(key_update_operation) => {
+                if key_update_operation == SpdmKeyUpdateOperation::SpdmUpdateSingleKey {
+                    let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
+                    let session = self.common.get_session_mut(session_id)?;
+                    session.create_data_secret_update(spdm_version_sel, false, true)?;
+                }
+                self.encap_context.last_request = SpdmRequestResponseCode::SpdmRequestKeyUpdate;
+                self.encap_context.key_update_operation = key_update_operation;
+            }
+            SpdmEncapRequest::Challenge(slot_id) => {
+                self.common
+                    .get_random(&mut self.encap_context.challenge_nonce)?;
+                self.encap_context.last_request = SpdmRequestResponseCode::SpdmRequestChallenge;
+                self.encap_context.req_slot_id = slot_id;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_spdm_encap_request(&mut self, request: SpdmEncapRequest, writer: &mut Writer) {
+        match request {
+            SpdmEncapRequest::GetDigests => self.write_spdm_encap_get_digests(writer),
+            SpdmEncapRequest::GetCertificate(_) => self.write_spdm_encap_get_certificate(writer),
+            SpdmEncapRequest::KeyUpdate(_) => self.write_spdm_encap_key_update(writer),
+            SpdmEncapRequest::Challenge(_) => self.write_spdm_encap_challenge(writer),
+        }
+    }
+
+    // Return the request to send next, or None once the flow is complete.
+    fn process_spdm_encap_response(
+        &mut self,
+        session_id: u32,
+        encap_response: &[u8],
+    ) -> SpdmResult<Option<SpdmEncapRequest>> {
+        let mut_auth = !self.get_encap_mut_auth_requested(session_id).is_empty();
+        match self.encap_context.last_request {
+            SpdmRequestResponseCode::SpdmRequestGetDigests => {
+                let req_slot_id = self.handle_spdm_encap_digests(encap_response)?;
+                if mut_auth {
+                    self.encap_context.cert_offset = 0;
+                    Ok(Some(SpdmEncapRequest::GetCertificate(req_slot_id)))
+                } else {
+                    Ok(None)
+                }
+            }
+            SpdmRequestResponseCode::SpdmRequestGetCertificate => {
+                let req_slot_id = self.encap_context.req_slot_id;
+                if self.handle_spdm_encap_certificate(encap_response)? != 0 {
+                    return Ok(Some(SpdmEncapRequest::GetCertificate(req_slot_id)));
+                }
+                self.verify_spdm_encap_certificate_chain(req_slot_id)?;
+                info!(
+                    "requester certificate chain received in slot {}\n",
+                    req_slot_id
+                );
+                if mut_auth {
+                    let session = self.common.get_session_mut(session_id)?;
+                    session.req_slot_id = req_slot_id;
+                }
+                Ok(None)
+            }
+            SpdmRequestResponseCode::SpdmRequestKeyUpdate => {
+                self.handle_spdm_encap_key_update_ack(session_id, encap_response)?;
+                Ok(None)
+            }
+            _ => spdm_result_err!(EINVAL),
+        }
+    }
+
+    // Encode the request into buf, it is part of the basic mutual authentication transcript.
+    fn encode_spdm_basic_mut_auth_encap_request(
+        &mut self,
+        request: SpdmEncapRequest,
+        buf: &mut [u8],
+    ) -> SpdmResult<usize> {
+        self.start_spdm_encap_request(0, request)?;
+        let mut writer = Writer::init(buf);
+        self.write_spdm_encap_request(request, &mut writer);
+        self.common
+            .runtime_info
+            .message_mut_c
+            .append_message(writer.used_slice())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        Ok(writer.used())
+    }
+
+    // Return the request to send next, or None once the requester is authenticated.
+    fn process_spdm_basic_mut_auth_response(
+        &mut self,
+        encap_response: &[u8],
+    ) -> SpdmResult<Option<SpdmEncapRequest>> {
+        match self.encap_context.last_request {
+            SpdmRequestResponseCode::SpdmRequestGetDigests => {
+                let req_slot_id = self.handle_spdm_encap_digests(encap_response)?;
+                self.common
+                    .runtime_info
+                    .message_mut_c
+                    .append_message(encap_response)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+                self.encap_context.cert_offset = 0;
+                Ok(Some(SpdmEncapRequest::GetCertificate(req_slot_id)))
+            }
+            SpdmRequestResponseCode::SpdmRequestGetCertificate => {
+                let req_slot_id = self.encap_context.req_slot_id;
+                let remainder_length = self.handle_spdm_encap_certificate(encap_response)?;
+                self.common
+                    .runtime_info
+                    .message_mut_c
+                    .append_message(encap_response)
+                    .ok_or_else(|| spdm_err!(ENOMEM))?;
+                if remainder_length != 0 {
+                    return Ok(Some(SpdmEncapRequest::GetCertificate(req_slot_id)));
+                }
+                self.verify_spdm_encap_certificate_chain(req_slot_id)?;
+                Ok(Some(SpdmEncapRequest::Challenge(req_slot_id)))
+            }
+            SpdmRequestResponseCode::SpdmRequestChallenge => {
+                self.handle_spdm_encap_challenge_auth(encap_response)?;
+                info!("requester basic mutual authentication pass\n");
+                self.common.runtime_info.basic_mut_auth_verified = true;
+                Ok(None)
+            }
+            _ => spdm_result_err!(EINVAL),
+        }
+    }
+
+    fn get_encap_mut_auth_requested(&self, session_id: u32) -> SpdmKeyExchangeMutAuthAttributes {
+        match self.common.get_immutable_session_via_id(session_id) {
+            Some(session)
+                if session.get_session_state() == SpdmSessionState::SpdmSessionHandshaking =>
+            {
+                session.mut_auth_requested
+            }
+            _ => SpdmKeyExchangeMutAuthAttributes::empty(),
+        }
+    }
+
+    fn write_spdm_encap_response_ack(
+        &mut self,
+        request_id: u8,
+        payload_type: SpdmEncapsulatedResponseAckPayloadType,
+        ack_request_id: u8,
+        writer: &mut Writer,
+    ) {
+        info!("send spdm encapsulated_response_ack\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck,
+            },
+            payload: SpdmMessagePayload::SpdmEncapsulatedResponseAckResponse(
+                SpdmEncapsulatedResponseAckPayload {
+                    request_id,
+                    payload_type,
+                    ack_request_id,
+                },
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+
+    fn write_spdm_encap_get_digests(&mut self, writer: &mut Writer) {
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetDigests,
+            },
+            payload: SpdmMessagePayload::SpdmGetDigestsRequest(SpdmGetDigestsRequestPayload {}),
+        };
+        request.spdm_encode(&mut self.common, writer);
+    }
+
+    fn write_spdm_encap_get_certificate(&mut self, writer: &mut Writer) {
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestGetCertificate,
+            },
+            payload: SpdmMessagePayload::SpdmGetCertificateRequest(
+                SpdmGetCertificateRequestPayload {
+                    slot_id: self.encap_context.req_slot_id,
+                    offset: self.encap_context.cert_offset,
+                    length: config::MAX_SPDM_CERT_PORTION_LEN as u16,
+                },
+            ),
+        };
+        request.spdm_encode(&mut self.common, writer);
+    }
+
+    // Return the first slot the requester reported a certificate chain for.
+    fn handle_spdm_encap_digests(&mut self, bytes: &[u8]) -> SpdmResult<u8> {
+        let mut reader = Reader::init(bytes);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseDigests => {
+                    let digests =
+                        SpdmDigestsResponsePayload::spdm_read(&mut self.common, &mut reader)
+                            .ok_or_else(|| spdm_err!(Codec))?;
+                    debug!("!!! encapsulated digests : {:02x?}\n", digests);
+                    self.common.update_peer_key_info(&digests);
+                    (0..SPDM_MAX_SLOT_NUMBER as u8)
+                        .find(|slot_id| digests.slot_mask & (1 << slot_id) != 0)
+                        .ok_or_else(|| spdm_err!(EINVAL))
+                }
+                _ => spdm_result_err!(EINVAL),
+            },
+            None => spdm_result_err!(Codec),
+        }
+    }
+
+    // Return the remainder length of the requester certificate chain.
+    fn handle_spdm_encap_certificate(&mut self, bytes: &[u8]) -> SpdmResult<u16> {
+        let mut reader = Reader::init(bytes);
+        let certificate = match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseCertificate => {
+                    SpdmCertificateResponsePayload::spdm_read(&mut self.common, &mut reader)
+                        .ok_or_else(|| spdm_err!(Codec))?
+                }
+                _ => return spdm_result_err!(EINVAL),
+            },
+            None => return spdm_result_err!(Codec),
+        };
+        debug!("!!! encapsulated certificate : {:02x?}\n", certificate);
+
+        let slot_id = self.encap_context.req_slot_id;
+        let offset = self.encap_context.cert_offset;
+        if certificate.slot_id != slot_id {
+            error!("slot id is not match between requester and responder!\n");
+            return spdm_result_err!(EINVAL);
+        }
+        if certificate.portion_length as usize > config::MAX_SPDM_CERT_PORTION_LEN
+            || offset as usize + certificate.portion_length as usize
+                > config::MAX_SPDM_CERT_CHAIN_DATA_SIZE
+        {
+            return spdm_result_err!(ENOMEM);
+        }
+
+        if offset == 0 {
+            self.common.peer_info.peer_cert_chain[slot_id as usize] =
+                Some(SpdmCertChain::default());
+        }
+        let peer_cert_chain = self.common.peer_info.peer_cert_chain[slot_id as usize]
+            .as_mut()
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        peer_cert_chain.cert_chain.data
+            [(offset as usize)..(offset as usize + certificate.portion_length as usize)]
+            .copy_from_slice(&certificate.cert_chain[..(certificate.portion_length as usize)]);
+        peer_cert_chain.cert_chain.data_size = offset + certificate.portion_length;
+        self.common.peer_info.peer_cert_chain_hash[slot_id as usize] = None;
+
+        self.encap_context.cert_offset = offset + certificate.portion_length;
+        Ok(certificate.remainder_length)
+    }
+
+    fn write_spdm_encap_challenge(&mut self, writer: &mut Writer) {
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestChallenge,
+            },
+            payload: SpdmMessagePayload::SpdmChallengeRequest(SpdmChallengeRequestPayload {
+                slot_id: self.encap_context.req_slot_id,
+                measurement_summary_hash_type:
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                nonce: SpdmNonceStruct {
+                    data: self.encap_context.challenge_nonce,
+                },
+            }),
+        };
+        request.spdm_encode(&mut self.common, writer);
+    }
+
+    // Verify the CHALLENGE_AUTH signed by the requester with the certificate
+    // chain retrieved by the encapsulated GET_CERTIFICATE.
+    fn handle_spdm_encap_challenge_auth(&mut self, bytes: &[u8]) -> SpdmResult {
+        let mut reader = Reader::init(bytes);
+        match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseChallengeAuth => {}
+                _ => return spdm_result_err!(EINVAL),
+            },
+            None => return spdm_result_err!(Codec),
+        }
+
+        self.common.runtime_info.need_measurement_summary_hash = false;
+        self.common.runtime_info.req_challenge_auth = true;
+        let challenge_auth =
+            SpdmChallengeAuthResponsePayload::spdm_read(&mut self.common, &mut reader);
+        self.common.runtime_info.req_challenge_auth = false;
+        let challenge_auth = challenge_auth.ok_or_else(|| spdm_err!(Codec))?;
+        debug!(
+            "!!! encapsulated challenge_auth : {:02x?}\n",
+            challenge_auth
+        );
+
+        let req_slot_id = self.encap_context.req_slot_id;
+        if challenge_auth.slot_id != req_slot_id {
+            error!("slot id is not match between requester and responder!\n");
+            return spdm_result_err!(EINVAL);
+        }
+        let cert_chain = &self.common.peer_info.peer_cert_chain[req_slot_id as usize]
+            .as_ref()
+            .ok_or_else(|| spdm_err!(EINVAL))?
+            .cert_chain;
+        let cert_chain_hash = crypto::hash::hash_all(
+            self.common.negotiate_info.base_hash_sel,
+            &cert_chain.data[..(cert_chain.data_size as usize)],
+        )
+        .ok_or_else(|| spdm_err!(Crypto))?;
+        if cert_chain_hash.as_ref() != challenge_auth.cert_chain_hash.as_ref() {
+            error!("requester cert_chain_hash - fail!\n");
+            return spdm_result_err!(VerificationFailed);
+        }
+
+        let used = reader.used();
+        let req_asym_size = self.common.negotiate_info.req_asym_sel.get_size() as usize;
+        self.common
+            .runtime_info
+            .message_mut_c
+            .append_message(&bytes[..(used - req_asym_size)])
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+        let message = self.common.calc_basic_mut_auth_sign_data()?;
+        self.verify_spdm_req_signature(req_slot_id, message.as_ref(), &challenge_auth.signature)
+    }
+
+    fn write_spdm_encap_key_update(&mut self, writer: &mut Writer) {
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestKeyUpdate,
+            },
+            payload: SpdmMessagePayload::SpdmKeyUpdateRequest(SpdmKeyUpdateRequestPayload {
+                key_update_operation: self.encap_context.key_update_operation,
+                tag: self.encap_context.request_id,
+            }),
+        };
+        request.spdm_encode(&mut self.common, writer);
+    }
+
+    fn handle_spdm_encap_key_update_ack(&mut self, session_id: u32, bytes: &[u8]) -> SpdmResult {
+        let mut reader = Reader::init(bytes);
+        let key_update_ack = match SpdmMessageHeader::read(&mut reader) {
+            Some(message_header) => match message_header.request_response_code {
+                SpdmRequestResponseCode::SpdmResponseKeyUpdateAck => {
+                    SpdmKeyUpdateResponsePayload::spdm_read(&mut self.common, &mut reader)
+                }
+                _ => None,
+            },
+            None => None,
+        };
+        debug!(
+            "!!! encapsulated key_update_ack : {:02x?}\n",
+            key_update_ack
+        );
+
+        let key_update_operation = self.encap_context.key_update_operation;
+        let tag = self.encap_context.request_id;
+        let valid = match &key_update_ack {
+            Some(key_update_ack) => {
+                key_update_ack.key_update_operation == key_update_operation
+                    && key_update_ack.tag == tag
+            }
+            None => false,
+        };
+
+        if key_update_operation == SpdmKeyUpdateOperation::SpdmUpdateSingleKey {
+            let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
+            let session = self.common.get_session_mut(session_id)?;
+            session.activate_data_secret_update(spdm_version_sel, false, true, valid)?;
+            if valid {
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::KeyUpdated);
+            }
+        }
+
+        if valid {
+            Ok(())
+        } else {
+            error!("!!! encapsulated key_update_ack : fail !!!\n");
+            spdm_result_err!(EINVAL)
+        }
+    }
+
+    fn verify_spdm_encap_certificate_chain(&self, slot_id: u8) -> SpdmResult {
+        let cert_chain = &self.common.peer_info.peer_cert_chain[slot_id as usize]
+            .as_ref()
+            .ok_or_else(|| spdm_err!(EINVAL))?
+            .cert_chain;
+        let hash_size = self.common.negotiate_info.base_hash_sel.get_size() as usize;
+        if cert_chain.data_size as usize <= 4 + hash_size {
+            return spdm_result_err!(Codec);
+        }
+
+        let certs = &cert_chain.data[(4 + hash_size)..(cert_chain.data_size as usize)];
+        let (root_cert_begin, root_cert_end) =
+            crypto::cert_operation::get_cert_from_cert_chain(certs, 0)?;
+        let root_hash = crypto::hash::hash_all(
+            self.common.negotiate_info.base_hash_sel,
+            &certs[root_cert_begin..root_cert_end],
+        )
+        .ok_or_else(|| spdm_err!(Crypto))?;
+        if root_hash.as_ref() != &cert_chain.data[4..(4 + hash_size)] {
+            error!("root_hash - fail!\n");
+            return spdm_result_err!(VerificationFailed);
+        }
+
+        let required_ekus = self
+            .common
+            .config_info
+            .requester_cert_eku_policy
+            .required_ekus(true);
+        let validity_time = self.common.get_cert_validity_time()?;
+        crypto::cert_operation::verify_cert_chain(certs, required_ekus, validity_time)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::common::session::SpdmSession;
+    use crate::protocol::gen_array_clone;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::{Codec, Writer};
+
+    #[test]
+    fn test_case0_handle_spdm_get_encapsulated_request() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        context.common.session[0].setup(4294901758).unwrap();
+        context.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
+        context.common.session[0].mut_auth_requested =
+            SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_ENCAP_REQUEST;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetEncapsulatedRequestPayload {};
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_encapsulated_request_response(4294901758, bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest
+        );
+        if let SpdmMessagePayload::SpdmEncapsulatedRequestResponse(payload) = &spdm_message.payload
+        {
+            assert_eq!(payload.request_id, 1);
+        }
+        let encap_request = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            encap_request.request_response_code,
+            SpdmRequestResponseCode::SpdmRequestGetDigests
+        );
+        assert_eq!(
+            context.encap_context.last_request,
+            SpdmRequestResponseCode::SpdmRequestGetDigests
+        );
+    }
+
+    #[test]
+    fn test_case1_handle_spdm_get_encapsulated_request() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        context.common.session[0].setup(4294901758).unwrap();
+        context.common.session[0].set_session_state(SpdmSessionState::SpdmSessionHandshaking);
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetEncapsulatedRequestPayload {};
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_encapsulated_request_response(4294901758, bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnexpectedRequest
+            );
+        }
+    }
+
+    #[test]
+    fn test_case0_handle_spdm_basic_mut_auth_get_encapsulated_request() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        context.common.config_info.basic_mut_auth_requested = true;
+        context.common.negotiate_info.req_capabilities_sel =
+            SpdmRequestCapabilityFlags::MUT_AUTH_CAP | SpdmRequestCapabilityFlags::ENCAP_CAP;
+        context.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::MUT_AUTH_CAP | SpdmResponseCapabilityFlags::ENCAP_CAP;
+        assert!(context.is_basic_mut_auth_supported());
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        assert!(!context.is_basic_mut_auth_supported());
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetEncapsulatedRequestPayload {};
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        // no CHALLENGE_AUTH asked for basic mutual authentication yet
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_basic_mut_auth_encapsulated_request_response(bytes, &mut writer);
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+
+        context.init_spdm_basic_mut_auth();
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_basic_mut_auth_encapsulated_request_response(bytes, &mut writer);
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest
+        );
+        let encap_request = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            encap_request.request_response_code,
+            SpdmRequestResponseCode::SpdmRequestGetDigests
+        );
+        assert!(context.encap_context.basic_mut_auth);
+        assert_eq!(
+            context.common.runtime_info.message_mut_c.as_ref(),
+            &writer.used_slice()[4..]
+        );
+        assert!(!context.is_basic_mut_auth_verified());
+    }
+
+    #[test]
+    fn test_case0_handle_spdm_encap_key_update() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        let session_id = 4294901758u32;
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+        context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        context.common.negotiate_info.req_capabilities_sel =
+            SpdmRequestCapabilityFlags::ENCAP_CAP | SpdmRequestCapabilityFlags::KEY_UPD_CAP;
+        context.common.negotiate_info.rsp_capabilities_sel =
+            SpdmResponseCapabilityFlags::ENCAP_CAP | SpdmResponseCapabilityFlags::KEY_UPD_CAP;
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        context.common.session[0].setup(session_id).unwrap();
+        context.common.session[0].set_crypto_param(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmAeadAlgo::AES_256_GCM,
+            SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        );
+        let dhe_secret = SpdmDheFinalKeyStruct {
+            data_size: 48,
+            data: Box::new([0; SPDM_MAX_DHE_KEY_SIZE]),
+        };
+        let _ = context.common.session[0].set_dhe_secret(SpdmVersion::SpdmVersion12, dhe_secret);
+        let _ = context.common.session[0].generate_handshake_secret(
+            SpdmVersion::SpdmVersion12,
+            &SpdmDigestStruct {
+                data_size: 48,
+                data: Box::new([0; SPDM_MAX_HASH_SIZE]),
+            },
+        );
+        let _ = context.common.session[0].generate_data_secret(
+            SpdmVersion::SpdmVersion12,
+            &SpdmDigestStruct {
+                data_size: 48,
+                data: Box::new([0; SPDM_MAX_HASH_SIZE]),
+            },
+        );
+        context.common.session[0].set_session_state(SpdmSessionState::SpdmSessionEstablished);
+
+        context
+            .set_spdm_encap_request(
+                session_id,
+                SpdmEncapRequest::KeyUpdate(SpdmKeyUpdateOperation::SpdmUpdateAllKeys),
+            )
+            .unwrap_err();
+        context
+            .set_spdm_encap_request(
+                session_id,
+                SpdmEncapRequest::KeyUpdate(SpdmKeyUpdateOperation::SpdmUpdateSingleKey),
+            )
+            .unwrap();
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetEncapsulatedRequest,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetEncapsulatedRequestPayload {};
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_encapsulated_request_response(session_id, bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedRequest
+        );
+        let encap_request = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        let tag = if let SpdmMessagePayload::SpdmKeyUpdateRequest(payload) = &encap_request.payload
+        {
+            assert_eq!(
+                payload.key_update_operation,
+                SpdmKeyUpdateOperation::SpdmUpdateSingleKey
+            );
+            payload.tag
+        } else {
+            panic!("not a KEY_UPDATE request");
+        };
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestDeliverEncapsulatedResponse,
+        };
+        value.encode(&mut writer);
+        let value = SpdmDeliverEncapsulatedResponsePayload { request_id: 1 };
+        value.spdm_encode(&mut context.common, &mut writer);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmResponseKeyUpdateAck,
+        };
+        value.encode(&mut writer);
+        let value = SpdmKeyUpdateResponsePayload {
+            key_update_operation: SpdmKeyUpdateOperation::SpdmUpdateSingleKey,
+            tag,
+        };
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_encapsulated_response_ack_response(session_id, bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseEncapsulatedResponseAck
+        );
+        if let SpdmMessagePayload::SpdmEncapsulatedResponseAckResponse(payload) =
+            &spdm_message.payload
+        {
+            assert_eq!(
+                payload.payload_type,
+                SpdmEncapsulatedResponseAckPayloadType::SpdmEncapsulatedResponseAckPayloadTypeAbsent
+            );
+            assert_eq!(payload.ack_request_id, 1);
+        }
+        assert_eq!(
+            context.encap_context.last_request,
+            SpdmRequestResponseCode::Unknown(0)
+        );
+    }
+}
@@ -2,9 +2,10 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::common::SpdmCodec;
-use crate::error::{spdm_err, SpdmResult};
+use crate::common::{SpdmCodec, SpdmSessionEvent};
+use crate::error::SpdmResult;
 use crate::message::*;
+use crate::protocol::*;
 use crate::responder::*;
 
 impl<'a> ResponderContext<'a> {
@@ -13,11 +14,15 @@ impl<'a> ResponderContext<'a> {
         let mut writer = Writer::init(&mut send_buffer);
         if self.write_spdm_end_session_response(bytes, &mut writer) {
             self.send_secured_message(session_id, writer.used_slice(), false)?;
-            let session = self
-                .common
-                .get_session_via_id(session_id)
-                .ok_or(spdm_err!(EINVAL))?;
-            session.teardown(session_id)
+            let session = self.common.get_session_mut(session_id)?;
+            session.teardown(session_id)?;
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+
+            if !self.is_negotiated_state_preserved(bytes) {
+                self.common.reset_negotiate_info();
+            }
+            Ok(())
         } else {
             self.send_message(writer.used_slice())
         }
@@ -48,6 +53,27 @@ impl<'a> ResponderContext<'a> {
         response.spdm_encode(&mut self.common, writer);
         true
     }
+
+    // Without CACHE_CAP the negotiated state is kept whatever the requester asks.
+    fn is_negotiated_state_preserved(&mut self, bytes: &[u8]) -> bool {
+        if !self
+            .common
+            .negotiate_info
+            .rsp_capabilities_sel
+            .contains(SpdmResponseCapabilityFlags::CACHE_CAP)
+        {
+            return true;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+        match SpdmEndSessionRequestPayload::spdm_read(&mut self.common, &mut reader) {
+            Some(end_session_req) => end_session_req
+                .end_session_request_attributes
+                .contains(SpdmEndSessionRequestAttributes::PRESERVE_NEGOTIATED_STATE),
+            None => true,
+        }
+    }
 }
 
 #[cfg(all(test,))]
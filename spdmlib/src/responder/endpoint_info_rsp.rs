@@ -0,0 +1,245 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::ManagedBuffer;
+use crate::common::SpdmCodec;
+use crate::crypto;
+use crate::error::{spdm_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::responder::*;
+use crate::secret;
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_endpoint_info(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_endpoint_info_response(bytes, &mut writer);
+        match session_id {
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+        }
+    }
+
+    pub fn write_spdm_endpoint_info_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+        let ep_info_cap = self.common.config_info.rsp_capabilities
+            & SpdmResponseCapabilityFlags::EP_INFO_CAP_MASK;
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            < SpdmVersion::SpdmVersion13.get_u8()
+            || ep_info_cap.is_empty()
+        {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestGetEndpointInfo.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let get_endpoint_info =
+            SpdmGetEndpointInfoRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let get_endpoint_info = if let Some(get_endpoint_info) = get_endpoint_info {
+            debug!("!!! get_endpoint_info : {:02x?}\n", get_endpoint_info);
+            get_endpoint_info
+        } else {
+            error!("!!! get_endpoint_info : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        };
+
+        let signature_requested = get_endpoint_info
+            .request_attributes
+            .contains(SpdmEndpointInfoRequestAttributes::SIGNATURE_REQUESTED);
+        if signature_requested
+            && (!ep_info_cap.contains(SpdmResponseCapabilityFlags::EP_INFO_CAP_SIG)
+                || self
+                    .common
+                    .get_my_cert_chain(get_endpoint_info.slot_id)
+                    .is_none()
+                || !self.common.has_slot_key(get_endpoint_info.slot_id))
+        {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+        if let SpdmEndpointInfoSubCode::Unknown(_) = get_endpoint_info.sub_code {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        let mut endpoint_info = SpdmEndpointInfoResponsePayload {
+            slot_id: get_endpoint_info.slot_id,
+            ..Default::default()
+        };
+        match secret::spdm_get_endpoint_info(get_endpoint_info.sub_code, &mut endpoint_info.ep_info)
+        {
+            Ok(ep_info_len) if ep_info_len <= SPDM_MAX_ENDPOINT_INFO_LEN => {
+                endpoint_info.ep_info_len = ep_info_len as u32;
+            }
+            _ => {
+                error!("!!! get_endpoint_info : provider fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+        }
+
+        self.common.runtime_info.need_endpoint_info_signature = signature_requested;
+        if signature_requested {
+            if self
+                .common
+                .get_random(&mut endpoint_info.nonce.data)
+                .is_err()
+            {
+                error!("!!! get_endpoint_info : no random !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+            endpoint_info.signature = SpdmSignatureStruct {
+                data_size: self.common.negotiate_info.base_asym_sel.get_size(),
+                data: [0xbb; SPDM_MAX_ASYM_KEY_SIZE],
+            };
+        }
+
+        info!("send spdm endpoint_info\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseEndpointInfo,
+            },
+            payload: SpdmMessagePayload::SpdmEndpointInfoResponse(endpoint_info),
+        };
+        // The signature covers the response itself, so build it aside and
+        // only copy it out once it is complete.
+        let mut rsp_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut rsp_writer = Writer::init(&mut rsp_buffer);
+        response.spdm_encode(&mut self.common, &mut rsp_writer);
+        let used = rsp_writer.used();
+
+        if signature_requested {
+            // generat signature
+            let base_asym_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
+            let mut message_e = ManagedBuffer::default();
+            if message_e.append_message(&bytes[..reader.used()]).is_none()
+                || message_e
+                    .append_message(&rsp_writer.used_slice()[..(used - base_asym_size)])
+                    .is_none()
+            {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+            let signature =
+                self.generate_endpoint_info_signature(get_endpoint_info.slot_id, &message_e);
+            if signature.is_err() {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+            let signature = signature.unwrap();
+            // patch the message before send
+            rsp_writer.mut_used_slice()[(used - base_asym_size)..used]
+                .copy_from_slice(signature.as_ref());
+        }
+
+        let _ = writer.extend_from_slice(rsp_writer.used_slice());
+    }
+
+    pub fn generate_endpoint_info_signature(
+        &self,
+        slot_id: u8,
+        message_e: &ManagedBuffer,
+    ) -> SpdmResult<SpdmSignatureStruct> {
+        let mut message = ManagedBuffer::default();
+        message
+            .append_message(self.common.runtime_info.message_a.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(message_e.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let message_hash =
+            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
+                .ok_or_else(|| spdm_err!(Crypto))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        message.reset_message();
+        message
+            .append_message(&SPDM_VERSION_1_3_SIGNING_PREFIX_CONTEXT)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_5)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(&SPDM_ENDPOINT_INFO_SIGN_CONTEXT)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        message
+            .append_message(message_hash.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+        self.common.sign_with_slot_key(
+            slot_id,
+            self.common.negotiate_info.base_asym_sel,
+            message.as_ref(),
+        )
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::{Codec, Writer};
+
+    #[test]
+    fn test_case0_handle_spdm_endpoint_info() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetEndpointInfo,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetEndpointInfoRequestPayload {
+            sub_code: SpdmEndpointInfoSubCode::SpdmEndpointInfoDeviceClassIdentifier,
+            ..Default::default()
+        };
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_endpoint_info_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnsupportedRequest
+            );
+        }
+    }
+}
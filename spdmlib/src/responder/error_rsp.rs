@@ -12,6 +12,23 @@ impl<'a> ResponderContext<'a> {
         error_code: SpdmErrorCode,
         error_data: u8,
         writer: &mut Writer,
+    ) {
+        self.write_spdm_error_with_ext_data(
+            error_code,
+            error_data,
+            SpdmErrorResponseExtData::SpdmErrorExtDataNone(SpdmErrorResponseNoneExtData {}),
+            writer,
+        );
+    }
+
+    /// ERROR carrying ExtendedErrorData, e.g. ERROR(VendorDefined) with the
+    /// registry ID as error_data and the vendor ID and opaque data as extended_data.
+    pub fn write_spdm_error_with_ext_data(
+        &mut self,
+        error_code: SpdmErrorCode,
+        error_data: u8,
+        extended_data: SpdmErrorResponseExtData,
+        writer: &mut Writer,
     ) {
         let error = SpdmMessage {
             header: SpdmMessageHeader {
@@ -21,9 +38,7 @@ impl<'a> ResponderContext<'a> {
             payload: SpdmMessagePayload::SpdmErrorResponse(SpdmErrorResponsePayload {
                 error_code,
                 error_data,
-                extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataNone(
-                    SpdmErrorResponseNoneExtData {},
-                ),
+                extended_data,
             }),
         };
         error.spdm_encode(&mut self.common, writer);
@@ -36,6 +51,16 @@ impl<'a> ResponderContext<'a> {
         self.write_spdm_error(error_code, error_data, &mut writer);
         let _ = self.send_message(writer.used_slice());
     }
+
+    // A request of session session_id did not decrypt: ERROR(DecryptError)
+    // in the session, for the requester to send it again.
+    pub(crate) fn send_spdm_decrypt_error(&mut self, session_id: u32) {
+        info!("send spdm decrypt error\n");
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_error(SpdmErrorCode::SpdmErrorDecryptError, 0, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+    }
 }
 
 #[cfg(all(test,))]
@@ -61,4 +86,50 @@ mod tests_responder {
 
         context.send_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0);
     }
+
+    #[test]
+    fn test_case0_write_spdm_error_with_ext_data() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        let mut extended_data = SpdmErrorResponseVendorExtData {
+            data_size: 4,
+            ..Default::default()
+        };
+        extended_data.vendor_id.len = 2;
+        extended_data.vendor_id.vendor_id[..2].copy_from_slice(&[0x86, 0x80]);
+        extended_data.data[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+        let response = &mut [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(response);
+        context.write_spdm_error_with_ext_data(
+            SpdmErrorCode::SpdmErrorVendorDefined,
+            RegistryOrStandardsBodyID::PCISIG.get_u16() as u8,
+            SpdmErrorResponseExtData::SpdmErrorExtDataVendorDefined(extended_data.clone()),
+            &mut writer,
+        );
+        assert_eq!(writer.used(), 4 + 3 + 4);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorVendorDefined);
+            assert_eq!(payload.error_data, 0x3);
+            assert_eq!(
+                payload.extended_data,
+                SpdmErrorResponseExtData::SpdmErrorExtDataVendorDefined(extended_data)
+            );
+        } else {
+            panic!("ERROR response expected");
+        }
+    }
 }
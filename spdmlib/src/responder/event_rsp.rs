@@ -0,0 +1,276 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::session::SpdmSessionState;
+use crate::common::SpdmCodec;
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::*;
+use crate::protocol::*;
+use crate::responder::*;
+use crate::secret;
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_get_supported_event_types(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_supported_event_types_response(bytes, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+    }
+
+    pub fn write_spdm_supported_event_types_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+        if !self.is_event_supported() {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let get_supported_event_types =
+            SpdmGetSupportedEventTypesRequestPayload::spdm_read(&mut self.common, &mut reader);
+        if let Some(get_supported_event_types) = get_supported_event_types {
+            debug!(
+                "!!! get_supported_event_types : {:02x?}\n",
+                get_supported_event_types
+            );
+        } else {
+            error!("!!! get_supported_event_types : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        let mut supported_event_types = SpdmSupportedEventTypesResponsePayload::default();
+        match secret::spdm_get_supported_event_types(
+            &mut supported_event_types.supported_event_groups_list,
+        ) {
+            Ok(event_group_count) => {
+                supported_event_types.event_group_count = event_group_count;
+            }
+            Err(_) => {
+                error!("!!! get_supported_event_types : provider fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+        }
+
+        info!("send spdm supported_event_types\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseSupportedEventTypes,
+            },
+            payload: SpdmMessagePayload::SpdmSupportedEventTypesResponse(supported_event_types),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+
+    pub fn handle_spdm_subscribe_event_types(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_subscribe_event_types_response(session_id, bytes, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+    }
+
+    pub fn write_spdm_subscribe_event_types_response(
+        &mut self,
+        session_id: u32,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
+        if !self.is_event_supported() {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestSubscribeEventTypes.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let subscribe_event_types =
+            SpdmSubscribeEventTypesRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let subscribe_event_types = if let Some(subscribe_event_types) = subscribe_event_types {
+            debug!(
+                "!!! subscribe_event_types : {:02x?}\n",
+                subscribe_event_types
+            );
+            subscribe_event_types
+        } else {
+            error!("!!! subscribe_event_types : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        };
+
+        if secret::spdm_subscribe_event_types(
+            session_id,
+            subscribe_event_types.subscribe_event_group_count,
+            &subscribe_event_types.subscribe_list,
+        )
+        .is_err()
+        {
+            error!("!!! subscribe_event_types : provider fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        match self.common.get_session_via_id(session_id) {
+            Some(session) => session.set_event_subscription(
+                subscribe_event_types.subscribe_event_group_count,
+                &subscribe_event_types.subscribe_list,
+            ),
+            None => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+        }
+
+        info!("send spdm subscribe_event_types_ack\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseSubscribeEventTypesAck,
+            },
+            payload: SpdmMessagePayload::SpdmSubscribeEventTypesAckResponse(
+                SpdmSubscribeEventTypesAckResponsePayload {},
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+
+    /// Queue an event for the requester subscribed in session_id.
+    /// The event goes out on the next send_spdm_events().
+    pub fn publish_spdm_event(
+        &mut self,
+        session_id: u32,
+        event: &SpdmEventStruct,
+    ) -> SpdmResult<u32> {
+        let session = self.common.get_session_mut(session_id)?;
+        if session.get_session_state() != SpdmSessionState::SpdmSessionEstablished {
+            return spdm_result_err!(EINVAL);
+        }
+        session.push_event(event)
+    }
+
+    /// Send the pending events of session_id in a SEND_EVENT message.
+    /// They stay queued until the EVENT_ACK is received.
+    pub fn send_spdm_events(&mut self, session_id: u32) -> SpdmResult {
+        let mut send_event = SpdmSendEventRequestPayload::default();
+        {
+            let session = self.common.get_session_mut(session_id)?;
+            if session.get_sent_event_count() != 0 {
+                return spdm_result_err!(EBUSY);
+            }
+            let pending_events = session.get_pending_events();
+            if pending_events.is_empty() {
+                return Ok(());
+            }
+            let event_count = core::cmp::min(pending_events.len(), SPDM_MAX_EVENT_COUNT);
+            send_event.event_list[..event_count].clone_from_slice(&pending_events[..event_count]);
+            send_event.event_count = event_count as u32;
+            session.set_sent_event_count(event_count);
+        }
+
+        info!("send spdm send_event\n");
+
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestSendEvent,
+            },
+            payload: SpdmMessagePayload::SpdmSendEventRequest(send_event),
+        };
+        request.spdm_encode(&mut self.common, &mut writer);
+        let used = writer.used();
+        self.send_secured_message(session_id, &send_buffer[..used], false)
+    }
+
+    pub fn handle_spdm_event_ack(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let event_ack = SpdmEventAckResponsePayload::spdm_read(&mut self.common, &mut reader);
+        if let Some(event_ack) = event_ack {
+            debug!("!!! event_ack : {:02x?}\n", event_ack);
+        } else {
+            error!("!!! event_ack : fail !!!\n");
+            return;
+        }
+
+        if let Some(session) = self.common.get_session_via_id(session_id) {
+            let count = session.ack_sent_events();
+            debug!("!!! event_ack : {:x?} events acknowledged\n", count);
+        }
+    }
+
+    fn is_event_supported(&self) -> bool {
+        self.common.negotiate_info.spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion13.get_u8()
+            && self
+                .common
+                .config_info
+                .rsp_capabilities
+                .contains(SpdmResponseCapabilityFlags::EVENT_CAP)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::{Codec, Writer};
+
+    #[test]
+    fn test_case0_handle_spdm_get_supported_event_types() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetSupportedEventTypes,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetSupportedEventTypesRequestPayload {};
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_supported_event_types_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnsupportedRequest
+            );
+        }
+    }
+}
@@ -2,31 +2,69 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::common::SpdmCodec;
-#[cfg(feature = "hash-update")]
+use crate::common::ManagedBuffer;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
 use crate::crypto;
+use crate::error::{spdm_err, SpdmResult};
 use crate::protocol::*;
 use crate::responder::*;
 
-#[cfg(not(feature = "hash-update"))]
-use crate::common::ManagedBuffer;
 use crate::message::*;
 extern crate alloc;
 use alloc::boxed::Box;
 
 impl<'a> ResponderContext<'a> {
+    /// FINISH received outside of a session, for the session of the last
+    /// KEY_EXCHANGE when the handshake is in the clear.
+    pub fn handle_spdm_finish_in_the_clear(&mut self, bytes: &[u8]) -> bool {
+        let session_id = match self.clear_handshake_session_id {
+            Some(session_id) => session_id,
+            None => return false,
+        };
+        match self.common.get_immutable_session_via_id(session_id) {
+            Some(session)
+                if session.get_handshake_in_the_clear()
+                    && session.get_session_state()
+                        == crate::common::session::SpdmSessionState::SpdmSessionHandshaking => {}
+            _ => return false,
+        }
+        self.handle_spdm_finish(session_id, bytes);
+        true
+    }
+
     pub fn handle_spdm_finish(&mut self, session_id: u32, bytes: &[u8]) {
+        let in_clear_text = self
+            .common
+            .get_immutable_session_via_id(session_id)
+            .map_or(false, |session| session.get_handshake_in_the_clear());
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let mut writer = Writer::init(&mut send_buffer);
         if self.write_spdm_finish_response(session_id, bytes, &mut writer) {
-            let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            if in_clear_text {
+                self.clear_handshake_session_id = None;
+                let _ = self.send_message(writer.used_slice());
+            } else {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
             // change state after message is sent.
-            let session = self.common.get_session_via_id(session_id).unwrap();
-            session.set_session_state(
-                crate::common::session::SpdmSessionState::SpdmSessionEstablished,
-            );
+            if let Ok(session) = self.common.get_session_mut(session_id) {
+                session.set_session_state(
+                    crate::common::session::SpdmSessionState::SpdmSessionEstablished,
+                );
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Established);
+            }
         } else {
             let _ = self.send_message(writer.used_slice());
+            // the failures past the HMAC check tear the session down
+            if self
+                .common
+                .get_immutable_session_via_id(session_id)
+                .is_none()
+            {
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
         }
     }
 
@@ -51,10 +89,80 @@ impl<'a> ResponderContext<'a> {
         let finish_req = finish_req.unwrap();
         let read_used = reader.used();
 
+        let session = match self.common.get_session(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
+        let mut_auth_requested = !session.mut_auth_requested.is_empty();
+        let slot_id = session.slot_id;
+        let req_slot_id = session.req_slot_id;
+        let signature_included = finish_req
+            .finish_request_attributes
+            .contains(SpdmFinishRequestAttributes::SIGNATURE_INCLUDED);
+        if mut_auth_requested != signature_included
+            || (signature_included && finish_req.req_slot_id != req_slot_id)
+        {
+            error!("!!! finish req : requester signature mismatch !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return false;
+        }
+
         // verify HMAC with finished_key
         let base_hash_size = self.common.negotiate_info.base_hash_sel.get_size() as usize;
         let temp_used = read_used - base_hash_size;
 
+        if mut_auth_requested {
+            // the requester certificate chain hash follows message_k in the transcript
+            let cert_chain_hash = self.common.get_certchain_hash_req(req_slot_id, false);
+            if cert_chain_hash.is_none() {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+            let cert_chain_hash = cert_chain_hash.unwrap();
+            let session = match self.common.get_session_mut(session_id) {
+                Ok(session) => session,
+                Err(_) => {
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return false;
+                }
+            };
+            #[cfg(not(feature = "hash-update"))]
+            if session
+                .runtime_info
+                .message_k
+                .append_message(cert_chain_hash.as_ref())
+                .is_none()
+            {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+            #[cfg(feature = "hash-update")]
+            crypto::hash::hash_ctx_update(
+                session.runtime_info.message_k.as_mut().unwrap(),
+                cert_chain_hash.as_ref(),
+            );
+
+            let req_asym_size = self.common.negotiate_info.req_asym_sel.get_size() as usize;
+            if self
+                .verify_finish_req_signature(
+                    session_id,
+                    req_slot_id,
+                    &bytes[..(temp_used - req_asym_size)],
+                    &finish_req.signature,
+                )
+                .is_err()
+            {
+                error!("verify_finish_req_signature fail");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            } else {
+                info!("verify_finish_req_signature pass");
+            }
+        }
+
         #[cfg(not(feature = "hash-update"))]
         let mut message_f = ManagedBuffer::default();
         #[cfg(not(feature = "hash-update"))]
@@ -63,17 +171,18 @@ impl<'a> ResponderContext<'a> {
         }
 
         #[cfg(not(feature = "hash-update"))]
-        let session = self
-            .common
-            .get_immutable_session_via_id(session_id)
-            .unwrap();
-        #[cfg(not(feature = "hash-update"))]
-        let message_k = &session.runtime_info.message_k.clone();
+        let message_k = &match self.common.get_session(session_id) {
+            Ok(session) => session.runtime_info.message_k.clone(),
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
         #[cfg(not(feature = "hash-update"))]
         let transcript_data =
             &self
                 .common
-                .calc_rsp_transcript_data(false, message_k, Some(&message_f));
+                .calc_rsp_transcript_data(slot_id, false, message_k, Some(&message_f));
         #[cfg(not(feature = "hash-update"))]
         if transcript_data.is_err() {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
@@ -82,7 +191,13 @@ impl<'a> ResponderContext<'a> {
         #[cfg(not(feature = "hash-update"))]
         let transcript_data = transcript_data.as_ref().unwrap();
 
-        let session = self.common.get_session_via_id(session_id).unwrap();
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
         #[cfg(feature = "hash-update")]
         let mut message_f = session.runtime_info.message_k.as_mut().cloned();
 
@@ -102,6 +217,8 @@ impl<'a> ResponderContext<'a> {
             .is_err()
         {
             error!("verify_hmac_with_request_finished_key fail");
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::HmacFailure);
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return false;
         } else {
@@ -119,16 +236,7 @@ impl<'a> ResponderContext<'a> {
         #[cfg(feature = "hash-update")]
         crypto::hash::hash_ctx_update(message_f.as_mut().unwrap(), finish_req.verify_data.as_ref());
 
-        let in_clear_text = self
-            .common
-            .negotiate_info
-            .req_capabilities_sel
-            .contains(SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP)
-            && self
-                .common
-                .negotiate_info
-                .rsp_capabilities_sel
-                .contains(SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP);
+        let in_clear_text = self.common.is_handshake_in_the_clear();
 
         info!("send spdm finish rsp\n");
 
@@ -172,18 +280,25 @@ impl<'a> ResponderContext<'a> {
             #[cfg(not(feature = "hash-update"))]
             let transcript_data =
                 self.common
-                    .calc_rsp_transcript_data(false, message_k, Some(&message_f));
+                    .calc_rsp_transcript_data(slot_id, false, message_k, Some(&message_f));
             #[cfg(not(feature = "hash-update"))]
             if transcript_data.is_err() {
                 self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-                let session = self.common.get_session_via_id(session_id).unwrap();
-                let _ = session.teardown(session_id);
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    let _ = session.teardown(session_id);
+                }
                 return false;
             }
             #[cfg(not(feature = "hash-update"))]
             let transcript_data = transcript_data.unwrap();
 
-            let session = self.common.get_session_via_id(session_id).unwrap();
+            let session = match self.common.get_session_mut(session_id) {
+                Ok(session) => session,
+                Err(_) => {
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return false;
+                }
+            };
             #[cfg(not(feature = "hash-update"))]
             let hmac = session.generate_hmac_with_response_finished_key(transcript_data.as_ref());
             #[cfg(feature = "hash-update")]
@@ -214,8 +329,9 @@ impl<'a> ResponderContext<'a> {
             #[cfg(not(feature = "hash-update"))]
             if message_f.append_message(writer.used_slice()).is_none() {
                 self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-                let session = self.common.get_session_via_id(session_id).unwrap();
-                let _ = session.teardown(session_id);
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    let _ = session.teardown(session_id);
+                }
                 return false;
             }
             #[cfg(feature = "hash-update")]
@@ -226,35 +342,169 @@ impl<'a> ResponderContext<'a> {
         #[cfg(not(feature = "hash-update"))]
         let th2 = self
             .common
-            .calc_rsp_transcript_hash(false, message_k, Some(&message_f));
+            .calc_rsp_transcript_hash(slot_id, false, message_k, Some(&message_f));
         #[cfg(feature = "hash-update")]
         let th2 = crypto::hash::hash_ctx_finalize(message_f.as_mut().cloned().unwrap());
         #[cfg(feature = "hash-update")]
         if th2.is_none() {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            let session = self.common.get_session_via_id(session_id).unwrap();
-            let _ = session.teardown(session_id);
+            if let Ok(session) = self.common.get_session_mut(session_id) {
+                let _ = session.teardown(session_id);
+            }
             return false;
         }
         #[cfg(not(feature = "hash-update"))]
         if th2.is_err() {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            let session = self.common.get_session_via_id(session_id).unwrap();
-            let _ = session.teardown(session_id);
+            if let Ok(session) = self.common.get_session_mut(session_id) {
+                let _ = session.teardown(session_id);
+            }
             return false;
         }
         let th2 = th2.unwrap();
         debug!("!!! th2 : {:02x?}\n", th2.as_ref());
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = self.common.get_session_via_id(session_id).unwrap();
-        session
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
+        if session
             .generate_data_secret(spdm_version_sel, &th2)
-            .unwrap();
+            .is_err()
+        {
+            let _ = session.teardown(session_id);
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return false;
+        }
 
         session.runtime_info.message_f = message_f;
 
         true
     }
+
+    #[cfg(feature = "hash-update")]
+    pub fn verify_finish_req_signature(
+        &self,
+        session_id: u32,
+        req_slot_id: u8,
+        message_f: &[u8],
+        signature: &SpdmSignatureStruct,
+    ) -> SpdmResult {
+        let session = self.common.get_session(session_id)?;
+        let mut message_k = session
+            .runtime_info
+            .message_k
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        crypto::hash::hash_ctx_update(&mut message_k, message_f);
+        let message_hash =
+            crypto::hash::hash_ctx_finalize(message_k).ok_or_else(|| spdm_err!(Crypto))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        let mut message = ManagedBuffer::default();
+        if let Some(signing_prefix_context) = self
+            .common
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            message
+                .append_message(signing_prefix_context)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_12)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_FINISH_SIGN_CONTEXT)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+        message
+            .append_message(message_hash.as_ref())
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+
+        self.verify_spdm_req_signature(req_slot_id, message.as_ref(), signature)
+    }
+
+    #[cfg(not(feature = "hash-update"))]
+    pub fn verify_finish_req_signature(
+        &mut self,
+        session_id: u32,
+        req_slot_id: u8,
+        message_f: &[u8],
+        signature: &SpdmSignatureStruct,
+    ) -> SpdmResult {
+        let session = self.common.get_session(session_id)?;
+        let slot_id = session.slot_id;
+        let message_k = session.runtime_info.message_k.clone();
+        let mut message_f_buffer = ManagedBuffer::default();
+        message_f_buffer
+            .append_message(message_f)
+            .ok_or_else(|| spdm_err!(ENOMEM))?;
+        let mut message = self.common.calc_rsp_transcript_data(
+            slot_id,
+            false,
+            &message_k,
+            Some(&message_f_buffer),
+        )?;
+        // we dont need create message hash for verify
+        // we just print message hash for debug purpose
+        let message_hash =
+            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
+                .ok_or_else(|| spdm_err!(EFAULT))?;
+        debug!("message_hash - {:02x?}", message_hash.as_ref());
+
+        if let Some(signing_prefix_context) = self
+            .common
+            .negotiate_info
+            .spdm_version_sel
+            .get_signing_prefix_context()
+        {
+            message.reset_message();
+            message
+                .append_message(signing_prefix_context)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_12)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(&SPDM_FINISH_SIGN_CONTEXT)
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+            message
+                .append_message(message_hash.as_ref())
+                .ok_or_else(|| spdm_err!(ENOMEM))?;
+        }
+
+        self.verify_spdm_req_signature(req_slot_id, message.as_ref(), signature)
+    }
+
+    pub(crate) fn verify_spdm_req_signature(
+        &self,
+        req_slot_id: u8,
+        message: &[u8],
+        signature: &SpdmSignatureStruct,
+    ) -> SpdmResult {
+        let peer_cert_chain = self.common.peer_info.peer_cert_chain[req_slot_id as usize]
+            .as_ref()
+            .ok_or_else(|| spdm_err!(EINVAL))?;
+        let cert_chain_data = &peer_cert_chain.cert_chain.data[(4usize
+            + self.common.negotiate_info.base_hash_sel.get_size() as usize)
+            ..(peer_cert_chain.cert_chain.data_size as usize)];
+
+        crypto::asym_verify::verify(
+            self.common.negotiate_info.base_hash_sel,
+            SpdmBaseAsymAlgo::from_bits_truncate(
+                self.common.negotiate_info.req_asym_sel.bits() as u32
+            ),
+            cert_chain_data,
+            message,
+            signature,
+        )
+        .map_err(|_| spdm_err!(VerificationFailed))
+    }
 }
 
 #[cfg(all(test,))]
@@ -357,6 +607,7 @@ mod tests_responder {
 
         context.common.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
         context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        context.common.negotiate_info.req_asym_sel = SpdmReqAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
         context.common.negotiate_info.req_capabilities_sel = SpdmRequestCapabilityFlags::CERT_CAP;
         context.common.negotiate_info.rsp_capabilities_sel =
             SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP;
@@ -2,11 +2,55 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::common::SpdmCodec;
+use crate::common::session::SpdmSessionState;
+use crate::common::{SpdmCodec, SpdmSessionEvent, ST1};
 use crate::message::*;
 use crate::responder::*;
 
 impl<'a> ResponderContext<'a> {
+    /// Check the requesters are alive against time::now(). An established
+    /// session the requester sent nothing in for twice its HeartbeatPeriod is
+    /// torn down, with a HeartbeatTimeout event before the Terminated one.
+    /// process_message runs it after each message. Return the number of
+    /// sessions torn down.
+    pub fn process_spdm_heartbeat_liveness(&mut self) -> usize {
+        self.check_heartbeat_liveness(crate::time::now())
+    }
+
+    // now and the last activity of the sessions are in microseconds,
+    // HeartbeatPeriod is in seconds.
+    fn check_heartbeat_liveness(&mut self, now: u64) -> usize {
+        let mut expired = 0;
+        for i in 0..self.common.session.len() {
+            let session = &self.common.session[i];
+            let session_id = session.get_session_id();
+            if session_id == 0
+                || session.heartbeat_period == 0
+                || session.get_session_state() != SpdmSessionState::SpdmSessionEstablished
+            {
+                continue;
+            }
+            let max_idle = 2 * session.heartbeat_period as u64 * ST1 as u64;
+            if now.saturating_sub(session.get_last_activity()) < max_idle {
+                continue;
+            }
+            error!(
+                "!!! heartbeat : requester silent, session {:08x} closed !!!\n",
+                session_id
+            );
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::HeartbeatTimeout);
+            // the observer may have ended the session already
+            if let Some(session) = self.common.get_session_via_id(session_id) {
+                let _ = session.teardown(session_id);
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
+            expired += 1;
+        }
+        expired
+    }
+
     pub fn handle_spdm_heartbeat(&mut self, session_id: u32, bytes: &[u8]) {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let mut writer = Writer::init(&mut send_buffer);
@@ -16,6 +60,15 @@ impl<'a> ResponderContext<'a> {
     }
 
     pub fn write_spdm_heartbeat_response(&mut self, bytes: &[u8], writer: &mut Writer) -> bool {
+        if !self.common.is_heartbeat_negotiated() {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestHeartbeat.get_u8(),
+                writer,
+            );
+            return true;
+        }
+
         let mut reader = Reader::init(bytes);
         SpdmMessageHeader::read(&mut reader);
 
@@ -67,6 +120,8 @@ mod tests_responder {
         let rsp_session_id = 0xffu16;
         let session_id = (0xffu32 << 16) + rsp_session_id as u32;
         context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        context.common.negotiate_info.req_capabilities_sel = SpdmRequestCapabilityFlags::HBEAT_CAP;
+        context.common.negotiate_info.rsp_capabilities_sel = SpdmResponseCapabilityFlags::HBEAT_CAP;
         context.common.session = gen_array_clone(SpdmSession::new(), 4);
         context.common.session[0].setup(session_id).unwrap();
         context.common.session[0].set_crypto_param(
@@ -88,4 +143,52 @@ mod tests_responder {
 
         context.handle_spdm_heartbeat(session_id, bytes);
     }
+
+    #[test]
+    fn test_case1_process_spdm_heartbeat_liveness() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let session_events = SessionEventRecorder::default();
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.register_session_observer(&session_events);
+
+        context.common.session = gen_array_clone(SpdmSession::new(), 4);
+        for (i, heartbeat_period, session_state) in [
+            (0, 2, SpdmSessionState::SpdmSessionEstablished),
+            (1, 0, SpdmSessionState::SpdmSessionEstablished),
+            (2, 2, SpdmSessionState::SpdmSessionHandshaking),
+            (3, 2, SpdmSessionState::SpdmSessionEstablished),
+        ] {
+            let session = &mut context.common.session[i];
+            session.setup(0xFFFE_0000 + i as u32).unwrap();
+            session.heartbeat_period = heartbeat_period;
+            session.set_session_state(session_state);
+        }
+
+        // heartbeat periods of 2 seconds, set up now
+        assert_eq!(context.process_spdm_heartbeat_liveness(), 0);
+        let now = crate::time::now();
+        assert_eq!(context.check_heartbeat_liveness(now + 3 * ST1 as u64), 0);
+        assert_eq!(context.check_heartbeat_liveness(now + 4 * ST1 as u64), 2);
+        assert_eq!(context.common.session[0].get_session_id(), 0);
+        assert_eq!(context.common.session[1].get_session_id(), 0xFFFE_0001);
+        assert_eq!(context.common.session[2].get_session_id(), 0xFFFE_0002);
+        assert_eq!(context.common.session[3].get_session_id(), 0);
+        assert_eq!(
+            session_events.events.borrow().as_slice(),
+            &[
+                (0xFFFE_0000, SpdmSessionEvent::HeartbeatTimeout),
+                (0xFFFE_0000, SpdmSessionEvent::Terminated),
+                (0xFFFE_0003, SpdmSessionEvent::HeartbeatTimeout),
+                (0xFFFE_0003, SpdmSessionEvent::Terminated),
+            ]
+        );
+    }
 }
@@ -5,9 +5,11 @@
 use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::responder::*;
 
-use crate::common::SpdmCodec;
-use crate::common::{ManagedBuffer, SpdmOpaqueSupport};
+use crate::common::session::SpdmSessionId;
+use crate::common::ManagedBuffer;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
 use crate::crypto;
+use crate::crypto::SpdmAsymSignOperation;
 use crate::protocol::*;
 extern crate alloc;
 use crate::common::opaque::SpdmOpaqueStruct;
@@ -16,6 +18,24 @@ use crate::crypto::HashCtx;
 use crate::message::*;
 use alloc::boxed::Box;
 
+// KEY_EXCHANGE_RSP state needed to set up the session once its signature is
+// known, kept aside while an external signer is busy.
+pub(crate) struct SpdmKeyExchangeDeferred {
+    slot_id: u8,
+    session_id: u32,
+    in_clear_text: bool,
+    final_key: SpdmDheFinalKeyStruct,
+    #[cfg(not(feature = "hash-update"))]
+    message_k: ManagedBuffer,
+    #[cfg(feature = "hash-update")]
+    message_k: HashCtx,
+    signature_offset: usize,
+    heartbeat_period: u8,
+    secure_spdm_version_sel: Option<u8>,
+    mut_auth_req: SpdmKeyExchangeMutAuthAttributes,
+    session_policy: u8,
+}
+
 impl<'a> ResponderContext<'a> {
     pub fn handle_spdm_key_exchange(&mut self, bytes: &[u8]) -> SpdmResult {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
@@ -36,6 +56,7 @@ impl<'a> ResponderContext<'a> {
             SpdmKeyExchangeRequestPayload::spdm_read(&mut self.common, &mut reader);
 
         let mut return_opaque = SpdmOpaqueStruct::default();
+        let mut secure_spdm_version_sel = None;
 
         if let Some(key_exchange_req) = &key_exchange_req {
             debug!("!!! key_exchange req : {:02x?}\n", key_exchange_req);
@@ -50,47 +71,20 @@ impl<'a> ResponderContext<'a> {
                 self.common.runtime_info.need_measurement_summary_hash = false;
             }
 
-            if key_exchange_req.session_policy
-                & KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_MASK
-                == KEY_EXCHANGE_REQUESTER_SESSION_POLICY_TERMINATION_POLICY_VALUE
-            {
-                self.common.negotiate_info.termination_policy_set = true;
-            } else {
-                self.common.negotiate_info.termination_policy_set = false;
-            }
-
             if let Some(secured_message_version_list) = key_exchange_req
                 .opaque
                 .rsp_get_dmtf_supported_secure_spdm_version_list(&mut self.common)
             {
-                for index in 0..secured_message_version_list.version_count as usize {
-                    if secured_message_version_list.versions_list[index].get_secure_spdm_version()
-                        == self.common.config_info.secure_spdm_version
-                    {
-                        if self
-                            .common
-                            .negotiate_info
-                            .opaque_data_support
-                            .contains(SpdmOpaqueSupport::OPAQUE_DATA_FMT1)
-                        {
-                            return_opaque.data_size =
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT1
-                                    .len() as u16;
-                            return_opaque.data[..(return_opaque.data_size as usize)]
-                                .copy_from_slice(
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT1
-                                    .as_ref(),
-                            );
-                        } else {
-                            return_opaque.data_size =
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT0
-                                    .len() as u16;
-                            return_opaque.data[..(return_opaque.data_size as usize)]
-                                .copy_from_slice(
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT0
-                                    .as_ref(),
-                            );
-                        }
+                if let Some(selected_version) = crate::common::opaque::select_secure_spdm_version(
+                    &secured_message_version_list,
+                    self.common.config_info.secure_spdm_version,
+                ) {
+                    if let Ok(opaque) = SpdmOpaqueStruct::rsp_dmtf_secure_spdm_version_selection(
+                        &mut self.common,
+                        selected_version,
+                    ) {
+                        return_opaque = opaque;
+                        secure_spdm_version_sel = Some(selected_version.get_secure_spdm_version());
                     }
                 }
             }
@@ -100,31 +94,115 @@ impl<'a> ResponderContext<'a> {
             return spdm_result_err!(EINVAL);
         }
 
-        info!("send spdm key_exchange rsp\n");
+        let slot_id = key_exchange_req.as_ref().unwrap().slot_id;
+        // SessionPolicy is reserved before spdm 1.2
+        let session_policy = if self.common.negotiate_info.spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion12.get_u8()
+        {
+            key_exchange_req.as_ref().unwrap().session_policy
+        } else {
+            0
+        };
+        if self.common.get_my_cert_chain(slot_id).is_none() {
+            error!(
+                "!!! key_exchange req : slot {} is not provisioned !!!\n",
+                slot_id
+            );
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return spdm_result_err!(EINVAL);
+        }
+        if !self.common.is_key_usage_allowed(
+            &self.common.provision_info.my_key_usage_mask,
+            slot_id,
+            SpdmKeyUsageBitMask::KEY_EX_USE,
+        ) {
+            error!(
+                "!!! key_exchange req : slot {} key is not for key exchange !!!\n",
+                slot_id
+            );
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return spdm_result_err!(EINVAL);
+        }
 
-        let (exchange, key_exchange_context) =
-            crypto::dhe::generate_key_pair(self.common.negotiate_info.dhe_sel).unwrap();
+        if self.common.get_next_avaiable_session().is_none() || !self.reserve_shared_session() {
+            error!("!!! key_exchange req : too many sessions !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorSessionLimitExceeded, 0, writer);
+            return spdm_result_err!(ENOMEM);
+        }
 
-        debug!("!!! exchange data : {:02x?}\n", exchange);
+        info!("send spdm key_exchange rsp\n");
 
         debug!(
             "!!! exchange data (peer) : {:02x?}\n",
             &key_exchange_req.as_ref().unwrap().exchange
         );
 
-        let final_key =
-            key_exchange_context.compute_final_key(&key_exchange_req.as_ref().unwrap().exchange);
-
-        if final_key.is_none() {
+        let dhe_response = self.common.generate_dhe_response(
+            self.common.negotiate_info.dhe_sel,
+            &key_exchange_req.as_ref().unwrap().exchange,
+        );
+        if dhe_response.is_none() {
             return spdm_result_err!(ESEC);
         }
-        let final_key = final_key.unwrap();
+        let (exchange, final_key) = dhe_response.unwrap();
+
+        debug!("!!! exchange data : {:02x?}\n", exchange);
         debug!("!!! final_key : {:02x?}\n", final_key.as_ref());
 
         let mut random = [0u8; SPDM_RANDOM_SIZE];
-        let _ = crypto::rand::get_random(&mut random);
+        if let Err(e) = self.common.get_random(&mut random) {
+            error!("!!! key_exchange req : no random !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return Err(e);
+        }
 
-        let rsp_session_id = 0xFFFE;
+        let rsp_session_id = match self.common.generate_session_id_half(false) {
+            Ok(rsp_session_id) => rsp_session_id,
+            Err(e) => {
+                error!("!!! key_exchange req : no session id !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return Err(e);
+            }
+        };
+
+        let mut mut_auth_req = SpdmKeyExchangeMutAuthAttributes::empty();
+        if self
+            .common
+            .negotiate_info
+            .req_capabilities_sel
+            .contains(SpdmRequestCapabilityFlags::MUT_AUTH_CAP)
+            && self
+                .common
+                .negotiate_info
+                .rsp_capabilities_sel
+                .contains(SpdmResponseCapabilityFlags::MUT_AUTH_CAP)
+        {
+            mut_auth_req = self.common.config_info.mut_auth_requested;
+            // the encapsulated requests would need the handshake keys
+            if self.common.is_handshake_in_the_clear()
+                || !self
+                    .common
+                    .negotiate_info
+                    .req_capabilities_sel
+                    .contains(SpdmRequestCapabilityFlags::ENCAP_CAP)
+                || !self
+                    .common
+                    .negotiate_info
+                    .rsp_capabilities_sel
+                    .contains(SpdmResponseCapabilityFlags::ENCAP_CAP)
+            {
+                mut_auth_req.remove(
+                    SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_ENCAP_REQUEST
+                        | SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_GET_DIGESTS,
+                );
+            }
+        }
+
+        let heartbeat_period = if self.common.is_heartbeat_negotiated() {
+            self.common.config_info.heartbeat_period
+        } else {
+            0
+        };
 
         let response = SpdmMessage {
             header: SpdmMessageHeader {
@@ -132,9 +210,9 @@ impl<'a> ResponderContext<'a> {
                 request_response_code: SpdmRequestResponseCode::SpdmResponseKeyExchangeRsp,
             },
             payload: SpdmMessagePayload::SpdmKeyExchangeResponse(SpdmKeyExchangeResponsePayload {
-                heartbeat_period: self.common.config_info.heartbeat_period,
+                heartbeat_period,
                 rsp_session_id,
-                mut_auth_req: SpdmKeyExchangeMutAuthAttributes::empty(),
+                mut_auth_req,
                 req_slot_id: 0x0,
                 random: SpdmRandomStruct { data: random },
                 exchange,
@@ -155,12 +233,21 @@ impl<'a> ResponderContext<'a> {
         };
 
         response.spdm_encode(&mut self.common, writer);
+        if self.check_response_size(false, writer) {
+            return Ok(());
+        }
         let used = writer.used();
 
         // generate signature
         let base_asym_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
-        let base_hash_size = self.common.negotiate_info.base_hash_sel.get_size() as usize;
-        let temp_used = used - base_asym_size - base_hash_size;
+        // no ResponderVerifyData in the clear
+        let in_clear_text = self.common.is_handshake_in_the_clear();
+        let verify_data_size = if in_clear_text {
+            0
+        } else {
+            self.common.negotiate_info.base_hash_sel.get_size() as usize
+        };
+        let temp_used = used - base_asym_size - verify_data_size;
 
         #[cfg(not(feature = "hash-update"))]
         let mut message_k = ManagedBuffer::default();
@@ -182,7 +269,7 @@ impl<'a> ResponderContext<'a> {
         #[cfg(feature = "hash-update")]
         let cert_chain_hash;
         #[cfg(feature = "hash-update")]
-        if let Some(hash) = self.common.get_certchain_hash_rsp(false) {
+        if let Some(hash) = self.common.get_certchain_hash_rsp(slot_id, false) {
             cert_chain_hash = hash;
         } else {
             panic!("get_certchain_hash_req failed!");
@@ -202,27 +289,93 @@ impl<'a> ResponderContext<'a> {
             crypto::hash::hash_ctx_update(&mut message_k, &writer.used_slice()[..temp_used]);
         }
         #[cfg(not(feature = "hash-update"))]
-        let signature = self.generate_key_exchange_rsp_signature(&message_k);
+        let sign_operation = self.generate_key_exchange_rsp_signature(slot_id, &message_k);
         #[cfg(feature = "hash-update")]
-        let signature = self.generate_key_exchange_rsp_signature(message_k.clone());
-        if signature.is_err() {
+        let sign_operation = self.generate_key_exchange_rsp_signature(slot_id, message_k.clone());
+
+        let key_exchange = SpdmKeyExchangeDeferred {
+            slot_id,
+            session_id: SpdmSessionId::new(
+                key_exchange_req.unwrap().req_session_id,
+                rsp_session_id,
+            )
+            .as_u32(),
+            in_clear_text,
+            final_key,
+            message_k,
+            signature_offset: temp_used,
+            heartbeat_period,
+            secure_spdm_version_sel,
+            mut_auth_req,
+            session_policy,
+        };
+
+        // patch the signature before send, or defer the response while the signer is busy
+        if sign_operation
+            .and_then(|sign_operation| {
+                self.write_spdm_signed_response_at(
+                    None,
+                    SpdmRequestResponseCode::SpdmRequestKeyExchange,
+                    sign_operation,
+                    temp_used,
+                    writer,
+                )
+            })
+            .is_err()
+        {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return spdm_result_err!(EFAULT);
         }
-        let signature = signature.unwrap();
+        if self.respond_if_ready_context.sign_operation.is_some() {
+            // the session is created once RESPOND_IF_READY finds the signature
+            self.respond_if_ready_context.key_exchange = Some(key_exchange);
+            return Ok(());
+        }
+
+        self.finish_spdm_key_exchange_response(key_exchange, writer)
+    }
+
+    /// Set up the session of a KEY_EXCHANGE_RSP whose signature is in
+    /// `writer`, and fill in ResponderVerifyData.
+    pub(crate) fn finish_spdm_key_exchange_response(
+        &mut self,
+        key_exchange: SpdmKeyExchangeDeferred,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        let SpdmKeyExchangeDeferred {
+            slot_id,
+            session_id,
+            in_clear_text,
+            final_key,
+            mut message_k,
+            signature_offset,
+            heartbeat_period,
+            secure_spdm_version_sel,
+            mut_auth_req,
+            session_policy,
+        } = key_exchange;
+
+        let used = writer.used();
+        let base_asym_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
+        let verify_data_size = if in_clear_text {
+            0
+        } else {
+            self.common.negotiate_info.base_hash_sel.get_size() as usize
+        };
+        let signature = &writer.used_slice()[signature_offset..(signature_offset + base_asym_size)];
         #[cfg(not(feature = "hash-update"))]
-        if message_k.append_message(signature.as_ref()).is_none() {
+        if message_k.append_message(signature).is_none() {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return spdm_result_err!(EFAULT);
         }
         #[cfg(feature = "hash-update")]
-        crypto::hash::hash_ctx_update(&mut message_k, signature.as_ref());
+        crypto::hash::hash_ctx_update(&mut message_k, signature);
 
         // create session - generate the handshake secret (including finished_key)
         #[cfg(not(feature = "hash-update"))]
         let th1 = self
             .common
-            .calc_rsp_transcript_hash(false, &message_k, None);
+            .calc_rsp_transcript_hash(slot_id, false, &message_k, None);
         #[cfg(feature = "hash-update")]
         let th1 = crypto::hash::hash_ctx_finalize(message_k.clone());
         #[cfg(not(feature = "hash-update"))]
@@ -236,88 +389,115 @@ impl<'a> ResponderContext<'a> {
         let dhe_algo = self.common.negotiate_info.dhe_sel;
         let aead_algo = self.common.negotiate_info.aead_sel;
         let key_schedule_algo = self.common.negotiate_info.key_schedule_sel;
-        let sequence_number_count = self.common.transport_encap.get_sequence_number_count();
-        let max_random_count = self.common.transport_encap.get_max_random_count();
+        let transport_param = self.common.get_session_transport_param();
 
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = self.common.get_next_avaiable_session();
-        if session.is_none() {
-            error!("!!! too many sessions : fail !!!\n");
-            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            return spdm_result_err!(EFAULT);
-        }
-
-        let session = session.unwrap();
-        let session_id =
-            ((key_exchange_req.unwrap().req_session_id as u32) << 16) + rsp_session_id as u32;
+        let session = self
+            .common
+            .get_next_avaiable_session()
+            .ok_or(spdm_err!(ENOMEM))?;
         session.setup(session_id).unwrap();
         session.set_use_psk(false);
+        session.set_handshake_in_the_clear(in_clear_text);
         session.set_crypto_param(hash_algo, dhe_algo, aead_algo, key_schedule_algo);
-        session.set_transport_param(sequence_number_count, max_random_count);
-        session.set_dhe_secret(spdm_version_sel, final_key)?;
-        session
-            .generate_handshake_secret(spdm_version_sel, &th1)
-            .unwrap();
-
-        // generate HMAC with finished_key
-        #[cfg(not(feature = "hash-update"))]
-        let transcript_data = self
-            .common
-            .calc_rsp_transcript_data(false, &message_k, None);
-        #[cfg(not(feature = "hash-update"))]
-        if transcript_data.is_err() {
-            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            return spdm_result_err!(EFAULT);
-        }
-        #[cfg(not(feature = "hash-update"))]
-        let transcript_data = transcript_data.unwrap();
-
-        let session = self.common.get_session_via_id(session_id).unwrap();
-        #[cfg(not(feature = "hash-update"))]
-        let hmac = session.generate_hmac_with_response_finished_key(transcript_data.as_ref());
-        #[cfg(feature = "hash-update")]
-        let hmac = session.generate_hmac_with_response_finished_key(
-            crypto::hash::hash_ctx_finalize(message_k.clone())
-                .unwrap()
-                .as_ref(),
-        );
-        if hmac.is_err() {
+        session.set_transport_param(transport_param);
+        if session.set_dhe_secret(spdm_version_sel, final_key).is_err()
+            || session
+                .generate_handshake_secret(spdm_version_sel, &th1)
+                .is_err()
+        {
             let _ = session.teardown(session_id);
-            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            return spdm_result_err!(EFAULT);
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return spdm_result_err!(ESEC);
         }
-        let hmac = hmac.unwrap();
-        #[cfg(not(feature = "hash-update"))]
-        {
+
+        if !in_clear_text {
+            // generate HMAC with finished_key
+            #[cfg(not(feature = "hash-update"))]
+            let transcript_data = self
+                .common
+                .calc_rsp_transcript_data(slot_id, false, &message_k, None);
+            #[cfg(not(feature = "hash-update"))]
+            if transcript_data.is_err() {
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    let _ = session.teardown(session_id);
+                }
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+            #[cfg(not(feature = "hash-update"))]
+            let transcript_data = transcript_data.unwrap();
+
+            let session = match self.common.get_session_mut(session_id) {
+                Ok(session) => session,
+                Err(_) => {
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return spdm_result_err!(EFAULT);
+                }
+            };
+            #[cfg(not(feature = "hash-update"))]
+            let hmac = session.generate_hmac_with_response_finished_key(transcript_data.as_ref());
+            #[cfg(feature = "hash-update")]
+            let hmac = session.generate_hmac_with_response_finished_key(
+                crypto::hash::hash_ctx_finalize(message_k.clone())
+                    .unwrap()
+                    .as_ref(),
+            );
+            if hmac.is_err() {
+                let _ = session.teardown(session_id);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+            let hmac = hmac.unwrap();
+            #[cfg(not(feature = "hash-update"))]
             if message_k.append_message(hmac.as_ref()).is_none() {
                 let _ = session.teardown(session_id);
                 self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
                 return spdm_result_err!(EFAULT);
-                session.runtime_info.message_k = message_k;
             }
+            #[cfg(feature = "hash-update")]
+            crypto::hash::hash_ctx_update(&mut message_k, hmac.as_ref());
+
+            // patch the message before send
+            writer.mut_used_slice()[(used - verify_data_size)..used].copy_from_slice(hmac.as_ref());
         }
 
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+        };
+        #[cfg(not(feature = "hash-update"))]
+        {
+            session.runtime_info.message_k = message_k;
+        }
         #[cfg(feature = "hash-update")]
         {
-            crypto::hash::hash_ctx_update(&mut message_k, hmac.as_ref());
-
             session.runtime_info.message_k = Some(message_k);
         }
 
-        // patch the message before send
-        writer.mut_used_slice()[(used - base_hash_size - base_asym_size)..(used - base_hash_size)]
-            .copy_from_slice(signature.as_ref());
-        writer.mut_used_slice()[(used - base_hash_size)..used].copy_from_slice(hmac.as_ref()); // impl AsRef<[u8]> for SpdmDigestStruct
-
-        let heartbeat_period = self.common.config_info.heartbeat_period;
-        let secure_spdm_version_sel = self.common.config_info.secure_spdm_version;
-        let session = self.common.get_session_via_id(session_id).unwrap();
         session.set_session_state(crate::common::session::SpdmSessionState::SpdmSessionHandshaking);
 
         session.heartbeat_period = heartbeat_period;
-        if return_opaque.data_size != 0 {
+        if let Some(secure_spdm_version_sel) = secure_spdm_version_sel {
             session.secure_spdm_version_sel = secure_spdm_version_sel;
         }
+        session.mut_auth_requested = mut_auth_req;
+        session.slot_id = slot_id;
+        session.req_slot_id = 0;
+        session.session_policy = session_policy;
+
+        if mut_auth_req.contains(SpdmKeyExchangeMutAuthAttributes::MUT_AUTH_REQ_WITH_GET_DIGESTS) {
+            self.init_spdm_encap_get_digests(session_id);
+        }
+        if in_clear_text {
+            self.clear_handshake_session_id = Some(session_id);
+        }
+
+        self.common
+            .notify_session_event(session_id, SpdmSessionEvent::HandshakeStarted);
 
         Ok(())
     }
@@ -325,8 +505,9 @@ impl<'a> ResponderContext<'a> {
     #[cfg(feature = "hash-update")]
     pub fn generate_key_exchange_rsp_signature(
         &mut self,
+        slot_id: u8,
         message_k: HashCtx,
-    ) -> SpdmResult<SpdmSignatureStruct> {
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
         let message_hash = crypto::hash::hash_ctx_finalize(message_k).unwrap();
         debug!("message_hash - {:02x?}", message_hash.as_ref());
 
@@ -347,22 +528,22 @@ impl<'a> ResponderContext<'a> {
                 .ok_or_else(|| spdm_err!(ENOMEM))?;
         }
 
-        crypto::asym_sign::sign(
-            self.common.negotiate_info.base_hash_sel,
+        self.common.sign_start_with_slot_key(
+            slot_id,
             self.common.negotiate_info.base_asym_sel,
             message.as_ref(),
         )
-        .ok_or_else(|| spdm_err!(EFAULT))
     }
 
     #[cfg(not(feature = "hash-update"))]
     pub fn generate_key_exchange_rsp_signature(
         &mut self,
+        slot_id: u8,
         message_k: &ManagedBuffer,
-    ) -> SpdmResult<SpdmSignatureStruct> {
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
         let mut message = self
             .common
-            .calc_rsp_transcript_data(false, message_k, None)?;
+            .calc_rsp_transcript_data(slot_id, false, message_k, None)?;
         // we dont need create message hash for verify
         // we just print message hash for debug purpose
         let message_hash =
@@ -386,12 +567,11 @@ impl<'a> ResponderContext<'a> {
                 .ok_or_else(|| spdm_err!(ENOMEM))?;
         }
 
-        crypto::asym_sign::sign(
-            self.common.negotiate_info.base_hash_sel,
+        self.common.sign_start_with_slot_key(
+            slot_id,
             self.common.negotiate_info.base_asym_sel,
             message.as_ref(),
         )
-        .ok_or_else(|| spdm_err!(EFAULT))
     }
 }
 
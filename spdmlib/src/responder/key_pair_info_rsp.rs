@@ -0,0 +1,213 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::SpdmCodec;
+use crate::message::*;
+use crate::protocol::*;
+use crate::responder::*;
+use crate::secret;
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_get_key_pair_info(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_key_pair_info_response(bytes, &mut writer);
+        match session_id {
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+        }
+    }
+
+    pub fn write_spdm_key_pair_info_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            < SpdmVersion::SpdmVersion13.get_u8()
+            || !self
+                .common
+                .config_info
+                .rsp_capabilities
+                .contains(SpdmResponseCapabilityFlags::GET_KEY_PAIR_INFO_CAP)
+        {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let get_key_pair_info =
+            SpdmGetKeyPairInfoRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let key_pair_id = if let Some(get_key_pair_info) = get_key_pair_info {
+            debug!("!!! get_key_pair_info : {:02x?}\n", get_key_pair_info);
+            get_key_pair_info.key_pair_id
+        } else {
+            error!("!!! get_key_pair_info : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        };
+
+        // KeyPairID starts from 1.
+        let key_pair_info = if key_pair_id == 0 {
+            None
+        } else {
+            secret::spdm_get_key_pair_info(key_pair_id)
+        };
+        let key_pair_info = match key_pair_info {
+            Some(key_pair_info)
+                if key_pair_info.key_pair_id == key_pair_id
+                    && key_pair_id <= key_pair_info.total_key_pairs =>
+            {
+                key_pair_info
+            }
+            _ => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
+
+        info!("send spdm key_pair_info\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseKeyPairInfo,
+            },
+            payload: SpdmMessagePayload::SpdmKeyPairInfoResponse(key_pair_info),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+
+    pub fn handle_spdm_set_key_pair_info(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_set_key_pair_info_ack_response(bytes, &mut writer);
+        match session_id {
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+        }
+    }
+
+    pub fn write_spdm_set_key_pair_info_ack_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            < SpdmVersion::SpdmVersion13.get_u8()
+            || !self
+                .common
+                .config_info
+                .rsp_capabilities
+                .contains(SpdmResponseCapabilityFlags::SET_KEY_PAIR_INFO_CAP)
+        {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestSetKeyPairInfo.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let set_key_pair_info =
+            SpdmSetKeyPairInfoRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let set_key_pair_info = match set_key_pair_info {
+            Some(set_key_pair_info)
+                if set_key_pair_info.key_pair_id != 0
+                    && !matches!(
+                        set_key_pair_info.operation,
+                        SpdmSetKeyPairInfoOperation::Unknown(_)
+                    ) =>
+            {
+                debug!("!!! set_key_pair_info : {:02x?}\n", set_key_pair_info);
+                set_key_pair_info
+            }
+            _ => {
+                error!("!!! set_key_pair_info : fail !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+        };
+
+        if secret::spdm_set_key_pair_info(&set_key_pair_info).is_err() {
+            error!("!!! set_key_pair_info : operation fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorOperationFailed, 0, writer);
+            return;
+        }
+
+        info!("send spdm set_key_pair_info_ack\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseSetKeyPairInfoAck,
+            },
+            payload: SpdmMessagePayload::SpdmSetKeyPairInfoAckResponse(
+                SpdmSetKeyPairInfoAckResponsePayload {},
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::{Codec, Writer};
+
+    #[test]
+    fn test_case0_handle_spdm_get_key_pair_info() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion12;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion12,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetKeyPairInfo,
+        };
+        value.encode(&mut writer);
+        let value = SpdmGetKeyPairInfoRequestPayload { key_pair_id: 1 };
+        value.spdm_encode(&mut context.common, &mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_key_pair_info_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnsupportedRequest
+            );
+        }
+    }
+}
@@ -2,7 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use crate::common::SpdmCodec;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
 use crate::message::*;
 use crate::responder::*;
 
@@ -37,23 +37,41 @@ impl<'a> ResponderContext<'a> {
         let key_update_req = key_update_req.unwrap();
 
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = self.common.get_session_via_id(session_id).unwrap();
-        match key_update_req.key_update_operation {
-            SpdmKeyUpdateOperation::SpdmUpdateSingleKey => {
-                let _ = session.create_data_secret_update(spdm_version_sel, true, false);
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
             }
-            SpdmKeyUpdateOperation::SpdmUpdateAllKeys => {
-                let _ = session.create_data_secret_update(spdm_version_sel, true, true);
-                let _ = session.activate_data_secret_update(spdm_version_sel, true, true, true);
+        };
+        // The KEY_UPDATE_ACK of UpdateAllKeys goes out with the new response key,
+        // the old request key is kept until VerifyNewKey is received.
+        let result = match key_update_req.key_update_operation {
+            SpdmKeyUpdateOperation::SpdmUpdateSingleKey => {
+                session.create_data_secret_update(spdm_version_sel, true, false)
             }
+            SpdmKeyUpdateOperation::SpdmUpdateAllKeys => session
+                .create_data_secret_update(spdm_version_sel, true, true)
+                .and_then(|_| {
+                    session.activate_data_secret_update(spdm_version_sel, false, true, true)
+                }),
             SpdmKeyUpdateOperation::SpdmVerifyNewKey => {
-                let _ = session.activate_data_secret_update(spdm_version_sel, true, false, true);
+                session.activate_data_secret_update(spdm_version_sel, true, false, true)
             }
             _ => {
                 error!("!!! key_update req : fail !!!\n");
                 self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
                 return false;
             }
+        };
+        if result.is_err() {
+            error!("!!! key_update : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return false;
+        }
+        if key_update_req.key_update_operation == SpdmKeyUpdateOperation::SpdmVerifyNewKey {
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::KeyUpdated);
         }
 
         info!("send spdm key_update rsp\n");
@@ -6,6 +6,7 @@ use crate::common::opaque::SpdmOpaqueStruct;
 use crate::common::ManagedBuffer;
 use crate::common::SpdmCodec;
 use crate::crypto;
+use crate::crypto::SpdmAsymSignOperation;
 #[cfg(not(feature = "hash-update"))]
 use crate::error::spdm_result_err;
 use crate::error::{spdm_err, SpdmResult};
@@ -14,6 +15,9 @@ use crate::protocol::*;
 use crate::responder::*;
 use crate::secret::*;
 
+extern crate alloc;
+use alloc::boxed::Box;
+
 impl<'a> ResponderContext<'a> {
     pub fn handle_spdm_measurement(&mut self, session_id: Option<u32>, bytes: &[u8]) {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
@@ -44,6 +48,7 @@ impl<'a> ResponderContext<'a> {
             debug!("!!! get_measurements : {:02x?}\n", get_measurements);
         } else {
             error!("!!! get_measurements : fail !!!\n");
+            self.reset_message_m(session_id);
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return;
         }
@@ -55,6 +60,32 @@ impl<'a> ResponderContext<'a> {
             .measurement_attributes
             .contains(SpdmMeasurementeAttributes::SIGNATURE_REQUESTED)
         {
+            if self
+                .common
+                .get_my_cert_chain(get_measurements.slot_id)
+                .is_none()
+            {
+                error!(
+                    "!!! get_measurements : slot {} is not provisioned !!!\n",
+                    get_measurements.slot_id
+                );
+                self.reset_message_m(session_id);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
+            if !self.common.is_key_usage_allowed(
+                &self.common.provision_info.my_key_usage_mask,
+                get_measurements.slot_id,
+                SpdmKeyUsageBitMask::MEASUREMENT_USE,
+            ) {
+                error!(
+                    "!!! get_measurements : slot {} key is not for measurement !!!\n",
+                    get_measurements.slot_id
+                );
+                self.reset_message_m(session_id);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return;
+            }
             self.common.runtime_info.need_measurement_signature = true;
         } else {
             self.common.runtime_info.need_measurement_signature = false;
@@ -69,12 +100,18 @@ impl<'a> ResponderContext<'a> {
         let runtime_content_change_support = self.common.config_info.runtime_content_change_support;
         let content_changed = self.common.runtime_info.content_changed;
         let base_asym_sel = self.common.negotiate_info.base_asym_sel;
+        let measurement_hash_sel = self.common.negotiate_info.measurement_hash_sel;
+        // no digest is computed with RAW_BIT_STREAM, the provider answers raw blocks
+        let measurement_hash_algo = measurement_hash_sel
+            .to_base_hash_algo()
+            .unwrap_or(base_hash_sel);
 
         #[cfg(not(feature = "hash-update"))]
         if self
             .append_message_m_response(session_id, &bytes[..reader.used()])
             .is_none()
         {
+            self.reset_message_m(session_id);
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return;
         }
@@ -82,16 +119,26 @@ impl<'a> ResponderContext<'a> {
         info!("send spdm measurement\n");
 
         let mut nonce = [0u8; SPDM_NONCE_SIZE];
-        let _ = crypto::rand::get_random(&mut nonce);
+        if self.common.get_random(&mut nonce).is_err() {
+            error!("!!! get_measurements : no random !!!\n");
+            self.reset_message_m(session_id);
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
 
-        let real_measurement_block_count = spdm_measurement_collection(
+        let real_measurement_block_count = match spdm_measurement_collection(
             spdm_version_sel,
             measurement_specification_sel,
-            base_hash_sel,
+            measurement_hash_algo,
             SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber.get_u8() as usize,
-        )
-        .unwrap()
-        .number_of_blocks;
+        ) {
+            Some(measurement_record) => measurement_record.number_of_blocks,
+            None => {
+                self.reset_message_m(session_id);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return;
+            }
+        };
 
         let number_of_measurement: u8 = if get_measurements.measurement_operation
             == SpdmMeasurementOperation::SpdmMeasurementRequestAll
@@ -102,33 +149,96 @@ impl<'a> ResponderContext<'a> {
         } else {
             1
         };
-        let measurement_record = if get_measurements.measurement_operation
+        let mut measurement_record = if get_measurements.measurement_operation
             == SpdmMeasurementOperation::SpdmMeasurementRequestAll
         {
-            spdm_measurement_collection(
+            match spdm_measurement_collection(
                 spdm_version_sel,
                 measurement_specification_sel,
-                base_hash_sel,
+                measurement_hash_algo,
                 SpdmMeasurementOperation::SpdmMeasurementRequestAll.get_u8() as usize,
-            )
-            .unwrap()
-        } else if let SpdmMeasurementOperation::Unknown(index) =
-            get_measurements.measurement_operation
+            ) {
+                Some(measurement_record) => measurement_record,
+                None => {
+                    self.reset_message_m(session_id);
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                    return;
+                }
+            }
+        } else if get_measurements.measurement_operation
+            == SpdmMeasurementOperation::SpdmMeasurementRequestManifest
+            || get_measurements.measurement_operation
+                == SpdmMeasurementOperation::SpdmMeasurementRequestDeviceMode
         {
-            if index > real_measurement_block_count {
+            if spdm_version_sel.get_u8() < SpdmVersion::SpdmVersion12.get_u8() {
+                self.reset_message_m(session_id);
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
                 return;
             }
-            spdm_measurement_collection(
+            match spdm_measurement_collection(
+                spdm_version_sel,
+                measurement_specification_sel,
+                measurement_hash_algo,
+                get_measurements.measurement_operation.get_u8() as usize,
+            ) {
+                Some(measurement_record) if measurement_record.number_of_blocks == 1 => {
+                    measurement_record
+                }
+                _ => {
+                    error!("!!! get_measurements : special index not supported !!!\n");
+                    self.reset_message_m(session_id);
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return;
+                }
+            }
+        } else if let SpdmMeasurementOperation::Unknown(index) =
+            get_measurements.measurement_operation
+        {
+            // indices need not be contiguous, the provider knows which exist
+            match spdm_measurement_collection(
                 spdm_version_sel,
                 measurement_specification_sel,
-                base_hash_sel,
+                measurement_hash_algo,
                 index as usize,
-            )
-            .unwrap()
+            ) {
+                Some(measurement_record) if measurement_record.number_of_blocks == 1 => {
+                    measurement_record
+                }
+                _ => {
+                    error!("!!! get_measurements : index {} not found !!!\n", index);
+                    self.reset_message_m(session_id);
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return;
+                }
+            }
         } else {
             SpdmMeasurementRecordStructure::default()
         };
 
+        // the provider may return raw bit streams, hash them unless requested as is
+        let raw_bit_stream_requested = spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion12.get_u8()
+            && get_measurements
+                .measurement_attributes
+                .contains(SpdmMeasurementeAttributes::RAW_BIT_STREAM_REQUESTED);
+        if !raw_bit_stream_requested
+            && measurement_hash_sel != SpdmMeasurementHashAlgo::RAW_BIT_STREAM
+        {
+            let number_of_blocks = measurement_record.number_of_blocks as usize;
+            for block in measurement_record.record.iter_mut().take(number_of_blocks) {
+                if !block.measurement.is_raw_bit_stream() {
+                    continue;
+                }
+                if let Some(measurement) = crate::common::spdm_measurement_to_digest(
+                    &block.measurement,
+                    measurement_hash_algo,
+                ) {
+                    block.measurement_size = measurement.value_size + 3;
+                    block.measurement = measurement;
+                }
+            }
+        }
+
         let content_changed =
             if runtime_content_change_support && spdm_version_sel == SpdmVersion::SpdmVersion12 {
                 content_changed
@@ -161,20 +271,28 @@ impl<'a> ResponderContext<'a> {
         };
 
         response.spdm_encode(&mut self.common, writer);
+        if self.check_response_size(session_id.is_some(), writer) {
+            // the requester drops L1/L2 on the error
+            self.reset_message_m(session_id);
+            return;
+        }
         let used = writer.used();
 
         #[cfg(feature = "hash-update")]
         let message_m = match session_id {
             Some(session_id) => {
-                let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                    s
-                } else {
-                    panic!("invalid session id");
+                let session = match self.common.get_session_mut(session_id) {
+                    Ok(session) => session,
+                    Err(_) => {
+                        writer.clear();
+                        self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                        return;
+                    }
                 };
 
                 if session.runtime_info.message_m.is_none() {
                     session.runtime_info.message_m = crypto::hash::hash_ctx_init(base_hash_sel);
-                    if spdm_version_sel == SpdmVersion::SpdmVersion12 {
+                    if spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
                         crypto::hash::hash_ctx_update(
                             session.runtime_info.message_m.as_mut().unwrap(),
                             message_a.as_ref(),
@@ -188,7 +306,7 @@ impl<'a> ResponderContext<'a> {
                 if self.common.runtime_info.message_mes_no_session.is_none() {
                     self.common.runtime_info.message_mes_no_session =
                         crypto::hash::hash_ctx_init(base_hash_sel);
-                    if spdm_version_sel == SpdmVersion::SpdmVersion12 {
+                    if spdm_version_sel.get_u8() >= SpdmVersion::SpdmVersion12.get_u8() {
                         crypto::hash::hash_ctx_update(
                             self.common
                                 .runtime_info
@@ -222,59 +340,57 @@ impl<'a> ResponderContext<'a> {
                 &writer.used_slice()[..temp_used],
             );
 
-            let signature = self.generate_measurement_signature(session_id);
-            if signature.is_err() {
+            // patch the message before send, or defer it while the signer is busy
+            if self
+                .generate_measurement_signature(session_id, get_measurements.slot_id)
+                .and_then(|sign_operation| {
+                    self.write_spdm_signed_response(
+                        session_id,
+                        SpdmRequestResponseCode::SpdmRequestGetMeasurements,
+                        sign_operation,
+                        writer,
+                    )
+                })
+                .is_err()
+            {
+                self.reset_message_m(session_id);
                 self.send_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0);
                 return;
             }
-            let signature = signature.unwrap();
-            // patch the message before send
-            writer.mut_used_slice()[(used - base_asym_size)..used]
-                .copy_from_slice(signature.as_ref());
-            #[cfg(not(feature = "hash-update"))]
-            match session_id {
-                Some(session_id) => {
-                    self.common
-                        .get_session_via_id(session_id)
-                        .unwrap()
-                        .runtime_info
-                        .message_m
-                        .reset_message();
-                }
-                None => {
-                    self.common.runtime_info.message_m.reset_message();
-                }
-            }
-            #[cfg(feature = "hash-update")]
-            match session_id {
-                Some(session_id) => {
-                    self.common
-                        .get_session_via_id(session_id)
-                        .unwrap()
-                        .runtime_info
-                        .message_m = None;
-                }
-                None => {
-                    self.common.runtime_info.message_mes_no_session = None;
-                }
-            }
+            self.reset_message_m(session_id);
         } else {
             #[cfg(not(feature = "hash-update"))]
             self.append_message_m_response(session_id, writer.used_slice());
             #[cfg(feature = "hash-update")]
-            match session_id {
-                Some(_) => {
-                    crypto::hash::hash_ctx_update(message_m.as_mut().unwrap(), writer.used_slice());
+            crypto::hash::hash_ctx_update(message_m.as_mut().unwrap(), writer.used_slice());
+        }
+    }
+    // Start L1/L2 over, after a signed MEASUREMENTS or an error.
+    fn reset_message_m(&mut self, session_id: Option<u32>) {
+        #[cfg(not(feature = "hash-update"))]
+        match session_id {
+            Some(session_id) => {
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    session.runtime_info.message_m.reset_message();
                 }
-                None => {
-                    crypto::hash::hash_ctx_update(
-                        self.common.runtime_info.message_m.as_mut().unwrap(),
-                        writer.used_slice(),
-                    );
+            }
+            None => {
+                self.common.runtime_info.message_m.reset_message();
+            }
+        }
+        #[cfg(feature = "hash-update")]
+        match session_id {
+            Some(session_id) => {
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    session.runtime_info.message_m = None;
                 }
             }
+            None => {
+                self.common.runtime_info.message_mes_no_session = None;
+            }
         }
     }
+
     #[cfg(not(feature = "hash-update"))]
     pub fn append_message_m_response(
         &mut self,
@@ -283,23 +399,33 @@ impl<'a> ResponderContext<'a> {
     ) -> Option<usize> {
         match session_id {
             None => self.common.runtime_info.message_m.append_message(bytes),
-            Some(session_id) => {
-                let session = self.common.get_session_via_id(session_id).unwrap();
-                session.runtime_info.message_m.append_message(bytes)
-            }
+            Some(session_id) => self
+                .common
+                .get_session_mut(session_id)
+                .ok()?
+                .runtime_info
+                .message_m
+                .append_message(bytes),
         }
     }
 
     pub fn generate_measurement_signature(
         &mut self,
         session_id: Option<u32>,
-    ) -> SpdmResult<SpdmSignatureStruct> {
-        let mut message = ManagedBuffer::default();
+        slot_id: u8,
+    ) -> SpdmResult<Box<dyn SpdmAsymSignOperation>> {
+        // with hash-update only the hash of the transcript is kept
+        #[cfg(not(feature = "hash-update"))]
+        let mut transcript = ManagedBuffer::default();
+        #[cfg(feature = "hash-update")]
+        let transcript = ManagedBuffer::default();
 
         #[cfg(not(feature = "hash-update"))]
-        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            >= SpdmVersion::SpdmVersion12.get_u8()
+        {
             let message_a = self.common.runtime_info.message_a.clone();
-            message
+            transcript
                 .append_message(message_a.as_ref())
                 .map_or_else(|| spdm_result_err!(ENOMEM), |_| Ok(()))?;
         }
@@ -307,17 +433,13 @@ impl<'a> ResponderContext<'a> {
         #[cfg(not(feature = "hash-update"))]
         match session_id {
             None => {
-                message
+                transcript
                     .append_message(self.common.runtime_info.message_m.as_ref())
                     .ok_or_else(|| spdm_err!(ENOMEM))?;
             }
             Some(session_id) => {
-                let session = if let Some(s) = self.common.get_session_via_id(session_id) {
-                    s
-                } else {
-                    return spdm_result_err!(EINVAL);
-                };
-                message
+                let session = self.common.get_session(session_id)?;
+                transcript
                     .append_message(session.runtime_info.message_m.as_ref())
                     .ok_or_else(|| spdm_err!(ENOMEM))?;
             }
@@ -325,16 +447,17 @@ impl<'a> ResponderContext<'a> {
         // we dont need create message hash for verify
         // we just print message hash for debug purpose
         #[cfg(not(feature = "hash-update"))]
-        let message_hash =
-            crypto::hash::hash_all(self.common.negotiate_info.base_hash_sel, message.as_ref())
-                .ok_or_else(|| spdm_err!(EFAULT))?;
+        let message_hash = crypto::hash::hash_all(
+            self.common.negotiate_info.base_hash_sel,
+            transcript.as_ref(),
+        )
+        .ok_or_else(|| spdm_err!(EFAULT))?;
 
         #[cfg(feature = "hash-update")]
         let message_hash = match session_id {
             Some(session_id) => crypto::hash::hash_ctx_finalize(
                 self.common
-                    .get_session_via_id(session_id)
-                    .unwrap()
+                    .get_session_mut(session_id)?
                     .runtime_info
                     .message_m
                     .as_mut()
@@ -354,28 +477,15 @@ impl<'a> ResponderContext<'a> {
         };
         debug!("message_hash - {:02x?}", message_hash.as_ref());
 
-        if self.common.negotiate_info.spdm_version_sel == SpdmVersion::SpdmVersion12 {
-            message.reset_message();
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_PREFIX_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_VERSION_1_2_SIGNING_CONTEXT_ZEROPAD_6)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(&SPDM_MEASUREMENTS_SIGN_CONTEXT)
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-            message
-                .append_message(message_hash.as_ref())
-                .ok_or_else(|| spdm_err!(ENOMEM))?;
-        }
+        let message = self
+            .common
+            .get_measurement_signing_message(transcript.as_ref(), message_hash.as_ref())?;
 
-        crypto::asym_sign::sign(
-            self.common.negotiate_info.base_hash_sel,
+        self.common.sign_start_with_slot_key(
+            slot_id,
             self.common.negotiate_info.base_asym_sel,
             message.as_ref(),
         )
-        .ok_or_else(|| spdm_err!(EFAULT))
     }
 }
 
@@ -402,6 +512,7 @@ mod tests_responder {
         );
 
         crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        crate::secret::register(SECRET_IMPL.clone());
 
         context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         context.common.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
@@ -539,6 +650,7 @@ mod tests_responder {
         );
 
         crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        crate::secret::register(SECRET_IMPL.clone());
 
         context.common.negotiate_info.base_hash_sel = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
         context.common.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
@@ -2,28 +2,43 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+mod async_context;
+mod builder;
 mod context;
 
 mod algorithm_rsp;
 mod capability_rsp;
 mod certificate_rsp;
 mod challenge_rsp;
+mod chunk_rsp;
 mod digest_rsp;
+mod encap_rsp;
 mod end_session_rsp;
+mod endpoint_info_rsp;
+mod event_rsp;
 mod finish_rsp;
 mod heartbeat_rsp;
 mod key_exchange_rsp;
+mod key_pair_info_rsp;
 mod key_update_rsp;
 mod measurement_rsp;
 mod psk_exchange_rsp;
 mod psk_finish_rsp;
 mod respond_if_ready_rsp;
+mod set_certificate_rsp;
+mod shared_state;
 mod version_rsp;
 
 mod error_rsp;
 mod vendor_rsp;
 
+pub use async_context::AsyncResponderContext;
+pub use builder::ResponderBuilder;
 pub use context::ResponderContext;
+pub use encap_rsp::SpdmEncapRequest;
+pub use respond_if_ready_rsp::SpdmDeferralHandler;
+pub use shared_state::{SpdmResponderSharedState, SpdmSharedConnection, SpdmSharedSessionInfo};
+pub use vendor_rsp::{SpdmVendorDefinedHandler, MAX_SPDM_VENDOR_DEFINED_HANDLER_COUNT};
 
 use crate::config;
 use codec::{Codec, Reader, Writer};
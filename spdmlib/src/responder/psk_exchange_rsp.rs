@@ -3,10 +3,11 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use crate::common::opaque::SpdmOpaqueStruct;
-use crate::common::SpdmCodec;
-use crate::common::SpdmOpaqueSupport;
+use crate::common::session::SpdmSessionId;
+use crate::common::SpdmPskStore;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
 use crate::crypto;
-use crate::error::{spdm_result_err, SpdmResult};
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::protocol::*;
 use crate::responder::*;
@@ -18,6 +19,11 @@ use alloc::boxed::Box;
 use crate::common::ManagedBuffer;
 
 impl<'a> ResponderContext<'a> {
+    /// Register the store used to look up the PSK by the PSKHint of PSK_EXCHANGE.
+    pub fn register_psk_store(&mut self, psk_store: &'a dyn SpdmPskStore) {
+        self.common.psk_store = Some(psk_store);
+    }
+
     pub fn handle_spdm_psk_exchange(&mut self, bytes: &[u8]) -> SpdmResult {
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let mut writer = Writer::init(&mut send_buffer);
@@ -37,10 +43,28 @@ impl<'a> ResponderContext<'a> {
             SpdmPskExchangeRequestPayload::spdm_read(&mut self.common, &mut reader);
 
         let mut return_opaque = SpdmOpaqueStruct::default();
+        let mut secure_spdm_version_sel = None;
+        let psk_info = psk_exchange_req
+            .as_ref()
+            .and_then(|psk_exchange_req| self.common.get_psk(psk_exchange_req.psk_hint.as_ref()));
 
         if let Some(psk_exchange_req) = &psk_exchange_req {
             debug!("!!! psk_exchange req : {:02x?}\n", psk_exchange_req);
 
+            if psk_info.is_none() {
+                error!("!!! psk_exchange req : unknown psk hint !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+
+            if psk_exchange_req.psk_context.data_size
+                < self.common.negotiate_info.base_hash_sel.get_size()
+            {
+                error!("!!! psk_exchange req : requester context too short !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EINVAL);
+            }
+
             if (psk_exchange_req.measurement_summary_hash_type
                 == SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeTcb)
                 || (psk_exchange_req.measurement_summary_hash_type
@@ -55,34 +79,16 @@ impl<'a> ResponderContext<'a> {
                 .opaque
                 .rsp_get_dmtf_supported_secure_spdm_version_list(&mut self.common)
             {
-                for index in 0..secured_message_version_list.version_count as usize {
-                    if secured_message_version_list.versions_list[index].get_secure_spdm_version()
-                        == self.common.config_info.secure_spdm_version
-                    {
-                        if self
-                            .common
-                            .negotiate_info
-                            .opaque_data_support
-                            .contains(SpdmOpaqueSupport::OPAQUE_DATA_FMT1)
-                        {
-                            return_opaque.data_size =
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT1
-                                    .len() as u16;
-                            return_opaque.data[..(return_opaque.data_size as usize)]
-                                .copy_from_slice(
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT1
-                                    .as_ref(),
-                            );
-                        } else {
-                            return_opaque.data_size =
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT0
-                                    .len() as u16;
-                            return_opaque.data[..(return_opaque.data_size as usize)]
-                                .copy_from_slice(
-                                crate::common::opaque::RSP_DMTF_OPAQUE_DATA_VERSION_SELECTION_FMT0
-                                    .as_ref(),
-                            );
-                        }
+                if let Some(selected_version) = crate::common::opaque::select_secure_spdm_version(
+                    &secured_message_version_list,
+                    self.common.config_info.secure_spdm_version,
+                ) {
+                    if let Ok(opaque) = SpdmOpaqueStruct::rsp_dmtf_secure_spdm_version_selection(
+                        &mut self.common,
+                        selected_version,
+                    ) {
+                        return_opaque = opaque;
+                        secure_spdm_version_sel = Some(selected_version.get_secure_spdm_version());
                     }
                 }
             }
@@ -92,6 +98,12 @@ impl<'a> ResponderContext<'a> {
             return spdm_result_err!(EFAULT);
         }
 
+        if self.common.get_next_avaiable_session().is_none() || !self.reserve_shared_session() {
+            error!("!!! psk_exchange req : too many sessions !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorSessionLimitExceeded, 0, writer);
+            return spdm_result_err!(ENOMEM);
+        }
+
         #[cfg(feature = "hash-update")]
         let mut message_k =
             crypto::hash::hash_ctx_init(self.common.negotiate_info.base_hash_sel).unwrap();
@@ -100,10 +112,44 @@ impl<'a> ResponderContext<'a> {
 
         info!("send spdm psk_exchange rsp\n");
 
-        let mut psk_context = [0u8; MAX_SPDM_PSK_CONTEXT_SIZE];
-        let _ = crypto::rand::get_random(&mut psk_context);
+        let psk_info = psk_info.unwrap();
+        // ResponderContext is only sent by a responder supporting it
+        let psk_context = if !self
+            .common
+            .negotiate_info
+            .rsp_capabilities_sel
+            .contains(SpdmResponseCapabilityFlags::PSK_CAP_WITH_CONTEXT)
+        {
+            SpdmPskContextStruct::default()
+        } else if psk_info.psk_context.data_size == 0 {
+            let mut psk_context = SpdmPskContextStruct {
+                data_size: self.common.negotiate_info.base_hash_sel.get_size(),
+                data: [0u8; MAX_SPDM_PSK_CONTEXT_SIZE],
+            };
+            if let Err(e) = self.common.get_random(&mut psk_context.data) {
+                error!("!!! psk_exchange req : no random !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return Err(e);
+            }
+            psk_context
+        } else {
+            psk_info.psk_context.clone()
+        };
 
-        let rsp_session_id = 0xFFFD;
+        let rsp_session_id = match self.common.generate_session_id_half(false) {
+            Ok(rsp_session_id) => rsp_session_id,
+            Err(e) => {
+                error!("!!! psk_exchange req : no session id !!!\n");
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+                return Err(e);
+            }
+        };
+
+        let heartbeat_period = if self.common.is_heartbeat_negotiated() {
+            self.common.config_info.heartbeat_period
+        } else {
+            0
+        };
 
         let response = SpdmMessage {
             header: SpdmMessageHeader {
@@ -111,16 +157,13 @@ impl<'a> ResponderContext<'a> {
                 request_response_code: SpdmRequestResponseCode::SpdmResponsePskExchangeRsp,
             },
             payload: SpdmMessagePayload::SpdmPskExchangeResponse(SpdmPskExchangeResponsePayload {
-                heartbeat_period: self.common.config_info.heartbeat_period,
+                heartbeat_period,
                 rsp_session_id,
                 measurement_summary_hash: SpdmDigestStruct {
                     data_size: self.common.negotiate_info.base_hash_sel.get_size(),
                     data: Box::new([0xaa; SPDM_MAX_HASH_SIZE]),
                 },
-                psk_context: SpdmPskContextStruct {
-                    data_size: self.common.negotiate_info.base_hash_sel.get_size(),
-                    data: psk_context,
-                },
+                psk_context,
                 opaque: return_opaque.clone(),
                 verify_data: SpdmDigestStruct {
                     data_size: self.common.negotiate_info.base_hash_sel.get_size(),
@@ -130,6 +173,9 @@ impl<'a> ResponderContext<'a> {
         };
 
         response.spdm_encode(&mut self.common, writer);
+        if self.check_response_size(false, writer) {
+            return Ok(());
+        }
         let used = writer.used();
 
         let base_hash_size = self.common.negotiate_info.base_hash_sel.get_size() as usize;
@@ -160,7 +206,9 @@ impl<'a> ResponderContext<'a> {
 
         // create session - generate the handshake secret (including finished_key)
         #[cfg(not(feature = "hash-update"))]
-        let th1 = self.common.calc_rsp_transcript_hash(true, &message_k, None);
+        let th1 = self
+            .common
+            .calc_rsp_transcript_hash(INVALID_SLOT, true, &message_k, None);
         #[cfg(feature = "hash-update")]
         let th1 = crypto::hash::hash_ctx_finalize(message_k.clone());
         #[cfg(feature = "hash-update")]
@@ -179,46 +227,56 @@ impl<'a> ResponderContext<'a> {
         let dhe_algo = self.common.negotiate_info.dhe_sel;
         let aead_algo = self.common.negotiate_info.aead_sel;
         let key_schedule_algo = self.common.negotiate_info.key_schedule_sel;
-        let sequence_number_count = self.common.transport_encap.get_sequence_number_count();
-        let max_random_count = self.common.transport_encap.get_max_random_count();
+        let transport_param = self.common.get_session_transport_param();
 
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = self.common.get_next_avaiable_session();
-        if session.is_none() {
-            error!("!!! too many sessions : fail !!!\n");
-            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-            return spdm_result_err!(EFAULT);
-        }
-
-        let session = session.unwrap();
+        let session = self
+            .common
+            .get_next_avaiable_session()
+            .ok_or(spdm_err!(ENOMEM))?;
         let session_id =
-            ((psk_exchange_req.unwrap().req_session_id as u32) << 16) + rsp_session_id as u32;
+            SpdmSessionId::new(psk_exchange_req.unwrap().req_session_id, rsp_session_id).as_u32();
         session.setup(session_id).unwrap();
         session.set_use_psk(true);
-        let mut psk_key = SpdmDheFinalKeyStruct {
-            data_size: b"TestPskData\0".len() as u16,
-            data: Box::new([0; SPDM_MAX_DHE_KEY_SIZE]),
-        };
-        psk_key.data[0..(psk_key.data_size as usize)].copy_from_slice(b"TestPskData\0");
+        session.slot_id = INVALID_SLOT;
         session.set_crypto_param(hash_algo, dhe_algo, aead_algo, key_schedule_algo);
-        session.set_transport_param(sequence_number_count, max_random_count);
-        session.set_dhe_secret(spdm_version_sel, psk_key)?; // transfer the ownership out
-        session
-            .generate_handshake_secret(spdm_version_sel, &th1)
-            .unwrap();
+        session.set_transport_param(transport_param);
+        // transfer the ownership out
+        if session
+            .set_dhe_secret(spdm_version_sel, psk_info.psk)
+            .is_err()
+            || session
+                .generate_handshake_secret(spdm_version_sel, &th1)
+                .is_err()
+        {
+            let _ = session.teardown(session_id);
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return spdm_result_err!(ESEC);
+        }
 
         // generate HMAC with finished_key
         #[cfg(not(feature = "hash-update"))]
-        let transcript_data = self.common.calc_rsp_transcript_data(true, &message_k, None);
+        let transcript_data =
+            self.common
+                .calc_rsp_transcript_data(INVALID_SLOT, true, &message_k, None);
         #[cfg(not(feature = "hash-update"))]
         if transcript_data.is_err() {
+            if let Ok(session) = self.common.get_session_mut(session_id) {
+                let _ = session.teardown(session_id);
+            }
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return spdm_result_err!(EFAULT);
         }
         #[cfg(not(feature = "hash-update"))]
         let transcript_data = transcript_data.unwrap();
 
-        let session = self.common.get_session_via_id(session_id).unwrap();
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+        };
         #[cfg(not(feature = "hash-update"))]
         let hmac = session.generate_hmac_with_response_finished_key(transcript_data.as_ref());
         #[cfg(feature = "hash-update")]
@@ -250,16 +308,23 @@ impl<'a> ResponderContext<'a> {
 
         // patch the message before send
         writer.mut_used_slice()[(used - base_hash_size)..used].copy_from_slice(hmac.as_ref());
-        let heartbeat_period = self.common.config_info.heartbeat_period;
-        let secure_spdm_version_sel = self.common.config_info.secure_spdm_version;
-        let session = self.common.get_session_via_id(session_id).unwrap();
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return spdm_result_err!(EFAULT);
+            }
+        };
         session.set_session_state(crate::common::session::SpdmSessionState::SpdmSessionHandshaking);
 
         session.heartbeat_period = heartbeat_period;
-        if return_opaque.data_size != 0 {
+        if let Some(secure_spdm_version_sel) = secure_spdm_version_sel {
             session.secure_spdm_version_sel = secure_spdm_version_sel;
         }
 
+        self.common
+            .notify_session_event(session_id, SpdmSessionEvent::HandshakeStarted);
+
         Ok(())
     }
 }
@@ -288,7 +353,7 @@ mod tests_responder {
             config_info,
             provision_info,
         );
-        context.common.provision_info.my_cert_chain = Some(SpdmCertChainData {
+        context.common.provision_info.my_cert_chain[0] = Some(SpdmCertChainData {
             data_size: 512u16,
             data: [0u8; config::MAX_SPDM_CERT_CHAIN_DATA_SIZE],
         });
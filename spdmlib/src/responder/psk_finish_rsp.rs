@@ -4,7 +4,7 @@
 
 #[cfg(not(feature = "hash-update"))]
 use crate::common::ManagedBuffer;
-use crate::common::SpdmCodec;
+use crate::common::{SpdmCodec, SpdmSessionEvent};
 #[cfg(feature = "hash-update")]
 use crate::crypto;
 use crate::responder::*;
@@ -18,12 +18,24 @@ impl<'a> ResponderContext<'a> {
         if self.write_spdm_psk_finish_response(session_id, bytes, &mut writer) {
             let _ = self.send_secured_message(session_id, writer.used_slice(), false);
             // change state after message is sent.
-            let session = self.common.get_session_via_id(session_id).unwrap();
-            session.set_session_state(
-                crate::common::session::SpdmSessionState::SpdmSessionEstablished,
-            );
+            if let Ok(session) = self.common.get_session_mut(session_id) {
+                session.set_session_state(
+                    crate::common::session::SpdmSessionState::SpdmSessionEstablished,
+                );
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Established);
+            }
         } else {
             let _ = self.send_message(writer.used_slice());
+            // the failures past the HMAC check tear the session down
+            if self
+                .common
+                .get_immutable_session_via_id(session_id)
+                .is_none()
+            {
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
         }
     }
 
@@ -60,7 +72,13 @@ impl<'a> ResponderContext<'a> {
             panic!("message_f add the message error");
         }
 
-        let session = self.common.get_session_via_id(session_id).unwrap();
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
 
         #[cfg(feature = "hash-update")]
         let mut message_f = session.runtime_info.message_k.as_mut().cloned();
@@ -74,7 +92,7 @@ impl<'a> ResponderContext<'a> {
         #[cfg(not(feature = "hash-update"))]
         let transcript_data =
             self.common
-                .calc_rsp_transcript_data(true, message_k, Some(&message_f));
+                .calc_rsp_transcript_data(INVALID_SLOT, true, message_k, Some(&message_f));
         #[cfg(not(feature = "hash-update"))]
         if transcript_data.is_err() {
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
@@ -82,10 +100,13 @@ impl<'a> ResponderContext<'a> {
         }
         #[cfg(not(feature = "hash-update"))]
         let transcript_data = transcript_data.unwrap();
-        let session = self
-            .common
-            .get_immutable_session_via_id(session_id)
-            .unwrap();
+        let session = match self.common.get_session(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
         if session
             .verify_hmac_with_request_finished_key(
                 #[cfg(not(feature = "hash-update"))]
@@ -99,6 +120,8 @@ impl<'a> ResponderContext<'a> {
             .is_err()
         {
             error!("verify_hmac_with_request_finished_key fail");
+            self.common
+                .notify_session_event(session_id, SpdmSessionEvent::HmacFailure);
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return false;
         } else {
@@ -138,19 +161,29 @@ impl<'a> ResponderContext<'a> {
         #[cfg(feature = "hash-update")]
         crypto::hash::hash_ctx_update(message_f.as_mut().unwrap(), writer.used_slice());
 
-        let session = self.common.get_session_via_id(session_id).unwrap();
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
         // generate the data secret
         let th2;
         #[cfg(not(feature = "hash-update"))]
         {
             session.runtime_info.message_f = message_f.clone();
-            th2 = self
-                .common
-                .calc_rsp_transcript_hash(true, message_k, Some(&message_f));
+            th2 = self.common.calc_rsp_transcript_hash(
+                INVALID_SLOT,
+                true,
+                message_k,
+                Some(&message_f),
+            );
             if th2.is_err() {
                 self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
-                let session = self.common.get_session_via_id(session_id).unwrap();
-                let _ = session.teardown(session_id);
+                if let Ok(session) = self.common.get_session_mut(session_id) {
+                    let _ = session.teardown(session_id);
+                }
                 return false;
             }
         }
@@ -163,10 +196,21 @@ impl<'a> ResponderContext<'a> {
         let th2 = th2.unwrap();
         debug!("!!! th2 : {:02x?}\n", th2.as_ref());
         let spdm_version_sel = self.common.negotiate_info.spdm_version_sel;
-        let session = self.common.get_session_via_id(session_id).unwrap();
-        session
+        let session = match self.common.get_session_mut(session_id) {
+            Ok(session) => session,
+            Err(_) => {
+                self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                return false;
+            }
+        };
+        if session
             .generate_data_secret(spdm_version_sel, &th2)
-            .unwrap();
+            .is_err()
+        {
+            let _ = session.teardown(session_id);
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return false;
+        }
 
         true
     }
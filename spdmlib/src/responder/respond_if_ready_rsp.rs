@@ -3,32 +3,607 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use crate::common::SpdmCodec;
+use crate::crypto::{SpdmAsymSignOperation, SpdmAsymSignPoll};
+use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
+use crate::responder::key_exchange_rsp::SpdmKeyExchangeDeferred;
 use crate::responder::*;
 
+extern crate alloc;
+use alloc::boxed::Box;
+
+// Retry budget multiplier suggested to the requester while a signature is pending.
+const SPDM_DEFERRED_SIGN_RDTM: u8 = 2;
+
+/// Deferral of the responses to the requests of the application's choice:
+/// gets the session ID, None out of a session, and the request code. Some
+/// RDT exponent and RDTM answer the request with ERROR(ResponseNotReady),
+/// then the handler is asked again on each RESPOND_IF_READY; None answers
+/// the request as usual.
+pub type SpdmDeferralHandler<'a> =
+    &'a dyn Fn(Option<u32>, SpdmRequestResponseCode) -> Option<(u8, u8)>;
+
+// Track the response the responder deferred with ERROR(ResponseNotReady).
+pub struct SpdmRespondIfReadyContext {
+    pub pending: bool,
+    pub ready: bool,
+    pub session_id: Option<u32>,
+    pub extend_error_data: SpdmErrorResponseNotReadyExtData,
+    pub next_token: u8,
+    pub used: usize,
+    pub response: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    // request held back by the deferral handler, dispatched again once let through
+    pub request_used: usize,
+    pub request: [u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+    // signature still owed by an external signer, patched at signature_offset
+    pub sign_operation: Option<Box<dyn SpdmAsymSignOperation>>,
+    pub signature_offset: usize,
+    // KEY_EXCHANGE_RSP session setup waiting for the signature
+    pub(crate) key_exchange: Option<SpdmKeyExchangeDeferred>,
+}
+
+impl Default for SpdmRespondIfReadyContext {
+    fn default() -> Self {
+        SpdmRespondIfReadyContext {
+            pending: false,
+            ready: false,
+            session_id: None,
+            extend_error_data: SpdmErrorResponseNotReadyExtData::default(),
+            next_token: 0,
+            used: 0,
+            response: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            request_used: 0,
+            request: [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE],
+            sign_operation: None,
+            signature_offset: 0,
+            key_exchange: None,
+        }
+    }
+}
+
 impl<'a> ResponderContext<'a> {
-    pub fn handle_spdm_respond_if_ready(&mut self, bytes: &[u8]) {
+    /// Defer the responses to the requests handler picks, see
+    /// SpdmDeferralHandler.
+    pub fn register_deferral_handler(&mut self, handler: SpdmDeferralHandler<'a>) {
+        self.deferral_handler = Some(handler);
+    }
+
+    pub fn handle_spdm_respond_if_ready(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        if self.replay_spdm_deferred_request(session_id, bytes) {
+            return;
+        }
+
         let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
         let mut writer = Writer::init(&mut send_buffer);
-        self.write_spdm_respond_if_ready_response(bytes, &mut writer);
-        let _ = self.send_message(writer.used_slice());
+        self.write_spdm_respond_if_ready_response(session_id, bytes, &mut writer);
+        match session_id {
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+        }
     }
 
-    pub fn write_spdm_respond_if_ready_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+    pub fn write_spdm_respond_if_ready_response(
+        &mut self,
+        session_id: Option<u32>,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
         let mut reader = Reader::init(bytes);
         SpdmMessageHeader::read(&mut reader);
 
         let respond_if_ready =
             SpdmRespondIfReadyRequestPayload::spdm_read(&mut self.common, &mut reader);
-        if let Some(respond_if_ready) = respond_if_ready {
+        let respond_if_ready = if let Some(respond_if_ready) = respond_if_ready {
             debug!("!!! respond_if_ready : {:02x?}\n", respond_if_ready);
+            respond_if_ready
         } else {
             error!("!!! respond_if_ready : fail !!!\n");
             self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
             return;
+        };
+
+        let ctx = &self.respond_if_ready_context;
+        if !ctx.pending
+            || ctx.session_id != session_id
+            || ctx.extend_error_data.request_code != respond_if_ready.request_code
+            || ctx.extend_error_data.token != respond_if_ready.token
+        {
+            error!("!!! respond_if_ready : no matching deferred response !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        if !ctx.ready
+            && ctx.sign_operation.is_some()
+            && self.poll_spdm_deferred_signature().is_err()
+        {
+            error!("!!! respond_if_ready : signing fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
+
+        let ctx = &self.respond_if_ready_context;
+        if !ctx.ready {
+            let extend_error_data = ctx.extend_error_data.clone();
+            self.write_spdm_error_not_ready(extend_error_data, writer);
+            return;
+        }
+
+        let used = ctx.used;
+        let _ = writer.extend_from_slice(&self.respond_if_ready_context.response[..used]);
+        self.respond_if_ready_context.pending = false;
+        self.respond_if_ready_context.ready = false;
+        self.respond_if_ready_context.used = 0;
+    }
+
+    // Answer ERROR(ResponseNotReady) to a request the deferral handler holds
+    // back, keeping the request to dispatch again on RESPOND_IF_READY. The
+    // request and its response only get into the transcript then.
+    pub(crate) fn defer_spdm_request(&mut self, session_id: Option<u32>, bytes: &[u8]) -> bool {
+        let handler = match self.deferral_handler {
+            Some(handler) => handler,
+            None => return false,
+        };
+        let request_code = match SpdmMessageHeader::read_bytes(bytes) {
+            Some(message_header)
+                if !matches!(
+                    message_header.request_response_code,
+                    SpdmRequestResponseCode::SpdmRequestResponseIfReady
+                        | SpdmRequestResponseCode::SpdmRequestChunkSend
+                        | SpdmRequestResponseCode::SpdmRequestChunkGet
+                ) =>
+            {
+                message_header.request_response_code
+            }
+            _ => return false,
+        };
+        let (rdt_exponent, rdtm) = match handler(session_id, request_code) {
+            Some(deferral) => deferral,
+            None => return false,
+        };
+        if bytes.len() > self.respond_if_ready_context.request.len() {
+            return false;
+        }
+
+        info!("defer the response to {:?}\n", request_code);
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_response_not_ready(
+            session_id,
+            request_code,
+            rdt_exponent,
+            rdtm,
+            &mut writer,
+        );
+        let ctx = &mut self.respond_if_ready_context;
+        ctx.request[..bytes.len()].copy_from_slice(bytes);
+        ctx.request_used = bytes.len();
+        match session_id {
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+        }
+        true
+    }
+
+    // Dispatch the request deferred by defer_spdm_request() again, unless the
+    // deferral handler still holds it back. False if bytes is not the
+    // RESPOND_IF_READY for such a request.
+    fn replay_spdm_deferred_request(&mut self, session_id: Option<u32>, bytes: &[u8]) -> bool {
+        let mut reader = Reader::init(bytes);
+        if SpdmMessageHeader::read(&mut reader).is_none() {
+            return false;
+        }
+        let respond_if_ready =
+            match SpdmRespondIfReadyRequestPayload::spdm_read(&mut self.common, &mut reader) {
+                Some(respond_if_ready) => respond_if_ready,
+                None => return false,
+            };
+        let ctx = &self.respond_if_ready_context;
+        if !ctx.pending
+            || ctx.request_used == 0
+            || ctx.session_id != session_id
+            || ctx.extend_error_data.request_code != respond_if_ready.request_code
+            || ctx.extend_error_data.token != respond_if_ready.token
+        {
+            return false;
+        }
+
+        let mut request = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let used = ctx.request_used;
+        request[..used].copy_from_slice(&ctx.request[..used]);
+        self.drop_spdm_deferred_response();
+        if !self.defer_spdm_request(session_id, &request[..used]) {
+            match session_id {
+                None => {
+                    self.dispatch_spdm_request(&request[..used]);
+                }
+                Some(session_id) => {
+                    self.dispatch_secured_spdm_request(session_id, &request[..used]);
+                }
+            }
         }
+        true
+    }
+
+    /// Defer the response to `request_code`.
+    ///
+    /// An ERROR(ResponseNotReady) carrying a new token is written to `writer`.
+    /// The caller keeps the request to dispatch again or the response to
+    /// finish, which is returned once the requester retries with a matching
+    /// RESPOND_IF_READY. Returns the token.
+    pub(crate) fn write_spdm_response_not_ready(
+        &mut self,
+        session_id: Option<u32>,
+        request_code: SpdmRequestResponseCode,
+        rdt_exponent: u8,
+        rdtm: u8,
+        writer: &mut Writer,
+    ) -> u8 {
+        let ctx = &mut self.respond_if_ready_context;
+        let token = ctx.next_token;
+        ctx.next_token = ctx.next_token.wrapping_add(1);
+        ctx.pending = true;
+        ctx.ready = false;
+        ctx.session_id = session_id;
+        ctx.used = 0;
+        ctx.request_used = 0;
+        ctx.sign_operation = None;
+        ctx.key_exchange = None;
+        ctx.extend_error_data = SpdmErrorResponseNotReadyExtData {
+            rdt_exponent,
+            request_code: request_code.get_u8(),
+            token,
+            rdtm,
+        };
+
+        let extend_error_data = ctx.extend_error_data.clone();
+        self.write_spdm_error_not_ready(extend_error_data, writer);
+        token
+    }
+
+    /// Finish a response whose signature occupies the end of `writer`.
+    ///
+    /// A signature available right away is patched in place. If the signer
+    /// is still busy the response is kept aside and ERROR(ResponseNotReady)
+    /// is written instead; the signature is polled again on each
+    /// RESPOND_IF_READY. Fails only if the signer fails.
+    pub fn write_spdm_signed_response(
+        &mut self,
+        session_id: Option<u32>,
+        request_code: SpdmRequestResponseCode,
+        sign_operation: Box<dyn SpdmAsymSignOperation>,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        let signature_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
+        if writer.used() < signature_size {
+            return spdm_result_err!(ENOMEM);
+        }
+        let signature_offset = writer.used() - signature_size;
+        self.write_spdm_signed_response_at(
+            session_id,
+            request_code,
+            sign_operation,
+            signature_offset,
+            writer,
+        )
+    }
+
+    /// Same as `write_spdm_signed_response()`, for a signature at
+    /// `signature_offset` that is followed by more fields.
+    pub(crate) fn write_spdm_signed_response_at(
+        &mut self,
+        session_id: Option<u32>,
+        request_code: SpdmRequestResponseCode,
+        mut sign_operation: Box<dyn SpdmAsymSignOperation>,
+        signature_offset: usize,
+        writer: &mut Writer,
+    ) -> SpdmResult {
+        let used = writer.used();
+        let signature_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
+        let signature_end = signature_offset + signature_size;
+        if signature_end > used || used > self.respond_if_ready_context.response.len() {
+            return spdm_result_err!(ENOMEM);
+        }
+
+        match sign_operation.poll() {
+            SpdmAsymSignPoll::Ready(signature) => {
+                if signature.data_size as usize != signature_size {
+                    return spdm_result_err!(EFAULT);
+                }
+                writer.mut_used_slice()[signature_offset..signature_end]
+                    .copy_from_slice(signature.as_ref());
+                Ok(())
+            }
+            SpdmAsymSignPoll::Failed => spdm_result_err!(EFAULT),
+            SpdmAsymSignPoll::Pending => {
+                info!("signature pending, defer the response\n");
+                let mut response = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+                response[..used].copy_from_slice(writer.used_slice());
+                writer.clear();
+                let rdt_exponent = self.common.negotiate_info.rsp_ct_exponent_sel;
+                self.write_spdm_response_not_ready(
+                    session_id,
+                    request_code,
+                    rdt_exponent,
+                    SPDM_DEFERRED_SIGN_RDTM,
+                    writer,
+                );
+
+                let ctx = &mut self.respond_if_ready_context;
+                ctx.response[..used].copy_from_slice(&response[..used]);
+                ctx.used = used;
+                ctx.signature_offset = signature_offset;
+                ctx.sign_operation = Some(sign_operation);
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_spdm_deferred_signature(&mut self) -> SpdmResult {
+        let signature_size = self.common.negotiate_info.base_asym_sel.get_size() as usize;
+        let ctx = &mut self.respond_if_ready_context;
+        let poll = match ctx.sign_operation.as_mut() {
+            Some(sign_operation) => sign_operation.poll(),
+            None => return Ok(()),
+        };
+        let signature_end = ctx.signature_offset + signature_size;
+        match poll {
+            SpdmAsymSignPoll::Pending => return Ok(()),
+            SpdmAsymSignPoll::Ready(signature)
+                if signature.data_size as usize == signature_size && signature_end <= ctx.used =>
+            {
+                ctx.response[ctx.signature_offset..signature_end]
+                    .copy_from_slice(signature.as_ref());
+                ctx.sign_operation = None;
+            }
+            _ => {
+                self.drop_spdm_deferred_response();
+                return spdm_result_err!(EFAULT);
+            }
+        }
+
+        if let Some(key_exchange) = self.respond_if_ready_context.key_exchange.take() {
+            let used = self.respond_if_ready_context.used;
+            let mut response = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let mut writer = Writer::init(&mut response);
+            let _ = writer.extend_from_slice(&self.respond_if_ready_context.response[..used]);
+            if self
+                .finish_spdm_key_exchange_response(key_exchange, &mut writer)
+                .is_err()
+                || writer.used() != used
+            {
+                self.drop_spdm_deferred_response();
+                return spdm_result_err!(EFAULT);
+            }
+            self.respond_if_ready_context.response[..used].copy_from_slice(writer.used_slice());
+        }
+        self.respond_if_ready_context.ready = true;
+        Ok(())
+    }
+
+    fn drop_spdm_deferred_response(&mut self) {
+        let ctx = &mut self.respond_if_ready_context;
+        ctx.sign_operation = None;
+        ctx.key_exchange = None;
+        ctx.pending = false;
+        ctx.used = 0;
+        ctx.request_used = 0;
+    }
+
+    fn write_spdm_error_not_ready(
+        &mut self,
+        extend_error_data: SpdmErrorResponseNotReadyExtData,
+        writer: &mut Writer,
+    ) {
+        let error = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseError,
+            },
+            payload: SpdmMessagePayload::SpdmErrorResponse(SpdmErrorResponsePayload {
+                error_code: SpdmErrorCode::SpdmErrorResponseNotReady,
+                error_data: 0,
+                extended_data: SpdmErrorResponseExtData::SpdmErrorExtDataNotReady(
+                    extend_error_data,
+                ),
+            }),
+        };
+        error.spdm_encode(&mut self.common, writer);
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+
+    #[test]
+    fn test_case0_write_spdm_respond_if_ready_response() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        let request_code = SpdmRequestResponseCode::SpdmRequestGetMeasurements;
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        let token = context.write_spdm_response_not_ready(None, request_code, 2, 1, &mut writer);
+        let used = writer.used();
+        let mut reader = Reader::init(&response_buffer[..used]);
+        let header = SpdmMessageHeader::read(&mut reader).unwrap();
+        assert_eq!(
+            header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        let payload =
+            SpdmErrorResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseNotReady);
+
+        let mut request_buffer = [0u8; 4];
+        let mut writer = Writer::init(&mut request_buffer);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: SpdmVersion::SpdmVersion11,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestResponseIfReady,
+            },
+            payload: SpdmMessagePayload::SpdmMessageGeneral(SpdmMessageGeneralPayload {
+                param1: request_code.get_u8(),
+                param2: token,
+            }),
+        };
+        request.spdm_encode(&mut context.common, &mut writer);
+
+        // Work still outstanding: the responder keeps answering not ready.
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        context.write_spdm_respond_if_ready_response(None, &request_buffer, &mut writer);
+        let used = writer.used();
+        let mut reader = Reader::init(&response_buffer[..used]);
+        SpdmMessageHeader::read(&mut reader).unwrap();
+        let payload =
+            SpdmErrorResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseNotReady);
+
+        // A RESPOND_IF_READY with another token matches no deferred response.
+        request_buffer[3] = token.wrapping_add(1);
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        context.write_spdm_respond_if_ready_response(None, &request_buffer, &mut writer);
+        let used = writer.used();
+        let mut reader = Reader::init(&response_buffer[..used]);
+        SpdmMessageHeader::read(&mut reader).unwrap();
+        let payload =
+            SpdmErrorResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorInvalidRequest);
+    }
+
+    struct SlowSignOperation {
+        polls_left: u8,
+    }
+
+    impl SpdmAsymSignOperation for SlowSignOperation {
+        fn poll(&mut self) -> SpdmAsymSignPoll {
+            if self.polls_left > 0 {
+                self.polls_left -= 1;
+                return SpdmAsymSignPoll::Pending;
+            }
+            SpdmAsymSignPoll::Ready(SpdmSignatureStruct {
+                data_size: 96,
+                data: [0x5au8; SPDM_MAX_ASYM_KEY_SIZE],
+            })
+        }
+    }
+
+    #[test]
+    fn test_case1_write_spdm_signed_response() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+        context.common.negotiate_info.base_asym_sel = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        let request_code = SpdmRequestResponseCode::SpdmRequestChallenge;
+
+        // a signature available right away is patched in place
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        writer
+            .extend_from_slice(&[0x11u8, 0x03, 0x00, 0x00])
+            .unwrap();
+        writer.extend_from_slice(&[0u8; 96]).unwrap();
+        assert!(context
+            .write_spdm_signed_response(
+                None,
+                request_code,
+                Box::new(SlowSignOperation { polls_left: 0 }),
+                &mut writer,
+            )
+            .is_ok());
+        assert_eq!(writer.used(), 100);
+        assert_eq!(&writer.used_slice()[4..], &[0x5au8; 96][..]);
+
+        // a busy signer defers the response
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        writer
+            .extend_from_slice(&[0x11u8, 0x03, 0x00, 0x00])
+            .unwrap();
+        writer.extend_from_slice(&[0u8; 96]).unwrap();
+        assert!(context
+            .write_spdm_signed_response(
+                None,
+                request_code,
+                Box::new(SlowSignOperation { polls_left: 2 }),
+                &mut writer,
+            )
+            .is_ok());
+        let used = writer.used();
+        let mut reader = Reader::init(&response_buffer[..used]);
+        SpdmMessageHeader::read(&mut reader).unwrap();
+        let payload =
+            SpdmErrorResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseNotReady);
+        let token = match payload.extended_data {
+            SpdmErrorResponseExtData::SpdmErrorExtDataNotReady(extend_error_data) => {
+                extend_error_data.token
+            }
+            _ => panic!(),
+        };
+
+        let mut request_buffer = [0u8; 4];
+        let mut writer = Writer::init(&mut request_buffer);
+        let request = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: SpdmVersion::SpdmVersion11,
+                request_response_code: SpdmRequestResponseCode::SpdmRequestResponseIfReady,
+            },
+            payload: SpdmMessagePayload::SpdmMessageGeneral(SpdmMessageGeneralPayload {
+                param1: request_code.get_u8(),
+                param2: token,
+            }),
+        };
+        request.spdm_encode(&mut context.common, &mut writer);
+
+        // one more poll still finds the signer busy
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        context.write_spdm_respond_if_ready_response(None, &request_buffer, &mut writer);
+        let used = writer.used();
+        let mut reader = Reader::init(&response_buffer[..used]);
+        SpdmMessageHeader::read(&mut reader).unwrap();
+        let payload =
+            SpdmErrorResponsePayload::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(payload.error_code, SpdmErrorCode::SpdmErrorResponseNotReady);
 
-        //TODO: implement respond if ready
-        self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        context.write_spdm_respond_if_ready_response(None, &request_buffer, &mut writer);
+        assert_eq!(writer.used(), 100);
+        assert_eq!(&writer.used_slice()[..4], &[0x11u8, 0x03, 0x00, 0x00]);
+        assert_eq!(&writer.used_slice()[4..], &[0x5au8; 96][..]);
     }
 }
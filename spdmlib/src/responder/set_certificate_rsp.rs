@@ -0,0 +1,163 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::common::SpdmCodec;
+use crate::crypto;
+use crate::message::*;
+use crate::protocol::*;
+use crate::responder::*;
+use crate::secret;
+
+impl<'a> ResponderContext<'a> {
+    pub fn handle_spdm_set_certificate(&mut self, session_id: Option<u32>, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_set_certificate_response(bytes, &mut writer);
+        match session_id {
+            Some(session_id) => {
+                let _ = self.send_secured_message(session_id, writer.used_slice(), false);
+            }
+            None => {
+                let _ = self.send_message(writer.used_slice());
+            }
+        }
+    }
+
+    pub fn write_spdm_set_certificate_response(&mut self, bytes: &[u8], writer: &mut Writer) {
+        if self.common.negotiate_info.spdm_version_sel.get_u8()
+            < SpdmVersion::SpdmVersion12.get_u8()
+            || !self
+                .common
+                .config_info
+                .rsp_capabilities
+                .contains(SpdmResponseCapabilityFlags::SET_CERT_CAP)
+        {
+            self.write_spdm_error(
+                SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                SpdmRequestResponseCode::SpdmRequestSetCertificate.get_u8(),
+                writer,
+            );
+            return;
+        }
+
+        let mut reader = Reader::init(bytes);
+        SpdmMessageHeader::read(&mut reader);
+
+        let set_certificate =
+            SpdmSetCertificateRequestPayload::spdm_read(&mut self.common, &mut reader);
+        let set_certificate = if let Some(set_certificate) = set_certificate {
+            debug!("!!! set_certificate : {:02x?}\n", set_certificate.slot_id);
+            set_certificate
+        } else {
+            error!("!!! set_certificate : fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        };
+
+        let slot_id = set_certificate.slot_id;
+        if slot_id as usize >= SPDM_MAX_SLOT_NUMBER {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        // Length(2) + Reserved(2) + RootHash(H) + Certificates
+        let cert_chain = set_certificate.cert_chain.as_ref();
+        let certs_offset = 4 + self.common.get_hash_size() as usize;
+        let required_ekus = self
+            .common
+            .config_info
+            .responder_cert_eku_policy
+            .required_ekus(false);
+        let validity_time = if let Ok(validity_time) = self.common.get_cert_validity_time() {
+            validity_time
+        } else {
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        };
+        if cert_chain.len() <= certs_offset
+            || crypto::cert_operation::verify_cert_chain(
+                &cert_chain[certs_offset..],
+                required_ekus,
+                validity_time,
+            )
+            .is_err()
+        {
+            error!("!!! set_certificate : invalid cert chain !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+            return;
+        }
+
+        if secret::spdm_store_cert_chain(slot_id, cert_chain).is_err() {
+            error!("!!! set_certificate : store cert chain fail !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
+
+        self.common.provision_info.my_cert_chain[slot_id as usize] =
+            Some(set_certificate.cert_chain);
+
+        info!("send spdm set_certificate rsp\n");
+
+        let response = SpdmMessage {
+            header: SpdmMessageHeader {
+                version: self.common.negotiate_info.spdm_version_sel,
+                request_response_code: SpdmRequestResponseCode::SpdmResponseSetCertificateRsp,
+            },
+            payload: SpdmMessagePayload::SpdmSetCertificateResponse(
+                SpdmSetCertificateResponsePayload { slot_id },
+            ),
+        };
+        response.spdm_encode(&mut self.common, writer);
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::testlib::*;
+    use crate::{crypto, responder};
+    use codec::{Codec, Writer};
+
+    #[test]
+    fn test_case0_handle_spdm_set_certificate() {
+        let (config_info, provision_info) = create_info();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        context.common.negotiate_info.spdm_version_sel = SpdmVersion::SpdmVersion11;
+
+        let bytes = &mut [0u8; 1024];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion11,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestSetCertificate,
+        };
+        value.encode(&mut writer);
+
+        let response = &mut [0u8; 1024];
+        let mut writer = Writer::init(response);
+        context.write_spdm_set_certificate_response(bytes, &mut writer);
+
+        let mut reader = Reader::init(writer.used_slice());
+        let spdm_message = SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        assert_eq!(
+            spdm_message.header.request_response_code,
+            SpdmRequestResponseCode::SpdmResponseError
+        );
+        if let SpdmMessagePayload::SpdmErrorResponse(payload) = &spdm_message.payload {
+            assert_eq!(
+                payload.error_code,
+                SpdmErrorCode::SpdmErrorUnsupportedRequest
+            );
+        }
+    }
+}
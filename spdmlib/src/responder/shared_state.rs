@@ -0,0 +1,320 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! State shared by the responder contexts of several requesters.
+//!
+//! A responder serving several requesters at once runs one ResponderContext
+//! per requester connection, each on its own thread, and registers the same
+//! SpdmResponderSharedState with all of them. A context keeps writing its
+//! own negotiated state and sessions, the shared state is Sync and gives
+//! the other threads a locked view of them:
+//!
+//! - the session limit holds for the sessions of all connections together,
+//!   KEY_EXCHANGE and PSK_EXCHANGE answer SessionLimitExceeded beyond it;
+//! - `get_sessions` lists the sessions of every connection;
+//! - `request_end_session` has the owning context tear a session down.
+//!
+//! A context publishes its sessions and applies the requested terminations
+//! after each process_message, or when sync_shared_state is called.
+//!
+//! ```ignore
+//! let shared_state = SpdmResponderSharedState::new(8);
+//! std::thread::scope(|s| {
+//!     for (device_io, transport_encap) in connections {
+//!         s.spawn(|| {
+//!             let mut responder = ResponderContext::new(device_io, transport_encap, ...);
+//!             responder.register_shared_state(&shared_state)?;
+//!             loop {
+//!                 let _ = responder.process_message(ST1);
+//!             }
+//!         });
+//!     }
+//! });
+//! ```
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::common::session::{SpdmSession, SpdmSessionState};
+use crate::common::SpdmSessionEvent;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::responder::ResponderContext;
+
+/// A session as the other threads see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpdmSharedSessionInfo {
+    pub connection: usize,
+    pub session_id: u32,
+    pub session_state: SpdmSessionState,
+    pub use_psk: bool,
+}
+
+#[derive(Default)]
+struct SpdmSharedConnectionEntry {
+    in_use: bool,
+    sessions: Vec<SpdmSharedSessionInfo>,
+    // sessions being set up, counted until the next sync
+    reserved: usize,
+    end_session_requests: Vec<u32>,
+}
+
+pub struct SpdmResponderSharedState {
+    max_session_count: usize,
+    connections: spin::Mutex<Vec<SpdmSharedConnectionEntry>>,
+}
+
+impl SpdmResponderSharedState {
+    /// max_session_count caps the sessions of all connections, 0 is no
+    /// limit beyond the one of each context.
+    pub const fn new(max_session_count: usize) -> Self {
+        SpdmResponderSharedState {
+            max_session_count,
+            connections: spin::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A connection of one more context, released when dropped.
+    pub fn connect(&self) -> SpdmSharedConnection<'_> {
+        let mut connections = self.connections.lock();
+        let index = match connections.iter().position(|c| !c.in_use) {
+            Some(index) => index,
+            None => {
+                connections.push(SpdmSharedConnectionEntry::default());
+                connections.len() - 1
+            }
+        };
+        connections[index].in_use = true;
+        SpdmSharedConnection {
+            shared_state: self,
+            index,
+        }
+    }
+
+    /// Sessions of all connections, handshaking or established.
+    pub fn get_session_count(&self) -> usize {
+        self.connections
+            .lock()
+            .iter()
+            .map(|c| c.sessions.len() + c.reserved)
+            .sum()
+    }
+
+    /// The sessions of every connection as last published.
+    pub fn get_sessions(&self) -> Vec<SpdmSharedSessionInfo> {
+        self.connections
+            .lock()
+            .iter()
+            .flat_map(|c| c.sessions.iter().copied())
+            .collect()
+    }
+
+    /// Have the context of connection tear session_id down the next time it
+    /// syncs, false if the connection has no such session.
+    pub fn request_end_session(&self, connection: usize, session_id: u32) -> bool {
+        let mut connections = self.connections.lock();
+        match connections.get_mut(connection) {
+            Some(entry)
+                if entry.in_use && entry.sessions.iter().any(|s| s.session_id == session_id) =>
+            {
+                if !entry.end_session_requests.contains(&session_id) {
+                    entry.end_session_requests.push(session_id);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The part of an SpdmResponderSharedState of one context.
+pub struct SpdmSharedConnection<'a> {
+    shared_state: &'a SpdmResponderSharedState,
+    index: usize,
+}
+
+impl<'a> SpdmSharedConnection<'a> {
+    pub fn get_connection_index(&self) -> usize {
+        self.index
+    }
+
+    // Room for one more session with session_count open in this connection,
+    // it is counted until the next sync.
+    fn reserve_session(&self, session_count: usize) -> bool {
+        let mut connections = self.shared_state.connections.lock();
+        let others: usize = connections
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != self.index)
+            .map(|(_, c)| c.sessions.len() + c.reserved)
+            .sum();
+        let max_session_count = self.shared_state.max_session_count;
+        if max_session_count != 0 && others + session_count >= max_session_count {
+            return false;
+        }
+        connections[self.index].reserved += 1;
+        true
+    }
+
+    // Publish sessions and return the session IDs to tear down.
+    fn sync(&self, sessions: &[SpdmSession]) -> Vec<u32> {
+        let mut connections = self.shared_state.connections.lock();
+        let entry = &mut connections[self.index];
+        entry.sessions.clear();
+        for session in sessions.iter().filter(|s| s.get_session_id() != 0) {
+            entry.sessions.push(SpdmSharedSessionInfo {
+                connection: self.index,
+                session_id: session.get_session_id(),
+                session_state: session.get_session_state(),
+                use_psk: session.get_use_psk(),
+            });
+        }
+        entry.reserved = 0;
+        core::mem::take(&mut entry.end_session_requests)
+    }
+}
+
+impl<'a> Drop for SpdmSharedConnection<'a> {
+    fn drop(&mut self) {
+        let mut connections = self.shared_state.connections.lock();
+        connections[self.index] = SpdmSharedConnectionEntry::default();
+    }
+}
+
+impl<'a> ResponderContext<'a> {
+    /// Count the sessions of this context in shared_state, and take the
+    /// session requests of the other threads from it.
+    pub fn register_shared_state(
+        &mut self,
+        shared_state: &'a SpdmResponderSharedState,
+    ) -> SpdmResult {
+        if self.shared_connection.is_some() {
+            return spdm_result_err!(EBUSY);
+        }
+        self.shared_connection = Some(shared_state.connect());
+        self.sync_shared_state();
+        Ok(())
+    }
+
+    /// Index of this context in the registered shared state.
+    pub fn get_shared_connection_index(&self) -> Option<usize> {
+        self.shared_connection
+            .as_ref()
+            .map(|shared_connection| shared_connection.get_connection_index())
+    }
+
+    /// Publish the sessions to the registered shared state and tear down
+    /// the ones other threads asked to end. process_message does it after
+    /// each message, an idle context may call it periodically.
+    pub fn sync_shared_state(&mut self) {
+        let end_session_requests = match &self.shared_connection {
+            Some(shared_connection) => shared_connection.sync(&self.common.session),
+            None => return,
+        };
+        if end_session_requests.is_empty() {
+            return;
+        }
+        for session_id in end_session_requests {
+            if let Some(session) = self.common.get_session_via_id(session_id) {
+                info!("!!! shared state : end session {:08x} !!!\n", session_id);
+                let _ = session.teardown(session_id);
+                self.common
+                    .notify_session_event(session_id, SpdmSessionEvent::Terminated);
+            }
+        }
+        if let Some(shared_connection) = &self.shared_connection {
+            let _ = shared_connection.sync(&self.common.session);
+        }
+    }
+
+    // False when the sessions of all connections reached the shared limit.
+    pub(crate) fn reserve_shared_session(&self) -> bool {
+        match &self.shared_connection {
+            Some(shared_connection) => {
+                shared_connection.reserve_session(self.common.get_session_count())
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(all(test,))]
+mod tests_responder {
+    use super::*;
+    use crate::crypto;
+    use crate::testlib::*;
+
+    #[test]
+    fn test_case0_shared_session_limit() {
+        let shared_state = SpdmResponderSharedState::new(2);
+        let connection0 = shared_state.connect();
+        let connection1 = shared_state.connect();
+        assert_ne!(
+            connection0.get_connection_index(),
+            connection1.get_connection_index()
+        );
+
+        let mut sessions0: [SpdmSession; 2] = [SpdmSession::new(), SpdmSession::new()];
+        assert!(connection0.reserve_session(0));
+        sessions0[0].setup(0xFFFE_FFFE).unwrap();
+        let _ = connection0.sync(&sessions0);
+        assert_eq!(shared_state.get_session_count(), 1);
+
+        assert!(connection1.reserve_session(0));
+        assert!(!connection0.reserve_session(1));
+        // the reservation of a failed KEY_EXCHANGE goes with the next sync
+        let _ = connection1.sync(&[]);
+        assert!(connection0.reserve_session(1));
+        assert_eq!(shared_state.get_session_count(), 2);
+
+        drop(connection0);
+        assert_eq!(shared_state.get_session_count(), 0);
+        assert!(shared_state.get_sessions().is_empty());
+        let connection2 = shared_state.connect();
+        assert_eq!(connection2.get_connection_index(), 0);
+    }
+
+    #[test]
+    fn test_case1_shared_end_session() {
+        let (config_info, provision_info) = create_info();
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let shared_state = SpdmResponderSharedState::new(0);
+        let mut responder = ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+        assert!(responder.register_shared_state(&shared_state).is_ok());
+        assert!(responder.register_shared_state(&shared_state).is_err());
+        let connection = responder.get_shared_connection_index().unwrap();
+
+        let session_id = 0xFFFE_FFFEu32;
+        responder.common.session[0].setup(session_id).unwrap();
+        responder.common.session[0].set_session_state(SpdmSessionState::SpdmSessionEstablished);
+        responder.sync_shared_state();
+        assert_eq!(
+            shared_state.get_sessions().as_slice(),
+            &[SpdmSharedSessionInfo {
+                connection,
+                session_id,
+                session_state: SpdmSessionState::SpdmSessionEstablished,
+                use_psk: false,
+            }]
+        );
+
+        assert!(!shared_state.request_end_session(connection, 0x1234_5678));
+        assert!(shared_state.request_end_session(connection, session_id));
+        responder.sync_shared_state();
+        assert!(responder
+            .common
+            .get_immutable_session_via_id(session_id)
+            .is_none());
+        assert!(shared_state.get_sessions().is_empty());
+    }
+}
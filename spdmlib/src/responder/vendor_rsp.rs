@@ -2,25 +2,143 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::common::session::SpdmSessionAppData;
 use crate::common::SpdmCodec;
-use crate::error::SpdmResult;
+use crate::error::{spdm_result_err, SpdmResult};
 use crate::message::*;
 use crate::responder::*;
 
+pub const MAX_SPDM_VENDOR_DEFINED_HANDLER_COUNT: usize = 4;
+
+/// Handler of VENDOR_DEFINED_REQUEST messages: gets the session ID, the app
+/// data of the session and the request payload.
+pub type SpdmVendorDefinedHandler<'a> = &'a dyn Fn(
+    u32,
+    &mut SpdmSessionAppData,
+    &VendorDefinedReqPayloadStruct,
+) -> SpdmResult<VendorDefinedRspPayloadStruct>;
+
+#[derive(Clone, Copy)]
+pub(crate) struct SpdmVendorDefinedHandlerEntry<'a> {
+    standard_id: RegistryOrStandardsBodyID,
+    vendor_id: VendorIDStruct,
+    handler: SpdmVendorDefinedHandler<'a>,
+}
+
 impl<'a> ResponderContext<'a> {
+    /// Register a handler for VENDOR_DEFINED_REQUEST messages carrying the
+    /// given standard ID and vendor ID. A handler already registered for the
+    /// same IDs is replaced.
+    pub fn register_vendor_defined_handler(
+        &mut self,
+        standard_id: RegistryOrStandardsBodyID,
+        vendor_id: VendorIDStruct,
+        handler: SpdmVendorDefinedHandler<'a>,
+    ) -> SpdmResult {
+        if vendor_id.len as usize > config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN {
+            return spdm_result_err!(EINVAL);
+        }
+
+        let entry = SpdmVendorDefinedHandlerEntry {
+            standard_id,
+            vendor_id,
+            handler,
+        };
+        if let Some(slot) = self.vendor_defined_handlers.iter_mut().find(|slot| {
+            matches!(slot, Some(e) if e.standard_id == standard_id && e.vendor_id == vendor_id)
+        }) {
+            *slot = Some(entry);
+            return Ok(());
+        }
+        match self
+            .vendor_defined_handlers
+            .iter_mut()
+            .find(|slot| slot.is_none())
+        {
+            Some(slot) => {
+                *slot = Some(entry);
+                Ok(())
+            }
+            None => spdm_result_err!(ENOMEM),
+        }
+    }
+
+    pub fn unregister_vendor_defined_handler(
+        &mut self,
+        standard_id: RegistryOrStandardsBodyID,
+        vendor_id: &VendorIDStruct,
+    ) -> bool {
+        for slot in self.vendor_defined_handlers.iter_mut() {
+            if matches!(slot, Some(e) if e.standard_id == standard_id && e.vendor_id == *vendor_id)
+            {
+                *slot = None;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn get_vendor_defined_handler(
+        &self,
+        standard_id: RegistryOrStandardsBodyID,
+        vendor_id: &VendorIDStruct,
+    ) -> Option<SpdmVendorDefinedHandler<'a>> {
+        self.vendor_defined_handlers
+            .iter()
+            .flatten()
+            .find(|e| e.standard_id == standard_id && e.vendor_id == *vendor_id)
+            .map(|e| e.handler)
+    }
+
     pub fn handle_spdm_vendor_defined_request(&mut self, session_id: u32, bytes: &[u8]) {
+        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut send_buffer);
+        self.write_spdm_vendor_defined_response(session_id, bytes, &mut writer);
+        let _ = self.send_secured_message(session_id, writer.used_slice(), true);
+    }
+
+    pub fn write_spdm_vendor_defined_response(
+        &mut self,
+        session_id: u32,
+        bytes: &[u8],
+        writer: &mut Writer,
+    ) {
         let mut reader = Reader::init(bytes);
         SpdmMessageHeader::read(&mut reader);
         let vendor_defined_request_payload =
-            SpdmVendorDefinedRequestPayload::spdm_read(&mut self.common, &mut reader).unwrap();
+            match SpdmVendorDefinedRequestPayload::spdm_read(&mut self.common, &mut reader) {
+                Some(payload) => payload,
+                None => {
+                    error!("!!! vendor defined req : fail !!!\n");
+                    self.write_spdm_error(SpdmErrorCode::SpdmErrorInvalidRequest, 0, writer);
+                    return;
+                }
+            };
         let standard_id = vendor_defined_request_payload.standard_id;
         let vendor_id = vendor_defined_request_payload.vendor_id;
         let req_payload = vendor_defined_request_payload.req_payload;
-        let rsp_payload = self
-            .respond_to_vendor_defined_request(&req_payload, vendor_defined_request_handler)
-            .unwrap();
-        let mut send_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
-        let mut writer = Writer::init(&mut send_buffer);
+
+        // Registered handlers take precedence over the global handler.
+        let rsp_payload = match self.get_vendor_defined_handler(standard_id, &vendor_id) {
+            Some(handler) => match self.common.get_session_via_id(session_id) {
+                Some(session) => handler(session_id, &mut session.app_data, &req_payload),
+                None => spdm_result_err!(EINVAL),
+            },
+            None => {
+                self.respond_to_vendor_defined_request(&req_payload, vendor_defined_request_handler)
+            }
+        };
+        let rsp_payload = match rsp_payload {
+            Ok(rsp_payload) => rsp_payload,
+            Err(_) => {
+                self.write_spdm_error(
+                    SpdmErrorCode::SpdmErrorUnsupportedRequest,
+                    SpdmRequestResponseCode::SpdmRequestVendorDefinedRequest.get_u8(),
+                    writer,
+                );
+                return;
+            }
+        };
 
         let response = SpdmMessage {
             header: SpdmMessageHeader {
@@ -35,9 +153,7 @@ impl<'a> ResponderContext<'a> {
                 },
             ),
         };
-        response.spdm_encode(&mut self.common, &mut writer);
-        let used = writer.used();
-        let _ = self.send_secured_message(session_id, &send_buffer[..used], true);
+        response.spdm_encode(&mut self.common, writer);
     }
 
     pub fn respond_to_vendor_defined_request<F>(
@@ -110,4 +226,120 @@ mod tests_requester {
             assert!(false, "Not expected result!");
         }
     }
+
+    #[test]
+    fn test_case1_write_spdm_vendor_defined_response() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+
+        let shared_buffer = SharedBuffer::new();
+        let mut device_io_responder = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        // echo the request, counting the requests of the session
+        let handler = |_session_id: u32,
+                       app_data: &mut SpdmSessionAppData,
+                       req: &VendorDefinedReqPayloadStruct|
+         -> SpdmResult<VendorDefinedRspPayloadStruct> {
+            match app_data.get_mut::<u32>() {
+                Some(count) => *count += 1,
+                None => app_data.set(1u32),
+            }
+            let mut rsp = VendorDefinedRspPayloadStruct {
+                rsp_length: req.req_length,
+                vendor_defined_rsp_payload: [0; config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE],
+            };
+            rsp.vendor_defined_rsp_payload[..req.req_length as usize]
+                .copy_from_slice(&req.vendor_defined_req_payload[..req.req_length as usize]);
+            Ok(rsp)
+        };
+
+        let mut responder = ResponderContext::new(
+            &mut device_io_responder,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+        let session_id = 0xFFFFFFFEu32;
+        responder.common.session[0].setup(session_id).unwrap();
+
+        let mut vendor_id = VendorIDStruct {
+            len: 2,
+            vendor_id: [0u8; config::MAX_SPDM_VENDOR_DEFINED_VENDOR_ID_LEN],
+        };
+        vendor_id.vendor_id[0] = 0x86;
+        vendor_id.vendor_id[1] = 0x80;
+        assert!(responder
+            .register_vendor_defined_handler(RegistryOrStandardsBodyID::PCISIG, vendor_id, &handler)
+            .is_ok());
+
+        let mut req_payload = VendorDefinedReqPayloadStruct {
+            req_length: 4,
+            vendor_defined_req_payload: [0; config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE],
+        };
+        req_payload.vendor_defined_req_payload[..4].copy_from_slice(b"echo");
+
+        for (standard_id, expected) in [
+            (
+                RegistryOrStandardsBodyID::PCISIG,
+                SpdmRequestResponseCode::SpdmResponseVendorDefinedResponse,
+            ),
+            (
+                RegistryOrStandardsBodyID::CXL,
+                SpdmRequestResponseCode::SpdmResponseError,
+            ),
+        ]
+        .iter()
+        {
+            let request = SpdmMessage {
+                header: SpdmMessageHeader {
+                    version: SpdmVersion::SpdmVersion11,
+                    request_response_code: SpdmRequestResponseCode::SpdmRequestVendorDefinedRequest,
+                },
+                payload: SpdmMessagePayload::SpdmVendorDefinedRequest(
+                    SpdmVendorDefinedRequestPayload {
+                        standard_id: *standard_id,
+                        vendor_id,
+                        req_payload: req_payload.clone(),
+                    },
+                ),
+            };
+            let mut request_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let mut writer = Writer::init(&mut request_buffer);
+            request.spdm_encode(&mut responder.common, &mut writer);
+            let used = writer.used();
+
+            let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+            let mut writer = Writer::init(&mut response_buffer);
+            responder.write_spdm_vendor_defined_response(
+                session_id,
+                &request_buffer[..used],
+                &mut writer,
+            );
+            let used = writer.used();
+
+            let mut reader = Reader::init(&response_buffer[..used]);
+            let header = SpdmMessageHeader::read(&mut reader).unwrap();
+            assert_eq!(header.request_response_code, *expected);
+            if *expected == SpdmRequestResponseCode::SpdmResponseVendorDefinedResponse {
+                let response =
+                    SpdmVendorDefinedResponsePayload::spdm_read(&mut responder.common, &mut reader)
+                        .unwrap();
+                assert_eq!(response.vendor_id, vendor_id);
+                assert_eq!(response.rsp_payload.rsp_length, 4);
+                assert_eq!(
+                    &response.rsp_payload.vendor_defined_rsp_payload[..4],
+                    b"echo"
+                );
+            }
+        }
+
+        assert_eq!(responder.common.session[0].app_data.get::<u32>(), Some(&1));
+
+        assert!(responder
+            .unregister_vendor_defined_handler(RegistryOrStandardsBodyID::PCISIG, &vendor_id));
+        assert!(!responder
+            .unregister_vendor_defined_handler(RegistryOrStandardsBodyID::PCISIG, &vendor_id));
+    }
 }
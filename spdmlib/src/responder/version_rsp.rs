@@ -42,6 +42,22 @@ impl<'a> ResponderContext<'a> {
             return;
         }
 
+        let mut version_number_entry_count = 0u8;
+        let mut versions =
+            gen_array_clone(SpdmVersionStruct::default(), config::MAX_SPDM_VERSION_COUNT);
+        for version in self.common.config_info.spdm_version.iter() {
+            if let SpdmVersion::Unknown(_) = version {
+                continue;
+            }
+            versions[version_number_entry_count as usize] = SpdmVersionStruct::new(*version, 0, 0);
+            version_number_entry_count += 1;
+        }
+        if version_number_entry_count == 0 {
+            error!("!!! no spdm version configured !!!\n");
+            self.write_spdm_error(SpdmErrorCode::SpdmErrorUnspecified, 0, writer);
+            return;
+        }
+
         info!("send spdm version\n");
         let response = SpdmMessage {
             header: SpdmMessageHeader {
@@ -49,21 +65,8 @@ impl<'a> ResponderContext<'a> {
                 request_response_code: SpdmRequestResponseCode::SpdmResponseVersion,
             },
             payload: SpdmMessagePayload::SpdmVersionResponse(SpdmVersionResponsePayload {
-                version_number_entry_count: 3,
-                versions: [
-                    SpdmVersionStruct {
-                        update: 0,
-                        version: self.common.config_info.spdm_version[0],
-                    },
-                    SpdmVersionStruct {
-                        update: 0,
-                        version: self.common.config_info.spdm_version[1],
-                    },
-                    SpdmVersionStruct {
-                        update: 0,
-                        version: self.common.config_info.spdm_version[2],
-                    },
-                ],
+                version_number_entry_count,
+                versions,
             }),
         };
 
@@ -144,4 +147,50 @@ mod tests_responder {
             assert_eq!(payload.versions[2].version, SpdmVersion::SpdmVersion12);
         }
     }
+    #[test]
+    fn test_case1_handle_spdm_version() {
+        let (mut config_info, provision_info) = create_info();
+        config_info.spdm_version = [
+            SpdmVersion::SpdmVersion11,
+            SpdmVersion::Unknown(0),
+            SpdmVersion::SpdmVersion12,
+        ];
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let shared_buffer = SharedBuffer::new();
+        let mut socket_io_transport = FakeSpdmDeviceIoReceve::new(&shared_buffer);
+
+        let mut context = responder::ResponderContext::new(
+            &mut socket_io_transport,
+            pcidoe_transport_encap,
+            config_info,
+            provision_info,
+        );
+
+        let bytes = &mut [0u8; 4];
+        let mut writer = Writer::init(bytes);
+        let value = SpdmMessageHeader {
+            version: SpdmVersion::SpdmVersion10,
+            request_response_code: SpdmRequestResponseCode::SpdmRequestGetVersion,
+        };
+        value.encode(&mut writer);
+
+        let mut response_buffer = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+        let mut writer = Writer::init(&mut response_buffer);
+        context.write_spdm_version_response(bytes, &mut writer);
+        let used = writer.used();
+
+        let mut reader = Reader::init(&response_buffer[..used]);
+        let spdm_message: SpdmMessage =
+            SpdmMessage::spdm_read(&mut context.common, &mut reader).unwrap();
+        if let SpdmMessagePayload::SpdmVersionResponse(payload) = &spdm_message.payload {
+            assert_eq!(payload.version_number_entry_count, 2);
+            assert_eq!(payload.versions[0].version, SpdmVersion::SpdmVersion11);
+            assert_eq!(payload.versions[1].version, SpdmVersion::SpdmVersion12);
+        } else {
+            assert!(false, "Not expected result!");
+        }
+    }
 }
@@ -3,16 +3,45 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 mod secret_callback;
 
+use crate::error::{spdm_err, spdm_result_err, SpdmResult};
+use crate::message::{
+    SpdmEndpointInfoSubCode, SpdmEventGroupListStruct, SpdmKeyPairInfoResponsePayload,
+    SpdmSetKeyPairInfoRequestPayload,
+};
 use crate::protocol::*;
 use conquer_once::spin::OnceCell;
-pub use secret_callback::SpdmSecret;
+pub use secret_callback::{
+    SpdmCertStore, SpdmEndpointInfoProvider, SpdmEventProvider, SpdmKeyPairStore, SpdmSecret,
+};
 
 pub static SECRET_INSTANCE: OnceCell<SpdmSecret> = OnceCell::uninit();
+pub static CERT_STORE_INSTANCE: OnceCell<SpdmCertStore> = OnceCell::uninit();
+pub static KEY_PAIR_STORE_INSTANCE: OnceCell<SpdmKeyPairStore> = OnceCell::uninit();
+pub static ENDPOINT_INFO_PROVIDER_INSTANCE: OnceCell<SpdmEndpointInfoProvider> = OnceCell::uninit();
+pub static EVENT_PROVIDER_INSTANCE: OnceCell<SpdmEventProvider> = OnceCell::uninit();
 
 pub fn register(context: SpdmSecret) -> bool {
     SECRET_INSTANCE.try_init_once(|| context).is_ok()
 }
 
+pub fn register_cert_store(context: SpdmCertStore) -> bool {
+    CERT_STORE_INSTANCE.try_init_once(|| context).is_ok()
+}
+
+pub fn register_key_pair_store(context: SpdmKeyPairStore) -> bool {
+    KEY_PAIR_STORE_INSTANCE.try_init_once(|| context).is_ok()
+}
+
+pub fn register_endpoint_info_provider(context: SpdmEndpointInfoProvider) -> bool {
+    ENDPOINT_INFO_PROVIDER_INSTANCE
+        .try_init_once(|| context)
+        .is_ok()
+}
+
+pub fn register_event_provider(context: SpdmEventProvider) -> bool {
+    EVENT_PROVIDER_INSTANCE.try_init_once(|| context).is_ok()
+}
+
 static UNIMPLETEMTED: SpdmSecret = SpdmSecret {
     spdm_measurement_collection_cb: |_spdm_version: SpdmVersion,
                                      _measurement_specification: SpdmMeasurementSpecification,
@@ -67,6 +96,48 @@ static UNIMPLETEMTED: SpdmSecret = SpdmSecret {
      -> Option<SpdmHKDFKeyStruct> { unimplemented!() },
 };
 
+static CERT_STORE_UNIMPLEMENTED: SpdmCertStore = SpdmCertStore {
+    store_cert_chain_cb: |_slot_id: u8, _cert_chain: &[u8]| -> SpdmResult {
+        log::info!("not implement cert store!!!\n");
+        spdm_result_err!(ENOSYS)
+    },
+};
+
+static KEY_PAIR_STORE_UNIMPLEMENTED: SpdmKeyPairStore = SpdmKeyPairStore {
+    get_key_pair_info_cb: |_key_pair_id: u8| -> Option<SpdmKeyPairInfoResponsePayload> {
+        log::info!("not implement key pair store!!!\n");
+        None
+    },
+    set_key_pair_info_cb: |_set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload| -> SpdmResult {
+        log::info!("not implement key pair store!!!\n");
+        spdm_result_err!(ENOSYS)
+    },
+};
+
+static ENDPOINT_INFO_PROVIDER_UNIMPLEMENTED: SpdmEndpointInfoProvider = SpdmEndpointInfoProvider {
+    get_endpoint_info_cb: |_sub_code: SpdmEndpointInfoSubCode,
+                           _ep_info: &mut [u8]|
+     -> SpdmResult<usize> {
+        log::info!("not implement endpoint info provider!!!\n");
+        spdm_result_err!(ENOSYS)
+    },
+};
+
+static EVENT_PROVIDER_UNIMPLEMENTED: SpdmEventProvider = SpdmEventProvider {
+    get_supported_event_types_cb:
+        |_supported_event_groups_list: &mut SpdmEventGroupListStruct| -> SpdmResult<u8> {
+            log::info!("not implement event provider!!!\n");
+            spdm_result_err!(ENOSYS)
+        },
+    subscribe_event_types_cb: |_session_id: u32,
+                               _subscribe_event_group_count: u8,
+                               _subscribe_list: &SpdmEventGroupListStruct|
+     -> SpdmResult {
+        log::info!("not implement event provider!!!\n");
+        spdm_result_err!(ENOSYS)
+    },
+};
+
 /*
     Function to get measurements.
 
@@ -204,3 +275,97 @@ pub fn spdm_psk_master_secret_hkdf_expand(
         info_size,
     )
 }
+
+/*
+    Function to persist a certificate chain provisioned by SET_CERTIFICATE.
+
+    This function wraps SpdmCertStore.store_cert_chain_cb callback.
+    The cert_chain is in SPDM certificate chain format (Length, Reserved,
+    RootHash, Certificates). If CERT_STORE_INSTANCE got no registered,
+    ENOSYS is returned and the request is rejected.
+*/
+pub fn spdm_store_cert_chain(slot_id: u8, cert_chain: &[u8]) -> SpdmResult {
+    (CERT_STORE_INSTANCE
+        .try_get_or_init(|| CERT_STORE_UNIMPLEMENTED.clone())
+        .map_err(|_| spdm_err!(EFAULT))?
+        .store_cert_chain_cb)(slot_id, cert_chain)
+}
+
+/*
+    Function to report the key pair information for GET_KEY_PAIR_INFO.
+
+    This function wraps SpdmKeyPairStore.get_key_pair_info_cb callback.
+    key_pair_id starts from 1. None is returned if the key pair does not
+    exist or KEY_PAIR_STORE_INSTANCE got no registered.
+*/
+pub fn spdm_get_key_pair_info(key_pair_id: u8) -> Option<SpdmKeyPairInfoResponsePayload> {
+    (KEY_PAIR_STORE_INSTANCE
+        .try_get_or_init(|| KEY_PAIR_STORE_UNIMPLEMENTED.clone())
+        .ok()?
+        .get_key_pair_info_cb)(key_pair_id)
+}
+
+/*
+    Function to reconfigure, erase or generate a key pair for SET_KEY_PAIR_INFO.
+
+    This function wraps SpdmKeyPairStore.set_key_pair_info_cb callback.
+    If KEY_PAIR_STORE_INSTANCE got no registered, ENOSYS is returned.
+*/
+pub fn spdm_set_key_pair_info(set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload) -> SpdmResult {
+    (KEY_PAIR_STORE_INSTANCE
+        .try_get_or_init(|| KEY_PAIR_STORE_UNIMPLEMENTED.clone())
+        .map_err(|_| spdm_err!(EFAULT))?
+        .set_key_pair_info_cb)(set_key_pair_info)
+}
+
+/*
+    Function to fill the endpoint information for GET_ENDPOINT_INFO.
+
+    This function wraps SpdmEndpointInfoProvider.get_endpoint_info_cb callback.
+    The endpoint information is written to ep_info and its length is returned.
+    If ENDPOINT_INFO_PROVIDER_INSTANCE got no registered, ENOSYS is returned.
+*/
+pub fn spdm_get_endpoint_info(
+    sub_code: SpdmEndpointInfoSubCode,
+    ep_info: &mut [u8],
+) -> SpdmResult<usize> {
+    (ENDPOINT_INFO_PROVIDER_INSTANCE
+        .try_get_or_init(|| ENDPOINT_INFO_PROVIDER_UNIMPLEMENTED.clone())
+        .map_err(|_| spdm_err!(EFAULT))?
+        .get_endpoint_info_cb)(sub_code, ep_info)
+}
+
+/*
+    Function to get the event groups supported by the device.
+
+    This function wraps SpdmEventProvider.get_supported_event_types_cb callback.
+    The SupportedEventGroupsList is written to supported_event_groups_list and
+    the EventGroupCount is returned.
+    If EVENT_PROVIDER_INSTANCE got no registered, ENOSYS is returned.
+*/
+pub fn spdm_get_supported_event_types(
+    supported_event_groups_list: &mut SpdmEventGroupListStruct,
+) -> SpdmResult<u8> {
+    (EVENT_PROVIDER_INSTANCE
+        .try_get_or_init(|| EVENT_PROVIDER_UNIMPLEMENTED.clone())
+        .map_err(|_| spdm_err!(EFAULT))?
+        .get_supported_event_types_cb)(supported_event_groups_list)
+}
+
+/*
+    Function to notify the device of a new event subscription.
+
+    This function wraps SpdmEventProvider.subscribe_event_types_cb callback.
+    A subscribe_event_group_count of 0 clears all the subscriptions of the session.
+    If EVENT_PROVIDER_INSTANCE got no registered, ENOSYS is returned.
+*/
+pub fn spdm_subscribe_event_types(
+    session_id: u32,
+    subscribe_event_group_count: u8,
+    subscribe_list: &SpdmEventGroupListStruct,
+) -> SpdmResult {
+    (EVENT_PROVIDER_INSTANCE
+        .try_get_or_init(|| EVENT_PROVIDER_UNIMPLEMENTED.clone())
+        .map_err(|_| spdm_err!(EFAULT))?
+        .subscribe_event_types_cb)(session_id, subscribe_event_group_count, subscribe_list)
+}
@@ -2,6 +2,11 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::error::SpdmResult;
+use crate::message::{
+    SpdmEndpointInfoSubCode, SpdmEventGroupListStruct, SpdmKeyPairInfoResponsePayload,
+    SpdmSetKeyPairInfoRequestPayload,
+};
 use crate::protocol::{
     SpdmBaseHashAlgo, SpdmDigestStruct, SpdmHKDFKeyStruct, SpdmMeasurementRecordStructure,
     SpdmMeasurementSpecification, SpdmMeasurementSummaryHashType, SpdmReqAsymAlgo,
@@ -72,3 +77,46 @@ pub struct SpdmSecret {
 
     pub spdm_psk_master_secret_hkdf_expand_cb: SpdmPskMasterSecretHkdfExpandCbType,
 }
+
+type SpdmStoreCertChainCbType = fn(slot_id: u8, cert_chain: &[u8]) -> SpdmResult;
+
+#[derive(Clone)]
+pub struct SpdmCertStore {
+    pub store_cert_chain_cb: SpdmStoreCertChainCbType,
+}
+
+type SpdmGetKeyPairInfoCbType = fn(key_pair_id: u8) -> Option<SpdmKeyPairInfoResponsePayload>;
+
+type SpdmSetKeyPairInfoCbType =
+    fn(set_key_pair_info: &SpdmSetKeyPairInfoRequestPayload) -> SpdmResult;
+
+#[derive(Clone)]
+pub struct SpdmKeyPairStore {
+    pub get_key_pair_info_cb: SpdmGetKeyPairInfoCbType,
+
+    pub set_key_pair_info_cb: SpdmSetKeyPairInfoCbType,
+}
+
+type SpdmGetEndpointInfoCbType =
+    fn(sub_code: SpdmEndpointInfoSubCode, ep_info: &mut [u8]) -> SpdmResult<usize>;
+
+#[derive(Clone)]
+pub struct SpdmEndpointInfoProvider {
+    pub get_endpoint_info_cb: SpdmGetEndpointInfoCbType,
+}
+
+type SpdmGetSupportedEventTypesCbType =
+    fn(supported_event_groups_list: &mut SpdmEventGroupListStruct) -> SpdmResult<u8>;
+
+type SpdmSubscribeEventTypesCbType = fn(
+    session_id: u32,
+    subscribe_event_group_count: u8,
+    subscribe_list: &SpdmEventGroupListStruct,
+) -> SpdmResult;
+
+#[derive(Clone)]
+pub struct SpdmEventProvider {
+    pub get_supported_event_types_cb: SpdmGetSupportedEventTypesCbType,
+
+    pub subscribe_event_types_cb: SpdmSubscribeEventTypesCbType,
+}
@@ -5,12 +5,14 @@
 #![allow(unused)]
 
 use crate::common::*;
-use crate::crypto::{SpdmAsymSign, SpdmCryptoRandom, SpdmHmac};
+use crate::crypto::{SpdmAsymSign, SpdmCryptoRandom, SpdmHmac, SpdmRng};
 pub use crate::protocol::*;
 use crate::{common, responder};
 
 use crate::error::SpdmResult;
 use crate::message::*;
+use crate::secret::SpdmSecret;
+use crate::time::SpdmTime;
 use crate::{spdm_err, spdm_result_err};
 use codec::enum_builder;
 use codec::{Codec, Reader, Writer};
@@ -103,11 +105,19 @@ pub fn create_info() -> (common::SpdmConfigInfo, common::SpdmProvisionInfo) {
         .copy_from_slice(leaf_cert.as_ref());
 
     let provision_info = common::SpdmProvisionInfo {
-        my_cert_chain_data: Some(my_cert_chain_data.clone()),
-        my_cert_chain: None,
+        my_cert_chain_data: [
+            Some(my_cert_chain_data.clone()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
         peer_cert_chain_data: Some(my_cert_chain_data),
-        peer_cert_chain_root_hash: None,
-        default_version: SpdmVersion::SpdmVersion11,
+        ..Default::default()
     };
 
     (config_info, provision_info)
@@ -275,8 +285,123 @@ impl SpdmTransportEncap for PciDoeTransportEncap {
     fn get_max_random_count(&mut self) -> u16 {
         0
     }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        Some(8)
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        let aligned_payload_len = (spdm_size + 3) / 4 * 4;
+        if transport_buffer.len() < 8 + aligned_payload_len {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut writer = Writer::init(&mut transport_buffer[..8]);
+        let pcidoe_header = PciDoeMessageHeader {
+            vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
+            data_object_type: if secured_message {
+                PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm
+            } else {
+                PciDoeDataObjectType::PciDoeDataObjectTypeSpdm
+            },
+            payload_length: aligned_payload_len as u32,
+        };
+        pcidoe_header.encode(&mut writer);
+        transport_buffer[(8 + spdm_size)..(8 + aligned_payload_len)].fill(0);
+        Ok(8 + aligned_payload_len)
+    }
+
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
+        let mut reader = Reader::init(transport_buffer);
+        let secured_message;
+        match PciDoeMessageHeader::read(&mut reader) {
+            Some(pcidoe_header) => {
+                match pcidoe_header.vendor_id {
+                    PciDoeVendorId::PciDoeVendorIdPciSig => {}
+                    _ => return spdm_result_err!(EINVAL),
+                }
+                match pcidoe_header.data_object_type {
+                    PciDoeDataObjectType::PciDoeDataObjectTypeSpdm => secured_message = false,
+                    PciDoeDataObjectType::PciDoeDataObjectTypeSecuredSpdm => secured_message = true,
+                    _ => return spdm_result_err!(EINVAL),
+                }
+            }
+            None => return spdm_result_err!(EIO),
+        }
+        let header_size = reader.used();
+        Ok((
+            header_size,
+            transport_buffer.len() - header_size,
+            secured_message,
+        ))
+    }
 }
 
+// Measurement summary hash of 0xaa bytes, everything else unimplemented.
+// Register it before anything reaches the secret, the first one in stays.
+pub static SECRET_IMPL: SpdmSecret = SpdmSecret {
+    spdm_measurement_collection_cb: |_spdm_version: SpdmVersion,
+                                     _measurement_specification: SpdmMeasurementSpecification,
+                                     _measurement_hash_algo: SpdmBaseHashAlgo,
+                                     _measurement_index: usize|
+     -> Option<SpdmMeasurementRecordStructure> {
+        unimplemented!()
+    },
+
+    spdm_generate_measurement_summary_hash_cb:
+        |_spdm_version: SpdmVersion,
+         base_hash_algo: SpdmBaseHashAlgo,
+         _measurement_specification: SpdmMeasurementSpecification,
+         _measurement_hash_algo: SpdmBaseHashAlgo,
+         _measurement_summary_hash_type: SpdmMeasurementSummaryHashType|
+         -> Option<SpdmDigestStruct> {
+            Some(SpdmDigestStruct {
+                data_size: base_hash_algo.get_size(),
+                data: Box::new([0xaa; SPDM_MAX_HASH_SIZE]),
+            })
+        },
+
+    spdm_requester_data_sign_cb: |_spdm_version: SpdmVersion,
+                                  _op_code: u8,
+                                  _req_base_asym_alg: SpdmReqAsymAlgo,
+                                  _base_hash_algo: SpdmBaseHashAlgo,
+                                  _is_data_hash: bool,
+                                  _message: &[u8],
+                                  _message_size: u8|
+     -> Option<SpdmSignatureStruct> { unimplemented!() },
+
+    spdm_responder_data_sign_cb: |_spdm_version: SpdmVersion,
+                                  _op_code: u8,
+                                  _req_base_asym_alg: SpdmReqAsymAlgo,
+                                  _base_hash_algo: SpdmBaseHashAlgo,
+                                  _is_data_hash: bool,
+                                  _message: &[u8],
+                                  _message_size: u8|
+     -> Option<SpdmSignatureStruct> { unimplemented!() },
+
+    spdm_psk_handshake_secret_hkdf_expand_cb: |_spdm_version: SpdmVersion,
+                                               _base_hash_algo: SpdmBaseHashAlgo,
+                                               _psk_hint: &[u8],
+                                               _psk_hint_size: Option<usize>,
+                                               _info: Option<&[u8]>,
+                                               _info_size: Option<usize>|
+     -> Option<SpdmHKDFKeyStruct> {
+        unimplemented!()
+    },
+
+    spdm_psk_master_secret_hkdf_expand_cb: |_spdm_version: SpdmVersion,
+                                            _base_hash_algo: SpdmBaseHashAlgo,
+                                            _psk_hint: &[u8],
+                                            _psk_hint_size: Option<usize>,
+                                            _info: Option<&[u8]>,
+                                            _info_size: Option<usize>|
+     -> Option<SpdmHKDFKeyStruct> { unimplemented!() },
+};
+
 pub static ASYM_SIGN_IMPL: SpdmAsymSign = SpdmAsymSign { sign_cb: asym_sign };
 
 fn asym_sign(
@@ -489,6 +614,37 @@ fn get_random(data: &mut [u8]) -> SpdmResult<usize> {
     Ok(data.len())
 }
 
+// the responders of the tests are in process, nothing to wait for
+pub static TIME_TEST: SpdmTime = SpdmTime {
+    sleep_cb: |_: usize| {},
+    now_cb: || 0,
+};
+
+/// SpdmRng counting up from its byte, for nonces that differ and are known.
+pub struct CountingRng(pub u8);
+
+impl SpdmRng for CountingRng {
+    fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+        for byte in data.iter_mut() {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+        Ok(data.len())
+    }
+}
+
+/// SpdmSessionObserver recording the events notified, in order.
+#[derive(Default)]
+pub struct SessionEventRecorder {
+    pub events: RefCell<Vec<(u32, SpdmSessionEvent)>>,
+}
+
+impl SpdmSessionObserver for SessionEventRecorder {
+    fn notify(&self, session_id: u32, event: SpdmSessionEvent) {
+        self.events.borrow_mut().push((session_id, event));
+    }
+}
+
 pub const REQ_CERT_CHAIN_DATA: SpdmCertChainData = SpdmCertChainData {
     data_size: 1545,
     data: [
@@ -0,0 +1,1492 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! In-process requester to responder pipe, to run a RequesterContext
+//! against a ResponderContext in tests and examples without a socket.
+//!
+//! ```ignore
+//! let pipe = MemoryPipe::new();
+//! let mut responder_io = pipe.responder_io();
+//! let mut responder = ResponderContext::new(&mut responder_io, ...);
+//! let mut requester_io = pipe.requester_io(&mut responder);
+//! let mut requester = RequesterContext::new(&mut requester_io, ...);
+//! requester.init_connection()?;
+//! ```
+//!
+//! Transport messages keep their boundaries, so fragmenting transports work
+//! too. The responder is run when the requester waits for a response, until
+//! it sent one or has no request left.
+
+use core::cell::RefCell;
+
+use crate::common::{SpdmDeviceIo, ST1};
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::responder::ResponderContext;
+
+extern crate alloc;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Transport messages in flight, one queue per direction.
+#[derive(Default)]
+pub struct MemoryPipe {
+    to_responder: RefCell<VecDeque<Vec<u8>>>,
+    to_requester: RefCell<VecDeque<Vec<u8>>>,
+}
+
+impl MemoryPipe {
+    pub fn new() -> Self {
+        MemoryPipe::default()
+    }
+
+    /// SpdmDeviceIo of the ResponderContext.
+    pub fn responder_io(&self) -> MemoryPipeResponderIo<'_> {
+        MemoryPipeResponderIo { pipe: self }
+    }
+
+    /// SpdmDeviceIo of the RequesterContext, running responder on demand.
+    pub fn requester_io<'a>(
+        &'a self,
+        responder: &'a mut ResponderContext<'a>,
+    ) -> MemoryPipeRequesterIo<'a> {
+        MemoryPipeRequesterIo {
+            pipe: self,
+            responder,
+        }
+    }
+
+    /// Number of transport messages the responder has not received yet.
+    pub fn pending_requests(&self) -> usize {
+        self.to_responder.borrow().len()
+    }
+
+    /// Number of transport messages the requester has not received yet.
+    pub fn pending_responses(&self) -> usize {
+        self.to_requester.borrow().len()
+    }
+
+    /// Drop everything in flight.
+    pub fn clear(&self) {
+        self.to_responder.borrow_mut().clear();
+        self.to_requester.borrow_mut().clear();
+    }
+}
+
+fn pop_message(queue: &RefCell<VecDeque<Vec<u8>>>, buffer: &mut [u8]) -> Result<usize, usize> {
+    let mut queue = queue.borrow_mut();
+    match queue.front() {
+        Some(message) if message.len() <= buffer.len() => {
+            let used = message.len();
+            buffer[..used].copy_from_slice(message);
+            queue.pop_front();
+            Ok(used)
+        }
+        Some(message) => {
+            error!(
+                "!!! pipe receive : buffer {} too small for {} !!!\n",
+                buffer.len(),
+                message.len()
+            );
+            Err(0)
+        }
+        None => Err(0),
+    }
+}
+
+pub struct MemoryPipeResponderIo<'a> {
+    pipe: &'a MemoryPipe,
+}
+
+impl SpdmDeviceIo for MemoryPipeResponderIo<'_> {
+    fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+        self.pipe
+            .to_requester
+            .borrow_mut()
+            .push_back(buffer.to_vec());
+        Ok(())
+    }
+
+    fn receive(&mut self, buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+        pop_message(&self.pipe.to_responder, buffer)
+    }
+
+    fn flush_all(&mut self) -> SpdmResult {
+        Ok(())
+    }
+}
+
+pub struct MemoryPipeRequesterIo<'a> {
+    pipe: &'a MemoryPipe,
+    responder: &'a mut ResponderContext<'a>,
+}
+
+impl<'a> MemoryPipeRequesterIo<'a> {
+    /// The responder, e.g. to check its state after a request.
+    pub fn responder(&mut self) -> &mut ResponderContext<'a> {
+        self.responder
+    }
+
+    fn run_responder(&mut self) -> SpdmResult {
+        while self.pipe.pending_responses() == 0 && self.pipe.pending_requests() != 0 {
+            if self.responder.process_message(ST1).is_err() {
+                return spdm_result_err!(EIO);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SpdmDeviceIo for MemoryPipeRequesterIo<'_> {
+    fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+        self.pipe
+            .to_responder
+            .borrow_mut()
+            .push_back(buffer.to_vec());
+        Ok(())
+    }
+
+    fn receive(&mut self, buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+        if self.run_responder().is_err() {
+            return Err(0);
+        }
+        pop_message(&self.pipe.to_requester, buffer)
+    }
+
+    fn flush_all(&mut self) -> SpdmResult {
+        self.pipe.clear();
+        Ok(())
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::common::connection_info::SpdmSessionInfo;
+    use crate::common::session::{SpdmSessionId, SpdmSessionState};
+    use crate::common::{
+        SpdmConfigError, SpdmDecryptErrorPolicy, SpdmPskEntry, SpdmPskTable, SpdmSessionEvent,
+        SpdmTimeProvider, SpdmTraceDirection, SpdmTracer,
+    };
+    use crate::config;
+    use crate::crypto::SpdmAsymSign;
+    use crate::crypto::{self, SpdmCertValidityTime};
+    use crate::error::{SpdmError, SpdmErrorNum};
+    use crate::message::{SpdmErrorCode, SpdmKeyUpdateOperation, SpdmRequestResponseCode};
+    use crate::requester::{RequesterBuilder, RequesterContext};
+    use crate::responder::ResponderBuilder;
+    use crate::testlib::*;
+    use codec::Writer;
+    use core::cell::Cell;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_case0_memory_pipe() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert_eq!(pipe.pending_requests(), 0);
+        assert_eq!(pipe.pending_responses(), 0);
+    }
+
+    #[test]
+    fn test_case1_memory_pipe_message_boundary() {
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        pipe.to_responder.borrow_mut().push_back([1u8, 2].to_vec());
+        pipe.to_responder.borrow_mut().push_back([3u8].to_vec());
+
+        let mut buffer = [0u8; 8];
+        assert_eq!(responder_io.receive(&mut buffer[..1], ST1), Err(0));
+        assert_eq!(responder_io.receive(&mut buffer, ST1), Ok(2));
+        assert_eq!(&buffer[..2], &[1, 2]);
+        assert_eq!(responder_io.receive(&mut buffer, ST1), Ok(1));
+        assert_eq!(responder_io.receive(&mut buffer, ST1), Err(0));
+
+        assert!(responder_io.send(&[4, 5, 6]).is_ok());
+        assert_eq!(pipe.pending_responses(), 1);
+        pipe.clear();
+        assert_eq!(pipe.pending_responses(), 0);
+    }
+
+    #[derive(Default)]
+    struct TraceRecorder {
+        records: RefCell<Vec<(SpdmTraceDirection, Option<u32>, usize)>>,
+        timestamps: RefCell<Vec<u64>>,
+    }
+
+    // A clock ticking once per reading.
+    #[derive(Default)]
+    struct TickingClock {
+        ticks: Cell<u64>,
+    }
+
+    impl SpdmTimeProvider for TickingClock {
+        fn get_cert_validity_time(&self) -> SpdmResult<SpdmCertValidityTime> {
+            Ok(SpdmCertValidityTime::Skip)
+        }
+
+        fn now(&self) -> u64 {
+            self.ticks.set(self.ticks.get() + 1);
+            self.ticks.get()
+        }
+    }
+
+    impl SpdmTracer for TraceRecorder {
+        fn trace(
+            &self,
+            direction: SpdmTraceDirection,
+            session_id: Option<u32>,
+            timestamp: u64,
+            transport_message: &[u8],
+        ) {
+            self.records
+                .borrow_mut()
+                .push((direction, session_id, transport_message.len()));
+            self.timestamps.borrow_mut().push(timestamp);
+        }
+    }
+
+    #[test]
+    fn test_case2_memory_pipe_trace() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let responder_trace = TraceRecorder::default();
+        let requester_trace = TraceRecorder::default();
+        let clock = TickingClock::default();
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+        responder.register_tracer(&responder_trace);
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_tracer(&requester_trace);
+        requester.register_time_provider(&clock);
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        let traced = requester_trace.records.borrow().len();
+        assert!(requester.end_session(session_id).is_ok());
+
+        let records = requester_trace.records.borrow();
+        assert_eq!(records[0].0, SpdmTraceDirection::Send);
+        assert_eq!(records[0].1, None);
+        assert_eq!(records[1].0, SpdmTraceDirection::Receive);
+        // END_SESSION and END_SESSION_ACK
+        assert_eq!(records.len(), traced + 2);
+        assert_eq!(records[traced].0, SpdmTraceDirection::Send);
+        assert_eq!(records[traced].1, Some(session_id.as_u32()));
+        assert_eq!(records[traced + 1].1, Some(session_id.as_u32()));
+
+        // the responder saw the same messages the other way round
+        let responder_records = responder_trace.records.borrow();
+        assert_eq!(responder_records.len(), records.len());
+        for (requester_record, responder_record) in records.iter().zip(responder_records.iter()) {
+            assert_ne!(requester_record.0, responder_record.0);
+            assert_eq!(requester_record.1, responder_record.1);
+            assert_eq!(requester_record.2, responder_record.2);
+        }
+
+        // timestamps of the time provider, 0 without one
+        let timestamps = requester_trace.timestamps.borrow();
+        assert!(timestamps
+            .iter()
+            .zip(1..)
+            .all(|(timestamp, tick)| *timestamp == tick));
+        assert!(responder_trace
+            .timestamps
+            .borrow()
+            .iter()
+            .all(|timestamp| *timestamp == 0));
+    }
+
+    #[test]
+    fn test_case3_memory_pipe_session_events() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let responder_events = SessionEventRecorder::default();
+        let requester_events = SessionEventRecorder::default();
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+        responder.register_session_observer(&responder_events);
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_session_observer(&requester_events);
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        assert!(requester
+            .send_receive_spdm_key_update(session_id, SpdmKeyUpdateOperation::SpdmUpdateAllKeys)
+            .is_ok());
+        assert!(requester.end_session(session_id).is_ok());
+
+        let expected = [
+            (session_id.as_u32(), SpdmSessionEvent::HandshakeStarted),
+            (session_id.as_u32(), SpdmSessionEvent::Established),
+            (session_id.as_u32(), SpdmSessionEvent::KeyUpdated),
+            (session_id.as_u32(), SpdmSessionEvent::Terminated),
+        ];
+        assert_eq!(requester_events.events.borrow().as_slice(), &expected);
+        assert_eq!(responder_events.events.borrow().as_slice(), &expected);
+    }
+
+    #[test]
+    fn test_case4_memory_pipe_session_audit() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let (session_id, requester_audit) = {
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            assert!(requester.init_connection().is_ok());
+            assert!(requester.send_receive_spdm_digest(None).is_ok());
+            assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+            let session_id = requester
+                .start_session(
+                    false,
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                )
+                .unwrap();
+            assert!(requester
+                .get_session_audit(SpdmSessionId::from(session_id.as_u32() + 1))
+                .is_err());
+            (session_id, requester.get_session_audit(session_id).unwrap())
+        };
+        let responder_audit = responder.get_session_audit(session_id.as_u32()).unwrap();
+
+        // both ends derived the session keys from the same transcript
+        assert_eq!(requester_audit.session_id, session_id.as_u32());
+        assert!(!requester_audit.use_psk);
+        assert_eq!(requester_audit.dhe_algo, responder_audit.dhe_algo);
+        assert_eq!(requester_audit.aead_algo, responder_audit.aead_algo);
+        assert_eq!(
+            requester_audit.cert_chain_hash.as_ref().unwrap().as_ref(),
+            responder_audit.cert_chain_hash.as_ref().unwrap().as_ref()
+        );
+        assert_eq!(requester_audit.th2.as_ref(), responder_audit.th2.as_ref());
+        #[cfg(not(feature = "hash-update"))]
+        {
+            assert_eq!(
+                requester_audit.message_k.as_ref(),
+                responder_audit.message_k.as_ref()
+            );
+            assert_eq!(
+                requester_audit.message_f.as_ref(),
+                responder_audit.message_f.as_ref()
+            );
+        }
+    }
+
+    #[test]
+    fn test_case5_memory_pipe_handshake_in_the_clear() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP;
+        req_config_info.req_capabilities = SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::KEY_UPD_CAP
+            | SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP;
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let requester_trace = TraceRecorder::default();
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let (session_id, requester_audit) = {
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+            requester.register_tracer(&requester_trace);
+
+            assert!(requester.init_connection().is_ok());
+            assert!(requester.common.is_handshake_in_the_clear());
+            assert!(requester.send_receive_spdm_digest(None).is_ok());
+            assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+            let session_id = requester
+                .start_session(
+                    false,
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                )
+                .unwrap();
+            let traced = requester_trace.records.borrow().len();
+            let audit = requester.get_session_audit(session_id).unwrap();
+            assert!(requester
+                .send_receive_spdm_key_update(session_id, SpdmKeyUpdateOperation::SpdmUpdateAllKeys)
+                .is_ok());
+
+            // KEY_EXCHANGE to FINISH_RSP in the clear, the session keys after
+            let records = requester_trace.records.borrow();
+            assert!(records[..traced].iter().all(|record| record.1.is_none()));
+            assert!(records[traced..]
+                .iter()
+                .all(|record| record.1 == Some(session_id.as_u32())));
+            (session_id, audit)
+        };
+        let responder_audit = responder.get_session_audit(session_id.as_u32()).unwrap();
+        assert_eq!(requester_audit.th2.as_ref(), responder_audit.th2.as_ref());
+        assert!(responder.clear_handshake_session_id.is_none());
+
+        // a FINISH in the clear is refused once the session is established
+        let finish = [0x12u8, 0xe5, 0, 0];
+        assert!(!responder.handle_spdm_finish_in_the_clear(&finish));
+    }
+
+    // Requester IO of a transport corrupting the next corrupt_count secured
+    // messages to the responder, which it then cannot decrypt.
+    struct CorruptingRequesterIo<'a> {
+        pipe: &'a MemoryPipe,
+        responder: &'a mut ResponderContext<'a>,
+        corrupt_count: &'a Cell<usize>,
+    }
+
+    impl CorruptingRequesterIo<'_> {
+        fn is_secured_message(&mut self, message: &[u8]) -> bool {
+            let mut buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            matches!(
+                self.responder
+                    .common
+                    .transport_encap
+                    .decap(message, &mut buffer),
+                Ok((_, true))
+            )
+        }
+
+        fn run_responder(&mut self) -> SpdmResult {
+            while self.pipe.pending_responses() == 0 && self.pipe.pending_requests() != 0 {
+                if self.responder.process_message(ST1).is_err() {
+                    return spdm_result_err!(EIO);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl SpdmDeviceIo for CorruptingRequesterIo<'_> {
+        fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+            let mut message = buffer.to_vec();
+            if self.corrupt_count.get() != 0 && self.is_secured_message(&message) {
+                self.corrupt_count.set(self.corrupt_count.get() - 1);
+                // within the MAC, past any transport padding
+                let index = message.len() - 5;
+                message[index] ^= 0xff;
+            }
+            self.pipe.to_responder.borrow_mut().push_back(message);
+            Ok(())
+        }
+
+        fn receive(&mut self, buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+            if self.run_responder().is_err() {
+                return Err(0);
+            }
+            pop_message(&self.pipe.to_requester, buffer)
+        }
+
+        fn flush_all(&mut self) -> SpdmResult {
+            self.pipe.clear();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case6_memory_pipe_decrypt_error_recovery() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        rsp_config_info.keep_session_on_decrypt_error = true;
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.req_capabilities = SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::KEY_UPD_CAP
+            | SpdmRequestCapabilityFlags::HBEAT_CAP;
+        req_config_info.decrypt_error_policy = SpdmDecryptErrorPolicy {
+            retry_count: 1,
+            rekey: false,
+            peer_keeps_session: true,
+        };
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let requester_events = SessionEventRecorder::default();
+        let corrupt_count = Cell::new(0);
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let mut requester_io = CorruptingRequesterIo {
+            pipe: &pipe,
+            responder: &mut responder,
+            corrupt_count: &corrupt_count,
+        };
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_session_observer(&requester_events);
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+
+        // the responder could not decrypt the HEARTBEAT, it is sent again
+        corrupt_count.set(1);
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+        assert_eq!(corrupt_count.get(), 0);
+
+        // nor the HEARTBEAT sent again, the keys are updated first
+        requester.common.config_info.decrypt_error_policy = SpdmDecryptErrorPolicy {
+            retry_count: 1,
+            rekey: true,
+            peer_keeps_session: true,
+        };
+        corrupt_count.set(2);
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+        assert_eq!(corrupt_count.get(), 0);
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+
+        // a responder not known to keep the session is not sent it again
+        requester.common.config_info.decrypt_error_policy = SpdmDecryptErrorPolicy {
+            retry_count: 1,
+            rekey: true,
+            peer_keeps_session: false,
+        };
+        corrupt_count.set(1);
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_err());
+        assert!(requester
+            .common
+            .get_immutable_session_via_id(session_id.as_u32())
+            .is_none());
+
+        let expected = [
+            (session_id.as_u32(), SpdmSessionEvent::HandshakeStarted),
+            (session_id.as_u32(), SpdmSessionEvent::Established),
+            (session_id.as_u32(), SpdmSessionEvent::KeyUpdated),
+            (session_id.as_u32(), SpdmSessionEvent::Terminated),
+        ];
+        assert_eq!(requester_events.events.borrow().as_slice(), &expected);
+    }
+
+    #[test]
+    fn test_case7_memory_pipe_builders() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+        // start_session sends no PSK hint
+        let psk_entries = [SpdmPskEntry {
+            psk_hint: b"",
+            psk: &[0x5au8; 48],
+            psk_context: &[0x22u8; 48],
+        }];
+        let psk_table = SpdmPskTable::new(&psk_entries);
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderBuilder::new()
+            .config_info(rsp_config_info)
+            .provision_info(rsp_provision_info)
+            .psk_store(&psk_table)
+            .build(&mut responder_io, pcidoe_transport_encap)
+            .unwrap();
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let builder = RequesterBuilder::new()
+            .config_info(req_config_info)
+            .provision_info(req_provision_info)
+            .capabilities(
+                SpdmRequestCapabilityFlags::CERT_CAP
+                    | SpdmRequestCapabilityFlags::ENCRYPT_CAP
+                    | SpdmRequestCapabilityFlags::MAC_CAP
+                    | SpdmRequestCapabilityFlags::PSK_CAP,
+            );
+        let mut requester = builder
+            .psk_store(&psk_table)
+            .build(&mut requester_io, pcidoe_transport_encap2)
+            .unwrap();
+
+        assert!(requester.init_connection().is_ok());
+        let session_id = requester
+            .start_session(
+                true,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        assert!(requester.end_session(session_id).is_ok());
+    }
+
+    #[test]
+    fn test_case8_builders_reject_config() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let error = ResponderBuilder::new()
+            .config_info(rsp_config_info)
+            .provision_info(rsp_provision_info)
+            .build(&mut responder_io, pcidoe_transport_encap)
+            .err();
+        assert_eq!(error, Some(SpdmConfigError::PskWithoutPskStore));
+
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.spdm_version = Default::default();
+        let pipe2 = MemoryPipe::new();
+        let mut requester_io = pipe2.responder_io();
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let error = RequesterBuilder::new()
+            .config_info(req_config_info)
+            .provision_info(req_provision_info)
+            .build(&mut requester_io, pcidoe_transport_encap2)
+            .err();
+        assert_eq!(error, Some(SpdmConfigError::NoVersion));
+    }
+
+    // Requester IO of a responder that lost its connection state: the next
+    // resynch_count requests request_code are answered with
+    // ERROR(RequestResynch).
+    struct ResynchResponderIo<'a> {
+        pipe: &'a MemoryPipe,
+        responder: &'a mut ResponderContext<'a>,
+        request_code: SpdmRequestResponseCode,
+        resynch_count: &'a Cell<usize>,
+    }
+
+    impl ResynchResponderIo<'_> {
+        fn peek_request_code(&mut self) -> Option<u8> {
+            let message = self.pipe.to_responder.borrow().front()?.clone();
+            let mut buffer = [0u8; config::DATA_TRANSFER_SIZE];
+            let (used, secured_message) = self
+                .responder
+                .common
+                .transport_encap
+                .decap(&message, &mut buffer)
+                .ok()?;
+            if secured_message || used < 2 {
+                return None;
+            }
+            Some(buffer[1])
+        }
+
+        fn run_responder(&mut self) -> SpdmResult {
+            while self.pipe.pending_responses() == 0 && self.pipe.pending_requests() != 0 {
+                if self.resynch_count.get() != 0
+                    && self.peek_request_code() == Some(self.request_code.get_u8())
+                {
+                    self.resynch_count.set(self.resynch_count.get() - 1);
+                    self.pipe.to_responder.borrow_mut().pop_front();
+                    let mut error = [0u8; config::MAX_SPDM_MESSAGE_BUFFER_SIZE];
+                    let mut writer = Writer::init(&mut error);
+                    self.responder.write_spdm_error(
+                        SpdmErrorCode::SpdmErrorRequestResynch,
+                        0,
+                        &mut writer,
+                    );
+                    self.responder.send_message(writer.used_slice())?;
+                    continue;
+                }
+                if self.responder.process_message(ST1).is_err() {
+                    return spdm_result_err!(EIO);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl SpdmDeviceIo for ResynchResponderIo<'_> {
+        fn send(&mut self, buffer: &[u8]) -> SpdmResult {
+            self.pipe
+                .to_responder
+                .borrow_mut()
+                .push_back(buffer.to_vec());
+            Ok(())
+        }
+
+        fn receive(&mut self, buffer: &mut [u8], _timeout: usize) -> Result<usize, usize> {
+            if self.run_responder().is_err() {
+                return Err(0);
+            }
+            pop_message(&self.pipe.to_requester, buffer)
+        }
+
+        fn flush_all(&mut self) -> SpdmResult {
+            self.pipe.clear();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_case9_memory_pipe_request_resynch() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        req_config_info.req_capabilities = SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::HBEAT_CAP;
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let requester_events = SessionEventRecorder::default();
+        let approve = Cell::new(false);
+        let resynch_handler = || approve.get();
+        let resynch_count = Cell::new(0);
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let mut requester_io = ResynchResponderIo {
+            pipe: &pipe,
+            responder: &mut responder,
+            request_code: SpdmRequestResponseCode::SpdmRequestChallenge,
+            resynch_count: &resynch_count,
+        };
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_session_observer(&requester_events);
+        requester.register_resynch_handler(&resynch_handler);
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+
+        // refused: the session is gone, the connection is left to the caller
+        resynch_count.set(1);
+        let error = requester
+            .send_receive_spdm_challenge(
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.num(), SpdmErrorNum::EDEV);
+        assert!(requester.common.get_session(session_id.as_u32()).is_err());
+        assert_eq!(
+            requester.common.negotiate_info.spdm_version_sel,
+            SpdmVersion::default()
+        );
+        assert!(requester.common.peer_info.peer_cert_chain[0].is_none());
+        assert!(requester_events
+            .events
+            .borrow()
+            .contains(&(session_id.as_u32(), SpdmSessionEvent::Terminated)));
+
+        // approved: connection negotiated and the chain retrieved again, the
+        // caller retries its request as is
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        approve.set(true);
+        resynch_count.set(1);
+        let error = requester
+            .send_receive_spdm_challenge(
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.num(), SpdmErrorNum::EAGAIN);
+        assert!(requester.common.get_session(session_id.as_u32()).is_err());
+        assert_ne!(
+            requester.common.negotiate_info.spdm_version_sel,
+            SpdmVersion::default()
+        );
+        assert!(requester.common.peer_info.peer_cert_chain[0].is_some());
+        assert!(requester
+            .send_receive_spdm_challenge(
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .is_ok());
+        assert!(requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_case10_memory_pipe_connection_info() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let (session_id, requester_info) = {
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            let connection_info = requester.get_connection_info();
+            assert_eq!(connection_info.spdm_version, SpdmVersion::default());
+            assert!(connection_info.sessions.is_empty());
+
+            assert!(requester.init_connection().is_ok());
+            assert!(requester.send_receive_spdm_digest(None).is_ok());
+            assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+            let session_id = requester
+                .start_session(
+                    false,
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                )
+                .unwrap();
+            (session_id, requester.get_connection_info())
+        };
+        let responder_info = responder.get_connection_info();
+
+        assert_ne!(requester_info.spdm_version, SpdmVersion::default());
+        assert_eq!(requester_info.spdm_version, responder_info.spdm_version);
+        assert_eq!(
+            requester_info.rsp_capabilities,
+            responder_info.rsp_capabilities
+        );
+        assert_eq!(requester_info.base_hash_algo, responder_info.base_hash_algo);
+        assert_eq!(requester_info.base_asym_algo, responder_info.base_asym_algo);
+        assert_eq!(requester_info.dhe_algo, responder_info.dhe_algo);
+        assert_eq!(requester_info.aead_algo, responder_info.aead_algo);
+        assert_eq!(requester_info.peer_slot_mask & 1, 1);
+        assert!(requester_info.peer_cert_chain_hash[0].is_some());
+        assert!(requester_info.peer_cert_chain_hash[1..]
+            .iter()
+            .all(Option::is_none));
+        assert!(responder_info
+            .peer_cert_chain_hash
+            .iter()
+            .all(Option::is_none));
+
+        let session = SpdmSessionInfo {
+            session_id: session_id.as_u32(),
+            session_state: SpdmSessionState::SpdmSessionEstablished,
+            use_psk: false,
+            slot_id: 0,
+        };
+        assert_eq!(requester_info.sessions, [session].to_vec());
+        assert_eq!(responder_info.sessions, [session].to_vec());
+    }
+
+    #[test]
+    fn test_case11_memory_pipe_transcript_hash() {
+        let (rsp_config_info, rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let (session_id, requester_m1, requester_th2, requester_audit) = {
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            assert!(requester.init_connection().is_ok());
+            assert!(requester.send_receive_spdm_digest(None).is_ok());
+            assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+            assert!(requester
+                .send_receive_spdm_challenge(
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                )
+                .is_ok());
+            let m1 = requester.get_m1m2_hash().unwrap();
+            let session_id = requester
+                .send_receive_spdm_key_exchange(
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                )
+                .unwrap();
+            // no TH2 before FINISH
+            assert!(requester.get_session_transcript_hash(session_id).is_err());
+            assert!(requester.send_receive_spdm_finish(0, session_id).is_ok());
+            assert!(requester
+                .get_session_transcript_hash(SpdmSessionId::from(session_id.as_u32() + 1))
+                .is_err());
+            (
+                session_id,
+                m1,
+                requester.get_session_transcript_hash(session_id).unwrap(),
+                requester.get_session_audit(session_id).unwrap(),
+            )
+        };
+
+        // both ends hashed the same transcripts
+        let responder_m2 = responder.get_m1m2_hash().unwrap();
+        assert_eq!(requester_m1.as_ref(), responder_m2.as_ref());
+        let responder_th2 = responder
+            .get_session_transcript_hash(session_id.as_u32())
+            .unwrap();
+        assert_eq!(requester_th2.as_ref(), responder_th2.as_ref());
+        assert_eq!(requester_th2.as_ref(), requester_audit.th2.as_ref());
+
+        // TH2 = Hash(message_a || Hash(cert chain) || message_k || message_f)
+        #[cfg(not(feature = "hash-update"))]
+        {
+            let mut transcript = Vec::new();
+            transcript.extend_from_slice(requester_audit.message_a.as_ref());
+            transcript.extend_from_slice(requester_audit.cert_chain_hash.unwrap().as_ref());
+            transcript.extend_from_slice(requester_audit.message_k.as_ref());
+            transcript.extend_from_slice(requester_audit.message_f.as_ref());
+            let th2 = crypto::hash::hash_all(requester_audit.base_hash_algo, &transcript).unwrap();
+            assert_eq!(requester_th2.as_ref(), th2.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_case12_memory_pipe_response_not_ready() {
+        // request_code, session or not, rdtm, not_ready_count, success
+        let cases = [
+            (
+                SpdmRequestResponseCode::SpdmRequestGetDigests,
+                false,
+                3,
+                3,
+                true,
+            ),
+            (
+                SpdmRequestResponseCode::SpdmRequestChallenge,
+                false,
+                3,
+                3,
+                true,
+            ),
+            (
+                SpdmRequestResponseCode::SpdmRequestHeartbeat,
+                true,
+                3,
+                3,
+                true,
+            ),
+            (
+                SpdmRequestResponseCode::SpdmRequestHeartbeat,
+                true,
+                1,
+                2,
+                false,
+            ),
+        ];
+        for (request_code, in_session, rdtm, not_ready_count, success) in cases {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (mut req_config_info, req_provision_info) = create_info();
+            req_config_info.req_capabilities = SpdmRequestCapabilityFlags::ENCRYPT_CAP
+                | SpdmRequestCapabilityFlags::MAC_CAP
+                | SpdmRequestCapabilityFlags::KEY_EX_CAP
+                | SpdmRequestCapabilityFlags::HBEAT_CAP;
+
+            crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+            crate::time::register(TIME_TEST.clone());
+
+            // the response to request_code is deferred not_ready_count times
+            // in all, recording the session each RESPOND_IF_READY came in
+            let respond_if_ready = RefCell::new(Vec::new());
+            let not_ready_left = Cell::new(not_ready_count);
+            let deferred = Cell::new(false);
+            let deferral_handler =
+                |session_id: Option<u32>, code: SpdmRequestResponseCode| -> Option<(u8, u8)> {
+                    if code != request_code {
+                        return None;
+                    }
+                    if deferred.get() {
+                        respond_if_ready.borrow_mut().push(session_id);
+                    } else if not_ready_left.get() == not_ready_count {
+                        deferred.set(true);
+                    } else {
+                        return None;
+                    }
+                    match not_ready_left.get() {
+                        0 => {
+                            deferred.set(false);
+                            None
+                        }
+                        left => {
+                            not_ready_left.set(left - 1);
+                            Some((0, rdtm))
+                        }
+                    }
+                };
+            let pipe = MemoryPipe::new();
+            let mut responder_io = pipe.responder_io();
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+            let mut responder = ResponderContext::new(
+                &mut responder_io,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+            responder.register_deferral_handler(&deferral_handler);
+
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+
+            assert!(requester.init_connection().is_ok());
+            let (session_id, result) = if in_session {
+                assert!(requester.send_receive_spdm_digest(None).is_ok());
+                assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+                let session_id = requester
+                    .start_session(
+                        false,
+                        0,
+                        SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                    )
+                    .unwrap();
+                let result = requester.send_receive_spdm_heartbeat(session_id);
+                (Some(session_id), result)
+            } else if request_code == SpdmRequestResponseCode::SpdmRequestChallenge {
+                // CHALLENGE and CHALLENGE_AUTH get into the signed transcript once
+                assert!(requester.send_receive_spdm_digest(None).is_ok());
+                assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+                let result = requester.send_receive_spdm_challenge(
+                    0,
+                    SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+                );
+                (None, result)
+            } else {
+                (None, requester.send_receive_spdm_digest(None).map(|_| ()))
+            };
+
+            // RESPOND_IF_READY in the session of the request, up to WT_Max
+            if success {
+                assert!(result.is_ok());
+                assert_eq!(
+                    *respond_if_ready.borrow(),
+                    [session_id.map(u32::from); 3].to_vec()
+                );
+            } else {
+                assert_eq!(result.unwrap_err(), SpdmError::Timeout);
+                assert_eq!(
+                    *respond_if_ready.borrow(),
+                    [session_id.map(u32::from)].to_vec()
+                );
+            }
+            if let Some(session_id) = session_id.filter(|_| success) {
+                assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+            }
+        }
+    }
+
+    static SLOT2_SIGNATURES: AtomicUsize = AtomicUsize::new(0);
+
+    fn slot2_asym_sign(
+        base_hash_algo: SpdmBaseHashAlgo,
+        base_asym_algo: SpdmBaseAsymAlgo,
+        data: &[u8],
+    ) -> Option<SpdmSignatureStruct> {
+        SLOT2_SIGNATURES.fetch_add(1, Ordering::SeqCst);
+        (ASYM_SIGN_IMPL.sign_cb)(base_hash_algo, base_asym_algo, data)
+    }
+
+    #[test]
+    fn test_case13_memory_pipe_slot_signing_key() {
+        let (rsp_config_info, mut rsp_provision_info) = create_info();
+        let (req_config_info, req_provision_info) = create_info();
+        // slot 2 comes with its own signer, slot 3 has none
+        rsp_provision_info.my_cert_chain_data[2] = rsp_provision_info.my_cert_chain_data[0].clone();
+        rsp_provision_info.my_cert_chain_data[3] = rsp_provision_info.my_cert_chain_data[0].clone();
+        rsp_provision_info.my_asym_sign[2] = Some(SpdmAsymSign {
+            sign_cb: slot2_asym_sign,
+        });
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert_eq!(requester.common.peer_info.peer_slot_mask, 0b1101);
+
+        // slot 1 is not in the slot mask, nothing is sent for it
+        assert!(requester.send_receive_spdm_certificate(None, 1).is_err());
+        assert_eq!(pipe.pending_requests(), 0);
+
+        assert!(requester.send_receive_spdm_certificate(None, 2).is_ok());
+        assert!(requester
+            .send_receive_spdm_challenge(
+                2,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .is_ok());
+        assert_eq!(SLOT2_SIGNATURES.load(Ordering::SeqCst), 1);
+        let session_id = requester
+            .start_session(
+                false,
+                2,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        assert_eq!(SLOT2_SIGNATURES.load(Ordering::SeqCst), 2);
+        assert!(requester.end_session(session_id).is_ok());
+
+        // the key of slot 0 does not sign for slot 3
+        assert!(requester.send_receive_spdm_certificate(None, 3).is_ok());
+        assert!(requester
+            .send_receive_spdm_challenge(
+                3,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .is_err());
+        assert_eq!(SLOT2_SIGNATURES.load(Ordering::SeqCst), 2);
+    }
+
+    // The SPDM request codes sent in the clear, and the largest SPDM
+    // message in the clear either way.
+    #[derive(Default)]
+    struct ClearMessageRecorder {
+        request_codes: RefCell<Vec<u8>>,
+        max_size: Cell<usize>,
+    }
+
+    impl SpdmTracer for ClearMessageRecorder {
+        fn trace(
+            &self,
+            direction: SpdmTraceDirection,
+            session_id: Option<u32>,
+            _timestamp: u64,
+            transport_message: &[u8],
+        ) {
+            if session_id.is_some() {
+                return;
+            }
+            // past the 8-byte PCI-DOE header
+            let spdm_message = &transport_message[8..];
+            self.max_size
+                .set(self.max_size.get().max(spdm_message.len()));
+            if direction == SpdmTraceDirection::Send {
+                self.request_codes.borrow_mut().push(spdm_message[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_case14_memory_pipe_chunking() {
+        let (mut rsp_config_info, rsp_provision_info) = create_info();
+        let (mut req_config_info, req_provision_info) = create_info();
+        rsp_config_info.rsp_capabilities |= SpdmResponseCapabilityFlags::CHUNK_CAP;
+        rsp_config_info.data_transfer_size = 0x40;
+        rsp_config_info.max_spdm_msg_size = config::MAX_SPDM_MESSAGE_BUFFER_SIZE as u32;
+        req_config_info.req_capabilities = SpdmRequestCapabilityFlags::ENCRYPT_CAP
+            | SpdmRequestCapabilityFlags::MAC_CAP
+            | SpdmRequestCapabilityFlags::KEY_EX_CAP
+            | SpdmRequestCapabilityFlags::HBEAT_CAP
+            | SpdmRequestCapabilityFlags::CHUNK_CAP;
+        req_config_info.data_transfer_size = 0x40;
+        req_config_info.max_spdm_msg_size = config::MAX_SPDM_MESSAGE_BUFFER_SIZE as u32;
+
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        let requester_trace = ClearMessageRecorder::default();
+        let pipe = MemoryPipe::new();
+        let mut responder_io = pipe.responder_io();
+        let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+        let mut responder = ResponderContext::new(
+            &mut responder_io,
+            pcidoe_transport_encap,
+            rsp_config_info,
+            rsp_provision_info,
+        );
+
+        let mut requester_io = pipe.requester_io(&mut responder);
+        let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+        let mut requester = RequesterContext::new(
+            &mut requester_io,
+            pcidoe_transport_encap2,
+            req_config_info,
+            req_provision_info,
+        );
+        requester.register_tracer(&requester_trace);
+
+        assert!(requester.init_connection().is_ok());
+        assert!(requester.common.is_chunk_cap_negotiated());
+        assert!(requester.send_receive_spdm_digest(None).is_ok());
+        assert!(requester.send_receive_spdm_certificate(None, 0).is_ok());
+
+        // CHALLENGE_AUTH comes with CHUNK_GET
+        assert!(requester
+            .send_receive_spdm_challenge(
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .is_ok());
+        assert!(requester_trace
+            .request_codes
+            .borrow()
+            .contains(&SpdmRequestResponseCode::SpdmRequestChunkGet.get_u8()));
+        assert!(!requester_trace
+            .request_codes
+            .borrow()
+            .contains(&SpdmRequestResponseCode::SpdmRequestChunkSend.get_u8()));
+
+        // KEY_EXCHANGE goes with CHUNK_SEND, KEY_EXCHANGE_RSP with CHUNK_GET
+        let session_id = requester
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap();
+        assert!(requester_trace
+            .request_codes
+            .borrow()
+            .contains(&SpdmRequestResponseCode::SpdmRequestChunkSend.get_u8()));
+        assert!(requester.send_receive_spdm_heartbeat(session_id).is_ok());
+        assert!(requester.end_session(session_id).is_ok());
+
+        // no single transfer was over DataTransferSize
+        assert!(requester_trace.max_size.get() <= 0x40);
+        assert_eq!(pipe.pending_requests(), 0);
+        assert_eq!(pipe.pending_responses(), 0);
+    }
+
+    // A clock stopped at a trusted time.
+    struct FixedClock(u64);
+
+    impl SpdmTimeProvider for FixedClock {
+        fn get_cert_validity_time(&self) -> SpdmResult<SpdmCertValidityTime> {
+            Ok(SpdmCertValidityTime::At(self.0))
+        }
+
+        fn now(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_case15_memory_pipe_time_provider() {
+        crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
+
+        // 2023-11-14, then 1970-01-01, before the responder certificates
+        for (clock, verified) in [(FixedClock(1700000000), true), (FixedClock(0), false)] {
+            let (rsp_config_info, rsp_provision_info) = create_info();
+            let (req_config_info, req_provision_info) = create_info();
+
+            let pipe = MemoryPipe::new();
+            let mut responder_io = pipe.responder_io();
+            let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+            let mut responder = ResponderContext::new(
+                &mut responder_io,
+                pcidoe_transport_encap,
+                rsp_config_info,
+                rsp_provision_info,
+            );
+            responder.register_time_provider(&clock);
+
+            let mut requester_io = pipe.requester_io(&mut responder);
+            let pcidoe_transport_encap2 = &mut PciDoeTransportEncap {};
+            let mut requester = RequesterContext::new(
+                &mut requester_io,
+                pcidoe_transport_encap2,
+                req_config_info,
+                req_provision_info,
+            );
+            requester.register_time_provider(&clock);
+
+            assert!(requester.init_connection().is_ok());
+            assert!(requester.send_receive_spdm_digest(None).is_ok());
+            match requester.send_receive_spdm_certificate(None, 0) {
+                Ok(()) => assert!(verified),
+                Err(error) => {
+                    assert!(!verified);
+                    assert_eq!(error.num(), SpdmErrorNum::ESEC);
+                }
+            }
+        }
+    }
+}
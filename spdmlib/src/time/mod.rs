@@ -12,6 +12,7 @@ static TIME_INSTANCE: OnceCell<SpdmTime> = OnceCell::uninit();
 
 static DEFAULT: SpdmTime = SpdmTime {
     sleep_cb: |_: usize| unimplemented!(),
+    now_cb: || 0,
 };
 
 pub fn register(context: SpdmTime) -> bool {
@@ -25,3 +26,12 @@ pub fn sleep(us: usize) {
         .unwrap()
         .sleep_cb)(us)
 }
+
+/// Timestamp of the registered clock, 0 without one.
+pub fn now() -> u64 {
+    (TIME_INSTANCE
+        .try_get_or_init(|| DEFAULT.clone())
+        .ok()
+        .unwrap()
+        .now_cb)()
+}
@@ -5,4 +5,6 @@
 #[derive(Clone)]
 pub struct SpdmTime {
     pub sleep_cb: fn(us: usize),
+    /// Microseconds from a monotonic clock.
+    pub now_cb: fn() -> u64,
 }
@@ -0,0 +1,257 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Reference values of the measurements of a responder, for
+//! RequesterContext::attest_measurements to check them against.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::common::spdm_measurement_to_digest;
+use crate::crypto;
+use crate::error::{spdm_result_err, SpdmResult};
+use crate::protocol::{
+    SpdmMeasurementBlockStructure, SpdmMeasurementHashAlgo, SpdmMeasurementRecordStructure,
+};
+
+/// Expected value of measurement block index, under hash_algo.
+#[derive(Debug, Clone)]
+pub struct SpdmReferenceMeasurement {
+    pub index: u8,
+    pub hash_algo: SpdmMeasurementHashAlgo,
+    pub digest: Vec<u8>,
+    // a block in raw bit stream form is compared with it, instead of digest
+    pub raw_value: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpdmAttestationReport {
+    pub matched: Vec<u8>,
+    pub mismatched: Vec<u8>,
+    // indices with a reference value the responder did not report
+    pub missing: Vec<u8>,
+    // indices the responder reported without a reference value
+    pub unreferenced: Vec<u8>,
+}
+
+impl SpdmAttestationReport {
+    /// Every reference value matched.
+    pub fn is_success(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpdmMeasurementVerifier {
+    references: Vec<SpdmReferenceMeasurement>,
+}
+
+impl SpdmMeasurementVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect block index to be digest under hash_algo, replacing any
+    /// reference of the same index and hash algorithm.
+    pub fn register_digest(
+        &mut self,
+        index: u8,
+        hash_algo: SpdmMeasurementHashAlgo,
+        digest: &[u8],
+    ) -> SpdmResult {
+        if index == 0
+            || index == 0xff
+            || hash_algo.to_base_hash_algo().is_none()
+            || digest.len() != hash_algo.get_size() as usize
+        {
+            return spdm_result_err!(EINVAL);
+        }
+        self.register(SpdmReferenceMeasurement {
+            index,
+            hash_algo,
+            digest: digest.to_vec(),
+            raw_value: None,
+        });
+        Ok(())
+    }
+
+    /// Expect block index to be raw_value, or its digest under hash_algo.
+    /// Under RAW_BIT_STREAM only the raw value is compared.
+    pub fn register_raw_value(
+        &mut self,
+        index: u8,
+        hash_algo: SpdmMeasurementHashAlgo,
+        raw_value: &[u8],
+    ) -> SpdmResult {
+        if index == 0 || index == 0xff {
+            return spdm_result_err!(EINVAL);
+        }
+        let digest = match hash_algo.to_base_hash_algo() {
+            Some(base_hash_algo) => match crypto::hash::hash_all(base_hash_algo, raw_value) {
+                Some(digest) => digest.as_ref().to_vec(),
+                None => return spdm_result_err!(EINVAL),
+            },
+            None if hash_algo == SpdmMeasurementHashAlgo::RAW_BIT_STREAM => Vec::new(),
+            None => return spdm_result_err!(EINVAL),
+        };
+        self.register(SpdmReferenceMeasurement {
+            index,
+            hash_algo,
+            digest,
+            raw_value: Some(raw_value.to_vec()),
+        });
+        Ok(())
+    }
+
+    fn register(&mut self, reference: SpdmReferenceMeasurement) {
+        self.references
+            .retain(|r| r.index != reference.index || r.hash_algo != reference.hash_algo);
+        self.references.push(reference);
+    }
+
+    pub fn get_references(&self) -> &[SpdmReferenceMeasurement] {
+        &self.references
+    }
+
+    /// Compare the blocks of measurement_record, under the negotiated
+    /// measurement hash algorithm hash_algo, to the reference values of
+    /// that hash algorithm, the others are ignored.
+    pub fn verify(
+        &self,
+        measurement_record: &SpdmMeasurementRecordStructure,
+        hash_algo: SpdmMeasurementHashAlgo,
+    ) -> SpdmAttestationReport {
+        let blocks = &measurement_record.record[..measurement_record.number_of_blocks as usize];
+        let mut report = SpdmAttestationReport::default();
+        for block in blocks {
+            match self
+                .references
+                .iter()
+                .find(|r| r.index == block.index && r.hash_algo == hash_algo)
+            {
+                Some(reference) if Self::matches(reference, block) => {
+                    report.matched.push(block.index)
+                }
+                Some(_) => report.mismatched.push(block.index),
+                None => report.unreferenced.push(block.index),
+            }
+        }
+        for reference in self.references.iter().filter(|r| r.hash_algo == hash_algo) {
+            if !blocks.iter().any(|block| block.index == reference.index) {
+                report.missing.push(reference.index);
+            }
+        }
+        report
+    }
+
+    fn matches(
+        reference: &SpdmReferenceMeasurement,
+        block: &SpdmMeasurementBlockStructure,
+    ) -> bool {
+        let measurement = &block.measurement;
+        if measurement.is_raw_bit_stream() {
+            if let Some(raw_value) = &reference.raw_value {
+                return raw_value.as_slice()
+                    == &measurement.value[..measurement.value_size as usize];
+            }
+        }
+        let base_hash_algo = match reference.hash_algo.to_base_hash_algo() {
+            Some(base_hash_algo) => base_hash_algo,
+            None => return false,
+        };
+        match spdm_measurement_to_digest(measurement, base_hash_algo) {
+            Some(digest) => {
+                reference.digest.as_slice() == &digest.value[..digest.value_size as usize]
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::measurement::{
+        spdm_build_measurement_record, SpdmFirmwareRegion, SpdmFirmwareRegionKind,
+    };
+    use crate::protocol::{SpdmBaseHashAlgo, SpdmDmtfMeasurementRepresentation};
+
+    #[test]
+    fn test_case0_measurement_verifier() {
+        let hash_algo = SpdmMeasurementHashAlgo::TPM_ALG_SHA_384;
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let firmware_regions = [
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::ImmutableRom,
+                tcb: true,
+                data: &[&b"rom"[..]],
+            },
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::MutableFirmware,
+                tcb: true,
+                data: &[&b"firmware"[..]],
+            },
+            SpdmFirmwareRegion {
+                kind: SpdmFirmwareRegionKind::FirmwareConfig,
+                tcb: false,
+                data: &[&b"config"[..]],
+            },
+        ];
+        let mut record =
+            spdm_build_measurement_record(&firmware_regions, base_hash_algo, 0xff).unwrap();
+
+        let mut verifier = SpdmMeasurementVerifier::new();
+        assert!(verifier.register_digest(1, hash_algo, &[0u8; 32]).is_err());
+        assert!(verifier.register_digest(0, hash_algo, &[0u8; 48]).is_err());
+        assert!(verifier.register_raw_value(1, hash_algo, b"rom").is_ok());
+        let digest = crypto::hash::hash_all(base_hash_algo, b"patched firmware").unwrap();
+        assert!(verifier
+            .register_digest(2, hash_algo, digest.as_ref())
+            .is_ok());
+        assert!(verifier
+            .register_raw_value(4, hash_algo, b"manifest")
+            .is_ok());
+        // ignored, another hash algorithm
+        assert!(verifier
+            .register_raw_value(3, SpdmMeasurementHashAlgo::TPM_ALG_SHA_256, b"config")
+            .is_ok());
+
+        let report = verifier.verify(&record, hash_algo);
+        assert_eq!(
+            report,
+            SpdmAttestationReport {
+                matched: [1].to_vec(),
+                mismatched: [2].to_vec(),
+                missing: [4].to_vec(),
+                unreferenced: [3].to_vec(),
+            }
+        );
+        assert!(!report.is_success());
+
+        // the reference value replaced, a raw bit stream block
+        assert!(verifier
+            .register_raw_value(2, hash_algo, b"firmware")
+            .is_ok());
+        assert_eq!(verifier.get_references().len(), 4);
+        let measurement = &mut record.record[1].measurement;
+        measurement.representation = SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementRawBit;
+        measurement.value_size = 8;
+        measurement.value[..8].copy_from_slice(b"firmware");
+        record.number_of_blocks = 2;
+        let report = verifier.verify(&record, hash_algo);
+        assert_eq!(report.matched, [1, 2].to_vec());
+        assert_eq!(report.missing, [4].to_vec());
+
+        // under RAW_BIT_STREAM only raw values compare
+        assert!(verifier
+            .register_digest(1, SpdmMeasurementHashAlgo::RAW_BIT_STREAM, &[])
+            .is_err());
+        assert!(verifier
+            .register_raw_value(2, SpdmMeasurementHashAlgo::RAW_BIT_STREAM, b"firmware")
+            .is_ok());
+        let report = verifier.verify(&record, SpdmMeasurementHashAlgo::RAW_BIT_STREAM);
+        assert_eq!(report.matched, [2].to_vec());
+        assert_eq!(report.unreferenced, [1].to_vec());
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The AEAD offload engine is registered process wide, so this test has a
+// binary of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::crypto::{self, SpdmAeadKeyHandle, SpdmAeadOffload};
+use spdmlib::error::{spdm_err, SpdmResult};
+use spdmlib::protocol::{SpdmAeadAlgo, SpdmMeasurementSummaryHashType};
+use spdmlib::{requester, responder};
+use std::sync::Mutex;
+
+// Engine holding its keys in a table, backed by the software AEAD.
+struct TestAeadOffload {
+    keys: Mutex<Vec<Option<Vec<u8>>>>,
+    encrypted: AtomicUsize,
+    decrypted: AtomicUsize,
+}
+
+static TEST_AEAD_OFFLOAD: TestAeadOffload = TestAeadOffload {
+    keys: Mutex::new(Vec::new()),
+    encrypted: AtomicUsize::new(0),
+    decrypted: AtomicUsize::new(0),
+};
+
+impl TestAeadOffload {
+    fn get_key(&self, key_handle: SpdmAeadKeyHandle) -> SpdmResult<Vec<u8>> {
+        self.keys
+            .lock()
+            .unwrap()
+            .get(key_handle.0 as usize)
+            .cloned()
+            .flatten()
+            .ok_or(spdm_err!(EINVAL))
+    }
+
+    fn imported_keys(&self) -> usize {
+        self.keys.lock().unwrap().len()
+    }
+
+    fn held_keys(&self) -> usize {
+        self.keys
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|key| key.is_some())
+            .count()
+    }
+}
+
+impl SpdmAeadOffload for TestAeadOffload {
+    fn import_key(&self, _aead_algo: SpdmAeadAlgo, key: &[u8]) -> SpdmResult<SpdmAeadKeyHandle> {
+        let mut keys = self.keys.lock().unwrap();
+        keys.push(Some(key.to_vec()));
+        Ok(SpdmAeadKeyHandle(keys.len() as u32 - 1))
+    }
+
+    fn release_key(&self, key_handle: SpdmAeadKeyHandle) {
+        self.keys.lock().unwrap()[key_handle.0 as usize] = None;
+    }
+
+    fn encrypt(
+        &self,
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        plain_text: &[u8],
+        tag: &mut [u8],
+        cipher_text: &mut [u8],
+    ) -> SpdmResult<(usize, usize)> {
+        let key = self.get_key(key_handle)?;
+        self.encrypted.fetch_add(1, Ordering::SeqCst);
+        crypto::aead::encrypt(aead_algo, &key, iv, aad, plain_text, tag, cipher_text)
+    }
+
+    fn decrypt(
+        &self,
+        aead_algo: SpdmAeadAlgo,
+        key_handle: SpdmAeadKeyHandle,
+        iv: &[u8],
+        aad: &[u8],
+        cipher_text: &[u8],
+        tag: &[u8],
+        plain_text: &mut [u8],
+    ) -> SpdmResult<usize> {
+        let key = self.get_key(key_handle)?;
+        self.decrypted.fetch_add(1, Ordering::SeqCst);
+        crypto::aead::decrypt(aead_algo, &key, iv, aad, cipher_text, tag, plain_text)
+    }
+}
+
+#[test]
+fn intergration_aead_offload() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+    assert!(crypto::aead::register_offload(&TEST_AEAD_OFFLOAD));
+    assert!(crypto::aead::is_offload_registered());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+
+    // both sides hand their session keys over to the engine, FINISH and its
+    // response already go through it
+    let session_id = requester_context
+        .start_session(
+            false,
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .unwrap();
+    assert!(TEST_AEAD_OFFLOAD.imported_keys() > 0);
+    assert!(TEST_AEAD_OFFLOAD.held_keys() > 0);
+    assert!(TEST_AEAD_OFFLOAD.encrypted.load(Ordering::SeqCst) > 0);
+    assert!(TEST_AEAD_OFFLOAD.decrypted.load(Ordering::SeqCst) > 0);
+
+    // no key stays in software
+    let session = requester_context
+        .common
+        .get_session_via_id(session_id.as_u32())
+        .unwrap();
+    let (request_direction, response_direction) = session.export_keys();
+    assert!(request_direction.key_handle.is_some());
+    assert_eq!(request_direction.encryption_key.data_size, 0);
+    assert!(response_direction.key_handle.is_some());
+    assert_eq!(response_direction.encryption_key.data_size, 0);
+
+    // and the engine drops them with the sessions
+    let encrypted = TEST_AEAD_OFFLOAD.encrypted.load(Ordering::SeqCst);
+    assert!(requester_context.end_session(session_id).is_ok());
+    assert!(TEST_AEAD_OFFLOAD.encrypted.load(Ordering::SeqCst) > encrypted);
+    assert_eq!(TEST_AEAD_OFFLOAD.held_keys(), 0);
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use mctp_transport::MctpTransportEncap;
+use spdmlib::common::session::SpdmSessionAppData;
+use spdmlib::error::SpdmResult;
+use spdmlib::protocol::SpdmMeasurementSummaryHashType;
+use spdmlib::{requester, responder};
+
+// MCTP vendor defined message, IANA enterprise number 343 (Intel)
+const VENDOR_MESSAGE_HEADER: [u8; 5] = [0x7f, 0x00, 0x00, 0x01, 0x57];
+
+#[test]
+fn intergration_app_message() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut MctpTransportEncap {};
+
+    // PLDM requests are answered reversed, vendor defined ones echoed, both
+    // followed by the number of requests seen in the session
+    let handler = |_session_id: u32,
+                   app_data: &mut SpdmSessionAppData,
+                   request: &[u8],
+                   response: &mut [u8]|
+     -> SpdmResult<usize> {
+        match app_data.get_mut::<u8>() {
+            Some(count) => *count += 1,
+            None => app_data.set(1u8),
+        }
+        if request.starts_with(&VENDOR_MESSAGE_HEADER) {
+            response[..request.len()].copy_from_slice(request);
+        } else {
+            for (i, data) in request.iter().rev().enumerate() {
+                response[i] = *data;
+            }
+        }
+        response[request.len()] = *app_data.get::<u8>().unwrap();
+        Ok(request.len() + 1)
+    };
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+    responder_context.register_app_message_handler(&handler);
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut MctpTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+    let session_id = requester_context
+        .start_session(
+            false,
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .unwrap();
+
+    // PLDM GetTID
+    let mut response = [0u8; 16];
+    let used = requester_context
+        .send_receive_app_message(session_id, &[0x80, 0x00, 0x02], &mut response)
+        .unwrap();
+    assert_eq!(&response[..used], &[0x02, 0x00, 0x80, 1]);
+
+    let mut request = VENDOR_MESSAGE_HEADER.to_vec();
+    request.extend_from_slice(&[0xde, 0xad]);
+    let used = requester_context
+        .send_receive_app_message(session_id, &request, &mut response)
+        .unwrap();
+    assert_eq!(&response[..(used - 1)], &request[..]);
+    assert_eq!(response[used - 1], 2);
+
+    assert!(requester_context.end_session(session_id).is_ok());
+}
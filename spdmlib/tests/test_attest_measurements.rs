@@ -0,0 +1,123 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The measurement provider is registered process wide, so this test has a
+// binary of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::crypto;
+use spdmlib::measurement::{
+    spdm_build_measurement_record, SpdmFirmwareRegion, SpdmFirmwareRegionKind,
+};
+use spdmlib::protocol::{
+    SpdmBaseHashAlgo, SpdmMeasurementHashAlgo, SpdmMeasurementRecordStructure,
+    SpdmMeasurementSpecification, SpdmVersion,
+};
+use spdmlib::secret::SpdmSecret;
+use spdmlib::verifier::SpdmMeasurementVerifier;
+use spdmlib::{requester, responder};
+
+static FIRMWARE_REGIONS: [SpdmFirmwareRegion<'static>; 2] = [
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::ImmutableRom,
+        tcb: true,
+        data: &[b"rom"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::MutableFirmware,
+        tcb: true,
+        data: &[b"firmware", b" patch"],
+    },
+];
+
+fn measurement_collection(
+    _spdm_version: SpdmVersion,
+    _measurement_specification: SpdmMeasurementSpecification,
+    measurement_hash_algo: SpdmBaseHashAlgo,
+    measurement_index: usize,
+) -> Option<SpdmMeasurementRecordStructure> {
+    spdm_build_measurement_record(&FIRMWARE_REGIONS, measurement_hash_algo, measurement_index)
+}
+
+static SECRET_IMPL: SpdmSecret = SpdmSecret {
+    spdm_measurement_collection_cb: measurement_collection,
+    spdm_generate_measurement_summary_hash_cb: |_, _, _, _, _| None,
+    spdm_requester_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_responder_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_psk_handshake_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+    spdm_psk_master_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+};
+
+#[test]
+fn intergration_attest_measurements() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+    spdmlib::secret::register(SECRET_IMPL.clone());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    // measurements are hashed with another algorithm than the transcript
+    let (mut config_info, provision_info) = common::utils::rsp_create_info();
+    config_info.measurement_hash_algo = SpdmMeasurementHashAlgo::TPM_ALG_SHA_256;
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+
+    let hash_algo = SpdmMeasurementHashAlgo::TPM_ALG_SHA_256;
+    let mut verifier = SpdmMeasurementVerifier::new();
+    assert!(verifier.register_raw_value(1, hash_algo, b"rom").is_ok());
+    assert!(verifier
+        .register_raw_value(2, hash_algo, b"firmware patch")
+        .is_ok());
+    // the same values under the transcript hash algorithm are not used
+    let digest = crypto::hash::hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_384, b"rom").unwrap();
+    assert!(verifier
+        .register_digest(1, SpdmMeasurementHashAlgo::TPM_ALG_SHA_384, digest.as_ref())
+        .is_ok());
+
+    let report = requester_context
+        .attest_measurements(None, 0, &verifier)
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.matched, [1, 2].to_vec());
+    assert!(report.unreferenced.is_empty());
+
+    // a changed firmware region is reported
+    let mut verifier = SpdmMeasurementVerifier::new();
+    assert!(verifier.register_raw_value(1, hash_algo, b"rom").is_ok());
+    assert!(verifier
+        .register_raw_value(2, hash_algo, b"firmware")
+        .is_ok());
+    let report = requester_context
+        .attest_measurements(None, 0, &verifier)
+        .unwrap();
+    assert!(!report.is_success());
+    assert_eq!(report.matched, [1].to_vec());
+    assert_eq!(report.mismatched, [2].to_vec());
+}
@@ -55,13 +55,13 @@ fn intergration_client_server() {
     if let Ok(session_id) = result {
         log::info!(
             "\nSession established ... session_id is {:0x?}\n",
-            session_id
+            session_id.as_u32()
         );
         log::info!("Key Information ...\n");
 
         let session = requester_context
             .common
-            .get_session_via_id(session_id)
+            .get_session_via_id(session_id.as_u32())
             .expect("get session failed!");
         let (request_direction, response_direction) = session.export_keys();
         log::info!(
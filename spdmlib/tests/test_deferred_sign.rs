@@ -0,0 +1,116 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The external signer is registered process wide, so this test has a binary
+// of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::crypto::{SpdmAsymSignDeferred, SpdmAsymSignOperation, SpdmAsymSignPoll};
+use spdmlib::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmMeasurementSummaryHashType, SpdmSignatureStruct,
+};
+use spdmlib::time::SpdmTime;
+use spdmlib::{requester, responder};
+
+static PENDING_POLLS: AtomicUsize = AtomicUsize::new(0);
+
+// Answers Pending once before handing out the signature.
+struct SlowSignOperation {
+    signature: Option<SpdmSignatureStruct>,
+    busy: bool,
+}
+
+impl SpdmAsymSignOperation for SlowSignOperation {
+    fn poll(&mut self) -> SpdmAsymSignPoll {
+        if self.busy {
+            self.busy = false;
+            PENDING_POLLS.fetch_add(1, Ordering::SeqCst);
+            return SpdmAsymSignPoll::Pending;
+        }
+        match self.signature.take() {
+            Some(signature) => SpdmAsymSignPoll::Ready(signature),
+            None => SpdmAsymSignPoll::Failed,
+        }
+    }
+}
+
+fn slow_sign_start(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<Box<dyn SpdmAsymSignOperation>> {
+    let signature =
+        (common::crypto_callbacks::ASYM_SIGN_IMPL.sign_cb)(base_hash_algo, base_asym_algo, data)?;
+    Some(Box::new(SlowSignOperation {
+        signature: Some(signature),
+        busy: true,
+    }))
+}
+
+#[test]
+fn intergration_deferred_sign() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+    spdmlib::crypto::asym_sign::register_deferred(SpdmAsymSignDeferred {
+        sign_start_cb: slow_sign_start,
+    });
+    // the responder is in process, nothing to wait for
+    spdmlib::time::register(SpdmTime {
+        sleep_cb: |_: usize| {},
+        now_cb: || 0,
+    });
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+
+    // CHALLENGE_AUTH is deferred with ERROR(ResponseNotReady)
+    assert!(requester_context
+        .send_receive_spdm_challenge(
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .is_ok());
+    assert_eq!(PENDING_POLLS.load(Ordering::SeqCst), 1);
+
+    // so is KEY_EXCHANGE_RSP, the session is set up on RESPOND_IF_READY
+    let session_id = requester_context
+        .start_session(
+            false,
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .unwrap();
+    assert_eq!(PENDING_POLLS.load(Ordering::SeqCst), 2);
+    assert!(requester_context.end_session(session_id).is_ok());
+}
@@ -0,0 +1,139 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// With hash-update, M1/M2, L1/L2 and TH are running hashes, so a transcript
+// outgrows MAX_SPDM_MESSAGE_BUFFER_SIZE and is still signed and verified.
+// The measurement provider is registered process wide, so this test has a
+// binary of its own.
+
+#![cfg(feature = "hash-update")]
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::config::MAX_SPDM_MESSAGE_BUFFER_SIZE;
+use spdmlib::measurement::{
+    spdm_build_measurement_record, SpdmFirmwareRegion, SpdmFirmwareRegionKind,
+};
+use spdmlib::message::{SpdmMeasurementOperation, SpdmMeasurementeAttributes};
+use spdmlib::protocol::{
+    SpdmBaseHashAlgo, SpdmMeasurementRecordStructure, SpdmMeasurementSpecification,
+    SpdmMeasurementSummaryHashType, SpdmVersion,
+};
+use spdmlib::secret::SpdmSecret;
+use spdmlib::{requester, responder};
+
+static FIRMWARE_REGIONS: [SpdmFirmwareRegion<'static>; 2] = [
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::ImmutableRom,
+        tcb: true,
+        data: &[b"rom"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::MutableFirmware,
+        tcb: true,
+        data: &[b"firmware"],
+    },
+];
+
+static SECRET_IMPL: SpdmSecret = SpdmSecret {
+    spdm_measurement_collection_cb: |_spdm_version: SpdmVersion,
+                                     _measurement_specification: SpdmMeasurementSpecification,
+                                     measurement_hash_algo: SpdmBaseHashAlgo,
+                                     measurement_index: usize|
+     -> Option<SpdmMeasurementRecordStructure> {
+        spdm_build_measurement_record(&FIRMWARE_REGIONS, measurement_hash_algo, measurement_index)
+    },
+    spdm_generate_measurement_summary_hash_cb: |_, _, _, _, _| None,
+    spdm_requester_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_responder_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_psk_handshake_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+    spdm_psk_master_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+};
+
+#[test]
+fn intergration_hash_update() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+    spdmlib::secret::register(SECRET_IMPL.clone());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+
+    // message_b gets the certificate chain on every GET_CERTIFICATE, M1/M2
+    // is past the message buffer before CHALLENGE_AUTH is verified
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+    let cert_chain_size = requester_context.common.peer_info.peer_cert_chain[0]
+        .as_ref()
+        .unwrap()
+        .cert_chain
+        .data_size as usize;
+    for _ in 0..MAX_SPDM_MESSAGE_BUFFER_SIZE / cert_chain_size {
+        assert!(requester_context
+            .send_receive_spdm_certificate(None, 0)
+            .is_ok());
+    }
+    assert!(requester_context
+        .send_receive_spdm_challenge(
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone
+        )
+        .is_ok());
+
+    // each unsigned MEASUREMENTS exchange is well over 64 bytes, L1/L2 is
+    // past the message buffer before the signed one is verified
+    for _ in 0..MAX_SPDM_MESSAGE_BUFFER_SIZE / 64 {
+        assert!(requester_context
+            .send_receive_spdm_measurement(
+                None,
+                0,
+                SpdmMeasurementeAttributes::empty(),
+                SpdmMeasurementOperation::SpdmMeasurementRequestAll,
+            )
+            .is_ok());
+    }
+    assert!(requester_context
+        .send_receive_spdm_measurement(
+            None,
+            0,
+            SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
+            SpdmMeasurementOperation::SpdmMeasurementRequestAll,
+        )
+        .is_ok());
+
+    // TH runs on from the same message_a, FINISH verifies it on both sides
+    assert!(requester_context
+        .start_session(
+            false,
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .is_ok());
+}
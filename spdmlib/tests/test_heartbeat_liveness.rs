@@ -0,0 +1,112 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The clock is registered process wide, so this test has a binary of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU64, Ordering};
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::common::{SpdmSessionEvent, SpdmSessionObserver, ST1};
+use spdmlib::protocol::SpdmMeasurementSummaryHashType;
+use spdmlib::time::SpdmTime;
+use spdmlib::{requester, responder};
+
+static NOW: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+struct SessionEventRecorder {
+    events: RefCell<Vec<(u32, SpdmSessionEvent)>>,
+}
+
+impl SpdmSessionObserver for SessionEventRecorder {
+    fn notify(&self, session_id: u32, event: SpdmSessionEvent) {
+        self.events.borrow_mut().push((session_id, event));
+    }
+}
+
+#[test]
+fn intergration_heartbeat_liveness() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+    spdmlib::time::register(SpdmTime {
+        sleep_cb: |_: usize| {},
+        now_cb: || NOW.load(Ordering::SeqCst),
+    });
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let session_events = SessionEventRecorder::default();
+    let (mut config_info, provision_info) = common::utils::rsp_create_info();
+    config_info.heartbeat_period = 1;
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+    responder_context.register_session_observer(&session_events);
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+    let mut start_session = || {
+        requester_context
+            .start_session(
+                false,
+                0,
+                SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+            )
+            .unwrap()
+    };
+    let silent_session_id = start_session();
+    let session_id = start_session();
+
+    // HeartbeatPeriod of 1 second, the silent requester is gone after 2
+    NOW.store(2 * ST1 as u64 - 1, Ordering::SeqCst);
+    assert!(requester_context
+        .send_receive_spdm_heartbeat(session_id)
+        .is_ok());
+    assert!(!session_events.events.borrow().contains(&(
+        silent_session_id.as_u32(),
+        SpdmSessionEvent::HeartbeatTimeout
+    )));
+
+    NOW.store(2 * ST1 as u64, Ordering::SeqCst);
+    assert!(requester_context
+        .send_receive_spdm_heartbeat(session_id)
+        .is_ok());
+    assert!(session_events.events.borrow().ends_with(&[
+        (
+            silent_session_id.as_u32(),
+            SpdmSessionEvent::HeartbeatTimeout
+        ),
+        (silent_session_id.as_u32(), SpdmSessionEvent::Terminated),
+    ]));
+
+    // the responder freed the silent session, the other one goes on
+    assert!(requester_context
+        .send_receive_spdm_heartbeat(silent_session_id)
+        .is_err());
+    assert!(requester_context
+        .send_receive_spdm_heartbeat(session_id)
+        .is_ok());
+}
@@ -2,7 +2,7 @@ mod common;
 
 use codec::{Reader, Writer};
 use spdmlib::common::opaque::*;
-use spdmlib::common::SpdmCodec;
+use spdmlib::common::{spdm_measurement_to_digest, SpdmCodec};
 use spdmlib::config::{
     MAX_SPDM_CERT_CHAIN_DATA_SIZE, MAX_SPDM_MEASUREMENT_BLOCK_COUNT,
     MAX_SPDM_MEASUREMENT_VALUE_LEN, MAX_SPDM_OPAQUE_SIZE,
@@ -41,6 +41,88 @@ fn test_case0_spdm_opaque_struct() {
     assert_eq!(2, reader.left());
 }
 
+#[test]
+fn test_case1_spdm_opaque_struct() {
+    let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
+    let my_spdm_device_io = &mut MySpdmDeviceIo;
+    let mut context = new_context(my_spdm_device_io, pcidoe_transport_encap);
+
+    context.negotiate_info.opaque_data_support = SpdmOpaqueSupport::OPAQUE_DATA_FMT1;
+    let opaque =
+        SpdmOpaqueStruct::req_dmtf_supported_secure_spdm_version_list(&mut context).unwrap();
+    assert_eq!(
+        &opaque.data[..opaque.data_size as usize],
+        &REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT1[..]
+    );
+
+    context.negotiate_info.opaque_data_support = SpdmOpaqueSupport::OPAQUE_DATA_FMT0;
+    let opaque =
+        SpdmOpaqueStruct::req_dmtf_supported_secure_spdm_version_list(&mut context).unwrap();
+    assert_eq!(
+        &opaque.data[..opaque.data_size as usize],
+        &REQ_DMTF_OPAQUE_DATA_SUPPORT_VERSION_LIST_FMT0[..]
+    );
+
+    let secured_message_version_list = opaque
+        .rsp_get_dmtf_supported_secure_spdm_version_list(&mut context)
+        .unwrap();
+    assert_eq!(secured_message_version_list.version_count, 2);
+    let selected_version =
+        select_secure_spdm_version(&secured_message_version_list, DMTF_SECURE_SPDM_VERSION_11)
+            .unwrap();
+    assert_eq!(
+        selected_version.get_secure_spdm_version(),
+        DMTF_SECURE_SPDM_VERSION_11
+    );
+    let selected_version =
+        select_secure_spdm_version(&secured_message_version_list, DMTF_SECURE_SPDM_VERSION_10)
+            .unwrap();
+    assert_eq!(
+        selected_version.get_secure_spdm_version(),
+        DMTF_SECURE_SPDM_VERSION_10
+    );
+
+    let opaque =
+        SpdmOpaqueStruct::rsp_dmtf_secure_spdm_version_selection(&mut context, selected_version)
+            .unwrap();
+    assert_eq!(opaque.data_size, 16);
+    let secured_message_version = opaque
+        .req_get_dmtf_secure_spdm_version_selection(&mut context)
+        .unwrap();
+    assert_eq!(
+        secured_message_version.get_secure_spdm_version(),
+        DMTF_SECURE_SPDM_VERSION_10
+    );
+
+    // a vendor element ahead of the DMTF element is skipped
+    let mut general_opaque_data = SpdmGeneralOpaqueData::new();
+    let mut vendor_element = SpdmOpaqueElement {
+        data_len: 3,
+        ..Default::default()
+    };
+    vendor_element.header.id = 0x03;
+    vendor_element.header.vendor_len = 2;
+    general_opaque_data.add_element(vendor_element).unwrap();
+    general_opaque_data
+        .add_element(
+            SpdmOpaqueElement::new_dmtf_sm_element(VERSION_SELECTION_SM_DATA_ID, &[0x00, 0x11])
+                .unwrap(),
+        )
+        .unwrap();
+    let opaque =
+        SpdmOpaqueStruct::from_general_opaque_data(&mut context, &general_opaque_data).unwrap();
+    let general_opaque_data = opaque.get_general_opaque_data(&mut context).unwrap();
+    assert_eq!(general_opaque_data.get_elements().len(), 2);
+    assert!(!general_opaque_data.get_elements()[0].is_dmtf());
+    let secured_message_version = opaque
+        .req_get_dmtf_secure_spdm_version_selection(&mut context)
+        .unwrap();
+    assert_eq!(
+        secured_message_version.get_secure_spdm_version(),
+        DMTF_SECURE_SPDM_VERSION_11
+    );
+}
+
 #[test]
 fn test_case0_spdm_digest_struct() {
     let u8_slice = &mut [0u8; 68];
@@ -132,6 +214,7 @@ fn test_case0_spdm_measurement_record_structure() {
                 index: 100u8,
                 measurement_specification: SpdmMeasurementSpecification::DMTF,
                 measurement_size: 67u16,
+                tcb: false,
                 measurement: SpdmDmtfMeasurementStructure {
                     r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom,
                     representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementDigest,
@@ -266,6 +349,29 @@ fn test_case0_spdm_dmtf_measurement_structure() {
     }
 }
 #[test]
+fn test_case0_spdm_measurement_to_digest() {
+    let mut value = SpdmDmtfMeasurementStructure {
+        r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
+        representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementRawBit,
+        value_size: 8u16,
+        value: [0u8; MAX_SPDM_MEASUREMENT_VALUE_LEN],
+    };
+    value.value[..8].copy_from_slice(b"deadbeef");
+
+    let digest = spdm_measurement_to_digest(&value, SpdmBaseHashAlgo::TPM_ALG_SHA_384).unwrap();
+    assert!(!digest.is_raw_bit_stream());
+    assert_eq!(digest.value_size, 48);
+    let expected =
+        spdmlib::crypto::hash::hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_384, b"deadbeef").unwrap();
+    assert_eq!(&digest.value[..48], expected.as_ref());
+
+    let same = spdm_measurement_to_digest(&digest, SpdmBaseHashAlgo::TPM_ALG_SHA_384).unwrap();
+    assert_eq!(same.value_size, 48);
+
+    value.r#type = SpdmDmtfMeasurementType::SpdmDmtfMeasurementStructuredRepresentationMode;
+    assert!(spdm_measurement_to_digest(&value, SpdmBaseHashAlgo::TPM_ALG_SHA_384).is_none());
+}
+#[test]
 fn test_case0_spdm_measurement_block_structure() {
     let u8_slice = &mut [0u8; 80];
     let mut writer = Writer::init(u8_slice);
@@ -273,6 +379,7 @@ fn test_case0_spdm_measurement_block_structure() {
         index: 100u8,
         measurement_specification: SpdmMeasurementSpecification::DMTF,
         measurement_size: 100u16,
+        tcb: false,
         measurement: SpdmDmtfMeasurementStructure {
             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementRom,
             representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementDigest,
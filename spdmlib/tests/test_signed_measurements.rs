@@ -0,0 +1,203 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The measurement provider and the signer are registered process wide, so
+// this test has a binary of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::crypto::{SpdmAsymSign, SpdmRng};
+use spdmlib::error::{SpdmError, SpdmResult};
+use spdmlib::measurement::{
+    spdm_build_measurement_record, SpdmFirmwareRegion, SpdmFirmwareRegionKind,
+};
+use spdmlib::message::{SpdmMeasurementOperation, SpdmMeasurementeAttributes};
+use spdmlib::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmMeasurementRecordStructure,
+    SpdmMeasurementSpecification, SpdmSignatureStruct, SpdmVersion,
+};
+use spdmlib::secret::SpdmSecret;
+use spdmlib::{requester, responder};
+
+static FIRMWARE_REGIONS: [SpdmFirmwareRegion<'static>; 4] = [
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::ImmutableRom,
+        tcb: true,
+        data: &[b"rom"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::MutableFirmware,
+        tcb: true,
+        data: &[b"firmware"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::HardwareConfig,
+        tcb: false,
+        data: &[b"hardware config"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::FirmwareConfig,
+        tcb: false,
+        data: &[b"firmware config"],
+    },
+];
+
+static CORRUPT_SIGNATURE: AtomicBool = AtomicBool::new(false);
+static REPLAY_NONCE: AtomicBool = AtomicBool::new(false);
+
+// A counter for the responder nonces, which gives the last nonce again
+// while REPLAY_NONCE is set.
+#[derive(Default)]
+struct ReplayRng {
+    counter: u32,
+    last: Vec<u8>,
+}
+
+impl SpdmRng for ReplayRng {
+    fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+        if !REPLAY_NONCE.load(Ordering::SeqCst) || self.last.len() != data.len() {
+            self.counter += 1;
+            data.fill(0);
+            for (byte, counter) in data.iter_mut().zip(self.counter.to_le_bytes().iter()) {
+                *byte = *counter;
+            }
+            self.last = data.to_vec();
+        }
+        data.copy_from_slice(&self.last);
+        Ok(data.len())
+    }
+}
+
+static SECRET_IMPL: SpdmSecret = SpdmSecret {
+    spdm_measurement_collection_cb: |_spdm_version: SpdmVersion,
+                                     _measurement_specification: SpdmMeasurementSpecification,
+                                     measurement_hash_algo: SpdmBaseHashAlgo,
+                                     measurement_index: usize|
+     -> Option<SpdmMeasurementRecordStructure> {
+        spdm_build_measurement_record(&FIRMWARE_REGIONS, measurement_hash_algo, measurement_index)
+    },
+    spdm_generate_measurement_summary_hash_cb: |_, _, _, _, _| None,
+    spdm_requester_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_responder_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_psk_handshake_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+    spdm_psk_master_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+};
+
+fn corrupting_sign(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    let mut signature =
+        (common::crypto_callbacks::ASYM_SIGN_IMPL.sign_cb)(base_hash_algo, base_asym_algo, data)?;
+    if CORRUPT_SIGNATURE.load(Ordering::SeqCst) {
+        signature.data[0] ^= 0xff;
+    }
+    Some(signature)
+}
+
+fn block_indices(measurement_record: &SpdmMeasurementRecordStructure) -> Vec<u8> {
+    measurement_record.record[..measurement_record.number_of_blocks as usize]
+        .iter()
+        .map(|block| block.index)
+        .collect()
+}
+
+fn signed_measurements(spdm_version: SpdmVersion) {
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let (mut config_info, provision_info) = common::utils::rsp_create_info();
+    config_info.spdm_version = [
+        SpdmVersion::SpdmVersion11,
+        SpdmVersion::SpdmVersion12,
+        spdm_version,
+    ];
+    let mut rng = ReplayRng::default();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+    responder_context.register_rng(&mut rng);
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (mut config_info, provision_info) = common::utils::req_create_info();
+    config_info.spdm_version = [
+        SpdmVersion::SpdmVersion11,
+        SpdmVersion::SpdmVersion12,
+        spdm_version,
+    ];
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert_eq!(
+        requester_context.common.negotiate_info.spdm_version_sel,
+        spdm_version
+    );
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+
+    // blocks 2 and 4 left out, the signature covers 1 and 3 only
+    let measurement_record = requester_context
+        .get_signed_measurements(None, 0, &[1, 3])
+        .unwrap();
+    assert_eq!(block_indices(&measurement_record), [1, 3].to_vec());
+
+    let measurement_record = requester_context
+        .get_signed_measurements(None, 0, &[4, 2])
+        .unwrap();
+    assert_eq!(block_indices(&measurement_record), [4, 2].to_vec());
+
+    CORRUPT_SIGNATURE.store(true, Ordering::SeqCst);
+    assert!(requester_context
+        .get_signed_measurements(None, 0, &[1, 3])
+        .is_err());
+    CORRUPT_SIGNATURE.store(false, Ordering::SeqCst);
+
+    // a signed response repeating the nonce of the last one verified is
+    // reported as such, and leaves L1/L2 for the next signed response
+    let signed_request_all = |requester_context: &mut requester::RequesterContext| {
+        requester_context.send_receive_spdm_measurement(
+            None,
+            0,
+            SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
+            SpdmMeasurementOperation::SpdmMeasurementRequestAll,
+        )
+    };
+    assert!(signed_request_all(&mut requester_context).is_ok());
+    REPLAY_NONCE.store(true, Ordering::SeqCst);
+    assert!(matches!(
+        signed_request_all(&mut requester_context),
+        Err(SpdmError::StaleNonce(_))
+    ));
+    REPLAY_NONCE.store(false, Ordering::SeqCst);
+    assert!(signed_request_all(&mut requester_context).is_ok());
+}
+
+#[test]
+fn intergration_signed_measurements() {
+    spdmlib::crypto::asym_sign::register(SpdmAsymSign {
+        sign_cb: corrupting_sign,
+    });
+    spdmlib::secret::register(SECRET_IMPL.clone());
+
+    signed_measurements(SpdmVersion::SpdmVersion12);
+    signed_measurements(SpdmVersion::SpdmVersion13);
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+// The measurement provider and the signer are registered process wide, so
+// this test has a binary of its own.
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::crypto::SpdmAsymSign;
+use spdmlib::error::SpdmError;
+use spdmlib::measurement::{
+    spdm_build_measurement_record, SpdmFirmwareRegion, SpdmFirmwareRegionKind,
+};
+use spdmlib::message::{SpdmErrorCode, SpdmMeasurementOperation, SpdmMeasurementeAttributes};
+use spdmlib::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmMeasurementRecordStructure,
+    SpdmMeasurementSpecification, SpdmSignatureStruct, SpdmVersion,
+};
+use spdmlib::secret::SpdmSecret;
+use spdmlib::{requester, responder};
+
+static FIRMWARE_REGIONS: [SpdmFirmwareRegion<'static>; 2] = [
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::ImmutableRom,
+        tcb: true,
+        data: &[b"rom"],
+    },
+    SpdmFirmwareRegion {
+        kind: SpdmFirmwareRegionKind::MutableFirmware,
+        tcb: true,
+        data: &[b"firmware"],
+    },
+];
+
+static NO_MEASUREMENT: AtomicBool = AtomicBool::new(false);
+static SIGNATURES: AtomicUsize = AtomicUsize::new(0);
+
+// Blocks 1 and 3, there is no block 2.
+fn measurement_collection(
+    _spdm_version: SpdmVersion,
+    _measurement_specification: SpdmMeasurementSpecification,
+    measurement_hash_algo: SpdmBaseHashAlgo,
+    measurement_index: usize,
+) -> Option<SpdmMeasurementRecordStructure> {
+    let firmware_regions: &[SpdmFirmwareRegion] = if NO_MEASUREMENT.load(Ordering::SeqCst) {
+        &[]
+    } else {
+        &FIRMWARE_REGIONS
+    };
+    let mut measurement_record = match measurement_index {
+        2 => return None,
+        3 => spdm_build_measurement_record(firmware_regions, measurement_hash_algo, 2)?,
+        _ => spdm_build_measurement_record(
+            firmware_regions,
+            measurement_hash_algo,
+            measurement_index,
+        )?,
+    };
+    for block in measurement_record.record.iter_mut() {
+        if block.index == 2 {
+            block.index = 3;
+        }
+    }
+    Some(measurement_record)
+}
+
+static SECRET_IMPL: SpdmSecret = SpdmSecret {
+    spdm_measurement_collection_cb: measurement_collection,
+    spdm_generate_measurement_summary_hash_cb: |_, _, _, _, _| None,
+    spdm_requester_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_responder_data_sign_cb: |_, _, _, _, _, _, _| None,
+    spdm_psk_handshake_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+    spdm_psk_master_secret_hkdf_expand_cb: |_, _, _, _, _, _| None,
+};
+
+fn counting_sign(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    SIGNATURES.fetch_add(1, Ordering::SeqCst);
+    (common::crypto_callbacks::ASYM_SIGN_IMPL.sign_cb)(base_hash_algo, base_asym_algo, data)
+}
+
+fn block_indices(measurement_record: &SpdmMeasurementRecordStructure) -> Vec<u8> {
+    measurement_record.record[..measurement_record.number_of_blocks as usize]
+        .iter()
+        .map(|block| block.index)
+        .collect()
+}
+
+#[test]
+fn intergration_sparse_measurements() {
+    spdmlib::crypto::asym_sign::register(SpdmAsymSign {
+        sign_cb: counting_sign,
+    });
+    spdmlib::secret::register(SECRET_IMPL.clone());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+
+    let measurement_record = requester_context
+        .get_all_measurements(None, 0, SpdmMeasurementeAttributes::empty())
+        .unwrap();
+    assert_eq!(block_indices(&measurement_record), [1, 3].to_vec());
+    assert_eq!(SIGNATURES.load(Ordering::SeqCst), 0);
+
+    // the signature covers the blocks got again after the probe
+    let measurement_record = requester_context
+        .get_all_measurements(None, 0, SpdmMeasurementeAttributes::SIGNATURE_REQUESTED)
+        .unwrap();
+    assert_eq!(block_indices(&measurement_record), [1, 3].to_vec());
+    assert_eq!(SIGNATURES.load(Ordering::SeqCst), 1);
+
+    // a missing index drops L1/L2 on both sides
+    match requester_context.get_signed_measurements(None, 0, &[1, 2]) {
+        Err(SpdmError::PeerError { code, .. }) => {
+            assert_eq!(code, SpdmErrorCode::SpdmErrorInvalidRequest)
+        }
+        _ => panic!("index 2 does not exist"),
+    }
+    let measurement_record = requester_context
+        .get_signed_measurements(None, 0, &[3, 1])
+        .unwrap();
+    assert_eq!(block_indices(&measurement_record), [3, 1].to_vec());
+    assert_eq!(SIGNATURES.load(Ordering::SeqCst), 2);
+
+    // without any measurement, the total number query is signed
+    NO_MEASUREMENT.store(true, Ordering::SeqCst);
+    let measurements = requester_context
+        .send_receive_spdm_measurement(
+            None,
+            0,
+            SpdmMeasurementeAttributes::empty(),
+            SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
+        )
+        .unwrap();
+    assert_eq!(measurements.number_of_measurement, 0);
+    let measurement_record = requester_context
+        .get_all_measurements(None, 0, SpdmMeasurementeAttributes::SIGNATURE_REQUESTED)
+        .unwrap();
+    assert_eq!(measurement_record.number_of_blocks, 0);
+    assert_eq!(SIGNATURES.load(Ordering::SeqCst), 3);
+}
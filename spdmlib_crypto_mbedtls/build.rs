@@ -16,6 +16,8 @@ fn mbedtls_support() {
         .file("src/aead_impl_chacha20_poly1305.c")
         .file("src/aead_impl_gcm.c")
         .file("src/dhe_impl.c")
+        .file("src/hash_impl.c")
+        .file("src/asym_sign_impl.c")
         .file("src/asym_verify_impl.c")
         .file("src/cert_operation_impl.c");
     if !(os == "uefi" || os == "windows") {
@@ -799,7 +799,7 @@
 // #define MBEDTLS_ECP_DP_SECP224R1_ENABLED
 #define MBEDTLS_ECP_DP_SECP256R1_ENABLED
 #define MBEDTLS_ECP_DP_SECP384R1_ENABLED
-// #define MBEDTLS_ECP_DP_SECP521R1_ENABLED
+#define MBEDTLS_ECP_DP_SECP521R1_ENABLED
 // #define MBEDTLS_ECP_DP_SECP192K1_ENABLED
 // #define MBEDTLS_ECP_DP_SECP224K1_ENABLED
 // #define MBEDTLS_ECP_DP_SECP256K1_ENABLED
@@ -0,0 +1,75 @@
+/** @file
+ * EcDSA and RSA signing Wrapper Implementation.
+ *
+ **/
+
+#include <mbedtls/pk.h>
+#include <mbedtls/rsa.h>
+
+/**
+ * Signs a hash with a DER encoded private key.
+ *
+ * @param[in]       md_type         Hash algorithm used.
+ * @param[in]       use_pss         Non zero for RSA-PSS, ignored for an EC key.
+ * @param[in]       key             PKCS#8, PKCS#1 or SEC1 DER private key.
+ * @param[in]       key_size        Private key size in bytes.
+ * @param[in]       hash            Pointer to the hash to sign.
+ * @param[in]       hash_size       Size of the hash in bytes.
+ * @param[out]      signature       Pointer to the signature buffer. An EC signature
+ *                                  is DER encoded, a RSA signature is the key size.
+ * @param[in, out]  signature_size  On input, the size of the signature buffer.
+ *                                  On output, the size of the signature.
+ * @param[in]       random_fn          The RNG function.
+ * @param[in]       random_fn_param    RNG function context pass to random_fn.
+ *
+ * @retval  0       The signature is generated.
+ * @retval  not 0   Failure.
+ *
+ **/
+int spdm_pk_sign(
+    const int md_type, const int use_pss,
+    const uint8_t *key, size_t key_size,
+    const uint8_t *hash, size_t hash_size,
+    uint8_t *signature, size_t *signature_size,
+    void *random_fn, void *random_fn_param)
+{
+    mbedtls_pk_context pk;
+    int ret;
+
+    mbedtls_pk_init(&pk);
+
+    ret = mbedtls_pk_parse_key(&pk, key, key_size, NULL, 0);
+
+    if (ret == 0 && mbedtls_pk_get_len(&pk) > *signature_size)
+    {
+        ret = MBEDTLS_ERR_PK_BAD_INPUT_DATA;
+    }
+
+    if (ret == 0)
+    {
+        if (mbedtls_pk_get_type(&pk) == MBEDTLS_PK_RSA && use_pss)
+        {
+            mbedtls_rsa_context *rsa = mbedtls_pk_rsa(pk);
+            mbedtls_rsa_set_padding(rsa, MBEDTLS_RSA_PKCS_V21, (mbedtls_md_type_t)md_type);
+            ret = mbedtls_rsa_rsassa_pss_sign(
+                rsa, random_fn, random_fn_param,
+                MBEDTLS_RSA_PRIVATE,
+                (mbedtls_md_type_t)md_type, (unsigned int)hash_size, hash, signature);
+            if (ret == 0)
+            {
+                *signature_size = mbedtls_rsa_get_len(rsa);
+            }
+        }
+        else
+        {
+            ret = mbedtls_pk_sign(
+                &pk, (mbedtls_md_type_t)md_type, hash, hash_size,
+                signature, signature_size,
+                random_fn, random_fn_param);
+        }
+    }
+
+    mbedtls_pk_free(&pk);
+
+    return ret;
+}
@@ -0,0 +1,290 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use spdmlib::crypto::SpdmAsymSign;
+use spdmlib::error::{spdm_err, spdm_result_err, SpdmResult};
+use spdmlib::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmSignatureStruct, SPDM_MAX_ASYM_KEY_SIZE,
+};
+
+use super::ffi::spdm_pk_sign;
+use super::rand_impl::mbedtls_f_rng;
+use core::ffi::{c_int, c_void};
+
+struct SigningKey {
+    base_asym_algo: SpdmBaseAsymAlgo,
+    key: Vec<u8>,
+}
+
+impl Drop for SigningKey {
+    fn drop(&mut self) {
+        for b in self.key.iter_mut() {
+            unsafe { core::ptr::write_volatile(b, 0) };
+        }
+    }
+}
+
+static SIGNING_KEY: spin::Mutex<Option<SigningKey>> = spin::Mutex::new(None);
+
+pub static DEFAULT: SpdmAsymSign = SpdmAsymSign { sign_cb: asym_sign };
+
+/// Provision the device private key used by `DEFAULT`, once.
+///
+/// The key is PKCS#8, PKCS#1 RSAPrivateKey or SEC1 ECPrivateKey DER, it is
+/// parsed by mbedtls on each signature.
+pub fn set_signing_key(base_asym_algo: SpdmBaseAsymAlgo, private_key_der: &[u8]) -> SpdmResult {
+    match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521 => {}
+        _ => return spdm_result_err!(EINVAL),
+    }
+
+    let mut signing_key = SIGNING_KEY.lock();
+    if signing_key.is_some() {
+        return spdm_result_err!(EEXIST);
+    }
+    *signing_key = Some(SigningKey {
+        base_asym_algo,
+        key: private_key_der.to_vec(),
+    });
+    Ok(())
+}
+
+fn is_rsa(base_asym_algo: SpdmBaseAsymAlgo) -> bool {
+    matches!(
+        base_asym_algo,
+        SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048
+            | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+            | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096
+            | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
+            | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
+            | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096
+    )
+}
+
+fn asym_sign(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    let signing_key = SIGNING_KEY.lock();
+    sign_with_key(signing_key.as_ref()?, base_hash_algo, base_asym_algo, data)
+}
+
+fn sign_with_key(
+    signing_key: &SigningKey,
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    let md_type: c_int = match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => 6,
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => 7,
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => 8,
+        _ => return None,
+    };
+    let use_pss: c_int = match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
+        | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096 => 1,
+        _ => 0,
+    };
+
+    // one RSA key serves both RSASSA and RSAPSS of its size
+    let key_matches = if is_rsa(signing_key.base_asym_algo) {
+        is_rsa(base_asym_algo) && signing_key.base_asym_algo.get_size() == base_asym_algo.get_size()
+    } else {
+        signing_key.base_asym_algo == base_asym_algo
+    };
+    if !key_matches {
+        return None;
+    }
+
+    let hash = (super::hash_impl::DEFAULT.hash_all_cb)(base_hash_algo, data)?;
+
+    let mut sig = [0u8; 512];
+    let mut sig_size = sig.len();
+    let ret = unsafe {
+        spdm_pk_sign(
+            md_type,
+            use_pss,
+            signing_key.key.as_ptr(),
+            signing_key.key.len(),
+            hash.data.as_ptr(),
+            hash.data_size as usize,
+            sig.as_mut_ptr(),
+            &mut sig_size,
+            mbedtls_f_rng as *const c_void,
+            core::ptr::null(),
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+
+    let mut signature = SpdmSignatureStruct {
+        data_size: base_asym_algo.get_size(),
+        data: [0u8; SPDM_MAX_ASYM_KEY_SIZE],
+    };
+    if is_rsa(base_asym_algo) {
+        if sig_size != signature.data_size as usize {
+            return None;
+        }
+        signature.data[..sig_size].copy_from_slice(&sig[..sig_size]);
+    } else {
+        ecc_signature_der_to_bin(
+            &sig[..sig_size],
+            &mut signature.data[..(signature.data_size as usize)],
+        )?;
+    }
+    Some(signature)
+}
+
+// SEQUENCE { INTEGER r, INTEGER s } to r || s, each left padded to half of signature
+fn ecc_signature_der_to_bin(der_signature: &[u8], signature: &mut [u8]) -> Option<()> {
+    let half_size = signature.len() / 2;
+    if der_signature.len() < 2 || der_signature[0] != 0x30 {
+        return None;
+    }
+    let (seq_len, mut offset) = if der_signature[1] < 0x80 {
+        (der_signature[1] as usize, 2)
+    } else if der_signature[1] == 0x81 && der_signature.len() > 2 {
+        (der_signature[2] as usize, 3)
+    } else {
+        return None;
+    };
+    if offset + seq_len != der_signature.len() {
+        return None;
+    }
+
+    for part in signature.chunks_mut(half_size) {
+        if der_signature.len() < offset + 2 || der_signature[offset] != 0x02 {
+            return None;
+        }
+        let int_len = der_signature[offset + 1] as usize;
+        offset += 2;
+        if der_signature.len() < offset + int_len {
+            return None;
+        }
+        let mut int = &der_signature[offset..(offset + int_len)];
+        offset += int_len;
+        while int.len() > half_size && int[0] == 0 {
+            int = &int[1..];
+        }
+        if int.len() > half_size {
+            return None;
+        }
+        part[(half_size - int.len())..].copy_from_slice(int);
+    }
+    if offset != der_signature.len() {
+        return None;
+    }
+    Some(())
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_asym_sign() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_384;
+        let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384;
+        let private_key_der = &include_bytes!("../../test_key/EcP384/end_responder.key.p8")[..];
+        let public_cert_der =
+            &include_bytes!("../../test_key/EcP384/bundle_responder.certchain.der")[..];
+
+        assert!(set_signing_key(SpdmBaseAsymAlgo::empty(), private_key_der).is_err());
+        assert!(set_signing_key(base_asym_algo, private_key_der).is_ok());
+        assert!(set_signing_key(base_asym_algo, private_key_der).is_err());
+
+        let data = &mut [0x10u8; 256];
+        let signature = asym_sign(base_hash_algo, base_asym_algo, data).unwrap();
+        assert_eq!(signature.data_size, 96);
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_ok());
+
+        data[0] ^= 1;
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_err());
+
+        let signature = asym_sign(
+            base_hash_algo,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256,
+            data,
+        );
+        assert!(signature.is_none());
+        let signature = asym_sign(base_hash_algo, SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072, data);
+        assert!(signature.is_none());
+    }
+
+    #[test]
+    fn test_case2_sign_with_key_p521() {
+        let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_512;
+        let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521;
+        let signing_key = SigningKey {
+            base_asym_algo,
+            key: include_bytes!("../../test_key/EcP521/end_responder.key.p8").to_vec(),
+        };
+        let public_cert_der =
+            &include_bytes!("../../test_key/EcP521/bundle_responder.certchain.der")[..];
+
+        let data = &mut [0x10u8; 256];
+        let signature = sign_with_key(&signing_key, base_hash_algo, base_asym_algo, data).unwrap();
+        assert_eq!(signature.data_size, 132);
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_ok());
+
+        data[0] ^= 1;
+        assert!((super::super::asym_verify_impl::DEFAULT.verify_cb)(
+            base_hash_algo,
+            base_asym_algo,
+            public_cert_der,
+            data,
+            &signature,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_case1_ecc_signature_der_to_bin() {
+        // r with a leading zero, s shorter than half size
+        let der = [0x30, 0x08, 0x02, 0x03, 0x00, 0x81, 0x02, 0x02, 0x01, 0x03];
+        let mut bin = [0xffu8; 4];
+        assert!(ecc_signature_der_to_bin(&der, &mut bin).is_some());
+        assert_eq!(bin, [0x81, 0x02, 0x00, 0x03]);
+
+        assert!(ecc_signature_der_to_bin(&der[..9], &mut bin).is_none());
+        let mut bin = [0u8; 2];
+        assert!(ecc_signature_der_to_bin(&der, &mut bin).is_none());
+    }
+}
@@ -14,6 +14,7 @@ use core::ffi::c_int;
 
 const MBEDTLS_MD_SHA256: c_int = 6;
 const MBEDTLS_MD_SHA384: c_int = 7;
+const MBEDTLS_MD_SHA512: c_int = 8;
 use super::ffi::{spdm_pk_verify, spdm_rsa_pss_verify};
 
 fn asym_verify(
@@ -24,6 +25,7 @@ fn asym_verify(
     signature: &SpdmSignatureStruct,
 ) -> SpdmResult {
     let mbedtls_hash_algo = match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => MBEDTLS_MD_SHA512,
         SpdmBaseHashAlgo::TPM_ALG_SHA_384 => MBEDTLS_MD_SHA384,
         SpdmBaseHashAlgo::TPM_ALG_SHA_256 => MBEDTLS_MD_SHA256,
         _ => {
@@ -35,13 +37,11 @@ fn asym_verify(
 
     let signature = match base_asym_algo {
         SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
-        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => {
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521 => {
             let der_sign_size = ecc_signature_bin_to_der(signature.as_ref(), &mut der_signature);
             &der_signature[0..der_sign_size]
         }
-        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521 => {
-            panic!("unsupported asym algo")
-        }
         SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_2048
         | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_3072
         | SpdmBaseAsymAlgo::TPM_ALG_RSAPSS_4096
@@ -62,6 +62,7 @@ fn asym_verify(
     let ret = match base_asym_algo {
         SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256
         | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+        | SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P521
         | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048
         | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
         | SpdmBaseAsymAlgo::TPM_ALG_RSASSA_4096 => unsafe {
@@ -129,14 +130,23 @@ fn ecc_signature_bin_to_der(signature: &[u8], der_signature: &mut [u8]) -> usize
 
     let der_r_size = if r[0] < 0x80 { r_size } else { r_size + 1 };
     let der_s_size = if s[0] < 0x80 { s_size } else { s_size + 1 };
-    let der_sign_size = der_r_size + der_s_size + 6;
+    // P-521 needs the long form of the SEQUENCE length
+    let seq_size = der_r_size + der_s_size + 4;
+    let seq_header_size = if seq_size < 0x80 { 2 } else { 3 };
+    let der_sign_size = seq_size + seq_header_size;
 
     if der_signature.len() < der_sign_size {
         panic!("der_signature too small");
     }
 
     der_signature[0] = 0x30u8;
-    der_signature[1] = (der_sign_size - 2) as u8;
+    if seq_header_size == 2 {
+        der_signature[1] = seq_size as u8;
+    } else {
+        der_signature[1] = 0x81u8;
+        der_signature[2] = seq_size as u8;
+    }
+    let der_signature = &mut der_signature[(seq_header_size - 2)..];
     der_signature[2] = 0x02u8;
     der_signature[3] = der_r_size as u8;
     if r[0] < 0x80 {
@@ -202,6 +212,15 @@ mod tests {
         ecc_signature_bin_to_der(signature, der_signature);
     }
     #[test]
+    fn test_case4_ecc_signature_bin_to_der() {
+        let signature = &mut [0xffu8; 132];
+        let der_signature = &mut [0u8; 66 * 2 + 8 + 1];
+        let der_sign_size = ecc_signature_bin_to_der(signature, der_signature);
+        assert_eq!(der_sign_size, 141);
+        assert_eq!(der_signature[..5], [0x30, 0x81, 0x8a, 0x02, 0x43]);
+        assert_eq!(der_signature[72..75], [0x02, 0x43, 0x00]);
+    }
+    #[test]
     fn test_case0_asym_verify() {
         let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
         let base_asym_algo = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256;
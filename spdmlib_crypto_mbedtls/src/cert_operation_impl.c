@@ -88,6 +88,36 @@ int spdm_verify_cert_chain(char *certchain, size_t certchain_size)
     return ret;
 }
 
+/**
+ * Check a certificate allows an extended key usage.
+ *
+ * @param[in]  cert        Pointer to the DER-encoded certificate data.
+ * @param[in]  cert_size   The size of certificate data in bytes.
+ * @param[in]  oid         KeyPurposeId OID, DER content without tag and length.
+ * @param[in]  oid_size    The size of the OID in bytes.
+ *
+ * @retval  0       The certificate has the usage, or no extended key usage extension.
+ * @retval  not 0   The usage is not allowed or the certificate is invalid.
+ **/
+int spdm_x509_check_extended_key_usage(const unsigned char *cert, size_t cert_size,
+                                       const unsigned char *oid, size_t oid_size)
+{
+    mbedtls_x509_crt crt;
+    int ret;
+
+    mbedtls_x509_crt_init(&crt);
+
+    ret = mbedtls_x509_crt_parse_der(&crt, cert, cert_size);
+    if (ret == 0)
+    {
+        ret = mbedtls_x509_crt_check_extended_key_usage(&crt, (const char *)oid, oid_size);
+    }
+
+    mbedtls_x509_crt_free(&crt);
+
+    return ret;
+}
+
 /**
  * Certificate Check for SPDM leaf cert.
  *
@@ -4,7 +4,8 @@
 
 extern crate alloc;
 
-use spdmlib::crypto::SpdmCertOperation;
+use spdmlib::common::x509::spdm_get_cert_validity;
+use spdmlib::crypto::{SpdmCertOperation, SpdmCertValidityTime};
 use spdmlib::error::{spdm_err, spdm_result_err, SpdmResult};
 
 pub static DEFAULT: SpdmCertOperation = SpdmCertOperation {
@@ -12,7 +13,7 @@ pub static DEFAULT: SpdmCertOperation = SpdmCertOperation {
     verify_cert_chain_cb: verify_cert_chain,
 };
 
-use crate::ffi::spdm_verify_cert_chain;
+use crate::ffi::{spdm_verify_cert_chain, spdm_x509_check_extended_key_usage};
 
 fn get_cert_from_cert_chain(cert_chain: &[u8], index: isize) -> SpdmResult<(usize, usize)> {
     let mut offset = 0usize;
@@ -40,13 +41,54 @@ fn get_cert_from_cert_chain(cert_chain: &[u8], index: isize) -> SpdmResult<(usiz
     }
 }
 
-fn verify_cert_chain(cert_chain: &[u8]) -> SpdmResult {
+// mbedtls is built without MBEDTLS_HAVE_TIME_DATE, so it checks no
+// validity period and PlatformClock behaves as Skip.
+fn check_validity_time(cert_chain: &[u8], validity_time: SpdmCertValidityTime) -> SpdmResult {
+    let time = match validity_time {
+        SpdmCertValidityTime::At(time) => time,
+        SpdmCertValidityTime::PlatformClock | SpdmCertValidityTime::Skip => return Ok(()),
+    };
+    let mut index = 0isize;
+    loop {
+        let (cert_begin, cert_end) = get_cert_from_cert_chain(cert_chain, index)?;
+        let (not_before, not_after) = spdm_get_cert_validity(&cert_chain[cert_begin..cert_end])?;
+        if time < not_before || time > not_after {
+            return spdm_result_err!(ESEC);
+        }
+        if cert_end == cert_chain.len() {
+            return Ok(());
+        }
+        index += 1;
+    }
+}
+
+fn verify_cert_chain(
+    cert_chain: &[u8],
+    required_ekus: Option<&[&'static [u8]]>,
+    validity_time: SpdmCertValidityTime,
+) -> SpdmResult {
     let ret = unsafe { spdm_verify_cert_chain(cert_chain.as_ptr(), cert_chain.len()) };
-    if ret == 0 {
-        Ok(())
-    } else {
-        spdm_result_err!(EINVAL)
+    if ret != 0 {
+        return spdm_result_err!(EINVAL);
+    }
+    check_validity_time(cert_chain, validity_time)?;
+
+    if let Some(required_ekus) = required_ekus {
+        let (leaf_begin, leaf_end) = get_cert_from_cert_chain(cert_chain, -1)?;
+        let leaf_cert = &cert_chain[leaf_begin..leaf_end];
+        let allowed = required_ekus.iter().any(|eku| unsafe {
+            spdm_x509_check_extended_key_usage(
+                leaf_cert.as_ptr(),
+                leaf_cert.len(),
+                eku.as_ptr(),
+                eku.len(),
+            ) == 0
+        });
+        if !allowed {
+            return spdm_result_err!(ESEC);
+        }
     }
+    Ok(())
 }
 
 #[cfg(all(test,))]
@@ -92,7 +134,43 @@ mod tests {
         let status = get_cert_from_cert_chain(cert_chain, -1).is_ok();
         assert!(status);
 
-        let status = verify_cert_chain(cert_chain).is_ok();
+        let status =
+            verify_cert_chain(cert_chain, None, SpdmCertValidityTime::PlatformClock).is_ok();
         assert!(status);
     }
+    #[test]
+    fn test_case6_verify_cert_chain_eku() {
+        use spdmlib::common::cert_chain::{EKU_SERVER_AUTH, EKU_SPDM_RESPONDER_AUTH};
+        let cert_chain =
+            &include_bytes!("../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        let validity_time = SpdmCertValidityTime::PlatformClock;
+        assert!(verify_cert_chain(cert_chain, None, validity_time).is_ok());
+        assert!(
+            verify_cert_chain(cert_chain, Some(&[EKU_SPDM_RESPONDER_AUTH]), validity_time).is_err()
+        );
+        assert!(verify_cert_chain(
+            cert_chain,
+            Some(&[EKU_SPDM_RESPONDER_AUTH, EKU_SERVER_AUTH]),
+            validity_time
+        )
+        .is_ok());
+    }
+    #[test]
+    fn test_case7_verify_cert_chain_validity_time() {
+        let cert_chain =
+            &include_bytes!("../../test_key/EcP384/bundle_responder.certchain.der")[..];
+        // leaf and intermediate valid from 2022-01-05 06:08:34Z to 2032-01-03 06:08:34Z
+        for (time, valid) in [
+            (1641362914u64, true),
+            (1956722914, true),
+            (1641362913, false),
+            (1956722915, false),
+        ] {
+            assert_eq!(
+                verify_cert_chain(cert_chain, None, SpdmCertValidityTime::At(time)).is_ok(),
+                valid
+            );
+        }
+        assert!(verify_cert_chain(cert_chain, None, SpdmCertValidityTime::Skip).is_ok());
+    }
 }
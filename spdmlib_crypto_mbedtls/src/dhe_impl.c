@@ -2,6 +2,7 @@
  * ECDH Wrapper Implementation.
  **/
 
+#include <mbedtls/bignum.h>
 #include <mbedtls/ecdh.h>
 
 /**
@@ -136,7 +137,8 @@ int spdm_ecdh_compute_shared(
     if (ret == 0)
     {
 
-        size_t bufferlen = mbedtls_mpi_size(&shared_key);
+        /* the shared secret is the X coordinate, left padded to the field size */
+        size_t bufferlen = (grp.pbits + 7) / 8;
         if (*out_len < bufferlen)
         {
             return MBEDTLS_ERR_MPI_BUFFER_TOO_SMALL;
@@ -180,6 +182,17 @@ int spdm_ecdh_gen_public_p384(
         random_fn, random_fn_param);
 }
 
+int spdm_ecdh_gen_public_p521(
+    unsigned char *pubkey, size_t *publen,
+    unsigned char *prikey, size_t *prilen,
+    void *random_fn, void *random_fn_param)
+{
+    return spdm_ecdh_gen_public(
+        MBEDTLS_ECP_DP_SECP521R1,
+        pubkey, publen, prikey, prilen,
+        random_fn, random_fn_param);
+}
+
 int spdm_ecdh_compute_shared_p256(
     unsigned char *prikey,
     size_t prilen,
@@ -214,6 +227,91 @@ int spdm_ecdh_compute_shared_p384(
         random_fn, random_fn_param);
 }
 
+int spdm_ecdh_compute_shared_p521(
+    unsigned char *prikey,
+    size_t prilen,
+    unsigned char *peer_pubkey,
+    size_t peer_pubkey_len,
+    unsigned char *out_buffer,
+    size_t *out_len,
+    void *random_fn, void *random_fn_param)
+{
+    return spdm_ecdh_compute_shared(
+        MBEDTLS_ECP_DP_SECP521R1,
+        prikey, prilen,
+        peer_pubkey, peer_pubkey_len,
+        out_buffer, out_len,
+        random_fn, random_fn_param);
+}
+
+/**
+ * Computes base^exponent mod prime, for finite field Diffie-Hellman.
+ *
+ * All values are big-endian. The exponentiation is mbedtls_mpi_exp_mod, whose
+ * table lookups do not depend on the exponent.
+ *
+ * @param[in]       prime              The odd modulus.
+ * @param[in]       prime_len          Size of prime in bytes.
+ * @param[in]       base               The base, less than prime.
+ * @param[in]       base_len           Size of base in bytes.
+ * @param[in]       exponent           The exponent.
+ * @param[in]       exponent_len       Size of exponent in bytes.
+ * @param[out]      out_buffer         Pointer to the buffer to receive the result,
+ *                                     left padded to out_len.
+ * @param[in]       out_len            Size of out_buffer in bytes.
+ *
+ * @retval 0          The exponentiation succeeded.
+ * @retval not 0      The exponentiation failed.
+ *
+ **/
+int spdm_ffdhe_exp_mod(
+    const unsigned char *prime, size_t prime_len,
+    const unsigned char *base, size_t base_len,
+    const unsigned char *exponent, size_t exponent_len,
+    unsigned char *out_buffer, size_t out_len)
+{
+    mbedtls_mpi p;
+    mbedtls_mpi g;
+    mbedtls_mpi x;
+    mbedtls_mpi y;
+
+    mbedtls_mpi_init(&p);
+    mbedtls_mpi_init(&g);
+    mbedtls_mpi_init(&x);
+    mbedtls_mpi_init(&y);
+
+    int ret = 0;
+    ret = mbedtls_mpi_read_binary(&p, prime, prime_len);
+
+    if (ret == 0)
+    {
+        ret = mbedtls_mpi_read_binary(&g, base, base_len);
+    }
+
+    if (ret == 0)
+    {
+        ret = mbedtls_mpi_read_binary(&x, exponent, exponent_len);
+    }
+
+    if (ret == 0)
+    {
+        ret = mbedtls_mpi_exp_mod(&y, &g, &x, &p, NULL);
+    }
+
+    if (ret == 0)
+    {
+        ret = mbedtls_mpi_write_binary(&y, out_buffer, out_len);
+    }
+
+    /* mbedtls_mpi_free zeroizes the limbs */
+    mbedtls_mpi_free(&p);
+    mbedtls_mpi_free(&g);
+    mbedtls_mpi_free(&x);
+    mbedtls_mpi_free(&y);
+
+    return ret;
+}
+
 #if SELF_DEBUG
 #include <stdio.h>
 #include <stdlib.h>
@@ -5,7 +5,8 @@
 extern crate alloc;
 use alloc::boxed::Box;
 
-use spdmlib::crypto::{SpdmDhe, SpdmDheKeyExchange};
+use spdmlib::crypto::ffdhe;
+use spdmlib::crypto::{SpdmDhe, SpdmDheKeyExchange, SpdmRng};
 use spdmlib::protocol::{
     SpdmDheAlgo, SpdmDheExchangeStruct, SpdmDheFinalKeyStruct, SPDM_MAX_DHE_KEY_SIZE,
 };
@@ -14,54 +15,28 @@ pub static DEFAULT: SpdmDhe = SpdmDhe {
     generate_key_pair_cb: generate_key_pair,
 };
 
-use core::ffi::{c_int, c_uchar, c_void};
+use core::ffi::c_void;
 
 use super::ffi::{
-    spdm_ecdh_compute_shared_p256, spdm_ecdh_compute_shared_p384, spdm_ecdh_gen_public_p256,
-    spdm_ecdh_gen_public_p384,
+    spdm_ecdh_compute_shared_p256, spdm_ecdh_compute_shared_p384, spdm_ecdh_compute_shared_p521,
+    spdm_ecdh_gen_public_p256, spdm_ecdh_gen_public_p384, spdm_ecdh_gen_public_p521,
+    spdm_ffdhe_exp_mod,
 };
+use super::rand_impl::{mbedtls_f_rng, mbedtls_f_rng_spdm_rng};
 
 fn generate_key_pair(
     dhe_algo: SpdmDheAlgo,
+    rng: &mut dyn SpdmRng,
 ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
     match dhe_algo {
-        SpdmDheAlgo::SECP_256_R1 => SpdmDheKeyExchangeP256::generate_key_pair(),
-        SpdmDheAlgo::SECP_384_R1 => SpdmDheKeyExchangeP384::generate_key_pair(),
-        SpdmDheAlgo::SECP_521_R1 => None,
-        SpdmDheAlgo::FFDHE_2048 => None,
-        SpdmDheAlgo::FFDHE_3072 => None,
-        SpdmDheAlgo::FFDHE_4096 => None,
-        _ => None,
-    }
-}
-
-extern "C" fn f_rng(_rng_state: *mut c_void, output: *mut c_uchar, len: usize) -> c_int {
-    use core::arch::x86_64::_rdrand64_step;
-    let mut remain = len;
-    while remain > 8 {
-        remain -= 8;
-        let mut count = 0;
-        unsafe {
-            while _rdrand64_step(&mut *(output.add(remain) as *mut u64)) != 1 || count > 5 {
-                count += 1;
-            }
-            if count > 5 {
-                return 1;
-            }
-        }
-    }
-    let mut buf = [0u8; 8];
-    unsafe {
-        let mut count = 0;
-        while _rdrand64_step(&mut *(buf.as_mut_ptr() as *mut u64)) != 1 || count > 5 {
-            count += 1;
-        }
-        if count > 5 {
-            return 1;
+        SpdmDheAlgo::SECP_256_R1 => SpdmDheKeyExchangeP256::generate_key_pair(rng),
+        SpdmDheAlgo::SECP_384_R1 => SpdmDheKeyExchangeP384::generate_key_pair(rng),
+        SpdmDheAlgo::SECP_521_R1 => SpdmDheKeyExchangeP521::generate_key_pair(rng),
+        SpdmDheAlgo::FFDHE_2048 | SpdmDheAlgo::FFDHE_3072 | SpdmDheAlgo::FFDHE_4096 => {
+            SpdmDheKeyExchangeFfdhe::generate_key_pair(dhe_algo, rng)
         }
-        core::slice::from_raw_parts_mut(output, remain).copy_from_slice(&buf[0..remain]);
+        _ => None,
     }
-    0
 }
 
 const MAX_PRIVATE_KEY_LEN: usize = 512;
@@ -73,7 +48,9 @@ struct EphemeralPrivateKey {
 struct SpdmDheKeyExchangeP256(EphemeralPrivateKey);
 
 impl SpdmDheKeyExchangeP256 {
-    fn generate_key_pair() -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+    fn generate_key_pair(
+        mut rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
         let mut private_key = EphemeralPrivateKey {
             key_len: MAX_PRIVATE_KEY_LEN,
             key: [0u8; MAX_PRIVATE_KEY_LEN],
@@ -88,8 +65,8 @@ impl SpdmDheKeyExchangeP256 {
                 &mut data_size,
                 private_key.key.as_mut_ptr(),
                 &mut private_key.key_len,
-                f_rng as *const c_void,
-                core::ptr::null(),
+                mbedtls_f_rng_spdm_rng as *const c_void,
+                &mut rng as *mut &mut dyn SpdmRng as *const c_void,
             );
             if ret == 0 {
                 public_key.data_size = data_size as u16;
@@ -120,7 +97,7 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP256 {
                 peer_pub_key.data_size as usize,
                 final_key.data.as_mut_ptr(),
                 &mut final_key_size,
-                f_rng as *const c_void,
+                mbedtls_f_rng as *const c_void,
                 core::ptr::null(),
             );
             if res == 0 {
@@ -136,7 +113,9 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP256 {
 struct SpdmDheKeyExchangeP384(EphemeralPrivateKey);
 
 impl SpdmDheKeyExchangeP384 {
-    fn generate_key_pair() -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+    fn generate_key_pair(
+        mut rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
         let mut private_key = EphemeralPrivateKey {
             key_len: MAX_PRIVATE_KEY_LEN,
             key: [0u8; MAX_PRIVATE_KEY_LEN],
@@ -151,8 +130,8 @@ impl SpdmDheKeyExchangeP384 {
                 &mut data_size,
                 private_key.key.as_mut_ptr(),
                 &mut private_key.key_len,
-                f_rng as *const c_void,
-                core::ptr::null(),
+                mbedtls_f_rng_spdm_rng as *const c_void,
+                &mut rng as *mut &mut dyn SpdmRng as *const c_void,
             );
             if ret == 0 {
                 // convert mbedtls public_key to spdm public key format
@@ -201,7 +180,7 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP384 {
                 peer_pub_key.data_size as usize,
                 final_key.data.as_mut_ptr(),
                 &mut final_key_size,
-                f_rng as *const c_void,
+                mbedtls_f_rng as *const c_void,
                 core::ptr::null(),
             );
             if res == 0 {
@@ -214,34 +193,295 @@ impl SpdmDheKeyExchange for SpdmDheKeyExchangeP384 {
     }
 }
 
+struct SpdmDheKeyExchangeP521(EphemeralPrivateKey);
+
+impl SpdmDheKeyExchangeP521 {
+    fn generate_key_pair(
+        mut rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let mut private_key = EphemeralPrivateKey {
+            key_len: MAX_PRIVATE_KEY_LEN,
+            key: [0u8; MAX_PRIVATE_KEY_LEN],
+        };
+        let mut public_key = SpdmDheExchangeStruct::default();
+        unsafe {
+            private_key.key_len = MAX_PRIVATE_KEY_LEN;
+            public_key.data_size = 512;
+            let mut data_size = 512usize;
+            let ret = spdm_ecdh_gen_public_p521(
+                public_key.data.as_mut_ptr(),
+                &mut data_size,
+                private_key.key.as_mut_ptr(),
+                &mut private_key.key_len,
+                mbedtls_f_rng_spdm_rng as *const c_void,
+                &mut rng as *mut &mut dyn SpdmRng as *const c_void,
+            );
+            if ret == 0 {
+                public_key.data_size = data_size as u16;
+                let public_key = mbedtls_public_key_to_spdm_public_key(public_key);
+                let res: Box<dyn SpdmDheKeyExchange> = Box::new(Self(private_key));
+                Some((public_key, res))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeP521 {
+    fn compute_final_key(
+        self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        let peer_pub_key = peer_pub_key.clone();
+        let peer_pub_key = spdm_public_key_to_mbedtls_public_key(peer_pub_key);
+        let mut final_key = SpdmDheFinalKeyStruct::default();
+        unsafe {
+            let mut final_key_size = SPDM_MAX_DHE_KEY_SIZE;
+            let res = spdm_ecdh_compute_shared_p521(
+                self.0.key.as_ptr(),
+                self.0.key_len,
+                peer_pub_key.data.as_ptr(),
+                peer_pub_key.data_size as usize,
+                final_key.data.as_mut_ptr(),
+                &mut final_key_size,
+                mbedtls_f_rng as *const c_void,
+                core::ptr::null(),
+            );
+            if res == 0 {
+                final_key.data_size = final_key_size as u16;
+                Some(final_key)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+struct SpdmDheKeyExchangeFfdhe {
+    prime: &'static [u8],
+    private_key: [u8; ffdhe::FFDHE_PRIVATE_KEY_SIZE],
+}
+
+impl Drop for SpdmDheKeyExchangeFfdhe {
+    fn drop(&mut self) {
+        self.private_key.iter_mut().for_each(|byte| *byte = 0);
+    }
+}
+
+impl SpdmDheKeyExchangeFfdhe {
+    // base^private_key mod prime, padded to the size of prime
+    fn exp_mod(&self, base: &[u8], out: &mut [u8]) -> Option<()> {
+        let ret = unsafe {
+            spdm_ffdhe_exp_mod(
+                self.prime.as_ptr(),
+                self.prime.len(),
+                base.as_ptr(),
+                base.len(),
+                self.private_key.as_ptr(),
+                self.private_key.len(),
+                out.as_mut_ptr(),
+                self.prime.len(),
+            )
+        };
+        if ret == 0 {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn generate_key_pair(
+        dhe_algo: SpdmDheAlgo,
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let prime = ffdhe::get_prime(dhe_algo)?;
+        let mut private_key = Self {
+            prime,
+            private_key: [0u8; ffdhe::FFDHE_PRIVATE_KEY_SIZE],
+        };
+        ffdhe::generate_private_key(rng, &mut private_key.private_key)?;
+
+        let mut public_key = SpdmDheExchangeStruct {
+            data_size: prime.len() as u16,
+            ..Default::default()
+        };
+        private_key.exp_mod(&ffdhe::FFDHE_GENERATOR, &mut public_key.data[..prime.len()])?;
+        let res: Box<dyn SpdmDheKeyExchange> = Box::new(private_key);
+        Some((public_key, res))
+    }
+}
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeFfdhe {
+    fn compute_final_key(
+        self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        if !ffdhe::is_valid_public_key(peer_pub_key.as_ref(), self.prime) {
+            return None;
+        }
+        let mut final_key = SpdmDheFinalKeyStruct::default();
+        self.exp_mod(
+            peer_pub_key.as_ref(),
+            &mut final_key.data[..self.prime.len()],
+        )?;
+        final_key.data_size = self.prime.len() as u16;
+        Some(final_key)
+    }
+}
+
 #[cfg(all(test,))]
 mod tests {
     use super::*;
+    use spdmlib::error::SpdmResult;
+    use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
+
+    struct TestRng;
+
+    impl SpdmRng for TestRng {
+        fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+            (crate::rand_impl::DEFAULT.get_random_cb)(data)
+        }
+    }
 
     #[test]
     fn test_case0_dhe() {
-        for dhe_algo in [SpdmDheAlgo::SECP_256_R1, SpdmDheAlgo::SECP_384_R1].iter() {
-            let (exchange1, private1) = generate_key_pair(*dhe_algo).unwrap();
-            let (exchange2, private2) = generate_key_pair(*dhe_algo).unwrap();
+        for dhe_algo in [
+            SpdmDheAlgo::SECP_256_R1,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmDheAlgo::SECP_521_R1,
+        ]
+        .iter()
+        {
+            let (exchange1, private1) = generate_key_pair(*dhe_algo, &mut TestRng).unwrap();
+            let (exchange2, private2) = generate_key_pair(*dhe_algo, &mut TestRng).unwrap();
+            assert_eq!(exchange1.data_size, dhe_algo.get_size());
 
             let peer1 = private1.compute_final_key(&exchange2).unwrap();
             let peer2 = private2.compute_final_key(&exchange1).unwrap();
 
             assert_eq!(peer1.as_ref(), peer2.as_ref());
+            assert_eq!(peer1.data_size, dhe_algo.get_size() / 2);
         }
     }
     #[test]
     fn test_case1_dhe() {
-        for dhe_algo in [
-            SpdmDheAlgo::SECP_521_R1,
+        assert_eq!(
+            generate_key_pair(SpdmDheAlgo::empty(), &mut TestRng).is_none(),
+            true
+        );
+
+        // RFC 7919 section 5.1, the peer value must be in (1, p - 1)
+        let prime = ffdhe::get_prime(SpdmDheAlgo::FFDHE_2048).unwrap();
+        let mut exchange = SpdmDheExchangeStruct {
+            data_size: prime.len() as u16,
+            ..Default::default()
+        };
+        exchange.data[prime.len() - 1] = 1;
+        let (_, private) = generate_key_pair(SpdmDheAlgo::FFDHE_2048, &mut TestRng).unwrap();
+        assert!(private.compute_final_key(&exchange).is_none());
+
+        exchange.data[..prime.len()].copy_from_slice(prime);
+        exchange.data[prime.len() - 1] -= 1;
+        let (_, private) = generate_key_pair(SpdmDheAlgo::FFDHE_2048, &mut TestRng).unwrap();
+        assert!(private.compute_final_key(&exchange).is_none());
+    }
+
+    // counts up from its seed, for the same private keys on every run
+    struct KatRng(u8);
+
+    impl SpdmRng for KatRng {
+        fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+            for byte in data.iter_mut() {
+                self.0 = self.0.wrapping_add(1);
+                *byte = self.0;
+            }
+            Ok(data.len())
+        }
+    }
+
+    // SHA-256 of g^a, g^b and g^(ab) mod p, a drawn from KatRng(0) and b from
+    // KatRng(0x40), computed independently with Python's pow()
+    const FFDHE_KAT: [(SpdmDheAlgo, [[u8; 32]; 3]); 3] = [
+        (
             SpdmDheAlgo::FFDHE_2048,
+            [
+                [
+                    0x56, 0x06, 0x11, 0x39, 0x2f, 0x71, 0xcf, 0x1f, 0x81, 0xe6, 0x75, 0x6a, 0x86,
+                    0xf0, 0x16, 0x98, 0x5f, 0x59, 0xfc, 0xa7, 0x81, 0x1c, 0xa3, 0xf7, 0x16, 0xe9,
+                    0xda, 0xac, 0xb2, 0xb5, 0x2f, 0x6b,
+                ],
+                [
+                    0x4e, 0x71, 0xff, 0xbf, 0xed, 0x6d, 0x59, 0x05, 0x25, 0xef, 0x44, 0x80, 0x31,
+                    0x2d, 0xbe, 0xf5, 0x24, 0xc2, 0x96, 0x28, 0x74, 0xf6, 0xd7, 0xa4, 0x50, 0xcb,
+                    0x51, 0x82, 0x42, 0x91, 0x66, 0x7f,
+                ],
+                [
+                    0x62, 0x74, 0x32, 0xa0, 0x0e, 0x60, 0xd9, 0x01, 0x15, 0x4f, 0xe9, 0xda, 0x6c,
+                    0xd5, 0xc1, 0x65, 0x16, 0xc1, 0xb7, 0xe1, 0xcb, 0x70, 0xe4, 0x3c, 0xf1, 0x8f,
+                    0xef, 0xaf, 0x53, 0x07, 0xba, 0xf2,
+                ],
+            ],
+        ),
+        (
             SpdmDheAlgo::FFDHE_3072,
+            [
+                [
+                    0x91, 0x4b, 0x8c, 0x2e, 0xda, 0x6e, 0x19, 0xab, 0xc7, 0xd2, 0x62, 0xc9, 0x00,
+                    0x01, 0xbd, 0x50, 0x1c, 0x29, 0xb2, 0x24, 0xe3, 0x17, 0xac, 0x97, 0x82, 0xf3,
+                    0xaf, 0xd7, 0x40, 0x26, 0x73, 0x91,
+                ],
+                [
+                    0x5c, 0x3b, 0x47, 0xbd, 0xe4, 0x9c, 0xd9, 0xca, 0xa3, 0xb6, 0x13, 0xcc, 0xad,
+                    0xbf, 0x25, 0xed, 0x9f, 0xf7, 0xc0, 0xa4, 0x24, 0xfa, 0x34, 0xea, 0xda, 0x91,
+                    0x95, 0xf1, 0x93, 0x4b, 0x9d, 0x74,
+                ],
+                [
+                    0x6e, 0xf4, 0xda, 0x23, 0xf7, 0x40, 0x19, 0xee, 0x26, 0xb7, 0x15, 0xee, 0x2e,
+                    0x1e, 0xe3, 0x16, 0xe2, 0xa9, 0xb4, 0x9d, 0x40, 0xc4, 0x3f, 0xe8, 0xf3, 0xe5,
+                    0xa2, 0x98, 0xd6, 0x41, 0x3d, 0xda,
+                ],
+            ],
+        ),
+        (
             SpdmDheAlgo::FFDHE_4096,
-            SpdmDheAlgo::empty(),
-        ]
-        .iter()
-        {
-            assert_eq!(generate_key_pair(*dhe_algo).is_none(), true);
+            [
+                [
+                    0x77, 0x27, 0x7d, 0x4c, 0x49, 0x1b, 0xcf, 0xb7, 0x54, 0x1d, 0x8b, 0x26, 0x4d,
+                    0x70, 0xd8, 0x76, 0x12, 0xcb, 0xf4, 0x2b, 0xec, 0x19, 0xfc, 0xde, 0x49, 0xcb,
+                    0xac, 0xdb, 0x6e, 0xd4, 0xe8, 0xa6,
+                ],
+                [
+                    0x25, 0x1d, 0x4f, 0xd6, 0x47, 0x32, 0x0e, 0x7c, 0x88, 0x38, 0x2d, 0x2a, 0x32,
+                    0x84, 0xab, 0x2a, 0x8f, 0xb7, 0x95, 0x2e, 0x06, 0xac, 0xa9, 0xf2, 0xab, 0xff,
+                    0x45, 0x53, 0x45, 0x26, 0xbd, 0xf4,
+                ],
+                [
+                    0x6b, 0x4a, 0xe7, 0x4a, 0x0b, 0xea, 0x4d, 0x3a, 0xf0, 0xac, 0x63, 0xbf, 0xcd,
+                    0x5d, 0x79, 0xcb, 0xb4, 0xff, 0x55, 0x76, 0x35, 0x5e, 0x98, 0xa6, 0x9e, 0xe4,
+                    0xfd, 0x0f, 0x6d, 0x7d, 0xaf, 0xb0,
+                ],
+            ],
+        ),
+    ];
+
+    fn sha256(data: &[u8]) -> SpdmDigestStruct {
+        (crate::hash_impl::DEFAULT.hash_all_cb)(SpdmBaseHashAlgo::TPM_ALG_SHA_256, data).unwrap()
+    }
+
+    #[test]
+    fn test_case2_dhe() {
+        for (dhe_algo, expected) in FFDHE_KAT.iter() {
+            let (exchange1, private1) = generate_key_pair(*dhe_algo, &mut KatRng(0)).unwrap();
+            let (exchange2, private2) = generate_key_pair(*dhe_algo, &mut KatRng(0x40)).unwrap();
+            assert_eq!(exchange1.data_size, dhe_algo.get_size());
+            assert_eq!(sha256(exchange1.as_ref()).as_ref(), expected[0]);
+            assert_eq!(sha256(exchange2.as_ref()).as_ref(), expected[1]);
+
+            let peer1 = private1.compute_final_key(&exchange2).unwrap();
+            let peer2 = private2.compute_final_key(&exchange1).unwrap();
+            assert_eq!(sha256(peer1.as_ref()).as_ref(), expected[2]);
+            assert_eq!(peer1.as_ref(), peer2.as_ref());
         }
     }
 }
@@ -86,6 +86,12 @@ extern "C" {
     ) -> c_int;
 
     pub fn spdm_verify_cert_chain(certchain: *const c_uchar, certchain_size: usize) -> c_int;
+    pub fn spdm_x509_check_extended_key_usage(
+        cert: *const c_uchar,
+        cert_size: usize,
+        oid: *const c_uchar,
+        oid_size: usize,
+    ) -> c_int;
 
     pub fn spdm_ecdh_compute_shared_p256(
         private_key: *const c_uchar,
@@ -127,6 +133,37 @@ extern "C" {
         random_fn_param: *const c_void,
     ) -> c_int;
 
+    pub fn spdm_ecdh_compute_shared_p521(
+        private_key: *const c_uchar,
+        private_key_len: usize,
+        peer_public_key: *const c_uchar,
+        peer_public_key_len: usize,
+        shared_key: *mut c_uchar,
+        shared_ken_len: *mut usize,
+        random_fn: *const c_void,
+        random_fn_param: *const c_void,
+    ) -> c_int;
+
+    pub fn spdm_ecdh_gen_public_p521(
+        public_key: *mut c_uchar,
+        public_key_len: *mut usize,
+        private_key: *mut c_uchar,
+        private_key_len: *mut usize,
+        random_fn: *const c_void,
+        random_fn_param: *const c_void,
+    ) -> c_int;
+
+    pub fn spdm_ffdhe_exp_mod(
+        prime: *const c_uchar,
+        prime_len: usize,
+        base: *const c_uchar,
+        base_len: usize,
+        exponent: *const c_uchar,
+        exponent_len: usize,
+        out_buffer: *mut c_uchar,
+        out_len: usize,
+    ) -> c_int;
+
     pub fn mbedtls_sha256(
         data: *const c_uchar,
         data_len: usize,
@@ -162,4 +199,24 @@ extern "C" {
     ) -> c_int;
 
     pub fn mbedtls_md_get_size(md_info: *const c_void) -> c_uchar;
+
+    pub fn spdm_hash_ctx_size() -> usize;
+    pub fn spdm_hash_ctx_init(ctx: *mut c_void, md_type: c_int) -> c_int;
+    pub fn spdm_hash_ctx_update(ctx: *mut c_void, data: *const c_uchar, data_size: usize) -> c_int;
+    pub fn spdm_hash_ctx_finalize(ctx: *mut c_void, digest: *mut c_uchar) -> c_int;
+    pub fn spdm_hash_ctx_clone(dst: *mut c_void, src: *const c_void);
+    pub fn spdm_hash_ctx_free(ctx: *mut c_void);
+
+    pub fn spdm_pk_sign(
+        md_type: c_int,
+        use_pss: c_int,
+        key: *const c_uchar,
+        key_size: usize,
+        hash: *const c_uchar,
+        hash_size: usize,
+        signature: *mut c_uchar,
+        signature_size: *mut usize,
+        random_fn: *const c_void,
+        random_fn_param: *const c_void,
+    ) -> c_int;
 }
@@ -0,0 +1,139 @@
+/** @file
+ * SHA-256/384/512 running hash Wrapper Implementation.
+ *
+ * The context is allocated by the caller, with spdm_hash_ctx_size() bytes.
+ **/
+
+#include <mbedtls/md.h>
+#include <mbedtls/sha256.h>
+#include <mbedtls/sha512.h>
+
+typedef struct
+{
+    int md_type;
+    union
+    {
+        mbedtls_sha256_context sha256;
+        mbedtls_sha512_context sha512;
+    } u;
+} spdm_hash_ctx;
+
+/**
+ * Size in bytes of the running hash context.
+ **/
+size_t spdm_hash_ctx_size(void)
+{
+    return sizeof(spdm_hash_ctx);
+}
+
+/**
+ * Starts a running hash.
+ *
+ * @param[out]  ctx        Pointer to the context buffer.
+ * @param[in]   md_type    MBEDTLS_MD_SHA256, MBEDTLS_MD_SHA384 or MBEDTLS_MD_SHA512.
+ *
+ * @retval 0       The hash started.
+ * @retval not 0   Unsupported md_type.
+ *
+ **/
+int spdm_hash_ctx_init(void *ctx, int md_type)
+{
+    spdm_hash_ctx *hash_ctx = (spdm_hash_ctx *)ctx;
+
+    hash_ctx->md_type = md_type;
+    switch (md_type)
+    {
+    case MBEDTLS_MD_SHA256:
+        mbedtls_sha256_init(&hash_ctx->u.sha256);
+        return mbedtls_sha256_starts_ret(&hash_ctx->u.sha256, 0);
+    case MBEDTLS_MD_SHA384:
+        mbedtls_sha512_init(&hash_ctx->u.sha512);
+        return mbedtls_sha512_starts_ret(&hash_ctx->u.sha512, 1);
+    case MBEDTLS_MD_SHA512:
+        mbedtls_sha512_init(&hash_ctx->u.sha512);
+        return mbedtls_sha512_starts_ret(&hash_ctx->u.sha512, 0);
+    default:
+        return -1;
+    }
+}
+
+/**
+ * Feeds data to a running hash.
+ *
+ * @param[in, out]  ctx         Pointer to the context started by spdm_hash_ctx_init.
+ * @param[in]       data        Pointer to the data.
+ * @param[in]       data_size   Size of the data in bytes.
+ *
+ * @retval 0       Success.
+ * @retval not 0   Failure.
+ *
+ **/
+int spdm_hash_ctx_update(void *ctx, const uint8_t *data, size_t data_size)
+{
+    spdm_hash_ctx *hash_ctx = (spdm_hash_ctx *)ctx;
+
+    if (hash_ctx->md_type == MBEDTLS_MD_SHA256)
+    {
+        return mbedtls_sha256_update_ret(&hash_ctx->u.sha256, data, data_size);
+    }
+    return mbedtls_sha512_update_ret(&hash_ctx->u.sha512, data, data_size);
+}
+
+/**
+ * Writes the digest of a running hash.
+ *
+ * @param[in, out]  ctx      Pointer to the context started by spdm_hash_ctx_init.
+ * @param[out]      digest   Pointer to the digest buffer, 64 bytes at least.
+ *
+ * @retval 0       Success.
+ * @retval not 0   Failure.
+ *
+ **/
+int spdm_hash_ctx_finalize(void *ctx, uint8_t *digest)
+{
+    spdm_hash_ctx *hash_ctx = (spdm_hash_ctx *)ctx;
+
+    if (hash_ctx->md_type == MBEDTLS_MD_SHA256)
+    {
+        return mbedtls_sha256_finish_ret(&hash_ctx->u.sha256, digest);
+    }
+    return mbedtls_sha512_finish_ret(&hash_ctx->u.sha512, digest);
+}
+
+/**
+ * Copies a running hash into an uninitialized context buffer.
+ **/
+void spdm_hash_ctx_clone(void *dst, const void *src)
+{
+    spdm_hash_ctx *dst_ctx = (spdm_hash_ctx *)dst;
+    const spdm_hash_ctx *src_ctx = (const spdm_hash_ctx *)src;
+
+    dst_ctx->md_type = src_ctx->md_type;
+    if (src_ctx->md_type == MBEDTLS_MD_SHA256)
+    {
+        mbedtls_sha256_init(&dst_ctx->u.sha256);
+        mbedtls_sha256_clone(&dst_ctx->u.sha256, &src_ctx->u.sha256);
+    }
+    else
+    {
+        mbedtls_sha512_init(&dst_ctx->u.sha512);
+        mbedtls_sha512_clone(&dst_ctx->u.sha512, &src_ctx->u.sha512);
+    }
+}
+
+/**
+ * Clears a running hash.
+ **/
+void spdm_hash_ctx_free(void *ctx)
+{
+    spdm_hash_ctx *hash_ctx = (spdm_hash_ctx *)ctx;
+
+    if (hash_ctx->md_type == MBEDTLS_MD_SHA256)
+    {
+        mbedtls_sha256_free(&hash_ctx->u.sha256);
+    }
+    else
+    {
+        mbedtls_sha512_free(&hash_ctx->u.sha512);
+    }
+}
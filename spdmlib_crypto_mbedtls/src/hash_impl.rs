@@ -2,15 +2,119 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use spdmlib::crypto::SpdmHash;
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use spdmlib::crypto::{HashCtx, SpdmHash, SpdmHashContext};
 use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
 
 pub static DEFAULT: SpdmHash = SpdmHash {
     hash_all_cb: hash_all,
+    hash_ctx_init_cb: hash_ctx_init,
+    hash_ctx_update_cb: hash_ctx_update,
+    hash_ctx_finalize_cb: hash_ctx_finalize,
+};
+
+use super::ffi::{
+    mbedtls_sha256, mbedtls_sha512, spdm_hash_ctx_clone, spdm_hash_ctx_finalize,
+    spdm_hash_ctx_free, spdm_hash_ctx_init, spdm_hash_ctx_size, spdm_hash_ctx_update,
 };
+use core::ffi::{c_int, c_uchar, c_void};
+
+// MBEDTLS_MD_SHA256, MBEDTLS_MD_SHA384, MBEDTLS_MD_SHA512
+const MBEDTLS_MD_SHA256: c_int = 6;
+const MBEDTLS_MD_SHA384: c_int = 7;
+const MBEDTLS_MD_SHA512: c_int = 8;
+
+/// spdm_hash_ctx of hash_impl.c, in a u64 aligned buffer.
+///
+/// update cannot return an error, a failed update is kept in `failed` and
+/// reported by finalize.
+struct MbedtlsHashCtx {
+    digest_size: u16,
+    failed: bool,
+    ctx: Vec<u64>,
+}
+
+impl MbedtlsHashCtx {
+    fn alloc(digest_size: u16) -> Self {
+        let size = unsafe { spdm_hash_ctx_size() };
+        MbedtlsHashCtx {
+            digest_size,
+            failed: false,
+            ctx: vec![0u64; (size + 7) / 8],
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut c_void {
+        self.ctx.as_mut_ptr() as *mut c_void
+    }
+}
+
+impl SpdmHashContext for MbedtlsHashCtx {
+    fn update(&mut self, data: &[u8]) {
+        if self.failed {
+            return;
+        }
+        let ret = unsafe { spdm_hash_ctx_update(self.as_mut_ptr(), data.as_ptr(), data.len()) };
+        if ret != 0 {
+            self.failed = true;
+        }
+    }
+
+    fn finalize(mut self: Box<Self>) -> Option<SpdmDigestStruct> {
+        if self.failed {
+            return None;
+        }
+        let mut spdm_digest = SpdmDigestStruct::default();
+        let ret =
+            unsafe { spdm_hash_ctx_finalize(self.as_mut_ptr(), spdm_digest.data.as_mut_ptr()) };
+        if ret != 0 {
+            return None;
+        }
+        spdm_digest.data_size = self.digest_size;
+        Some(spdm_digest)
+    }
+
+    fn box_clone(&self) -> Box<dyn SpdmHashContext> {
+        let mut ctx = MbedtlsHashCtx::alloc(self.digest_size);
+        ctx.failed = self.failed;
+        unsafe {
+            spdm_hash_ctx_clone(ctx.as_mut_ptr(), self.ctx.as_ptr() as *const c_void);
+        }
+        Box::new(ctx)
+    }
+}
 
-use super::ffi::{mbedtls_sha256, mbedtls_sha512};
-use core::ffi::c_uchar;
+impl Drop for MbedtlsHashCtx {
+    fn drop(&mut self) {
+        unsafe { spdm_hash_ctx_free(self.as_mut_ptr()) }
+    }
+}
+
+fn hash_ctx_init(base_hash_algo: SpdmBaseHashAlgo) -> Option<HashCtx> {
+    let (md_type, digest_size) = match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => (MBEDTLS_MD_SHA256, 32),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => (MBEDTLS_MD_SHA384, 48),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => (MBEDTLS_MD_SHA512, 64),
+        _ => return None,
+    };
+    let mut ctx = MbedtlsHashCtx::alloc(digest_size);
+    if unsafe { spdm_hash_ctx_init(ctx.as_mut_ptr(), md_type) } != 0 {
+        return None;
+    }
+    Some(Box::new(ctx))
+}
+
+fn hash_ctx_update(ctx: &mut HashCtx, data: &[u8]) {
+    ctx.update(data)
+}
+
+fn hash_ctx_finalize(ctx: HashCtx) -> Option<SpdmDigestStruct> {
+    ctx.finalize()
+}
 
 fn hash_all(base_hash_algo: SpdmBaseHashAlgo, data: &[u8]) -> Option<SpdmDigestStruct> {
     let d = data.as_ptr() as *const c_uchar;
@@ -85,4 +189,39 @@ mod tests {
         let hash_all = hash_all(base_hash_algo, data);
         assert_eq!(hash_all.is_none(), true);
     }
+    #[test]
+    fn test_case3_hash_ctx() {
+        let data = [0x5au8; 300];
+        for base_hash_algo in [
+            SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_512,
+        ]
+        .iter()
+        {
+            let mut ctx = hash_ctx_init(*base_hash_algo).unwrap();
+            for chunk in data.chunks(7) {
+                hash_ctx_update(&mut ctx, chunk);
+            }
+            let digest_clone = hash_ctx_finalize(ctx.clone()).unwrap();
+            let digest = hash_ctx_finalize(ctx).unwrap();
+            let expected = hash_all(*base_hash_algo, &data).unwrap();
+            assert_eq!(digest.as_ref(), expected.as_ref());
+            assert_eq!(digest_clone.as_ref(), expected.as_ref());
+        }
+        assert!(hash_ctx_init(SpdmBaseHashAlgo::empty()).is_none());
+    }
+    #[test]
+    fn test_case4_hash_ctx_failed_update() {
+        let mut ctx = MbedtlsHashCtx::alloc(32);
+        assert_eq!(
+            unsafe { spdm_hash_ctx_init(ctx.as_mut_ptr(), MBEDTLS_MD_SHA256) },
+            0
+        );
+        ctx.failed = true;
+        let mut ctx: HashCtx = Box::new(ctx);
+        hash_ctx_update(&mut ctx, b"hello");
+        assert!(hash_ctx_finalize(ctx.clone()).is_none());
+        assert!(hash_ctx_finalize(ctx).is_none());
+    }
 }
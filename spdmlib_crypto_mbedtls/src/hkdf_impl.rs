@@ -6,15 +6,48 @@ use spdmlib::crypto::SpdmHkdf;
 use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
 
 pub static DEFAULT: SpdmHkdf = SpdmHkdf {
+    hkdf_extract_cb: hkdf_extract,
     hkdf_expand_cb: hkdf_expand,
 };
 
-use super::ffi::{mbedtls_hkdf_expand, mbedtls_md_info_from_type};
+use super::ffi::{
+    mbedtls_hkdf_expand, mbedtls_md_get_size, mbedtls_md_hmac, mbedtls_md_info_from_type,
+};
 use core::ffi::c_int;
 const MBEDTLS_MD_SHA256: c_int = 6;
 const MBEDTLS_MD_SHA384: c_int = 7;
 const MBEDTLS_MD_SHA512: c_int = 8;
 
+fn hkdf_extract(hash_algo: SpdmBaseHashAlgo, salt: &[u8], ikm: &[u8]) -> Option<SpdmDigestStruct> {
+    let algorithm = match hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => Some(MBEDTLS_MD_SHA256),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => Some(MBEDTLS_MD_SHA384),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => Some(MBEDTLS_MD_SHA512),
+        _ => None,
+    }?;
+    let mut digest = SpdmDigestStruct::default();
+    unsafe {
+        let md_info = mbedtls_md_info_from_type(algorithm);
+        if md_info.is_null() {
+            return None;
+        }
+        // PRK = HMAC-Hash(salt, IKM)
+        let res = mbedtls_md_hmac(
+            md_info,
+            salt.as_ptr(),
+            salt.len(),
+            ikm.as_ptr(),
+            ikm.len(),
+            digest.data.as_mut_ptr(),
+        );
+        if res != 0 {
+            return None;
+        }
+        digest.data_size = mbedtls_md_get_size(md_info) as u16;
+    }
+    Some(digest)
+}
+
 fn hkdf_expand(
     hash_algo: SpdmBaseHashAlgo,
     pk: &[u8],
@@ -54,6 +87,26 @@ fn hkdf_expand(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_case0_hkdf_extract() {
+        // RFC 5869 test case 1
+        let salt = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+        ];
+        let ikm = [0x0bu8; 22];
+        let prk = hkdf_extract(SpdmBaseHashAlgo::TPM_ALG_SHA_256, &salt, &ikm).unwrap();
+        assert_eq!(
+            prk.as_ref(),
+            &[
+                0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b,
+                0xba, 0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a,
+                0xd7, 0xc2, 0xb3, 0xe5,
+            ][..]
+        );
+
+        assert!(hkdf_extract(SpdmBaseHashAlgo::empty(), &salt, &ikm).is_none());
+    }
+
     #[test]
     fn test_case0_hkdf_expand() {
         let base_hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
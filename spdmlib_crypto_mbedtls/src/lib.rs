@@ -10,6 +10,7 @@ extern crate alloc;
 mod ffi;
 
 pub mod aead_impl;
+pub mod asym_sign_impl;
 pub mod asym_verify_impl;
 pub mod cert_operation_impl;
 pub mod dhe_impl;
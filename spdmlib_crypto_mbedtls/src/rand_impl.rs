@@ -3,7 +3,8 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use core::arch::x86_64::_rdrand64_step;
-use spdmlib::crypto::SpdmCryptoRandom;
+use core::ffi::{c_int, c_uchar, c_void};
+use spdmlib::crypto::{SpdmCryptoRandom, SpdmRng};
 use spdmlib::error::{spdm_err, spdm_result_err, SpdmResult};
 
 pub static DEFAULT: SpdmCryptoRandom = SpdmCryptoRandom {
@@ -18,6 +19,29 @@ fn get_random(data: &mut [u8]) -> SpdmResult<usize> {
     }
 }
 
+/// mbedtls f_rng for blinding and signing, p_rng is unused.
+pub(crate) extern "C" fn mbedtls_f_rng(
+    _p_rng: *mut c_void,
+    output: *mut c_uchar,
+    len: usize,
+) -> c_int {
+    f_rng(output, len)
+}
+
+/// mbedtls f_rng drawing from the `&mut dyn SpdmRng` p_rng points to.
+pub(crate) extern "C" fn mbedtls_f_rng_spdm_rng(
+    p_rng: *mut c_void,
+    output: *mut c_uchar,
+    len: usize,
+) -> c_int {
+    let rng = unsafe { &mut *(p_rng as *mut &mut dyn SpdmRng) };
+    let output = unsafe { core::slice::from_raw_parts_mut(output, len) };
+    match rng.get_random(output) {
+        Ok(size) if size == len => 0,
+        _ => 1,
+    }
+}
+
 fn f_rng(output: *mut u8, len: usize) -> i32 {
     let mut remain = len;
     while remain > 8 {
@@ -83,6 +83,26 @@ mod test_mbedtls {
         }
     }
 
+    #[test]
+    fn test_mbedtls_asym_sign() {
+        let hash_algo = SpdmBaseHashAlgo::TPM_ALG_SHA_256;
+        let asym_algo = SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256;
+        let cert_chain = &include_bytes!(
+            "../../../rust-spdm/test_key/EcP256/bundle_responder.certchain.der"
+        )[..];
+        let private_key_der =
+            &include_bytes!("../../../rust-spdm/test_key/EcP256/end_responder.key.p8")[..];
+        spdmlib_crypto_mbedtls::asym_sign_impl::set_signing_key(asym_algo, private_key_der)
+            .unwrap();
+
+        let data = &b"hello"[..];
+        let sig =
+            (spdmlib_crypto_mbedtls::asym_sign_impl::DEFAULT.sign_cb)(hash_algo, asym_algo, data)
+                .unwrap();
+
+        spdmlib::crypto::asym_verify::verify(hash_algo, asym_algo, cert_chain, data, &sig).unwrap();
+    }
+
     fn test_asym_sign_verify(
         hash_algo: SpdmBaseHashAlgo,
         asym_algo: SpdmBaseAsymAlgo,
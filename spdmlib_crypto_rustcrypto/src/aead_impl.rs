@@ -0,0 +1,131 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use aes_gcm::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20poly1305::ChaCha20Poly1305;
+use spdmlib::crypto::SpdmAead;
+use spdmlib::error::{spdm_result_err, SpdmResult};
+use spdmlib::protocol::SpdmAeadAlgo;
+
+pub static DEFAULT: SpdmAead = SpdmAead {
+    encrypt_cb: encrypt,
+    decrypt_cb: decrypt,
+};
+
+fn check_sizes(aead_algo: SpdmAeadAlgo, key: &[u8], iv: &[u8], tag: &[u8]) -> SpdmResult {
+    match aead_algo {
+        SpdmAeadAlgo::AES_128_GCM | SpdmAeadAlgo::AES_256_GCM | SpdmAeadAlgo::CHACHA20_POLY1305 => {
+        }
+        _ => return spdm_result_err!(EINVAL),
+    }
+    if key.len() != aead_algo.get_key_size() as usize
+        || iv.len() != aead_algo.get_iv_size() as usize
+        || tag.len() != aead_algo.get_tag_size() as usize
+    {
+        return spdm_result_err!(EINVAL);
+    }
+    Ok(())
+}
+
+fn encrypt(
+    aead_algo: SpdmAeadAlgo,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    plain_text: &[u8],
+    tag: &mut [u8],
+    cipher_text: &mut [u8],
+) -> SpdmResult<(usize, usize)> {
+    check_sizes(aead_algo, key, iv, tag)?;
+    if cipher_text.len() != plain_text.len() {
+        return spdm_result_err!(EINVAL);
+    }
+
+    cipher_text.copy_from_slice(plain_text);
+    let nonce = GenericArray::from_slice(iv);
+    let res = match aead_algo {
+        SpdmAeadAlgo::AES_128_GCM => Aes128Gcm::new(GenericArray::from_slice(key))
+            .encrypt_in_place_detached(nonce, aad, cipher_text),
+        SpdmAeadAlgo::AES_256_GCM => Aes256Gcm::new(GenericArray::from_slice(key))
+            .encrypt_in_place_detached(nonce, aad, cipher_text),
+        _ => ChaCha20Poly1305::new(GenericArray::from_slice(key)).encrypt_in_place_detached(
+            nonce,
+            aad,
+            cipher_text,
+        ),
+    };
+    match res {
+        Ok(computed_tag) => {
+            tag.copy_from_slice(&computed_tag);
+            Ok((cipher_text.len(), tag.len()))
+        }
+        Err(_) => spdm_result_err!(ESEC),
+    }
+}
+
+fn decrypt(
+    aead_algo: SpdmAeadAlgo,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    cipher_text: &[u8],
+    tag: &[u8],
+    plain_text: &mut [u8],
+) -> SpdmResult<usize> {
+    check_sizes(aead_algo, key, iv, tag)?;
+    if plain_text.len() < cipher_text.len() {
+        return spdm_result_err!(EINVAL);
+    }
+
+    let plain_text = &mut plain_text[..cipher_text.len()];
+    plain_text.copy_from_slice(cipher_text);
+    let nonce = GenericArray::from_slice(iv);
+    let tag = GenericArray::from_slice(tag);
+    let res = match aead_algo {
+        SpdmAeadAlgo::AES_128_GCM => Aes128Gcm::new(GenericArray::from_slice(key))
+            .decrypt_in_place_detached(nonce, aad, plain_text, tag),
+        SpdmAeadAlgo::AES_256_GCM => Aes256Gcm::new(GenericArray::from_slice(key))
+            .decrypt_in_place_detached(nonce, aad, plain_text, tag),
+        _ => ChaCha20Poly1305::new(GenericArray::from_slice(key))
+            .decrypt_in_place_detached(nonce, aad, plain_text, tag),
+    };
+    match res {
+        Ok(()) => Ok(plain_text.len()),
+        Err(_) => spdm_result_err!(ESEC),
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    fn round_trip(aead_algo: SpdmAeadAlgo) {
+        let key = &[100u8; 32][..aead_algo.get_key_size() as usize];
+        let iv = &[100u8; 12];
+        let aad = &[0u8; 16];
+        let plain_text = &b"hello"[..];
+        let tag = &mut [0u8; 16];
+        let cipher_text = &mut [0u8; 5];
+
+        let (cipher_text_len, tag_len) =
+            encrypt(aead_algo, key, iv, aad, plain_text, tag, cipher_text).unwrap();
+        assert_eq!(cipher_text_len, 5);
+        assert_eq!(tag_len, 16);
+
+        let plain_text_out = &mut [0u8; 100];
+        let used = decrypt(aead_algo, key, iv, aad, cipher_text, tag, plain_text_out).unwrap();
+        assert_eq!(&plain_text_out[..used], plain_text);
+
+        tag[0] ^= 1;
+        assert!(decrypt(aead_algo, key, iv, aad, cipher_text, tag, plain_text_out).is_err());
+    }
+
+    #[test]
+    fn test_case0_aead() {
+        round_trip(SpdmAeadAlgo::AES_128_GCM);
+        round_trip(SpdmAeadAlgo::AES_256_GCM);
+        round_trip(SpdmAeadAlgo::CHACHA20_POLY1305);
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use p256::ecdsa::signature::hazmat::PrehashSigner;
+use spdmlib::crypto::SpdmAsymSign;
+use spdmlib::error::{spdm_result_err, SpdmResult};
+use spdmlib::protocol::{
+    SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmSignatureStruct, SPDM_MAX_ASYM_KEY_SIZE,
+};
+use spin::Mutex;
+use zeroize::Zeroizing;
+
+const MAX_ECC_PRIVATE_KEY_SIZE: usize = 48;
+
+struct SigningKey {
+    base_asym_algo: SpdmBaseAsymAlgo,
+    private_key: Zeroizing<[u8; MAX_ECC_PRIVATE_KEY_SIZE]>,
+    private_key_size: usize,
+}
+
+static SIGNING_KEY: Mutex<Option<SigningKey>> = Mutex::new(None);
+
+pub static DEFAULT: SpdmAsymSign = SpdmAsymSign { sign_cb: asym_sign };
+
+/// Provision the raw ECC private scalar used by `DEFAULT` to sign.
+pub fn set_signing_key(base_asym_algo: SpdmBaseAsymAlgo, private_key: &[u8]) -> SpdmResult {
+    let valid = match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 => {
+            p256::ecdsa::SigningKey::from_bytes(private_key).is_ok()
+        }
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => {
+            p384::ecdsa::SigningKey::from_bytes(private_key).is_ok()
+        }
+        _ => false,
+    };
+    if !valid {
+        return spdm_result_err!(EINVAL);
+    }
+
+    let mut signing_key = SigningKey {
+        base_asym_algo,
+        private_key: Zeroizing::new([0u8; MAX_ECC_PRIVATE_KEY_SIZE]),
+        private_key_size: private_key.len(),
+    };
+    signing_key.private_key[..private_key.len()].copy_from_slice(private_key);
+    *SIGNING_KEY.lock() = Some(signing_key);
+    Ok(())
+}
+
+fn asym_sign(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    data: &[u8],
+) -> Option<SpdmSignatureStruct> {
+    let digest = crate::hash_impl::hash_all(base_hash_algo, data)?;
+
+    let signing_key = SIGNING_KEY.lock();
+    let signing_key = signing_key.as_ref()?;
+    if signing_key.base_asym_algo != base_asym_algo {
+        return None;
+    }
+    let private_key = &signing_key.private_key[..signing_key.private_key_size];
+
+    let mut signature = SpdmSignatureStruct {
+        data_size: 0,
+        data: [0u8; SPDM_MAX_ASYM_KEY_SIZE],
+    };
+    match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 => {
+            let key = p256::ecdsa::SigningKey::from_bytes(private_key).ok()?;
+            let sig: p256::ecdsa::Signature = key.sign_prehash(digest.as_ref()).ok()?;
+            let sig = sig.as_ref();
+            signature.data[..sig.len()].copy_from_slice(sig);
+            signature.data_size = sig.len() as u16;
+        }
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => {
+            let key = p384::ecdsa::SigningKey::from_bytes(private_key).ok()?;
+            let sig: p384::ecdsa::Signature = key.sign_prehash(digest.as_ref()).ok()?;
+            let sig = sig.as_ref();
+            signature.data[..sig.len()].copy_from_slice(sig);
+            signature.data_size = sig.len() as u16;
+        }
+        _ => return None,
+    }
+    Some(signature)
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use core::convert::TryFrom;
+    use p256::ecdsa::signature::hazmat::PrehashVerifier;
+
+    #[test]
+    fn test_case0_asym_sign() {
+        let private_key = [0x11u8; 32];
+        assert!(set_signing_key(SpdmBaseAsymAlgo::TPM_ALG_RSASSA_2048, &private_key).is_err());
+        assert!(
+            set_signing_key(SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256, &private_key).is_ok()
+        );
+
+        let signature = asym_sign(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256,
+            b"hello",
+        )
+        .unwrap();
+        assert_eq!(signature.data_size, 64);
+
+        let verifying_key = p256::ecdsa::SigningKey::from_bytes(&private_key)
+            .unwrap()
+            .verifying_key();
+        let digest =
+            crate::hash_impl::hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_256, b"hello").unwrap();
+        let sig = p256::ecdsa::Signature::try_from(signature.as_ref()).unwrap();
+        assert!(verifying_key.verify_prehash(digest.as_ref(), &sig).is_ok());
+
+        assert!(asym_sign(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_256,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
+            b"hello",
+        )
+        .is_none());
+    }
+}
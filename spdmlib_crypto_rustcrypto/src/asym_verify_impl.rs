@@ -0,0 +1,137 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use core::convert::TryFrom;
+use der::Decode;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use spdmlib::crypto::SpdmAsymVerify;
+use spdmlib::error::{spdm_result_err, SpdmResult};
+use spdmlib::protocol::{SpdmBaseAsymAlgo, SpdmBaseHashAlgo, SpdmSignatureStruct};
+
+pub static DEFAULT: SpdmAsymVerify = SpdmAsymVerify {
+    verify_cb: asym_verify,
+};
+
+fn asym_verify(
+    base_hash_algo: SpdmBaseHashAlgo,
+    base_asym_algo: SpdmBaseAsymAlgo,
+    public_cert_der: &[u8],
+    data: &[u8],
+    signature: &SpdmSignatureStruct,
+) -> SpdmResult {
+    let leaf_cert_der = match get_leaf_cert(public_cert_der) {
+        Some(leaf_cert_der) => leaf_cert_der,
+        None => return spdm_result_err!(EFAULT),
+    };
+    let leaf_cert = match x509_cert::Certificate::from_der(leaf_cert_der) {
+        Ok(leaf_cert) => leaf_cert,
+        Err(_) => return spdm_result_err!(EFAULT),
+    };
+    let public_key = leaf_cert
+        .tbs_certificate
+        .subject_public_key_info
+        .subject_public_key;
+
+    let digest = match crate::hash_impl::hash_all(base_hash_algo, data) {
+        Some(digest) => digest,
+        None => return spdm_result_err!(EINVAL),
+    };
+
+    let res = match base_asym_algo {
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256 => {
+            let verifying_key = match p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key) {
+                Ok(verifying_key) => verifying_key,
+                Err(_) => return spdm_result_err!(EFAULT),
+            };
+            let signature = match p256::ecdsa::Signature::try_from(signature.as_ref()) {
+                Ok(signature) => signature,
+                Err(_) => return spdm_result_err!(EFAULT),
+            };
+            verifying_key.verify_prehash(digest.as_ref(), &signature)
+        }
+        SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384 => {
+            let verifying_key = match p384::ecdsa::VerifyingKey::from_sec1_bytes(public_key) {
+                Ok(verifying_key) => verifying_key,
+                Err(_) => return spdm_result_err!(EFAULT),
+            };
+            let signature = match p384::ecdsa::Signature::try_from(signature.as_ref()) {
+                Ok(signature) => signature,
+                Err(_) => return spdm_result_err!(EFAULT),
+            };
+            verifying_key.verify_prehash(digest.as_ref(), &signature)
+        }
+        _ => return spdm_result_err!(EINVAL),
+    };
+
+    match res {
+        Ok(()) => Ok(()),
+        Err(_) => spdm_result_err!(EFAULT),
+    }
+}
+
+// Walk the DER certificates in the chain and return the last one.
+fn get_leaf_cert(cert_chain: &[u8]) -> Option<&[u8]> {
+    let mut offset = 0usize;
+    let mut leaf = None;
+    while offset < cert_chain.len() {
+        let cert_len = get_der_sequence_len(&cert_chain[offset..])?;
+        if offset + cert_len > cert_chain.len() {
+            return None;
+        }
+        leaf = Some(&cert_chain[offset..(offset + cert_len)]);
+        offset += cert_len;
+    }
+    leaf
+}
+
+// Total size of the DER SEQUENCE at the start of `der`, header included.
+fn get_der_sequence_len(der: &[u8]) -> Option<usize> {
+    if der.len() < 2 || der[0] != 0x30 {
+        return None;
+    }
+    match der[1] {
+        len @ 0x00..=0x7f => Some(2 + len as usize),
+        0x81 if der.len() >= 3 => Some(3 + der[2] as usize),
+        0x82 if der.len() >= 4 => Some(4 + ((der[2] as usize) << 8) + der[3] as usize),
+        _ => None,
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_get_leaf_cert() {
+        let cert_chain = &mut [0u8; 0x200];
+        cert_chain[0] = 0x30;
+        cert_chain[1] = 0x82;
+        cert_chain[2] = 0x01;
+        cert_chain[3] = 0x00;
+        cert_chain[0x104] = 0x30;
+        cert_chain[0x105] = 0x81;
+        cert_chain[0x106] = 0xf9;
+        let leaf = get_leaf_cert(&cert_chain[..0x200]).unwrap();
+        assert_eq!(leaf.len(), 0xfc);
+        assert_eq!(leaf[0], 0x30);
+
+        assert!(get_leaf_cert(&cert_chain[..0x1ff]).is_none());
+        assert!(get_leaf_cert(&[0x31, 0x00]).is_none());
+    }
+    #[test]
+    fn test_case0_asym_verify() {
+        let signature = SpdmSignatureStruct {
+            data_size: 64,
+            data: [0u8; spdmlib::protocol::SPDM_MAX_ASYM_KEY_SIZE],
+        };
+        assert!(asym_verify(
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P256,
+            &[0x30, 0x00],
+            b"hello",
+            &signature,
+        )
+        .is_err());
+    }
+}
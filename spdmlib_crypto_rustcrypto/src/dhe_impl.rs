@@ -0,0 +1,377 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use alloc::boxed::Box;
+use bytes::BytesMut;
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{Encoding, Uint, U2048, U3072, U4096, U512};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use spdmlib::crypto::ffdhe;
+use spdmlib::crypto::{SpdmDhe, SpdmDheKeyExchange, SpdmRng};
+use spdmlib::protocol::{SpdmDheAlgo, SpdmDheExchangeStruct, SpdmDheFinalKeyStruct};
+use zeroize::Zeroize;
+
+pub static DEFAULT: SpdmDhe = SpdmDhe {
+    generate_key_pair_cb: generate_key_pair,
+};
+
+fn generate_key_pair(
+    dhe_algo: SpdmDheAlgo,
+    rng: &mut dyn SpdmRng,
+) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+    match dhe_algo {
+        SpdmDheAlgo::SECP_256_R1 => SpdmDheKeyExchangeP256::generate_key_pair(rng),
+        SpdmDheAlgo::SECP_384_R1 => SpdmDheKeyExchangeP384::generate_key_pair(rng),
+        SpdmDheAlgo::FFDHE_2048 | SpdmDheAlgo::FFDHE_3072 | SpdmDheAlgo::FFDHE_4096 => {
+            SpdmDheKeyExchangeFfdhe::generate_key_pair(dhe_algo, rng)
+        }
+        _ => None,
+    }
+}
+
+// Draws of a private scalar out of [1, n) before giving up on the rng.
+const SECRET_KEY_ATTEMPTS: usize = 8;
+
+// The private scalar is read from rng directly rather than through rand_core,
+// whose fill_bytes cannot report an rng failure.
+fn random_secret_key<K>(
+    rng: &mut dyn SpdmRng,
+    scalar: &mut [u8],
+    from_be_bytes: impl Fn(&[u8]) -> Option<K>,
+) -> Option<K> {
+    let mut secret_key = None;
+    for _ in 0..SECRET_KEY_ATTEMPTS {
+        match rng.get_random(scalar) {
+            Ok(used) if used == scalar.len() => {}
+            _ => break,
+        }
+        secret_key = from_be_bytes(scalar);
+        if secret_key.is_some() {
+            break;
+        }
+    }
+    scalar.zeroize();
+    secret_key
+}
+
+// The SPDM exchange data is the uncompressed point without the 0x04 prefix.
+fn peer_sec1_point(peer_pub_key: &SpdmDheExchangeStruct) -> BytesMut {
+    let mut pubkey = BytesMut::new();
+    pubkey.extend_from_slice(&[0x04u8]);
+    pubkey.extend_from_slice(peer_pub_key.as_ref());
+    pubkey
+}
+
+struct SpdmDheKeyExchangeP256(p256::SecretKey);
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeP256 {
+    fn compute_final_key(
+        self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        let peer_public_key =
+            p256::PublicKey::from_sec1_bytes(peer_sec1_point(peer_pub_key).as_ref()).ok()?;
+        let shared_secret =
+            p256::ecdh::diffie_hellman(self.0.to_nonzero_scalar(), peer_public_key.as_affine());
+        Some(SpdmDheFinalKeyStruct::from(BytesMut::from(
+            shared_secret.raw_secret_bytes().as_slice(),
+        )))
+    }
+}
+
+impl SpdmDheKeyExchangeP256 {
+    fn generate_key_pair(
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let private_key = random_secret_key(rng, &mut [0u8; 32], |scalar| {
+            p256::SecretKey::from_be_bytes(scalar).ok()
+        })?;
+        let encoded_point = private_key.public_key().to_encoded_point(false);
+        let public_key = BytesMut::from(&encoded_point.as_bytes()[1..]);
+
+        let res: Box<dyn SpdmDheKeyExchange> = Box::new(Self(private_key));
+
+        Some((SpdmDheExchangeStruct::from(public_key), res))
+    }
+}
+
+struct SpdmDheKeyExchangeP384(p384::SecretKey);
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeP384 {
+    fn compute_final_key(
+        self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        let peer_public_key =
+            p384::PublicKey::from_sec1_bytes(peer_sec1_point(peer_pub_key).as_ref()).ok()?;
+        let shared_secret =
+            p384::ecdh::diffie_hellman(self.0.to_nonzero_scalar(), peer_public_key.as_affine());
+        Some(SpdmDheFinalKeyStruct::from(BytesMut::from(
+            shared_secret.raw_secret_bytes().as_slice(),
+        )))
+    }
+}
+
+impl SpdmDheKeyExchangeP384 {
+    fn generate_key_pair(
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let private_key = random_secret_key(rng, &mut [0u8; 48], |scalar| {
+            p384::SecretKey::from_be_bytes(scalar).ok()
+        })?;
+        let encoded_point = private_key.public_key().to_encoded_point(false);
+        let public_key = BytesMut::from(&encoded_point.as_bytes()[1..]);
+
+        let res: Box<dyn SpdmDheKeyExchange> = Box::new(Self(private_key));
+
+        Some((SpdmDheExchangeStruct::from(public_key), res))
+    }
+}
+
+// base^exponent mod prime in Montgomery form, with the same steps for every
+// exponent of the same width. The result is padded to the size of prime.
+fn ffdhe_mod_exp<const LIMBS: usize>(base: &[u8], exponent: &U512, prime: &[u8]) -> BytesMut
+where
+    Uint<LIMBS>: Encoding,
+{
+    let params = DynResidueParams::new(&Uint::<LIMBS>::from_be_slice(prime));
+    let mut result = DynResidue::new(&Uint::<LIMBS>::from_be_slice(base), params)
+        .pow(exponent)
+        .retrieve();
+    let mut result_bytes = result.to_be_bytes();
+    let res = BytesMut::from(result_bytes.as_ref());
+    result.zeroize();
+    result_bytes.as_mut().zeroize();
+    res
+}
+
+struct SpdmDheKeyExchangeFfdhe {
+    dhe_algo: SpdmDheAlgo,
+    private_key: [u8; ffdhe::FFDHE_PRIVATE_KEY_SIZE],
+}
+
+impl Drop for SpdmDheKeyExchangeFfdhe {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl SpdmDheKeyExchangeFfdhe {
+    fn mod_exp(&self, base: &[u8]) -> Option<BytesMut> {
+        let prime = ffdhe::get_prime(self.dhe_algo)?;
+        let mut exponent = U512::from_be_slice(&self.private_key);
+        let res = match self.dhe_algo {
+            SpdmDheAlgo::FFDHE_2048 => ffdhe_mod_exp::<{ U2048::LIMBS }>(base, &exponent, prime),
+            SpdmDheAlgo::FFDHE_3072 => ffdhe_mod_exp::<{ U3072::LIMBS }>(base, &exponent, prime),
+            SpdmDheAlgo::FFDHE_4096 => ffdhe_mod_exp::<{ U4096::LIMBS }>(base, &exponent, prime),
+            _ => return None,
+        };
+        exponent.zeroize();
+        Some(res)
+    }
+
+    fn generate_key_pair(
+        dhe_algo: SpdmDheAlgo,
+        rng: &mut dyn SpdmRng,
+    ) -> Option<(SpdmDheExchangeStruct, Box<dyn SpdmDheKeyExchange>)> {
+        let prime = ffdhe::get_prime(dhe_algo)?;
+        let mut private_key = Self {
+            dhe_algo,
+            private_key: [0u8; ffdhe::FFDHE_PRIVATE_KEY_SIZE],
+        };
+        ffdhe::generate_private_key(rng, &mut private_key.private_key)?;
+
+        // the generator padded to the size of the prime
+        let mut generator = BytesMut::new();
+        generator.resize(prime.len() - ffdhe::FFDHE_GENERATOR.len(), 0);
+        generator.extend_from_slice(&ffdhe::FFDHE_GENERATOR);
+        let public_key = private_key.mod_exp(generator.as_ref())?;
+
+        let res: Box<dyn SpdmDheKeyExchange> = Box::new(private_key);
+
+        Some((SpdmDheExchangeStruct::from(public_key), res))
+    }
+}
+
+impl SpdmDheKeyExchange for SpdmDheKeyExchangeFfdhe {
+    fn compute_final_key(
+        self: Box<Self>,
+        peer_pub_key: &SpdmDheExchangeStruct,
+    ) -> Option<SpdmDheFinalKeyStruct> {
+        let prime = ffdhe::get_prime(self.dhe_algo)?;
+        if !ffdhe::is_valid_public_key(peer_pub_key.as_ref(), prime) {
+            return None;
+        }
+        let mut final_key = self.mod_exp(peer_pub_key.as_ref())?;
+        let res = SpdmDheFinalKeyStruct::from(BytesMut::from(final_key.as_ref()));
+        final_key.as_mut().zeroize();
+        Some(res)
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use spdmlib::error::{spdm_result_err, SpdmResult};
+
+    struct TestRng;
+
+    impl SpdmRng for TestRng {
+        fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+            (crate::rand_impl::DEFAULT.get_random_cb)(data)
+        }
+    }
+
+    #[test]
+    fn test_case0_dhe() {
+        for dhe_algo in [
+            SpdmDheAlgo::SECP_256_R1,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmDheAlgo::FFDHE_2048,
+            SpdmDheAlgo::FFDHE_3072,
+            SpdmDheAlgo::FFDHE_4096,
+        ] {
+            let (exchange1, private1) = generate_key_pair(dhe_algo, &mut TestRng).unwrap();
+            let (exchange2, private2) = generate_key_pair(dhe_algo, &mut TestRng).unwrap();
+            assert_eq!(exchange1.data_size, dhe_algo.get_size());
+
+            let peer1 = private1.compute_final_key(&exchange2).unwrap();
+            let peer2 = private2.compute_final_key(&exchange1).unwrap();
+            assert_eq!(peer1.as_ref(), peer2.as_ref());
+        }
+    }
+    #[test]
+    fn test_case1_dhe() {
+        assert!(generate_key_pair(SpdmDheAlgo::SECP_521_R1, &mut TestRng).is_none());
+
+        // RFC 7919 section 5.1, the peer value must be in (1, p - 1)
+        let prime = ffdhe::get_prime(SpdmDheAlgo::FFDHE_2048).unwrap();
+        let mut exchange = SpdmDheExchangeStruct {
+            data_size: prime.len() as u16,
+            ..Default::default()
+        };
+        exchange.data[prime.len() - 1] = 1;
+        let (_, private) = generate_key_pair(SpdmDheAlgo::FFDHE_2048, &mut TestRng).unwrap();
+        assert!(private.compute_final_key(&exchange).is_none());
+
+        exchange.data[..prime.len()].copy_from_slice(prime);
+        exchange.data[prime.len() - 1] -= 1;
+        let (_, private) = generate_key_pair(SpdmDheAlgo::FFDHE_2048, &mut TestRng).unwrap();
+        assert!(private.compute_final_key(&exchange).is_none());
+    }
+
+    struct FailingRng;
+
+    impl SpdmRng for FailingRng {
+        fn get_random(&mut self, _data: &mut [u8]) -> SpdmResult<usize> {
+            spdm_result_err!(ESEC)
+        }
+    }
+
+    #[test]
+    fn test_case2_dhe() {
+        for dhe_algo in [
+            SpdmDheAlgo::SECP_256_R1,
+            SpdmDheAlgo::SECP_384_R1,
+            SpdmDheAlgo::FFDHE_2048,
+        ] {
+            assert!(generate_key_pair(dhe_algo, &mut FailingRng).is_none());
+        }
+    }
+
+    // counts up from its seed, for the same private keys on every run
+    struct KatRng(u8);
+
+    impl SpdmRng for KatRng {
+        fn get_random(&mut self, data: &mut [u8]) -> SpdmResult<usize> {
+            for byte in data.iter_mut() {
+                self.0 = self.0.wrapping_add(1);
+                *byte = self.0;
+            }
+            Ok(data.len())
+        }
+    }
+
+    // SHA-256 of g^a, g^b and g^(ab) mod p, a drawn from KatRng(0) and b from
+    // KatRng(0x40), computed independently with Python's pow()
+    const FFDHE_KAT: [(SpdmDheAlgo, [[u8; 32]; 3]); 3] = [
+        (
+            SpdmDheAlgo::FFDHE_2048,
+            [
+                [
+                    0x56, 0x06, 0x11, 0x39, 0x2f, 0x71, 0xcf, 0x1f, 0x81, 0xe6, 0x75, 0x6a, 0x86,
+                    0xf0, 0x16, 0x98, 0x5f, 0x59, 0xfc, 0xa7, 0x81, 0x1c, 0xa3, 0xf7, 0x16, 0xe9,
+                    0xda, 0xac, 0xb2, 0xb5, 0x2f, 0x6b,
+                ],
+                [
+                    0x4e, 0x71, 0xff, 0xbf, 0xed, 0x6d, 0x59, 0x05, 0x25, 0xef, 0x44, 0x80, 0x31,
+                    0x2d, 0xbe, 0xf5, 0x24, 0xc2, 0x96, 0x28, 0x74, 0xf6, 0xd7, 0xa4, 0x50, 0xcb,
+                    0x51, 0x82, 0x42, 0x91, 0x66, 0x7f,
+                ],
+                [
+                    0x62, 0x74, 0x32, 0xa0, 0x0e, 0x60, 0xd9, 0x01, 0x15, 0x4f, 0xe9, 0xda, 0x6c,
+                    0xd5, 0xc1, 0x65, 0x16, 0xc1, 0xb7, 0xe1, 0xcb, 0x70, 0xe4, 0x3c, 0xf1, 0x8f,
+                    0xef, 0xaf, 0x53, 0x07, 0xba, 0xf2,
+                ],
+            ],
+        ),
+        (
+            SpdmDheAlgo::FFDHE_3072,
+            [
+                [
+                    0x91, 0x4b, 0x8c, 0x2e, 0xda, 0x6e, 0x19, 0xab, 0xc7, 0xd2, 0x62, 0xc9, 0x00,
+                    0x01, 0xbd, 0x50, 0x1c, 0x29, 0xb2, 0x24, 0xe3, 0x17, 0xac, 0x97, 0x82, 0xf3,
+                    0xaf, 0xd7, 0x40, 0x26, 0x73, 0x91,
+                ],
+                [
+                    0x5c, 0x3b, 0x47, 0xbd, 0xe4, 0x9c, 0xd9, 0xca, 0xa3, 0xb6, 0x13, 0xcc, 0xad,
+                    0xbf, 0x25, 0xed, 0x9f, 0xf7, 0xc0, 0xa4, 0x24, 0xfa, 0x34, 0xea, 0xda, 0x91,
+                    0x95, 0xf1, 0x93, 0x4b, 0x9d, 0x74,
+                ],
+                [
+                    0x6e, 0xf4, 0xda, 0x23, 0xf7, 0x40, 0x19, 0xee, 0x26, 0xb7, 0x15, 0xee, 0x2e,
+                    0x1e, 0xe3, 0x16, 0xe2, 0xa9, 0xb4, 0x9d, 0x40, 0xc4, 0x3f, 0xe8, 0xf3, 0xe5,
+                    0xa2, 0x98, 0xd6, 0x41, 0x3d, 0xda,
+                ],
+            ],
+        ),
+        (
+            SpdmDheAlgo::FFDHE_4096,
+            [
+                [
+                    0x77, 0x27, 0x7d, 0x4c, 0x49, 0x1b, 0xcf, 0xb7, 0x54, 0x1d, 0x8b, 0x26, 0x4d,
+                    0x70, 0xd8, 0x76, 0x12, 0xcb, 0xf4, 0x2b, 0xec, 0x19, 0xfc, 0xde, 0x49, 0xcb,
+                    0xac, 0xdb, 0x6e, 0xd4, 0xe8, 0xa6,
+                ],
+                [
+                    0x25, 0x1d, 0x4f, 0xd6, 0x47, 0x32, 0x0e, 0x7c, 0x88, 0x38, 0x2d, 0x2a, 0x32,
+                    0x84, 0xab, 0x2a, 0x8f, 0xb7, 0x95, 0x2e, 0x06, 0xac, 0xa9, 0xf2, 0xab, 0xff,
+                    0x45, 0x53, 0x45, 0x26, 0xbd, 0xf4,
+                ],
+                [
+                    0x6b, 0x4a, 0xe7, 0x4a, 0x0b, 0xea, 0x4d, 0x3a, 0xf0, 0xac, 0x63, 0xbf, 0xcd,
+                    0x5d, 0x79, 0xcb, 0xb4, 0xff, 0x55, 0x76, 0x35, 0x5e, 0x98, 0xa6, 0x9e, 0xe4,
+                    0xfd, 0x0f, 0x6d, 0x7d, 0xaf, 0xb0,
+                ],
+            ],
+        ),
+    ];
+
+    #[test]
+    fn test_case3_dhe() {
+        for (dhe_algo, expected) in FFDHE_KAT {
+            let (exchange1, private1) = generate_key_pair(dhe_algo, &mut KatRng(0)).unwrap();
+            let (exchange2, private2) = generate_key_pair(dhe_algo, &mut KatRng(0x40)).unwrap();
+            assert_eq!(exchange1.data_size, dhe_algo.get_size());
+            assert_eq!(Sha256::digest(exchange1.as_ref()).as_slice(), expected[0]);
+            assert_eq!(Sha256::digest(exchange2.as_ref()).as_slice(), expected[1]);
+
+            let peer1 = private1.compute_final_key(&exchange2).unwrap();
+            let peer2 = private2.compute_final_key(&exchange1).unwrap();
+            assert_eq!(Sha256::digest(peer1.as_ref()).as_slice(), expected[2]);
+            assert_eq!(peer1.as_ref(), peer2.as_ref());
+        }
+    }
+}
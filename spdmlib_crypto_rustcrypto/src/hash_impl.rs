@@ -0,0 +1,107 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use alloc::boxed::Box;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use spdmlib::crypto::{HashCtx, SpdmHash, SpdmHashContext};
+use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
+
+pub static DEFAULT: SpdmHash = SpdmHash {
+    hash_all_cb: hash_all,
+    hash_ctx_init_cb: hash_ctx_init,
+    hash_ctx_update_cb: hash_ctx_update,
+    hash_ctx_finalize_cb: hash_ctx_finalize,
+};
+
+#[derive(Clone)]
+enum RustCryptoHashCtx {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl SpdmHashContext for RustCryptoHashCtx {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            RustCryptoHashCtx::Sha256(ctx) => ctx.update(data),
+            RustCryptoHashCtx::Sha384(ctx) => ctx.update(data),
+            RustCryptoHashCtx::Sha512(ctx) => ctx.update(data),
+        }
+    }
+
+    fn finalize(self: Box<Self>) -> Option<SpdmDigestStruct> {
+        match *self {
+            RustCryptoHashCtx::Sha256(ctx) => Some(SpdmDigestStruct::from(&ctx.finalize()[..])),
+            RustCryptoHashCtx::Sha384(ctx) => Some(SpdmDigestStruct::from(&ctx.finalize()[..])),
+            RustCryptoHashCtx::Sha512(ctx) => Some(SpdmDigestStruct::from(&ctx.finalize()[..])),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn SpdmHashContext> {
+        Box::new(self.clone())
+    }
+}
+
+pub(crate) fn hash_all(base_hash_algo: SpdmBaseHashAlgo, data: &[u8]) -> Option<SpdmDigestStruct> {
+    let mut ctx = hash_ctx_init(base_hash_algo)?;
+    ctx.update(data);
+    ctx.finalize()
+}
+
+fn hash_ctx_init(base_hash_algo: SpdmBaseHashAlgo) -> Option<HashCtx> {
+    let ctx = match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => RustCryptoHashCtx::Sha256(Sha256::new()),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => RustCryptoHashCtx::Sha384(Sha384::new()),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => RustCryptoHashCtx::Sha512(Sha512::new()),
+        _ => return None,
+    };
+    Some(Box::new(ctx))
+}
+
+fn hash_ctx_update(ctx: &mut HashCtx, data: &[u8]) {
+    ctx.update(data)
+}
+
+fn hash_ctx_finalize(ctx: HashCtx) -> Option<SpdmDigestStruct> {
+    ctx.finalize()
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_hash_all() {
+        let hash_all = hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_256, b"hello").unwrap();
+        assert_eq!(
+            hash_all.as_ref(),
+            &[
+                0x2c, 0xf2, 0x4d, 0xba, 0x5f, 0xb0, 0xa3, 0x0e, 0x26, 0xe8, 0x3b, 0x2a, 0xc5, 0xb9,
+                0xe2, 0x9e, 0x1b, 0x16, 0x1e, 0x5c, 0x1f, 0xa7, 0x42, 0x5e, 0x73, 0x04, 0x33, 0x62,
+                0x93, 0x8b, 0x98, 0x24
+            ][..]
+        );
+        assert!(hash_all(SpdmBaseHashAlgo::empty(), b"hello").is_none());
+    }
+    #[test]
+    fn test_case0_hash_update() {
+        let helloworld = hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_384, b"hello, world").unwrap();
+        let hellobuddy = hash_all(SpdmBaseHashAlgo::TPM_ALG_SHA_384, b"hello, buddy").unwrap();
+
+        let mut ctx = hash_ctx_init(SpdmBaseHashAlgo::TPM_ALG_SHA_384).unwrap();
+        hash_ctx_update(&mut ctx, b"hello");
+        hash_ctx_update(&mut ctx, b", ");
+        let mut ctx_d = ctx.clone();
+        hash_ctx_update(&mut ctx_d, b"buddy");
+        hash_ctx_update(&mut ctx, b"world");
+        assert_eq!(
+            hash_ctx_finalize(ctx).unwrap().as_ref(),
+            helloworld.as_ref()
+        );
+        assert_eq!(
+            hash_ctx_finalize(ctx_d).unwrap().as_ref(),
+            hellobuddy.as_ref()
+        );
+    }
+}
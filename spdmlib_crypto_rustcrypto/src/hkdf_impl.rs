@@ -0,0 +1,110 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha384, Sha512};
+use spdmlib::crypto::SpdmHkdf;
+use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct, SPDM_MAX_HASH_SIZE};
+
+pub static DEFAULT: SpdmHkdf = SpdmHkdf {
+    hkdf_extract_cb: hkdf_extract,
+    hkdf_expand_cb: hkdf_expand,
+};
+
+fn hkdf_extract(hash_algo: SpdmBaseHashAlgo, salt: &[u8], ikm: &[u8]) -> Option<SpdmDigestStruct> {
+    match hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => Some(SpdmDigestStruct::from(
+            &Hkdf::<Sha256>::extract(Some(salt), ikm).0[..],
+        )),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => Some(SpdmDigestStruct::from(
+            &Hkdf::<Sha384>::extract(Some(salt), ikm).0[..],
+        )),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => Some(SpdmDigestStruct::from(
+            &Hkdf::<Sha512>::extract(Some(salt), ikm).0[..],
+        )),
+        _ => None,
+    }
+}
+
+fn hkdf_expand(
+    hash_algo: SpdmBaseHashAlgo,
+    pk: &[u8],
+    info: &[u8],
+    out_size: u16,
+) -> Option<SpdmDigestStruct> {
+    let hash_size = match hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256
+        | SpdmBaseHashAlgo::TPM_ALG_SHA_384
+        | SpdmBaseHashAlgo::TPM_ALG_SHA_512 => hash_algo.get_size() as usize,
+        _ => return None,
+    };
+    if pk.len() != hash_size || out_size as usize > SPDM_MAX_HASH_SIZE {
+        return None;
+    }
+
+    let mut ret = SpdmDigestStruct {
+        data_size: out_size,
+        ..Default::default()
+    };
+    let okm = &mut ret.data[..out_size as usize];
+    let res = match hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => Hkdf::<Sha256>::from_prk(pk).ok()?.expand(info, okm),
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => Hkdf::<Sha384>::from_prk(pk).ok()?.expand(info, okm),
+        _ => Hkdf::<Sha512>::from_prk(pk).ok()?.expand(info, okm),
+    };
+    match res {
+        Ok(()) => Some(ret),
+        Err(_) => None,
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_hkdf_extract() {
+        // RFC 5869 test case 3, an empty salt is a hash length of zeros
+        let ikm = [0x0bu8; 22];
+        let prk = hkdf_extract(SpdmBaseHashAlgo::TPM_ALG_SHA_256, &[], &ikm).unwrap();
+        assert_eq!(
+            prk.as_ref(),
+            &[
+                0x19, 0xef, 0x24, 0xa3, 0x2c, 0x71, 0x7b, 0x16, 0x7f, 0x33, 0xa9, 0x1d, 0x6f, 0x64,
+                0x8b, 0xdf, 0x96, 0x59, 0x67, 0x76, 0xaf, 0xdb, 0x63, 0x77, 0xac, 0x43, 0x4c, 0x1c,
+                0x29, 0x3c, 0xcb, 0x04,
+            ][..]
+        );
+        assert_eq!(
+            prk.as_ref(),
+            hkdf_extract(SpdmBaseHashAlgo::TPM_ALG_SHA_256, &[0u8; 32], &ikm)
+                .unwrap()
+                .as_ref()
+        );
+
+        assert!(hkdf_extract(SpdmBaseHashAlgo::empty(), &[], &ikm).is_none());
+    }
+
+    #[test]
+    fn test_case0_hkdf_expand() {
+        // RFC 5869 test case 1
+        let prk = [
+            0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b,
+            0xba, 0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a,
+            0xd7, 0xc2, 0xb3, 0xe5,
+        ];
+        let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+        let okm = hkdf_expand(SpdmBaseHashAlgo::TPM_ALG_SHA_256, &prk, &info, 42).unwrap();
+        assert_eq!(
+            okm.as_ref(),
+            &[
+                0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+                0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+                0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+            ][..]
+        );
+
+        assert!(hkdf_expand(SpdmBaseHashAlgo::TPM_ALG_SHA_384, &prk, &info, 48).is_none());
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use spdmlib::crypto::SpdmHmac;
+use spdmlib::error::{spdm_err, spdm_result_err, SpdmResult};
+use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmDigestStruct};
+
+pub static DEFAULT: SpdmHmac = SpdmHmac {
+    hmac_cb: hmac,
+    hmac_verify_cb: hmac_verify,
+};
+
+fn hmac(base_hash_algo: SpdmBaseHashAlgo, key: &[u8], data: &[u8]) -> Option<SpdmDigestStruct> {
+    match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => {
+            let mut ctx = Hmac::<Sha256>::new_from_slice(key).ok()?;
+            ctx.update(data);
+            Some(SpdmDigestStruct::from(&ctx.finalize().into_bytes()[..]))
+        }
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => {
+            let mut ctx = Hmac::<Sha384>::new_from_slice(key).ok()?;
+            ctx.update(data);
+            Some(SpdmDigestStruct::from(&ctx.finalize().into_bytes()[..]))
+        }
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => {
+            let mut ctx = Hmac::<Sha512>::new_from_slice(key).ok()?;
+            ctx.update(data);
+            Some(SpdmDigestStruct::from(&ctx.finalize().into_bytes()[..]))
+        }
+        _ => None,
+    }
+}
+
+fn hmac_verify(
+    base_hash_algo: SpdmBaseHashAlgo,
+    key: &[u8],
+    data: &[u8],
+    hmac: &SpdmDigestStruct,
+) -> SpdmResult {
+    let res = match base_hash_algo {
+        SpdmBaseHashAlgo::TPM_ALG_SHA_256 => {
+            let mut ctx = Hmac::<Sha256>::new_from_slice(key).map_err(|_| spdm_err!(EINVAL))?;
+            ctx.update(data);
+            ctx.verify_slice(hmac.as_ref())
+        }
+        SpdmBaseHashAlgo::TPM_ALG_SHA_384 => {
+            let mut ctx = Hmac::<Sha384>::new_from_slice(key).map_err(|_| spdm_err!(EINVAL))?;
+            ctx.update(data);
+            ctx.verify_slice(hmac.as_ref())
+        }
+        SpdmBaseHashAlgo::TPM_ALG_SHA_512 => {
+            let mut ctx = Hmac::<Sha512>::new_from_slice(key).map_err(|_| spdm_err!(EINVAL))?;
+            ctx.update(data);
+            ctx.verify_slice(hmac.as_ref())
+        }
+        _ => return spdm_result_err!(EINVAL),
+    };
+    match res {
+        Ok(()) => Ok(()),
+        Err(_) => spdm_result_err!(EFAULT),
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_hmac() {
+        let key = &[10u8; 48];
+        let data = &[20u8; 64];
+        let hmac_data = hmac(SpdmBaseHashAlgo::TPM_ALG_SHA_384, key, data).unwrap();
+        assert_eq!(hmac_data.data_size, 48);
+        assert!(hmac_verify(SpdmBaseHashAlgo::TPM_ALG_SHA_384, key, data, &hmac_data).is_ok());
+
+        let data = &[21u8; 64];
+        assert!(hmac_verify(SpdmBaseHashAlgo::TPM_ALG_SHA_384, key, data, &hmac_data).is_err());
+    }
+}
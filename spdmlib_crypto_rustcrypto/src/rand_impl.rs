@@ -0,0 +1,30 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use rand_core::{OsRng, RngCore};
+use spdmlib::crypto::SpdmCryptoRandom;
+use spdmlib::error::{spdm_result_err, SpdmResult};
+
+pub static DEFAULT: SpdmCryptoRandom = SpdmCryptoRandom {
+    get_random_cb: get_random,
+};
+
+fn get_random(data: &mut [u8]) -> SpdmResult<usize> {
+    match OsRng.try_fill_bytes(data) {
+        Ok(()) => Ok(data.len()),
+        Err(_) => spdm_result_err!(ESEC),
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_get_random() {
+        let data = &mut [0u8; 100];
+        assert_eq!(get_random(data).unwrap(), 100);
+        assert_ne!(data, &[0u8; 100]);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+//! Security Send / Security Receive command blocks of each storage
+//! interface for a StorageTransportHeader.
+
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+use crate::header::StorageTransportHeader;
+
+pub const NVME_ADMIN_SECURITY_SEND: u8 = 0x81;
+pub const NVME_ADMIN_SECURITY_RECEIVE: u8 = 0x82;
+pub const SCSI_SECURITY_PROTOCOL_IN: u8 = 0xA2;
+pub const SCSI_SECURITY_PROTOCOL_OUT: u8 = 0xB5;
+pub const SCSI_SECURITY_PROTOCOL_CDB_SIZE: usize = 12;
+pub const ATA_TRUSTED_RECEIVE: u8 = 0x5C;
+pub const ATA_TRUSTED_SEND: u8 = 0x5E;
+pub const ATA_TRUSTED_BLOCK_SIZE: u32 = 512;
+
+/// NVMe Security Send / Security Receive admin command, the dwords not
+/// listed here are zero.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StorageNvmeSecurityCommand {
+    pub opcode: u8,
+    pub cdw10: u32, // SECP | SPSP1 | SPSP0 | NSSF
+    pub cdw11: u32, // transfer length / allocation length
+}
+
+/// ATA TRUSTED SEND / TRUSTED RECEIVE, transfer length in 512 byte blocks.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StorageAtaTrustedCommand {
+    pub command: u8,
+    pub features: u8,
+    pub count: u8,
+    pub lba: u32, // 24 bits
+}
+
+pub fn storage_nvme_security_command(
+    header: &StorageTransportHeader,
+    send: bool,
+) -> StorageNvmeSecurityCommand {
+    StorageNvmeSecurityCommand {
+        opcode: if send {
+            NVME_ADMIN_SECURITY_SEND
+        } else {
+            NVME_ADMIN_SECURITY_RECEIVE
+        },
+        cdw10: ((header.security_protocol as u32) << 24) | ((header.sp_specific() as u32) << 8),
+        cdw11: header.transfer_length,
+    }
+}
+
+/// SECURITY PROTOCOL OUT / IN CDB with INC_512 clear, the length is in
+/// bytes.
+pub fn storage_scsi_security_cdb(
+    header: &StorageTransportHeader,
+    send: bool,
+) -> [u8; SCSI_SECURITY_PROTOCOL_CDB_SIZE] {
+    let mut cdb = [0u8; SCSI_SECURITY_PROTOCOL_CDB_SIZE];
+    cdb[0] = if send {
+        SCSI_SECURITY_PROTOCOL_OUT
+    } else {
+        SCSI_SECURITY_PROTOCOL_IN
+    };
+    cdb[1] = header.security_protocol;
+    cdb[2..4].copy_from_slice(&header.sp_specific().to_be_bytes());
+    cdb[6..10].copy_from_slice(&header.transfer_length.to_be_bytes());
+    cdb
+}
+
+/// Fails if the transfer length does not fit the 16 bit block count, the
+/// data buffer is padded to a multiple of ATA_TRUSTED_BLOCK_SIZE.
+pub fn storage_ata_trusted_command(
+    header: &StorageTransportHeader,
+    send: bool,
+) -> SpdmResult<StorageAtaTrustedCommand> {
+    let blocks = (header.transfer_length + ATA_TRUSTED_BLOCK_SIZE - 1) / ATA_TRUSTED_BLOCK_SIZE;
+    if blocks > u16::MAX as u32 {
+        return spdm_result_err!(EINVAL);
+    }
+    Ok(StorageAtaTrustedCommand {
+        command: if send {
+            ATA_TRUSTED_SEND
+        } else {
+            ATA_TRUSTED_RECEIVE
+        },
+        features: header.security_protocol,
+        count: (blocks & 0xFF) as u8,
+        lba: ((header.sp_specific() as u32) << 8) | (blocks >> 8),
+    })
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::header::*;
+
+    #[test]
+    fn test_case0_storage_commands() {
+        let header = StorageTransportHeader {
+            security_protocol: STORAGE_SECURITY_PROTOCOL_DMTF_SPDM,
+            connection_id: 2,
+            operation: StorageSpdmOperation::StorageSpdmOperationMessage,
+            transfer_length: 0x1234,
+        };
+
+        assert_eq!(
+            storage_nvme_security_command(&header, true),
+            StorageNvmeSecurityCommand {
+                opcode: NVME_ADMIN_SECURITY_SEND,
+                cdw10: 0xE805_0200,
+                cdw11: 0x1234,
+            }
+        );
+        assert_eq!(
+            storage_nvme_security_command(&header, false).opcode,
+            NVME_ADMIN_SECURITY_RECEIVE
+        );
+
+        assert_eq!(
+            storage_scsi_security_cdb(&header, false),
+            [0xA2, 0xE8, 0x05, 0x02, 0, 0, 0x00, 0x00, 0x12, 0x34, 0, 0]
+        );
+
+        assert_eq!(
+            storage_ata_trusted_command(&header, true).unwrap(),
+            StorageAtaTrustedCommand {
+                command: ATA_TRUSTED_SEND,
+                features: 0xE8,
+                count: 0x0A,
+                lba: 0x05_02_00,
+            }
+        );
+
+        let header = StorageTransportHeader {
+            transfer_length: 0x0200_0000,
+            ..header
+        };
+        assert!(storage_ata_trusted_command(&header, false).is_err());
+    }
+}
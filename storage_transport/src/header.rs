@@ -0,0 +1,310 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use codec::enum_builder;
+use codec::{Codec, Reader, Writer};
+use spdmlib::common::SpdmTransportEncap;
+use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
+
+/// Security Protocol value the DMTF assigned to SPDM (DSP0286).
+pub const STORAGE_SECURITY_PROTOCOL_DMTF_SPDM: u8 = 0xE8;
+pub const STORAGE_SPDM_BINDING_VERSION: u16 = 0x1000;
+pub const STORAGE_MAX_CONNECTION_ID: u8 = 0x03;
+pub const STORAGE_TRANSPORT_HEADER_SIZE: usize = 8;
+
+enum_builder! {
+    @U8
+    EnumName: StorageSpdmOperation;
+    EnumVal{
+        StorageSpdmOperationDiscovery => 0x01,
+        StorageSpdmOperationPendingInfo => 0x02,
+        StorageSpdmOperationMessage => 0x05,
+        StorageSpdmOperationSecuredMessage => 0x06
+    }
+}
+
+/// Parameters of the Security Send or Security Receive command carrying
+/// one transport message. The SP Specific field holds the SPDM operation
+/// in its high byte and the connection ID in its low byte.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StorageTransportHeader {
+    pub security_protocol: u8,
+    pub connection_id: u8,
+    pub operation: StorageSpdmOperation,
+    pub transfer_length: u32, // in bytes
+}
+
+impl StorageTransportHeader {
+    pub fn sp_specific(&self) -> u16 {
+        ((self.operation.get_u8() as u16) << 8) | (self.connection_id as u16)
+    }
+}
+
+impl Codec for StorageTransportHeader {
+    fn encode(&self, bytes: &mut Writer) {
+        self.security_protocol.encode(bytes);
+        self.sp_specific().encode(bytes);
+        0u8.encode(bytes);
+        self.transfer_length.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<StorageTransportHeader> {
+        let security_protocol = u8::read(r)?;
+        // SP Specific is little endian: SPSP0 first, then SPSP1
+        let connection_id = u8::read(r)?;
+        let operation = StorageSpdmOperation::read(r)?;
+        u8::read(r)?;
+        let transfer_length = u32::read(r)?;
+        if connection_id > STORAGE_MAX_CONNECTION_ID {
+            return None;
+        }
+        Some(StorageTransportHeader {
+            security_protocol,
+            connection_id,
+            operation,
+            transfer_length,
+        })
+    }
+}
+
+/// Data returned by Security Receive with the Discovery operation.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StorageSpdmDiscoveryResponse {
+    pub binding_version: u16,
+    pub max_connection_id: u8,
+    pub supported_operations: [u8; 8], // bitmap indexed by operation code
+}
+
+impl StorageSpdmDiscoveryResponse {
+    pub fn supports(&self, operation: StorageSpdmOperation) -> bool {
+        let operation = operation.get_u8() as usize;
+        operation < 64 && self.supported_operations[operation / 8] & (1 << (operation % 8)) != 0
+    }
+}
+
+impl Codec for StorageSpdmDiscoveryResponse {
+    fn encode(&self, bytes: &mut Writer) {
+        16u16.encode(bytes); // data length
+        self.binding_version.encode(bytes);
+        (self.max_connection_id & STORAGE_MAX_CONNECTION_ID).encode(bytes);
+        for _ in 0..3 {
+            0u8.encode(bytes);
+        }
+        for supported_operations in self.supported_operations.iter() {
+            supported_operations.encode(bytes);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<StorageSpdmDiscoveryResponse> {
+        let data_length = u16::read(r)?;
+        if data_length < 16 {
+            return None;
+        }
+        let binding_version = u16::read(r)?;
+        let max_connection_id = u8::read(r)? & STORAGE_MAX_CONNECTION_ID;
+        for _ in 0..3 {
+            u8::read(r)?;
+        }
+        let mut supported_operations = [0u8; 8];
+        for supported_operation in supported_operations.iter_mut() {
+            *supported_operation = u8::read(r)?;
+        }
+        Some(StorageSpdmDiscoveryResponse {
+            binding_version,
+            max_connection_id,
+            supported_operations,
+        })
+    }
+}
+
+/// SPDM over the storage Security Send and Security Receive commands.
+///
+/// A transport message is a StorageTransportHeader followed by the SPDM
+/// message. The device IO turns the header into the NVMe, SCSI or ATA
+/// command (see the command module) and, on receive, reports the number
+/// of bytes the device returned in transfer_length.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StorageTransportEncap {
+    pub connection_id: u8,
+}
+
+impl SpdmTransportEncap for StorageTransportEncap {
+    fn encap(
+        &mut self,
+        spdm_buffer: &[u8],
+        transport_buffer: &mut [u8],
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        let payload_len = spdm_buffer.len();
+        if transport_buffer.len() < STORAGE_TRANSPORT_HEADER_SIZE + payload_len {
+            return spdm_result_err!(EINVAL);
+        }
+        transport_buffer
+            [STORAGE_TRANSPORT_HEADER_SIZE..(STORAGE_TRANSPORT_HEADER_SIZE + payload_len)]
+            .copy_from_slice(spdm_buffer);
+        self.encap_in_place(transport_buffer, payload_len, secured_message)
+    }
+
+    fn decap(
+        &mut self,
+        transport_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        let (offset, payload_size, secured_message) = self.decap_in_place(transport_buffer)?;
+        if spdm_buffer.len() < payload_size {
+            return spdm_result_err!(EINVAL);
+        }
+        spdm_buffer[..payload_size]
+            .copy_from_slice(&transport_buffer[offset..(offset + payload_size)]);
+        Ok((payload_size, secured_message))
+    }
+
+    fn get_encap_header_size(&mut self) -> Option<usize> {
+        Some(STORAGE_TRANSPORT_HEADER_SIZE)
+    }
+
+    fn encap_in_place(
+        &mut self,
+        transport_buffer: &mut [u8],
+        spdm_size: usize,
+        secured_message: bool,
+    ) -> SpdmResult<usize> {
+        if transport_buffer.len() < STORAGE_TRANSPORT_HEADER_SIZE + spdm_size {
+            return spdm_result_err!(EINVAL);
+        }
+        let mut writer = Writer::init(&mut transport_buffer[..STORAGE_TRANSPORT_HEADER_SIZE]);
+        let storage_header = StorageTransportHeader {
+            security_protocol: STORAGE_SECURITY_PROTOCOL_DMTF_SPDM,
+            connection_id: self.connection_id,
+            operation: if secured_message {
+                StorageSpdmOperation::StorageSpdmOperationSecuredMessage
+            } else {
+                StorageSpdmOperation::StorageSpdmOperationMessage
+            },
+            transfer_length: spdm_size as u32,
+        };
+        storage_header.encode(&mut writer);
+        Ok(STORAGE_TRANSPORT_HEADER_SIZE + spdm_size)
+    }
+
+    fn decap_in_place(&mut self, transport_buffer: &[u8]) -> SpdmResult<(usize, usize, bool)> {
+        let mut reader = Reader::init(transport_buffer);
+        let storage_header =
+            StorageTransportHeader::read(&mut reader).ok_or_else(|| spdm_err!(EIO))?;
+        if storage_header.security_protocol != STORAGE_SECURITY_PROTOCOL_DMTF_SPDM
+            || storage_header.connection_id != self.connection_id
+        {
+            return spdm_result_err!(EINVAL);
+        }
+        let secured_message = match storage_header.operation {
+            StorageSpdmOperation::StorageSpdmOperationMessage => false,
+            StorageSpdmOperation::StorageSpdmOperationSecuredMessage => true,
+            _ => return spdm_result_err!(EINVAL),
+        };
+        let header_size = reader.used();
+        let payload_size = storage_header.transfer_length as usize;
+        if transport_buffer.len() < header_size + payload_size {
+            return spdm_result_err!(EIO);
+        }
+        Ok((header_size, payload_size, secured_message))
+    }
+
+    fn encap_app(
+        &mut self,
+        spdm_buffer: &[u8],
+        app_buffer: &mut [u8],
+        _is_app_message: bool,
+    ) -> SpdmResult<usize> {
+        if app_buffer.len() < spdm_buffer.len() {
+            return spdm_result_err!(EINVAL);
+        }
+        app_buffer[0..spdm_buffer.len()].copy_from_slice(spdm_buffer);
+        Ok(spdm_buffer.len())
+    }
+
+    fn decap_app(
+        &mut self,
+        app_buffer: &[u8],
+        spdm_buffer: &mut [u8],
+    ) -> SpdmResult<(usize, bool)> {
+        if spdm_buffer.len() < app_buffer.len() {
+            return spdm_result_err!(EINVAL);
+        }
+        spdm_buffer[0..app_buffer.len()].copy_from_slice(app_buffer);
+        Ok((app_buffer.len(), false))
+    }
+
+    fn get_sequence_number_count(&mut self) -> u8 {
+        0
+    }
+    fn get_max_random_count(&mut self) -> u16 {
+        0
+    }
+}
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case0_storage_encap_decap() {
+        let mut storage_transport_encap = StorageTransportEncap { connection_id: 1 };
+        let spdm_message = [0x12u8, 0x84, 0x00, 0x00];
+        let mut transport_buffer = [0u8; 64];
+        let used = storage_transport_encap
+            .encap(&spdm_message, &mut transport_buffer, true)
+            .unwrap();
+        assert_eq!(used, 12);
+        assert_eq!(
+            &transport_buffer[..8],
+            &[0xE8, 0x01, 0x06, 0x00, 0x04, 0x00, 0x00, 0x00]
+        );
+
+        let mut spdm_buffer = [0u8; 64];
+        let (size, secured_message) = storage_transport_encap
+            .decap(&transport_buffer[..used], &mut spdm_buffer)
+            .unwrap();
+        assert!(secured_message);
+        assert_eq!(&spdm_buffer[..size], &spdm_message[..]);
+
+        // the device returned less than transfer_length
+        assert!(storage_transport_encap
+            .decap(&transport_buffer[..used - 1], &mut spdm_buffer)
+            .is_err());
+        // another connection
+        let mut other_transport_encap = StorageTransportEncap { connection_id: 0 };
+        assert!(other_transport_encap
+            .decap(&transport_buffer[..used], &mut spdm_buffer)
+            .is_err());
+        // not SPDM
+        transport_buffer[0] = 0x01;
+        assert!(storage_transport_encap
+            .decap(&transport_buffer[..used], &mut spdm_buffer)
+            .is_err());
+    }
+
+    #[test]
+    fn test_case1_storage_discovery_response() {
+        let mut value = StorageSpdmDiscoveryResponse {
+            binding_version: STORAGE_SPDM_BINDING_VERSION,
+            max_connection_id: 1,
+            ..Default::default()
+        };
+        value.supported_operations[0] = 0x66;
+        let u8_slice = &mut [0u8; 16];
+        let mut writer = Writer::init(u8_slice);
+        value.encode(&mut writer);
+        assert_eq!(writer.used(), 16);
+
+        let mut reader = Reader::init(u8_slice);
+        let response = StorageSpdmDiscoveryResponse::read(&mut reader).unwrap();
+        assert_eq!(response.binding_version, STORAGE_SPDM_BINDING_VERSION);
+        assert_eq!(response.max_connection_id, 1);
+        assert!(response.supports(StorageSpdmOperation::StorageSpdmOperationDiscovery));
+        assert!(response.supports(StorageSpdmOperation::StorageSpdmOperationSecuredMessage));
+        assert!(!response.supports(StorageSpdmOperation::Unknown(0x03)));
+        assert!(!response.supports(StorageSpdmOperation::Unknown(0x80)));
+    }
+}
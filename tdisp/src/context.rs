@@ -5,7 +5,7 @@
 pub use crate::message::*;
 use crate::{
     common::{InternalError, TdispResult},
-    config::MAX_TDISP_MESSAGE_SIZE,
+    config::{MAX_MESSAGE_INTERNAL_BUFFER_SIZE, MAX_TDISP_MESSAGE_SIZE},
     device::TdispConfiguration,
     state_machine::{TDIState, TdispStateMachine},
 };
@@ -23,6 +23,13 @@ pub struct TdispContext<'a> {
 
     pub spdm_session_id: u32,
 
+    // nonce of the last LOCK_INTERFACE_RESPONSE, START_INTERFACE_REQUEST echoes it
+    pub start_interface_nonce: [u8; NONCE_LENGTH],
+
+    // TDI report the requester read with GET_DEVICE_INTERFACE_REPORT
+    pub interface_report: [u8; MAX_MESSAGE_INTERNAL_BUFFER_SIZE],
+    pub interface_report_length: usize,
+
     // volatile
     pub request_message: [u8; MAX_TDISP_MESSAGE_SIZE],
     pub request_code: TdispRequestResponseCode,
@@ -41,6 +48,9 @@ impl<'a> TdispContext<'a> {
             state_machine: TdispStateMachine::new(),
             configuration,
             spdm_session_id,
+            start_interface_nonce: [0u8; NONCE_LENGTH],
+            interface_report: [0u8; MAX_MESSAGE_INTERNAL_BUFFER_SIZE],
+            interface_report_length: 0,
             request_message: [0u8; MAX_TDISP_MESSAGE_SIZE],
             request_code: TdispRequestResponseCode::Unknown(0),
             response_message: [0u8; MAX_TDISP_MESSAGE_SIZE],
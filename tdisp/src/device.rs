@@ -15,4 +15,7 @@ pub trait TdispConfiguration: Debug {
     fn erase_confidential_config(&mut self) -> TdispResult;
 
     fn track_config_changes(&mut self) -> TdispResult;
+
+    // write the TDI report into report, return its length
+    fn get_interface_report(&mut self, report: &mut [u8]) -> TdispResult<usize>;
 }
@@ -14,3 +14,6 @@ pub mod state_machine;
 pub mod tdisp_codec;
 pub mod tdisp_requester;
 pub mod tdisp_responder;
+
+#[cfg(test)]
+mod testlib;
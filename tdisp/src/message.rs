@@ -19,6 +19,12 @@ use crate::{
 type ProtocolId = u8;
 pub const PROTOCOL_ID: ProtocolId = 0x1;
 
+// PROTOCOL_ID, TDISP version, message type, reserved and INTERFACE_ID
+pub const TDISP_MESSAGE_HEADER_SIZE: usize = 13;
+// what fits a DEVICE_INTERFACE_REPORT after PORTION_LENGTH and REMAINDER_LENGTH
+pub const MAX_DEVICE_INTERFACE_REPORT_PORTION_LENGTH: usize =
+    spdmlib::config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE - TDISP_MESSAGE_HEADER_SIZE - 4;
+
 enum_builder! {
     @U8
     EnumName: TdispRequestResponseCode;
@@ -229,6 +235,7 @@ pub struct TdispMessageHeader {
 
 impl TdispCodec for TdispMessageHeader {
     fn tdisp_encode(&self, context: &mut TdispContext, bytes: &mut Writer) {
+        PROTOCOL_ID.encode(bytes);
         self.tdisp_version.encode(bytes);
         self.message_type.encode(bytes);
         0u16.encode(bytes); // reserved
@@ -236,6 +243,9 @@ impl TdispCodec for TdispMessageHeader {
     }
 
     fn tdisp_read(context: &mut TdispContext, r: &mut Reader) -> Option<Self> {
+        if ProtocolId::read(r)? != PROTOCOL_ID {
+            return None;
+        }
         let tdisp_version = TdispVersion::read(r)?;
         let message_type = TdispRequestResponseCode::read(r)?;
         u16::read(r)?;
@@ -251,6 +261,7 @@ impl TdispCodec for TdispMessageHeader {
 
 impl Codec for TdispMessageHeader {
     fn encode(&self, bytes: &mut Writer) {
+        PROTOCOL_ID.encode(bytes);
         self.tdisp_version.encode(bytes);
         self.message_type.encode(bytes);
         0u16.encode(bytes); // reserved
@@ -258,6 +269,9 @@ impl Codec for TdispMessageHeader {
     }
 
     fn read(r: &mut Reader) -> Option<Self> {
+        if ProtocolId::read(r)? != PROTOCOL_ID {
+            return None;
+        }
         let tdisp_version = TdispVersion::read(r)?;
         let message_type = TdispRequestResponseCode::read(r)?;
         u16::read(r)?;
@@ -309,13 +323,11 @@ where
     T: TdispCodec + Default + Debug,
 {
     fn tdisp_encode(&self, context: &mut TdispContext, bytes: &mut Writer) {
-        PROTOCOL_ID.encode(bytes);
         self.tdisp_message_header.tdisp_encode(context, bytes);
         self.tdisp_message_payload.tdisp_encode(context, bytes);
     }
 
     fn tdisp_read(context: &mut TdispContext, r: &mut Reader) -> Option<Self> {
-        let _ = ProtocolId::read(r)?; // protocol id
         let tdisp_message_header = TdispMessageHeader::tdisp_read(context, r)?;
         let tdisp_message_payload = T::tdisp_read(context, r)?;
 
@@ -487,16 +499,21 @@ impl TdispCodec for MessagePayloadRequestLockInterface {
 
 #[derive(Debug, Default)]
 pub struct MessagePayloadResponseLockInterface {
-    pub start_interface_nonce: u128,
+    pub start_interface_nonce: [u8; NONCE_LENGTH],
 }
 
 impl TdispCodec for MessagePayloadResponseLockInterface {
     fn tdisp_encode(&self, _context: &mut TdispContext, bytes: &mut Writer) {
-        self.start_interface_nonce.encode(bytes);
+        for b in self.start_interface_nonce.iter().take(NONCE_LENGTH) {
+            b.encode(bytes);
+        }
     }
 
     fn tdisp_read(_context: &mut TdispContext, r: &mut Reader) -> Option<Self> {
-        let start_interface_nonce = u128::read(r)?;
+        let mut start_interface_nonce: [u8; NONCE_LENGTH] = [0u8; NONCE_LENGTH];
+        for b in start_interface_nonce.iter_mut().take(NONCE_LENGTH) {
+            *b = u8::read(r)?;
+        }
         Some(MessagePayloadResponseLockInterface {
             start_interface_nonce,
         })
@@ -1040,3 +1057,358 @@ impl TdispCodec for MessagePayloadResponseVDM {
         })
     }
 }
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::state_machine::TDIState;
+    use crate::testlib::FakeTdispConfiguration;
+
+    #[test]
+    fn test_case0_tdisp_message_header() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let value = TdispMessageHeader {
+            tdisp_version: 0x10,
+            message_type: TdispRequestResponseCode::RequestLockInterfaceRequest,
+            interface_id: InterfaceId {
+                function_id: FunctionId {
+                    requester_id: 0x1234,
+                    requester_segment: 5,
+                    requester_segment_valid: true,
+                },
+            },
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), TDISP_MESSAGE_HEADER_SIZE);
+        assert_eq!(
+            &u8_slice[..TDISP_MESSAGE_HEADER_SIZE],
+            &[1, 0x10, 0x83, 0, 0, 0x34, 0x12, 5, 1, 0, 0, 0, 0]
+        );
+
+        let mut reader = Reader::init(u8_slice);
+        let tmh = TdispMessageHeader::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(tmh.tdisp_version, 0x10);
+        assert_eq!(
+            tmh.message_type,
+            TdispRequestResponseCode::RequestLockInterfaceRequest
+        );
+        assert_eq!(tmh.interface_id.function_id.requester_id, 0x1234);
+        assert_eq!(
+            tmh.interface_id.function_id.get_requester_segment(),
+            Some(5)
+        );
+        // the plain codec reads the same bytes
+        let tmh = TdispMessageHeader::read_bytes(u8_slice).unwrap();
+        assert_eq!(
+            tmh.message_type,
+            TdispRequestResponseCode::RequestLockInterfaceRequest
+        );
+
+        // not a TDISP message
+        u8_slice[0] = 2;
+        let mut reader = Reader::init(u8_slice);
+        assert!(TdispMessageHeader::tdisp_read(&mut context, &mut reader).is_none());
+        // neither is a reserved requester segment valid byte
+        u8_slice[0] = 1;
+        u8_slice[8] = 2;
+        let mut reader = Reader::init(u8_slice);
+        assert!(TdispMessageHeader::tdisp_read(&mut context, &mut reader).is_none());
+    }
+
+    #[test]
+    fn test_case1_tdisp_version() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let value = MessagePayloadResponseVersion {
+            version_num_count: 2,
+            version_num_entry: [0x10, 0x11],
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 3);
+        assert_eq!(&u8_slice[..3], &[2, 0x10, 0x11]);
+
+        let mut reader = Reader::init(&u8_slice[..3]);
+        let version = MessagePayloadResponseVersion::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(version.version_num_count, 2);
+        assert_eq!(version.version_num_entry, [0x10, 0x11]);
+
+        // fewer entries than announced
+        let mut reader = Reader::init(&u8_slice[..2]);
+        assert!(MessagePayloadResponseVersion::tdisp_read(&mut context, &mut reader).is_none());
+    }
+
+    #[test]
+    fn test_case2_lock_interface() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let value = MessagePayloadRequestLockInterface {
+            flags: LockInterfaceFlag::NO_FW_UPDATE | LockInterfaceFlag::LOCK_MSIX,
+            stream_id_for_default_stream: 3,
+            mmio_reporting_offset: 0x1_0000_0000,
+            bind_p2p_address_mask: 0xffff_0000,
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 20);
+        assert_eq!(&u8_slice[..4], &[0x05, 0, 3, 0]);
+
+        let mut reader = Reader::init(&u8_slice[..20]);
+        let lock =
+            MessagePayloadRequestLockInterface::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            lock.flags,
+            LockInterfaceFlag::NO_FW_UPDATE | LockInterfaceFlag::LOCK_MSIX
+        );
+        assert_eq!(lock.stream_id_for_default_stream, 3);
+        assert_eq!(lock.mmio_reporting_offset, 0x1_0000_0000);
+        assert_eq!(lock.bind_p2p_address_mask, 0xffff_0000);
+
+        // undefined flag
+        u8_slice[1] = 0x80;
+        let mut reader = Reader::init(&u8_slice[..20]);
+        assert!(
+            MessagePayloadRequestLockInterface::tdisp_read(&mut context, &mut reader).is_none()
+        );
+
+        let value = MessagePayloadResponseLockInterface {
+            start_interface_nonce: [0x5a; NONCE_LENGTH],
+        };
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), NONCE_LENGTH);
+        let mut reader = Reader::init(&u8_slice[..NONCE_LENGTH]);
+        let lock =
+            MessagePayloadResponseLockInterface::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(lock.start_interface_nonce, [0x5a; NONCE_LENGTH]);
+        let mut reader = Reader::init(&u8_slice[..NONCE_LENGTH - 1]);
+        assert!(
+            MessagePayloadResponseLockInterface::tdisp_read(&mut context, &mut reader).is_none()
+        );
+    }
+
+    #[test]
+    fn test_case3_device_interface_report() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let mut value = TDIReport {
+            interface_info: InterfaceInfo::DMA_REQUESTS_WITHOUT_PASID,
+            msi_x_message_control: 0x8001,
+            mmio_range_count: 1,
+            device_specific_info_len: 3,
+            ..Default::default()
+        };
+        value.mmio_range[0] = MMIORange {
+            first_4k_page_with_offset_added: 0x10_0000,
+            number_of_4k_pages: 4,
+            range_attribute: MMIORangeAttribute::MSI_X_TABLE,
+            range_id: 1,
+        };
+        value.device_specific_info[..3].copy_from_slice(&[1, 2, 3]);
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        let report_length = writer.used();
+        assert_eq!(report_length, 14 + 16 + 4 + 3);
+
+        let mut reader = Reader::init(&u8_slice[..report_length]);
+        let report = TDIReport::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            report.interface_info,
+            InterfaceInfo::DMA_REQUESTS_WITHOUT_PASID
+        );
+        assert_eq!(report.msi_x_message_control, 0x8001);
+        assert_eq!(report.mmio_range_count, 1);
+        assert_eq!(
+            report.mmio_range[0].first_4k_page_with_offset_added,
+            0x10_0000
+        );
+        assert_eq!(report.mmio_range[0].number_of_4k_pages, 4);
+        assert_eq!(
+            report.mmio_range[0].range_attribute,
+            MMIORangeAttribute::MSI_X_TABLE
+        );
+        assert_eq!(report.mmio_range[0].range_id, 1);
+        assert_eq!(report.device_specific_info_len, 3);
+        assert_eq!(&report.device_specific_info[..3], &[1, 2, 3]);
+
+        // the report travels in portions
+        let mut value = MessagePayloadResponseDeviceInterfaceReport {
+            portion_length: 8,
+            reminder_length: (report_length - 8) as u16,
+            ..Default::default()
+        };
+        value.report_bytes[..8].copy_from_slice(&u8_slice[..8]);
+        let portion_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(portion_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 4 + 8);
+
+        let mut reader = Reader::init(&portion_slice[..12]);
+        let portion =
+            MessagePayloadResponseDeviceInterfaceReport::tdisp_read(&mut context, &mut reader)
+                .unwrap();
+        assert_eq!(portion.portion_length, 8);
+        assert_eq!(portion.reminder_length as usize, report_length - 8);
+        assert_eq!(&portion.report_bytes[..8], &u8_slice[..8]);
+        let mut reader = Reader::init(&portion_slice[..11]);
+        assert!(
+            MessagePayloadResponseDeviceInterfaceReport::tdisp_read(&mut context, &mut reader)
+                .is_none()
+        );
+
+        let value = MessagePayloadRequestGetDeviceInterfaceReport {
+            offset: 8,
+            length: 0x100,
+        };
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 4);
+        assert_eq!(&u8_slice[..4], &[8, 0, 0, 1]);
+        let mut reader = Reader::init(&u8_slice[..4]);
+        let request =
+            MessagePayloadRequestGetDeviceInterfaceReport::tdisp_read(&mut context, &mut reader)
+                .unwrap();
+        assert_eq!(request.offset, 8);
+        assert_eq!(request.length, 0x100);
+    }
+
+    #[test]
+    fn test_case4_device_interface_state() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let value = MessagePayloadResponseDeviceInterfaceState {
+            tdi_state: TdispStateMachine {
+                current_state: TDIState::Run,
+            },
+        };
+        let u8_slice = &mut [0u8; 8];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 1);
+        assert_eq!(u8_slice[0], 2);
+
+        let mut reader = Reader::init(&u8_slice[..1]);
+        let state =
+            MessagePayloadResponseDeviceInterfaceState::tdisp_read(&mut context, &mut reader)
+                .unwrap();
+        assert_eq!(state.tdi_state.current_state, TDIState::Run);
+    }
+
+    #[test]
+    fn test_case5_tdisp_error() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let value = MessagePayloadResponseTdispError {
+            error_code: GenericErrorResponseCode::InvalidNonce,
+            error_data: 0,
+            extended_error_data: ExtendedErrorData::default(),
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 8);
+        assert_eq!(&u8_slice[..8], &[0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+
+        let mut reader = Reader::init(&u8_slice[..8]);
+        let error =
+            MessagePayloadResponseTdispError::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(error.error_code, GenericErrorResponseCode::InvalidNonce);
+        assert_eq!(error.error_data, 0);
+
+        // vendor specific errors carry the vendor ID and error_data bytes
+        let mut value = MessagePayloadResponseTdispError {
+            error_code: GenericErrorResponseCode::VendorSpecificError,
+            error_data: 2,
+            extended_error_data: ExtendedErrorData {
+                registry_id: 1,
+                vendor_id_len: 2,
+                ..Default::default()
+            },
+        };
+        value.extended_error_data.vendor_id[..2].copy_from_slice(&[0x86, 0x80]);
+        value.extended_error_data.vendor_err_data[..2].copy_from_slice(&[0xde, 0xad]);
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        assert_eq!(writer.used(), 8 + 2 + 2 + 2);
+
+        let mut reader = Reader::init(&u8_slice[..14]);
+        let error =
+            MessagePayloadResponseTdispError::tdisp_read(&mut context, &mut reader).unwrap();
+        assert_eq!(
+            error.error_code,
+            GenericErrorResponseCode::VendorSpecificError
+        );
+        assert_eq!(error.extended_error_data.registry_id, 1);
+        assert_eq!(error.extended_error_data.vendor_id_len, 2);
+        assert_eq!(&error.extended_error_data.vendor_id[..2], &[0x86, 0x80]);
+        assert_eq!(
+            &error.extended_error_data.vendor_err_data[..2],
+            &[0xde, 0xad]
+        );
+        let mut reader = Reader::init(&u8_slice[..13]);
+        assert!(MessagePayloadResponseTdispError::tdisp_read(&mut context, &mut reader).is_none());
+    }
+
+    #[test]
+    fn test_case6_tdisp_message() {
+        let mut configuration = FakeTdispConfiguration::default();
+        let mut context = TdispContext::new(InterfaceId::default(), &mut configuration, 0);
+
+        let mut value = TdispMessage::<MessagePayloadRequestSetMmioAttribute>::default();
+        value.tdisp_message_header.tdisp_version = 0x10;
+        value.tdisp_message_header.message_type =
+            TdispRequestResponseCode::RequestSetMmioAttributeRequest;
+        value.tdisp_message_payload.mmio_range = MMIORange {
+            first_4k_page_with_offset_added: 0x20_0000,
+            number_of_4k_pages: 1,
+            range_attribute: MMIORangeAttribute::IS_NON_TEE_MEM,
+            range_id: 2,
+        };
+        let u8_slice = &mut [0u8; 64];
+        let mut writer = Writer::init(u8_slice);
+        value.tdisp_encode(&mut context, &mut writer);
+        let used = writer.used();
+        assert_eq!(used, TDISP_MESSAGE_HEADER_SIZE + 16);
+
+        let mut reader = Reader::init(&u8_slice[..used]);
+        let message = TdispMessage::<MessagePayloadRequestSetMmioAttribute>::tdisp_read(
+            &mut context,
+            &mut reader,
+        )
+        .unwrap();
+        assert_eq!(
+            message.tdisp_message_header.message_type,
+            TdispRequestResponseCode::RequestSetMmioAttributeRequest
+        );
+        let mmio_range = message.tdisp_message_payload.mmio_range;
+        assert_eq!(mmio_range.first_4k_page_with_offset_added, 0x20_0000);
+        assert_eq!(mmio_range.number_of_4k_pages, 1);
+        assert_eq!(
+            mmio_range.range_attribute,
+            MMIORangeAttribute::IS_NON_TEE_MEM
+        );
+        assert_eq!(mmio_range.range_id, 2);
+
+        // the payload must follow the header
+        let mut reader = Reader::init(&u8_slice[..used - 1]);
+        assert!(
+            TdispMessage::<MessagePayloadRequestSetMmioAttribute>::tdisp_read(
+                &mut context,
+                &mut reader
+            )
+            .is_none()
+        );
+    }
+}
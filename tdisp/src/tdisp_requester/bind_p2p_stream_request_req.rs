@@ -13,6 +13,7 @@ use spdmlib::{
 
 use crate::{
     common::{InternalError, TdispResult, PCI_VENDOR_ID_STRUCT},
+    config::MAX_TDISP_MESSAGE_SIZE,
     context::{MessagePayloadRequestBindP2pStream, TdispMessage, TdispRequestResponseCode},
     tdisp_codec::TdispCodec,
 };
@@ -38,29 +39,32 @@ impl<'a> TdispRequester<'a> {
             vendor_defined_req_payload,
         };
 
-        self.tdisp_requester_context
-            .request_message
-            .copy_from_slice(&vendor_defined_req_payload);
+        self.tdisp_requester_context.zero_messages();
+        self.tdisp_requester_context.request_message[..req_length as usize]
+            .copy_from_slice(&vendor_defined_req_payload[..req_length as usize]);
         self.tdisp_requester_context.request_code =
             TdispRequestResponseCode::RequestBindP2pStreamRequest;
 
         match spdm_requester.send_spdm_vendor_defined_request(
-            self.tdisp_requester_context.spdm_session_id,
+            self.tdisp_requester_context.spdm_session_id.into(),
             RegistryOrStandardsBodyID::PCISIG,
             PCI_VENDOR_ID_STRUCT,
             vdrp,
         ) {
             Ok(vdrp) => {
                 let VendorDefinedRspPayloadStruct {
-                    rsp_length: _,
+                    rsp_length,
                     vendor_defined_rsp_payload,
                 } = vdrp;
 
                 self.tdisp_requester_context.response_code =
                     TdispRequestResponseCode::ResponseBindP2pStreamResponse;
-                self.tdisp_requester_context
-                    .response_message
-                    .copy_from_slice(&vendor_defined_rsp_payload);
+                let rsp_length = rsp_length as usize;
+                if rsp_length > MAX_TDISP_MESSAGE_SIZE {
+                    return Err(InternalError::Violation);
+                }
+                self.tdisp_requester_context.response_message[..rsp_length]
+                    .copy_from_slice(&vendor_defined_rsp_payload[..rsp_length]);
 
                 self.handle_bind_p2p_stream_response(spdm_requester)
             }
@@ -13,8 +13,10 @@ use spdmlib::{
 
 use crate::{
     common::{InternalError, TdispResult, PCI_VENDOR_ID_STRUCT},
+    config::{MAX_MESSAGE_INTERNAL_BUFFER_SIZE, MAX_TDISP_MESSAGE_SIZE},
     context::{
-        MessagePayloadRequestGetDeviceInterfaceReport, TdispMessage, TdispRequestResponseCode,
+        MessagePayloadRequestGetDeviceInterfaceReport, MessagePayloadResponseDeviceInterfaceReport,
+        TdispMessage, TdispRequestResponseCode, MAX_DEVICE_INTERFACE_REPORT_PORTION_LENGTH,
     },
     tdisp_codec::TdispCodec,
 };
@@ -22,14 +24,42 @@ use crate::{
 use super::*;
 
 impl<'a> TdispRequester<'a> {
+    /// Read the whole TDI report into interface_report, one portion per
+    /// request.
     pub fn send_receive_get_device_interface_report(
         &mut self,
         spdm_requester: &mut RequesterContext,
     ) -> TdispResult {
+        self.tdisp_requester_context.interface_report_length = 0;
+        loop {
+            let offset = self.tdisp_requester_context.interface_report_length;
+            let remainder_length = self.send_receive_get_device_interface_report_portion(
+                spdm_requester,
+                offset as u16,
+                MAX_DEVICE_INTERFACE_REPORT_PORTION_LENGTH as u16,
+            )?;
+            if remainder_length == 0 {
+                return Ok(());
+            }
+            if self.tdisp_requester_context.interface_report_length == offset {
+                // no progress
+                return Err(InternalError::Violation);
+            }
+        }
+    }
+
+    fn send_receive_get_device_interface_report_portion(
+        &mut self,
+        spdm_requester: &mut RequesterContext,
+        offset: u16,
+        length: u16,
+    ) -> TdispResult<u16> {
         let mut tdisp_message =
             TdispMessage::<MessagePayloadRequestGetDeviceInterfaceReport>::default();
         tdisp_message.tdisp_message_header.interface_id = self.tdisp_requester_context.tdi;
         tdisp_message.tdisp_message_header.tdisp_version = self.tdisp_requester_context.version_sel;
+        tdisp_message.tdisp_message_payload.offset = offset;
+        tdisp_message.tdisp_message_payload.length = length;
         let mut vendor_defined_req_payload =
             [0u8; spdmlib::config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE];
         let mut writer = Writer::init(&mut vendor_defined_req_payload);
@@ -41,31 +71,34 @@ impl<'a> TdispRequester<'a> {
             vendor_defined_req_payload,
         };
 
-        self.tdisp_requester_context
-            .request_message
-            .copy_from_slice(&vendor_defined_req_payload);
+        self.tdisp_requester_context.zero_messages();
+        self.tdisp_requester_context.request_message[..req_length as usize]
+            .copy_from_slice(&vendor_defined_req_payload[..req_length as usize]);
         self.tdisp_requester_context.request_code =
             TdispRequestResponseCode::RequestGetDeviceInterfaceReport;
 
         match spdm_requester.send_spdm_vendor_defined_request(
-            self.tdisp_requester_context.spdm_session_id,
+            self.tdisp_requester_context.spdm_session_id.into(),
             RegistryOrStandardsBodyID::PCISIG,
             PCI_VENDOR_ID_STRUCT,
             vdrp,
         ) {
             Ok(vdrp) => {
                 let VendorDefinedRspPayloadStruct {
-                    rsp_length: _,
+                    rsp_length,
                     vendor_defined_rsp_payload,
                 } = vdrp;
 
                 self.tdisp_requester_context.response_code =
                     TdispRequestResponseCode::ResponseDeviceInterfaceReport;
-                self.tdisp_requester_context
-                    .response_message
-                    .copy_from_slice(&vendor_defined_rsp_payload);
+                let rsp_length = rsp_length as usize;
+                if rsp_length > MAX_TDISP_MESSAGE_SIZE {
+                    return Err(InternalError::Violation);
+                }
+                self.tdisp_requester_context.response_message[..rsp_length]
+                    .copy_from_slice(&vendor_defined_rsp_payload[..rsp_length]);
 
-                self.handle_get_device_interface_report_response(spdm_requester)
+                self.handle_get_device_interface_report_response(spdm_requester, length)
             }
             Err(_) => Err(InternalError::Unrecoverable),
         }
@@ -74,7 +107,21 @@ impl<'a> TdispRequester<'a> {
     fn handle_get_device_interface_report_response(
         &mut self,
         _spdm_requester: &mut RequesterContext,
-    ) -> TdispResult {
-        Ok(())
+        length: u16,
+    ) -> TdispResult<u16> {
+        let mprr: MessagePayloadResponseDeviceInterfaceReport =
+            self.read_tdisp_response(TdispRequestResponseCode::ResponseDeviceInterfaceReport)?;
+        let offset = self.tdisp_requester_context.interface_report_length;
+        let portion_length = mprr.portion_length as usize;
+        if portion_length > length as usize
+            || offset + portion_length + mprr.reminder_length as usize
+                > MAX_MESSAGE_INTERNAL_BUFFER_SIZE
+        {
+            return Err(InternalError::Violation);
+        }
+        self.tdisp_requester_context.interface_report[offset..offset + portion_length]
+            .copy_from_slice(&mprr.report_bytes[..portion_length]);
+        self.tdisp_requester_context.interface_report_length += portion_length;
+        Ok(mprr.reminder_length)
     }
 }
@@ -13,7 +13,11 @@ use spdmlib::{
 
 use crate::{
     common::{InternalError, TdispResult, PCI_VENDOR_ID_STRUCT},
-    context::{MessagePayloadRequestLockInterface, TdispMessage, TdispRequestResponseCode},
+    config::MAX_TDISP_MESSAGE_SIZE,
+    context::{
+        MessagePayloadRequestLockInterface, MessagePayloadResponseLockInterface, TdispMessage,
+        TdispRequestResponseCode,
+    },
     tdisp_codec::TdispCodec,
 };
 
@@ -38,29 +42,32 @@ impl<'a> TdispRequester<'a> {
             vendor_defined_req_payload,
         };
 
-        self.tdisp_requester_context
-            .request_message
-            .copy_from_slice(&vendor_defined_req_payload);
+        self.tdisp_requester_context.zero_messages();
+        self.tdisp_requester_context.request_message[..req_length as usize]
+            .copy_from_slice(&vendor_defined_req_payload[..req_length as usize]);
         self.tdisp_requester_context.request_code =
             TdispRequestResponseCode::RequestLockInterfaceRequest;
 
         match spdm_requester.send_spdm_vendor_defined_request(
-            self.tdisp_requester_context.spdm_session_id,
+            self.tdisp_requester_context.spdm_session_id.into(),
             RegistryOrStandardsBodyID::PCISIG,
             PCI_VENDOR_ID_STRUCT,
             vdrp,
         ) {
             Ok(vdrp) => {
                 let VendorDefinedRspPayloadStruct {
-                    rsp_length: _,
+                    rsp_length,
                     vendor_defined_rsp_payload,
                 } = vdrp;
 
                 self.tdisp_requester_context.response_code =
                     TdispRequestResponseCode::ResponseLockInterfaceResponse;
-                self.tdisp_requester_context
-                    .response_message
-                    .copy_from_slice(&vendor_defined_rsp_payload);
+                let rsp_length = rsp_length as usize;
+                if rsp_length > MAX_TDISP_MESSAGE_SIZE {
+                    return Err(InternalError::Violation);
+                }
+                self.tdisp_requester_context.response_message[..rsp_length]
+                    .copy_from_slice(&vendor_defined_rsp_payload[..rsp_length]);
 
                 self.handle_lock_interface_request_response(spdm_requester)
             }
@@ -72,6 +79,9 @@ impl<'a> TdispRequester<'a> {
         &mut self,
         _spdm_requester: &mut RequesterContext,
     ) -> TdispResult {
+        let mprr: MessagePayloadResponseLockInterface =
+            self.read_tdisp_response(TdispRequestResponseCode::ResponseLockInterfaceResponse)?;
+        self.tdisp_requester_context.start_interface_nonce = mprr.start_interface_nonce;
         Ok(())
     }
 }
@@ -3,9 +3,13 @@
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
 use crate::{
-    context::{InterfaceId, TdispContext},
+    common::{InternalError, TdispResult},
+    context::{InterfaceId, TdispContext, TdispMessageHeader, TdispRequestResponseCode},
     device::TdispConfiguration,
+    tdisp_codec::{Reader, TdispCodec},
 };
+use core::fmt::Debug;
+use spdmlib::common::session::SpdmSessionId;
 
 pub struct TdispRequester<'a> {
     pub tdisp_requester_context: TdispContext<'a>,
@@ -15,16 +19,38 @@ impl<'a> TdispRequester<'a> {
     pub fn new(
         interface_id: InterfaceId,
         configuration: &'a mut dyn TdispConfiguration,
-        spdm_session_id: u32,
+        spdm_session_id: SpdmSessionId,
     ) -> Self {
         TdispRequester {
             tdisp_requester_context: TdispContext::new(
                 interface_id,
                 configuration,
-                spdm_session_id,
+                spdm_session_id.as_u32(),
             ),
         }
     }
+
+    // read the payload of the response kept in response_message, which must
+    // be response_code
+    fn read_tdisp_response<T: TdispCodec + Default + Debug>(
+        &mut self,
+        response_code: TdispRequestResponseCode,
+    ) -> TdispResult<T> {
+        let response_message = self.tdisp_requester_context.response_message;
+        let mut reader = Reader::init(&response_message);
+        let tmh = TdispMessageHeader::tdisp_read(&mut self.tdisp_requester_context, &mut reader)
+            .ok_or(InternalError::Violation)?;
+        if tmh.message_type == TdispRequestResponseCode::ResponseTdispError {
+            return Err(InternalError::ErrStr("TDISP_ERROR response"));
+        }
+        if tmh.message_type != response_code
+            || tmh.tdisp_version != self.tdisp_requester_context.version_sel
+        {
+            return Err(InternalError::Violation);
+        }
+        T::tdisp_read(&mut self.tdisp_requester_context, &mut reader)
+            .ok_or(InternalError::Violation)
+    }
 }
 
 pub mod bind_p2p_stream_request_req;
@@ -13,7 +13,11 @@ use spdmlib::{
 
 use crate::{
     common::{InternalError, TdispResult, PCI_VENDOR_ID_STRUCT},
-    context::{MessagePayloadRequestStartInterface, TdispMessage, TdispRequestResponseCode},
+    config::MAX_TDISP_MESSAGE_SIZE,
+    context::{
+        MessagePayloadRequestStartInterface, MessagePayloadResponseStartInterface, TdispMessage,
+        TdispRequestResponseCode, NONCE_LENGTH,
+    },
     tdisp_codec::TdispCodec,
 };
 
@@ -27,6 +31,8 @@ impl<'a> TdispRequester<'a> {
         let mut tdisp_message = TdispMessage::<MessagePayloadRequestStartInterface>::default();
         tdisp_message.tdisp_message_header.interface_id = self.tdisp_requester_context.tdi;
         tdisp_message.tdisp_message_header.tdisp_version = self.tdisp_requester_context.version_sel;
+        tdisp_message.tdisp_message_payload.start_interface_nonce =
+            self.tdisp_requester_context.start_interface_nonce;
         let mut vendor_defined_req_payload =
             [0u8; spdmlib::config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE];
         let mut writer = Writer::init(&mut vendor_defined_req_payload);
@@ -38,29 +44,32 @@ impl<'a> TdispRequester<'a> {
             vendor_defined_req_payload,
         };
 
-        self.tdisp_requester_context
-            .request_message
-            .copy_from_slice(&vendor_defined_req_payload);
+        self.tdisp_requester_context.zero_messages();
+        self.tdisp_requester_context.request_message[..req_length as usize]
+            .copy_from_slice(&vendor_defined_req_payload[..req_length as usize]);
         self.tdisp_requester_context.request_code =
             TdispRequestResponseCode::RequestStartInterfaceRequest;
 
         match spdm_requester.send_spdm_vendor_defined_request(
-            self.tdisp_requester_context.spdm_session_id,
+            self.tdisp_requester_context.spdm_session_id.into(),
             RegistryOrStandardsBodyID::PCISIG,
             PCI_VENDOR_ID_STRUCT,
             vdrp,
         ) {
             Ok(vdrp) => {
                 let VendorDefinedRspPayloadStruct {
-                    rsp_length: _,
+                    rsp_length,
                     vendor_defined_rsp_payload,
                 } = vdrp;
 
                 self.tdisp_requester_context.response_code =
                     TdispRequestResponseCode::ResponseStartInterfaceResponse;
-                self.tdisp_requester_context
-                    .response_message
-                    .copy_from_slice(&vendor_defined_rsp_payload);
+                let rsp_length = rsp_length as usize;
+                if rsp_length > MAX_TDISP_MESSAGE_SIZE {
+                    return Err(InternalError::Violation);
+                }
+                self.tdisp_requester_context.response_message[..rsp_length]
+                    .copy_from_slice(&vendor_defined_rsp_payload[..rsp_length]);
 
                 self.handle_start_interface_response(spdm_requester)
             }
@@ -72,6 +81,10 @@ impl<'a> TdispRequester<'a> {
         &mut self,
         _spdm_requester: &mut RequesterContext,
     ) -> TdispResult {
+        let _: MessagePayloadResponseStartInterface =
+            self.read_tdisp_response(TdispRequestResponseCode::ResponseStartInterfaceResponse)?;
+        // the nonce is single use
+        self.tdisp_requester_context.start_interface_nonce = [0u8; NONCE_LENGTH];
         Ok(())
     }
 }
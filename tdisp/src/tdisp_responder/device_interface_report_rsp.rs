@@ -5,8 +5,10 @@
 use spdmlib::error::*;
 
 use crate::{
+    config::MAX_MESSAGE_INTERNAL_BUFFER_SIZE,
     context::{
         MessagePayloadRequestGetDeviceInterfaceReport, MessagePayloadResponseDeviceInterfaceReport,
+        MAX_DEVICE_INTERFACE_REPORT_PORTION_LENGTH,
     },
     state_machine::TDIState,
 };
@@ -16,7 +18,7 @@ use super::*;
 // security check
 // Interface ID in the request is not hosted by the device
 // DONE - TDI is not in CONFIG_LOCKED or RUN
-// Invalid offset specified
+// DONE - Invalid offset specified
 
 impl<'a> TdispResponder<'a> {
     pub fn handle_device_interface_report(
@@ -36,7 +38,7 @@ impl<'a> TdispResponder<'a> {
                 MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INVALID_REQUEST,
             )
         } else if self.tdisp_requester_context.state_machine.current_state != TDIState::Run
-            || self.tdisp_requester_context.state_machine.current_state != TDIState::ConfigLocked
+            && self.tdisp_requester_context.state_machine.current_state != TDIState::ConfigLocked
         {
             self.handle_tdisp_error(
                 vendor_defined_req_payload_struct,
@@ -58,13 +60,45 @@ impl<'a> TdispResponder<'a> {
                 interface_id: self.tdisp_requester_context.tdi,
             };
 
-            let mprr = MessagePayloadResponseDeviceInterfaceReport::default();
-            // mprr.portion_length
-            // mprr.reminder_length
-            // mprr.report_bytes
+            let mpr = mpr.unwrap();
+            let mut report = [0u8; MAX_MESSAGE_INTERNAL_BUFFER_SIZE];
+            let report_length = match self
+                .tdisp_requester_context
+                .configuration
+                .get_interface_report(&mut report)
+            {
+                Ok(report_length) if report_length <= MAX_MESSAGE_INTERNAL_BUFFER_SIZE => {
+                    report_length
+                }
+                _ => {
+                    return self.handle_tdisp_error(
+                        vendor_defined_req_payload_struct,
+                        MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_UNSPECIFIED,
+                    )
+                }
+            };
+            let offset = mpr.offset as usize;
+            if offset > report_length {
+                return self.handle_tdisp_error(
+                    vendor_defined_req_payload_struct,
+                    MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INVALID_REQUEST,
+                );
+            }
+            let portion_length = (report_length - offset)
+                .min(mpr.length as usize)
+                .min(MAX_DEVICE_INTERFACE_REPORT_PORTION_LENGTH);
+
+            let mut mprr = MessagePayloadResponseDeviceInterfaceReport {
+                portion_length: portion_length as u16,
+                reminder_length: (report_length - offset - portion_length) as u16,
+                ..Default::default()
+            };
+            mprr.report_bytes[..portion_length]
+                .copy_from_slice(&report[offset..offset + portion_length]);
 
             tmhr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
             mprr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
+            vendor_defined_rsp_payload_struct.rsp_length = writer.used() as u16;
 
             Ok(vendor_defined_rsp_payload_struct)
         }
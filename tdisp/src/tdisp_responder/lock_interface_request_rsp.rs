@@ -77,11 +77,18 @@ impl<'a> TdispResponder<'a> {
                 interface_id: self.tdisp_requester_context.tdi,
             };
 
-            let mprr = MessagePayloadResponseLockInterface::default();
-            // mprr.start_interface_nonce
+            let mut mprr = MessagePayloadResponseLockInterface::default();
+            if spdmlib::crypto::rand::get_random(&mut mprr.start_interface_nonce).is_err() {
+                return self.handle_tdisp_error(
+                    vendor_defined_req_payload_struct,
+                    MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INSUFFICIENT_ENTROPY,
+                );
+            }
+            self.tdisp_requester_context.start_interface_nonce = mprr.start_interface_nonce;
 
             tmhr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
             mprr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
+            vendor_defined_rsp_payload_struct.rsp_length = writer.used() as u16;
 
             match self.tdisp_requester_context.configuration.init_config() {
                 Ok(_) => match self.tdisp_requester_context.configuration.lock_config() {
@@ -265,3 +265,357 @@ pub const MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INVALID_DEVICE_CONFIGURATION:
         vendor_err_data: [0u8; MAX_EXTENDED_ERROR_DATA_LENGTH],
     },
 };
+
+#[cfg(all(test,))]
+mod tests {
+    use super::*;
+    use crate::context::*;
+    use crate::state_machine::TDIState;
+    use crate::testlib::FakeTdispConfiguration;
+    use core::fmt::Debug;
+
+    fn encode_tdisp_request<T: TdispCodec>(
+        responder: &mut TdispResponder,
+        message_type: TdispRequestResponseCode,
+        payload: &T,
+    ) -> VendorDefinedReqPayloadStruct {
+        let tmh = TdispMessageHeader {
+            tdisp_version: 0x10,
+            message_type,
+            interface_id: InterfaceId::default(),
+        };
+        let mut vendor_defined_req_payload =
+            [0u8; spdmlib::config::MAX_SPDM_VENDOR_DEFINED_PAYLOAD_SIZE];
+        let mut writer = Writer::init(&mut vendor_defined_req_payload);
+        tmh.tdisp_encode(&mut responder.tdisp_requester_context, &mut writer);
+        payload.tdisp_encode(&mut responder.tdisp_requester_context, &mut writer);
+        let req_length = writer.used() as u16;
+        VendorDefinedReqPayloadStruct {
+            req_length,
+            vendor_defined_req_payload,
+        }
+    }
+
+    fn read_tdisp_response<T: TdispCodec + Default + Debug>(
+        responder: &mut TdispResponder,
+        response: &VendorDefinedRspPayloadStruct,
+        message_type: TdispRequestResponseCode,
+    ) -> T {
+        let mut reader =
+            Reader::init(&response.vendor_defined_rsp_payload[..response.rsp_length as usize]);
+        let tmh =
+            TdispMessageHeader::tdisp_read(&mut responder.tdisp_requester_context, &mut reader)
+                .unwrap();
+        assert_eq!(tmh.message_type, message_type);
+        assert_eq!(tmh.tdisp_version, 0x10);
+        T::tdisp_read(&mut responder.tdisp_requester_context, &mut reader).unwrap()
+    }
+
+    fn read_tdisp_error(
+        responder: &mut TdispResponder,
+        response: &VendorDefinedRspPayloadStruct,
+    ) -> GenericErrorResponseCode {
+        let mut reader =
+            Reader::init(&response.vendor_defined_rsp_payload[..response.rsp_length as usize]);
+        let tmh =
+            TdispMessageHeader::tdisp_read(&mut responder.tdisp_requester_context, &mut reader)
+                .unwrap();
+        assert_eq!(
+            tmh.message_type,
+            TdispRequestResponseCode::ResponseTdispError
+        );
+        MessagePayloadResponseTdispError::tdisp_read(
+            &mut responder.tdisp_requester_context,
+            &mut reader,
+        )
+        .unwrap()
+        .error_code
+    }
+
+    fn lock_interface(responder: &mut TdispResponder) -> [u8; NONCE_LENGTH] {
+        let request = encode_tdisp_request(
+            responder,
+            TdispRequestResponseCode::RequestLockInterfaceRequest,
+            &MessagePayloadRequestLockInterface {
+                flags: LockInterfaceFlag::NO_FW_UPDATE,
+                ..Default::default()
+            },
+        );
+        let response = responder.handle_lock_interface_request(&request).unwrap();
+        let lock: MessagePayloadResponseLockInterface = read_tdisp_response(
+            responder,
+            &response,
+            TdispRequestResponseCode::ResponseLockInterfaceResponse,
+        );
+        lock.start_interface_nonce
+    }
+
+    fn start_interface(
+        responder: &mut TdispResponder,
+        start_interface_nonce: [u8; NONCE_LENGTH],
+    ) -> VendorDefinedRspPayloadStruct {
+        let request = encode_tdisp_request(
+            responder,
+            TdispRequestResponseCode::RequestStartInterfaceRequest,
+            &MessagePayloadRequestStartInterface {
+                start_interface_nonce,
+            },
+        );
+        responder.handle_start_interface_request(&request).unwrap()
+    }
+
+    fn stop_interface(responder: &mut TdispResponder) {
+        let request = encode_tdisp_request(
+            responder,
+            TdispRequestResponseCode::RequestStopInterfaceRequest,
+            &MessagePayloadRequestStopInterface::default(),
+        );
+        let response = responder.handle_stop_interface_request(&request).unwrap();
+        let _: MessagePayloadResponseStopInterface = read_tdisp_response(
+            responder,
+            &response,
+            TdispRequestResponseCode::ResponseStopInterfaceResponse,
+        );
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::ConfigUnlocked
+        );
+    }
+
+    fn get_device_interface_report(
+        responder: &mut TdispResponder,
+        offset: u16,
+        length: u16,
+    ) -> VendorDefinedRspPayloadStruct {
+        let request = encode_tdisp_request(
+            responder,
+            TdispRequestResponseCode::RequestGetDeviceInterfaceReport,
+            &MessagePayloadRequestGetDeviceInterfaceReport { offset, length },
+        );
+        responder.handle_device_interface_report(&request).unwrap()
+    }
+
+    #[test]
+    fn test_case0_tdisp_interface_lifecycle() {
+        let report: Vec<u8> = (0..100u8).collect();
+        let mut configuration = FakeTdispConfiguration {
+            report: report.clone(),
+            ..Default::default()
+        };
+        let mut responder = TdispResponder::new(InterfaceId::default(), &mut configuration, 1);
+
+        let request = encode_tdisp_request(
+            &mut responder,
+            TdispRequestResponseCode::RequestGetTdispVersion,
+            &MessagePayloadRequestGetVersion::default(),
+        );
+        let response = responder.handle_tdisp_version(&request).unwrap();
+        let version: MessagePayloadResponseVersion = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseTdispVersion,
+        );
+        assert_eq!(version.version_num_count, 1);
+        assert_eq!(version.version_num_entry[0], 0x10);
+
+        // no report before the configuration is locked, and the TDI goes to
+        // ERROR until it is stopped
+        let response = get_device_interface_report(&mut responder, 0, 0x100);
+        assert_eq!(
+            read_tdisp_error(&mut responder, &response),
+            GenericErrorResponseCode::InvalidInterfaceState
+        );
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::Error
+        );
+        stop_interface(&mut responder);
+
+        let start_interface_nonce = lock_interface(&mut responder);
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::ConfigLocked
+        );
+
+        // the report is read in portions
+        let response = get_device_interface_report(&mut responder, 0, 40);
+        let portion: MessagePayloadResponseDeviceInterfaceReport = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseDeviceInterfaceReport,
+        );
+        assert_eq!(portion.portion_length, 40);
+        assert_eq!(portion.reminder_length, 60);
+        assert_eq!(&portion.report_bytes[..40], &report[..40]);
+        let response = get_device_interface_report(&mut responder, 40, 0x1000);
+        let portion: MessagePayloadResponseDeviceInterfaceReport = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseDeviceInterfaceReport,
+        );
+        assert_eq!(portion.portion_length, 60);
+        assert_eq!(portion.reminder_length, 0);
+        assert_eq!(&portion.report_bytes[..60], &report[40..]);
+
+        let response = start_interface(&mut responder, start_interface_nonce);
+        let _: MessagePayloadResponseStartInterface = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseStartInterfaceResponse,
+        );
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::Run
+        );
+
+        let request = encode_tdisp_request(
+            &mut responder,
+            TdispRequestResponseCode::RequestGetDeviceInterfaceState,
+            &MessagePayloadRequestGetDeviceInterfaceState::default(),
+        );
+        let response = responder.handle_device_interface_state(&request).unwrap();
+        let state: MessagePayloadResponseDeviceInterfaceState = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseDeviceInterfaceState,
+        );
+        assert_eq!(state.tdi_state.current_state, TDIState::Run);
+
+        let request = encode_tdisp_request(
+            &mut responder,
+            TdispRequestResponseCode::RequestSetMmioAttributeRequest,
+            &MessagePayloadRequestSetMmioAttribute::default(),
+        );
+        let response = responder
+            .handle_set_mmio_attribute_request(&request)
+            .unwrap();
+        let _: MessagePayloadResponseSetMmioAttribute = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseSetMmioAttributeResponse,
+        );
+
+        stop_interface(&mut responder);
+        drop(responder);
+        assert!(!configuration.locked);
+        assert!(!configuration.tracking);
+        // once for the ERROR state, once per STOP_INTERFACE_REQUEST
+        assert_eq!(configuration.erase_count, 3);
+    }
+
+    #[test]
+    fn test_case1_tdisp_interface_errors() {
+        let mut configuration = FakeTdispConfiguration {
+            report: [0x5au8; 16].to_vec(),
+            ..Default::default()
+        };
+        let mut responder = TdispResponder::new(InterfaceId::default(), &mut configuration, 1);
+
+        // the nonce of a previous lock is not accepted
+        let stale_nonce = lock_interface(&mut responder);
+        stop_interface(&mut responder);
+        let start_interface_nonce = lock_interface(&mut responder);
+        assert_ne!(stale_nonce, start_interface_nonce);
+        let response = start_interface(&mut responder, stale_nonce);
+        assert_eq!(
+            read_tdisp_error(&mut responder, &response),
+            GenericErrorResponseCode::InvalidNonce
+        );
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::Error
+        );
+        stop_interface(&mut responder);
+
+        // a locked interface is not locked again
+        let _ = lock_interface(&mut responder);
+        assert_eq!(
+            lock_interface_error(&mut responder),
+            GenericErrorResponseCode::InvalidInterfaceState
+        );
+        stop_interface(&mut responder);
+
+        // nor is a running interface started again
+        let start_interface_nonce = lock_interface(&mut responder);
+        let response = start_interface(&mut responder, start_interface_nonce);
+        let _: MessagePayloadResponseStartInterface = read_tdisp_response(
+            &mut responder,
+            &response,
+            TdispRequestResponseCode::ResponseStartInterfaceResponse,
+        );
+        let response = start_interface(&mut responder, start_interface_nonce);
+        assert_eq!(
+            read_tdisp_error(&mut responder, &response),
+            GenericErrorResponseCode::InvalidInterfaceState
+        );
+        stop_interface(&mut responder);
+
+        // offset past the end of the report
+        let _ = lock_interface(&mut responder);
+        let response = get_device_interface_report(&mut responder, 17, 0x100);
+        assert_eq!(
+            read_tdisp_error(&mut responder, &response),
+            GenericErrorResponseCode::InvalidRequest
+        );
+        stop_interface(&mut responder);
+
+        // not a TDISP message
+        let mut request = encode_tdisp_request(
+            &mut responder,
+            TdispRequestResponseCode::RequestGetDeviceInterfaceState,
+            &MessagePayloadRequestGetDeviceInterfaceState::default(),
+        );
+        request.vendor_defined_req_payload[0] = 2;
+        let response = responder.handle_device_interface_state(&request).unwrap();
+        assert_eq!(
+            read_tdisp_error(&mut responder, &response),
+            GenericErrorResponseCode::InvalidRequest
+        );
+        drop(responder);
+
+        // the device refuses to lock
+        let mut configuration = FakeTdispConfiguration {
+            fail_lock: true,
+            ..Default::default()
+        };
+        let mut responder = TdispResponder::new(InterfaceId::default(), &mut configuration, 1);
+        assert_eq!(
+            lock_interface_error(&mut responder),
+            GenericErrorResponseCode::InvalidDeviceConfiguration
+        );
+        assert_eq!(
+            responder
+                .tdisp_requester_context
+                .state_machine
+                .current_state,
+            TDIState::Error
+        );
+        drop(responder);
+        assert!(!configuration.locked);
+    }
+
+    fn lock_interface_error(responder: &mut TdispResponder) -> GenericErrorResponseCode {
+        let request = encode_tdisp_request(
+            responder,
+            TdispRequestResponseCode::RequestLockInterfaceRequest,
+            &MessagePayloadRequestLockInterface::default(),
+        );
+        let response = responder.handle_lock_interface_request(&request).unwrap();
+        read_tdisp_error(responder, &response)
+    }
+}
@@ -62,6 +62,7 @@ impl<'a> TdispResponder<'a> {
 
             tmhr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
             mprr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
+            vendor_defined_rsp_payload_struct.rsp_length = writer.used() as u16;
 
             Ok(vendor_defined_rsp_payload_struct)
         }
@@ -5,14 +5,16 @@
 use spdmlib::error::*;
 
 use crate::{
-    context::{MessagePayloadRequestStartInterface, MessagePayloadResponseStartInterface},
+    context::{
+        MessagePayloadRequestStartInterface, MessagePayloadResponseStartInterface, NONCE_LENGTH,
+    },
     state_machine::TDIState,
 };
 
 use super::*;
 
 // If the interface ID in the request is not hosted by the device.
-// START_INTERFACE_NONCE in the request is not valid i.e., does not match the nonce generated by the device in the LOCK_INTERFACE_RESPONSE.
+// DONE - START_INTERFACE_NONCE in the request is not valid i.e., does not match the nonce generated by the device in the LOCK_INTERFACE_RESPONSE.
 // DONE - TDI is not in CONFIG_LOCKED.
 
 impl<'a> TdispResponder<'a> {
@@ -38,7 +40,17 @@ impl<'a> TdispResponder<'a> {
                 vendor_defined_req_payload_struct,
                 MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INVALID_INTERFACE_STATE,
             )
+        } else if mpr.unwrap().start_interface_nonce
+            != self.tdisp_requester_context.start_interface_nonce
+        {
+            self.handle_tdisp_error(
+                vendor_defined_req_payload_struct,
+                MESSAGE_PAYLOAD_RESPONSE_TDISP_ERROR_INVALID_NONCE,
+            )
         } else {
+            // the nonce is single use
+            self.tdisp_requester_context.start_interface_nonce = [0u8; NONCE_LENGTH];
+
             let mut vendor_defined_rsp_payload_struct: VendorDefinedRspPayloadStruct =
                 VendorDefinedRspPayloadStruct {
                     rsp_length: 0,
@@ -58,6 +70,7 @@ impl<'a> TdispResponder<'a> {
 
             tmhr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
             mprr.tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
+            vendor_defined_rsp_payload_struct.rsp_length = writer.used() as u16;
 
             match self
                 .tdisp_requester_context
@@ -37,6 +37,7 @@ impl<'a> TdispResponder<'a> {
 
         message_payload_response_tdisp_error
             .tdisp_encode(&mut self.tdisp_requester_context, &mut writer);
+        vendor_defined_rsp_payload_struct.rsp_length = writer.used() as u16;
 
         match self
             .tdisp_requester_context
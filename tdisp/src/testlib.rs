@@ -0,0 +1,56 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+#![allow(unused)]
+
+use crate::common::{InternalError, TdispResult};
+use crate::device::TdispConfiguration;
+
+// Device keeping track of what the TDISP handlers asked it to do.
+#[derive(Debug, Default)]
+pub struct FakeTdispConfiguration {
+    pub locked: bool,
+    pub tracking: bool,
+    pub erase_count: usize,
+    pub fail_lock: bool,
+    pub report: Vec<u8>,
+}
+
+impl TdispConfiguration for FakeTdispConfiguration {
+    fn init_config(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn lock_config(&mut self) -> TdispResult {
+        if self.fail_lock {
+            return Err(InternalError::Unrecoverable);
+        }
+        self.locked = true;
+        Ok(())
+    }
+
+    fn unlock_config(&mut self) -> TdispResult {
+        self.locked = false;
+        self.tracking = false;
+        Ok(())
+    }
+
+    fn erase_confidential_config(&mut self) -> TdispResult {
+        self.erase_count += 1;
+        Ok(())
+    }
+
+    fn track_config_changes(&mut self) -> TdispResult {
+        self.tracking = true;
+        Ok(())
+    }
+
+    fn get_interface_report(&mut self, report: &mut [u8]) -> TdispResult<usize> {
+        if self.report.len() > report.len() {
+            return Err(InternalError::Violation);
+        }
+        report[..self.report.len()].copy_from_slice(&self.report);
+        Ok(self.report.len())
+    }
+}
@@ -0,0 +1,12 @@
+// Copyright (c) 2021 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+pub mod crypto_callbacks;
+pub mod fake_device_io;
+pub mod shared_buffer;
+pub mod utils;
+
+// TBD: need test different algorithm combinations
+pub const USE_ECDSA: bool = true;
+pub const USE_ECDH: bool = true;
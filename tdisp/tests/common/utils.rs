@@ -0,0 +1,217 @@
+// Copyright (c) 2021 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+#![allow(unused)]
+
+use super::{USE_ECDH, USE_ECDSA};
+use spdmlib::common;
+use spdmlib::common::SpdmOpaqueSupport;
+use spdmlib::config;
+use spdmlib::protocol::*;
+use std::path::PathBuf;
+
+/// Get test_key Dir
+pub fn get_test_key_directory() -> PathBuf {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let crate_dir = crate_dir.parent().expect("can't find parent dir");
+    crate_dir.to_path_buf()
+}
+
+/// Create requester config and provision info
+pub fn req_create_info() -> (common::SpdmConfigInfo, common::SpdmProvisionInfo) {
+    let config_info = common::SpdmConfigInfo {
+        spdm_version: [
+            SpdmVersion::SpdmVersion10,
+            SpdmVersion::SpdmVersion11,
+            SpdmVersion::SpdmVersion12,
+        ],
+        req_capabilities: SpdmRequestCapabilityFlags::CERT_CAP
+        | SpdmRequestCapabilityFlags::CHAL_CAP
+        | SpdmRequestCapabilityFlags::ENCRYPT_CAP
+        | SpdmRequestCapabilityFlags::MAC_CAP
+        //| SpdmRequestCapabilityFlags::MUT_AUTH_CAP
+        | SpdmRequestCapabilityFlags::KEY_EX_CAP
+        | SpdmRequestCapabilityFlags::PSK_CAP
+        | SpdmRequestCapabilityFlags::ENCAP_CAP
+        | SpdmRequestCapabilityFlags::HBEAT_CAP
+        | SpdmRequestCapabilityFlags::KEY_UPD_CAP, // | SpdmRequestCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP
+        // | SpdmRequestCapabilityFlags::PUB_KEY_ID_CAP
+        req_ct_exponent: 0,
+        measurement_specification: SpdmMeasurementSpecification::DMTF,
+        base_asym_algo: if USE_ECDSA {
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+        } else {
+            SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+        },
+        base_hash_algo: SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+        dhe_algo: if USE_ECDH {
+            SpdmDheAlgo::SECP_384_R1
+        } else {
+            SpdmDheAlgo::FFDHE_3072
+        },
+        aead_algo: SpdmAeadAlgo::AES_256_GCM,
+        req_asym_algo: SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048,
+        key_schedule_algo: SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        opaque_support: SpdmOpaqueSupport::OPAQUE_DATA_FMT1,
+        data_transfer_size: config::DATA_TRANSFER_SIZE as u32,
+        max_spdm_msg_size: config::MAX_SPDM_MSG_SIZE as u32,
+        ..Default::default()
+    };
+
+    let mut peer_cert_chain_data = SpdmCertChainData {
+        ..Default::default()
+    };
+
+    let crate_dir = get_test_key_directory();
+    let ca_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/ca.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/ca.cert.der")
+    };
+    let ca_cert = std::fs::read(ca_file_path).expect("unable to read ca cert!");
+    let inter_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/inter.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/inter.cert.der")
+    };
+    let inter_cert = std::fs::read(inter_file_path).expect("unable to read inter cert!");
+    let leaf_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/end_responder.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/end_responder.cert.der")
+    };
+    let leaf_cert = std::fs::read(leaf_file_path).expect("unable to read leaf cert!");
+
+    let ca_len = ca_cert.len();
+    let inter_len = inter_cert.len();
+    let leaf_len = leaf_cert.len();
+    log::info!(
+        "total cert size - {:?} = {:?} + {:?} + {:?}",
+        ca_len + inter_len + leaf_len,
+        ca_len,
+        inter_len,
+        leaf_len
+    );
+    peer_cert_chain_data.data_size = (ca_len + inter_len + leaf_len) as u16;
+    peer_cert_chain_data.data[0..ca_len].copy_from_slice(ca_cert.as_ref());
+    peer_cert_chain_data.data[ca_len..(ca_len + inter_len)].copy_from_slice(inter_cert.as_ref());
+    peer_cert_chain_data.data[(ca_len + inter_len)..(ca_len + inter_len + leaf_len)]
+        .copy_from_slice(leaf_cert.as_ref());
+
+    let provision_info = common::SpdmProvisionInfo {
+        my_cert_chain_data: [None, None, None, None, None, None, None, None],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
+        peer_cert_chain_data: Some(peer_cert_chain_data),
+        peer_cert_chain_root_hash: None,
+        ..Default::default()
+    };
+
+    (config_info, provision_info)
+}
+
+pub fn rsp_create_info() -> (common::SpdmConfigInfo, common::SpdmProvisionInfo) {
+    let config_info = common::SpdmConfigInfo {
+        spdm_version: [
+            SpdmVersion::SpdmVersion10,
+            SpdmVersion::SpdmVersion11,
+            SpdmVersion::SpdmVersion12,
+        ],
+        rsp_capabilities: SpdmResponseCapabilityFlags::CERT_CAP
+        | SpdmResponseCapabilityFlags::CHAL_CAP
+        | SpdmResponseCapabilityFlags::MEAS_CAP_SIG
+        | SpdmResponseCapabilityFlags::MEAS_FRESH_CAP
+        | SpdmResponseCapabilityFlags::ENCRYPT_CAP
+        | SpdmResponseCapabilityFlags::MAC_CAP
+        //| SpdmResponseCapabilityFlags::MUT_AUTH_CAP
+        | SpdmResponseCapabilityFlags::KEY_EX_CAP
+        | SpdmResponseCapabilityFlags::PSK_CAP_WITH_CONTEXT
+        | SpdmResponseCapabilityFlags::ENCAP_CAP
+        | SpdmResponseCapabilityFlags::HBEAT_CAP
+        | SpdmResponseCapabilityFlags::KEY_UPD_CAP, // | SpdmResponseCapabilityFlags::HANDSHAKE_IN_THE_CLEAR_CAP
+        // | SpdmResponseCapabilityFlags::PUB_KEY_ID_CAP
+        rsp_ct_exponent: 0,
+        measurement_specification: SpdmMeasurementSpecification::DMTF,
+        measurement_hash_algo: SpdmMeasurementHashAlgo::TPM_ALG_SHA_384,
+        base_asym_algo: if USE_ECDSA {
+            SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384
+        } else {
+            SpdmBaseAsymAlgo::TPM_ALG_RSASSA_3072
+        },
+        base_hash_algo: SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+        dhe_algo: if USE_ECDH {
+            SpdmDheAlgo::SECP_384_R1
+        } else {
+            SpdmDheAlgo::FFDHE_3072
+        },
+        aead_algo: SpdmAeadAlgo::AES_256_GCM,
+        req_asym_algo: SpdmReqAsymAlgo::TPM_ALG_RSAPSS_2048,
+        key_schedule_algo: SpdmKeyScheduleAlgo::SPDM_KEY_SCHEDULE,
+        opaque_support: SpdmOpaqueSupport::OPAQUE_DATA_FMT1,
+        data_transfer_size: config::DATA_TRANSFER_SIZE as u32,
+        max_spdm_msg_size: config::MAX_SPDM_MSG_SIZE as u32,
+        heartbeat_period: config::HEARTBEAT_PERIOD,
+        secure_spdm_version: config::SECURE_SPDM_VERSION,
+        ..Default::default()
+    };
+
+    let mut my_cert_chain_data = SpdmCertChainData {
+        ..Default::default()
+    };
+
+    let crate_dir = get_test_key_directory();
+    let ca_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/ca.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/ca.cert.der")
+    };
+    log::info!("{}", ca_file_path.display());
+    let ca_cert = std::fs::read(ca_file_path).expect("unable to read ca cert!");
+    let inter_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/inter.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/inter.cert.der")
+    };
+    let inter_cert = std::fs::read(inter_file_path).expect("unable to read inter cert!");
+    let leaf_file_path = if USE_ECDSA {
+        crate_dir.join("test_key/EcP384/end_responder.cert.der")
+    } else {
+        crate_dir.join("test_key/Rsa3072/end_responder.cert.der")
+    };
+    let leaf_cert = std::fs::read(leaf_file_path).expect("unable to read leaf cert!");
+
+    let ca_len = ca_cert.len();
+    let inter_len = inter_cert.len();
+    let leaf_len = leaf_cert.len();
+    log::info!(
+        "total cert size - {:?} = {:?} + {:?} + {:?}",
+        ca_len + inter_len + leaf_len,
+        ca_len,
+        inter_len,
+        leaf_len
+    );
+    my_cert_chain_data.data_size = (ca_len + inter_len + leaf_len) as u16;
+    my_cert_chain_data.data[0..ca_len].copy_from_slice(ca_cert.as_ref());
+    my_cert_chain_data.data[ca_len..(ca_len + inter_len)].copy_from_slice(inter_cert.as_ref());
+    my_cert_chain_data.data[(ca_len + inter_len)..(ca_len + inter_len + leaf_len)]
+        .copy_from_slice(leaf_cert.as_ref());
+
+    let provision_info = common::SpdmProvisionInfo {
+        my_cert_chain_data: [
+            Some(my_cert_chain_data),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
+        peer_cert_chain_data: None,
+        peer_cert_chain_root_hash: None,
+        ..Default::default()
+    };
+
+    (config_info, provision_info)
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+mod common;
+use common::fake_device_io::{FakeSpdmDeviceIo, FakeSpdmDeviceIoReceve};
+use common::shared_buffer::SharedBuffer;
+
+use pcidoe_transport::PciDoeTransportEncap;
+use spdmlib::common::session::SpdmSessionAppData;
+use spdmlib::error::SpdmResult;
+use spdmlib::message::{
+    RegistryOrStandardsBodyID, VendorDefinedReqPayloadStruct, VendorDefinedRspPayloadStruct,
+};
+use spdmlib::protocol::SpdmMeasurementSummaryHashType;
+use spdmlib::{requester, responder};
+use tdisp::common::{TdispResult, PCI_VENDOR_ID_STRUCT};
+use tdisp::context::InterfaceId;
+use tdisp::device::TdispConfiguration;
+use tdisp::state_machine::TDIState;
+use tdisp::tdisp_requester::TdispRequester;
+use tdisp::tdisp_responder::{self, TdispResponder, TDISP_RESPONDER};
+
+const INTERFACE_REPORT: [u8; 300] = [0xa5u8; 300];
+
+#[derive(Debug, Default)]
+struct FakeTdispConfiguration {}
+
+impl TdispConfiguration for FakeTdispConfiguration {
+    fn init_config(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn lock_config(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn unlock_config(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn erase_confidential_config(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn track_config_changes(&mut self) -> TdispResult {
+        Ok(())
+    }
+
+    fn get_interface_report(&mut self, report: &mut [u8]) -> TdispResult<usize> {
+        report[..INTERFACE_REPORT.len()].copy_from_slice(&INTERFACE_REPORT);
+        Ok(INTERFACE_REPORT.len())
+    }
+}
+
+fn tdisp_responder_state() -> TDIState {
+    unsafe {
+        TDISP_RESPONDER
+            .as_ref()
+            .unwrap()
+            .tdisp_requester_context
+            .state_machine
+            .current_state
+    }
+}
+
+#[test]
+fn intergration_tdisp() {
+    spdmlib::crypto::asym_sign::register(common::crypto_callbacks::ASYM_SIGN_IMPL.clone());
+
+    let shared_buffer = SharedBuffer::new();
+    let device_io_responder = &mut FakeSpdmDeviceIoReceve::new(&shared_buffer);
+    let transport_encap_responder = &mut PciDoeTransportEncap {};
+
+    let handler = |_session_id: u32,
+                   _app_data: &mut SpdmSessionAppData,
+                   request: &VendorDefinedReqPayloadStruct|
+     -> SpdmResult<VendorDefinedRspPayloadStruct> {
+        tdisp_responder::vendor_defined_request_handler(request)
+    };
+
+    let (config_info, provision_info) = common::utils::rsp_create_info();
+    let mut responder_context = responder::ResponderContext::new(
+        device_io_responder,
+        transport_encap_responder,
+        config_info,
+        provision_info,
+    );
+    assert!(responder_context
+        .register_vendor_defined_handler(
+            RegistryOrStandardsBodyID::PCISIG,
+            PCI_VENDOR_ID_STRUCT,
+            &handler
+        )
+        .is_ok());
+
+    let device_io_requester = &mut FakeSpdmDeviceIo::new(&shared_buffer, &mut responder_context);
+    let transport_encap_requester = &mut PciDoeTransportEncap {};
+
+    let (config_info, provision_info) = common::utils::req_create_info();
+    let mut requester_context = requester::RequesterContext::new(
+        device_io_requester,
+        transport_encap_requester,
+        config_info,
+        provision_info,
+    );
+
+    assert!(requester_context.init_connection().is_ok());
+    assert!(requester_context.send_receive_spdm_digest(None).is_ok());
+    assert!(requester_context
+        .send_receive_spdm_certificate(None, 0)
+        .is_ok());
+    let session_id = requester_context
+        .start_session(
+            false,
+            0,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        )
+        .unwrap();
+
+    unsafe {
+        TDISP_RESPONDER = Some(TdispResponder::new(
+            InterfaceId::default(),
+            Box::leak(Box::new(FakeTdispConfiguration::default())),
+            session_id.as_u32(),
+        ));
+    }
+    let mut configuration = FakeTdispConfiguration::default();
+    let mut tdisp_requester =
+        TdispRequester::new(InterfaceId::default(), &mut configuration, session_id);
+
+    assert!(tdisp_requester
+        .send_receive_get_tdisp_version(&mut requester_context)
+        .is_ok());
+    assert!(tdisp_requester
+        .send_receive_get_tdisp_capabilities(&mut requester_context)
+        .is_ok());
+
+    assert!(tdisp_requester
+        .send_receive_lock_interface_request(&mut requester_context)
+        .is_ok());
+    assert_eq!(tdisp_responder_state(), TDIState::ConfigLocked);
+    assert_eq!(
+        tdisp_requester
+            .tdisp_requester_context
+            .start_interface_nonce,
+        unsafe {
+            TDISP_RESPONDER
+                .as_ref()
+                .unwrap()
+                .tdisp_requester_context
+                .start_interface_nonce
+        }
+    );
+
+    assert!(tdisp_requester
+        .send_receive_get_device_interface_report(&mut requester_context)
+        .is_ok());
+    let context = &tdisp_requester.tdisp_requester_context;
+    assert_eq!(
+        &context.interface_report[..context.interface_report_length],
+        &INTERFACE_REPORT[..]
+    );
+
+    assert!(tdisp_requester
+        .send_receive_start_interface(&mut requester_context)
+        .is_ok());
+    assert_eq!(tdisp_responder_state(), TDIState::Run);
+    assert!(tdisp_requester
+        .send_receive_get_device_interface_state(&mut requester_context)
+        .is_ok());
+
+    // the TDI runs already, a second START_INTERFACE_REQUEST gets TDISP_ERROR
+    // and the TDI goes to ERROR
+    assert!(tdisp_requester
+        .send_receive_start_interface(&mut requester_context)
+        .is_err());
+    assert_eq!(tdisp_responder_state(), TDIState::Error);
+
+    assert!(tdisp_requester
+        .send_receive_stop_interface(&mut requester_context)
+        .is_ok());
+    assert_eq!(tdisp_responder_state(), TDIState::ConfigUnlocked);
+
+    assert!(requester_context.end_session(session_id).is_ok());
+}
@@ -24,3 +24,43 @@ pub fn crypto_mbedtls_register_handles() {
 
     spdmlib::crypto::rand::register(spdmlib_crypto_mbedtls::rand_impl::DEFAULT.clone());
 }
+
+// Certificate chain operations are not provided by the RustCrypto backend.
+#[cfg(feature = "crypto_rustcrypto")]
+pub fn crypto_rustcrypto_register_handles() {
+    spdmlib::crypto::aead::register(spdmlib_crypto_rustcrypto::aead_impl::DEFAULT.clone());
+
+    spdmlib::crypto::asym_sign::register(
+        spdmlib_crypto_rustcrypto::asym_sign_impl::DEFAULT.clone(),
+    );
+
+    spdmlib::crypto::asym_verify::register(
+        spdmlib_crypto_rustcrypto::asym_verify_impl::DEFAULT.clone(),
+    );
+
+    spdmlib::crypto::dhe::register(spdmlib_crypto_rustcrypto::dhe_impl::DEFAULT.clone());
+
+    spdmlib::crypto::hash::register(spdmlib_crypto_rustcrypto::hash_impl::DEFAULT.clone());
+
+    spdmlib::crypto::hkdf::register(spdmlib_crypto_rustcrypto::hkdf_impl::DEFAULT.clone());
+
+    spdmlib::crypto::hmac::register(spdmlib_crypto_rustcrypto::hmac_impl::DEFAULT.clone());
+
+    spdmlib::crypto::rand::register(spdmlib_crypto_rustcrypto::rand_impl::DEFAULT.clone());
+}
+
+// The RustCrypto signer takes the raw scalar of the P-384 PKCS#8 key under test_key.
+#[cfg(feature = "crypto_rustcrypto")]
+pub fn crypto_rustcrypto_set_signing_key(key_file: &str) {
+    use p384::pkcs8::DecodePrivateKey;
+
+    let key_file_path = crate::spdm_emu::get_test_key_directory().join(key_file);
+    let der_file = std::fs::read(key_file_path).expect("unable to read key der!");
+    let secret_key =
+        p384::SecretKey::from_pkcs8_der(der_file.as_slice()).expect("unable to parse key der!");
+    spdmlib_crypto_rustcrypto::asym_sign_impl::set_signing_key(
+        spdmlib::protocol::SpdmBaseAsymAlgo::TPM_ALG_ECDSA_ECC_NIST_P384,
+        secret_key.to_be_bytes().as_slice(),
+    )
+    .expect("unable to set signing key!");
+}
@@ -2,8 +2,7 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
-use std::path::PathBuf;
-
+use crate::spdm_emu::get_test_key_directory;
 use spdmlib::crypto::SpdmAsymSign;
 
 use spdmlib::protocol::{
@@ -80,6 +79,7 @@ fn asym_sign(
                 data,
             )
         }
+        (_, SpdmBaseAsymAlgo::TPM_ALG_EDDSA_ED25519) => sign_eddsa_asym_algo(data),
         _ => {
             panic!();
         }
@@ -126,6 +126,29 @@ fn sign_ecdsa_asym_algo(
     })
 }
 
+fn sign_eddsa_asym_algo(data: &[u8]) -> Option<SpdmSignatureStruct> {
+    // openssl genpkey -algorithm ed25519 -outform DER > end_responder.key.p8
+    let crate_dir = get_test_key_directory();
+    let key_file_path = crate_dir.join("test_key/ed25519/end_responder.key.p8");
+    let der_file = std::fs::read(key_file_path).expect("unable to read key der!");
+    let key_bytes = der_file.as_slice();
+
+    // openssl emits PKCS#8 v1, without the public key ring prefers
+    let key_pair: ring::signature::Ed25519KeyPair =
+        ring::signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(key_bytes).ok()?;
+
+    let signature = key_pair.sign(data);
+    let signature = signature.as_ref();
+
+    let mut full_signature: [u8; SPDM_MAX_ASYM_KEY_SIZE] = [0u8; SPDM_MAX_ASYM_KEY_SIZE];
+    full_signature[..signature.len()].copy_from_slice(signature);
+
+    Some(SpdmSignatureStruct {
+        data_size: signature.len() as u16,
+        data: full_signature,
+    })
+}
+
 fn sign_rsa_asym_algo(
     padding_alg: &'static dyn ring::signature::RsaEncoding,
     key_len: usize,
@@ -171,13 +194,3 @@ fn sign_rsa_asym_algo(
         data: full_sign,
     })
 }
-
-fn get_test_key_directory() -> PathBuf {
-    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let crate_dir = crate_dir
-        .parent()
-        .expect("can't find parent dir")
-        .parent()
-        .expect("can't find parent_dir");
-    crate_dir.to_path_buf()
-}
@@ -4,6 +4,7 @@
 
 #![allow(dead_code)]
 #![allow(unused_variables)]
+use spdmlib::common::spdm_calc_measurement_summary_hash;
 use spdmlib::config;
 use spdmlib::crypto::hash;
 use spdmlib::message::*;
@@ -123,6 +124,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest1.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -135,6 +137,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest2.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -147,6 +150,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest3.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -159,6 +163,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest4.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -171,6 +176,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest5.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -183,6 +189,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest6.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -195,6 +202,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest7.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -207,6 +215,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest8.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -219,6 +228,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest9.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -231,6 +241,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest10.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -241,7 +252,53 @@ fn spdm_measurement_collection_impl(
                     },
                 ],
             })
-        } else if measurement_index > config::MAX_SPDM_MEASUREMENT_BLOCK_COUNT {
+        } else if measurement_index
+            == SpdmMeasurementOperation::SpdmMeasurementRequestManifest.get_u8() as usize
+            || measurement_index
+                == SpdmMeasurementOperation::SpdmMeasurementRequestDeviceMode.get_u8() as usize
+        {
+            if spdm_version.get_u8() < SpdmVersion::SpdmVersion12.get_u8() {
+                return None;
+            }
+            let mut value = [0u8; config::MAX_SPDM_MEASUREMENT_VALUE_LEN];
+            let (r#type, value_size) = if measurement_index
+                == SpdmMeasurementOperation::SpdmMeasurementRequestManifest.get_u8() as usize
+            {
+                let manifest = b"{\"firmware\":\"deadbeef\"}";
+                value[..manifest.len()].copy_from_slice(manifest);
+                (
+                    SpdmDmtfMeasurementType::SpdmDmtfMeasurementManifest,
+                    manifest.len() as u16,
+                )
+            } else {
+                // OperationalModeCapabilities, OperationalModeState,
+                // DeviceModeCapabilities, DeviceModeState
+                value[..16].copy_from_slice(&[
+                    0x07, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00,
+                ]);
+                (
+                    SpdmDmtfMeasurementType::SpdmDmtfMeasurementStructuredRepresentationMode,
+                    16,
+                )
+            };
+            let mut measurement_record = SpdmMeasurementRecordStructure::default();
+            measurement_record.number_of_blocks = 1;
+            measurement_record.record[0] = SpdmMeasurementBlockStructure {
+                index: measurement_index as u8,
+                measurement_specification,
+                measurement_size: value_size + 3,
+                tcb: true,
+                measurement: SpdmDmtfMeasurementStructure {
+                    r#type,
+                    representation: SpdmDmtfMeasurementRepresentation::SpdmDmtfMeasurementRawBit,
+                    value_size,
+                    value,
+                },
+            };
+            Some(measurement_record)
+        } else if measurement_index > 5 {
+            // indices 1 to 5, as the total number query tells
             None
         } else {
             let mut firmware: [u8; 8] = [0; 8];
@@ -261,6 +318,7 @@ fn spdm_measurement_collection_impl(
                         index: measurement_index as u8,
                         measurement_specification,
                         measurement_size: digest.data_size + 3,
+                        tcb: true,
                         measurement: SpdmDmtfMeasurementStructure {
                             r#type: SpdmDmtfMeasurementType::SpdmDmtfMeasurementFirmware,
                             representation:
@@ -291,7 +349,17 @@ fn spdm_generate_measurement_summary_hash_impl(
     measurement_hash_algo: SpdmBaseHashAlgo,
     measurement_summary_hash_type: SpdmMeasurementSummaryHashType,
 ) -> Option<SpdmDigestStruct> {
-    Some(SpdmDigestStruct::default())
+    let measurement_record = spdm_measurement_collection_impl(
+        spdm_version,
+        measurement_specification,
+        measurement_hash_algo,
+        SpdmMeasurementOperation::SpdmMeasurementRequestAll.get_u8() as usize,
+    )?;
+    spdm_calc_measurement_summary_hash(
+        &measurement_record,
+        base_hash_algo,
+        measurement_summary_hash_type,
+    )
 }
 
 fn spdm_requester_data_sign_impl(
@@ -343,7 +411,12 @@ fn spdm_psk_master_secret_hkdf_expand_impl(
 #[cfg(all(test,))]
 mod tests {
     use super::SECRET_IMPL_INSTANCE;
-    use spdmlib::protocol::{SpdmBaseHashAlgo, SpdmMeasurementSpecification, SpdmVersion};
+    use spdmlib::common::spdm_calc_measurement_summary_hash;
+    use spdmlib::message::SpdmMeasurementOperation;
+    use spdmlib::protocol::{
+        SpdmBaseHashAlgo, SpdmDmtfMeasurementType, SpdmMeasurementSpecification,
+        SpdmMeasurementSummaryHashType, SpdmVersion,
+    };
     use spdmlib::secret::*;
 
     #[test]
@@ -374,4 +447,79 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_case1_spdm_measurement_collection() {
+        let _ = register(SECRET_IMPL_INSTANCE.clone());
+
+        let records = spdm_measurement_collection(
+            SpdmVersion::SpdmVersion11,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_512,
+            0xFD,
+        );
+        assert!(records.is_none());
+
+        let records = spdm_measurement_collection(
+            SpdmVersion::SpdmVersion12,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_512,
+            0xFD,
+        )
+        .unwrap();
+        assert_eq!(records.number_of_blocks, 1);
+        assert_eq!(records.record[0].index, 0xFD);
+        assert_eq!(
+            records.record[0].measurement.r#type,
+            SpdmDmtfMeasurementType::SpdmDmtfMeasurementManifest
+        );
+
+        let records = spdm_measurement_collection(
+            SpdmVersion::SpdmVersion12,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_512,
+            0xFE,
+        )
+        .unwrap();
+        assert_eq!(records.record[0].measurement.value_size, 16);
+    }
+
+    #[test]
+    fn test_case0_spdm_generate_measurement_summary_hash() {
+        let _ = register(SECRET_IMPL_INSTANCE.clone());
+
+        let all = spdm_generate_measurement_summary_hash(
+            SpdmVersion::SpdmVersion11,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
+        )
+        .unwrap();
+        assert_eq!(all.data_size, 48);
+
+        let records = spdm_measurement_collection(
+            SpdmVersion::SpdmVersion11,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementOperation::SpdmMeasurementRequestAll.get_u8() as usize,
+        )
+        .unwrap();
+        let expected = spdm_calc_measurement_summary_hash(
+            &records,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeAll,
+        )
+        .unwrap();
+        assert_eq!(all.as_ref(), expected.as_ref());
+
+        let none = spdm_generate_measurement_summary_hash(
+            SpdmVersion::SpdmVersion11,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementSpecification::DMTF,
+            SpdmBaseHashAlgo::TPM_ALG_SHA_384,
+            SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
+        );
+        assert!(none.is_none());
+    }
 }
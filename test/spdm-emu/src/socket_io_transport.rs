@@ -8,6 +8,7 @@ use std::net::TcpStream;
 use spdmlib::common::SpdmDeviceIo;
 use spdmlib::config;
 use spdmlib::error::SpdmResult;
+use spdmlib::{spdm_err, spdm_result_err};
 
 pub struct SocketIoTransport<'a> {
     pub data: &'a mut TcpStream,
@@ -15,31 +16,51 @@ pub struct SocketIoTransport<'a> {
 }
 impl<'a> SocketIoTransport<'a> {
     pub fn new(stream: &'a mut TcpStream) -> Self {
-        SocketIoTransport {
-            data: stream,
-            transport_type: if USE_PCIDOE {
+        SocketIoTransport::new_with_transport_type(
+            stream,
+            if USE_PCIDOE {
                 SOCKET_TRANSPORT_TYPE_PCI_DOE
             } else {
                 SOCKET_TRANSPORT_TYPE_MCTP
             },
+        )
+    }
+
+    /// transport_type is SOCKET_TRANSPORT_TYPE_PCI_DOE or
+    /// SOCKET_TRANSPORT_TYPE_MCTP, it must match the transport encap.
+    pub fn new_with_transport_type(stream: &'a mut TcpStream, transport_type: u32) -> Self {
+        SocketIoTransport {
+            data: stream,
+            transport_type,
         }
     }
+
+    pub fn transport_type(&self) -> u32 {
+        self.transport_type
+    }
 }
 
 impl SpdmDeviceIo for SocketIoTransport<'_> {
     fn receive(&mut self, read_buffer: &mut [u8], timeout: usize) -> Result<usize, usize> {
         let mut buffer = [0u8; config::DATA_TRANSFER_SIZE];
 
-        if let Some((_, command, payload)) = receive_message(self.data, &mut buffer[..], timeout) {
-            // TBD: do we need this?
-            // self.transport_type = transport_type;
+        if let Some((transport_type, command, payload)) =
+            receive_message(self.data, &mut buffer[..], timeout)
+        {
             let used = payload.len();
             let total = used + SOCKET_HEADER_LEN;
-            if command == SOCKET_SPDM_COMMAND_NORMAL {
+            if command == SOCKET_SPDM_COMMAND_NORMAL && transport_type == self.transport_type {
+                if read_buffer.len() < used {
+                    return Err(0);
+                }
                 read_buffer[..used].copy_from_slice(payload);
                 Ok(used)
             } else {
-                // this commmand need caller to deal.
+                // this commmand, or a message for another transport, need
+                // caller to deal.
+                if read_buffer.len() < total {
+                    return Err(0);
+                }
                 read_buffer[..total].copy_from_slice(&buffer[..total]);
                 Err(total)
             }
@@ -50,13 +71,15 @@ impl SpdmDeviceIo for SocketIoTransport<'_> {
     }
 
     fn send(&mut self, buffer: &[u8]) -> SpdmResult {
-        send_message(
+        match try_send_message(
             self.data,
             self.transport_type,
             SOCKET_SPDM_COMMAND_NORMAL,
             buffer,
-        );
-        Ok(())
+        ) {
+            Ok(_) => Ok(()),
+            Err(_) => spdm_result_err!(EIO),
+        }
     }
 
     fn flush_all(&mut self) -> SpdmResult {
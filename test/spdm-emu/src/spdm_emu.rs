@@ -4,9 +4,9 @@
 
 use std::io::{Read, Write};
 use std::net::TcpStream;
+use std::path::PathBuf;
 
 use codec::{Codec, Reader, Writer};
-use spdmlib::config;
 
 pub const SOCKET_HEADER_LEN: usize = 12;
 pub const USE_PCIDOE: bool = true; // align with DMTF spdm_emu
@@ -17,6 +17,7 @@ pub const SOCKET_TRANSPORT_TYPE_MCTP: u32 = 0x01;
 pub const SOCKET_TRANSPORT_TYPE_PCI_DOE: u32 = 0x02;
 
 pub const SOCKET_SPDM_COMMAND_NORMAL: u32 = 0x0001;
+pub const SOCKET_SPDM_COMMAND_CONTINUE: u32 = 0xFFFD;
 pub const SOCKET_SPDM_COMMAND_STOP: u32 = 0xFFFE;
 pub const SOCKET_SPDM_COMMAND_UNKOWN: u32 = 0xFFFF;
 pub const SOCKET_SPDM_COMMAND_TEST: u32 = 0xDEAD;
@@ -48,48 +49,70 @@ impl Codec for SpdmSocketHeader {
     }
 }
 
+/// Socket transport type picked with `--trans PCI_DOE|MCTP`, as for the
+/// libspdm spdm_requester_emu and spdm_responder_emu. None for a bad value.
+/// Root of the repository, holding test_key.
+pub fn get_test_key_directory() -> PathBuf {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let crate_dir = crate_dir
+        .parent()
+        .expect("can't find parent dir")
+        .parent()
+        .expect("can't find parent_dir");
+    crate_dir.to_path_buf()
+}
+
+pub fn socket_transport_type_from_args<I: Iterator<Item = String>>(mut args: I) -> Option<u32> {
+    let mut transport_type = if USE_PCIDOE {
+        SOCKET_TRANSPORT_TYPE_PCI_DOE
+    } else {
+        SOCKET_TRANSPORT_TYPE_MCTP
+    };
+    while let Some(arg) = args.next() {
+        if arg == "--trans" {
+            transport_type = match args.next()?.as_str() {
+                "PCI_DOE" => SOCKET_TRANSPORT_TYPE_PCI_DOE,
+                "MCTP" => SOCKET_TRANSPORT_TYPE_MCTP,
+                _ => return None,
+            };
+        }
+    }
+    Some(transport_type)
+}
+
 // u32 type, u32 command, usize, payload
+// None once the peer closed the connection or the payload exceeds buffer.
 pub fn receive_message<'a>(
     stream: &mut TcpStream,
     buffer: &'a mut [u8],
     _timeout: usize,
 ) -> Option<(u32, u32, &'a [u8])> {
-    let mut buffer_size = 0;
-    let mut expected_size = 0;
-    loop {
-        let s = stream
-            .read(&mut buffer[buffer_size..])
-            .expect("socket read error!");
-        buffer_size += s;
-        if (expected_size == 0) && (buffer_size >= SOCKET_HEADER_LEN) {
-            let mut reader = Reader::init(&buffer[..core::mem::size_of::<SpdmSocketHeader>()]);
-            let socket_header = SpdmSocketHeader::read(&mut reader).unwrap();
-
-            expected_size = socket_header.payload_size.to_be() as usize + SOCKET_HEADER_LEN;
-            // println!("expected_size: {:?}", expected_size);
-        }
-        if (expected_size != 0) && (buffer_size >= expected_size) {
-            // println!("buffer_size: {:?}", buffer_size);
-            break;
-        }
+    if buffer.len() < SOCKET_HEADER_LEN {
+        return None;
+    }
+    stream.read_exact(&mut buffer[..SOCKET_HEADER_LEN]).ok()?;
+    let mut reader = Reader::init(&buffer[..SOCKET_HEADER_LEN]);
+    let socket_header = SpdmSocketHeader::read(&mut reader)?;
+
+    let payload_size = socket_header.payload_size.to_be() as usize;
+    if payload_size > buffer.len() - SOCKET_HEADER_LEN {
+        println!("socket payload size {:?} too large", payload_size);
+        return None;
     }
+    let buffer_size = SOCKET_HEADER_LEN + payload_size;
+    stream
+        .read_exact(&mut buffer[SOCKET_HEADER_LEN..buffer_size])
+        .ok()?;
     println!(
         "read: {:02X?}{:02X?}",
         &buffer[..SOCKET_HEADER_LEN],
         &buffer[SOCKET_HEADER_LEN..buffer_size]
     );
 
-    if buffer_size < SOCKET_HEADER_LEN {
-        return None;
-    }
-
-    let mut reader = Reader::init(&buffer[..SOCKET_HEADER_LEN]);
-    let socket_header = SpdmSocketHeader::read(&mut reader).unwrap();
-
     Some((
         socket_header.transport_type.to_be(),
         socket_header.command.to_be(),
-        &mut buffer[SOCKET_HEADER_LEN..buffer_size],
+        &buffer[SOCKET_HEADER_LEN..buffer_size],
     ))
 }
 
@@ -99,7 +122,16 @@ pub fn send_message(
     command: u32,
     payload: &[u8],
 ) -> usize {
-    let mut buffer = [0u8; config::DATA_TRANSFER_SIZE];
+    try_send_message(stream, transport_type, command, payload).expect("socket write error!")
+}
+
+pub fn try_send_message(
+    stream: &mut TcpStream,
+    transport_type: u32,
+    command: u32,
+    payload: &[u8],
+) -> std::io::Result<usize> {
+    let mut buffer = [0u8; SOCKET_HEADER_LEN];
 
     let mut writer = Writer::init(&mut buffer);
     let payload_size = payload.len();
@@ -113,14 +145,12 @@ pub fn send_message(
     assert_eq!(used, SOCKET_HEADER_LEN);
 
     let buffer_size = SOCKET_HEADER_LEN + payload_size;
-    stream
-        .write_all(&buffer[..used])
-        .expect("socket write error!");
-    stream.write_all(payload).expect("socket write error!");
-    stream.flush().expect("flush error");
+    stream.write_all(&buffer[..used])?;
+    stream.write_all(payload)?;
+    stream.flush()?;
     println!("write: {:02X?}{:02X?}", &buffer[..used], payload);
 
-    buffer_size
+    Ok(buffer_size)
 }
 
 #[cfg(all(test,))]
@@ -145,4 +175,26 @@ mod tests {
         assert_eq!(spdm_socket_header.transport_type, 0x200u32);
         assert_eq!(spdm_socket_header.payload_size, 0x300u32);
     }
+
+    #[test]
+    fn test_case0_socket_transport_type_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string());
+        assert_eq!(
+            socket_transport_type_from_args(args(&[])),
+            Some(SOCKET_TRANSPORT_TYPE_PCI_DOE)
+        );
+        assert_eq!(
+            socket_transport_type_from_args(args(&["--trans", "MCTP"])),
+            Some(SOCKET_TRANSPORT_TYPE_MCTP)
+        );
+        assert_eq!(
+            socket_transport_type_from_args(args(&["--trans", "PCI_DOE"])),
+            Some(SOCKET_TRANSPORT_TYPE_PCI_DOE)
+        );
+        assert_eq!(
+            socket_transport_type_from_args(args(&["--trans", "TCP"])),
+            None
+        );
+        assert_eq!(socket_transport_type_from_args(args(&["--trans"])), None);
+    }
 }
@@ -11,6 +11,7 @@ use log::*;
 use simple_logger::SimpleLogger;
 
 use spdmlib::common;
+use spdmlib::common::cert_chain::SpdmTrustAnchor;
 use spdmlib::common::SpdmOpaqueSupport;
 use spdmlib::common::ST1;
 use spdmlib::config;
@@ -151,12 +152,23 @@ fn test_spdm(
     peer_cert_chain_data.data[(ca_len + inter_len)..(ca_len + inter_len + leaf_len)]
         .copy_from_slice(leaf_cert.as_ref());
 
+    let mut peer_root_cert = SpdmCertChainData {
+        ..Default::default()
+    };
+    peer_root_cert.data_size = ca_len as u16;
+    peer_root_cert.data[0..ca_len].copy_from_slice(ca_cert.as_ref());
+
     let provision_info = common::SpdmProvisionInfo {
-        my_cert_chain_data: None,
-        my_cert_chain: None,
+        my_cert_chain_data: [None, None, None, None, None, None, None, None],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
         peer_cert_chain_data: Some(peer_cert_chain_data),
-        peer_cert_chain_root_hash: None,
-        default_version: SpdmVersion::SpdmVersion12,
+        peer_trust_anchors: [
+            Some(SpdmTrustAnchor::RootCert(peer_root_cert)),
+            None,
+            None,
+            None,
+        ],
+        ..Default::default()
     };
 
     let mut context = requester::RequesterContext::new(
@@ -188,16 +200,12 @@ fn test_spdm(
         return;
     }
 
-    let mut total_number: u8 = 0;
-    let mut spdm_measurement_record_structure = SpdmMeasurementRecordStructure::default();
     if context
         .send_receive_spdm_measurement(
             None,
             0,
             SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
             SpdmMeasurementOperation::SpdmMeasurementRequestAll,
-            &mut total_number,
-            &mut spdm_measurement_record_structure,
         )
         .is_err()
     {
@@ -210,10 +218,16 @@ fn test_spdm(
         SpdmMeasurementSummaryHashType::SpdmMeasurementSummaryHashTypeNone,
     );
     if let Ok(session_id) = result {
-        info!("\nSession established ... session_id {:0x?}\n", session_id);
+        info!(
+            "\nSession established ... session_id {:0x?}\n",
+            session_id.as_u32()
+        );
         info!("Key Information ...\n");
 
-        let session = context.common.get_session_via_id(session_id).unwrap();
+        let session = context
+            .common
+            .get_session_via_id(session_id.as_u32())
+            .unwrap();
         let (request_direction, response_direction) = session.export_keys();
         info!(
             "equest_direction.encryption_key {:0x?}\n",
@@ -249,8 +263,6 @@ fn test_spdm(
                 0,
                 SpdmMeasurementeAttributes::SIGNATURE_REQUESTED,
                 SpdmMeasurementOperation::SpdmMeasurementQueryTotalNumber,
-                &mut total_number,
-                &mut spdm_measurement_record_structure,
             )
             .is_err()
         {
@@ -312,32 +324,34 @@ fn main() {
     #[cfg(feature = "crypto_mbedtls")]
     spdm_emu::crypto::crypto_mbedtls_register_handles();
 
+    #[cfg(feature = "crypto_rustcrypto")]
+    spdm_emu::crypto::crypto_rustcrypto_register_handles();
+
     let since_the_epoch = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .expect("Time went backwards");
     println!("current unit time epoch - {:?}", since_the_epoch.as_secs());
 
+    let transport_type = socket_transport_type_from_args(std::env::args().skip(1))
+        .expect("usage: spdm-requester-emu [--trans PCI_DOE|MCTP]");
+
     let mut socket =
         TcpStream::connect("127.0.0.1:2323").expect("Couldn't connect to the server...");
 
     let pcidoe_transport_encap = &mut PciDoeTransportEncap {};
     let mctp_transport_encap = &mut MctpTransportEncap {};
 
-    let transport_encap: &mut dyn SpdmTransportEncap = if USE_PCIDOE {
-        pcidoe_transport_encap
-    } else {
-        mctp_transport_encap
-    };
-
-    let transport_type = if USE_PCIDOE {
-        SOCKET_TRANSPORT_TYPE_PCI_DOE
-    } else {
-        SOCKET_TRANSPORT_TYPE_MCTP
-    };
+    let transport_encap: &mut dyn SpdmTransportEncap =
+        if transport_type == SOCKET_TRANSPORT_TYPE_PCI_DOE {
+            pcidoe_transport_encap
+        } else {
+            mctp_transport_encap
+        };
 
     send_receive_hello(&mut socket, transport_encap, transport_type);
 
-    let socket_io_transport = &mut SocketIoTransport::new(&mut socket);
+    let socket_io_transport =
+        &mut SocketIoTransport::new_with_transport_type(&mut socket, transport_type);
     test_spdm(socket_io_transport, transport_encap);
 
     send_receive_stop(&mut socket, transport_encap, transport_type);
@@ -11,13 +11,15 @@ use spdmlib::common::SpdmOpaqueSupport;
 use std::net::{TcpListener, TcpStream};
 use std::u32;
 
-use codec::{Codec, Reader, Writer};
+use codec::{Codec, Reader};
 use common::SpdmTransportEncap;
 use common::ST1;
 use mctp_transport::MctpTransportEncap;
 use pcidoe_transport::{
-    PciDoeDataObjectType, PciDoeMessageHeader, PciDoeTransportEncap, PciDoeVendorId,
+    pci_doe_discovery_respond, PciDoeTransportEncap, PCI_DOE_PROTOCOL_DISCOVERY,
+    PCI_DOE_PROTOCOL_SECURED_SPDM, PCI_DOE_PROTOCOL_SPDM,
 };
+#[cfg(feature = "spdm-ring")]
 use spdm_emu::crypto_callback::ASYM_SIGN_IMPL;
 use spdm_emu::secret_impl_sample::*;
 use spdm_emu::socket_io_transport::SocketIoTransport;
@@ -28,6 +30,7 @@ use spdmlib::{common, config, protocol::*, responder};
 fn process_socket_message(
     stream: &mut TcpStream,
     transport_encap: &mut dyn SpdmTransportEncap,
+    transport_type: u32,
     buffer: &[u8],
 ) -> bool {
     if buffer.len() < SOCKET_HEADER_LEN {
@@ -51,9 +54,14 @@ fn process_socket_message(
             send_stop(stream, transport_encap, res.0);
             false
         }
+        SOCKET_SPDM_COMMAND_CONTINUE => {
+            // the next handle_message starts over with a new context
+            send_continue(stream, transport_encap, res.0);
+            true
+        }
         SOCKET_SPDM_COMMAND_NORMAL => true,
         _ => {
-            if USE_PCIDOE {
+            if transport_type == SOCKET_TRANSPORT_TYPE_PCI_DOE {
                 send_pci_discovery(stream, transport_encap, res.0, buffer)
             } else {
                 send_unknown(stream, transport_encap, res.0);
@@ -86,8 +94,17 @@ fn main() {
     #[cfg(feature = "crypto_mbedtls")]
     spdm_emu::crypto::crypto_mbedtls_register_handles();
 
+    #[cfg(feature = "crypto_rustcrypto")]
+    {
+        spdm_emu::crypto::crypto_rustcrypto_register_handles();
+        spdm_emu::crypto::crypto_rustcrypto_set_signing_key("test_key/EcP384/end_responder.key.p8");
+    }
+
     register(SECRET_IMPL_INSTANCE.clone());
 
+    let transport_type = socket_transport_type_from_args(std::env::args().skip(1))
+        .expect("usage: spdm-responder-emu [--trans PCI_DOE|MCTP]");
+
     let listener = TcpListener::bind("127.0.0.1:2323").expect("Couldn't bind to the server");
     println!("server start!");
 
@@ -101,11 +118,12 @@ fn main() {
         loop {
             let res = handle_message(
                 &mut stream,
-                if USE_PCIDOE {
+                if transport_type == SOCKET_TRANSPORT_TYPE_PCI_DOE {
                     pcidoe_transport_encap
                 } else {
                     mctp_transport_encap
                 },
+                transport_type,
             );
 
             match res {
@@ -115,11 +133,12 @@ fn main() {
                 Err((used, buffer)) => {
                     need_continue = process_socket_message(
                         &mut stream,
-                        if USE_PCIDOE {
+                        if transport_type == SOCKET_TRANSPORT_TYPE_PCI_DOE {
                             pcidoe_transport_encap
                         } else {
                             mctp_transport_encap
                         },
+                        transport_type,
                         &buffer[0..used],
                     );
                 }
@@ -135,9 +154,11 @@ fn main() {
 fn handle_message(
     stream: &mut TcpStream,
     transport_encap: &mut dyn SpdmTransportEncap,
+    transport_type: u32,
 ) -> Result<bool, (usize, [u8; config::DATA_TRANSFER_SIZE])> {
     println!("handle_message!");
-    let mut socket_io_transport = SocketIoTransport::new(stream);
+    let mut socket_io_transport =
+        SocketIoTransport::new_with_transport_type(stream, transport_type);
 
     let config_info = common::SpdmConfigInfo {
         spdm_version: [
@@ -223,13 +244,22 @@ fn handle_message(
         .copy_from_slice(leaf_cert.as_ref());
 
     let provision_info = common::SpdmProvisionInfo {
-        my_cert_chain_data: Some(my_cert_chain_data),
-        my_cert_chain: None,
+        my_cert_chain_data: [
+            Some(my_cert_chain_data),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ],
+        my_cert_chain: [None, None, None, None, None, None, None, None],
         peer_cert_chain_data: None,
-        peer_cert_chain_root_hash: None,
-        default_version: SpdmVersion::SpdmVersion12,
+        ..Default::default()
     };
 
+    #[cfg(feature = "spdm-ring")]
     spdmlib::crypto::asym_sign::register(ASYM_SIGN_IMPL.clone());
     let mut context = responder::ResponderContext::new(
         &mut socket_io_transport,
@@ -297,6 +327,21 @@ pub fn send_unknown(
     );
 }
 
+pub fn send_continue(
+    stream: &mut TcpStream,
+    _transport_encap: &mut dyn SpdmTransportEncap,
+    transport_type: u32,
+) {
+    println!("get continue");
+
+    let _buffer_size = spdm_emu::spdm_emu::send_message(
+        stream,
+        transport_type,
+        spdm_emu::spdm_emu::SOCKET_SPDM_COMMAND_CONTINUE,
+        &[],
+    );
+}
+
 pub fn send_stop(
     stream: &mut TcpStream,
     _transport_encap: &mut dyn SpdmTransportEncap,
@@ -318,63 +363,24 @@ pub fn send_pci_discovery(
     transport_type: u32,
     buffer: &[u8],
 ) -> bool {
-    let mut reader = Reader::init(buffer);
-    let mut unknown_message = false;
-    match PciDoeMessageHeader::read(&mut reader) {
-        Some(pcidoe_header) => {
-            match pcidoe_header.vendor_id {
-                PciDoeVendorId::PciDoeVendorIdPciSig => {}
-                _ => unknown_message = true,
-            }
-            match pcidoe_header.data_object_type {
-                PciDoeDataObjectType::PciDoeDataObjectTypeDoeDiscovery => {}
-                _ => unknown_message = true,
-            }
-        }
-        None => unknown_message = true,
-    }
-
-    let payload = &mut [1u8, 0u8, 0u8, 0u8];
-
-    match u8::read(&mut reader) {
-        None => unknown_message = true,
-        Some(discovery_index) => match discovery_index {
-            0 => {
-                payload[2] = 0;
-                payload[3] = 1;
-            }
-            1 => {
-                payload[2] = 1;
-                payload[3] = 2;
-            }
-            2 => {
-                payload[2] = 2;
-                payload[3] = 0;
-            }
-            _ => unknown_message = true,
-        },
-    }
-    if unknown_message {
-        send_unknown(stream, transport_encap, transport_type);
-        return false;
-    }
-
-    let payload_len = 4;
+    let protocols = [
+        PCI_DOE_PROTOCOL_DISCOVERY,
+        PCI_DOE_PROTOCOL_SPDM,
+        PCI_DOE_PROTOCOL_SECURED_SPDM,
+    ];
     let mut transport_buffer = [0u8; 1024];
-    let mut writer = Writer::init(&mut transport_buffer);
-    let pcidoe_header = PciDoeMessageHeader {
-        vendor_id: PciDoeVendorId::PciDoeVendorIdPciSig,
-        data_object_type: PciDoeDataObjectType::PciDoeDataObjectTypeDoeDiscovery,
-        payload_length: 4,
+    let used = match pci_doe_discovery_respond(&protocols, buffer, &mut transport_buffer) {
+        Ok(used) => used,
+        Err(_) => {
+            send_unknown(stream, transport_encap, transport_type);
+            return false;
+        }
     };
-    pcidoe_header.encode(&mut writer);
-    let header_size = writer.used();
-    transport_buffer[header_size..(header_size + payload_len)].copy_from_slice(payload);
     let _buffer_size = spdm_emu::spdm_emu::send_message(
         stream,
         SOCKET_TRANSPORT_TYPE_PCI_DOE,
         spdm_emu::spdm_emu::SOCKET_SPDM_COMMAND_NORMAL,
-        &transport_buffer[..(header_size + payload_len)],
+        &transport_buffer[..used],
     );
     //need continue
     true
@@ -0,0 +1,3 @@
+openssl ecparam -genkey -name secp384r1 -noout -out end_responder.key
+openssl req -x509 -new -key end_responder.key -out end_responder.cert -sha384 -days 3650 -set_serial 1 -subj "/CN=intel test DICE responder cert" -config ./openssl.cnf -extensions v3_dice
+openssl x509 -in end_responder.cert -out end_responder.cert.der -outform DER
@@ -0,0 +1,7 @@
+# ML-KEM-768 known answer data, needs OpenSSL 3.5 or later.
+# Key pair from seed d || z = 00..3f, ciphertext from a fresh encapsulation.
+
+SEED=000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f
+
+openssl genpkey -algorithm ML-KEM-768 -pkeyopt hexseed:$SEED -out mlkem768.key
+openssl pkeyutl -encap -inkey mlkem768.key -out mlkem768.ct.bin -secret mlkem768.ss.bin